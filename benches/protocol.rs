@@ -1,4 +1,5 @@
-use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
 use std::sync::Arc;
 use std::time::Duration;
 use tl_proto::TlRead;
@@ -6,9 +7,12 @@ use tokio_util::bytes::{Bytes, BytesMut};
 use tokio_util::codec::{Decoder, Encoder};
 use tonutils::adnl::{AdnlAesParams, AdnlCodec};
 use tonutils::tl::request::{GetMasterchainInfoExt, Request};
+use tonutils::tlb::TlbSerialize;
+use tonutils::tvm::vm::GetMethodRunner;
 use tonutils::tvm::{
     Builder, Cell, Slice, TvmStack, TvmStackEntry, deserialize_boc, serialize_boc,
 };
+use tonutils::wallet::{WalletV5R1Data, wallet_v5r1_code};
 
 fn fixture_cell() -> Arc<Cell> {
     let mut child = Builder::new();
@@ -145,11 +149,27 @@ fn bench_tvm_stack(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_tvm_get_method(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tvm_get_method");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(10));
+
+    let data = WalletV5R1Data::new(0x7fff_ff11, [7; 32]).to_cell().unwrap();
+    let runner = GetMethodRunner::new(wallet_v5r1_code().unwrap(), data);
+
+    group.bench_function("wallet_v5r1_seqno", |b| {
+        b.iter(|| black_box(runner.run_by_name("seqno", TvmStack::empty()).unwrap()))
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_adnl_codec,
     bench_tl_codec,
     bench_tvm_cells_and_boc,
-    bench_tvm_stack
+    bench_tvm_stack,
+    bench_tvm_get_method
 );
 criterion_main!(benches);
@@ -0,0 +1,44 @@
+mod common;
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use tonutils::adnl::AdnlNode;
+use tonutils::adnl::crypto::KeyPair;
+use tonutils::dht::DhtClient;
+use tonutils::mempool::{BASECHAIN_SHARD, MempoolFilter, MempoolScanner};
+use tonutils::overlay::OverlayManager;
+use tonutils::tvm::Address;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let config = common::load_config()?;
+    let zero_state = config
+        .validator
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("global config has no validator section"))?
+        .zero_state
+        .file_hash;
+
+    let node =
+        Arc::new(AdnlNode::bind("0.0.0.0:0", KeyPair::generate(&mut rand::rngs::OsRng)).await?);
+    let dht = Arc::new(DhtClient::from_config(node.clone(), &config)?);
+    let overlays = Arc::new(OverlayManager::new(node.clone()).with_dht(dht));
+    node.set_handler(overlays.clone());
+
+    let mut filter = MempoolFilter::new();
+    if let Ok(address) = std::env::var("TON_MEMPOOL_DESTINATION") {
+        filter = filter.with_destination(&Address::from_str(&address)?);
+    }
+    let scanner = MempoolScanner::new(overlays, zero_state).with_filter(filter);
+    let mut pending = scanner.subscribe(&[BASECHAIN_SHARD]).await;
+    while let Some(message) = pending.next().await {
+        println!(
+            "pending {} from {:?}: {} bytes",
+            hex::encode(message.hash),
+            message.peer,
+            message.boc.len()
+        );
+    }
+    Ok(())
+}
@@ -8,7 +8,7 @@ pub trait CryptoRandom: rand::RngCore + rand::CryptoRng {}
 impl<T> CryptoRandom for T where T: rand::RngCore + rand::CryptoRng {}
 
 /// Wrapper struct to hold ADNL address, which is a hash of public key
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Eq, Hash, Clone)]
 pub struct AdnlAddress([u8; 32]);
 
 impl std::fmt::Debug for AdnlAddress {
@@ -146,6 +146,12 @@ pub enum AdnlError {
     EndOfStream,
     #[error("Invalid public key")]
     InvalidPublicKey,
+    #[error("Invalid packet: {0}")]
+    InvalidPacket(String),
+    #[error("Invalid packet signature")]
+    InvalidSignature,
+    #[error("Unknown peer {0:?}")]
+    UnknownPeer(AdnlAddress),
 }
 
 /// Information about connected peers.
@@ -3,6 +3,8 @@
 pub mod crypto;
 pub mod helper_types;
 pub mod primitives;
+#[cfg(feature = "adnl-udp")]
+pub mod udp;
 pub mod wrappers;
 
 #[cfg(test)]
@@ -11,5 +13,7 @@ mod tests;
 pub use helper_types::{AdnlAddress, AdnlAesParams, AdnlConnectionInfo, AdnlError};
 pub use primitives::codec::AdnlCodec;
 pub use primitives::handshake::AdnlHandshake;
+#[cfg(feature = "adnl-udp")]
+pub use udp::{AdnlMessageHandler, AdnlNode};
 pub use wrappers::builder::AdnlBuilder;
 pub use wrappers::peer::AdnlPeer;
@@ -1,7 +1,6 @@
 use crate::adnl::crypto::{KeyPair, PublicKey};
-use crate::adnl::primitives::AdnlAes;
+use crate::adnl::primitives::{AdnlAes, adnl_aes_from_secret};
 use crate::adnl::{AdnlAddress, AdnlAesParams, AdnlError, AdnlPeer};
-use aes::cipher::KeyIvInit;
 use ctr::cipher::StreamCipher;
 use sha2::{Digest, Sha256};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -81,15 +80,7 @@ impl AdnlHandshake {
     }
 
     fn initialize_aes(secret: &[u8; 32], hash: &[u8]) -> AdnlAes {
-        let mut key = [0u8; 32];
-        key[..16].copy_from_slice(&secret[..16]);
-        key[16..32].copy_from_slice(&hash[16..32]);
-
-        let mut nonce = [0u8; 16];
-        nonce[..4].copy_from_slice(&hash[..4]);
-        nonce[4..16].copy_from_slice(&secret[20..32]);
-
-        AdnlAes::new(key.as_slice().into(), nonce.as_slice().into())
+        adnl_aes_from_secret(secret, hash)
     }
 
     fn sha256(data: impl AsRef<[u8]>) -> [u8; 32] {
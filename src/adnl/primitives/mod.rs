@@ -1,7 +1,22 @@
 use aes::Aes256;
+use aes::cipher::KeyIvInit;
 use ctr::Ctr128BE;
 
 pub mod codec;
 pub mod handshake;
 
 pub type AdnlAes = Ctr128BE<Aes256>;
+
+/// Builds the AES-CTR cipher keyed by a shared `secret` and the SHA-256 `hash`
+/// of the plaintext, as used by ADNL handshakes, datagrams, and channels.
+pub(crate) fn adnl_aes_from_secret(secret: &[u8; 32], hash: &[u8]) -> AdnlAes {
+    let mut key = [0u8; 32];
+    key[..16].copy_from_slice(&secret[..16]);
+    key[16..32].copy_from_slice(&hash[16..32]);
+
+    let mut nonce = [0u8; 16];
+    nonce[..4].copy_from_slice(&hash[..4]);
+    nonce[4..16].copy_from_slice(&secret[20..32]);
+
+    AdnlAes::new(key.as_slice().into(), nonce.as_slice().into())
+}
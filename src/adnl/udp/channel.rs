@@ -0,0 +1,105 @@
+use ctr::cipher::StreamCipher;
+use sha2::{Digest, Sha256};
+
+use crate::adnl::crypto::tl::PublicKey as TlPublicKey;
+use crate::adnl::crypto::{KeyPair, PublicKey};
+use crate::adnl::primitives::adnl_aes_from_secret;
+use crate::adnl::{AdnlAddress, AdnlError};
+
+use super::packet::decrypt_with_checksum;
+
+/// Symmetric ADNL channel between two nodes.
+///
+/// Each side contributes an ed25519 channel key through
+/// `adnl.message.createChannel`/`confirmChannel`. The x25519 secret of the two
+/// keys encrypts one direction and its byte-reversed form the other; the node
+/// with the smaller ADNL id sends with the reversed secret. Datagrams start with
+/// the `pub.aes` short id of the receiving key.
+#[derive(Clone)]
+pub struct AdnlChannel {
+    in_id: [u8; 32],
+    out_id: [u8; 32],
+    in_key: [u8; 32],
+    out_key: [u8; 32],
+    peer_key: PublicKey,
+}
+
+impl AdnlChannel {
+    /// Derives the channel between `local_id` using `our_key` and `peer_id`
+    /// using `peer_key`.
+    pub fn new(
+        local_id: &AdnlAddress,
+        peer_id: &AdnlAddress,
+        our_key: &KeyPair,
+        peer_key: &PublicKey,
+    ) -> Self {
+        let secret = our_key.compute_shared_secret(peer_key);
+        let mut reversed = secret;
+        reversed.reverse();
+        let (in_key, out_key) = match local_id.as_bytes().cmp(peer_id.as_bytes()) {
+            std::cmp::Ordering::Less => (secret, reversed),
+            std::cmp::Ordering::Greater => (reversed, secret),
+            std::cmp::Ordering::Equal => (secret, secret),
+        };
+        Self {
+            in_id: aes_key_id(&in_key),
+            out_id: aes_key_id(&out_key),
+            in_key,
+            out_key,
+            peer_key: *peer_key,
+        }
+    }
+
+    /// Id that prefixes datagrams received over this channel.
+    pub fn in_id(&self) -> &[u8; 32] {
+        &self.in_id
+    }
+
+    /// Id that prefixes datagrams sent over this channel.
+    pub fn out_id(&self) -> &[u8; 32] {
+        &self.out_id
+    }
+
+    /// Channel key announced by the peer.
+    pub fn peer_key(&self) -> &PublicKey {
+        &self.peer_key
+    }
+
+    /// Encrypts packet contents as `out_id || sha256(contents) ||
+    /// aes_ctr(contents)`.
+    pub fn encrypt(&self, contents: &[u8]) -> Vec<u8> {
+        let checksum: [u8; 32] = Sha256::digest(contents).into();
+        let mut datagram = Vec::with_capacity(64 + contents.len());
+        datagram.extend_from_slice(&self.out_id);
+        datagram.extend_from_slice(&checksum);
+        datagram.extend_from_slice(contents);
+        adnl_aes_from_secret(&self.out_key, &checksum).apply_keystream(&mut datagram[64..]);
+        datagram
+    }
+
+    /// Decrypts a datagram whose leading id is `in_id`.
+    pub fn decrypt(&self, datagram: &[u8]) -> Result<Vec<u8>, AdnlError> {
+        if datagram.len() < 64 {
+            return Err(AdnlError::TooShortPacket);
+        }
+        if datagram[..32] != self.in_id {
+            return Err(AdnlError::UnknownAddr(AdnlAddress::from(self.in_id)));
+        }
+        let checksum: [u8; 32] = datagram[32..64].try_into().unwrap();
+        decrypt_with_checksum(&self.in_key, &checksum, &datagram[64..])
+    }
+}
+
+impl std::fmt::Debug for AdnlChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdnlChannel")
+            .field("in_id", &hex::encode(self.in_id))
+            .field("out_id", &hex::encode(self.out_id))
+            .finish()
+    }
+}
+
+/// Short id of a `pub.aes` key: `sha256` of its boxed TL form.
+fn aes_key_id(key: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(tl_proto::serialize(TlPublicKey::Aes { key })).into()
+}
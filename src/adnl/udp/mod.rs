@@ -0,0 +1,25 @@
+//! ADNL over UDP.
+//!
+//! `AdnlPacketContents` models signed `adnl.packetContents`, `AdnlChannel`
+//! derives channel keys from `adnl.message.createChannel`/`confirmChannel`,
+//! and `AdnlNode` runs a tokio UDP socket that exchanges
+//! `adnl.message.query`/`answer` and custom messages with peers. This layer is
+//! the transport for DHT and overlay clients.
+
+mod channel;
+mod node;
+mod packet;
+mod peer;
+
+#[cfg(test)]
+mod tests;
+
+pub use channel::AdnlChannel;
+#[cfg(any(feature = "dht", feature = "rldp"))]
+pub(crate) use node::unix_now;
+pub use node::{ADNL_MAX_MESSAGE_BYTES, ADNL_MAX_PARTS_TOTAL_BYTES, AdnlMessageHandler, AdnlNode};
+pub use packet::{
+    AdnlAddressList, AdnlPacketContents, AdnlReinitDates, AdnlUdpAddress, decrypt_datagram,
+    encrypt_datagram,
+};
+pub use peer::SeqnoWindow;
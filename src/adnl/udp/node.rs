@@ -0,0 +1,673 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use tokio::net::{ToSocketAddrs, UdpSocket};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::adnl::crypto::{KeyPair, PublicKey};
+use crate::adnl::{AdnlAddress, AdnlError};
+use crate::tl::AdnlMessage;
+use crate::tl::common::Int256;
+
+use super::channel::AdnlChannel;
+use super::packet::{
+    AdnlAddressList, AdnlPacketContents, AdnlReinitDates, decrypt_datagram, encrypt_datagram,
+};
+use super::peer::{PartialMessage, PeerState};
+
+/// Serialized messages above this size are sent as `adnl.message.part` chunks
+/// of this size.
+pub const ADNL_MAX_MESSAGE_BYTES: usize = 1024;
+/// Largest message accepted from `adnl.message.part` chunks.
+pub const ADNL_MAX_PARTS_TOTAL_BYTES: usize = 1 << 20;
+/// Incomplete multipart messages kept per peer.
+const MAX_PARTIAL_MESSAGES: usize = 16;
+/// Peers kept by a node; the least recently seen one is evicted beyond this.
+pub(super) const MAX_PEERS: usize = 4096;
+const RECEIVE_BUFFER_BYTES: usize = 65_536;
+
+/// Callbacks for inbound queries and custom messages of an `AdnlNode`.
+#[async_trait]
+pub trait AdnlMessageHandler: Send + Sync {
+    /// Answers an `adnl.message.query`; `None` leaves it unanswered.
+    async fn handle_query(&self, peer: &AdnlAddress, query: &[u8]) -> Option<Vec<u8>>;
+
+    /// Receives an `adnl.message.custom` payload.
+    async fn handle_custom(&self, peer: &AdnlAddress, data: &[u8]) {
+        let _ = (peer, data);
+    }
+}
+
+/// ADNL node listening on a UDP socket.
+///
+/// The node signs packets sent outside a channel, asks every new peer for a
+/// channel with `adnl.message.createChannel`, and switches to channel
+/// encryption once the peer confirms. Packets carry seqnos and the node's
+/// start date (`reinit_date`); duplicates, stale sessions, and packets meant
+/// for an earlier start of this node are dropped. Lost datagrams are not
+/// retransmitted, so callers retry failed queries.
+///
+/// Dropping the node stops its receive task.
+pub struct AdnlNode {
+    inner: Arc<NodeInner>,
+    receiver: JoinHandle<()>,
+}
+
+struct NodeInner {
+    socket: UdpSocket,
+    keypair: KeyPair,
+    id: AdnlAddress,
+    reinit_date: i32,
+    address_list: AdnlAddressList,
+    state: Mutex<NodeState>,
+    handler: RwLock<Option<Arc<dyn AdnlMessageHandler>>>,
+}
+
+#[derive(Default)]
+struct NodeState {
+    peers: HashMap<AdnlAddress, PeerState>,
+    channels: HashMap<[u8; 32], AdnlAddress>,
+    queries: HashMap<[u8; 32], (AdnlAddress, oneshot::Sender<Vec<u8>>)>,
+}
+
+impl NodeState {
+    /// Adds a new peer, evicting the least recently seen one when the table
+    /// is full.
+    fn insert_peer(&mut self, id: AdnlAddress, peer: PeerState) {
+        if self.peers.len() >= MAX_PEERS
+            && let Some(oldest) = self
+                .peers
+                .iter()
+                .min_by_key(|(_, peer)| peer.last_seen)
+                .map(|(id, _)| id.clone())
+            && let Some(evicted) = self.peers.remove(&oldest)
+            && let Some(channel) = evicted.channel
+        {
+            self.channels.remove(channel.in_id());
+        }
+        self.peers.insert(id, peer);
+    }
+}
+
+/// Work left after a packet was processed under the state lock.
+enum Inbound {
+    Reply(AdnlMessage),
+    Query { query_id: Int256, query: Vec<u8> },
+    Custom(Vec<u8>),
+}
+
+impl AdnlNode {
+    /// Binds a UDP socket and starts receiving packets for `keypair`.
+    ///
+    /// The advertised address list holds the bound address unless it is
+    /// unspecified (`0.0.0.0` or `::`).
+    pub async fn bind<A: ToSocketAddrs>(addr: A, keypair: KeyPair) -> Result<Self, AdnlError> {
+        let socket = UdpSocket::bind(addr).await?;
+        let local_addr = socket.local_addr()?;
+        let reinit_date = unix_now();
+        let address_list = if local_addr.ip().is_unspecified() {
+            AdnlAddressList {
+                version: reinit_date,
+                reinit_date,
+                ..Default::default()
+            }
+        } else {
+            AdnlAddressList::udp(local_addr, reinit_date, reinit_date)
+        };
+        let inner = Arc::new(NodeInner {
+            socket,
+            id: AdnlAddress::from(&keypair.public_key),
+            keypair,
+            reinit_date,
+            address_list,
+            state: Mutex::new(NodeState::default()),
+            handler: RwLock::new(None),
+        });
+        let receiver = tokio::spawn(receive_loop(inner.clone()));
+        log::debug!("ADNL node {:?} listening on {local_addr}", inner.id);
+        Ok(Self { inner, receiver })
+    }
+
+    /// Sets the handler for inbound queries and custom messages.
+    pub fn set_handler(&self, handler: Arc<dyn AdnlMessageHandler>) {
+        *self.inner.handler.write().unwrap() = Some(handler);
+    }
+
+    pub fn id(&self) -> &AdnlAddress {
+        &self.inner.id
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.inner.keypair.public_key
+    }
+
+    /// Key pair of the node, used by protocols that sign records with the
+    /// ADNL identity.
+    #[cfg(feature = "overlay")]
+    pub(crate) fn keypair(&self) -> &KeyPair {
+        &self.inner.keypair
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, AdnlError> {
+        Ok(self.inner.socket.local_addr()?)
+    }
+
+    /// Address list sent in packets outside a channel.
+    pub fn address_list(&self) -> &AdnlAddressList {
+        &self.inner.address_list
+    }
+
+    /// Start date of this node, sent as `reinit_date`.
+    pub fn reinit_date(&self) -> i32 {
+        self.inner.reinit_date
+    }
+
+    /// Registers a peer reachable at `addr` and returns its ADNL id.
+    ///
+    /// Re-adding a known peer only updates its address.
+    pub fn add_peer(&self, public_key: PublicKey, addr: SocketAddr) -> AdnlAddress {
+        let id = AdnlAddress::from(&public_key);
+        let mut state = self.inner.state.lock().unwrap();
+        match state.peers.get_mut(&id) {
+            Some(peer) => peer.addr = addr,
+            None => state.insert_peer(id.clone(), PeerState::new(public_key, addr, unix_now())),
+        }
+        id
+    }
+
+    /// Returns whether `peer` is registered or has sent packets to this node.
+    pub fn has_peer(&self, peer: &AdnlAddress) -> bool {
+        self.inner.state.lock().unwrap().peers.contains_key(peer)
+    }
+
+    /// Returns whether packets to `peer` are sent over a confirmed channel.
+    pub fn has_channel(&self, peer: &AdnlAddress) -> bool {
+        let state = self.inner.state.lock().unwrap();
+        state.peers.get(peer).is_some_and(|peer| peer.channel_ready)
+    }
+
+    /// Sends an `adnl.message.custom` message to a known peer.
+    pub async fn send_custom(&self, peer: &AdnlAddress, data: Vec<u8>) -> Result<(), AdnlError> {
+        self.inner
+            .send_messages(peer, vec![AdnlMessage::Custom { data }])
+            .await
+    }
+
+    /// Sends `query` to a known peer and waits for its answer.
+    pub async fn query(
+        &self,
+        peer: &AdnlAddress,
+        query: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, AdnlError> {
+        let query_id = Int256::random();
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .state
+            .lock()
+            .unwrap()
+            .queries
+            .insert(query_id.0, (peer.clone(), sender));
+
+        let exchange = async {
+            self.inner
+                .send_messages(
+                    peer,
+                    vec![AdnlMessage::Query {
+                        query_id: query_id.clone(),
+                        query,
+                    }],
+                )
+                .await?;
+            receiver.await.map_err(|_| AdnlError::EndOfStream)
+        };
+        let result =
+            tokio::time::timeout(timeout, exchange)
+                .await
+                .unwrap_or(Err(AdnlError::Timeout {
+                    operation: "adnl_query",
+                    timeout,
+                }));
+        if result.is_err() {
+            self.inner.state.lock().unwrap().queries.remove(&query_id.0);
+        }
+        result
+    }
+}
+
+impl Drop for AdnlNode {
+    fn drop(&mut self) {
+        self.receiver.abort();
+    }
+}
+
+async fn receive_loop(inner: Arc<NodeInner>) {
+    let mut buffer = vec![0u8; RECEIVE_BUFFER_BYTES];
+    loop {
+        let (len, source) = match inner.socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(error) => {
+                log::debug!("ADNL UDP receive failed: {error}");
+                continue;
+            }
+        };
+        if let Err(error) = inner.handle_datagram(&buffer[..len], source).await {
+            log::debug!("dropping ADNL datagram from {source}: {error}");
+        }
+    }
+}
+
+impl NodeInner {
+    async fn send_messages(
+        &self,
+        peer_id: &AdnlAddress,
+        messages: Vec<AdnlMessage>,
+    ) -> Result<(), AdnlError> {
+        let (addr, datagrams) = {
+            let mut state = self.state.lock().unwrap();
+            let peer = state
+                .peers
+                .get_mut(peer_id)
+                .ok_or_else(|| AdnlError::UnknownPeer(peer_id.clone()))?;
+            let datagrams = split_messages(messages)
+                .into_iter()
+                .map(|batch| self.build_datagram(peer_id, peer, batch))
+                .collect::<Vec<_>>();
+            (peer.addr, datagrams)
+        };
+        for datagram in datagrams {
+            self.socket.send_to(&datagram, addr).await?;
+        }
+        Ok(())
+    }
+
+    fn build_datagram(
+        &self,
+        peer_id: &AdnlAddress,
+        peer: &mut PeerState,
+        mut messages: Vec<AdnlMessage>,
+    ) -> Vec<u8> {
+        let channel_key = Int256(peer.channel_key.public_key.to_bytes());
+        if !peer.channel_ready {
+            let control = match &peer.channel {
+                Some(channel) => AdnlMessage::ConfirmChannel {
+                    key: channel_key,
+                    peer_key: Int256(channel.peer_key().to_bytes()),
+                    date: peer.channel_date,
+                },
+                None => AdnlMessage::CreateChannel {
+                    key: channel_key,
+                    date: peer.channel_date,
+                },
+            };
+            messages.insert(0, control);
+        }
+
+        let mut contents = AdnlPacketContents::new(&mut rand::rngs::OsRng);
+        contents.set_messages(messages);
+        peer.out_seqno += 1;
+        contents.seqno = Some(peer.out_seqno);
+        contents.confirm_seqno = Some(peer.in_seqnos.max());
+
+        match &peer.channel {
+            Some(channel) if peer.channel_ready => channel.encrypt(&contents.to_bytes()),
+            _ => {
+                contents.address = Some(self.address_list.clone());
+                contents.reinit_dates = Some(AdnlReinitDates {
+                    reinit_date: self.reinit_date,
+                    dst_reinit_date: peer.reinit_date,
+                });
+                contents.sign(&self.keypair);
+                encrypt_datagram(
+                    peer_id,
+                    &peer.public_key,
+                    &contents.to_bytes(),
+                    &mut rand::rngs::OsRng,
+                )
+            }
+        }
+    }
+
+    async fn handle_datagram(
+        self: &Arc<Self>,
+        datagram: &[u8],
+        source: SocketAddr,
+    ) -> Result<(), AdnlError> {
+        if datagram.len() < 32 {
+            return Err(AdnlError::TooShortPacket);
+        }
+        let (mut contents, channel_peer) = if datagram[..32] == *self.id.as_bytes() {
+            let contents = decrypt_datagram(datagram, &self.keypair)?;
+            (AdnlPacketContents::from_bytes(&contents)?, None)
+        } else {
+            let (peer_id, channel) = self.channel_for(&datagram[..32])?;
+            let contents = channel.decrypt(datagram)?;
+            (AdnlPacketContents::from_bytes(&contents)?, Some(peer_id))
+        };
+
+        let signer = contents.verify()?;
+        let peer_id = match &channel_peer {
+            Some(peer_id) => {
+                if let Some(from) = contents.from_public_key()?
+                    && AdnlAddress::from(&from) != *peer_id
+                {
+                    return Err(AdnlError::InvalidPacket(
+                        "channel packet names another sender".to_string(),
+                    ));
+                }
+                peer_id.clone()
+            }
+            None => AdnlAddress::from(signer.as_ref().ok_or_else(|| {
+                AdnlError::InvalidPacket("packet outside a channel is not signed".to_string())
+            })?),
+        };
+
+        let inbound = self.accept_packet(
+            &peer_id,
+            signer,
+            channel_peer.is_some(),
+            &mut contents,
+            source,
+        )?;
+
+        let mut replies = Vec::new();
+        for item in inbound {
+            match item {
+                Inbound::Reply(message) => replies.push(message),
+                Inbound::Query { query_id, query } => {
+                    self.spawn_query(peer_id.clone(), query_id, query)
+                }
+                Inbound::Custom(data) => {
+                    let handler = self.handler.read().unwrap().clone();
+                    if let Some(handler) = handler {
+                        handler.handle_custom(&peer_id, &data).await;
+                    }
+                }
+            }
+        }
+        if !replies.is_empty() {
+            self.send_messages(&peer_id, replies).await?;
+        }
+        Ok(())
+    }
+
+    fn channel_for(&self, id: &[u8]) -> Result<(AdnlAddress, AdnlChannel), AdnlError> {
+        let state = self.state.lock().unwrap();
+        let unknown = || AdnlError::UnknownAddr(AdnlAddress::try_from(id).unwrap());
+        let peer_id = state.channels.get(id).ok_or_else(unknown)?;
+        let channel = state
+            .peers
+            .get(peer_id)
+            .and_then(|peer| peer.channel.clone())
+            .ok_or_else(unknown)?;
+        Ok((peer_id.clone(), channel))
+    }
+
+    /// Applies reinit, seqno, and channel bookkeeping for a decrypted packet
+    /// and collects the work its messages require.
+    fn accept_packet(
+        &self,
+        peer_id: &AdnlAddress,
+        signer: Option<PublicKey>,
+        via_channel: bool,
+        contents: &mut AdnlPacketContents,
+        source: SocketAddr,
+    ) -> Result<Vec<Inbound>, AdnlError> {
+        let now = unix_now();
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        // Unknown senders are only added once their packet passes the checks.
+        match state.peers.get_mut(peer_id) {
+            Some(peer) => self.accept_header(peer, &mut state.channels, contents, now)?,
+            None => {
+                let key = signer.ok_or_else(|| AdnlError::UnknownPeer(peer_id.clone()))?;
+                let mut peer = PeerState::new(key, source, now);
+                self.accept_header(&mut peer, &mut state.channels, contents, now)?;
+                state.insert_peer(peer_id.clone(), peer);
+            }
+        }
+        let NodeState {
+            peers,
+            channels,
+            queries,
+        } = state;
+        let peer = peers.get_mut(peer_id).expect("peer was just accepted");
+        peer.addr = source;
+        peer.last_seen = now;
+        if via_channel {
+            peer.channel_ready = true;
+        }
+
+        let mut inbound = Vec::new();
+        let mut pending = VecDeque::from(contents.take_messages());
+        while let Some(message) = pending.pop_front() {
+            match message {
+                AdnlMessage::CreateChannel { key, .. } => {
+                    let Some(key) = PublicKey::from_bytes(key.0) else {
+                        continue;
+                    };
+                    if peer.channel.as_ref().map(AdnlChannel::peer_key) != Some(&key) {
+                        self.open_channel(peer_id, peer, channels, key, false);
+                        inbound.push(Inbound::Reply(AdnlMessage::Nop));
+                    }
+                }
+                AdnlMessage::ConfirmChannel { key, peer_key, .. } => {
+                    if peer_key.0 != peer.channel_key.public_key.to_bytes() {
+                        continue;
+                    }
+                    let Some(key) = PublicKey::from_bytes(key.0) else {
+                        continue;
+                    };
+                    if peer.channel.as_ref().map(AdnlChannel::peer_key) != Some(&key) {
+                        self.open_channel(peer_id, peer, channels, key, true);
+                    } else {
+                        peer.channel_ready = true;
+                    }
+                }
+                AdnlMessage::Query { query_id, query } => {
+                    inbound.push(Inbound::Query { query_id, query })
+                }
+                AdnlMessage::Answer { query_id, answer } => {
+                    if let Entry::Occupied(entry) = queries.entry(query_id.0)
+                        && entry.get().0 == *peer_id
+                    {
+                        let _ = entry.remove().1.send(answer);
+                    }
+                }
+                AdnlMessage::Custom { data } => inbound.push(Inbound::Custom(data)),
+                AdnlMessage::Part {
+                    hash,
+                    total_size,
+                    offset,
+                    data,
+                } => {
+                    if let Some(message) = accept_part(peer, hash.0, total_size, offset, data)? {
+                        if matches!(message, AdnlMessage::Part { .. }) {
+                            return Err(AdnlError::InvalidPacket(
+                                "nested adnl.message.part".to_string(),
+                            ));
+                        }
+                        pending.push_front(message);
+                    }
+                }
+                AdnlMessage::Nop | AdnlMessage::Reinit { .. } => {}
+            }
+        }
+        Ok(inbound)
+    }
+
+    /// Checks the reinit dates, seqno, and confirmed seqno of a packet from
+    /// `peer` and records them.
+    fn accept_header(
+        &self,
+        peer: &mut PeerState,
+        channels: &mut HashMap<[u8; 32], AdnlAddress>,
+        contents: &AdnlPacketContents,
+        now: i32,
+    ) -> Result<(), AdnlError> {
+        if let Some(dates) = contents.reinit_dates {
+            if dates.dst_reinit_date != 0 && dates.dst_reinit_date != self.reinit_date {
+                return Err(AdnlError::InvalidPacket(format!(
+                    "packet is addressed to reinit date {}, ours is {}",
+                    dates.dst_reinit_date, self.reinit_date
+                )));
+            }
+            if dates.reinit_date < peer.reinit_date {
+                return Err(AdnlError::InvalidPacket(format!(
+                    "peer reinit date {} is older than {}",
+                    dates.reinit_date, peer.reinit_date
+                )));
+            }
+            if dates.reinit_date > peer.reinit_date {
+                if peer.reinit_date != 0
+                    && let Some(channel_id) = peer.reset(dates.reinit_date, now)
+                {
+                    channels.remove(&channel_id);
+                }
+                peer.reinit_date = dates.reinit_date;
+            }
+        }
+        if let Some(seqno) = contents.seqno
+            && peer.in_seqnos.is_duplicate(seqno)
+        {
+            return Err(AdnlError::InvalidPacket(format!("duplicate seqno {seqno}")));
+        }
+        if let Some(confirm) = contents.confirm_seqno {
+            if confirm > peer.out_seqno {
+                return Err(AdnlError::InvalidPacket(format!(
+                    "confirm seqno {confirm} is ahead of sent seqno {}",
+                    peer.out_seqno
+                )));
+            }
+            peer.ack_seqno = peer.ack_seqno.max(confirm);
+        }
+        if let Some(seqno) = contents.seqno {
+            peer.in_seqnos.mark(seqno);
+        }
+        Ok(())
+    }
+
+    fn open_channel(
+        &self,
+        peer_id: &AdnlAddress,
+        peer: &mut PeerState,
+        channels: &mut HashMap<[u8; 32], AdnlAddress>,
+        key: PublicKey,
+        ready: bool,
+    ) {
+        if let Some(old) = peer.channel.take() {
+            channels.remove(old.in_id());
+        }
+        let channel = AdnlChannel::new(&self.id, peer_id, &peer.channel_key, &key);
+        channels.insert(*channel.in_id(), peer_id.clone());
+        peer.channel = Some(channel);
+        peer.channel_ready = ready;
+    }
+
+    fn spawn_query(self: &Arc<Self>, peer_id: AdnlAddress, query_id: Int256, query: Vec<u8>) {
+        let Some(handler) = self.handler.read().unwrap().clone() else {
+            log::debug!("dropping ADNL query from {peer_id:?}: no handler is set");
+            return;
+        };
+        let inner = self.clone();
+        tokio::spawn(async move {
+            let Some(answer) = handler.handle_query(&peer_id, &query).await else {
+                return;
+            };
+            if let Err(error) = inner
+                .send_messages(&peer_id, vec![AdnlMessage::Answer { query_id, answer }])
+                .await
+            {
+                log::debug!("failed to answer ADNL query from {peer_id:?}: {error}");
+            }
+        });
+    }
+}
+
+/// Groups messages into packets, splitting oversized ones into
+/// `adnl.message.part` chunks sent one per packet.
+fn split_messages(messages: Vec<AdnlMessage>) -> Vec<Vec<AdnlMessage>> {
+    let mut packets = Vec::new();
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+    for message in messages {
+        let bytes = tl_proto::serialize(&message);
+        if bytes.len() > ADNL_MAX_MESSAGE_BYTES {
+            let hash = Int256(Sha256::digest(&bytes).into());
+            for (index, chunk) in bytes.chunks(ADNL_MAX_MESSAGE_BYTES).enumerate() {
+                packets.push(vec![AdnlMessage::Part {
+                    hash: hash.clone(),
+                    total_size: bytes.len() as i32,
+                    offset: (index * ADNL_MAX_MESSAGE_BYTES) as i32,
+                    data: chunk.to_vec(),
+                }]);
+            }
+            continue;
+        }
+        if batch_bytes + bytes.len() > ADNL_MAX_MESSAGE_BYTES && !batch.is_empty() {
+            packets.push(std::mem::take(&mut batch));
+            batch_bytes = 0;
+        }
+        batch_bytes += bytes.len();
+        batch.push(message);
+    }
+    if !batch.is_empty() || packets.is_empty() {
+        packets.push(batch);
+    }
+    packets
+}
+
+/// Stores one `adnl.message.part` chunk and returns the reassembled message
+/// once every byte has arrived.
+pub(super) fn accept_part(
+    peer: &mut PeerState,
+    hash: [u8; 32],
+    total_size: i32,
+    offset: i32,
+    data: Vec<u8>,
+) -> Result<Option<AdnlMessage>, AdnlError> {
+    let invalid = |reason: &str| AdnlError::InvalidPacket(format!("adnl.message.part {reason}"));
+    let total = usize::try_from(total_size)
+        .ok()
+        .filter(|total| (1..=ADNL_MAX_PARTS_TOTAL_BYTES).contains(total))
+        .ok_or_else(|| invalid("has an invalid total size"))?;
+    let offset = usize::try_from(offset).map_err(|_| invalid("has a negative offset"))?;
+    if offset + data.len() > total {
+        return Err(invalid("overflows its total size"));
+    }
+    if !peer.parts.contains_key(&hash) && peer.parts.len() >= MAX_PARTIAL_MESSAGES {
+        return Err(invalid("exceeds the pending multipart limit"));
+    }
+    let partial = peer
+        .parts
+        .entry(hash)
+        .or_insert_with(|| PartialMessage::new(total));
+    if partial.data.len() != total {
+        return Err(invalid("changes its total size"));
+    }
+    partial.insert(offset, &data);
+    if !partial.is_complete() {
+        return Ok(None);
+    }
+
+    let partial = peer.parts.remove(&hash).expect("entry was just updated");
+    if Sha256::digest(&partial.data).as_slice() != hash {
+        return Err(invalid("hash does not match the reassembled data"));
+    }
+    tl_proto::deserialize(&partial.data)
+        .map(Some)
+        .map_err(|error| AdnlError::InvalidPacket(error.to_string()))
+}
+
+pub(crate) fn unix_now() -> i32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i32)
+        .unwrap_or_default()
+}
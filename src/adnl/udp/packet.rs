@@ -0,0 +1,298 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use derivative::Derivative;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use tl_proto::{TlRead, TlWrite};
+
+use crate::adnl::crypto::tl::PublicKeyOwned;
+use crate::adnl::crypto::{KeyPair, PublicKey};
+use crate::adnl::helper_types::CryptoRandom;
+use crate::adnl::primitives::adnl_aes_from_secret;
+use crate::adnl::{AdnlAddress, AdnlError};
+use crate::tl::AdnlMessage;
+use crate::tl::common::Int256;
+use crate::tl::utils::fmt_bytes;
+use ctr::cipher::StreamCipher;
+
+/// Length of the random `rand1`/`rand2` paddings written by this crate.
+const PACKET_RAND_BYTES: usize = 15;
+
+/// `adnl.Address` entry of an address list.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum AdnlUdpAddress {
+    /// adnl.address.udp ip:int port:int = adnl.Address;
+    #[tl(id = 0x670da6e7)]
+    Udp { ip: i32, port: i32 },
+    /// adnl.address.udp6 ip:int128 port:int = adnl.Address;
+    #[tl(id = 0xe31d63fa)]
+    Udp6 { ip: [u8; 16], port: i32 },
+}
+
+impl From<SocketAddr> for AdnlUdpAddress {
+    fn from(addr: SocketAddr) -> Self {
+        match addr.ip() {
+            IpAddr::V4(ip) => Self::Udp {
+                ip: u32::from(ip) as i32,
+                port: addr.port() as i32,
+            },
+            IpAddr::V6(ip) => Self::Udp6 {
+                ip: ip.octets(),
+                port: addr.port() as i32,
+            },
+        }
+    }
+}
+
+impl AdnlUdpAddress {
+    /// Returns the socket address, or `None` for an out-of-range port.
+    pub fn to_socket_addr(&self) -> Option<SocketAddr> {
+        match *self {
+            Self::Udp { ip, port } => Some(SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::from(ip as u32)),
+                u16::try_from(port).ok()?,
+            )),
+            Self::Udp6 { ip, port } => Some(SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::from(ip)),
+                u16::try_from(port).ok()?,
+            )),
+        }
+    }
+}
+
+/// adnl.addressList addrs:(vector adnl.Address) version:int reinit_date:int priority:int expire_at:int = adnl.AddressList;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq, Default)]
+pub struct AdnlAddressList {
+    pub addrs: Vec<AdnlUdpAddress>,
+    pub version: i32,
+    pub reinit_date: i32,
+    pub priority: i32,
+    pub expire_at: i32,
+}
+
+impl tl_proto::BoxedConstructor for AdnlAddressList {
+    const TL_ID: u32 = 0x2227e658;
+}
+
+impl AdnlAddressList {
+    /// Creates a non-expiring list with a single UDP address.
+    pub fn udp(addr: SocketAddr, version: i32, reinit_date: i32) -> Self {
+        Self {
+            addrs: vec![addr.into()],
+            version,
+            reinit_date,
+            priority: 0,
+            expire_at: 0,
+        }
+    }
+
+    /// Returns the listed addresses that map to socket addresses.
+    pub fn socket_addrs(&self) -> Vec<SocketAddr> {
+        self.addrs
+            .iter()
+            .filter_map(AdnlUdpAddress::to_socket_addr)
+            .collect()
+    }
+}
+
+/// `reinit_date` and `dst_reinit_date`, which share flag bit 10 of
+/// `adnl.packetContents`.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, Copy, PartialEq)]
+pub struct AdnlReinitDates {
+    /// Start date of the sending node.
+    pub reinit_date: i32,
+    /// Start date of the receiving node as known to the sender, or `0`.
+    pub dst_reinit_date: i32,
+}
+
+/// adnl.packetContents rand1:bytes flags:# from:flags.0?PublicKey ... rand2:bytes = adnl.PacketContents;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed, id = 0xd142cd89)]
+pub struct AdnlPacketContents {
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub rand1: Vec<u8>,
+    #[tl(flags)]
+    pub flags: (),
+    #[tl(flags_bit = "flags.0")]
+    pub from: Option<PublicKeyOwned>,
+    #[tl(flags_bit = "flags.1")]
+    pub from_short: Option<Int256>,
+    #[tl(flags_bit = "flags.2")]
+    pub message: Option<AdnlMessage>,
+    #[tl(flags_bit = "flags.3")]
+    pub messages: Option<Vec<AdnlMessage>>,
+    #[tl(flags_bit = "flags.4")]
+    pub address: Option<AdnlAddressList>,
+    #[tl(flags_bit = "flags.5")]
+    pub priority_address: Option<AdnlAddressList>,
+    #[tl(flags_bit = "flags.6")]
+    pub seqno: Option<i64>,
+    #[tl(flags_bit = "flags.7")]
+    pub confirm_seqno: Option<i64>,
+    #[tl(flags_bit = "flags.8")]
+    pub recv_addr_list_version: Option<i32>,
+    #[tl(flags_bit = "flags.9")]
+    pub recv_priority_addr_list_version: Option<i32>,
+    #[tl(flags_bit = "flags.10")]
+    pub reinit_dates: Option<AdnlReinitDates>,
+    #[tl(flags_bit = "flags.11")]
+    pub signature: Option<Vec<u8>>,
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub rand2: Vec<u8>,
+}
+
+impl AdnlPacketContents {
+    /// Creates empty contents with random `rand1`/`rand2` paddings.
+    pub fn new<R: RngCore>(rng: &mut R) -> Self {
+        let mut rand1 = vec![0u8; PACKET_RAND_BYTES];
+        let mut rand2 = vec![0u8; PACKET_RAND_BYTES];
+        rng.fill_bytes(&mut rand1);
+        rng.fill_bytes(&mut rand2);
+        Self {
+            rand1,
+            flags: (),
+            from: None,
+            from_short: None,
+            message: None,
+            messages: None,
+            address: None,
+            priority_address: None,
+            seqno: None,
+            confirm_seqno: None,
+            recv_addr_list_version: None,
+            recv_priority_addr_list_version: None,
+            reinit_dates: None,
+            signature: None,
+            rand2,
+        }
+    }
+
+    /// Stores `messages` in `message` for a single entry and in `messages`
+    /// otherwise.
+    pub fn set_messages(&mut self, mut messages: Vec<AdnlMessage>) {
+        if messages.len() == 1 {
+            self.message = messages.pop();
+            self.messages = None;
+        } else {
+            self.message = None;
+            self.messages = Some(messages);
+        }
+    }
+
+    /// Returns the carried messages from both `message` and `messages`.
+    pub fn take_messages(&mut self) -> Vec<AdnlMessage> {
+        let mut messages = self.message.take().into_iter().collect::<Vec<_>>();
+        messages.extend(self.messages.take().unwrap_or_default());
+        messages
+    }
+
+    /// Returns the sender public key from `from`, if it is an ed25519 key.
+    pub fn from_public_key(&self) -> Result<Option<PublicKey>, AdnlError> {
+        match &self.from {
+            None => Ok(None),
+            Some(key) => PublicKey::from_tl(key.as_equivalent_ref())
+                .map(Some)
+                .ok_or(AdnlError::InvalidPublicKey),
+        }
+    }
+
+    /// Sets `from` to the key pair's public key and signs the contents.
+    ///
+    /// The signature covers the serialized contents with the `signature`
+    /// field absent.
+    pub fn sign(&mut self, keypair: &KeyPair) {
+        self.from = Some(keypair.public_key.as_tl().as_equivalent_owned());
+        self.signature = None;
+        let signature = keypair.sign_raw(&tl_proto::serialize(&*self));
+        self.signature = Some(signature.to_vec());
+    }
+
+    /// Checks the signature against the `from` key.
+    ///
+    /// Returns the signer, or `None` when the contents are unsigned.
+    pub fn verify(&self) -> Result<Option<PublicKey>, AdnlError> {
+        let Some(signature) = &self.signature else {
+            return Ok(None);
+        };
+        let signer = self.from_public_key()?.ok_or_else(|| {
+            AdnlError::InvalidPacket("signed packet has no `from` key".to_string())
+        })?;
+        let signature: &[u8; 64] = signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| AdnlError::InvalidSignature)?;
+        let mut unsigned = self.clone();
+        unsigned.signature = None;
+        if signer.verify_raw(&tl_proto::serialize(&unsigned), signature) {
+            Ok(Some(signer))
+        } else {
+            Err(AdnlError::InvalidSignature)
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        tl_proto::serialize(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AdnlError> {
+        tl_proto::deserialize(bytes).map_err(|error| AdnlError::InvalidPacket(error.to_string()))
+    }
+}
+
+/// Encrypts packet contents for `peer_key` without a channel.
+///
+/// The datagram is `peer_id || ephemeral_key || sha256(contents) ||
+/// aes_ctr(contents)`, keyed by ECDH between a fresh ephemeral key and the
+/// peer key.
+pub fn encrypt_datagram<R: CryptoRandom>(
+    peer_id: &AdnlAddress,
+    peer_key: &PublicKey,
+    contents: &[u8],
+    rng: &mut R,
+) -> Vec<u8> {
+    let ephemeral = KeyPair::generate(rng);
+    let secret = ephemeral.compute_shared_secret(peer_key);
+    let checksum: [u8; 32] = Sha256::digest(contents).into();
+
+    let mut datagram = Vec::with_capacity(96 + contents.len());
+    datagram.extend_from_slice(peer_id.as_bytes());
+    datagram.extend_from_slice(ephemeral.public_key.as_bytes());
+    datagram.extend_from_slice(&checksum);
+    let offset = datagram.len();
+    datagram.extend_from_slice(contents);
+    adnl_aes_from_secret(&secret, &checksum).apply_keystream(&mut datagram[offset..]);
+    datagram
+}
+
+/// Decrypts a datagram produced by `encrypt_datagram` for `keypair`.
+///
+/// The caller routes the datagram by its leading 32-byte id before calling
+/// this function.
+pub fn decrypt_datagram(datagram: &[u8], keypair: &KeyPair) -> Result<Vec<u8>, AdnlError> {
+    if datagram.len() < 96 {
+        return Err(AdnlError::TooShortPacket);
+    }
+    let ephemeral = PublicKey::from_bytes(datagram[32..64].try_into().unwrap())
+        .ok_or(AdnlError::InvalidPublicKey)?;
+    let checksum: [u8; 32] = datagram[64..96].try_into().unwrap();
+    let secret = keypair.compute_shared_secret(&ephemeral);
+    decrypt_with_checksum(&secret, &checksum, &datagram[96..])
+}
+
+pub(super) fn decrypt_with_checksum(
+    secret: &[u8; 32],
+    checksum: &[u8; 32],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, AdnlError> {
+    let mut contents = ciphertext.to_vec();
+    adnl_aes_from_secret(secret, checksum).apply_keystream(&mut contents);
+    if Sha256::digest(&contents).as_slice() != checksum {
+        return Err(AdnlError::IntegrityError);
+    }
+    Ok(contents)
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use crate::adnl::crypto::{KeyPair, PublicKey};
+
+use super::channel::AdnlChannel;
+
+/// Sliding window over received packet seqnos.
+///
+/// Bit `i` of `mask` records whether `max - i` was received, so duplicates
+/// within the last 64 seqnos are detected and older ones are rejected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeqnoWindow {
+    max: i64,
+    mask: u64,
+}
+
+impl SeqnoWindow {
+    /// Highest seqno received so far, `0` before the first packet.
+    pub fn max(&self) -> i64 {
+        self.max
+    }
+
+    /// Returns whether `seqno` was already received or fell out of the window.
+    pub fn is_duplicate(&self, seqno: i64) -> bool {
+        if seqno > self.max {
+            return false;
+        }
+        let distance = self.max - seqno;
+        distance >= 64 || self.mask & (1 << distance) != 0
+    }
+
+    /// Records `seqno` as received.
+    pub fn mark(&mut self, seqno: i64) {
+        if seqno > self.max {
+            let shift = seqno - self.max;
+            self.mask = if shift >= 64 { 0 } else { self.mask << shift };
+            self.mask |= 1;
+            self.max = seqno;
+        } else if self.max - seqno < 64 {
+            self.mask |= 1 << (self.max - seqno);
+        }
+    }
+}
+
+/// Partially received `adnl.message.part` sequence.
+///
+/// A bitmap records which bytes have arrived, so retransmitted or overlapping
+/// chunks are not counted twice.
+pub(super) struct PartialMessage {
+    pub data: Vec<u8>,
+    arrived: Vec<u64>,
+    received: usize,
+}
+
+impl PartialMessage {
+    pub fn new(total: usize) -> Self {
+        Self {
+            data: vec![0; total],
+            arrived: vec![0; total.div_ceil(64)],
+            received: 0,
+        }
+    }
+
+    /// Copies `chunk` to `offset`, which the caller has bounds-checked.
+    pub fn insert(&mut self, offset: usize, chunk: &[u8]) {
+        self.data[offset..offset + chunk.len()].copy_from_slice(chunk);
+        for index in offset..offset + chunk.len() {
+            let (word, bit) = (index / 64, 1 << (index % 64));
+            if self.arrived[word] & bit == 0 {
+                self.arrived[word] |= bit;
+                self.received += 1;
+            }
+        }
+    }
+
+    /// Returns whether every byte has arrived.
+    pub fn is_complete(&self) -> bool {
+        self.received == self.data.len()
+    }
+}
+
+/// Per-peer session state kept by `AdnlNode`.
+pub(super) struct PeerState {
+    pub public_key: PublicKey,
+    pub addr: SocketAddr,
+    /// Latest start date announced by the peer, `0` until known.
+    pub reinit_date: i32,
+    pub out_seqno: i64,
+    pub ack_seqno: i64,
+    pub in_seqnos: SeqnoWindow,
+    pub channel_key: KeyPair,
+    pub channel_date: i32,
+    pub channel: Option<AdnlChannel>,
+    /// Set once the peer is known to decrypt packets sent over `channel`.
+    pub channel_ready: bool,
+    pub parts: HashMap<[u8; 32], PartialMessage>,
+    /// Unix time of the last accepted packet, or of registration.
+    pub last_seen: i32,
+}
+
+impl PeerState {
+    pub fn new(public_key: PublicKey, addr: SocketAddr, now: i32) -> Self {
+        Self {
+            public_key,
+            addr,
+            reinit_date: 0,
+            out_seqno: 0,
+            ack_seqno: 0,
+            in_seqnos: SeqnoWindow::default(),
+            channel_key: KeyPair::generate(&mut rand::rngs::OsRng),
+            channel_date: now,
+            channel: None,
+            channel_ready: false,
+            parts: HashMap::new(),
+            last_seen: now,
+        }
+    }
+
+    /// Drops the session after the peer restarted with a newer `reinit_date`.
+    ///
+    /// Returns the inbound id of the discarded channel, if any.
+    pub fn reset(&mut self, reinit_date: i32, now: i32) -> Option<[u8; 32]> {
+        let channel = self.channel.take();
+        *self = Self {
+            reinit_date,
+            ..Self::new(self.public_key, self.addr, now)
+        };
+        channel.map(|channel| *channel.in_id())
+    }
+}
@@ -0,0 +1,337 @@
+use super::node::{MAX_PEERS, accept_part};
+use super::peer::PeerState;
+use super::*;
+use crate::adnl::crypto::KeyPair;
+use crate::adnl::{AdnlAddress, AdnlError};
+use crate::tl::AdnlMessage;
+use crate::tl::common::Int256;
+use async_trait::async_trait;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Reverse {
+    custom: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+#[async_trait]
+impl AdnlMessageHandler for Reverse {
+    async fn handle_query(&self, _peer: &AdnlAddress, query: &[u8]) -> Option<Vec<u8>> {
+        Some(query.iter().rev().copied().collect())
+    }
+
+    async fn handle_custom(&self, _peer: &AdnlAddress, data: &[u8]) {
+        self.custom.send(data.to_vec()).unwrap();
+    }
+}
+
+async fn node() -> AdnlNode {
+    AdnlNode::bind("127.0.0.1:0", KeyPair::generate(&mut OsRng))
+        .await
+        .unwrap()
+}
+
+#[test]
+fn packet_contents_roundtrip_and_verify_signature() {
+    let keypair = KeyPair::generate(&mut OsRng);
+    let mut contents = AdnlPacketContents::new(&mut OsRng);
+    contents.set_messages(vec![AdnlMessage::Custom {
+        data: b"hello".to_vec(),
+    }]);
+    contents.seqno = Some(1);
+    contents.confirm_seqno = Some(0);
+    contents.address = Some(AdnlAddressList::udp(
+        "127.0.0.1:3000".parse().unwrap(),
+        7,
+        7,
+    ));
+    contents.reinit_dates = Some(AdnlReinitDates {
+        reinit_date: 7,
+        dst_reinit_date: 0,
+    });
+    contents.sign(&keypair);
+
+    let decoded = AdnlPacketContents::from_bytes(&contents.to_bytes()).unwrap();
+    assert_eq!(decoded, contents);
+    assert_eq!(decoded.verify().unwrap(), Some(keypair.public_key));
+    assert_eq!(
+        decoded.address.as_ref().unwrap().socket_addrs(),
+        vec!["127.0.0.1:3000".parse().unwrap()]
+    );
+
+    let mut tampered = decoded.clone();
+    tampered.seqno = Some(2);
+    assert!(matches!(
+        tampered.verify(),
+        Err(AdnlError::InvalidSignature)
+    ));
+
+    let mut messages = decoded;
+    assert_eq!(messages.take_messages().len(), 1);
+}
+
+#[test]
+fn packet_contents_store_single_and_multiple_messages() {
+    let mut contents = AdnlPacketContents::new(&mut OsRng);
+    contents.set_messages(vec![AdnlMessage::Nop]);
+    assert!(contents.message.is_some() && contents.messages.is_none());
+
+    contents.set_messages(vec![AdnlMessage::Nop, AdnlMessage::Reinit { date: 1 }]);
+    assert!(contents.message.is_none());
+    assert_eq!(
+        contents.take_messages(),
+        vec![AdnlMessage::Nop, AdnlMessage::Reinit { date: 1 }]
+    );
+}
+
+#[test]
+fn datagram_encryption_roundtrips_and_detects_corruption() {
+    let receiver = KeyPair::generate(&mut OsRng);
+    let receiver_id = AdnlAddress::from(&receiver.public_key);
+    let datagram = encrypt_datagram(&receiver_id, &receiver.public_key, b"contents", &mut OsRng);
+
+    assert_eq!(&datagram[..32], receiver_id.as_bytes());
+    assert_eq!(decrypt_datagram(&datagram, &receiver).unwrap(), b"contents");
+
+    let mut corrupted = datagram;
+    *corrupted.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        decrypt_datagram(&corrupted, &receiver),
+        Err(AdnlError::IntegrityError)
+    ));
+}
+
+#[test]
+fn channel_keys_mirror_between_peers() {
+    let alice = KeyPair::generate(&mut OsRng);
+    let bob = KeyPair::generate(&mut OsRng);
+    let alice_channel_key = KeyPair::generate(&mut OsRng);
+    let bob_channel_key = KeyPair::generate(&mut OsRng);
+    let alice_id = AdnlAddress::from(&alice.public_key);
+    let bob_id = AdnlAddress::from(&bob.public_key);
+
+    let at_alice = AdnlChannel::new(
+        &alice_id,
+        &bob_id,
+        &alice_channel_key,
+        &bob_channel_key.public_key,
+    );
+    let at_bob = AdnlChannel::new(
+        &bob_id,
+        &alice_id,
+        &bob_channel_key,
+        &alice_channel_key.public_key,
+    );
+
+    assert_eq!(at_alice.out_id(), at_bob.in_id());
+    assert_eq!(at_bob.out_id(), at_alice.in_id());
+    assert_ne!(at_alice.in_id(), at_alice.out_id());
+    assert_eq!(
+        at_bob.decrypt(&at_alice.encrypt(b"to bob")).unwrap(),
+        b"to bob"
+    );
+    assert_eq!(
+        at_alice.decrypt(&at_bob.encrypt(b"to alice")).unwrap(),
+        b"to alice"
+    );
+    assert!(at_alice.decrypt(&at_alice.encrypt(b"loop")).is_err());
+}
+
+#[test]
+fn seqno_window_rejects_duplicates_and_old_packets() {
+    let mut window = SeqnoWindow::default();
+    assert!(!window.is_duplicate(1));
+    window.mark(1);
+    window.mark(3);
+    assert!(window.is_duplicate(1));
+    assert!(!window.is_duplicate(2));
+    assert!(window.is_duplicate(3));
+    window.mark(2);
+    assert!(window.is_duplicate(2));
+    window.mark(100);
+    assert_eq!(window.max(), 100);
+    assert!(window.is_duplicate(36));
+    assert!(!window.is_duplicate(37));
+}
+
+#[test]
+fn udp_address_maps_ipv4_and_ipv6() {
+    for addr in ["10.1.2.3:30303", "[::1]:40404"] {
+        let addr = addr.parse().unwrap();
+        assert_eq!(AdnlUdpAddress::from(addr).to_socket_addr(), Some(addr));
+    }
+    let bytes = tl_proto::serialize(AdnlUdpAddress::from(
+        "1.2.3.4:5".parse::<std::net::SocketAddr>().unwrap(),
+    ));
+    assert_eq!(bytes, [0xe7, 0xa6, 0x0d, 0x67, 4, 3, 2, 1, 5, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn nodes_exchange_queries_and_open_channels() {
+    let client = node().await;
+    let server = node().await;
+    let (custom, mut customs) = mpsc::unbounded_channel();
+    server.set_handler(Arc::new(Reverse { custom }));
+    let server_id = client.add_peer(*server.public_key(), server.local_addr().unwrap());
+
+    let answer = client
+        .query(&server_id, b"ping".to_vec(), TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(answer, b"gnip");
+    assert!(client.has_channel(&server_id));
+    assert!(!server.has_channel(client.id()));
+
+    let answer = client
+        .query(&server_id, b"over channel".to_vec(), TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(answer, b"lennahc revo");
+    assert!(server.has_channel(client.id()));
+
+    client
+        .send_custom(&server_id, b"custom".to_vec())
+        .await
+        .unwrap();
+    let received = tokio::time::timeout(TIMEOUT, customs.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(received, b"custom");
+}
+
+#[tokio::test]
+async fn large_queries_and_answers_use_message_parts() {
+    let client = node().await;
+    let server = node().await;
+    let (custom, _customs) = mpsc::unbounded_channel();
+    server.set_handler(Arc::new(Reverse { custom }));
+    let server_id = client.add_peer(*server.public_key(), server.local_addr().unwrap());
+
+    let query = (0..5_000).map(|byte| byte as u8).collect::<Vec<_>>();
+    let answer = client
+        .query(&server_id, query.clone(), TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(answer, query.into_iter().rev().collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn query_without_handler_times_out() {
+    let client = node().await;
+    let server = node().await;
+    let server_id = client.add_peer(*server.public_key(), server.local_addr().unwrap());
+
+    let result = client
+        .query(&server_id, b"ping".to_vec(), Duration::from_millis(200))
+        .await;
+    assert!(matches!(result, Err(AdnlError::Timeout { .. })));
+    assert!(matches!(
+        client
+            .query(
+                &AdnlAddress::from([7; 32]),
+                Vec::new(),
+                Duration::from_millis(200)
+            )
+            .await,
+        Err(AdnlError::UnknownPeer(_))
+    ));
+}
+
+#[test]
+fn message_constructor_ids_match_schema() {
+    let message = tl_proto::serialize(AdnlMessage::Part {
+        hash: Int256([0; 32]),
+        total_size: 1,
+        offset: 0,
+        data: vec![1],
+    });
+    assert_eq!(&message[..4], &0xfd452d39u32.to_le_bytes());
+    let contents = AdnlPacketContents::new(&mut OsRng).to_bytes();
+    assert_eq!(&contents[..4], &0xd142cd89u32.to_le_bytes());
+}
+
+#[test]
+fn retransmitted_message_part_is_counted_once() {
+    let keypair = KeyPair::generate(&mut OsRng);
+    let mut peer = PeerState::new(keypair.public_key, "127.0.0.1:1".parse().unwrap(), 0);
+    let message = AdnlMessage::Custom {
+        data: vec![7; 2 * ADNL_MAX_MESSAGE_BYTES],
+    };
+    let bytes = tl_proto::serialize(&message);
+    let hash: [u8; 32] = Sha256::digest(&bytes).into();
+    let chunks = bytes.chunks(ADNL_MAX_MESSAGE_BYTES).collect::<Vec<_>>();
+    assert_eq!(chunks.len(), 3);
+    let mut part = |index: usize| {
+        accept_part(
+            &mut peer,
+            hash,
+            bytes.len() as i32,
+            (index * ADNL_MAX_MESSAGE_BYTES) as i32,
+            chunks[index].to_vec(),
+        )
+        .unwrap()
+    };
+
+    assert!(part(0).is_none());
+    assert!(part(1).is_none());
+    assert!(part(1).is_none());
+    assert!(part(0).is_none());
+    assert_eq!(part(2), Some(message));
+}
+
+#[tokio::test]
+async fn rejected_packet_does_not_register_sender() {
+    let server = node().await;
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let sender = KeyPair::generate(&mut OsRng);
+    let sender_id = AdnlAddress::from(&sender.public_key);
+    let datagram = |dst_reinit_date: i32| {
+        let mut contents = AdnlPacketContents::new(&mut OsRng);
+        contents.set_messages(vec![AdnlMessage::Nop]);
+        contents.seqno = Some(1);
+        contents.reinit_dates = Some(AdnlReinitDates {
+            reinit_date: 1,
+            dst_reinit_date,
+        });
+        contents.sign(&sender);
+        encrypt_datagram(
+            server.id(),
+            server.public_key(),
+            &contents.to_bytes(),
+            &mut OsRng,
+        )
+    };
+    let server_addr = server.local_addr().unwrap();
+
+    let stale = datagram(server.reinit_date() - 1);
+    socket.send_to(&stale, server_addr).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!server.has_peer(&sender_id));
+
+    socket.send_to(&datagram(0), server_addr).await.unwrap();
+    tokio::time::timeout(TIMEOUT, async {
+        while !server.has_peer(&sender_id) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn peer_table_evicts_beyond_its_cap() {
+    let node = node().await;
+    let addr = "127.0.0.1:1".parse().unwrap();
+    let ids = (0..=MAX_PEERS)
+        .map(|_| node.add_peer(KeyPair::generate(&mut OsRng).public_key, addr))
+        .collect::<Vec<_>>();
+
+    let kept = ids.iter().filter(|id| node.has_peer(id)).count();
+    assert_eq!(kept, MAX_PEERS);
+    assert!(node.has_peer(ids.last().unwrap()));
+}
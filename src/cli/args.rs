@@ -21,6 +21,7 @@ pub enum OutputFormat {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum WalletVersionArg {
+    V3R2,
     V4R2,
     V5R1,
 }
@@ -330,6 +330,7 @@ pub(super) fn wallet_id_for_cli(
         return Ok(wallet_id);
     }
     match version {
+        WalletVersionArg::V3R2 => Ok(WALLET_V3R2_DEFAULT_ID),
         WalletVersionArg::V4R2 => Ok(WALLET_V4R2_DEFAULT_ID),
         WalletVersionArg::V5R1 => {
             let global_id = match network {
@@ -348,6 +349,9 @@ pub(super) fn wallet_address_view(
     public_key: [u8; 32],
 ) -> Result<WalletAddressView> {
     let address = match version {
+        WalletVersionArg::V3R2 => {
+            WalletV3R2::new(public_key, wallet_id, wallet_v3r2_code()?, workchain).address()?
+        }
         WalletVersionArg::V4R2 => {
             WalletV4R2::new(public_key, wallet_id, wallet_v4r2_code()?, workchain).address()?
         }
@@ -402,10 +406,7 @@ pub(super) fn comment_body(comment: &Option<String>) -> Result<Option<Arc<Cell>>
     let Some(comment) = comment else {
         return Ok(None);
     };
-    let mut builder = Builder::new();
-    builder.store_u32(0)?;
-    builder.store_bytes(comment.as_bytes())?;
-    Ok(Some(builder.build()?))
+    Ok(Some(crate::comment::text_comment_cell(comment)?))
 }
 
 pub(super) fn valid_until_from_timeout(timeout: u32) -> Result<u32> {
@@ -470,6 +471,16 @@ pub(super) fn build_wallet_transfer(
     }
     let valid_until = valid_until_from_timeout(args.timeout)?;
     let boc = match args.version {
+        WalletVersionArg::V3R2 => {
+            WalletV3R2::new(public_key, wallet_id, wallet_v3r2_code()?, args.workchain)
+                .build_external_message_boc(
+                    seqno,
+                    valid_until,
+                    vec![message],
+                    mnemonic.signing_key(),
+                    args.deploy,
+                )?
+        }
         WalletVersionArg::V4R2 => {
             WalletV4R2::new(public_key, wallet_id, wallet_v4r2_code()?, args.workchain)
                 .build_external_message_boc(
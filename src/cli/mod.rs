@@ -5,10 +5,11 @@ pub(super) use crate::liteclient::{
 pub(super) use crate::network_config::{ConfigGlobal, ConfigLiteServer, LiteServerBlacklist};
 pub(super) use crate::tl::{AccountId, BlockIdExt, Int256, common::TransactionId3};
 pub(super) use crate::tlb::TlbDeserialize;
-pub(super) use crate::tvm::{Builder, Cell, TvmStack, TvmStackEntry, address::Address};
+pub(super) use crate::tvm::{Cell, TvmStack, TvmStackEntry, address::Address};
 pub(super) use crate::wallet::{
-    MAINNET_GLOBAL_ID, TESTNET_GLOBAL_ID, TonMnemonic, WALLET_V4R2_DEFAULT_ID, WalletMessage,
-    WalletV4R2, WalletV5R1, WalletV5R1WalletId, wallet_v4r2_code, wallet_v5r1_code,
+    MAINNET_GLOBAL_ID, TESTNET_GLOBAL_ID, TonMnemonic, WALLET_V3R2_DEFAULT_ID,
+    WALLET_V4R2_DEFAULT_ID, WalletMessage, WalletV3R2, WalletV4R2, WalletV5R1, WalletV5R1WalletId,
+    wallet_v3r2_code, wallet_v4r2_code, wallet_v5r1_code,
 };
 pub(super) use anyhow::{Context, Result};
 pub(super) use base64::Engine;
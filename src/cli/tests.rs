@@ -993,6 +993,10 @@ mod tests {
 
     #[test]
     fn wallet_cli_preserves_default_wallet_ids_for_networks() {
+        assert_eq!(
+            wallet_id_for_cli(WalletVersionArg::V3R2, Network::Mainnet, 0, None).unwrap(),
+            WALLET_V3R2_DEFAULT_ID
+        );
         assert_eq!(
             wallet_id_for_cli(WalletVersionArg::V4R2, Network::Mainnet, 0, None).unwrap(),
             WALLET_V4R2_DEFAULT_ID
@@ -1024,6 +1028,7 @@ mod tests {
             (Network::Testnet, WalletVersionArg::V5R1),
             (Network::Mainnet, WalletVersionArg::V4R2),
             (Network::Testnet, WalletVersionArg::V4R2),
+            (Network::Mainnet, WalletVersionArg::V3R2),
         ] {
             for deploy in [false, true] {
                 let args = transfer_args(version, deploy);
@@ -577,6 +577,20 @@ pub(super) fn shard_account_value(shard: &crate::tlb::ShardAccount) -> Value {
     })
 }
 
+fn message_comment_value(message: &crate::tlb::Message) -> Value {
+    let body = match &message.body {
+        crate::tlb::Either::Left(body) | crate::tlb::Either::Right(body) => body.clone(),
+    };
+    match crate::comment::parse_comment(body) {
+        Ok(Some(crate::comment::MessageComment::Text(text))) => json!({ "text": text }),
+        Ok(Some(crate::comment::MessageComment::Encrypted(payload))) => {
+            json!({ "encrypted": hex::encode(payload) })
+        }
+        Ok(None) => Value::Null,
+        Err(error) => json!({ "error": error.to_string() }),
+    }
+}
+
 pub(super) fn transaction_value(tx: &crate::tlb::Transaction) -> Value {
     json!({
         "account_addr": hex::encode(tx.account_addr),
@@ -588,6 +602,7 @@ pub(super) fn transaction_value(tx: &crate::tlb::Transaction) -> Value {
         "orig_status": account_status_name(tx.orig_status),
         "end_status": account_status_name(tx.end_status),
         "has_in_msg": tx.in_msg.is_some(),
+        "in_msg_comment": tx.in_msg.as_ref().map_or(Value::Null, message_comment_value),
         "out_msgs_key_bits": tx.out_msgs.key_bits(),
         "total_fees": currency_collection_value(&tx.total_fees),
         "state_update": {
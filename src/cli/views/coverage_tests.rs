@@ -1,4 +1,5 @@
 use super::*;
+use crate::tvm::Builder;
 use crate::wallet::WALLET_V5R1_MAINNET_DEFAULT_ID;
 use num_bigint::BigUint;
 
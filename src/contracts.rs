@@ -1,12 +1,22 @@
 //! High-level smart-contract helpers built on LiteAPI calls.
 
 mod blueprint;
+mod confirm;
 mod contract;
+mod local;
 mod provider;
 mod run_method;
 mod stack;
 #[cfg(test)]
+pub(crate) mod test_fixtures;
+#[cfg(test)]
 mod tests;
+mod trace;
+mod transactions;
 
+pub use confirm::*;
+pub use local::*;
 pub use provider::*;
 pub use stack::*;
+pub use trace::*;
+pub use transactions::*;
@@ -0,0 +1,205 @@
+//! Sending an external message and waiting for the transaction that
+//! processed it.
+//!
+//! [`send_and_confirm`] identifies the message by its TEP-467 normalized
+//! hash, sends it through a [`LiteBalancer`], and follows the destination
+//! account's transactions until one has a matching inbound message or the
+//! message's `valid_until` has passed.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::StreamExt;
+
+use crate::liteclient::balancer::{BalancerError, LiteBalancer};
+use crate::tlb::{
+    CommonMsgInfo, Message, MsgAddressInt, TlbDeserialize, TrActionPhase, TrComputePhase,
+};
+use crate::tvm::deserialize_boc;
+
+use super::{AccountTransaction, Contract, ContractError, TransactionCursor};
+
+/// Options for [`send_and_confirm`].
+#[derive(Debug, Clone)]
+pub struct SendOptions {
+    /// Unix time after which the destination contract rejects the message.
+    pub valid_until: u32,
+    /// Number of peers to send the message to; `None` uses the balancer's
+    /// default spread.
+    pub fan_out: Option<usize>,
+    pub poll_interval: Duration,
+    /// How long to keep watching after `valid_until` for blocks that
+    /// liteservers have not reported yet.
+    pub expiry_grace: Duration,
+}
+
+impl SendOptions {
+    pub fn new(valid_until: u32) -> Self {
+        Self {
+            valid_until,
+            fan_out: None,
+            poll_interval: Duration::from_secs(3),
+            expiry_grace: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_fan_out(mut self, peers: usize) -> Self {
+        self.fan_out = Some(peers);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_expiry_grace(mut self, expiry_grace: Duration) -> Self {
+        self.expiry_grace = expiry_grace;
+        self
+    }
+}
+
+/// The transaction that processed a message sent by [`send_and_confirm`].
+#[derive(Debug, Clone)]
+pub struct ConfirmedMessage {
+    /// Normalized hash of the sent message.
+    pub message_hash: [u8; 32],
+    pub transaction: AccountTransaction,
+}
+
+impl ConfirmedMessage {
+    pub fn compute_phase(&self) -> Option<&TrComputePhase> {
+        self.transaction.transaction.description.compute_phase()
+    }
+
+    pub fn action_phase(&self) -> Option<&TrActionPhase> {
+        self.transaction.transaction.description.action_phase()
+    }
+
+    /// VM exit code, or `None` when the compute phase was skipped.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.compute_phase()?.exit_code()
+    }
+
+    /// Whether the VM and the action phase both succeeded and the transaction
+    /// was not aborted.
+    pub fn success(&self) -> bool {
+        let computed = matches!(
+            self.compute_phase(),
+            Some(TrComputePhase::Vm { success: true, .. })
+        );
+        let acted = self.action_phase().is_none_or(|action| action.success);
+        computed && acted && !self.transaction.transaction.description.aborted()
+    }
+}
+
+/// Errors returned by [`send_and_confirm`].
+#[derive(Debug, thiserror::Error)]
+pub enum SendConfirmError {
+    #[error("failed to decode external message: {0}")]
+    InvalidMessage(#[source] anyhow::Error),
+    #[error("message is not an inbound external message")]
+    NotExternalIn,
+    #[error("message destination is not a standard address")]
+    UnsupportedDestination,
+    #[error("failed to send message: {0}")]
+    Send(#[source] BalancerError),
+    #[error("failed to read destination transactions: {0}")]
+    Watch(#[source] ContractError<BalancerError>),
+    #[error("message was not processed before valid_until {valid_until}")]
+    Expired {
+        message_hash: [u8; 32],
+        valid_until: u32,
+    },
+}
+
+/// Sends an inbound external message BoC and waits for the destination
+/// transaction that processed it.
+///
+/// Transactions are matched by the normalized hash of their inbound message.
+/// Watching stops with [`SendConfirmError::Expired`] once a later transaction
+/// of the destination is past `valid_until`, or when `expiry_grace` has run
+/// out after `valid_until`. Errors while polling are retried until then.
+pub async fn send_and_confirm(
+    balancer: &mut LiteBalancer,
+    message_boc: Vec<u8>,
+    options: &SendOptions,
+) -> Result<ConfirmedMessage, SendConfirmError> {
+    let message = deserialize_boc(&message_boc)
+        .and_then(|root| Message::from_cell(root).map_err(Into::into))
+        .map_err(SendConfirmError::InvalidMessage)?;
+    let message_hash = message
+        .normalized_hash()
+        .map_err(|error| SendConfirmError::InvalidMessage(error.into()))?
+        .ok_or(SendConfirmError::NotExternalIn)?;
+    let CommonMsgInfo::ExternalIn {
+        dest: MsgAddressInt::Std { address, .. },
+        ..
+    } = message.info
+    else {
+        return Err(SendConfirmError::UnsupportedDestination);
+    };
+
+    // Pin the starting point before sending so the transaction cannot land
+    // unseen between the send and the first poll.
+    let after = Contract::new(balancer, address.clone())
+        .last_transaction()
+        .await
+        .map_err(|error| SendConfirmError::Watch(ContractError::Provider(error)))?
+        .unwrap_or(TransactionCursor::START);
+
+    match options.fan_out {
+        Some(peers) => balancer.send_message_to_peers(message_boc, peers).await,
+        None => balancer.send_message(message_boc).await,
+    }
+    .map_err(SendConfirmError::Send)?;
+
+    let expired = SendConfirmError::Expired {
+        message_hash,
+        valid_until: options.valid_until,
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let remaining = Duration::from_secs(u64::from(options.valid_until).saturating_sub(now))
+        + options.expiry_grace;
+
+    let mut contract = Contract::new(balancer, address);
+    let transactions = contract.follow_transactions(Some(after), options.poll_interval);
+    futures::pin_mut!(transactions);
+    let watch = async {
+        while let Some(item) = transactions.next().await {
+            let transaction = match item {
+                Ok(transaction) => transaction,
+                Err(error) => {
+                    log::debug!("retrying transaction poll: {error}");
+                    continue;
+                }
+            };
+            if inbound_hash(&transaction) == Some(message_hash) {
+                return Some(transaction);
+            }
+            // Transactions are ordered by time, so the message can no longer
+            // be accepted once the account has moved past `valid_until`.
+            if transaction.transaction.now > options.valid_until {
+                return None;
+            }
+        }
+        None
+    };
+    match tokio::time::timeout(remaining, watch).await {
+        Ok(Some(transaction)) => Ok(ConfirmedMessage {
+            message_hash,
+            transaction,
+        }),
+        Ok(None) | Err(_) => Err(expired),
+    }
+}
+
+fn inbound_hash(transaction: &AccountTransaction) -> Option<[u8; 32]> {
+    let message = transaction.transaction.in_msg.as_ref()?;
+    message.normalized_hash().ok().flatten()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,223 @@
+use super::*;
+
+use num_bigint::BigUint;
+
+use crate::contracts::test_fixtures::{action_phase, block, compute_phase};
+use crate::liteclient::server::{LiteServerFixtures, LocalLiteServer};
+use crate::tlb::{
+    AccStatusChange, Account, AccountState, AccountStatus, AccountStorage, CurrencyCollection,
+    Either, Grams, HashUpdateAccount, MsgAddressExt, StorageExtraInfo, StorageInfo, StorageUsed,
+    TlbSerialize, TrStoragePhase, Transaction, TransactionDescr,
+};
+use crate::tvm::{Address, Builder, HashmapE, serialize_boc};
+
+const VALID_UNTIL: u32 = 4_000_000_000;
+
+fn destination() -> Address {
+    Address::new(0, [0x42; 32])
+}
+
+fn uninit_account(address: &Address) -> Account {
+    Account::Full {
+        addr: MsgAddressInt::std(address.clone()),
+        storage_stat: StorageInfo {
+            used: StorageUsed::new(BigUint::from(1u8), BigUint::from(8u8)),
+            last_paid: 1_700_000_000,
+            due_payment: None,
+            extra: StorageExtraInfo::None,
+        },
+        storage: AccountStorage {
+            last_trans_lt: 0,
+            balance: CurrencyCollection::grams(Grams::from(1_000_000_000)),
+            state: AccountState::Uninit,
+        },
+    }
+}
+
+/// External message to the destination with an inline body tagged `tag`.
+fn external_message(tag: u8) -> Message {
+    let mut body = Builder::new();
+    body.store_byte(tag).unwrap();
+    Message {
+        info: CommonMsgInfo::ExternalIn {
+            src: MsgAddressExt::None,
+            dest: MsgAddressInt::std(destination()),
+            import_fee: Grams::from(0),
+        },
+        init: None,
+        body: Either::Left(body.build().unwrap()),
+    }
+}
+
+fn transaction(
+    lt: u64,
+    now: u32,
+    prev: Option<&Transaction>,
+    in_msg: Option<Message>,
+    compute_ph: TrComputePhase,
+) -> Transaction {
+    Transaction {
+        account_addr: destination().hash_part,
+        lt,
+        prev_trans_hash: prev.map_or([0; 32], |prev| prev.to_cell().unwrap().hash()),
+        prev_trans_lt: prev.map_or(0, |prev| prev.lt),
+        now,
+        outmsg_cnt: 0,
+        orig_status: AccountStatus::Active,
+        end_status: AccountStatus::Active,
+        in_msg,
+        out_msgs: HashmapE::new(15),
+        total_fees: CurrencyCollection::grams(Grams::from(lt)),
+        state_update: HashUpdateAccount {
+            old_hash: [0xAA; 32],
+            new_hash: [0xBB; 32],
+        },
+        description: TransactionDescr::Ordinary {
+            credit_first: true,
+            storage_ph: Some(TrStoragePhase {
+                storage_fees_collected: Grams::from(1),
+                storage_fees_due: None,
+                status_change: AccStatusChange::Unchanged,
+            }),
+            credit_ph: None,
+            compute_ph,
+            action: Some(action_phase()),
+            aborted: false,
+            bounce: None,
+            destroyed: false,
+        },
+    }
+}
+
+async fn start() -> (LocalLiteServer, LiteBalancer) {
+    let fixtures = LiteServerFixtures::new(block(-1, 3))
+        .with_time(1_700_000_500)
+        .with_account(&destination(), &uninit_account(&destination()))
+        .unwrap();
+    let server = LocalLiteServer::start(fixtures).await.unwrap();
+    let mut balancer = LiteBalancer::new(
+        vec![server.connect().await.unwrap()],
+        Duration::from_secs(5),
+    );
+    balancer.start_up().await.unwrap();
+    (server, balancer)
+}
+
+/// Waits until the server accepted a message, then records `transactions`
+/// for the destination.
+async fn land_after_send(server: &LocalLiteServer, transactions: Vec<Transaction>) {
+    while server.sent_messages().is_empty() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    server.update(|fixtures| {
+        for (index, transaction) in transactions.iter().enumerate() {
+            fixtures
+                .insert_transaction(block(0, 10 + index as i32), transaction)
+                .unwrap();
+        }
+    });
+}
+
+#[tokio::test]
+async fn confirms_transaction_with_matching_normalized_inbound_hash() {
+    let (server, mut balancer) = start().await;
+    let message = external_message(1);
+    let boc = serialize_boc(&message.to_cell().unwrap(), true).unwrap();
+
+    // The block stores the message with a referenced body and a source the
+    // sender never set; only the normalized hash matches.
+    let landed = Message {
+        info: CommonMsgInfo::ExternalIn {
+            src: MsgAddressExt::Extern {
+                data: vec![0xA0],
+                bit_len: 3,
+            },
+            dest: MsgAddressInt::std(destination()),
+            import_fee: Grams::from(5),
+        },
+        init: None,
+        body: match &message.body {
+            Either::Left(body) | Either::Right(body) => Either::Right(body.clone()),
+        },
+    };
+    let other = transaction(
+        100,
+        1_700_000_600,
+        None,
+        Some(external_message(2)),
+        compute_phase(0),
+    );
+    let ours = transaction(
+        200,
+        1_700_000_601,
+        Some(&other),
+        Some(landed),
+        compute_phase(0),
+    );
+    let options = SendOptions::new(VALID_UNTIL)
+        .with_fan_out(1)
+        .with_poll_interval(Duration::from_millis(10));
+
+    let (confirmed, ()) = tokio::join!(
+        send_and_confirm(&mut balancer, boc.clone(), &options),
+        land_after_send(&server, vec![other, ours.clone()]),
+    );
+    let confirmed = confirmed.unwrap();
+
+    assert_eq!(server.sent_messages(), vec![boc]);
+    assert_eq!(
+        Some(confirmed.message_hash),
+        message.normalized_hash().unwrap()
+    );
+    assert_eq!(confirmed.transaction.transaction, ours);
+    assert_eq!(confirmed.exit_code(), Some(0));
+    assert!(confirmed.action_phase().is_some());
+    assert!(confirmed.success());
+}
+
+#[tokio::test]
+async fn stops_once_account_moves_past_valid_until() {
+    let (server, mut balancer) = start().await;
+    let boc = serialize_boc(&external_message(1).to_cell().unwrap(), true).unwrap();
+    let later = transaction(
+        100,
+        1_700_000_601,
+        None,
+        Some(external_message(2)),
+        compute_phase(33),
+    );
+    let options = SendOptions::new(1_700_000_600).with_poll_interval(Duration::from_millis(10));
+
+    let (result, ()) = tokio::join!(
+        send_and_confirm(&mut balancer, boc, &options),
+        land_after_send(&server, vec![later]),
+    );
+
+    assert!(matches!(
+        result,
+        Err(SendConfirmError::Expired {
+            valid_until: 1_700_000_600,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn rejects_messages_that_are_not_inbound_external() {
+    let (server, mut balancer) = start().await;
+    let message = Message {
+        info: CommonMsgInfo::ExternalOut {
+            src: MsgAddressInt::std(destination()),
+            dest: MsgAddressExt::None,
+            created_lt: 1,
+            created_at: 2,
+        },
+        ..external_message(1)
+    };
+    let boc = serialize_boc(&message.to_cell().unwrap(), true).unwrap();
+
+    let result = send_and_confirm(&mut balancer, boc, &SendOptions::new(VALID_UNTIL)).await;
+
+    assert!(matches!(result, Err(SendConfirmError::NotExternalIn)));
+    assert!(server.sent_messages().is_empty());
+}
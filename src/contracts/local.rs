@@ -0,0 +1,186 @@
+use async_trait::async_trait;
+
+use crate::liteclient::boc::{DecodedAccountState, SimpleAccount};
+use crate::tl::{
+    BlockIdExt,
+    common::{AccountId, Int256, ZeroStateIdExt},
+    response::{AccountState, MasterchainInfo, RunMethodResult, TransactionList},
+};
+use crate::tlb::StateInit;
+use crate::tvm::vm::{GetMethodRunner, SmartContractContext};
+use crate::tvm::{Address, Cell, TvmStack};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{ContractProvider, address_from_state_init};
+
+/// Errors returned by [`LocalContractProvider`].
+#[derive(Debug, thiserror::Error)]
+pub enum LocalProviderError {
+    #[error("account {0} is not registered in the local provider")]
+    UnknownAccount(String),
+    #[error("{0} is not available offline")]
+    Unsupported(&'static str),
+    #[error("offline TVM execution failed: {0}")]
+    Vm(#[source] anyhow::Error),
+}
+
+/// Offline [`ContractProvider`] that answers get-methods with the pure Rust
+/// TVM instead of a liteserver.
+///
+/// Accounts are registered with their code and data; get-method calls run
+/// locally and return the same `RunMethodResult` shape as `LiteClient`.
+/// External messages are recorded and never executed. Account state,
+/// transaction, and config queries are not available offline.
+#[derive(Debug, Clone)]
+pub struct LocalContractProvider {
+    block: BlockIdExt,
+    accounts: HashMap<(i8, [u8; 32]), GetMethodRunner>,
+    sent_messages: Vec<Vec<u8>>,
+}
+
+impl LocalContractProvider {
+    pub fn new() -> Self {
+        Self::with_block(BlockIdExt {
+            workchain: -1,
+            shard: i64::MIN,
+            seqno: 0,
+            root_hash: Int256([0; 32]),
+            file_hash: Int256([0; 32]),
+        })
+    }
+
+    /// Creates a provider that reports `block` as the latest masterchain block.
+    pub fn with_block(block: BlockIdExt) -> Self {
+        Self {
+            block,
+            accounts: HashMap::new(),
+            sent_messages: Vec::new(),
+        }
+    }
+
+    /// Registers `runner` for `address`; `MYADDR` returns `address`.
+    pub fn insert_account(
+        &mut self,
+        address: Address,
+        runner: GetMethodRunner,
+    ) -> Option<GetMethodRunner> {
+        let context = SmartContractContext {
+            address: address.clone(),
+            ..runner.context().clone()
+        };
+        self.accounts.insert(
+            (address.workchain, address.hash_part),
+            runner.with_context(context),
+        )
+    }
+
+    /// Registers an account from its `StateInit` and returns the derived
+    /// address.
+    pub fn insert_state_init(
+        &mut self,
+        workchain: i8,
+        state_init: &StateInit,
+    ) -> anyhow::Result<Address> {
+        let address = address_from_state_init(workchain, state_init)?;
+        let runner = GetMethodRunner::from_state_init(state_init)?;
+        self.insert_account(address.clone(), runner);
+        Ok(address)
+    }
+
+    pub fn account(&self, address: &Address) -> Option<&GetMethodRunner> {
+        self.accounts.get(&(address.workchain, address.hash_part))
+    }
+
+    /// External message BoCs passed to `send_external_message_boc`.
+    pub fn sent_messages(&self) -> &[Vec<u8>] {
+        &self.sent_messages
+    }
+}
+
+impl Default for LocalContractProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ContractProvider for LocalContractProvider {
+    type Error = LocalProviderError;
+
+    async fn get_masterchain_info(&mut self) -> Result<MasterchainInfo, Self::Error> {
+        Ok(MasterchainInfo {
+            last: self.block.clone(),
+            state_root_hash: Int256([0; 32]),
+            init: ZeroStateIdExt {
+                workchain: -1,
+                root_hash: Int256([0; 32]),
+                file_hash: Int256([0; 32]),
+            },
+        })
+    }
+
+    async fn get_account_state(
+        &mut self,
+        _block: BlockIdExt,
+        _account: AccountId,
+    ) -> Result<AccountState, Self::Error> {
+        Err(LocalProviderError::Unsupported("raw account state"))
+    }
+
+    async fn get_account_state_typed(
+        &mut self,
+        _block: BlockIdExt,
+        _account: Address,
+    ) -> Result<DecodedAccountState, Self::Error> {
+        Err(LocalProviderError::Unsupported("decoded account state"))
+    }
+
+    async fn get_account_state_simple(
+        &mut self,
+        _block: BlockIdExt,
+        _account: Address,
+    ) -> Result<SimpleAccount, Self::Error> {
+        Err(LocalProviderError::Unsupported("simple account state"))
+    }
+
+    async fn run_get_method(
+        &mut self,
+        _mode: u32,
+        block: BlockIdExt,
+        account: Address,
+        method_id: u64,
+        stack: TvmStack,
+    ) -> Result<RunMethodResult, Self::Error> {
+        let runner = self
+            .account(&account)
+            .ok_or_else(|| LocalProviderError::UnknownAccount(account.to_raw()))?;
+        runner
+            .run(method_id, stack)
+            .and_then(|result| result.into_run_method_result(block))
+            .map_err(LocalProviderError::Vm)
+    }
+
+    async fn send_external_message_boc(&mut self, body: Vec<u8>) -> Result<u32, Self::Error> {
+        self.sent_messages.push(body);
+        Ok(1)
+    }
+
+    async fn get_transactions(
+        &mut self,
+        _count: u32,
+        _account: AccountId,
+        _lt: u64,
+        _hash: Int256,
+    ) -> Result<TransactionList, Self::Error> {
+        Err(LocalProviderError::Unsupported("transaction history"))
+    }
+
+    async fn get_config_param(
+        &mut self,
+        _block: BlockIdExt,
+        _id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error> {
+        Err(LocalProviderError::Unsupported("config params"))
+    }
+}
@@ -5,7 +5,7 @@ use crate::abi::{
     encode_get_method_inputs, encode_message_body,
 };
 use crate::liteclient::boc::{DecodedAccountState, SimpleAccount};
-use crate::liteclient::{balancer::LiteBalancer, client::LiteClient};
+use crate::liteclient::{balancer::LiteBalancer, client::LiteClient, proof::Verified};
 use crate::tl::{
     BlockIdExt,
     common::{AccountId, Int256},
@@ -121,6 +121,14 @@ pub trait ContractProvider: Send {
         lt: u64,
         hash: Int256,
     ) -> Result<TransactionList, Self::Error>;
+
+    /// Fetches raw config parameter `id` at `block`, or `None` when the
+    /// parameter is absent.
+    async fn get_config_param(
+        &mut self,
+        block: BlockIdExt,
+        id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error>;
 }
 
 #[async_trait]
@@ -144,7 +152,9 @@ impl ContractProvider for LiteClient {
         block: BlockIdExt,
         account: Address,
     ) -> Result<DecodedAccountState, Self::Error> {
-        LiteClient::get_account_state_typed(self, account, Some(block)).await
+        LiteClient::get_account_state_typed(self, account, Some(block))
+            .await
+            .map(Verified::into_inner)
     }
 
     async fn get_account_state_simple(
@@ -183,6 +193,14 @@ impl ContractProvider for LiteClient {
     ) -> Result<TransactionList, Self::Error> {
         LiteClient::get_transactions(self, count, account, lt, hash).await
     }
+
+    async fn get_config_param(
+        &mut self,
+        block: BlockIdExt,
+        id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error> {
+        LiteClient::get_config_param(self, block, id).await
+    }
 }
 
 #[async_trait]
@@ -206,7 +224,9 @@ impl ContractProvider for LiteBalancer {
         block: BlockIdExt,
         account: Address,
     ) -> Result<DecodedAccountState, Self::Error> {
-        LiteBalancer::get_account_state_typed(self, account, Some(block)).await
+        LiteBalancer::get_account_state_typed(self, account, Some(block))
+            .await
+            .map(Verified::into_inner)
     }
 
     async fn get_account_state_simple(
@@ -245,6 +265,14 @@ impl ContractProvider for LiteBalancer {
     ) -> Result<TransactionList, Self::Error> {
         LiteBalancer::get_transactions(self, count, account, lt, hash).await
     }
+
+    async fn get_config_param(
+        &mut self,
+        block: BlockIdExt,
+        id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error> {
+        LiteBalancer::get_config_param(self, block, id).await
+    }
 }
 
 /// A smart contract bound to an address and a LiteAPI provider.
@@ -262,6 +290,11 @@ impl<'a, P: ContractProvider + ?Sized> Contract<'a, P> {
         &self.address
     }
 
+    /// Reborrows the provider for a contract at another address.
+    pub fn at(&mut self, address: Address) -> Contract<'_, P> {
+        Contract::new(&mut *self.provider, address)
+    }
+
     pub async fn get_state(&mut self, block: BlockIdExt) -> Result<AccountState, P::Error> {
         self.provider
             .get_account_state(block, self.address.to_account_id())
@@ -0,0 +1,57 @@
+//! Block ids and transaction phases shared by the contract and LiteServer
+//! tests.
+
+use num_bigint::BigUint;
+
+use crate::tl::common::{BlockIdExt, Int256};
+use crate::tlb::{AccStatusChange, Grams, StorageUsed, TrActionPhase, TrComputePhase};
+
+/// Block id whose hashes are derived from `seqno`.
+pub(crate) fn block(workchain: i32, seqno: i32) -> BlockIdExt {
+    BlockIdExt {
+        workchain,
+        shard: i64::MIN,
+        seqno,
+        root_hash: Int256([seqno as u8; 32]),
+        file_hash: Int256([0xF0 | seqno as u8; 32]),
+    }
+}
+
+/// VM compute phase that succeeded when `exit_code` is `0`.
+pub(crate) fn compute_phase(exit_code: i32) -> TrComputePhase {
+    TrComputePhase::Vm {
+        success: exit_code == 0,
+        msg_state_used: false,
+        account_activated: false,
+        gas_fees: Grams::from(10),
+        gas_used: BigUint::from(10u8),
+        gas_limit: BigUint::from(100u8),
+        gas_credit: None,
+        mode: 0,
+        exit_code,
+        exit_arg: None,
+        vm_steps: 5,
+        vm_init_state_hash: [0; 32],
+        vm_final_state_hash: [0; 32],
+    }
+}
+
+/// Successful action phase that sent one message.
+pub(crate) fn action_phase() -> TrActionPhase {
+    TrActionPhase {
+        success: true,
+        valid: true,
+        no_funds: false,
+        status_change: AccStatusChange::Unchanged,
+        total_fwd_fees: None,
+        total_action_fees: None,
+        result_code: 0,
+        result_arg: None,
+        tot_actions: 1,
+        spec_actions: 0,
+        skipped_actions: 0,
+        msgs_created: 1,
+        action_list_hash: [0; 32],
+        tot_msg_size: StorageUsed::new(BigUint::from(0u8), BigUint::from(0u8)),
+    }
+}
@@ -16,7 +16,7 @@ mod tests {
         AccountStorage, CurrencyCollection, Grams, MsgAddressInt, StateInit, StorageExtraInfo,
         StorageInfo, StorageUsed, TlbSerialize,
     };
-    use crate::tvm::{Address, Builder, TvmStack, TvmStackEntry};
+    use crate::tvm::{Address, Builder, Cell, TvmStack, TvmStackEntry};
     use async_trait::async_trait;
     use num_bigint::{BigInt, BigUint};
     use std::borrow::Cow;
@@ -130,6 +130,14 @@ mod tests {
                 transactions: Vec::new(),
             })
         }
+
+        async fn get_config_param(
+            &mut self,
+            _block: BlockIdExt,
+            _id: u32,
+        ) -> Result<Option<Arc<Cell>>, Self::Error> {
+            Ok(None)
+        }
     }
 
     fn block(seqno: i32) -> BlockIdExt {
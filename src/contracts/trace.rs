@@ -0,0 +1,343 @@
+//! Message traces: the tree of transactions caused by one transaction.
+//!
+//! [`TraceBuilder`] follows every internal outbound message of a transaction
+//! to the transaction of the destination account whose inbound message has
+//! the same hash, and repeats that for the transactions it finds. Accounts in
+//! any workchain or shard are looked up through the same provider.
+
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::anyhow;
+
+use crate::tlb::{
+    CommonMsgInfo, CurrencyCollection, Message, MsgAddressInt, TrBouncePhase, TransactionDescr,
+};
+use crate::tvm::Address;
+
+use super::{AccountTransaction, Contract, ContractError, ContractProvider, TransactionCursor};
+
+/// Hops followed from the root transaction by default.
+const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Transactions requested per `getTransactions` page while searching for the
+/// consumer of a message.
+const SEARCH_PAGE: u32 = 16;
+
+/// Transactions of one account read by default while searching for
+/// consumers.
+const DEFAULT_MAX_TRANSACTIONS: usize = 1024;
+
+/// One transaction of a trace and the messages it sent.
+#[derive(Debug, Clone)]
+pub struct TraceNode {
+    pub transaction: AccountTransaction,
+    /// Outbound messages in `out_msgs` order.
+    pub out_msgs: Vec<TraceMessage>,
+}
+
+/// An outbound message of a traced transaction.
+#[derive(Debug, Clone)]
+pub struct TraceMessage {
+    /// Representation hash of the message cell as stored in `out_msgs`.
+    pub hash: [u8; 32],
+    pub message: Message,
+    /// Transaction that processed the message. `None` for external outbound
+    /// messages, messages not processed when the destination was first
+    /// queried, consumers past the transaction budget, and hops past the
+    /// depth limit.
+    pub consumer: Option<TraceNode>,
+}
+
+impl TraceNode {
+    /// Address of the account that ran the transaction.
+    pub fn address(&self) -> Address {
+        Address::new(
+            self.transaction.block.workchain as i8,
+            self.transaction.transaction.account_addr,
+        )
+    }
+
+    pub fn description(&self) -> &TransactionDescr {
+        &self.transaction.transaction.description
+    }
+
+    /// VM exit code, or `None` when the compute phase was skipped or absent.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.description().compute_phase()?.exit_code()
+    }
+
+    /// Action phase result code, when an action phase ran.
+    pub fn action_result_code(&self) -> Option<i32> {
+        Some(self.description().action_phase()?.result_code)
+    }
+
+    pub fn aborted(&self) -> bool {
+        self.description().aborted()
+    }
+
+    /// Whether the inbound message is a bounce of an earlier message.
+    pub fn bounced(&self) -> bool {
+        matches!(
+            self.transaction.transaction.in_msg,
+            Some(Message {
+                info: CommonMsgInfo::Internal { bounced: true, .. },
+                ..
+            })
+        )
+    }
+
+    /// Bounce phase of an ordinary transaction that tried to return its
+    /// inbound message.
+    pub fn bounce_phase(&self) -> Option<&TrBouncePhase> {
+        match self.description() {
+            TransactionDescr::Ordinary { bounce, .. } => bounce.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Value carried by the inbound internal message.
+    pub fn value_in(&self) -> Option<&CurrencyCollection> {
+        match &self.transaction.transaction.in_msg.as_ref()?.info {
+            CommonMsgInfo::Internal { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Fees charged by the transaction.
+    pub fn total_fees(&self) -> &CurrencyCollection {
+        &self.transaction.transaction.total_fees
+    }
+
+    /// This node and every node below it, depth first.
+    pub fn nodes(&self) -> Vec<&TraceNode> {
+        let mut nodes = Vec::new();
+        let mut pending = vec![self];
+        while let Some(node) = pending.pop() {
+            nodes.push(node);
+            pending.extend(
+                node.out_msgs
+                    .iter()
+                    .rev()
+                    .filter_map(|message| message.consumer.as_ref()),
+            );
+        }
+        nodes
+    }
+}
+
+impl TraceMessage {
+    /// Destination of an internal message with a standard address.
+    pub fn destination(&self) -> Option<&Address> {
+        match &self.message.info {
+            CommonMsgInfo::Internal {
+                dest: MsgAddressInt::Std { address, .. },
+                ..
+            } => Some(address),
+            _ => None,
+        }
+    }
+
+    /// Value attached to an internal message.
+    pub fn value(&self) -> Option<&CurrencyCollection> {
+        match &self.message.info {
+            CommonMsgInfo::Internal { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn created_lt(&self) -> Option<u64> {
+        match &self.message.info {
+            CommonMsgInfo::Internal { created_lt, .. }
+            | CommonMsgInfo::ExternalOut { created_lt, .. } => Some(*created_lt),
+            CommonMsgInfo::ExternalIn { .. } => None,
+        }
+    }
+}
+
+/// Builds [`TraceNode`] trees over a [`ContractProvider`].
+///
+/// The builder remembers the transactions it has read for every account, so
+/// traces that pass through the same account many times, and several traces
+/// built with one builder, fetch each page once. Account histories are read
+/// from the latest transaction at the first lookup, or from a cursor given
+/// with [`TraceBuilder::with_account_cursor`]; messages processed later are
+/// reported as not processed until a new builder is used.
+pub struct TraceBuilder<'a, P: ContractProvider + ?Sized> {
+    provider: &'a mut P,
+    max_depth: usize,
+    max_transactions: usize,
+    histories: HashMap<Address, AccountHistory>,
+}
+
+/// Transactions of one account read so far, newest first.
+#[derive(Default)]
+struct AccountHistory {
+    started: bool,
+    transactions: Vec<AccountTransaction>,
+    older: Option<TransactionCursor>,
+}
+
+impl<'a, P: ContractProvider + ?Sized> TraceBuilder<'a, P> {
+    pub fn new(provider: &'a mut P) -> Self {
+        Self {
+            provider,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_transactions: DEFAULT_MAX_TRANSACTIONS,
+            histories: HashMap::new(),
+        }
+    }
+
+    /// Stops following messages `max_depth` hops below the root.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Reads at most `max_transactions` transactions of each account while
+    /// looking for consumers. Messages consumed further back are reported as
+    /// not processed.
+    pub fn with_max_transactions(mut self, max_transactions: usize) -> Self {
+        self.max_transactions = max_transactions;
+        self
+    }
+
+    /// Searches the history of `address` back from `cursor` instead of from
+    /// its latest transaction.
+    pub fn with_account_cursor(mut self, address: Address, cursor: TransactionCursor) -> Self {
+        self.histories.insert(
+            address,
+            AccountHistory {
+                started: true,
+                transactions: Vec::new(),
+                older: Some(cursor),
+            },
+        );
+        self
+    }
+
+    /// Fetches the transaction at `cursor` of `address` and traces it.
+    pub async fn build_from(
+        &mut self,
+        address: &Address,
+        cursor: TransactionCursor,
+    ) -> Result<TraceNode, ContractError<P::Error>> {
+        let root = Contract::new(&mut *self.provider, address.clone())
+            .get_transactions_decoded(1, cursor)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ContractError::decode(anyhow!("transaction {} was not returned", cursor.lt))
+            })?;
+        self.build(root).await
+    }
+
+    /// Traces the messages sent by `root`.
+    pub async fn build(
+        &mut self,
+        root: AccountTransaction,
+    ) -> Result<TraceNode, ContractError<P::Error>> {
+        // Nodes are collected breadth first into a flat list and linked into a
+        // tree at the end, which keeps the async part free of recursion.
+        let mut nodes = vec![(root, Vec::new())];
+        let mut pending = VecDeque::from([(0, 0)]);
+        while let Some((index, depth)) = pending.pop_front() {
+            let transaction = &nodes[index].0;
+            let out_msgs: Vec<([u8; 32], Message)> = transaction
+                .out_msg_hashes
+                .iter()
+                .copied()
+                .zip(
+                    transaction
+                        .transaction
+                        .out_msgs
+                        .iter()
+                        .map(|(_, message)| message.clone()),
+                )
+                .collect();
+            for (hash, message) in out_msgs {
+                let mut consumer = None;
+                if depth < self.max_depth
+                    && let CommonMsgInfo::Internal {
+                        dest: MsgAddressInt::Std { address, .. },
+                        created_lt,
+                        ..
+                    } = &message.info
+                    && let Some(transaction) =
+                        self.find_consumer(address, hash, *created_lt).await?
+                {
+                    consumer = Some(nodes.len());
+                    pending.push_back((nodes.len(), depth + 1));
+                    nodes.push((transaction, Vec::new()));
+                }
+                nodes[index].1.push((hash, message, consumer));
+            }
+        }
+
+        let mut nodes: Vec<_> = nodes.into_iter().map(Some).collect();
+        Ok(link(&mut nodes, 0))
+    }
+
+    /// Transaction of `address` whose inbound message hash is `hash`.
+    async fn find_consumer(
+        &mut self,
+        address: &Address,
+        hash: [u8; 32],
+        created_lt: u64,
+    ) -> Result<Option<AccountTransaction>, ContractError<P::Error>> {
+        let history = self.histories.entry(address.clone()).or_default();
+        let mut contract = Contract::new(&mut *self.provider, address.clone());
+        if !history.started {
+            history.older = contract
+                .last_transaction()
+                .await
+                .map_err(ContractError::Provider)?;
+            history.started = true;
+        }
+
+        let mut scanned = 0;
+        loop {
+            for transaction in &history.transactions[scanned..] {
+                // The consumer runs after the message was created.
+                if transaction.transaction.lt <= created_lt {
+                    return Ok(None);
+                }
+                if transaction.in_msg_hash == Some(hash) {
+                    return Ok(Some(transaction.clone()));
+                }
+            }
+            scanned = history.transactions.len();
+            let Some(cursor) = history.older else {
+                return Ok(None);
+            };
+            if scanned >= self.max_transactions {
+                return Ok(None);
+            }
+            let count = SEARCH_PAGE.min((self.max_transactions - scanned) as u32);
+            let page = contract.get_transactions_decoded(count, cursor).await?;
+            history.older = page.last().and_then(AccountTransaction::prev_cursor);
+            history.transactions.extend(page);
+        }
+    }
+}
+
+type PendingNode = (AccountTransaction, Vec<([u8; 32], Message, Option<usize>)>);
+
+fn link(nodes: &mut [Option<PendingNode>], index: usize) -> TraceNode {
+    let (transaction, out_msgs) = nodes[index].take().expect("trace node linked once");
+    let out_msgs = out_msgs
+        .into_iter()
+        .map(|(hash, message, consumer)| TraceMessage {
+            hash,
+            message,
+            consumer: consumer.map(|consumer| link(nodes, consumer)),
+        })
+        .collect();
+    TraceNode {
+        transaction,
+        out_msgs,
+    }
+}
+
+#[cfg(test)]
+mod tests;
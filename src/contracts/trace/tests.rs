@@ -0,0 +1,517 @@
+use super::*;
+
+use async_trait::async_trait;
+use num_bigint::BigUint;
+use std::sync::Arc;
+
+use crate::contracts::test_fixtures::{action_phase, block, compute_phase};
+use crate::liteclient::boc::{DecodedAccountState, SimpleAccount, SimpleAccountState};
+use crate::tl::response::{AccountState, MasterchainInfo, RunMethodResult, TransactionList};
+use crate::tl::{AccountId, BlockIdExt, Int256};
+use crate::tlb::{
+    AccStatusChange, AccountStatus, Either, Grams, HashUpdateAccount, MsgAddressExt, StorageUsed,
+    TlbDeserialize, TlbSerialize, TrStoragePhase, Transaction,
+};
+use crate::tvm::{BitKey, Builder, Cell, HashmapE, Slice, TvmStack, serialize_boc_roots};
+
+const OWNER: [u8; 32] = [0x01; 32];
+const SENDER_WALLET: [u8; 32] = [0x02; 32];
+const RECEIVER_WALLET: [u8; 32] = [0x03; 32];
+const RECEIVER: [u8; 32] = [0x04; 32];
+const DEX: [u8; 32] = [0x05; 32];
+const IDLE: [u8; 32] = [0x06; 32];
+
+#[derive(Debug, thiserror::Error)]
+#[error("ledger provider error: {0}")]
+struct LedgerError(&'static str);
+
+/// Provider serving the transaction chains of several basechain accounts.
+#[derive(Default)]
+struct Ledger {
+    /// Transactions of every account, oldest first.
+    accounts: HashMap<[u8; 32], Vec<Transaction>>,
+    /// Cells served instead of the serialized transaction, by logical time.
+    cells: HashMap<u64, Arc<Cell>>,
+    transaction_calls: usize,
+}
+
+impl Ledger {
+    fn cell(&self, transaction: &Transaction) -> Arc<Cell> {
+        self.cells
+            .get(&transaction.lt)
+            .cloned()
+            .unwrap_or_else(|| transaction.to_cell().unwrap())
+    }
+
+    fn push(
+        &mut self,
+        account: [u8; 32],
+        lt: u64,
+        in_msg: Option<Message>,
+        out_msgs: Vec<Message>,
+        exit_code: i32,
+        bounce: Option<TrBouncePhase>,
+    ) -> Transaction {
+        let (prev_trans_lt, prev_trans_hash) = self
+            .accounts
+            .get(&account)
+            .and_then(|chain| chain.last())
+            .map(|prev| (prev.lt, self.cell(prev).hash()))
+            .unwrap_or_default();
+        let mut out = HashmapE::new(15);
+        for (index, message) in out_msgs.iter().enumerate() {
+            out.insert_bit_key(BitKey::from_u64(index as u64, 15).unwrap(), message.clone())
+                .unwrap();
+        }
+        let transaction = Transaction {
+            account_addr: account,
+            lt,
+            prev_trans_hash,
+            prev_trans_lt,
+            now: 1_700_000_000 + lt as u32,
+            outmsg_cnt: out_msgs.len() as u16,
+            orig_status: AccountStatus::Active,
+            end_status: AccountStatus::Active,
+            in_msg,
+            out_msgs: out,
+            total_fees: CurrencyCollection::grams(Grams::from(lt)),
+            state_update: HashUpdateAccount {
+                old_hash: [0xAA; 32],
+                new_hash: [0xBB; 32],
+            },
+            description: TransactionDescr::Ordinary {
+                credit_first: false,
+                storage_ph: Some(TrStoragePhase {
+                    storage_fees_collected: Grams::from(1),
+                    storage_fees_due: None,
+                    status_change: AccStatusChange::Unchanged,
+                }),
+                credit_ph: None,
+                compute_ph: compute_phase(exit_code),
+                action: (exit_code == 0).then(action_phase),
+                aborted: exit_code != 0,
+                bounce,
+                destroyed: false,
+            },
+        };
+        self.accounts
+            .entry(account)
+            .or_default()
+            .push(transaction.clone());
+        transaction
+    }
+
+    fn root(&self, account: [u8; 32], lt: u64) -> AccountTransaction {
+        let transaction = self.accounts[&account]
+            .iter()
+            .find(|transaction| transaction.lt == lt)
+            .unwrap();
+        AccountTransaction::from_cell(block(0, 7), self.cell(transaction)).unwrap()
+    }
+}
+
+#[async_trait]
+impl ContractProvider for Ledger {
+    type Error = LedgerError;
+
+    async fn get_masterchain_info(&mut self) -> Result<MasterchainInfo, Self::Error> {
+        Ok(MasterchainInfo {
+            last: block(0, 7),
+            state_root_hash: Int256([1; 32]),
+            init: crate::tl::common::ZeroStateIdExt {
+                workchain: -1,
+                root_hash: Int256([2; 32]),
+                file_hash: Int256([3; 32]),
+            },
+        })
+    }
+
+    async fn get_account_state(
+        &mut self,
+        _block: BlockIdExt,
+        _account: AccountId,
+    ) -> Result<AccountState, Self::Error> {
+        Err(LedgerError("raw account state"))
+    }
+
+    async fn get_account_state_typed(
+        &mut self,
+        _block: BlockIdExt,
+        _account: Address,
+    ) -> Result<DecodedAccountState, Self::Error> {
+        Err(LedgerError("typed account state"))
+    }
+
+    async fn get_account_state_simple(
+        &mut self,
+        block: BlockIdExt,
+        account: Address,
+    ) -> Result<SimpleAccount, Self::Error> {
+        let last = self
+            .accounts
+            .get(&account.hash_part)
+            .and_then(|chain| chain.last());
+        Ok(SimpleAccount {
+            block_id: block.clone(),
+            shard_block_id: block,
+            last_transaction_lt: Some(last.map_or(0, |last| last.lt)),
+            last_transaction_hash: Some(last.map_or([0; 32], |last| self.cell(last).hash())),
+            state: SimpleAccountState::Active,
+            account: None,
+        })
+    }
+
+    async fn run_get_method(
+        &mut self,
+        _mode: u32,
+        _block: BlockIdExt,
+        _account: Address,
+        _method_id: u64,
+        _stack: TvmStack,
+    ) -> Result<RunMethodResult, Self::Error> {
+        Err(LedgerError("get-method"))
+    }
+
+    async fn send_external_message_boc(&mut self, _body: Vec<u8>) -> Result<u32, Self::Error> {
+        Err(LedgerError("send message"))
+    }
+
+    async fn get_transactions(
+        &mut self,
+        count: u32,
+        account: AccountId,
+        lt: u64,
+        _hash: Int256,
+    ) -> Result<TransactionList, Self::Error> {
+        self.transaction_calls += 1;
+        let chain = self
+            .accounts
+            .get(&account.id.0)
+            .ok_or(LedgerError("unknown account"))?;
+        let first = chain
+            .iter()
+            .position(|transaction| transaction.lt == lt)
+            .ok_or(LedgerError("unknown transaction"))?;
+        let start = (first + 1).saturating_sub(count as usize);
+        let roots: Vec<Arc<Cell>> = chain[start..=first]
+            .iter()
+            .rev()
+            .map(|transaction| self.cell(transaction))
+            .collect();
+        Ok(TransactionList {
+            ids: roots.iter().map(|_| block(0, 7)).collect(),
+            transactions: serialize_boc_roots(&roots, true).unwrap(),
+        })
+    }
+
+    async fn get_config_param(
+        &mut self,
+        _block: BlockIdExt,
+        _id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error> {
+        Ok(None)
+    }
+}
+
+fn body(tag: u8) -> Arc<Cell> {
+    let mut builder = Builder::new();
+    builder.store_byte(tag).unwrap();
+    builder.build().unwrap()
+}
+
+fn internal(src: [u8; 32], dest: [u8; 32], value: u64, created_lt: u64, bounced: bool) -> Message {
+    Message {
+        info: CommonMsgInfo::Internal {
+            ihr_disabled: true,
+            bounce: !bounced,
+            bounced,
+            src: MsgAddressInt::std(Address::new(0, src)),
+            dest: MsgAddressInt::std(Address::new(0, dest)),
+            value: CurrencyCollection::grams(Grams::from(value)),
+            extra_flags: BigUint::from(0u8),
+            fwd_fee: Grams::from(1),
+            created_lt,
+            created_at: 1_700_000_000,
+        },
+        init: None,
+        body: Either::Right(body(created_lt as u8)),
+    }
+}
+
+/// `message` with its extra currency dictionary root relabeled from the
+/// shortest `hml_same` label to `hml_long`: a valid encoding that
+/// re-serializing the decoded message does not reproduce.
+fn relabeled(message: &Message) -> Arc<Cell> {
+    const KEY_BITS: usize = 32;
+    const LEN_BITS: usize = 6;
+    let cell = message.to_cell().unwrap();
+    let root = cell.reference(0).unwrap();
+    let mut slice = Slice::new(root.clone());
+    slice.skip_bits(3 + LEN_BITS).unwrap();
+    let mut builder = Builder::new();
+    builder.store_bit(true).unwrap().store_bit(false).unwrap();
+    builder
+        .store_uint_custom(KEY_BITS as u8, LEN_BITS)
+        .unwrap()
+        .store_bits(&[0; KEY_BITS / 8], KEY_BITS)
+        .unwrap();
+    builder.store_slice(&slice).unwrap();
+    replace_ref(&cell, 0, builder.build().unwrap())
+}
+
+/// Copy of `cell` with reference `index` replaced by `replacement`.
+fn replace_ref(cell: &Arc<Cell>, index: usize, replacement: Arc<Cell>) -> Arc<Cell> {
+    let mut copy = Cell::with_data(cell.data().to_vec(), cell.bit_len()).unwrap();
+    for (position, child) in cell.references().iter().enumerate() {
+        let child = if position == index {
+            replacement.clone()
+        } else {
+            child.clone()
+        };
+        copy.add_reference(child).unwrap();
+    }
+    Arc::new(copy)
+}
+
+fn external_in(dest: [u8; 32]) -> Message {
+    Message {
+        info: CommonMsgInfo::ExternalIn {
+            src: MsgAddressExt::None,
+            dest: MsgAddressInt::std(Address::new(0, dest)),
+            import_fee: Grams::from(0),
+        },
+        init: None,
+        body: Either::Right(body(0)),
+    }
+}
+
+/// A jetton transfer: owner → sender wallet → receiver wallet, which notifies
+/// the receiver and returns excess to the owner. The receiver forwards to a
+/// DEX that fails and bounces, and the receiver wallet also pings an account
+/// that never processed the message.
+fn jetton_transfer() -> Ledger {
+    let mut ledger = Ledger::default();
+    ledger.push(SENDER_WALLET, 90, None, Vec::new(), 0, None);
+
+    let transfer = internal(OWNER, SENDER_WALLET, 100, 101, false);
+    ledger.push(
+        OWNER,
+        100,
+        Some(external_in(OWNER)),
+        vec![transfer.clone()],
+        0,
+        None,
+    );
+
+    let internal_transfer = internal(SENDER_WALLET, RECEIVER_WALLET, 90, 201, false);
+    ledger.push(
+        SENDER_WALLET,
+        200,
+        Some(transfer),
+        vec![internal_transfer.clone()],
+        0,
+        None,
+    );
+    // Later unrelated activity pushes the consumer beyond one page.
+    for lt in 210..230 {
+        ledger.push(SENDER_WALLET, lt, None, Vec::new(), 0, None);
+    }
+
+    let notification = internal(RECEIVER_WALLET, RECEIVER, 1, 301, false);
+    let excess = internal(RECEIVER_WALLET, OWNER, 80, 302, false);
+    let external_out = Message {
+        info: CommonMsgInfo::ExternalOut {
+            src: MsgAddressInt::std(Address::new(0, RECEIVER_WALLET)),
+            dest: MsgAddressExt::None,
+            created_lt: 303,
+            created_at: 1_700_000_000,
+        },
+        init: None,
+        body: Either::Right(body(3)),
+    };
+    let ping = internal(RECEIVER_WALLET, IDLE, 1, 304, false);
+    ledger.push(
+        RECEIVER_WALLET,
+        300,
+        Some(internal_transfer),
+        vec![notification.clone(), excess.clone(), external_out, ping],
+        0,
+        None,
+    );
+
+    let swap = internal(RECEIVER, DEX, 1, 401, false);
+    ledger.push(
+        RECEIVER,
+        400,
+        Some(notification),
+        vec![swap.clone()],
+        0,
+        None,
+    );
+    ledger.push(OWNER, 500, Some(excess), Vec::new(), 0, None);
+
+    let bounce = internal(DEX, RECEIVER, 1, 701, true);
+    ledger.push(
+        DEX,
+        700,
+        Some(swap),
+        vec![bounce.clone()],
+        33,
+        Some(TrBouncePhase::Ok {
+            msg_size: StorageUsed::new(BigUint::from(0u8), BigUint::from(0u8)),
+            msg_fees: Grams::from(1),
+            fwd_fees: Grams::from(1),
+        }),
+    );
+    ledger.push(RECEIVER, 800, Some(bounce), Vec::new(), 0, None);
+    ledger
+}
+
+fn lts(node: &TraceNode) -> Vec<u64> {
+    node.nodes()
+        .iter()
+        .map(|node| node.transaction.transaction.lt)
+        .collect()
+}
+
+#[tokio::test]
+async fn trace_follows_messages_across_accounts() {
+    let mut ledger = jetton_transfer();
+    let root = ledger.root(OWNER, 100);
+
+    let trace = TraceBuilder::new(&mut ledger).build(root).await.unwrap();
+
+    assert_eq!(lts(&trace), vec![100, 200, 300, 400, 700, 800, 500]);
+    assert_eq!(trace.address(), Address::new(0, OWNER));
+
+    let sender_wallet = trace.out_msgs[0].consumer.as_ref().unwrap();
+    assert_eq!(
+        sender_wallet.value_in(),
+        Some(&CurrencyCollection::grams(Grams::from(100)))
+    );
+    assert_eq!(sender_wallet.exit_code(), Some(0));
+    assert_eq!(sender_wallet.action_result_code(), Some(0));
+
+    let receiver_wallet = sender_wallet.out_msgs[0].consumer.as_ref().unwrap();
+    let [notification, excess, external_out, ping] = &receiver_wallet.out_msgs[..] else {
+        panic!("expected four outbound messages");
+    };
+    assert_eq!(
+        excess.value(),
+        Some(&CurrencyCollection::grams(Grams::from(80)))
+    );
+    assert_eq!(excess.destination(), Some(&Address::new(0, OWNER)));
+    assert_eq!(
+        excess.consumer.as_ref().unwrap().transaction.transaction.lt,
+        500
+    );
+    assert_eq!(external_out.created_lt(), Some(303));
+    assert!(external_out.consumer.is_none());
+    assert!(ping.consumer.is_none());
+
+    let dex = notification.consumer.as_ref().unwrap().out_msgs[0]
+        .consumer
+        .as_ref()
+        .unwrap();
+    assert_eq!(dex.exit_code(), Some(33));
+    assert!(dex.aborted());
+    assert!(matches!(dex.bounce_phase(), Some(TrBouncePhase::Ok { .. })));
+    let bounced = dex.out_msgs[0].consumer.as_ref().unwrap();
+    assert!(bounced.bounced());
+    assert!(!dex.bounced());
+}
+
+#[tokio::test]
+async fn trace_respects_depth_limit_and_reuses_account_pages() {
+    let mut ledger = jetton_transfer();
+    let root = ledger.root(OWNER, 100);
+    let cursor = root.cursor();
+
+    let mut builder = TraceBuilder::new(&mut ledger).with_max_depth(1);
+    let shallow = builder.build(root).await.unwrap();
+    assert_eq!(lts(&shallow), vec![100, 200]);
+    assert!(
+        shallow.out_msgs[0].consumer.as_ref().unwrap().out_msgs[0]
+            .consumer
+            .is_none()
+    );
+
+    let again = builder
+        .build_from(&Address::new(0, OWNER), cursor)
+        .await
+        .unwrap();
+    assert_eq!(lts(&again), vec![100, 200]);
+    drop(builder);
+    // One lookup of the root plus two pages of the sender wallet history.
+    assert_eq!(ledger.transaction_calls, 3);
+}
+
+#[tokio::test]
+async fn trace_reports_consumers_past_the_budget_as_not_found() {
+    let mut ledger = jetton_transfer();
+    let root = ledger.root(OWNER, 100);
+    let trace = TraceBuilder::new(&mut ledger)
+        .with_max_transactions(16)
+        .build(root)
+        .await
+        .unwrap();
+    // The sender wallet consumed the transfer 21 transactions back.
+    assert_eq!(lts(&trace), vec![100]);
+    assert!(trace.out_msgs[0].consumer.is_none());
+    assert_eq!(ledger.transaction_calls, 1);
+
+    let root = ledger.root(OWNER, 100);
+    let start = ledger.root(SENDER_WALLET, 200).cursor();
+    let trace = TraceBuilder::new(&mut ledger)
+        .with_max_transactions(16)
+        .with_account_cursor(Address::new(0, SENDER_WALLET), start)
+        .build(root)
+        .await
+        .unwrap();
+    assert_eq!(lts(&trace), vec![100, 200, 300, 400, 700, 800, 500]);
+}
+
+#[tokio::test]
+async fn trace_matches_messages_by_their_original_cells() {
+    let mut ledger = Ledger::default();
+    let mut transfer = internal(OWNER, RECEIVER, 100, 101, false);
+    let CommonMsgInfo::Internal { value, .. } = &mut transfer.info else {
+        unreachable!()
+    };
+    value
+        .other
+        .insert_bit_key(BitKey::from_u64(0, 32).unwrap(), BigUint::from(5u8))
+        .unwrap();
+    ledger.push(
+        OWNER,
+        100,
+        Some(external_in(OWNER)),
+        vec![transfer.clone()],
+        0,
+        None,
+    );
+    ledger.push(RECEIVER, 200, Some(transfer.clone()), Vec::new(), 0, None);
+
+    let relabeled = relabeled(&transfer);
+    assert_eq!(Message::from_cell(relabeled.clone()).unwrap(), transfer);
+    assert_ne!(relabeled.hash(), transfer.to_cell().unwrap().hash());
+    // The sender's message cell holds `in_msg` and then the `out_msgs` root,
+    // whose only leaf references the message.
+    let sender = ledger.cell(&ledger.accounts[&OWNER][0]);
+    let messages = sender.reference(0).unwrap();
+    let out_msgs = replace_ref(messages.reference(1).unwrap(), 0, relabeled.clone());
+    let sender = replace_ref(&sender, 0, replace_ref(messages, 1, out_msgs));
+    ledger.cells.insert(100, sender);
+    let receiver = ledger.cell(&ledger.accounts[&RECEIVER][0]);
+    let messages = replace_ref(receiver.reference(0).unwrap(), 0, relabeled.clone());
+    ledger
+        .cells
+        .insert(200, replace_ref(&receiver, 0, messages));
+
+    let root = ledger.root(OWNER, 100);
+    assert_eq!(root.out_msg_hashes, vec![relabeled.hash()]);
+    let trace = TraceBuilder::new(&mut ledger).build(root).await.unwrap();
+
+    assert_eq!(trace.out_msgs[0].hash, relabeled.hash());
+    assert_eq!(lts(&trace), vec![100, 200]);
+}
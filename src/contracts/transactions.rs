@@ -0,0 +1,350 @@
+//! Account transaction history as async streams.
+//!
+//! [`Contract::transaction_history`] pages backward through an account's
+//! transactions from its latest one, and [`Contract::follow_transactions`]
+//! polls the account state and yields new transactions oldest first.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use futures::Stream;
+
+use crate::tl::{BlockIdExt, common::Int256};
+use crate::tlb::{TlbDeserialize, Transaction};
+use crate::tvm::{Cell, Slice, deserialize_boc_roots};
+
+use super::{Contract, ContractError, ContractProvider};
+
+/// Transactions requested per `getTransactions` page; liteservers return at
+/// most 16 at a time.
+const HISTORY_PAGE: u32 = 16;
+
+/// Transaction hashes remembered by [`Contract::follow_transactions`] for
+/// deduplication.
+const SEEN_TRANSACTIONS: usize = 1024;
+
+/// Key width of the `out_msgs` dictionary of a transaction.
+const OUT_MSG_KEY_BITS: usize = 15;
+
+/// Logical time and hash identifying one account transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransactionCursor {
+    pub lt: u64,
+    pub hash: [u8; 32],
+}
+
+impl TransactionCursor {
+    /// Position before an account's first transaction; following from it
+    /// yields the whole history.
+    pub const START: Self = Self {
+        lt: 0,
+        hash: [0; 32],
+    };
+}
+
+/// A decoded account transaction with its hash and the block holding it.
+#[derive(Debug, Clone)]
+pub struct AccountTransaction {
+    /// Representation hash of the transaction cell.
+    pub hash: [u8; 32],
+    /// Shard block the transaction belongs to.
+    pub block: BlockIdExt,
+    pub transaction: Transaction,
+    /// Representation hash of the inbound message cell as stored in the
+    /// transaction.
+    pub in_msg_hash: Option<[u8; 32]>,
+    /// Representation hashes of the outbound message cells as stored in the
+    /// transaction, in `out_msgs` order.
+    pub out_msg_hashes: Vec<[u8; 32]>,
+}
+
+impl AccountTransaction {
+    /// Decodes the transaction cell `root` from `block`, keeping the hashes
+    /// of its original message cells.
+    pub fn from_cell(block: BlockIdExt, root: Arc<Cell>) -> anyhow::Result<Self> {
+        let hash = root.hash();
+        let messages = root
+            .reference(0)
+            .context("Transaction has no message reference")?;
+        let mut messages = Slice::new(messages.clone());
+        let in_msg_hash = if messages.load_bit()? {
+            Some(messages.load_reference()?.hash())
+        } else {
+            None
+        };
+        let out_msg_hashes = messages
+            .load_hashmap_e_with(OUT_MSG_KEY_BITS, |slice| Ok(slice.load_reference()?.hash()))?
+            .iter()
+            .map(|(_, hash)| *hash)
+            .collect();
+        let transaction =
+            Transaction::from_cell(root).context("failed to decode Transaction TL-B")?;
+        Ok(Self {
+            hash,
+            block,
+            transaction,
+            in_msg_hash,
+            out_msg_hashes,
+        })
+    }
+
+    pub fn cursor(&self) -> TransactionCursor {
+        TransactionCursor {
+            lt: self.transaction.lt,
+            hash: self.hash,
+        }
+    }
+
+    /// Cursor of the transaction before this one, or `None` for the first
+    /// transaction of the account.
+    pub fn prev_cursor(&self) -> Option<TransactionCursor> {
+        (self.transaction.prev_trans_lt != 0).then_some(TransactionCursor {
+            lt: self.transaction.prev_trans_lt,
+            hash: self.transaction.prev_trans_hash,
+        })
+    }
+}
+
+impl<'a, P: ContractProvider + ?Sized> Contract<'a, P> {
+    /// Cursor of the account's latest transaction, or `None` when the account
+    /// has none.
+    pub async fn last_transaction(&mut self) -> Result<Option<TransactionCursor>, P::Error> {
+        let state = self.get_state_simple_latest().await?;
+        Ok(
+            match (state.last_transaction_lt, state.last_transaction_hash) {
+                (Some(lt), Some(hash)) if lt != 0 => Some(TransactionCursor { lt, hash }),
+                _ => None,
+            },
+        )
+    }
+
+    /// Fetches up to `count` transactions starting at `from` and going back in
+    /// time, newest first.
+    pub async fn get_transactions_decoded(
+        &mut self,
+        count: u32,
+        from: TransactionCursor,
+    ) -> Result<Vec<AccountTransaction>, ContractError<P::Error>> {
+        let list = self
+            .get_transactions(count, from.lt, Int256(from.hash))
+            .await
+            .map_err(ContractError::Provider)?;
+        let transactions =
+            decode_transactions(list.ids, &list.transactions).map_err(ContractError::decode)?;
+        if let Some(first) = transactions.first()
+            && first.cursor() != from
+        {
+            return Err(ContractError::decode(anyhow!(
+                "asked for transaction {}, got {}",
+                from.lt,
+                first.transaction.lt
+            )));
+        }
+        Ok(transactions)
+    }
+
+    /// Streams the account's transactions newest first, starting at `from`
+    /// or at the latest transaction, until the first transaction of the
+    /// account.
+    ///
+    /// The stream ends after yielding an error.
+    pub fn transaction_history(
+        &mut self,
+        from: Option<TransactionCursor>,
+    ) -> impl Stream<Item = Result<AccountTransaction, ContractError<P::Error>>> + '_ {
+        let history = History {
+            contract: self,
+            next: from.map_or(Page::Latest, Page::At),
+            page: VecDeque::new(),
+        };
+        futures::stream::unfold(history, |mut history| async move {
+            let item = history.next().await?;
+            Some((item, history))
+        })
+    }
+
+    /// Streams transactions that appear after `after`, oldest first, polling
+    /// the account state every `poll_interval`.
+    ///
+    /// Without `after`, only transactions newer than the latest one at the
+    /// first poll are yielded. Errors are yielded and the next poll retries,
+    /// so consumers should back off or stop on repeated errors.
+    pub fn follow_transactions(
+        &mut self,
+        after: Option<TransactionCursor>,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = Result<AccountTransaction, ContractError<P::Error>>> + '_ {
+        let follow = Follow {
+            contract: self,
+            last: after,
+            started: after.is_some(),
+            poll_interval,
+            polled: false,
+            pending: VecDeque::new(),
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+        };
+        futures::stream::unfold(follow, |mut follow| async move {
+            let item = follow.next().await;
+            Some((item, follow))
+        })
+    }
+}
+
+enum Page {
+    Latest,
+    At(TransactionCursor),
+    Done,
+}
+
+struct History<'c, 'a, P: ContractProvider + ?Sized> {
+    contract: &'c mut Contract<'a, P>,
+    next: Page,
+    page: VecDeque<AccountTransaction>,
+}
+
+impl<P: ContractProvider + ?Sized> History<'_, '_, P> {
+    async fn next(&mut self) -> Option<Result<AccountTransaction, ContractError<P::Error>>> {
+        loop {
+            if let Some(transaction) = self.page.pop_front() {
+                return Some(Ok(transaction));
+            }
+            let cursor = match std::mem::replace(&mut self.next, Page::Done) {
+                Page::Done => return None,
+                Page::At(cursor) => cursor,
+                Page::Latest => match self.contract.last_transaction().await {
+                    Ok(Some(cursor)) => cursor,
+                    Ok(None) => return None,
+                    Err(error) => return Some(Err(ContractError::Provider(error))),
+                },
+            };
+            let page = match self
+                .contract
+                .get_transactions_decoded(HISTORY_PAGE, cursor)
+                .await
+            {
+                Ok(page) => page,
+                Err(error) => return Some(Err(error)),
+            };
+            if let Some(prev) = page.last().and_then(AccountTransaction::prev_cursor) {
+                self.next = Page::At(prev);
+            }
+            self.page.extend(page);
+        }
+    }
+}
+
+struct Follow<'c, 'a, P: ContractProvider + ?Sized> {
+    contract: &'c mut Contract<'a, P>,
+    /// Newest transaction already yielded or skipped.
+    last: Option<TransactionCursor>,
+    /// Whether `last` marks the start point; unset until the first poll when
+    /// following from the latest transaction.
+    started: bool,
+    poll_interval: Duration,
+    polled: bool,
+    pending: VecDeque<AccountTransaction>,
+    seen: HashSet<[u8; 32]>,
+    seen_order: VecDeque<[u8; 32]>,
+}
+
+impl<P: ContractProvider + ?Sized> Follow<'_, '_, P> {
+    async fn next(&mut self) -> Result<AccountTransaction, ContractError<P::Error>> {
+        loop {
+            if let Some(transaction) = self.pending.pop_front() {
+                self.last = Some(transaction.cursor());
+                return Ok(transaction);
+            }
+            if self.polled {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+            self.polled = true;
+            self.poll().await?;
+        }
+    }
+
+    /// Queues transactions between `last` and the current latest one.
+    async fn poll(&mut self) -> Result<(), ContractError<P::Error>> {
+        let head = self
+            .contract
+            .last_transaction()
+            .await
+            .map_err(ContractError::Provider)?;
+        if !self.started {
+            self.started = true;
+            self.last = head;
+            return Ok(());
+        }
+        let Some(head) = head else {
+            return Ok(());
+        };
+        // A lagging liteserver may report an older head; wait for it to catch up.
+        let last_lt = self.last.map_or(0, |last| last.lt);
+        if head.lt <= last_lt {
+            return Ok(());
+        }
+
+        let mut new = Vec::new();
+        let mut next = Some(head);
+        'pages: while let Some(cursor) = next {
+            let page = self
+                .contract
+                .get_transactions_decoded(HISTORY_PAGE, cursor)
+                .await?;
+            next = page.last().and_then(AccountTransaction::prev_cursor);
+            for transaction in page {
+                if transaction.transaction.lt <= last_lt {
+                    break 'pages;
+                }
+                new.push(transaction);
+            }
+        }
+        for transaction in new.into_iter().rev() {
+            if self.remember(transaction.hash) {
+                self.pending.push_back(transaction);
+            }
+        }
+        Ok(())
+    }
+
+    /// Records `hash` and reports whether it was new.
+    fn remember(&mut self, hash: [u8; 32]) -> bool {
+        if !self.seen.insert(hash) {
+            return false;
+        }
+        self.seen_order.push_back(hash);
+        if self.seen_order.len() > SEEN_TRANSACTIONS
+            && let Some(oldest) = self.seen_order.pop_front()
+        {
+            self.seen.remove(&oldest);
+        }
+        true
+    }
+}
+
+/// Pairs every root of a `getTransactions` BoC with its block id and hash.
+fn decode_transactions(
+    ids: Vec<BlockIdExt>,
+    raw: &[u8],
+) -> anyhow::Result<Vec<AccountTransaction>> {
+    if raw.is_empty() {
+        return Ok(Vec::new());
+    }
+    let roots = deserialize_boc_roots(raw).context("failed to decode transaction list BoC")?;
+    if roots.len() != ids.len() {
+        return Err(anyhow!(
+            "transaction list has {} transactions but {} block ids",
+            roots.len(),
+            ids.len()
+        ));
+    }
+    ids.into_iter()
+        .zip(roots)
+        .map(|(block, root)| AccountTransaction::from_cell(block, root))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
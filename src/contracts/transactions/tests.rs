@@ -0,0 +1,288 @@
+use super::*;
+
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
+use std::sync::Arc;
+
+use crate::contracts::test_fixtures::block;
+use crate::liteclient::boc::{DecodedAccountState, SimpleAccount, SimpleAccountState};
+use crate::tl::AccountId;
+use crate::tl::response::{AccountState, MasterchainInfo, RunMethodResult, TransactionList};
+use crate::tlb::{
+    AccStatusChange, AccountStatus, CurrencyCollection, Grams, HashUpdateAccount, TlbSerialize,
+    TrStoragePhase, TransactionDescr,
+};
+use crate::tvm::{Address, Cell, HashmapE, TvmStack, serialize_boc_roots};
+
+const ACCOUNT: [u8; 32] = [0x11; 32];
+
+#[derive(Debug, thiserror::Error)]
+#[error("chain provider error: {0}")]
+struct ChainError(&'static str);
+
+/// Provider serving one account's transaction chain, revealing more of it on
+/// every account-state query.
+struct ChainProvider {
+    /// Transactions oldest first.
+    chain: Vec<Transaction>,
+    /// Number of visible transactions after each account-state query; the
+    /// last entry stays in effect.
+    heads: VecDeque<usize>,
+    visible: usize,
+    transaction_calls: usize,
+}
+
+impl ChainProvider {
+    fn new(len: usize, heads: impl IntoIterator<Item = usize>) -> Self {
+        let mut chain: Vec<Transaction> = Vec::new();
+        for index in 0..len as u64 {
+            let lt = (index + 1) * 10;
+            let (prev_lt, prev_hash) = chain
+                .last()
+                .map(|prev| (prev.lt, prev.to_cell().unwrap().hash()))
+                .unwrap_or_default();
+            chain.push(transaction(lt, prev_lt, prev_hash));
+        }
+        Self {
+            chain,
+            heads: heads.into_iter().collect(),
+            visible: len,
+            transaction_calls: 0,
+        }
+    }
+
+    fn cursor(&self, index: usize) -> TransactionCursor {
+        let transaction = &self.chain[index];
+        TransactionCursor {
+            lt: transaction.lt,
+            hash: transaction.to_cell().unwrap().hash(),
+        }
+    }
+}
+
+#[async_trait]
+impl ContractProvider for ChainProvider {
+    type Error = ChainError;
+
+    async fn get_masterchain_info(&mut self) -> Result<MasterchainInfo, Self::Error> {
+        Ok(MasterchainInfo {
+            last: block(0, 1),
+            state_root_hash: Int256([1; 32]),
+            init: crate::tl::common::ZeroStateIdExt {
+                workchain: -1,
+                root_hash: Int256([2; 32]),
+                file_hash: Int256([3; 32]),
+            },
+        })
+    }
+
+    async fn get_account_state(
+        &mut self,
+        _block: BlockIdExt,
+        _account: AccountId,
+    ) -> Result<AccountState, Self::Error> {
+        Err(ChainError("raw account state"))
+    }
+
+    async fn get_account_state_typed(
+        &mut self,
+        _block: BlockIdExt,
+        _account: Address,
+    ) -> Result<DecodedAccountState, Self::Error> {
+        Err(ChainError("typed account state"))
+    }
+
+    async fn get_account_state_simple(
+        &mut self,
+        block: BlockIdExt,
+        account: Address,
+    ) -> Result<SimpleAccount, Self::Error> {
+        assert_eq!(account.hash_part, ACCOUNT);
+        if let Some(visible) = self.heads.pop_front() {
+            self.visible = visible;
+            if self.heads.is_empty() {
+                self.heads.push_back(visible);
+            }
+        }
+        let last = self.visible.checked_sub(1).map(|index| self.cursor(index));
+        Ok(SimpleAccount {
+            block_id: block.clone(),
+            shard_block_id: block,
+            last_transaction_lt: Some(last.map_or(0, |last| last.lt)),
+            last_transaction_hash: Some(last.map_or([0; 32], |last| last.hash)),
+            state: SimpleAccountState::Active,
+            account: None,
+        })
+    }
+
+    async fn run_get_method(
+        &mut self,
+        _mode: u32,
+        _block: BlockIdExt,
+        _account: Address,
+        _method_id: u64,
+        _stack: TvmStack,
+    ) -> Result<RunMethodResult, Self::Error> {
+        Err(ChainError("get-method"))
+    }
+
+    async fn send_external_message_boc(&mut self, _body: Vec<u8>) -> Result<u32, Self::Error> {
+        Err(ChainError("send message"))
+    }
+
+    /// Looks transactions up by lt only so that hash checks are left to the
+    /// caller.
+    async fn get_transactions(
+        &mut self,
+        count: u32,
+        account: AccountId,
+        lt: u64,
+        _hash: Int256,
+    ) -> Result<TransactionList, Self::Error> {
+        assert_eq!(account.id.0, ACCOUNT);
+        assert!(count <= 16);
+        self.transaction_calls += 1;
+        let first = self.chain[..self.visible]
+            .iter()
+            .position(|transaction| transaction.lt == lt)
+            .ok_or(ChainError("unknown transaction"))?;
+        let start = (first + 1).saturating_sub(count as usize);
+        let roots: Vec<Arc<Cell>> = self.chain[start..=first]
+            .iter()
+            .rev()
+            .map(|transaction| transaction.to_cell().unwrap())
+            .collect();
+        Ok(TransactionList {
+            ids: roots.iter().map(|_| block(0, 2)).collect(),
+            transactions: serialize_boc_roots(&roots, true).unwrap(),
+        })
+    }
+
+    async fn get_config_param(
+        &mut self,
+        _block: BlockIdExt,
+        _id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error> {
+        Ok(None)
+    }
+}
+
+fn transaction(lt: u64, prev_lt: u64, prev_hash: [u8; 32]) -> Transaction {
+    Transaction {
+        account_addr: ACCOUNT,
+        lt,
+        prev_trans_hash: prev_hash,
+        prev_trans_lt: prev_lt,
+        now: 1_700_000_000 + lt as u32,
+        outmsg_cnt: 0,
+        orig_status: AccountStatus::Active,
+        end_status: AccountStatus::Active,
+        in_msg: None,
+        out_msgs: HashmapE::new(15),
+        total_fees: CurrencyCollection::grams(Grams::from(lt)),
+        state_update: HashUpdateAccount {
+            old_hash: [0xAA; 32],
+            new_hash: [0xBB; 32],
+        },
+        description: TransactionDescr::Storage {
+            storage_ph: TrStoragePhase {
+                storage_fees_collected: Grams::from(1),
+                storage_fees_due: None,
+                status_change: AccStatusChange::Unchanged,
+            },
+        },
+    }
+}
+
+fn lts(transactions: &[AccountTransaction]) -> Vec<u64> {
+    transactions
+        .iter()
+        .map(|transaction| transaction.transaction.lt)
+        .collect()
+}
+
+#[tokio::test]
+async fn history_pages_back_to_the_first_transaction() {
+    let mut provider = ChainProvider::new(40, []);
+    let mut contract = Contract::new(&mut provider, Address::new(0, ACCOUNT));
+
+    let history: Vec<_> = contract
+        .transaction_history(None)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        lts(&history),
+        (1..=40).rev().map(|i| i * 10).collect::<Vec<_>>()
+    );
+    assert!(
+        history
+            .iter()
+            .all(|item| item.hash == item.transaction.to_cell().unwrap().hash())
+    );
+    assert_eq!(provider.transaction_calls, 3);
+}
+
+#[tokio::test]
+async fn history_starts_at_cursor_and_rejects_hash_mismatch() {
+    let mut provider = ChainProvider::new(5, []);
+    let cursor = provider.cursor(2);
+    let mut contract = Contract::new(&mut provider, Address::new(0, ACCOUNT));
+
+    let history: Vec<_> = contract
+        .transaction_history(Some(cursor))
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(lts(&history), vec![30, 20, 10]);
+
+    let wrong = TransactionCursor {
+        hash: [0; 32],
+        ..cursor
+    };
+    let results: Vec<_> = contract.transaction_history(Some(wrong)).collect().await;
+    assert_eq!(results.len(), 1);
+    assert!(matches!(results[0], Err(ContractError::Decode(_))));
+}
+
+#[tokio::test]
+async fn history_of_account_without_transactions_is_empty() {
+    let mut provider = ChainProvider::new(0, []);
+    let mut contract = Contract::new(&mut provider, Address::new(0, ACCOUNT));
+
+    assert_eq!(contract.last_transaction().await.unwrap(), None);
+    let history: Vec<_> = contract.transaction_history(None).collect().await;
+    assert!(history.is_empty());
+}
+
+#[tokio::test]
+async fn follow_yields_new_transactions_oldest_first() {
+    let mut provider = ChainProvider::new(40, [2, 2, 5, 40]);
+    let mut contract = Contract::new(&mut provider, Address::new(0, ACCOUNT));
+
+    let followed: Vec<_> = contract
+        .follow_transactions(None, Duration::ZERO)
+        .take(38)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(lts(&followed), (3..=40).map(|i| i * 10).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn follow_skips_lagging_heads_without_repeats() {
+    let mut provider = ChainProvider::new(6, [3, 2, 3, 6]);
+    let after = provider.cursor(0);
+    let mut contract = Contract::new(&mut provider, Address::new(0, ACCOUNT));
+
+    let followed: Vec<_> = contract
+        .follow_transactions(Some(after), Duration::ZERO)
+        .take(5)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(lts(&followed), vec![20, 30, 40, 50, 60]);
+}
@@ -1,10 +1,13 @@
-use crc::{CRC_16_IBM_SDLC, CRC_32_ISO_HDLC, Crc};
+use crc::{CRC_16_XMODEM, CRC_32_ISCSI, CRC_32_ISO_HDLC, Crc};
 
-/// CRC16 implementation
-pub const CRC16: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_SDLC);
+/// CRC16/XMODEM implementation used for TON get-method ids
+pub const CRC16: Crc<u16> = Crc::<u16>::new(&CRC_16_XMODEM);
 
 /// CRC32 implementation
 pub const CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
 
+/// CRC32C (Castagnoli) implementation used for BoC checksums
+pub const CRC32C: Crc<u32> = Crc::<u32>::new(&CRC_32_ISCSI);
+
 #[cfg(test)]
 mod tests;
@@ -285,3 +285,10 @@ fn test_crc32_order_matters() {
     // Order should matter
     assert_ne!(checksum1, checksum2);
 }
+
+#[test]
+fn test_crc16_matches_ton_method_ids() {
+    // FunC derives get-method ids from CRC16/XMODEM: `seqno` is 85143.
+    assert_eq!(CRC16.checksum(b"seqno"), 0x4c97);
+    assert_eq!(crate::utils::method_name_to_id("seqno"), 85143);
+}
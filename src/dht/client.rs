@@ -0,0 +1,383 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::join_all;
+use rand::RngCore;
+use tl_proto::BoxedWrapper;
+
+use crate::adnl::crypto::{KeyPair, PublicKey};
+use crate::adnl::udp::{AdnlAddressList, AdnlNode, unix_now};
+use crate::adnl::{AdnlAddress, AdnlError};
+use crate::tl::common::Int256;
+
+use super::DhtError;
+use super::types::{
+    DhtKey, DhtNode, DhtNodes, DhtPong, DhtQuery, DhtStored, DhtValue, DhtValueResult, xor_distance,
+};
+
+/// Default replication factor used by TON nodes.
+pub const DEFAULT_DHT_K: usize = 10;
+/// Default lookup parallelism used by TON nodes.
+pub const DEFAULT_DHT_A: usize = 3;
+const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Kademlia DHT client.
+///
+/// Known nodes are keyed by their ADNL id. Lookups start from the `k` known
+/// nodes closest to the key by XOR distance, query `a` of them at a time, and
+/// learn new nodes from `dht.valueNotFound`/`dht.nodes` answers until the `k`
+/// closest nodes seen have all been asked. Unreachable nodes are skipped; a
+/// lookup only fails when no node returns a valid answer.
+pub struct DhtClient {
+    adnl: Arc<AdnlNode>,
+    k: usize,
+    a: usize,
+    timeout: Duration,
+    nodes: Mutex<HashMap<AdnlAddress, DhtNode>>,
+}
+
+/// Result of an iterative lookup.
+enum Lookup {
+    Value(DhtValue),
+    Nodes(Vec<DhtNode>),
+}
+
+impl DhtClient {
+    /// Creates a client without known nodes; add them with
+    /// [`DhtClient::add_node`].
+    pub fn new(adnl: Arc<AdnlNode>) -> Self {
+        Self {
+            adnl,
+            k: DEFAULT_DHT_K,
+            a: DEFAULT_DHT_A,
+            timeout: DEFAULT_QUERY_TIMEOUT,
+            nodes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a client that bootstraps from the `dht` section of the global
+    /// config.
+    ///
+    /// `k` and `a` are taken from the config. Static nodes with a bad
+    /// signature or without a UDP address are skipped.
+    #[cfg(feature = "network-config")]
+    pub fn from_config(
+        adnl: Arc<AdnlNode>,
+        config: &crate::network_config::ConfigGlobal,
+    ) -> Result<Self, DhtError> {
+        let dht = config.dht.as_ref().ok_or(DhtError::NoNodes)?;
+        let mut client = Self::new(adnl);
+        if dht.k > 0 {
+            client.k = dht.k as usize;
+        }
+        if dht.a > 0 {
+            client.a = dht.a as usize;
+        }
+        for node in &dht.static_nodes.nodes {
+            if let Err(error) = client.add_node(DhtNode::from(node)) {
+                log::warn!("skipping static DHT node: {error}");
+            }
+        }
+        if client.nodes.lock().unwrap().is_empty() {
+            return Err(DhtError::NoNodes);
+        }
+        Ok(client)
+    }
+
+    /// Sets how many closest nodes a lookup converges on and a value is
+    /// stored to.
+    pub fn with_k(mut self, k: usize) -> Self {
+        self.k = k.max(1);
+        self
+    }
+
+    /// Sets how many nodes are queried concurrently.
+    pub fn with_a(mut self, a: usize) -> Self {
+        self.a = a.max(1);
+        self
+    }
+
+    /// Sets the timeout of a single DHT query.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn adnl(&self) -> &Arc<AdnlNode> {
+        &self.adnl
+    }
+
+    /// Returns the known nodes.
+    pub fn nodes(&self) -> Vec<DhtNode> {
+        self.nodes.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Verifies `node` and registers it with the DHT and the ADNL node.
+    ///
+    /// A record with a lower `version` than the known one is ignored. The
+    /// first UDP address of the list is used to reach the node.
+    pub fn add_node(&self, node: DhtNode) -> Result<AdnlAddress, DhtError> {
+        let public_key = node.verify()?;
+        let id = AdnlAddress::from(&public_key);
+        if &id == self.adnl.id() {
+            return Ok(id);
+        }
+        let addr = node
+            .addr_list
+            .socket_addrs()
+            .into_iter()
+            .next()
+            .ok_or_else(|| DhtError::InvalidNode("node has no UDP address".to_string()))?;
+
+        let mut nodes = self.nodes.lock().unwrap();
+        if nodes
+            .get(&id)
+            .is_some_and(|known| known.version > node.version)
+        {
+            return Ok(id);
+        }
+        self.adnl.add_peer(public_key, addr);
+        nodes.insert(id.clone(), node);
+        Ok(id)
+    }
+
+    /// Sends `dht.ping` to a known node.
+    pub async fn ping(&self, peer: &AdnlAddress) -> Result<(), DhtError> {
+        let random_id = rand::rngs::OsRng.next_u64() as i64;
+        let pong: DhtPong = self.query(peer, &DhtQuery::Ping { random_id }).await?;
+        if pong.random_id != random_id {
+            return Err(AdnlError::InvalidPacket("pong for another ping".to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// Asks a known node for its current signed `dht.node` and registers it.
+    pub async fn get_signed_address_list(&self, peer: &AdnlAddress) -> Result<DhtNode, DhtError> {
+        let node = self
+            .query::<BoxedWrapper<DhtNode>>(peer, &DhtQuery::GetSignedAddressList)
+            .await?
+            .0;
+        if &node.adnl_id() != peer {
+            return Err(DhtError::InvalidNode(
+                "node answered with another id".to_string(),
+            ));
+        }
+        self.add_node(node.clone())?;
+        Ok(node)
+    }
+
+    /// Finds the `k` responsive nodes closest to `key` with `dht.findNode`.
+    pub async fn find_nodes(&self, key: &[u8; 32]) -> Result<Vec<DhtNode>, DhtError> {
+        match self.lookup(key, false).await? {
+            Lookup::Nodes(nodes) => Ok(nodes),
+            Lookup::Value(_) => unreachable!("`dht.findNode` lookups return nodes"),
+        }
+    }
+
+    /// Finds a valid value stored under `key` with `dht.findValue`.
+    pub async fn find_value(&self, key: &DhtKey) -> Result<DhtValue, DhtError> {
+        key.check()?;
+        match self.lookup(&key.hash(), true).await? {
+            Lookup::Value(value) => Ok(value),
+            Lookup::Nodes(_) => Err(DhtError::ValueNotFound),
+        }
+    }
+
+    /// Stores `value` on the `k` nodes closest to its key.
+    ///
+    /// Returns how many nodes answered `dht.stored`.
+    pub async fn store(&self, value: DhtValue) -> Result<usize, DhtError> {
+        value.verify(unix_now())?;
+        let peers = self
+            .find_nodes(&value.key_hash())
+            .await?
+            .iter()
+            .map(DhtNode::adnl_id)
+            .collect::<Vec<_>>();
+        let query = DhtQuery::Store { value };
+        let answers = join_all(
+            peers
+                .iter()
+                .map(|peer| self.query::<DhtStored>(peer, &query)),
+        )
+        .await;
+        let stored = answers.iter().filter(|answer| answer.is_ok()).count();
+        if stored == 0 {
+            return Err(DhtError::NotStored);
+        }
+        Ok(stored)
+    }
+
+    /// Resolves the ADNL address list published by `adnl_id`.
+    ///
+    /// Returns the node key along with the list, which is what
+    /// [`AdnlNode::add_peer`] needs to reach the node.
+    pub async fn resolve_address(
+        &self,
+        adnl_id: &AdnlAddress,
+    ) -> Result<(PublicKey, AdnlAddressList), DhtError> {
+        self.find_value(&DhtKey::address(adnl_id))
+            .await?
+            .to_address_list()
+    }
+
+    /// Publishes `addr_list` as the address of `keypair` until `ttl`.
+    pub async fn store_address(
+        &self,
+        keypair: &KeyPair,
+        addr_list: &AdnlAddressList,
+        ttl: i32,
+    ) -> Result<usize, DhtError> {
+        self.store(DhtValue::address_list(addr_list, ttl, keypair))
+            .await
+    }
+
+    async fn lookup(&self, key: &[u8; 32], find_value: bool) -> Result<Lookup, DhtError> {
+        let mut candidates = self
+            .nodes
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|id| (xor_distance(key, id.as_bytes()), id.clone()))
+            .collect::<BTreeMap<_, _>>();
+        if candidates.is_empty() {
+            return Err(DhtError::NoNodes);
+        }
+        let query = if find_value {
+            DhtQuery::FindValue {
+                key: Int256(*key),
+                k: self.k as i32,
+            }
+        } else {
+            DhtQuery::FindNode {
+                key: Int256(*key),
+                k: self.k as i32,
+            }
+        };
+
+        let mut queried = HashSet::new();
+        let mut responded = BTreeMap::new();
+        loop {
+            let batch = candidates
+                .values()
+                .take(self.k)
+                .filter(|id| !queried.contains(*id))
+                .take(self.a)
+                .cloned()
+                .collect::<Vec<_>>();
+            if batch.is_empty() {
+                break;
+            }
+            queried.extend(batch.iter().cloned());
+
+            let answers = join_all(batch.iter().map(|peer| self.query_raw(peer, &query))).await;
+            for (peer, answer) in batch.into_iter().zip(answers) {
+                let nodes =
+                    match answer.and_then(|answer| self.parse_answer(key, &answer, find_value)) {
+                        Ok(Lookup::Value(value)) => return Ok(Lookup::Value(value)),
+                        Ok(Lookup::Nodes(nodes)) => nodes,
+                        Err(error) => {
+                            log::debug!("DHT query to {peer:?} failed: {error}");
+                            continue;
+                        }
+                    };
+                responded.insert(xor_distance(key, peer.as_bytes()), peer);
+                for node in nodes {
+                    match self.add_node(node) {
+                        Ok(id) if &id != self.adnl.id() => {
+                            candidates.insert(xor_distance(key, id.as_bytes()), id);
+                        }
+                        Ok(_) => {}
+                        Err(error) => log::debug!("ignoring DHT node: {error}"),
+                    }
+                }
+            }
+        }
+
+        if responded.is_empty() {
+            return Err(DhtError::Adnl(AdnlError::Timeout {
+                operation: "dht_lookup",
+                timeout: self.timeout,
+            }));
+        }
+        let nodes = self.nodes.lock().unwrap();
+        Ok(Lookup::Nodes(
+            responded
+                .into_values()
+                .take(self.k)
+                .filter_map(|id| nodes.get(&id).cloned())
+                .collect(),
+        ))
+    }
+
+    fn parse_answer(
+        &self,
+        key: &[u8; 32],
+        answer: &[u8],
+        find_value: bool,
+    ) -> Result<Lookup, DhtError> {
+        if !find_value {
+            let nodes = tl_proto::deserialize::<BoxedWrapper<DhtNodes>>(answer)?.0;
+            return Ok(Lookup::Nodes(nodes.nodes));
+        }
+        match tl_proto::deserialize::<DhtValueResult>(answer)? {
+            DhtValueResult::Found { value } => {
+                if &value.key_hash() != key {
+                    return Err(DhtError::InvalidValue(
+                        "value stored under another key".to_string(),
+                    ));
+                }
+                value.verify(unix_now())?;
+                Ok(Lookup::Value(value))
+            }
+            DhtValueResult::NotFound { nodes } => Ok(Lookup::Nodes(nodes.nodes)),
+        }
+    }
+
+    async fn query<T>(&self, peer: &AdnlAddress, query: &DhtQuery) -> Result<T, DhtError>
+    where
+        T: for<'a> tl_proto::TlRead<'a>,
+    {
+        let answer = self.query_raw(peer, query).await?;
+        Ok(tl_proto::deserialize(&answer)?)
+    }
+
+    async fn query_raw(&self, peer: &AdnlAddress, query: &DhtQuery) -> Result<Vec<u8>, DhtError> {
+        Ok(self
+            .adnl
+            .query(peer, tl_proto::serialize(query), self.timeout)
+            .await?)
+    }
+}
+
+#[cfg(feature = "network-config")]
+impl From<&crate::network_config::ConfigDhtNode> for DhtNode {
+    fn from(node: &crate::network_config::ConfigDhtNode) -> Self {
+        use crate::adnl::crypto::tl::PublicKeyOwned;
+        use crate::adnl::udp::AdnlUdpAddress;
+        use crate::network_config::ConfigAdnlAddress;
+
+        Self {
+            id: PublicKeyOwned::Ed25519 {
+                key: node.id.to_bytes(),
+            },
+            addr_list: AdnlAddressList {
+                addrs: node
+                    .addr_list
+                    .addrs
+                    .iter()
+                    .map(|addr| match *addr {
+                        ConfigAdnlAddress::Udp { ip, port } => AdnlUdpAddress::Udp { ip, port },
+                    })
+                    .collect(),
+                version: node.addr_list.version,
+                reinit_date: node.addr_list.reinit_date,
+                priority: node.addr_list.priority,
+                expire_at: node.addr_list.expire_at,
+            },
+            version: node.version,
+            signature: node.signature.clone(),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Kademlia DHT client over ADNL UDP.
+//!
+//! `DhtClient` bootstraps from the `dht.static_nodes` of the global config,
+//! walks the key space with `dht.findNode`/`dht.findValue`, and publishes
+//! records with `dht.store`. Every `dht.node` and `dht.value` received from the
+//! network is checked against its signatures before it is used, so address
+//! lists resolved through [`DhtClient::resolve_address`] can be trusted to come
+//! from the owner of the ADNL id.
+
+mod client;
+mod types;
+
+#[cfg(test)]
+mod tests;
+
+use thiserror::Error;
+
+use crate::adnl::AdnlError;
+
+pub use client::{DEFAULT_DHT_A, DEFAULT_DHT_K, DhtClient};
+pub use types::{
+    DHT_ADDRESS_KEY_NAME, DHT_MAX_KEY_INDEX, DHT_MAX_NAME_BYTES, DHT_MAX_VALUE_BYTES, DhtKey,
+    DhtKeyDescription, DhtNode, DhtNodes, DhtPong, DhtQuery, DhtStored, DhtUpdateRule, DhtValue,
+    DhtValueResult, OverlayNode, OverlayNodes,
+};
+
+#[derive(Debug, Error)]
+pub enum DhtError {
+    #[error(transparent)]
+    Adnl(#[from] AdnlError),
+    #[error("invalid DHT response: {0}")]
+    InvalidResponse(#[from] tl_proto::TlError),
+    #[error("invalid DHT node: {0}")]
+    InvalidNode(String),
+    #[error("invalid DHT value: {0}")]
+    InvalidValue(String),
+    #[error("global config has no usable DHT nodes")]
+    NoNodes,
+    #[error("DHT value not found")]
+    ValueNotFound,
+    #[error("no DHT node accepted the value")]
+    NotStored,
+}
@@ -0,0 +1,432 @@
+use super::*;
+use crate::adnl::crypto::KeyPair;
+use crate::adnl::crypto::tl::PublicKeyOwned;
+use crate::adnl::udp::{AdnlAddressList, unix_now};
+use crate::adnl::{AdnlAddress, AdnlMessageHandler, AdnlNode};
+use crate::tl::common::Int256;
+use async_trait::async_trait;
+use rand::rngs::OsRng;
+use sha2::Digest;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tl_proto::BoxedConstructor;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// In-memory DHT node answering queries like a TON node would.
+struct DhtServer {
+    node: DhtNode,
+    peers: Mutex<Vec<DhtNode>>,
+    values: Mutex<HashMap<[u8; 32], DhtValue>>,
+    /// Answers every `dht.findValue` with this value when set.
+    forged: Option<DhtValue>,
+}
+
+impl DhtServer {
+    fn closest(&self, key: &[u8; 32], k: i32) -> DhtNodes {
+        let mut nodes = self.peers.lock().unwrap().clone();
+        nodes.sort_by_key(|node| xor(key, node.adnl_id().as_bytes()));
+        nodes.truncate(k as usize);
+        DhtNodes { nodes }
+    }
+}
+
+#[async_trait]
+impl AdnlMessageHandler for DhtServer {
+    async fn handle_query(&self, _peer: &AdnlAddress, query: &[u8]) -> Option<Vec<u8>> {
+        Some(match tl_proto::deserialize::<DhtQuery>(query).ok()? {
+            DhtQuery::Ping { random_id } => tl_proto::serialize(DhtPong { random_id }),
+            DhtQuery::Store { value } => {
+                value.verify(unix_now()).ok()?;
+                self.values.lock().unwrap().insert(value.key_hash(), value);
+                tl_proto::serialize(DhtStored)
+            }
+            DhtQuery::FindNode { key, k } => {
+                tl_proto::serialize(self.closest(&key.0, k).as_boxed())
+            }
+            DhtQuery::FindValue { key, k } => {
+                let value = self
+                    .forged
+                    .clone()
+                    .or_else(|| self.values.lock().unwrap().get(&key.0).cloned());
+                tl_proto::serialize(match value {
+                    Some(value) => DhtValueResult::Found { value },
+                    None => DhtValueResult::NotFound {
+                        nodes: self.closest(&key.0, k),
+                    },
+                })
+            }
+            DhtQuery::GetSignedAddressList => tl_proto::serialize(self.node.as_boxed()),
+        })
+    }
+}
+
+struct Network {
+    servers: Vec<(AdnlNode, Arc<DhtServer>)>,
+}
+
+impl Network {
+    /// Starts `count` servers where each one only knows its neighbours, so
+    /// lookups have to walk the chain.
+    async fn chain(count: usize) -> Self {
+        let mut servers = Vec::new();
+        for _ in 0..count {
+            servers.push(server(None).await);
+        }
+        for i in 0..count {
+            let mut peers = servers[i].1.peers.lock().unwrap();
+            if i > 0 {
+                peers.push(servers[i - 1].1.node.clone());
+            }
+            if i + 1 < count {
+                peers.push(servers[i + 1].1.node.clone());
+            }
+        }
+        Self { servers }
+    }
+
+    fn node(&self, index: usize) -> DhtNode {
+        self.servers[index].1.node.clone()
+    }
+
+    async fn client(&self) -> (KeyPair, DhtClient) {
+        let keypair = KeyPair::generate(&mut OsRng);
+        let adnl = AdnlNode::bind("127.0.0.1:0", keypair).await.unwrap();
+        let client = DhtClient::new(Arc::new(adnl)).with_timeout(TIMEOUT);
+        client.add_node(self.node(0)).unwrap();
+        (keypair, client)
+    }
+}
+
+async fn server(forged: Option<DhtValue>) -> (AdnlNode, Arc<DhtServer>) {
+    let keypair = KeyPair::generate(&mut OsRng);
+    let adnl = AdnlNode::bind("127.0.0.1:0", keypair).await.unwrap();
+    let handler = Arc::new(DhtServer {
+        node: DhtNode::signed(&keypair, adnl.address_list().clone(), unix_now()),
+        peers: Mutex::new(Vec::new()),
+        values: Mutex::new(HashMap::new()),
+        forged,
+    });
+    adnl.set_handler(handler.clone());
+    (adnl, handler)
+}
+
+fn xor(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    std::array::from_fn(|i| a[i] ^ b[i])
+}
+
+fn address_list() -> AdnlAddressList {
+    AdnlAddressList::udp("10.0.0.1:30303".parse().unwrap(), 1, 1)
+}
+
+#[test]
+fn node_signature_covers_all_fields() {
+    let keypair = KeyPair::generate(&mut OsRng);
+    let node = DhtNode::signed(&keypair, address_list(), 5);
+    assert_eq!(node.verify().unwrap(), keypair.public_key);
+    assert_eq!(node.adnl_id(), AdnlAddress::from(&keypair.public_key));
+
+    let decoded: tl_proto::BoxedWrapper<DhtNode> =
+        tl_proto::deserialize(&tl_proto::serialize(node.as_boxed())).unwrap();
+    assert_eq!(decoded.0, node);
+
+    let mut tampered = node.clone();
+    tampered.version = 6;
+    assert!(matches!(tampered.verify(), Err(DhtError::InvalidNode(_))));
+    let mut tampered = node;
+    tampered.addr_list = AdnlAddressList::udp("10.0.0.2:30303".parse().unwrap(), 1, 1);
+    assert!(matches!(tampered.verify(), Err(DhtError::InvalidNode(_))));
+}
+
+#[test]
+fn signed_value_is_validated() {
+    let keypair = KeyPair::generate(&mut OsRng);
+    let now = unix_now();
+    let value = DhtValue::address_list(&address_list(), now + 60, &keypair);
+    value.verify(now).unwrap();
+    assert_eq!(
+        value.key_hash(),
+        DhtKey::address(&AdnlAddress::from(&keypair.public_key)).hash()
+    );
+    let (public_key, list) = value.to_address_list().unwrap();
+    assert_eq!(public_key, keypair.public_key);
+    assert_eq!(list, address_list());
+
+    assert!(matches!(
+        value.verify(now + 60),
+        Err(DhtError::InvalidValue(_))
+    ));
+
+    let mut tampered = value.clone();
+    tampered.value.push(0);
+    assert!(matches!(
+        tampered.verify(now),
+        Err(DhtError::InvalidValue(_))
+    ));
+
+    let mut tampered = value.clone();
+    tampered.ttl += 1;
+    assert!(matches!(
+        tampered.verify(now),
+        Err(DhtError::InvalidValue(_))
+    ));
+
+    let other = KeyPair::generate(&mut OsRng);
+    let mut foreign = value.clone();
+    foreign.key.key.id = Int256(AdnlAddress::from(&other.public_key).to_bytes());
+    assert!(matches!(
+        foreign.verify(now),
+        Err(DhtError::InvalidValue(_))
+    ));
+
+    let mut anybody = value;
+    anybody.key.update_rule = DhtUpdateRule::Anybody;
+    assert!(matches!(
+        anybody.verify(now),
+        Err(DhtError::InvalidValue(_))
+    ));
+    anybody.key.signature.clear();
+    anybody.signature.clear();
+    anybody.verify(now).unwrap();
+}
+
+#[test]
+fn key_limits_are_checked() {
+    assert!(DhtKey::new([0; 32], "address", 0).check().is_ok());
+    assert!(DhtKey::new([0; 32], "", 0).check().is_err());
+    assert!(DhtKey::new([0; 32], &"a".repeat(128), 0).check().is_err());
+    assert!(DhtKey::new([0; 32], "address", 16).check().is_err());
+    assert!(DhtKey::new([0; 32], "address", -1).check().is_err());
+}
+
+#[test]
+fn overlay_nodes_value_checks_node_signatures() {
+    let overlay_key = PublicKeyOwned::Overlay {
+        name: b"overlay".to_vec(),
+    };
+    let overlay_id = Int256(sha2::Sha256::digest(tl_proto::serialize(&overlay_key)).into());
+    let member = KeyPair::generate(&mut OsRng);
+    let member_id = AdnlAddress::from(&member.public_key);
+
+    #[derive(tl_proto::TlWrite)]
+    #[tl(boxed, id = 0x03d8a8e1)]
+    struct ToSign {
+        id: Int256,
+        overlay: Int256,
+        version: i32,
+    }
+    let signature = member.sign_raw(&tl_proto::serialize(ToSign {
+        id: Int256(member_id.to_bytes()),
+        overlay: overlay_id.clone(),
+        version: 7,
+    }));
+    let nodes = OverlayNodes {
+        nodes: vec![OverlayNode {
+            id: member.public_key.as_tl().as_equivalent_owned(),
+            overlay: overlay_id.clone(),
+            version: 7,
+            signature: signature.to_vec(),
+        }],
+    };
+    let mut value = DhtValue {
+        key: DhtKeyDescription {
+            key: DhtKey {
+                id: overlay_id,
+                name: b"nodes".to_vec(),
+                idx: 0,
+            },
+            id: overlay_key,
+            update_rule: DhtUpdateRule::OverlayNodes,
+            signature: Vec::new(),
+        },
+        value: tl_proto::serialize(nodes.as_boxed()),
+        ttl: unix_now() + 60,
+        signature: Vec::new(),
+    };
+    value.verify(unix_now()).unwrap();
+
+    let mut forged = nodes;
+    forged.nodes[0].version = 8;
+    value.value = tl_proto::serialize(forged.as_boxed());
+    assert!(matches!(
+        value.verify(unix_now()),
+        Err(DhtError::InvalidValue(_))
+    ));
+}
+
+#[test]
+fn value_result_roundtrips_with_boxed_value() {
+    let keypair = KeyPair::generate(&mut OsRng);
+    let value = DhtValue::address_list(&address_list(), unix_now() + 60, &keypair);
+    let found = DhtValueResult::Found {
+        value: value.clone(),
+    };
+    let bytes = tl_proto::serialize(&found);
+    assert_eq!(&bytes[..4], &0xe40cf774u32.to_le_bytes());
+    assert_eq!(&bytes[4..8], &0x90ad27cbu32.to_le_bytes());
+    assert_eq!(
+        tl_proto::deserialize::<DhtValueResult>(&bytes).unwrap(),
+        found
+    );
+}
+
+#[tokio::test]
+async fn lookups_walk_the_network() {
+    let network = Network::chain(5).await;
+    let (_, client) = network.client().await;
+    assert_eq!(client.nodes().len(), 1);
+
+    let target = network.node(4).adnl_id();
+    let nodes = client.find_nodes(target.as_bytes()).await.unwrap();
+    assert_eq!(client.nodes().len(), 5);
+    assert_eq!(nodes.len(), 5);
+    assert_eq!(nodes[0].adnl_id(), target);
+
+    client.ping(&target).await.unwrap();
+    let node = client.get_signed_address_list(&target).await.unwrap();
+    assert_eq!(node, network.node(4));
+}
+
+#[tokio::test]
+async fn stored_address_lists_resolve_from_other_clients() {
+    let network = Network::chain(4).await;
+    let (publisher_key, publisher) = network.client().await;
+    let (_, resolver) = network.client().await;
+    let publisher_id = AdnlAddress::from(&publisher_key.public_key);
+    let list = publisher.adnl().address_list().clone();
+
+    let stored = publisher
+        .store_address(&publisher_key, &list, unix_now() + 600)
+        .await
+        .unwrap();
+    assert_eq!(stored, 4);
+
+    let (public_key, resolved) = resolver.resolve_address(&publisher_id).await.unwrap();
+    assert_eq!(public_key, publisher_key.public_key);
+    assert_eq!(resolved, list);
+
+    let missing = AdnlAddress::from([9; 32]);
+    assert!(matches!(
+        resolver.resolve_address(&missing).await,
+        Err(DhtError::ValueNotFound)
+    ));
+}
+
+#[tokio::test]
+async fn forged_values_are_ignored() {
+    let network = Network::chain(2).await;
+    let (owner_key, owner) = network.client().await;
+    let owner_id = AdnlAddress::from(&owner_key.public_key);
+    owner
+        .store_address(&owner_key, &address_list(), unix_now() + 600)
+        .await
+        .unwrap();
+
+    let mut forged = DhtValue::address_list(
+        &AdnlAddressList::udp("10.6.6.6:666".parse().unwrap(), 1, 1),
+        unix_now() + 600,
+        &owner_key,
+    );
+    forged.value =
+        tl_proto::serialize(AdnlAddressList::udp("10.6.6.7:666".parse().unwrap(), 1, 1).as_boxed());
+    let (_liar_adnl, liar) = server(Some(forged)).await;
+    liar.peers.lock().unwrap().push(network.node(0));
+
+    let (_, resolver) = network.client().await;
+    resolver.add_node(liar.node.clone()).unwrap();
+    let (_, resolved) = resolver.resolve_address(&owner_id).await.unwrap();
+    assert_eq!(resolved, address_list());
+}
+
+#[tokio::test]
+async fn lookups_without_reachable_nodes_fail() {
+    let adnl = AdnlNode::bind("127.0.0.1:0", KeyPair::generate(&mut OsRng))
+        .await
+        .unwrap();
+    let client = DhtClient::new(Arc::new(adnl)).with_timeout(Duration::from_millis(200));
+    assert!(matches!(
+        client.find_nodes(&[0; 32]).await,
+        Err(DhtError::NoNodes)
+    ));
+
+    let silent = KeyPair::generate(&mut OsRng);
+    client
+        .add_node(DhtNode::signed(&silent, address_list(), 1))
+        .unwrap();
+    assert!(matches!(
+        client.find_nodes(&[0; 32]).await,
+        Err(DhtError::Adnl(_))
+    ));
+}
+
+#[cfg(feature = "network-config")]
+#[tokio::test]
+async fn client_bootstraps_from_global_config() {
+    use base64::Engine;
+
+    let keypair = KeyPair::generate(&mut OsRng);
+    let node = DhtNode::signed(
+        &keypair,
+        AdnlAddressList {
+            version: 0,
+            reinit_date: 0,
+            ..AdnlAddressList::udp("1.2.3.4:5".parse().unwrap(), 0, 0)
+        },
+        -1,
+    );
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let json = format!(
+        r#"{{
+            "liteservers": [],
+            "dht": {{
+                "@type": "dht.config.global",
+                "k": 6,
+                "a": 3,
+                "static_nodes": {{
+                    "@type": "dht.nodes",
+                    "nodes": [
+                        {{
+                            "@type": "dht.node",
+                            "id": {{ "@type": "pub.ed25519", "key": "{key}" }},
+                            "addr_list": {{
+                                "@type": "adnl.addressList",
+                                "addrs": [{{ "@type": "adnl.address.udp", "ip": 16909060, "port": 5 }}],
+                                "version": 0,
+                                "reinit_date": 0,
+                                "priority": 0,
+                                "expire_at": 0
+                            }},
+                            "version": -1,
+                            "signature": "{signature}"
+                        }},
+                        {{
+                            "@type": "dht.node",
+                            "id": {{ "@type": "pub.ed25519", "key": "{key}" }},
+                            "addr_list": {{ "@type": "adnl.addressList", "addrs": [] }},
+                            "version": -1,
+                            "signature": "{signature}"
+                        }}
+                    ]
+                }}
+            }}
+        }}"#,
+        key = base64.encode(keypair.public_key.as_bytes()),
+        signature = base64.encode(&node.signature),
+    );
+    let config: crate::network_config::ConfigGlobal = json.parse().unwrap();
+
+    let adnl = Arc::new(
+        AdnlNode::bind("127.0.0.1:0", KeyPair::generate(&mut OsRng))
+            .await
+            .unwrap(),
+    );
+    let client = DhtClient::from_config(adnl.clone(), &config).unwrap();
+    assert_eq!(client.nodes(), vec![node]);
+
+    let config: crate::network_config::ConfigGlobal = r#"{"liteservers": []}"#.parse().unwrap();
+    assert!(matches!(
+        DhtClient::from_config(adnl, &config),
+        Err(DhtError::NoNodes)
+    ));
+}
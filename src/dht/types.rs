@@ -0,0 +1,494 @@
+use derivative::Derivative;
+use sha2::{Digest, Sha256};
+use tl_proto::{BoxedConstructor, BoxedWrapper, TlRead, TlWrite};
+
+use crate::adnl::AdnlAddress;
+use crate::adnl::crypto::tl::PublicKeyOwned;
+use crate::adnl::crypto::{KeyPair, PublicKey};
+use crate::adnl::udp::AdnlAddressList;
+use crate::tl::common::Int256;
+use crate::tl::utils::{fmt_bytes, fmt_string};
+
+use super::DhtError;
+
+/// Longest key name accepted by TON nodes.
+pub const DHT_MAX_NAME_BYTES: usize = 127;
+/// Largest key index accepted by TON nodes.
+pub const DHT_MAX_KEY_INDEX: i32 = 15;
+/// Largest value accepted by TON nodes.
+pub const DHT_MAX_VALUE_BYTES: usize = 768;
+/// Key name under which nodes publish their ADNL address list.
+pub const DHT_ADDRESS_KEY_NAME: &str = "address";
+
+/// dht.node id:PublicKey addr_list:adnl.addressList version:int signature:bytes = dht.Node;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct DhtNode {
+    pub id: PublicKeyOwned,
+    pub addr_list: AdnlAddressList,
+    pub version: i32,
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub signature: Vec<u8>,
+}
+
+impl BoxedConstructor for DhtNode {
+    const TL_ID: u32 = 0x84533248;
+}
+
+impl DhtNode {
+    /// Creates a node record for `keypair` signed over the boxed node with
+    /// an empty `signature`.
+    pub fn signed(keypair: &KeyPair, addr_list: AdnlAddressList, version: i32) -> Self {
+        let mut node = Self {
+            id: keypair.public_key.as_tl().as_equivalent_owned(),
+            addr_list,
+            version,
+            signature: Vec::new(),
+        };
+        node.signature = keypair
+            .sign_raw(&tl_proto::serialize(node.as_boxed()))
+            .to_vec();
+        node
+    }
+
+    /// Returns the node ed25519 key.
+    pub fn public_key(&self) -> Result<PublicKey, DhtError> {
+        PublicKey::from_tl(self.id.as_equivalent_ref())
+            .ok_or_else(|| DhtError::InvalidNode("node id is not an ed25519 key".to_string()))
+    }
+
+    /// ADNL id of the node, which is also its position in the DHT key space.
+    pub fn adnl_id(&self) -> AdnlAddress {
+        AdnlAddress::from(short_id(&self.id))
+    }
+
+    /// Checks the node signature and returns the node key.
+    pub fn verify(&self) -> Result<PublicKey, DhtError> {
+        let public_key = self.public_key()?;
+        let unsigned = Self {
+            signature: Vec::new(),
+            ..self.clone()
+        };
+        if verify_signature(&public_key, &unsigned.as_boxed(), &self.signature) {
+            Ok(public_key)
+        } else {
+            Err(DhtError::InvalidNode("bad node signature".to_string()))
+        }
+    }
+}
+
+/// dht.nodes nodes:(vector dht.node) = dht.Nodes;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq, Default)]
+pub struct DhtNodes {
+    pub nodes: Vec<DhtNode>,
+}
+
+impl BoxedConstructor for DhtNodes {
+    const TL_ID: u32 = 0x7974a0be;
+}
+
+/// dht.key id:int256 name:bytes idx:int = dht.Key;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct DhtKey {
+    pub id: Int256,
+    #[derivative(Debug(format_with = "fmt_string"))]
+    pub name: Vec<u8>,
+    pub idx: i32,
+}
+
+impl BoxedConstructor for DhtKey {
+    const TL_ID: u32 = 0xf667de8f;
+}
+
+impl DhtKey {
+    pub fn new(id: [u8; 32], name: &str, idx: i32) -> Self {
+        Self {
+            id: Int256(id),
+            name: name.as_bytes().to_vec(),
+            idx,
+        }
+    }
+
+    /// Key of the address list published by `adnl_id`.
+    pub fn address(adnl_id: &AdnlAddress) -> Self {
+        Self::new(adnl_id.to_bytes(), DHT_ADDRESS_KEY_NAME, 0)
+    }
+
+    /// Key id used for lookups: `sha256` of the boxed key.
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(tl_proto::serialize(self.as_boxed())).into()
+    }
+
+    /// Checks the name and index limits enforced by TON nodes.
+    pub fn check(&self) -> Result<(), DhtError> {
+        if self.name.is_empty() || self.name.len() > DHT_MAX_NAME_BYTES {
+            return Err(DhtError::InvalidValue(format!(
+                "key name length {} is out of range",
+                self.name.len()
+            )));
+        }
+        if !(0..=DHT_MAX_KEY_INDEX).contains(&self.idx) {
+            return Err(DhtError::InvalidValue(format!(
+                "key index {} is out of range",
+                self.idx
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// `dht.UpdateRule`: who may overwrite a value.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, Copy, PartialEq, Eq)]
+#[tl(boxed)]
+pub enum DhtUpdateRule {
+    /// dht.updateRule.signature = dht.UpdateRule;
+    #[tl(id = 0xcc9f31f7)]
+    Signature,
+    /// dht.updateRule.anybody = dht.UpdateRule;
+    #[tl(id = 0x61578e14)]
+    Anybody,
+    /// dht.updateRule.overlayNodes = dht.UpdateRule;
+    #[tl(id = 0x26779383)]
+    OverlayNodes,
+}
+
+/// dht.keyDescription key:dht.key id:PublicKey update_rule:dht.UpdateRule signature:bytes = dht.KeyDescription;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct DhtKeyDescription {
+    pub key: DhtKey,
+    pub id: PublicKeyOwned,
+    pub update_rule: DhtUpdateRule,
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub signature: Vec<u8>,
+}
+
+impl BoxedConstructor for DhtKeyDescription {
+    const TL_ID: u32 = 0x281d4e05;
+}
+
+/// dht.value key:dht.keyDescription value:bytes ttl:int signature:bytes = dht.Value;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct DhtValue {
+    pub key: DhtKeyDescription,
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub value: Vec<u8>,
+    /// Unix time after which the value is dropped.
+    pub ttl: i32,
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub signature: Vec<u8>,
+}
+
+impl BoxedConstructor for DhtValue {
+    const TL_ID: u32 = 0x90ad27cb;
+}
+
+impl DhtValue {
+    /// Creates a `dht.updateRule.signature` value owned by `keypair`.
+    ///
+    /// `key.id` is replaced with the ADNL id of `keypair`, and both the key
+    /// description and the value are signed.
+    pub fn signed(mut key: DhtKey, value: Vec<u8>, ttl: i32, keypair: &KeyPair) -> Self {
+        key.id = Int256(AdnlAddress::from(&keypair.public_key).to_bytes());
+        let mut description = DhtKeyDescription {
+            key,
+            id: keypair.public_key.as_tl().as_equivalent_owned(),
+            update_rule: DhtUpdateRule::Signature,
+            signature: Vec::new(),
+        };
+        description.signature = keypair
+            .sign_raw(&tl_proto::serialize(description.as_boxed()))
+            .to_vec();
+        let mut value = Self {
+            key: description,
+            value,
+            ttl,
+            signature: Vec::new(),
+        };
+        value.signature = keypair
+            .sign_raw(&tl_proto::serialize(value.as_boxed()))
+            .to_vec();
+        value
+    }
+
+    /// Creates an address list value published by `keypair`.
+    pub fn address_list(addr_list: &AdnlAddressList, ttl: i32, keypair: &KeyPair) -> Self {
+        let id = AdnlAddress::from(&keypair.public_key);
+        Self::signed(
+            DhtKey::address(&id),
+            tl_proto::serialize(addr_list.as_boxed()),
+            ttl,
+            keypair,
+        )
+    }
+
+    /// Key id this value is stored under.
+    pub fn key_hash(&self) -> [u8; 32] {
+        self.key.key.hash()
+    }
+
+    /// Validates the value the way TON nodes do before storing or returning
+    /// it.
+    ///
+    /// Checks the key limits, that `key.id` is the short id of the
+    /// description key, the `ttl` against `now`, and the signatures required
+    /// by the update rule. For `dht.updateRule.overlayNodes` every
+    /// `overlay.node` in the value must be signed for the overlay.
+    pub fn verify(&self, now: i32) -> Result<(), DhtError> {
+        let description = &self.key;
+        description.key.check()?;
+        if self.ttl <= now {
+            return Err(DhtError::InvalidValue(format!(
+                "value expired at {}",
+                self.ttl
+            )));
+        }
+        if self.value.len() > DHT_MAX_VALUE_BYTES {
+            return Err(DhtError::InvalidValue(format!(
+                "value has {} bytes",
+                self.value.len()
+            )));
+        }
+        if short_id(&description.id) != description.key.id.0 {
+            return Err(DhtError::InvalidValue(
+                "key id does not match the description key".to_string(),
+            ));
+        }
+
+        match description.update_rule {
+            DhtUpdateRule::Signature => {
+                let public_key = PublicKey::from_tl(description.id.as_equivalent_ref())
+                    .ok_or_else(|| {
+                        DhtError::InvalidValue("signed value needs an ed25519 key".to_string())
+                    })?;
+                let unsigned_description = DhtKeyDescription {
+                    signature: Vec::new(),
+                    ..description.clone()
+                };
+                if !verify_signature(
+                    &public_key,
+                    &unsigned_description.as_boxed(),
+                    &description.signature,
+                ) {
+                    return Err(DhtError::InvalidValue(
+                        "bad key description signature".to_string(),
+                    ));
+                }
+                let unsigned = Self {
+                    signature: Vec::new(),
+                    ..self.clone()
+                };
+                if !verify_signature(&public_key, &unsigned.as_boxed(), &self.signature) {
+                    return Err(DhtError::InvalidValue("bad value signature".to_string()));
+                }
+            }
+            DhtUpdateRule::Anybody => {
+                if !description.signature.is_empty() || !self.signature.is_empty() {
+                    return Err(DhtError::InvalidValue(
+                        "`anybody` value must not be signed".to_string(),
+                    ));
+                }
+            }
+            DhtUpdateRule::OverlayNodes => {
+                if !matches!(description.id, PublicKeyOwned::Overlay { .. })
+                    || !description.signature.is_empty()
+                    || !self.signature.is_empty()
+                {
+                    return Err(DhtError::InvalidValue(
+                        "overlay nodes value must be unsigned and keyed by an overlay".to_string(),
+                    ));
+                }
+                let nodes = tl_proto::deserialize::<BoxedWrapper<OverlayNodes>>(&self.value)?.0;
+                for node in &nodes.nodes {
+                    node.verify(&description.key.id)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the boxed `adnl.addressList` published under an address key
+    /// and returns it with the publishing node key.
+    pub fn to_address_list(&self) -> Result<(PublicKey, AdnlAddressList), DhtError> {
+        if self.key.key.name != DHT_ADDRESS_KEY_NAME.as_bytes() {
+            return Err(DhtError::InvalidValue(
+                "value is not an address list".to_string(),
+            ));
+        }
+        let public_key = PublicKey::from_tl(self.key.id.as_equivalent_ref())
+            .ok_or_else(|| DhtError::InvalidValue("address owner is not ed25519".to_string()))?;
+        let addr_list = tl_proto::deserialize::<BoxedWrapper<AdnlAddressList>>(&self.value)?.0;
+        Ok((public_key, addr_list))
+    }
+}
+
+/// overlay.node id:PublicKey overlay:int256 version:int signature:bytes = overlay.Node;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct OverlayNode {
+    pub id: PublicKeyOwned,
+    pub overlay: Int256,
+    pub version: i32,
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub signature: Vec<u8>,
+}
+
+impl OverlayNode {
+    /// Creates a member record of `overlay` for `keypair`, signed over
+    /// `overlay.node.toSign`.
+    pub fn signed(keypair: &KeyPair, overlay: Int256, version: i32) -> Self {
+        let id = keypair.public_key.as_tl().as_equivalent_owned();
+        let to_sign = OverlayNodeToSign {
+            id: Int256(short_id(&id)),
+            overlay: overlay.clone(),
+            version,
+        };
+        Self {
+            id,
+            overlay,
+            version,
+            signature: keypair.sign_raw(&tl_proto::serialize(to_sign)).to_vec(),
+        }
+    }
+
+    /// ADNL id of the member.
+    pub fn adnl_id(&self) -> AdnlAddress {
+        AdnlAddress::from(short_id(&self.id))
+    }
+
+    /// Checks that the node belongs to `overlay` and signed
+    /// `overlay.node.toSign`.
+    pub fn verify(&self, overlay: &Int256) -> Result<(), DhtError> {
+        if &self.overlay != overlay {
+            return Err(DhtError::InvalidValue(
+                "overlay node belongs to another overlay".to_string(),
+            ));
+        }
+        let public_key = PublicKey::from_tl(self.id.as_equivalent_ref()).ok_or_else(|| {
+            DhtError::InvalidValue("overlay node id is not an ed25519 key".to_string())
+        })?;
+        let to_sign = OverlayNodeToSign {
+            id: Int256(short_id(&self.id)),
+            overlay: self.overlay.clone(),
+            version: self.version,
+        };
+        if verify_signature(&public_key, &to_sign, &self.signature) {
+            Ok(())
+        } else {
+            Err(DhtError::InvalidValue(
+                "bad overlay node signature".to_string(),
+            ))
+        }
+    }
+}
+
+/// overlay.nodes nodes:(vector overlay.node) = overlay.Nodes;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq, Default)]
+pub struct OverlayNodes {
+    pub nodes: Vec<OverlayNode>,
+}
+
+impl BoxedConstructor for OverlayNodes {
+    const TL_ID: u32 = 0xe487290e;
+}
+
+/// overlay.node.toSign id:adnl.id.short overlay:int256 version:int = overlay.node.ToSign;
+#[derive(TlWrite)]
+#[tl(boxed, id = 0x03d8a8e1)]
+struct OverlayNodeToSign {
+    id: Int256,
+    overlay: Int256,
+    version: i32,
+}
+
+/// DHT queries.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum DhtQuery {
+    /// dht.ping random_id:long = dht.Pong;
+    #[tl(id = 0xcbeb3f18)]
+    Ping { random_id: i64 },
+    /// dht.store value:dht.value = dht.Stored;
+    #[tl(id = 0x34934212)]
+    Store { value: DhtValue },
+    /// dht.findNode key:int256 k:int = dht.Nodes;
+    #[tl(id = 0x6ce2ce6b)]
+    FindNode { key: Int256, k: i32 },
+    /// dht.findValue key:int256 k:int = dht.ValueResult;
+    #[tl(id = 0xae4b6011)]
+    FindValue { key: Int256, k: i32 },
+    /// dht.getSignedAddressList = dht.Node;
+    #[tl(id = 0xa97948ed)]
+    GetSignedAddressList,
+}
+
+/// dht.pong random_id:long = dht.Pong;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, Copy, PartialEq)]
+#[tl(boxed, id = 0x5a8aef81)]
+pub struct DhtPong {
+    pub random_id: i64,
+}
+
+/// dht.stored = dht.Stored;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, Copy, PartialEq)]
+#[tl(boxed, id = 0x7026fb08)]
+pub struct DhtStored;
+
+/// `dht.ValueResult` answer to `dht.findValue`.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum DhtValueResult {
+    /// dht.valueNotFound nodes:dht.nodes = dht.ValueResult;
+    #[tl(id = 0xa2620568)]
+    NotFound { nodes: DhtNodes },
+    /// dht.valueFound value:dht.Value = dht.ValueResult;
+    #[tl(id = 0xe40cf774)]
+    Found {
+        #[tl(with = "boxed_value")]
+        value: DhtValue,
+    },
+}
+
+/// Reads and writes the boxed `dht.Value` of `dht.valueFound`.
+mod boxed_value {
+    use tl_proto::{BoxedConstructor, BoxedWrapper, TlPacket, TlRead, TlResult, TlWrite};
+
+    use super::DhtValue;
+
+    pub fn size_hint(value: &DhtValue) -> usize {
+        value.as_boxed().max_size_hint()
+    }
+
+    pub fn write<P: TlPacket>(value: &DhtValue, packet: &mut P) {
+        value.as_boxed().write_to(packet)
+    }
+
+    pub fn read(packet: &mut &[u8]) -> TlResult<DhtValue> {
+        BoxedWrapper::<DhtValue>::read_from(packet).map(|value| value.0)
+    }
+}
+
+/// Short id of a TL public key: `sha256` of its boxed form.
+pub(crate) fn short_id(key: &PublicKeyOwned) -> [u8; 32] {
+    Sha256::digest(tl_proto::serialize(key)).into()
+}
+
+/// XOR distance between two points of the key space.
+pub(crate) fn xor_distance(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    std::array::from_fn(|i| a[i] ^ b[i])
+}
+
+fn verify_signature<T: TlWrite>(public_key: &PublicKey, data: &T, signature: &[u8]) -> bool {
+    signature
+        .try_into()
+        .is_ok_and(|signature| public_key.verify_raw(&tl_proto::serialize(data), signature))
+}
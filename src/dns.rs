@@ -0,0 +1,20 @@
+//! TEP-81 TON DNS helpers.
+//!
+//! This module encodes domain names into the reversed, zero-terminated label
+//! form expected by `dnsresolve`, and decodes the `DNSRecord` cells returned
+//! by resolver contracts. With the `liteclient` feature, `DnsResolver` walks
+//! `dns_next_resolver` links from the root DNS contract in config param 4.
+//! Auctions, domain ownership changes, and record updates are intentionally
+//! outside this layer.
+
+mod domain;
+mod record;
+#[cfg(feature = "liteclient")]
+mod resolver;
+#[cfg(test)]
+mod tests;
+
+pub use domain::*;
+pub use record::*;
+#[cfg(feature = "liteclient")]
+pub use resolver::*;
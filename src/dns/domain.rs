@@ -0,0 +1,115 @@
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Longest encoded domain accepted by `dnsresolve`, in bytes.
+///
+/// The subdomain is passed as a single-cell slice, so it must fit in 1023
+/// bits.
+pub const DNS_MAX_DOMAIN_BYTES: usize = 127;
+
+/// Record category selected by the `category` argument of `dnsresolve`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DnsCategory {
+    /// Category `0`: the resolver returns its whole record dictionary.
+    All,
+    /// `sha256("wallet")`: `dns_smc_address` wallet record.
+    Wallet,
+    /// `sha256("site")`: `dns_adnl_address` site record.
+    Site,
+    /// `sha256("storage")`: `dns_storage_address` bag record.
+    Storage,
+    /// `sha256("dns_next_resolver")`: `dns_next_resolver` record.
+    NextResolver,
+    /// Any other category hash.
+    Custom([u8; 32]),
+}
+
+impl DnsCategory {
+    /// Returns the 256-bit category key used by resolvers and record
+    /// dictionaries.
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            Self::All => [0; 32],
+            Self::Wallet => category_hash("wallet"),
+            Self::Site => category_hash("site"),
+            Self::Storage => category_hash("storage"),
+            Self::NextResolver => category_hash("dns_next_resolver"),
+            Self::Custom(hash) => *hash,
+        }
+    }
+
+    /// Maps a category key back to a known category when possible.
+    pub fn from_hash(hash: [u8; 32]) -> Self {
+        [
+            Self::All,
+            Self::Wallet,
+            Self::Site,
+            Self::Storage,
+            Self::NextResolver,
+        ]
+        .into_iter()
+        .find(|category| category.hash() == hash)
+        .unwrap_or(Self::Custom(hash))
+    }
+}
+
+fn category_hash(name: &str) -> [u8; 32] {
+    Sha256::digest(name.as_bytes()).into()
+}
+
+/// Errors returned while encoding a domain name for `dnsresolve`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DnsDomainError {
+    #[error("domain {domain:?} contains an empty label")]
+    EmptyLabel { domain: String },
+    #[error("domain {domain:?} contains byte 0x{byte:02x}, expected 0x21..=0x7e")]
+    InvalidByte { domain: String, byte: u8 },
+    #[error("domain {domain:?} encodes to {actual} bytes, expected at most {max}")]
+    TooLong {
+        domain: String,
+        actual: usize,
+        max: usize,
+    },
+}
+
+/// Encodes `domain` into TEP-81 internal form: labels in reverse order, each
+/// terminated by a zero byte.
+///
+/// `"foundation.ton"` becomes `b"ton\0foundation\0"`. A single trailing dot
+/// is ignored, and the root domain (`""` or `"."`) encodes as `b"\0"`.
+pub fn encode_dns_domain(domain: &str) -> Result<Vec<u8>, DnsDomainError> {
+    let trimmed = domain.strip_suffix('.').unwrap_or(domain);
+    if trimmed.is_empty() {
+        return Ok(vec![0]);
+    }
+
+    let mut encoded = Vec::with_capacity(trimmed.len() + 1);
+    for label in trimmed.split('.').rev() {
+        if label.is_empty() {
+            return Err(DnsDomainError::EmptyLabel {
+                domain: domain.to_string(),
+            });
+        }
+        if let Some(&byte) = label
+            .as_bytes()
+            .iter()
+            .find(|byte| !(0x21..=0x7e).contains(*byte))
+        {
+            return Err(DnsDomainError::InvalidByte {
+                domain: domain.to_string(),
+                byte,
+            });
+        }
+        encoded.extend_from_slice(label.as_bytes());
+        encoded.push(0);
+    }
+
+    if encoded.len() > DNS_MAX_DOMAIN_BYTES {
+        return Err(DnsDomainError::TooLong {
+            domain: domain.to_string(),
+            actual: encoded.len(),
+            max: DNS_MAX_DOMAIN_BYTES,
+        });
+    }
+    Ok(encoded)
+}
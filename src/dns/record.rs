@@ -0,0 +1,362 @@
+use crate::tlb::{MsgAddressInt, Result, TlbDeserialize, TlbError, TlbSerialize, ensure_empty};
+use crate::tvm::{Address, Builder, Cell, Slice};
+use std::sync::Arc;
+
+/// TEP-81 `dns_smc_address#9fd3` record tag.
+pub const DNS_SMC_ADDRESS_TAG: u16 = 0x9fd3;
+/// TEP-81 `dns_next_resolver#ba93` record tag.
+pub const DNS_NEXT_RESOLVER_TAG: u16 = 0xba93;
+/// TEP-81 `dns_adnl_address#ad01` record tag.
+pub const DNS_ADNL_ADDRESS_TAG: u16 = 0xad01;
+/// TEP-81 `dns_storage_address#7473` record tag.
+pub const DNS_STORAGE_ADDRESS_TAG: u16 = 0x7473;
+/// Key width of the `dns_dict` category dictionary.
+pub const DNS_CATEGORY_KEY_BITS: usize = 256;
+
+const CAP_METHOD_SEQNO_TAG: u16 = 0x5371;
+const CAP_METHOD_PUBKEY_TAG: u16 = 0x71f4;
+const CAP_IS_WALLET_TAG: u16 = 0x2177;
+const CAP_NAME_TAG: u8 = 0xff;
+const PROTO_HTTP_TAG: u16 = 0x4854;
+/// A referenced text chunk stores an 8-bit length next to its data, so at
+/// most 126 bytes fit in one cell.
+const TEXT_CHUNK_MAX_BYTES: usize = 126;
+
+/// TEP-81 `DNSRecord` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsRecord {
+    /// `dns_smc_address`: smart-contract (usually wallet) address.
+    ///
+    /// `capabilities` is `None` when `flags` bit 0 is clear.
+    SmcAddress {
+        address: Address,
+        capabilities: Option<Vec<DnsSmcCapability>>,
+    },
+    /// `dns_next_resolver`: resolver for the remaining subdomain.
+    NextResolver(Address),
+    /// `dns_adnl_address`: ADNL address of a TON Site or service.
+    ///
+    /// `protocols` is `None` when `flags` bit 0 is clear.
+    AdnlAddress {
+        address: [u8; 32],
+        protocols: Option<Vec<DnsProtocol>>,
+    },
+    /// `dns_storage_address`: TON Storage bag id.
+    StorageAddress([u8; 32]),
+}
+
+/// `SmcCapability` entry of a `dns_smc_address` record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsSmcCapability {
+    /// `cap_method_seqno#5371`.
+    Seqno,
+    /// `cap_method_pubkey#71f4`.
+    PublicKey,
+    /// `cap_is_wallet#2177`.
+    IsWallet,
+    /// `cap_name#ff name:Text`.
+    Name(String),
+}
+
+/// `Protocol` entry of a `dns_adnl_address` record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsProtocol {
+    /// `proto_http#4854`.
+    Http,
+}
+
+impl DnsRecord {
+    /// Returns the contract address of a `dns_smc_address` record.
+    pub fn smc_address(&self) -> Option<&Address> {
+        match self {
+            Self::SmcAddress { address, .. } => Some(address),
+            _ => None,
+        }
+    }
+
+    /// Returns the resolver address of a `dns_next_resolver` record.
+    pub fn next_resolver(&self) -> Option<&Address> {
+        match self {
+            Self::NextResolver(address) => Some(address),
+            _ => None,
+        }
+    }
+
+    /// Returns the ADNL address of a `dns_adnl_address` record.
+    pub fn adnl_address(&self) -> Option<[u8; 32]> {
+        match self {
+            Self::AdnlAddress { address, .. } => Some(*address),
+            _ => None,
+        }
+    }
+
+    /// Returns the bag id of a `dns_storage_address` record.
+    pub fn storage_bag_id(&self) -> Option<[u8; 32]> {
+        match self {
+            Self::StorageAddress(bag_id) => Some(*bag_id),
+            _ => None,
+        }
+    }
+
+    pub fn to_cell(&self) -> Result<Arc<Cell>> {
+        TlbSerialize::to_cell(self)
+    }
+
+    /// Decodes a record cell and rejects trailing bits or references.
+    pub fn from_cell(cell: Arc<Cell>) -> Result<Self> {
+        let mut slice = Slice::new(cell);
+        let record = Self::load_tlb(&mut slice)?;
+        ensure_empty(&slice)?;
+        Ok(record)
+    }
+}
+
+impl TlbSerialize for DnsRecord {
+    fn store_tlb(&self, builder: &mut Builder) -> Result<()> {
+        match self {
+            Self::SmcAddress {
+                address,
+                capabilities,
+            } => {
+                builder.store_u16(DNS_SMC_ADDRESS_TAG)?;
+                MsgAddressInt::std(address.clone()).store_tlb(builder)?;
+                builder.store_u8(capabilities.is_some() as u8)?;
+                for capability in capabilities.iter().flatten() {
+                    builder.store_bit(true)?;
+                    store_capability(builder, capability)?;
+                }
+                if capabilities.is_some() {
+                    builder.store_bit(false)?;
+                }
+            }
+            Self::NextResolver(address) => {
+                builder.store_u16(DNS_NEXT_RESOLVER_TAG)?;
+                MsgAddressInt::std(address.clone()).store_tlb(builder)?;
+            }
+            Self::AdnlAddress { address, protocols } => {
+                builder.store_u16(DNS_ADNL_ADDRESS_TAG)?;
+                builder.store_bytes(address)?;
+                builder.store_u8(protocols.is_some() as u8)?;
+                for protocol in protocols.iter().flatten() {
+                    builder.store_bit(true)?;
+                    match protocol {
+                        DnsProtocol::Http => builder.store_u16(PROTO_HTTP_TAG)?,
+                    };
+                }
+                if protocols.is_some() {
+                    builder.store_bit(false)?;
+                }
+            }
+            Self::StorageAddress(bag_id) => {
+                builder.store_u16(DNS_STORAGE_ADDRESS_TAG)?;
+                builder.store_bytes(bag_id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TlbDeserialize for DnsRecord {
+    fn load_tlb(slice: &mut Slice) -> Result<Self> {
+        match slice.load_u16()? {
+            DNS_SMC_ADDRESS_TAG => {
+                let address = load_std_address(slice)?;
+                let capabilities = if load_list_flags(slice)? {
+                    let mut capabilities = Vec::new();
+                    while slice.load_bit()? {
+                        capabilities.push(load_capability(slice)?);
+                    }
+                    Some(capabilities)
+                } else {
+                    None
+                };
+                Ok(Self::SmcAddress {
+                    address,
+                    capabilities,
+                })
+            }
+            DNS_NEXT_RESOLVER_TAG => Ok(Self::NextResolver(load_std_address(slice)?)),
+            DNS_ADNL_ADDRESS_TAG => {
+                let address = load_bits256(slice)?;
+                let protocols = if load_list_flags(slice)? {
+                    let mut protocols = Vec::new();
+                    while slice.load_bit()? {
+                        protocols.push(match slice.load_u16()? {
+                            PROTO_HTTP_TAG => DnsProtocol::Http,
+                            tag => {
+                                return Err(schema_error(format!(
+                                    "unknown protocol tag 0x{tag:04x}"
+                                )));
+                            }
+                        });
+                    }
+                    Some(protocols)
+                } else {
+                    None
+                };
+                Ok(Self::AdnlAddress { address, protocols })
+            }
+            DNS_STORAGE_ADDRESS_TAG => Ok(Self::StorageAddress(load_bits256(slice)?)),
+            tag => Err(schema_error(format!("unknown record tag 0x{tag:04x}"))),
+        }
+    }
+}
+
+/// Decodes a `dns_dict` returned by `dnsresolve` for category `0`.
+///
+/// The cell is the root of a non-empty `Hashmap 256 ^DNSRecord`; entries are
+/// returned in key order with their raw 256-bit category keys.
+pub fn decode_dns_record_dict(root: Arc<Cell>) -> Result<Vec<([u8; 32], DnsRecord)>> {
+    let mut builder = Builder::new();
+    builder.store_bit(true)?;
+    builder.store_ref(root)?;
+    let dict = Slice::new(builder.build()?)
+        .load_hashmap_e_with(DNS_CATEGORY_KEY_BITS, |slice| slice.load_reference())?;
+    dict.iter()
+        .map(|(key, value)| {
+            let category = key
+                .data()
+                .try_into()
+                .map_err(|_| schema_error("category key must be 256 bits".to_string()))?;
+            Ok((category, DnsRecord::from_cell(value.clone())?))
+        })
+        .collect()
+}
+
+fn store_capability(builder: &mut Builder, capability: &DnsSmcCapability) -> Result<()> {
+    match capability {
+        DnsSmcCapability::Seqno => {
+            builder.store_u16(CAP_METHOD_SEQNO_TAG)?;
+        }
+        DnsSmcCapability::PublicKey => {
+            builder.store_u16(CAP_METHOD_PUBKEY_TAG)?;
+        }
+        DnsSmcCapability::IsWallet => {
+            builder.store_u16(CAP_IS_WALLET_TAG)?;
+        }
+        DnsSmcCapability::Name(name) => {
+            builder.store_u8(CAP_NAME_TAG)?;
+            store_text(builder, name.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn load_capability(slice: &mut Slice) -> Result<DnsSmcCapability> {
+    let head = slice.load_u8()?;
+    if head == CAP_NAME_TAG {
+        let name = load_text(slice)?;
+        return String::from_utf8(name)
+            .map(DnsSmcCapability::Name)
+            .map_err(|error| schema_error(format!("cap_name is not UTF-8: {error}")));
+    }
+    match u16::from_be_bytes([head, slice.load_u8()?]) {
+        CAP_METHOD_SEQNO_TAG => Ok(DnsSmcCapability::Seqno),
+        CAP_METHOD_PUBKEY_TAG => Ok(DnsSmcCapability::PublicKey),
+        CAP_IS_WALLET_TAG => Ok(DnsSmcCapability::IsWallet),
+        tag => Err(schema_error(format!("unknown capability tag 0x{tag:04x}"))),
+    }
+}
+
+/// Stores TL-B `Text`, splitting bytes into referenced `TextChunks`.
+///
+/// The inline first chunk leaves room for its two length bytes and the
+/// `cap_list_nil` bit that normally follows.
+fn store_text(builder: &mut Builder, bytes: &[u8]) -> Result<()> {
+    let first_len = bytes
+        .len()
+        .min(TEXT_CHUNK_MAX_BYTES)
+        .min(builder.available_bits().saturating_sub(17) / 8);
+    let rest = &bytes[first_len..];
+    let chunks = if bytes.is_empty() {
+        0
+    } else {
+        1 + rest.len().div_ceil(TEXT_CHUNK_MAX_BYTES)
+    };
+    if chunks > u8::MAX as usize {
+        return Err(schema_error(format!(
+            "text needs {chunks} chunks, expected at most 255"
+        )));
+    }
+    builder.store_u8(chunks as u8)?;
+    if chunks == 0 {
+        return Ok(());
+    }
+    builder.store_u8(first_len as u8)?;
+    builder.store_bytes(&bytes[..first_len])?;
+    if !rest.is_empty() {
+        builder.store_ref(text_chunk_cell(rest)?)?;
+    }
+    Ok(())
+}
+
+fn text_chunk_cell(bytes: &[u8]) -> Result<Arc<Cell>> {
+    let (chunk, rest) = bytes.split_at(bytes.len().min(TEXT_CHUNK_MAX_BYTES));
+    let mut builder = Builder::new();
+    builder.store_u8(chunk.len() as u8)?;
+    builder.store_bytes(chunk)?;
+    if !rest.is_empty() {
+        builder.store_ref(text_chunk_cell(rest)?)?;
+    }
+    Ok(builder.build()?)
+}
+
+/// Loads TL-B `Text`, following `TextChunkRef` references.
+fn load_text(slice: &mut Slice) -> Result<Vec<u8>> {
+    let chunks = slice.load_u8()?;
+    let mut bytes = Vec::new();
+    if chunks == 0 {
+        return Ok(bytes);
+    }
+    let len = slice.load_u8()? as usize;
+    bytes.extend(slice.load_bytes(len)?);
+    if chunks == 1 {
+        return Ok(bytes);
+    }
+    let mut chunk = Slice::new(slice.load_reference()?);
+    for index in 1..chunks {
+        let len = chunk.load_u8()? as usize;
+        bytes.extend(chunk.load_bytes(len)?);
+        if index + 1 < chunks {
+            let next = chunk.load_reference()?;
+            ensure_empty(&chunk)?;
+            chunk = Slice::new(next);
+        }
+    }
+    ensure_empty(&chunk)?;
+    Ok(bytes)
+}
+
+fn load_list_flags(slice: &mut Slice) -> Result<bool> {
+    match slice.load_u8()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        flags => Err(schema_error(format!("flags {flags} must be at most 1"))),
+    }
+}
+
+fn load_std_address(slice: &mut Slice) -> Result<Address> {
+    match MsgAddressInt::load_tlb(slice)? {
+        MsgAddressInt::Std {
+            anycast: None,
+            address,
+        } => Ok(address),
+        _ => Err(schema_error(
+            "record address must be a standard address without anycast".to_string(),
+        )),
+    }
+}
+
+fn load_bits256(slice: &mut Slice) -> Result<[u8; 32]> {
+    Ok(slice
+        .load_bytes(32)?
+        .try_into()
+        .expect("load_bytes returns the requested length"))
+}
+
+fn schema_error(message: String) -> TlbError {
+    TlbError::CustomSchema {
+        schema: "DNSRecord",
+        message,
+    }
+}
@@ -0,0 +1,265 @@
+//! Recursive `dnsresolve` walker over a `ContractProvider`.
+
+use super::{DnsCategory, DnsRecord, decode_dns_record_dict, encode_dns_domain};
+use crate::contracts::{Contract, ContractError, ContractProvider};
+use crate::tl::BlockIdExt;
+use crate::tvm::{Address, Builder, Cell, Slice, TvmStack, TvmStackEntry};
+use num_bigint::{BigInt, Sign};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Config parameter holding the root DNS contract address hash.
+pub const DNS_ROOT_CONFIG_PARAM: u32 = 4;
+/// Maximum number of `dnsresolve` calls made for one lookup.
+pub const DNS_MAX_RESOLVER_HOPS: usize = 16;
+
+/// Errors reported by `DnsResolver` besides provider and get-method failures.
+#[derive(Debug, Error)]
+pub enum DnsResolverError {
+    #[error("config param 4 with the root DNS address is missing")]
+    MissingRootConfig,
+    #[error("config param 4 is malformed: {0}")]
+    MalformedRootConfig(String),
+    #[error("dnsresolve stack is malformed: {0}")]
+    MalformedStack(String),
+    #[error("dnsresolve resolved {resolved_bits} bits of a {subdomain_bits}-bit subdomain")]
+    InvalidResolvedBits {
+        resolved_bits: BigInt,
+        subdomain_bits: usize,
+    },
+    #[error("partially resolved domain did not return a dns_next_resolver record")]
+    MissingNextResolver,
+    #[error("DNS lookup exceeded {0} resolver hops")]
+    TooManyHops(usize),
+}
+
+/// TEP-81 resolver that follows `dns_next_resolver` records from a root DNS
+/// contract.
+///
+/// Every `dnsresolve` call of one lookup runs at the same masterchain block.
+pub struct DnsResolver<'a, P: ContractProvider + ?Sized> {
+    provider: &'a mut P,
+    root: Address,
+}
+
+impl<'a, P: ContractProvider + ?Sized> DnsResolver<'a, P> {
+    /// Creates a resolver that starts lookups at `root`.
+    pub fn new(provider: &'a mut P, root: Address) -> Self {
+        Self { provider, root }
+    }
+
+    /// Creates a resolver rooted at the DNS contract from config param 4 at
+    /// the provider's latest masterchain block.
+    pub async fn from_config(provider: &'a mut P) -> Result<Self, ContractError<P::Error>> {
+        let block = provider
+            .get_masterchain_info()
+            .await
+            .map_err(ContractError::Provider)?
+            .last;
+        let param = provider
+            .get_config_param(block, DNS_ROOT_CONFIG_PARAM)
+            .await
+            .map_err(ContractError::Provider)?
+            .ok_or_else(|| ContractError::decode(DnsResolverError::MissingRootConfig))?;
+        let root = dns_root_address_from_config(param).map_err(ContractError::decode)?;
+        Ok(Self::new(provider, root))
+    }
+
+    pub fn root(&self) -> &Address {
+        &self.root
+    }
+
+    /// Resolves `domain` to a single record of `category`.
+    ///
+    /// Returns `None` when a resolver reports the domain or record as absent.
+    /// `DnsCategory::All` returns a dictionary; use `resolve_all` instead.
+    pub async fn resolve(
+        &mut self,
+        domain: &str,
+        category: DnsCategory,
+    ) -> Result<Option<DnsRecord>, ContractError<P::Error>> {
+        self.resolve_raw(domain, category)
+            .await?
+            .map(DnsRecord::from_cell)
+            .transpose()
+            .map_err(ContractError::decode)
+    }
+
+    /// Resolves every record of `domain` with category `0`.
+    pub async fn resolve_all(
+        &mut self,
+        domain: &str,
+    ) -> Result<Vec<(DnsCategory, DnsRecord)>, ContractError<P::Error>> {
+        let Some(dict) = self.resolve_raw(domain, DnsCategory::All).await? else {
+            return Ok(Vec::new());
+        };
+        Ok(decode_dns_record_dict(dict)
+            .map_err(ContractError::decode)?
+            .into_iter()
+            .map(|(category, record)| (DnsCategory::from_hash(category), record))
+            .collect())
+    }
+
+    /// Resolves the `wallet` record of `domain` to a contract address.
+    pub async fn resolve_wallet(
+        &mut self,
+        domain: &str,
+    ) -> Result<Option<Address>, ContractError<P::Error>> {
+        Ok(self
+            .resolve(domain, DnsCategory::Wallet)
+            .await?
+            .and_then(|record| record.smc_address().cloned()))
+    }
+
+    /// Resolves the `site` record of `domain` to an ADNL address.
+    pub async fn resolve_adnl_address(
+        &mut self,
+        domain: &str,
+    ) -> Result<Option<[u8; 32]>, ContractError<P::Error>> {
+        Ok(self
+            .resolve(domain, DnsCategory::Site)
+            .await?
+            .and_then(|record| record.adnl_address()))
+    }
+
+    /// Resolves the `storage` record of `domain` to a TON Storage bag id.
+    pub async fn resolve_storage_bag_id(
+        &mut self,
+        domain: &str,
+    ) -> Result<Option<[u8; 32]>, ContractError<P::Error>> {
+        Ok(self
+            .resolve(domain, DnsCategory::Storage)
+            .await?
+            .and_then(|record| record.storage_bag_id()))
+    }
+
+    /// Runs the recursive lookup and returns the raw result cell of the last
+    /// resolver.
+    ///
+    /// A partially resolved subdomain continues at the returned
+    /// `dns_next_resolver`. A fully resolved subdomain that still yields a
+    /// `dns_next_resolver` (for example a `.ton` collection pointing at its
+    /// item) is followed with the self-reference subdomain `"\0"`, unless the
+    /// caller asked for `DnsCategory::NextResolver` itself.
+    pub async fn resolve_raw(
+        &mut self,
+        domain: &str,
+        category: DnsCategory,
+    ) -> Result<Option<Arc<Cell>>, ContractError<P::Error>> {
+        let mut subdomain = encode_dns_domain(domain).map_err(ContractError::decode)?;
+        let mut resolver = self.root.clone();
+        let block = self
+            .provider
+            .get_masterchain_info()
+            .await
+            .map_err(ContractError::Provider)?
+            .last;
+
+        for _ in 0..DNS_MAX_RESOLVER_HOPS {
+            let Some((resolved_bytes, cell)) = self
+                .dnsresolve(block.clone(), resolver, &subdomain, category)
+                .await?
+            else {
+                return Ok(None);
+            };
+            let rest = &subdomain[resolved_bytes..];
+            let next = match DnsRecord::from_cell(cell.clone()) {
+                Ok(DnsRecord::NextResolver(next)) => Some(next),
+                _ => None,
+            };
+
+            if rest.is_empty() {
+                match next {
+                    Some(next) if category != DnsCategory::NextResolver => {
+                        resolver = next;
+                        subdomain = vec![0];
+                    }
+                    _ => return Ok(Some(cell)),
+                }
+            } else {
+                resolver = next
+                    .ok_or_else(|| ContractError::decode(DnsResolverError::MissingNextResolver))?;
+                subdomain = rest.to_vec();
+            }
+        }
+
+        Err(ContractError::decode(DnsResolverError::TooManyHops(
+            DNS_MAX_RESOLVER_HOPS,
+        )))
+    }
+
+    /// Runs one `dnsresolve(subdomain, category)` call and returns the number
+    /// of resolved bytes with the result cell, or `None` when nothing was
+    /// resolved.
+    async fn dnsresolve(
+        &mut self,
+        block: BlockIdExt,
+        resolver: Address,
+        subdomain: &[u8],
+        category: DnsCategory,
+    ) -> Result<Option<(usize, Arc<Cell>)>, ContractError<P::Error>> {
+        let mut builder = Builder::new();
+        builder
+            .store_bytes(subdomain)
+            .map_err(ContractError::decode)?;
+        let stack = TvmStack::new(vec![
+            TvmStackEntry::Slice(builder.build().map_err(ContractError::decode)?),
+            TvmStackEntry::Int(BigInt::from_bytes_be(Sign::Plus, &category.hash())),
+        ]);
+        let result = Contract::new(&mut *self.provider, resolver)
+            .run_get_method_by_name_typed(block, "dnsresolve", stack)
+            .await?;
+        decode_dnsresolve_result(result, subdomain.len()).map_err(ContractError::decode)
+    }
+}
+
+/// Decodes config param 4 (`dns_root_addr:bits256`) into the masterchain
+/// root DNS address.
+pub fn dns_root_address_from_config(param: Arc<Cell>) -> Result<Address, DnsResolverError> {
+    let mut slice = Slice::new(param);
+    let hash = slice
+        .load_bytes(32)
+        .map_err(|error| DnsResolverError::MalformedRootConfig(error.to_string()))?;
+    Ok(Address::new(
+        -1,
+        hash.try_into()
+            .expect("load_bytes returns the requested length"),
+    ))
+}
+
+/// Decodes a `dnsresolve` result stack `(int resolved_bits, cell result)`.
+///
+/// Returns `None` when no bits were resolved or the result cell is null.
+pub fn decode_dnsresolve_result(
+    stack: Vec<TvmStackEntry>,
+    subdomain_bytes: usize,
+) -> Result<Option<(usize, Arc<Cell>)>, DnsResolverError> {
+    let [resolved_bits, result]: [TvmStackEntry; 2] =
+        stack.try_into().map_err(|stack: Vec<TvmStackEntry>| {
+            DnsResolverError::MalformedStack(format!("{} entries, expected 2", stack.len()))
+        })?;
+    let TvmStackEntry::Int(resolved_bits) = resolved_bits else {
+        return Err(DnsResolverError::MalformedStack(
+            "resolved bit count is not an integer".to_string(),
+        ));
+    };
+    let subdomain_bits = subdomain_bytes * 8;
+    let invalid = || DnsResolverError::InvalidResolvedBits {
+        resolved_bits: resolved_bits.clone(),
+        subdomain_bits,
+    };
+    let bits = usize::try_from(&resolved_bits).map_err(|_| invalid())?;
+    if bits % 8 != 0 || bits > subdomain_bits {
+        return Err(invalid());
+    }
+    if bits == 0 {
+        return Ok(None);
+    }
+    match result {
+        TvmStackEntry::Null => Ok(None),
+        TvmStackEntry::Cell(cell) => Ok(Some((bits / 8, cell))),
+        _ => Err(DnsResolverError::MalformedStack(
+            "result is neither a cell nor null".to_string(),
+        )),
+    }
+}
@@ -0,0 +1,446 @@
+use super::*;
+use crate::tlb::{MsgAddressInt, TlbSerialize};
+use crate::tvm::{Address, BitKey, Builder, Cell, HashmapE};
+use std::sync::Arc;
+
+fn address(byte: u8) -> Address {
+    Address::new(0, [byte; 32])
+}
+
+#[test]
+fn encodes_domains_as_reversed_zero_terminated_labels() {
+    assert_eq!(
+        encode_dns_domain("foundation.ton").unwrap(),
+        b"ton\0foundation\0".to_vec()
+    );
+    assert_eq!(
+        encode_dns_domain("wallet.foundation.ton.").unwrap(),
+        b"ton\0foundation\0wallet\0".to_vec()
+    );
+    assert_eq!(encode_dns_domain("").unwrap(), vec![0]);
+    assert_eq!(encode_dns_domain(".").unwrap(), vec![0]);
+}
+
+#[test]
+fn rejects_invalid_domains() {
+    assert!(matches!(
+        encode_dns_domain("foundation..ton"),
+        Err(DnsDomainError::EmptyLabel { .. })
+    ));
+    assert!(matches!(
+        encode_dns_domain("my site.ton"),
+        Err(DnsDomainError::InvalidByte { byte: b' ', .. })
+    ));
+    assert!(matches!(
+        encode_dns_domain(&format!("{}.ton", "a".repeat(123))),
+        Err(DnsDomainError::TooLong { actual: 128, .. })
+    ));
+}
+
+#[test]
+fn category_hashes_match_tep81_values() {
+    assert_eq!(
+        hex::encode(DnsCategory::Wallet.hash()),
+        "e8d44050873dba865aa7c170ab4cce64d90839a34dcfd6cf71d14e0205443b1b"
+    );
+    assert_eq!(DnsCategory::All.hash(), [0; 32]);
+    assert_eq!(
+        DnsCategory::from_hash(DnsCategory::Storage.hash()),
+        DnsCategory::Storage
+    );
+    assert_eq!(
+        DnsCategory::from_hash([7; 32]),
+        DnsCategory::Custom([7; 32])
+    );
+}
+
+#[test]
+fn records_roundtrip_through_cells() {
+    let records = [
+        DnsRecord::SmcAddress {
+            address: address(0x11),
+            capabilities: None,
+        },
+        DnsRecord::SmcAddress {
+            address: address(0x11),
+            capabilities: Some(vec![
+                DnsSmcCapability::IsWallet,
+                DnsSmcCapability::Seqno,
+                DnsSmcCapability::PublicKey,
+                DnsSmcCapability::Name("x".repeat(300)),
+            ]),
+        },
+        DnsRecord::NextResolver(address(0x22)),
+        DnsRecord::AdnlAddress {
+            address: [0x33; 32],
+            protocols: Some(vec![DnsProtocol::Http]),
+        },
+        DnsRecord::AdnlAddress {
+            address: [0x33; 32],
+            protocols: None,
+        },
+        DnsRecord::StorageAddress([0x44; 32]),
+    ];
+    for record in records {
+        assert_eq!(
+            DnsRecord::from_cell(record.to_cell().unwrap()).unwrap(),
+            record
+        );
+    }
+}
+
+#[test]
+fn decodes_wallet_record_layout() {
+    let mut builder = Builder::new();
+    builder.store_u16(DNS_SMC_ADDRESS_TAG).unwrap();
+    MsgAddressInt::std(address(0x55))
+        .store_tlb(&mut builder)
+        .unwrap();
+    builder.store_u8(0).unwrap();
+    let record = DnsRecord::from_cell(builder.build().unwrap()).unwrap();
+    assert_eq!(record.smc_address(), Some(&address(0x55)));
+}
+
+#[test]
+fn rejects_unknown_tags_and_bad_flags() {
+    let mut builder = Builder::new();
+    builder.store_u16(0x1234).unwrap();
+    assert!(DnsRecord::from_cell(builder.build().unwrap()).is_err());
+
+    let mut builder = Builder::new();
+    builder.store_u16(DNS_ADNL_ADDRESS_TAG).unwrap();
+    builder.store_bytes(&[0; 32]).unwrap();
+    builder.store_u8(2).unwrap();
+    assert!(DnsRecord::from_cell(builder.build().unwrap()).is_err());
+}
+
+fn record_dict(entries: &[(DnsCategory, DnsRecord)]) -> Arc<Cell> {
+    let mut dict = HashmapE::new(DNS_CATEGORY_KEY_BITS);
+    for (category, record) in entries {
+        dict.insert_bit_key(
+            BitKey::new(category.hash().to_vec(), DNS_CATEGORY_KEY_BITS).unwrap(),
+            record.to_cell().unwrap(),
+        )
+        .unwrap();
+    }
+    let mut builder = Builder::new();
+    builder
+        .store_hashmap_e_with(&dict, |builder, value| {
+            builder.store_ref(value.clone())?;
+            Ok(())
+        })
+        .unwrap();
+    let mut slice = crate::tvm::Slice::new(builder.build().unwrap());
+    assert!(slice.load_bit().unwrap());
+    slice.load_reference().unwrap()
+}
+
+#[test]
+fn decodes_category_dictionary() {
+    let wallet = DnsRecord::SmcAddress {
+        address: address(0x66),
+        capabilities: None,
+    };
+    let storage = DnsRecord::StorageAddress([0x77; 32]);
+    let decoded = decode_dns_record_dict(record_dict(&[
+        (DnsCategory::Wallet, wallet.clone()),
+        (DnsCategory::Storage, storage.clone()),
+    ]))
+    .unwrap();
+
+    assert_eq!(decoded.len(), 2);
+    assert!(decoded.contains(&(DnsCategory::Wallet.hash(), wallet)));
+    assert!(decoded.contains(&(DnsCategory::Storage.hash(), storage)));
+}
+
+#[cfg(feature = "liteclient")]
+mod resolver_tests {
+    use super::*;
+    use crate::contracts::{ContractError, ContractProvider};
+    use crate::liteclient::boc::{DecodedAccountState, SimpleAccount};
+    use crate::tl::{
+        BlockIdExt, Int256,
+        common::{AccountId, ZeroStateIdExt},
+        response::{AccountState, MasterchainInfo, RunMethodResult, TransactionList},
+    };
+    use crate::tvm::{TvmStack, TvmStackEntry};
+    use async_trait::async_trait;
+    use num_bigint::{BigInt, Sign};
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("mock dns provider error")]
+    struct MockProviderError;
+
+    struct ExpectedCall {
+        account: Address,
+        subdomain: &'static [u8],
+        result: TvmStack,
+    }
+
+    struct MockProvider {
+        latest: BlockIdExt,
+        root_config: Option<Arc<Cell>>,
+        category: DnsCategory,
+        calls: Vec<ExpectedCall>,
+    }
+
+    #[async_trait]
+    impl ContractProvider for MockProvider {
+        type Error = MockProviderError;
+
+        async fn get_masterchain_info(&mut self) -> Result<MasterchainInfo, Self::Error> {
+            Ok(MasterchainInfo {
+                last: self.latest.clone(),
+                state_root_hash: Int256([1; 32]),
+                init: ZeroStateIdExt {
+                    workchain: -1,
+                    root_hash: Int256([2; 32]),
+                    file_hash: Int256([3; 32]),
+                },
+            })
+        }
+
+        async fn get_account_state(
+            &mut self,
+            _block: BlockIdExt,
+            _account: AccountId,
+        ) -> Result<AccountState, Self::Error> {
+            unreachable!("dns resolver must not fetch account state")
+        }
+
+        async fn get_account_state_typed(
+            &mut self,
+            _block: BlockIdExt,
+            _account: Address,
+        ) -> Result<DecodedAccountState, Self::Error> {
+            unreachable!("dns resolver must not fetch account state")
+        }
+
+        async fn get_account_state_simple(
+            &mut self,
+            _block: BlockIdExt,
+            _account: Address,
+        ) -> Result<SimpleAccount, Self::Error> {
+            unreachable!("dns resolver must not fetch account state")
+        }
+
+        async fn run_get_method(
+            &mut self,
+            mode: u32,
+            block: BlockIdExt,
+            account: Address,
+            method_id: u64,
+            stack: TvmStack,
+        ) -> Result<RunMethodResult, Self::Error> {
+            assert_eq!(mode, 0);
+            assert_eq!(block, self.latest);
+            assert_eq!(method_id, crate::utils::method_name_to_id("dnsresolve"));
+            let expected = self.calls.remove(0);
+            assert_eq!(account, expected.account);
+            let mut subdomain = Builder::new();
+            subdomain.store_bytes(expected.subdomain).unwrap();
+            assert_eq!(
+                stack,
+                TvmStack::new(vec![
+                    TvmStackEntry::Slice(subdomain.build().unwrap()),
+                    TvmStackEntry::Int(BigInt::from_bytes_be(Sign::Plus, &self.category.hash())),
+                ])
+            );
+            Ok(RunMethodResult {
+                mode: (),
+                id: self.latest.clone(),
+                shardblk: self.latest.clone(),
+                shard_proof: None,
+                proof: None,
+                state_proof: None,
+                init_c7: None,
+                lib_extras: None,
+                exit_code: 0,
+                result: Some(expected.result.to_boc().unwrap()),
+            })
+        }
+
+        async fn send_external_message_boc(&mut self, _body: Vec<u8>) -> Result<u32, Self::Error> {
+            unreachable!("dns resolver must not send messages")
+        }
+
+        async fn get_transactions(
+            &mut self,
+            _count: u32,
+            _account: AccountId,
+            _lt: u64,
+            _hash: Int256,
+        ) -> Result<TransactionList, Self::Error> {
+            unreachable!("dns resolver must not fetch transactions")
+        }
+
+        async fn get_config_param(
+            &mut self,
+            block: BlockIdExt,
+            id: u32,
+        ) -> Result<Option<Arc<Cell>>, Self::Error> {
+            assert_eq!(block, self.latest);
+            assert_eq!(id, DNS_ROOT_CONFIG_PARAM);
+            Ok(self.root_config.clone())
+        }
+    }
+
+    fn provider(category: DnsCategory, calls: Vec<ExpectedCall>) -> MockProvider {
+        let mut root_config = Builder::new();
+        root_config.store_bytes(&[0xaa; 32]).unwrap();
+        MockProvider {
+            latest: BlockIdExt {
+                workchain: -1,
+                shard: i64::MIN,
+                seqno: 10,
+                root_hash: Int256([4; 32]),
+                file_hash: Int256([5; 32]),
+            },
+            root_config: Some(root_config.build().unwrap()),
+            category,
+            calls,
+        }
+    }
+
+    fn root() -> Address {
+        Address::new(-1, [0xaa; 32])
+    }
+
+    fn resolved(bits: i64, record: Option<DnsRecord>) -> TvmStack {
+        TvmStack::new(vec![
+            TvmStackEntry::int(bits),
+            record.map_or(TvmStackEntry::Null, |record| {
+                TvmStackEntry::Cell(record.to_cell().unwrap())
+            }),
+        ])
+    }
+
+    #[tokio::test]
+    async fn follows_next_resolvers_from_config_root_to_wallet_record() {
+        let mut provider = provider(
+            DnsCategory::Wallet,
+            vec![
+                ExpectedCall {
+                    account: root(),
+                    subdomain: b"ton\0foundation\0",
+                    result: resolved(32, Some(DnsRecord::NextResolver(address(0x01)))),
+                },
+                ExpectedCall {
+                    account: address(0x01),
+                    subdomain: b"foundation\0",
+                    result: resolved(88, Some(DnsRecord::NextResolver(address(0x02)))),
+                },
+                ExpectedCall {
+                    account: address(0x02),
+                    subdomain: b"\0",
+                    result: resolved(
+                        8,
+                        Some(DnsRecord::SmcAddress {
+                            address: address(0x03),
+                            capabilities: None,
+                        }),
+                    ),
+                },
+            ],
+        );
+
+        let mut resolver = DnsResolver::from_config(&mut provider).await.unwrap();
+        assert_eq!(resolver.root(), &root());
+        assert_eq!(
+            resolver.resolve_wallet("foundation.ton").await.unwrap(),
+            Some(address(0x03))
+        );
+        assert!(provider.calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn returns_next_resolver_when_requested_and_none_when_unresolved() {
+        let mut provider = provider(
+            DnsCategory::NextResolver,
+            vec![
+                ExpectedCall {
+                    account: root(),
+                    subdomain: b"ton\0",
+                    result: resolved(32, Some(DnsRecord::NextResolver(address(0x01)))),
+                },
+                ExpectedCall {
+                    account: root(),
+                    subdomain: b"missing\0",
+                    result: resolved(0, None),
+                },
+            ],
+        );
+
+        let mut resolver = DnsResolver::new(&mut provider, root());
+        assert_eq!(
+            resolver
+                .resolve("ton", DnsCategory::NextResolver)
+                .await
+                .unwrap(),
+            Some(DnsRecord::NextResolver(address(0x01)))
+        );
+        assert_eq!(
+            resolver
+                .resolve("missing", DnsCategory::NextResolver)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn resolve_all_decodes_final_record_dictionary() {
+        let site = DnsRecord::AdnlAddress {
+            address: [0x09; 32],
+            protocols: None,
+        };
+        let dict = record_dict(&[(DnsCategory::Site, site.clone())]);
+        let mut provider = provider(
+            DnsCategory::All,
+            vec![ExpectedCall {
+                account: root(),
+                subdomain: b"site\0",
+                result: TvmStack::new(vec![TvmStackEntry::int(40), TvmStackEntry::Cell(dict)]),
+            }],
+        );
+
+        let records = DnsResolver::new(&mut provider, root())
+            .resolve_all("site")
+            .await
+            .unwrap();
+        assert_eq!(records, vec![(DnsCategory::Site, site)]);
+    }
+
+    #[tokio::test]
+    async fn rejects_partial_resolution_without_next_resolver() {
+        let mut provider = provider(
+            DnsCategory::Storage,
+            vec![ExpectedCall {
+                account: root(),
+                subdomain: b"ton\0bag\0",
+                result: resolved(32, Some(DnsRecord::StorageAddress([1; 32]))),
+            }],
+        );
+
+        let error = DnsResolver::new(&mut provider, root())
+            .resolve_storage_bag_id("bag.ton")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ContractError::Decode(_)));
+        assert!(error.to_string().contains("dns_next_resolver"));
+    }
+
+    #[test]
+    fn rejects_resolved_bits_outside_subdomain() {
+        let stack = vec![TvmStackEntry::int(12), TvmStackEntry::Null];
+        assert!(matches!(
+            decode_dnsresolve_result(stack, 4),
+            Err(DnsResolverError::InvalidResolvedBits { .. })
+        ));
+        let stack = vec![TvmStackEntry::int(40), TvmStackEntry::Null];
+        assert!(matches!(
+            decode_dnsresolve_result(stack, 4),
+            Err(DnsResolverError::InvalidResolvedBits { .. })
+        ));
+    }
+}
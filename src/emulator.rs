@@ -0,0 +1,802 @@
+//! Offline emulation of ordinary transactions.
+//!
+//! [`TransactionEmulator`] applies one inbound message to a [`ShardAccount`]
+//! the way a validator builds an ordinary transaction: storage, credit,
+//! compute, action and bounce phases, priced with the [`FeeConfig`] of the
+//! network. The result holds the encoded [`Transaction`], the updated shard
+//! account and the action list committed by the contract, so a wallet or a
+//! contract interaction can be dry-run without a liteserver.
+//!
+//! The compute phase runs on the crate's TVM interpreter and reports zero VM
+//! state hashes. Library changes, extra-currency accounting beyond plain
+//! balance moves, and split or merge transactions are not emulated.
+
+mod actions;
+
+use std::sync::Arc;
+
+use num_bigint::BigUint;
+
+use crate::fees::{CellStats, FeeConfig, FeeError};
+use crate::tlb::{
+    AccStatusChange, Account, AccountState, AccountStatus, AccountStorage, CommonMsgInfo,
+    ComputeSkipReason, ConfigParams, CurrencyCollection, Either, Grams, HashUpdateAccount, Message,
+    MsgAddressInt, OutList, ShardAccount, StateInit, StorageExtraInfo, StorageInfo, StorageUsed,
+    TlbDeserialize, TlbError, TlbSerialize, TrBouncePhase, TrComputePhase, TrCreditPhase,
+    TrStoragePhase, Transaction, TransactionDescr,
+};
+use crate::tvm::vm::{SmartContractContext, VmGasLimits, VmInit, VmSlice, VmState, VmValue};
+use crate::tvm::{Address, BitKey, Builder, Cell, HashmapE, Slice};
+
+use actions::ActionPhase;
+
+/// Key width of the `out_msgs` dictionary of a transaction.
+const OUT_MSG_KEY_BITS: usize = 15;
+
+/// Key width of extra-currency dictionaries.
+const EXTRA_CURRENCY_KEY_BITS: usize = 32;
+
+/// Op code prefixed to the body of a bounced message.
+const BOUNCE_OP: u32 = 0xffff_ffff;
+
+/// Body bits of the original message kept in a bounced message.
+const BOUNCE_BODY_BITS: usize = 256;
+
+/// Errors returned while emulating a transaction.
+#[derive(Debug, thiserror::Error)]
+pub enum EmulatorError {
+    #[error(transparent)]
+    Fees(#[from] FeeError),
+    #[error("failed to encode or decode transaction data: {0}")]
+    Tlb(#[from] TlbError),
+    #[error("cell operation failed: {0}")]
+    Cell(#[from] anyhow::Error),
+    #[error("message is not an inbound message")]
+    NotInbound,
+    #[error("message destination is not a standard address")]
+    UnsupportedDestination,
+    #[error("message destination does not match the account address")]
+    AddressMismatch,
+    #[error("account balance does not cover the import fee of {required} nanotons")]
+    ImportFee { required: u64 },
+    /// An external message reached an account that cannot run it.
+    #[error("external message cannot be processed: compute phase skipped ({0:?})")]
+    Skipped(ComputeSkipReason),
+    /// The contract did not accept an external message; validators drop such
+    /// messages without a transaction.
+    #[error("external message was not accepted (exit code {exit_code})")]
+    NotAccepted { exit_code: i32, gas_used: u64 },
+}
+
+/// Outcome of one emulated transaction.
+#[derive(Debug, Clone)]
+pub struct EmulatedTransaction {
+    pub transaction: Transaction,
+    /// Account after the transaction, pointing at `transaction`.
+    pub shard_account: ShardAccount,
+    /// Actions committed by the compute phase, in execution order. Empty when
+    /// the compute phase was skipped or failed.
+    pub actions: OutList,
+    /// Logical time after the transaction and the messages it created.
+    pub end_lt: u64,
+}
+
+impl EmulatedTransaction {
+    pub fn account(&self) -> &Account {
+        &self.shard_account.account
+    }
+
+    pub fn description(&self) -> &TransactionDescr {
+        &self.transaction.description
+    }
+
+    /// VM exit code, or `None` when the compute phase was skipped.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.description().compute_phase()?.exit_code()
+    }
+
+    pub fn aborted(&self) -> bool {
+        self.description().aborted()
+    }
+
+    /// Messages created by the transaction, in creation order.
+    pub fn out_msgs(&self) -> Vec<&Message> {
+        self.transaction
+            .out_msgs
+            .iter()
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    /// Balance of the account after the transaction, in nanotons.
+    pub fn balance(&self) -> u128 {
+        match &self.shard_account.account {
+            Account::Full { storage, .. } => nanotons(&storage.balance.grams),
+            Account::None => 0,
+        }
+    }
+}
+
+/// Emulates ordinary transactions against fixed blockchain parameters.
+///
+/// The emulator is configured like a block: `now`, the logical time of the
+/// transaction, the random seed seen by `RANDSEED`, and the config dictionary
+/// seen by `CONFIGROOT`.
+#[derive(Debug, Clone)]
+pub struct TransactionEmulator {
+    fees: FeeConfig,
+    config_root: Option<Arc<Cell>>,
+    now: u32,
+    lt: u64,
+    rand_seed: [u8; 32],
+}
+
+impl TransactionEmulator {
+    /// Creates an emulator with `fees`, no config dictionary, and zero time,
+    /// logical time and seed.
+    pub fn new(fees: FeeConfig) -> Self {
+        Self {
+            fees,
+            config_root: None,
+            now: 0,
+            lt: 0,
+            rand_seed: [0; 32],
+        }
+    }
+
+    /// Creates an emulator priced by `config` that also exposes it to
+    /// contracts through `CONFIGROOT`.
+    pub fn from_config(config: &ConfigParams) -> Result<Self, EmulatorError> {
+        let fees = FeeConfig::from_config(config)?;
+        let mut slice = Slice::new(config.config.clone());
+        let config_root = if slice.load_bit()? {
+            Some(slice.load_reference()?)
+        } else {
+            None
+        };
+        Ok(Self {
+            config_root,
+            ..Self::new(fees)
+        })
+    }
+
+    /// Sets the Unix time of the transaction.
+    pub fn with_now(mut self, now: u32) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Sets the logical time of the transaction. Created messages take the
+    /// following logical times.
+    pub fn with_lt(mut self, lt: u64) -> Self {
+        self.lt = lt;
+        self
+    }
+
+    pub fn with_rand_seed(mut self, rand_seed: [u8; 32]) -> Self {
+        self.rand_seed = rand_seed;
+        self
+    }
+
+    pub fn fees(&self) -> &FeeConfig {
+        &self.fees
+    }
+
+    /// Applies `message` to `shard_account`.
+    ///
+    /// The account may be `Account::None`; an internal message then creates
+    /// it, and a message carrying the matching `StateInit` deploys it.
+    pub fn emulate(
+        &self,
+        shard_account: &ShardAccount,
+        message: &Message,
+    ) -> Result<EmulatedTransaction, EmulatorError> {
+        let (address, inbound) = match &message.info {
+            CommonMsgInfo::Internal {
+                dest: MsgAddressInt::Std { address, .. },
+                bounce,
+                value,
+                ..
+            } => (
+                address.clone(),
+                Inbound::Internal {
+                    bounce: *bounce,
+                    value: value.clone(),
+                },
+            ),
+            CommonMsgInfo::ExternalIn {
+                dest: MsgAddressInt::Std { address, .. },
+                ..
+            } => (address.clone(), Inbound::External),
+            CommonMsgInfo::ExternalOut { .. } => return Err(EmulatorError::NotInbound),
+            _ => return Err(EmulatorError::UnsupportedDestination),
+        };
+        let workchain = i32::from(address.workchain);
+        let in_msg_cell = message.to_cell()?;
+        let mut account = AccountData::load(&shard_account.account, &address, self.now)?;
+        let orig_status = account.status();
+        let old_hash = shard_account.account.to_cell()?.hash();
+        let mut total_fees = 0u128;
+
+        let credit_first = !matches!(inbound, Inbound::Internal { bounce: true, .. });
+        let mut credit_ph = None;
+        if credit_first && let Inbound::Internal { value, .. } = &inbound {
+            credit_ph = Some(account.credit(value, self.now)?);
+        }
+        let storage_ph = self.storage_phase(&mut account, workchain)?;
+        total_fees += nanotons(&storage_ph.storage_fees_collected);
+        if !credit_first && let Inbound::Internal { value, .. } = &inbound {
+            credit_ph = Some(account.credit(value, self.now)?);
+        }
+
+        if matches!(inbound, Inbound::External) {
+            let import_fee = self.fees.import_fee(&in_msg_cell)?;
+            if account.balance < u128::from(import_fee) {
+                return Err(EmulatorError::ImportFee {
+                    required: import_fee,
+                });
+            }
+            account.balance -= u128::from(import_fee);
+            total_fees += u128::from(import_fee);
+        }
+
+        let storage_fees =
+            u64::try_from(nanotons(&storage_ph.storage_fees_collected)).unwrap_or(u64::MAX);
+        let compute = self.compute_phase(
+            &account,
+            &address,
+            message,
+            &in_msg_cell,
+            &inbound,
+            storage_fees,
+        )?;
+        if let (Inbound::External, TrComputePhase::Skipped { reason }) = (&inbound, &compute.phase)
+        {
+            return Err(EmulatorError::Skipped(*reason));
+        }
+        let original_balance = account.balance;
+        let mut msg_balance_remaining = inbound.value();
+        if let TrComputePhase::Vm { gas_fees, .. } = &compute.phase {
+            let gas_fees = nanotons(gas_fees);
+            account.balance -= gas_fees.min(account.balance);
+            msg_balance_remaining -= gas_fees.min(msg_balance_remaining);
+            total_fees += gas_fees;
+        }
+
+        let mut actions = OutList::default();
+        let mut action = None;
+        let mut out_msgs = Vec::new();
+        let mut destroyed = false;
+        if let Some((data, action_list)) = &compute.committed {
+            let phase = ActionPhase {
+                fees: &self.fees,
+                address: &address,
+                now: self.now,
+                lt: self.lt,
+                original_balance,
+                msg_balance_remaining,
+            };
+            let result = phase.run(action_list, account.balance);
+            actions = result.actions;
+            if result.phase.success {
+                let mut state_init = compute.state_init.clone().unwrap_or_else(StateInit::empty);
+                state_init.data = Some(data.clone());
+                if let Some(code) = result.new_code {
+                    state_init.code = Some(code);
+                }
+                account.state = AccountState::Active { state_init };
+                account.balance = result.balance;
+                out_msgs = result.out_msgs;
+                destroyed = result.destroy && account.balance == 0;
+                total_fees += result.action_fees;
+            }
+            action = Some(result.phase);
+        }
+
+        let success = compute.committed.is_some() && action.as_ref().is_some_and(|a| a.success);
+        let aborted = !success;
+        let mut bounce = None;
+        if aborted && let Inbound::Internal { bounce: true, .. } = &inbound {
+            let (phase, bounced) =
+                self.bounce_phase(message, &address, msg_balance_remaining, out_msgs.len())?;
+            if let TrBouncePhase::Ok { msg_fees, .. } = &phase {
+                account.balance -= msg_balance_remaining.min(account.balance);
+                total_fees += nanotons(msg_fees);
+            }
+            out_msgs.extend(bounced);
+            bounce = Some(phase);
+        }
+
+        let end_lt = self.lt + 1 + out_msgs.len() as u64;
+        let deleted = storage_ph.status_change == AccStatusChange::Deleted;
+        // A message that leaves nothing behind does not create an account.
+        let never_created = orig_status == AccountStatus::Nonexist
+            && account.balance == 0
+            && matches!(account.state, AccountState::Uninit);
+        let new_account = if destroyed || deleted || never_created {
+            Account::None
+        } else {
+            account.store(&address, end_lt)?
+        };
+        let end_status = status_of(&new_account);
+
+        let mut out_msg_dict = HashmapE::new(OUT_MSG_KEY_BITS);
+        for (index, message) in out_msgs.iter().enumerate() {
+            out_msg_dict.insert_bit_key(
+                BitKey::from_u64(index as u64, OUT_MSG_KEY_BITS)?,
+                message.clone(),
+            )?;
+        }
+        let transaction = Transaction {
+            account_addr: address.hash_part,
+            lt: self.lt,
+            prev_trans_hash: shard_account.last_trans_hash,
+            prev_trans_lt: shard_account.last_trans_lt,
+            now: self.now,
+            outmsg_cnt: out_msgs.len() as u16,
+            orig_status,
+            end_status,
+            in_msg: Some(message.clone()),
+            out_msgs: out_msg_dict,
+            total_fees: CurrencyCollection::grams(grams(total_fees)),
+            state_update: HashUpdateAccount {
+                old_hash,
+                new_hash: new_account.to_cell()?.hash(),
+            },
+            description: TransactionDescr::Ordinary {
+                credit_first,
+                storage_ph: Some(storage_ph),
+                credit_ph,
+                compute_ph: compute.phase,
+                action,
+                aborted,
+                bounce,
+                destroyed: destroyed || (orig_status != AccountStatus::Nonexist && deleted),
+            },
+        };
+        let shard_account = ShardAccount {
+            account: new_account,
+            last_trans_hash: transaction.to_cell()?.hash(),
+            last_trans_lt: self.lt,
+        };
+        Ok(EmulatedTransaction {
+            transaction,
+            shard_account,
+            actions,
+            end_lt,
+        })
+    }
+
+    /// Collects storage fees due since the account last paid, freezing or
+    /// deleting an account that cannot pay past the configured limits.
+    fn storage_phase(
+        &self,
+        account: &mut AccountData,
+        workchain: i32,
+    ) -> Result<TrStoragePhase, EmulatorError> {
+        if !account.exists {
+            return Ok(TrStoragePhase {
+                storage_fees_collected: grams(0),
+                storage_fees_due: None,
+                status_change: AccStatusChange::Unchanged,
+            });
+        }
+        let used = &account.storage_stat.used;
+        let stats = CellStats {
+            cells: u64::try_from(&used.cells).unwrap_or(u64::MAX),
+            bits: u64::try_from(&used.bits).unwrap_or(u64::MAX),
+        };
+        let last_paid = account.storage_stat.last_paid;
+        let due = account
+            .storage_stat
+            .due_payment
+            .take()
+            .map_or(0, |due| nanotons(&due));
+        let fee = u128::from(self.fees.storage_fee(workchain, stats, last_paid, self.now)) + due;
+        account.storage_stat.last_paid = last_paid.max(self.now);
+
+        if account.balance >= fee {
+            account.balance -= fee;
+            return Ok(TrStoragePhase {
+                storage_fees_collected: grams(fee),
+                storage_fees_due: None,
+                status_change: AccStatusChange::Unchanged,
+            });
+        }
+        let collected = account.balance;
+        let due = fee - collected;
+        account.balance = 0;
+        account.storage_stat.due_payment = Some(grams(due));
+        let prices = self.fees.gas_prices(workchain);
+        let status_change = if due > u128::from(prices.delete_due_limit) {
+            AccStatusChange::Deleted
+        } else if due > u128::from(prices.freeze_due_limit)
+            && let AccountState::Active { state_init } = &account.state
+        {
+            account.state = AccountState::Frozen {
+                state_hash: state_init.to_cell()?.hash(),
+            };
+            AccStatusChange::Frozen
+        } else {
+            AccStatusChange::Unchanged
+        };
+        Ok(TrStoragePhase {
+            storage_fees_collected: grams(collected),
+            storage_fees_due: Some(grams(due)),
+            status_change,
+        })
+    }
+
+    fn compute_phase(
+        &self,
+        account: &AccountData,
+        address: &Address,
+        message: &Message,
+        in_msg_cell: &Arc<Cell>,
+        inbound: &Inbound,
+        storage_fees: u64,
+    ) -> Result<Compute, EmulatorError> {
+        let msg_init = match &message.init {
+            Some(Either::Left(init) | Either::Right(init)) => Some(init),
+            None => None,
+        };
+        // An account without code runs the `StateInit` of the message when
+        // it hashes to the account address, or to the frozen state.
+        let expected_hash = match &account.state {
+            AccountState::Active { .. } => None,
+            AccountState::Uninit => Some(address.hash_part),
+            AccountState::Frozen { state_hash } => Some(*state_hash),
+        };
+        let (state_init, msg_state_used) = match (&account.state, expected_hash, msg_init) {
+            (AccountState::Active { state_init }, _, _) => (state_init.clone(), false),
+            (_, Some(expected), Some(init)) if init.to_cell()?.hash() == expected => {
+                (init.clone(), true)
+            }
+            (_, _, Some(_)) => return Ok(Compute::skipped(ComputeSkipReason::BadState)),
+            (_, _, None) => return Ok(Compute::skipped(ComputeSkipReason::NoState)),
+        };
+        let Some(code) = state_init.code.clone() else {
+            return Ok(Compute::skipped(ComputeSkipReason::NoState));
+        };
+        let data = state_init
+            .data
+            .clone()
+            .unwrap_or_else(|| Arc::new(Cell::new()));
+
+        let workchain = i32::from(address.workchain);
+        let gas_max = self.fees.gas_bought_for(workchain, account.balance);
+        let gas = match inbound {
+            Inbound::Internal { .. } => VmGasLimits {
+                limit: self
+                    .fees
+                    .gas_bought_for(workchain, inbound.value())
+                    .min(gas_max),
+                max: gas_max,
+                credit: 0,
+            },
+            Inbound::External => VmGasLimits {
+                limit: 0,
+                max: gas_max,
+                credit: self.fees.gas_prices(workchain).gas_credit.min(gas_max),
+            },
+        };
+        if gas.limit == 0 && gas.credit == 0 {
+            return Ok(Compute::skipped(ComputeSkipReason::NoGas));
+        }
+
+        let body = match &message.body {
+            Either::Left(body) | Either::Right(body) => body.clone(),
+        };
+        let context = SmartContractContext {
+            now: self.now,
+            block_lt: self.lt,
+            trans_lt: self.lt,
+            rand_seed: self.rand_seed,
+            balance: BigUint::from(account.balance),
+            address: address.clone(),
+            config: self.config_root.clone(),
+            incoming_value: BigUint::from(inbound.value()),
+            storage_fees,
+        };
+        let stack = vec![
+            VmValue::Int(account.balance.into()),
+            VmValue::Int(inbound.value().into()),
+            VmValue::Cell(in_msg_cell.clone()),
+            VmValue::Slice(VmSlice::new(body)),
+            VmValue::Int(match inbound {
+                Inbound::Internal { .. } => 0.into(),
+                Inbound::External => (-1).into(),
+            }),
+        ];
+        let mut vm = VmState::new(VmInit {
+            c7: context.to_c7(&code)?,
+            code: code.clone(),
+            data,
+            stack,
+            gas,
+        });
+        let exit_code = vm.run();
+        let gas_used = vm.gas.consumed().clamp(0, vm.gas.base) as u64;
+        let accepted = vm.gas.credit == 0;
+        if matches!(inbound, Inbound::External) && !accepted {
+            return Err(EmulatorError::NotAccepted {
+                exit_code,
+                gas_used,
+            });
+        }
+
+        let committed = vm.committed.take();
+        let phase = TrComputePhase::Vm {
+            success: committed.is_some(),
+            msg_state_used,
+            account_activated: msg_state_used,
+            gas_fees: grams(u128::from(self.fees.gas_fee(workchain, gas_used))),
+            gas_used: BigUint::from(gas_used),
+            gas_limit: BigUint::from(gas.limit),
+            gas_credit: (gas.credit > 0).then(|| BigUint::from(gas.credit)),
+            mode: 0,
+            exit_code,
+            exit_arg: None,
+            vm_steps: u32::try_from(vm.steps).unwrap_or(u32::MAX),
+            vm_init_state_hash: [0; 32],
+            vm_final_state_hash: [0; 32],
+        };
+        Ok(Compute {
+            phase,
+            committed,
+            state_init: Some(state_init),
+        })
+    }
+
+    /// Returns the remaining value of a failed bounceable message to its
+    /// sender.
+    fn bounce_phase(
+        &self,
+        message: &Message,
+        address: &Address,
+        msg_balance_remaining: u128,
+        created: usize,
+    ) -> Result<(TrBouncePhase, Option<Message>), EmulatorError> {
+        let CommonMsgInfo::Internal { src, .. } = &message.info else {
+            return Ok((TrBouncePhase::NegativeFunds, None));
+        };
+        let body = match &message.body {
+            Either::Left(body) | Either::Right(body) => body.clone(),
+        };
+        let mut original = Slice::new(body);
+        let kept = original.remaining_bits().min(BOUNCE_BODY_BITS);
+        let mut bounced_body = Builder::new();
+        bounced_body.store_u32(BOUNCE_OP)?;
+        bounced_body.store_bits(&original.load_bits(kept)?, kept)?;
+        let bounced_body = bounced_body.build()?;
+
+        let mut bounced = Message {
+            info: CommonMsgInfo::Internal {
+                ihr_disabled: true,
+                bounce: false,
+                bounced: true,
+                src: MsgAddressInt::std(address.clone()),
+                dest: src.clone(),
+                value: CurrencyCollection::grams(grams(0)),
+                extra_flags: BigUint::from(0u8),
+                fwd_fee: grams(0),
+                created_lt: self.lt + 1 + created as u64,
+                created_at: self.now,
+            },
+            init: None,
+            body: Either::Left(bounced_body),
+        };
+        let stats = CellStats::of_message(&encode_message(&mut bounced)?);
+        let msg_size = storage_used(stats);
+        let workchain = i32::from(address.workchain);
+        let fwd = u128::from(self.fees.forward_fee_for(workchain, stats));
+        if msg_balance_remaining < fwd {
+            return Ok((
+                TrBouncePhase::NoFunds {
+                    msg_size,
+                    req_fwd_fees: grams(fwd),
+                },
+                None,
+            ));
+        }
+        let first_frac = self.fees.msg_forward_prices(workchain).first_frac;
+        let msg_fees = (fwd * u128::from(first_frac)) >> 16;
+        if let CommonMsgInfo::Internal { value, fwd_fee, .. } = &mut bounced.info {
+            *value = CurrencyCollection::grams(grams(msg_balance_remaining - fwd));
+            *fwd_fee = grams(fwd - msg_fees);
+        }
+        encode_message(&mut bounced)?;
+        Ok((
+            TrBouncePhase::Ok {
+                msg_size,
+                msg_fees: grams(msg_fees),
+                fwd_fees: grams(fwd - msg_fees),
+            },
+            Some(bounced),
+        ))
+    }
+}
+
+/// How the inbound message reaches the account.
+enum Inbound {
+    Internal {
+        bounce: bool,
+        value: CurrencyCollection,
+    },
+    External,
+}
+
+impl Inbound {
+    /// Nanotons carried by the message.
+    fn value(&self) -> u128 {
+        match self {
+            Self::Internal { value, .. } => nanotons(&value.grams),
+            Self::External => 0,
+        }
+    }
+}
+
+/// Result of the compute phase.
+struct Compute {
+    phase: TrComputePhase,
+    /// Data (`c4`) and actions (`c5`) committed by a successful run.
+    committed: Option<(Arc<Cell>, Arc<Cell>)>,
+    /// State the VM ran with, carrying the fields a deployment keeps.
+    state_init: Option<StateInit>,
+}
+
+impl Compute {
+    fn skipped(reason: ComputeSkipReason) -> Self {
+        Self {
+            phase: TrComputePhase::Skipped { reason },
+            committed: None,
+            state_init: None,
+        }
+    }
+}
+
+/// Mutable view of an account while a transaction runs.
+struct AccountData {
+    exists: bool,
+    storage_stat: StorageInfo,
+    balance: u128,
+    extra: HashmapE<BigUint>,
+    state: AccountState,
+}
+
+impl AccountData {
+    fn load(account: &Account, address: &Address, now: u32) -> Result<Self, EmulatorError> {
+        match account {
+            Account::None => Ok(Self {
+                exists: false,
+                storage_stat: StorageInfo {
+                    used: storage_used(CellStats::default()),
+                    last_paid: now,
+                    due_payment: None,
+                    extra: StorageExtraInfo::None,
+                },
+                balance: 0,
+                extra: HashmapE::new(EXTRA_CURRENCY_KEY_BITS),
+                state: AccountState::Uninit,
+            }),
+            Account::Full {
+                addr,
+                storage_stat,
+                storage,
+            } => {
+                let matches = matches!(
+                    addr,
+                    MsgAddressInt::Std { address: own, .. }
+                        if own.workchain == address.workchain && own.hash_part == address.hash_part
+                );
+                if !matches {
+                    return Err(EmulatorError::AddressMismatch);
+                }
+                Ok(Self {
+                    exists: true,
+                    storage_stat: storage_stat.clone(),
+                    balance: nanotons(&storage.balance.grams),
+                    extra: storage.balance.other.clone(),
+                    state: storage.state.clone(),
+                })
+            }
+        }
+    }
+
+    fn status(&self) -> AccountStatus {
+        if !self.exists {
+            return AccountStatus::Nonexist;
+        }
+        match self.state {
+            AccountState::Uninit => AccountStatus::Uninit,
+            AccountState::Frozen { .. } => AccountStatus::Frozen,
+            AccountState::Active { .. } => AccountStatus::Active,
+        }
+    }
+
+    /// Adds the message value; a nonexistent account starts paying storage
+    /// from now.
+    fn credit(
+        &mut self,
+        value: &CurrencyCollection,
+        now: u32,
+    ) -> Result<TrCreditPhase, EmulatorError> {
+        if !self.exists {
+            self.storage_stat.last_paid = now;
+        }
+        self.balance += nanotons(&value.grams);
+        for (key, amount) in value.other.iter() {
+            let total = self.extra.get_bit_key(key)?.cloned().unwrap_or_default() + amount;
+            self.extra.insert_bit_key(key.clone(), total)?;
+        }
+        self.exists |= self.balance > 0 || !value.other.is_empty();
+        Ok(TrCreditPhase {
+            due_fees_collected: None,
+            credit: value.clone(),
+        })
+    }
+
+    /// Encodes the account with storage usage recomputed.
+    fn store(mut self, address: &Address, last_trans_lt: u64) -> Result<Account, EmulatorError> {
+        let storage = AccountStorage {
+            last_trans_lt,
+            balance: CurrencyCollection {
+                grams: grams(self.balance),
+                other: self.extra,
+            },
+            state: self.state,
+        };
+        let mut builder = Builder::new();
+        storage.store_tlb(&mut builder)?;
+        self.storage_stat.used = storage_used(CellStats::of_tree(&builder.build()?));
+        Ok(Account::Full {
+            addr: MsgAddressInt::std(address.clone()),
+            storage_stat: self.storage_stat,
+            storage,
+        })
+    }
+}
+
+/// Encodes `message`, moving an inline `StateInit` and then an inline body
+/// into references when the root cell overflows.
+fn encode_message(message: &mut Message) -> Result<Arc<Cell>, EmulatorError> {
+    loop {
+        let error = match message.to_cell() {
+            Ok(cell) => return Ok(cell),
+            Err(error) => error,
+        };
+        if let Some(Either::Left(init)) = &message.init {
+            message.init = Some(Either::Right(init.clone()));
+        } else if let Either::Left(body) = &message.body {
+            message.body = Either::Right(body.clone());
+        } else {
+            return Err(error.into());
+        }
+    }
+}
+
+fn status_of(account: &Account) -> AccountStatus {
+    match account {
+        Account::None => AccountStatus::Nonexist,
+        Account::Full { storage, .. } => match storage.state {
+            AccountState::Uninit => AccountStatus::Uninit,
+            AccountState::Frozen { .. } => AccountStatus::Frozen,
+            AccountState::Active { .. } => AccountStatus::Active,
+        },
+    }
+}
+
+fn storage_used(stats: CellStats) -> StorageUsed {
+    StorageUsed::new(BigUint::from(stats.cells), BigUint::from(stats.bits))
+}
+
+fn nanotons(grams: &Grams) -> u128 {
+    u128::try_from(&grams.0).unwrap_or(u128::MAX)
+}
+
+fn grams(nanotons: u128) -> Grams {
+    Grams(BigUint::from(nanotons))
+}
+
+#[cfg(test)]
+mod tests;
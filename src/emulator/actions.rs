@@ -0,0 +1,333 @@
+//! Action phase: applies the `c5` action list committed by the compute phase.
+
+use super::*;
+
+use crate::tlb::{
+    CommonMsgInfoRelaxed, MessageRelaxed, MsgAddress, MsgAddressExt, OutAction, TrActionPhase,
+};
+
+/// Send mode flags of `action_send_msg`.
+const SEND_PAY_FEES_SEPARATELY: u8 = 1;
+const SEND_IGNORE_ERRORS: u8 = 2;
+const SEND_BOUNCE_IF_FAILED: u8 = 16;
+const SEND_DESTROY_IF_ZERO: u8 = 32;
+const SEND_REMAINING_VALUE: u8 = 64;
+const SEND_ALL_BALANCE: u8 = 128;
+
+/// Reserve mode flags of `action_reserve_currency`.
+const RESERVE_ALL_BUT: u8 = 1;
+const RESERVE_AT_MOST: u8 = 2;
+const RESERVE_ADD_ORIGINAL: u8 = 4;
+const RESERVE_NEGATE: u8 = 8;
+
+/// Action phase result codes.
+const RESULT_INVALID_LIST: i32 = 32;
+const RESULT_INVALID_ACTION: i32 = 34;
+const RESULT_INVALID_SOURCE: i32 = 35;
+const RESULT_NO_FUNDS: i32 = 37;
+const RESULT_VALUE_BELOW_FEES: i32 = 40;
+
+/// Inputs of one action phase.
+pub(super) struct ActionPhase<'a> {
+    pub(super) fees: &'a FeeConfig,
+    pub(super) address: &'a Address,
+    pub(super) now: u32,
+    pub(super) lt: u64,
+    /// Balance before the compute phase, used by `RESERVE_ADD_ORIGINAL`.
+    pub(super) original_balance: u128,
+    /// Inbound value left after gas fees, added by `SEND_REMAINING_VALUE`.
+    pub(super) msg_balance_remaining: u128,
+}
+
+/// Outcome of an action phase. Only `phase` and `actions` are meaningful
+/// when the phase failed.
+pub(super) struct ActionResult {
+    pub(super) phase: TrActionPhase,
+    pub(super) actions: OutList,
+    pub(super) out_msgs: Vec<Message>,
+    pub(super) balance: u128,
+    pub(super) new_code: Option<Arc<Cell>>,
+    /// A message was sent with `SEND_DESTROY_IF_ZERO`.
+    pub(super) destroy: bool,
+    /// Fees collected by the phase, the `total_action_fees` of `phase`.
+    pub(super) action_fees: u128,
+}
+
+/// Running totals while actions are applied.
+struct Progress {
+    balance: u128,
+    reserved: u128,
+    out_msgs: Vec<Message>,
+    new_code: Option<Arc<Cell>>,
+    destroy: bool,
+    fwd_fees: u128,
+    action_fees: u128,
+    spec_actions: u16,
+    skipped_actions: u16,
+    msg_size: CellStats,
+}
+
+impl ActionPhase<'_> {
+    /// Applies `action_list` to an account holding `balance` nanotons.
+    pub(super) fn run(&self, action_list: &Arc<Cell>, balance: u128) -> ActionResult {
+        let mut phase = TrActionPhase {
+            success: false,
+            valid: false,
+            no_funds: false,
+            status_change: AccStatusChange::Unchanged,
+            total_fwd_fees: None,
+            total_action_fees: None,
+            result_code: 0,
+            result_arg: None,
+            tot_actions: 0,
+            spec_actions: 0,
+            skipped_actions: 0,
+            msgs_created: 0,
+            action_list_hash: action_list.hash(),
+            tot_msg_size: storage_used(CellStats::default()),
+        };
+        let Ok(actions) = OutList::from_cell(action_list.clone()) else {
+            phase.result_code = RESULT_INVALID_LIST;
+            return ActionResult::failed(phase, OutList::default(), balance);
+        };
+        phase.valid = true;
+        phase.tot_actions = actions.len() as u16;
+
+        let mut progress = Progress {
+            balance,
+            reserved: 0,
+            out_msgs: Vec::new(),
+            new_code: None,
+            destroy: false,
+            fwd_fees: 0,
+            action_fees: 0,
+            spec_actions: 0,
+            skipped_actions: 0,
+            msg_size: CellStats::default(),
+        };
+        for (index, action) in actions.actions.iter().enumerate() {
+            let outcome = match action {
+                OutAction::SendMsg { mode, out_msg } => self.send(&mut progress, *mode, out_msg),
+                OutAction::ReserveCurrency { mode, currency } => {
+                    self.reserve(&mut progress, *mode, nanotons(&currency.grams))
+                }
+                OutAction::SetCode { new_code } => {
+                    progress.new_code = Some(new_code.clone());
+                    progress.spec_actions += 1;
+                    Ok(())
+                }
+                OutAction::ChangeLibrary { .. } => Err(RESULT_INVALID_ACTION),
+            };
+            if let Err(code) = outcome {
+                phase.result_code = code;
+                phase.result_arg = Some(index as i32);
+                phase.no_funds = code == RESULT_NO_FUNDS;
+                phase.spec_actions = progress.spec_actions;
+                phase.skipped_actions = progress.skipped_actions;
+                return ActionResult::failed(phase, actions, balance);
+            }
+        }
+
+        phase.success = true;
+        phase.status_change = if progress.destroy && progress.balance == 0 {
+            AccStatusChange::Deleted
+        } else {
+            AccStatusChange::Unchanged
+        };
+        phase.total_fwd_fees = (progress.fwd_fees > 0).then(|| grams(progress.fwd_fees));
+        phase.total_action_fees = (progress.action_fees > 0).then(|| grams(progress.action_fees));
+        phase.spec_actions = progress.spec_actions;
+        phase.skipped_actions = progress.skipped_actions;
+        phase.msgs_created = progress.out_msgs.len() as u16;
+        phase.tot_msg_size = storage_used(progress.msg_size);
+        ActionResult {
+            phase,
+            actions,
+            out_msgs: progress.out_msgs,
+            balance: progress.balance,
+            new_code: progress.new_code,
+            destroy: progress.destroy,
+            action_fees: progress.action_fees,
+        }
+    }
+
+    fn send(&self, progress: &mut Progress, mode: u8, message: &MessageRelaxed) -> Result<(), i32> {
+        let known = SEND_PAY_FEES_SEPARATELY
+            | SEND_IGNORE_ERRORS
+            | SEND_BOUNCE_IF_FAILED
+            | SEND_DESTROY_IF_ZERO
+            | SEND_REMAINING_VALUE
+            | SEND_ALL_BALANCE;
+        if mode & !known != 0 || (mode & SEND_REMAINING_VALUE != 0 && mode & SEND_ALL_BALANCE != 0)
+        {
+            return Err(RESULT_INVALID_ACTION);
+        }
+        match self.try_send(progress, mode, message) {
+            Err(_) if mode & SEND_IGNORE_ERRORS != 0 => {
+                progress.skipped_actions += 1;
+                Ok(())
+            }
+            outcome => outcome,
+        }
+    }
+
+    fn try_send(
+        &self,
+        progress: &mut Progress,
+        mode: u8,
+        relaxed: &MessageRelaxed,
+    ) -> Result<(), i32> {
+        let created_lt = self.lt + 1 + progress.out_msgs.len() as u64;
+        let remaining = progress.balance - progress.reserved;
+        let (info, value) = match &relaxed.info {
+            CommonMsgInfoRelaxed::Internal {
+                ihr_disabled,
+                bounce,
+                bounced,
+                src,
+                dest,
+                value,
+                extra_flags,
+                ..
+            } => {
+                self.check_source(src)?;
+                let mut amount = nanotons(&value.grams);
+                if mode & SEND_ALL_BALANCE != 0 {
+                    amount = remaining;
+                } else if mode & SEND_REMAINING_VALUE != 0 {
+                    amount += self.msg_balance_remaining;
+                }
+                let info = CommonMsgInfo::Internal {
+                    ihr_disabled: *ihr_disabled,
+                    bounce: *bounce,
+                    bounced: *bounced,
+                    src: MsgAddressInt::std(self.address.clone()),
+                    dest: dest.clone(),
+                    value: CurrencyCollection {
+                        grams: grams(amount),
+                        other: value.other.clone(),
+                    },
+                    extra_flags: extra_flags.clone(),
+                    fwd_fee: grams(0),
+                    created_lt,
+                    created_at: self.now,
+                };
+                (info, Some(amount))
+            }
+            CommonMsgInfoRelaxed::ExternalOut { src, dest, .. } => {
+                self.check_source(src)?;
+                let info = CommonMsgInfo::ExternalOut {
+                    src: MsgAddressInt::std(self.address.clone()),
+                    dest: dest.clone(),
+                    created_lt,
+                    created_at: self.now,
+                };
+                (info, None)
+            }
+        };
+        let mut message = Message {
+            info,
+            init: relaxed.init.clone(),
+            body: relaxed.body.clone(),
+        };
+
+        let cell = encode_message(&mut message).map_err(|_| RESULT_INVALID_ACTION)?;
+        let fees = self
+            .fees
+            .forward_fee(&cell)
+            .map_err(|_| RESULT_INVALID_ACTION)?;
+        let total = u128::from(fees.total);
+        let (charged, action_fee) = match value {
+            Some(amount) => {
+                // Sending the whole balance leaves nothing to pay fees from
+                // separately, so the fees always come out of the value.
+                let separate = mode & SEND_PAY_FEES_SEPARATELY != 0 && mode & SEND_ALL_BALANCE == 0;
+                let (sent, charged) = if separate {
+                    (amount, amount + total)
+                } else if amount < total {
+                    return Err(RESULT_VALUE_BELOW_FEES);
+                } else {
+                    (amount - total, amount)
+                };
+                if let CommonMsgInfo::Internal { value, fwd_fee, .. } = &mut message.info {
+                    value.grams = grams(sent);
+                    *fwd_fee = grams(u128::from(fees.fwd_fee));
+                }
+                (charged, u128::from(fees.action_fee))
+            }
+            // Nobody forwards external messages, so the whole fee is collected.
+            None => (total, total),
+        };
+        if charged > remaining {
+            return Err(RESULT_NO_FUNDS);
+        }
+        let cell = encode_message(&mut message).map_err(|_| RESULT_INVALID_ACTION)?;
+
+        progress.balance -= charged;
+        progress.fwd_fees += total;
+        progress.action_fees += action_fee;
+        let size = CellStats::of_tree(&cell);
+        progress.msg_size.cells += size.cells;
+        progress.msg_size.bits += size.bits;
+        progress.destroy |= mode & SEND_DESTROY_IF_ZERO != 0;
+        progress.out_msgs.push(message);
+        Ok(())
+    }
+
+    fn reserve(&self, progress: &mut Progress, mode: u8, amount: u128) -> Result<(), i32> {
+        if mode & !(RESERVE_ALL_BUT | RESERVE_AT_MOST | RESERVE_ADD_ORIGINAL | RESERVE_NEGATE) != 0
+            || (mode & RESERVE_NEGATE != 0 && mode & RESERVE_ADD_ORIGINAL == 0)
+        {
+            return Err(RESULT_INVALID_ACTION);
+        }
+        let mut amount = amount;
+        if mode & RESERVE_ADD_ORIGINAL != 0 {
+            amount = if mode & RESERVE_NEGATE != 0 {
+                self.original_balance.saturating_sub(amount)
+            } else {
+                self.original_balance.saturating_add(amount)
+            };
+        }
+        let remaining = progress.balance - progress.reserved;
+        if mode & RESERVE_AT_MOST != 0 {
+            amount = amount.min(remaining);
+        }
+        if amount > remaining {
+            return Err(RESULT_NO_FUNDS);
+        }
+        if mode & RESERVE_ALL_BUT != 0 {
+            amount = remaining - amount;
+        }
+        progress.reserved += amount;
+        progress.spec_actions += 1;
+        Ok(())
+    }
+
+    /// Outbound messages may leave the source empty or name the account.
+    fn check_source(&self, src: &MsgAddress) -> Result<(), i32> {
+        match src {
+            MsgAddress::Ext(MsgAddressExt::None) => Ok(()),
+            MsgAddress::Int(MsgAddressInt::Std { address, .. })
+                if address.workchain == self.address.workchain
+                    && address.hash_part == self.address.hash_part =>
+            {
+                Ok(())
+            }
+            _ => Err(RESULT_INVALID_SOURCE),
+        }
+    }
+}
+
+impl ActionResult {
+    fn failed(phase: TrActionPhase, actions: OutList, balance: u128) -> Self {
+        Self {
+            phase,
+            actions,
+            out_msgs: Vec::new(),
+            balance,
+            new_code: None,
+            destroy: false,
+            action_fees: 0,
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use super::*;
+
+use ed25519_dalek::SigningKey;
+
+use crate::fees::test_config::config_params;
+use crate::tvm::boc::deserialize_boc;
+use crate::wallet::{WalletMessage, WalletV4R2, WalletV4R2Data, wallet_v4r2_code};
+
+const NOW: u32 = 1_700_000_000;
+const LT: u64 = 50_000_000;
+const TON: u64 = 1_000_000_000;
+
+fn emulator() -> TransactionEmulator {
+    TransactionEmulator::from_config(&config_params())
+        .unwrap()
+        .with_now(NOW)
+        .with_lt(LT)
+}
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7u8; 32])
+}
+
+fn wallet() -> WalletV4R2 {
+    WalletV4R2::default(
+        signing_key().verifying_key().to_bytes(),
+        wallet_v4r2_code().unwrap(),
+        0,
+    )
+}
+
+fn shard_account(address: &Address, state: AccountState, balance: u64) -> ShardAccount {
+    ShardAccount {
+        account: Account::Full {
+            addr: MsgAddressInt::std(address.clone()),
+            storage_stat: StorageInfo {
+                used: storage_used(CellStats::default()),
+                last_paid: NOW,
+                due_payment: None,
+                extra: StorageExtraInfo::None,
+            },
+            storage: AccountStorage {
+                last_trans_lt: LT - 10,
+                balance: CurrencyCollection::grams(Grams::from(balance)),
+                state,
+            },
+        },
+        last_trans_hash: [0x33; 32],
+        last_trans_lt: LT - 10,
+    }
+}
+
+fn active_wallet(balance: u64) -> ShardAccount {
+    let wallet = wallet();
+    shard_account(
+        &wallet.address().unwrap(),
+        AccountState::Active {
+            state_init: wallet.state_init().unwrap(),
+        },
+        balance,
+    )
+}
+
+fn transfer(
+    seqno: u32,
+    messages: Vec<WalletMessage>,
+    include_state_init: bool,
+) -> (Message, Arc<Cell>) {
+    let boc = wallet()
+        .build_external_message_boc(
+            seqno,
+            NOW + 60,
+            messages,
+            &signing_key(),
+            include_state_init,
+        )
+        .unwrap();
+    let cell = deserialize_boc(&boc).unwrap();
+    (Message::from_cell(cell.clone()).unwrap(), cell)
+}
+
+fn seqno(account: &Account) -> u32 {
+    let Account::Full {
+        storage:
+            AccountStorage {
+                state: AccountState::Active { state_init },
+                ..
+            },
+        ..
+    } = account
+    else {
+        panic!("account is not active: {account:?}");
+    };
+    WalletV4R2Data::from_cell(state_init.data.clone().unwrap())
+        .unwrap()
+        .seqno
+}
+
+fn internal(dest: &Address, value: u64, bounce: bool, body: Arc<Cell>) -> Message {
+    Message {
+        info: CommonMsgInfo::Internal {
+            ihr_disabled: true,
+            bounce,
+            bounced: false,
+            src: MsgAddressInt::std(Address::new(0, [0x11; 32])),
+            dest: MsgAddressInt::std(dest.clone()),
+            value: CurrencyCollection::grams(Grams::from(value)),
+            extra_flags: BigUint::from(0u8),
+            fwd_fee: Grams::from(0),
+            created_lt: LT - 5,
+            created_at: NOW,
+        },
+        init: None,
+        body: Either::Right(body),
+    }
+}
+
+#[test]
+fn wallet_transfer_sends_message_and_charges_every_fee() {
+    let destination = Address::new(0, [0x44; 32]);
+    let account = active_wallet(10 * TON);
+    let (message, external) = transfer(
+        0,
+        vec![WalletMessage::internal(destination.clone(), TON)],
+        false,
+    );
+
+    let emulated = emulator().emulate(&account, &message).unwrap();
+
+    assert_eq!(emulated.exit_code(), Some(0));
+    assert!(!emulated.aborted());
+    assert_eq!(seqno(emulated.account()), 1);
+    assert_eq!(emulated.actions.len(), 1);
+    assert_eq!(emulated.end_lt, LT + 2);
+
+    let out_msgs = emulated.out_msgs();
+    assert_eq!(out_msgs.len(), 1);
+    let CommonMsgInfo::Internal {
+        src,
+        dest,
+        value,
+        created_lt,
+        created_at,
+        ..
+    } = &out_msgs[0].info
+    else {
+        panic!("expected an internal message");
+    };
+    assert_eq!(src, &MsgAddressInt::std(wallet().address().unwrap()));
+    assert_eq!(dest, &MsgAddressInt::std(destination));
+    assert_eq!(value.grams, Grams::from(TON));
+    assert_eq!((*created_lt, *created_at), (LT + 1, NOW));
+
+    let TransactionDescr::Ordinary {
+        compute_ph:
+            TrComputePhase::Vm {
+                gas_used,
+                gas_credit,
+                ..
+            },
+        action: Some(action),
+        ..
+    } = emulated.description()
+    else {
+        panic!("expected an ordinary transaction with a VM phase");
+    };
+    assert_eq!(gas_credit, &Some(BigUint::from(10_000u32)));
+    assert_eq!(action.msgs_created, 1);
+
+    // Everything spent besides the value sent matches the fee estimate.
+    let estimate = emulator()
+        .fees()
+        .wallet_transfer(
+            &external,
+            u64::try_from(gas_used).unwrap(),
+            &[out_msgs[0].to_cell().unwrap()],
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        u128::from(10 * TON) - emulated.balance() - u128::from(TON),
+        u128::from(estimate.total)
+    );
+
+    // The account points at the new transaction, which replays are checked
+    // against.
+    assert_eq!(
+        emulated.shard_account.last_trans_hash,
+        emulated.transaction.to_cell().unwrap().hash()
+    );
+    assert_eq!(emulated.transaction.prev_trans_hash, [0x33; 32]);
+    assert!(matches!(
+        emulator().emulate(&emulated.shard_account, &message),
+        Err(EmulatorError::NotAccepted { exit_code: 33, .. })
+    ));
+}
+
+#[test]
+fn external_message_with_state_init_deploys_wallet() {
+    let address = wallet().address().unwrap();
+    let account = shard_account(&address, AccountState::Uninit, TON);
+    let (message, _) = transfer(0, Vec::new(), true);
+
+    let emulated = emulator().emulate(&account, &message).unwrap();
+
+    assert_eq!(emulated.transaction.orig_status, AccountStatus::Uninit);
+    assert_eq!(emulated.transaction.end_status, AccountStatus::Active);
+    assert!(matches!(
+        emulated.description().compute_phase(),
+        Some(TrComputePhase::Vm {
+            success: true,
+            msg_state_used: true,
+            account_activated: true,
+            ..
+        })
+    ));
+    assert_eq!(seqno(emulated.account()), 1);
+
+    let (without_init, _) = transfer(0, Vec::new(), false);
+    assert!(matches!(
+        emulator().emulate(&account, &without_init),
+        Err(EmulatorError::Skipped(ComputeSkipReason::NoState))
+    ));
+}
+
+#[test]
+fn internal_message_to_missing_account_bounces_or_creates_it() {
+    let address = Address::new(0, [0x99; 32]);
+    let missing = ShardAccount {
+        account: Account::None,
+        last_trans_hash: [0; 32],
+        last_trans_lt: 0,
+    };
+    let mut body = Builder::new();
+    body.store_u32(0x1234_5678).unwrap();
+    body.store_u64(42).unwrap();
+    let body = body.build().unwrap();
+
+    let bounced = emulator()
+        .emulate(&missing, &internal(&address, TON, true, body.clone()))
+        .unwrap();
+    assert!(bounced.aborted());
+    assert_eq!(bounced.account(), &Account::None);
+    assert_eq!(bounced.transaction.end_status, AccountStatus::Nonexist);
+    let TransactionDescr::Ordinary {
+        credit_first: false,
+        compute_ph:
+            TrComputePhase::Skipped {
+                reason: ComputeSkipReason::NoState,
+            },
+        bounce: Some(TrBouncePhase::Ok {
+            msg_fees, fwd_fees, ..
+        }),
+        ..
+    } = bounced.description()
+    else {
+        panic!("expected a bounce: {:?}", bounced.description());
+    };
+    let out_msgs = bounced.out_msgs();
+    assert_eq!(out_msgs.len(), 1);
+    let CommonMsgInfo::Internal {
+        bounced: true,
+        bounce: false,
+        dest,
+        value,
+        ..
+    } = &out_msgs[0].info
+    else {
+        panic!("expected a bounced internal message");
+    };
+    assert_eq!(dest, &MsgAddressInt::std(Address::new(0, [0x11; 32])));
+    assert_eq!(
+        value.grams.0.clone() + &msg_fees.0 + &fwd_fees.0,
+        BigUint::from(TON)
+    );
+    let Either::Left(bounced_body) = &out_msgs[0].body else {
+        panic!("bounced body is stored inline");
+    };
+    let mut bounced_body = Slice::new(bounced_body.clone());
+    assert_eq!(bounced_body.load_u32().unwrap(), BOUNCE_OP);
+    assert_eq!(bounced_body.load_u32().unwrap(), 0x1234_5678);
+    assert_eq!(bounced_body.load_u64().unwrap(), 42);
+
+    let created = emulator()
+        .emulate(&missing, &internal(&address, TON, false, body))
+        .unwrap();
+    assert!(created.aborted());
+    assert!(created.out_msgs().is_empty());
+    assert_eq!(created.transaction.end_status, AccountStatus::Uninit);
+    assert_eq!(created.balance(), u128::from(TON));
+}
+
+#[test]
+fn failed_action_phase_rolls_back_state() {
+    let destination = Address::new(0, [0x44; 32]);
+    let account = active_wallet(2 * TON);
+    let (message, _) = transfer(
+        0,
+        vec![WalletMessage::internal(destination, 5 * TON).with_mode(1)],
+        false,
+    );
+
+    let emulated = emulator().emulate(&account, &message).unwrap();
+
+    assert_eq!(emulated.exit_code(), Some(0));
+    assert!(emulated.aborted());
+    let action = emulated.description().action_phase().unwrap();
+    assert!(!action.success);
+    assert!(action.valid);
+    assert!(action.no_funds);
+    assert_eq!(action.result_code, 37);
+    assert_eq!(action.result_arg, Some(0));
+    assert!(emulated.out_msgs().is_empty());
+    // The seqno bump is discarded along with the actions; gas is still paid.
+    assert_eq!(seqno(emulated.account()), 0);
+    assert!(emulated.balance() < u128::from(2 * TON));
+}
+
+#[test]
+fn all_balance_send_takes_fees_from_the_value() {
+    let destination = Address::new(0, [0x44; 32]);
+    let account = active_wallet(2 * TON);
+    let (message, _) = transfer(
+        0,
+        vec![WalletMessage::internal(destination, 0).with_mode(129)],
+        false,
+    );
+
+    let emulated = emulator().emulate(&account, &message).unwrap();
+
+    assert!(!emulated.aborted());
+    let action = emulated.description().action_phase().unwrap();
+    assert!(action.success);
+    assert_eq!(emulated.balance(), 0);
+    let out_msgs = emulated.out_msgs();
+    assert_eq!(out_msgs.len(), 1);
+    let CommonMsgInfo::Internal { value, .. } = &out_msgs[0].info else {
+        panic!("expected an internal message");
+    };
+    let sent = u128::try_from(&value.grams.0).unwrap();
+    assert!(sent > 0 && sent < u128::from(2 * TON));
+    assert_eq!(seqno(emulated.account()), 1);
+}
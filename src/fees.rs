@@ -0,0 +1,327 @@
+//! Fee estimation from blockchain config parameters.
+//!
+//! [`FeeConfig`] holds the storage, gas and message forwarding prices of
+//! config parameters 18, 20/21 and 24/25 and applies the validator formulas
+//! to them: import fees of inbound external messages, forward fees of
+//! outbound messages, gas fees, and storage fees over a period of time.
+//! [`FeeConfig::wallet_transfer`] combines them into the cost of a wallet
+//! transaction that sends messages.
+//!
+//! All amounts are in nanotons. Prices scaled by `2^16` are rounded up after
+//! scaling, as validators do.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::tlb::{
+    CommonMsgInfo, ConfigParams, GasLimitsPrices, Message, MsgAddressInt, MsgForwardPrices,
+    StoragePrices, TlbDeserialize, TlbError,
+};
+use crate::tvm::Cell;
+
+const MASTERCHAIN: i32 = -1;
+
+/// Errors returned while building a [`FeeConfig`] or reading messages.
+#[derive(Debug, thiserror::Error)]
+pub enum FeeError {
+    #[error("config parameter {0} is missing")]
+    MissingParam(u32),
+    #[error("failed to decode config parameter {id}: {source}")]
+    Config {
+        id: u32,
+        #[source]
+        source: TlbError,
+    },
+    #[error("failed to decode message: {0}")]
+    InvalidMessage(#[source] TlbError),
+    #[error("message is not an inbound external message")]
+    NotExternalIn,
+}
+
+/// Cell and bit counts of a cell tree, counting each distinct cell once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellStats {
+    pub cells: u64,
+    pub bits: u64,
+}
+
+impl CellStats {
+    /// Counts the whole tree under `root`, including `root`.
+    pub fn of_tree(root: &Arc<Cell>) -> Self {
+        let mut stats = Self::default();
+        stats.add_tree(root, &mut HashSet::new());
+        stats
+    }
+
+    /// Counts a message the way forward and import fees do: every cell below
+    /// the root, without the root cell itself.
+    pub fn of_message(message: &Arc<Cell>) -> Self {
+        let mut stats = Self::default();
+        let mut seen = HashSet::new();
+        for child in message.references() {
+            stats.add_tree(child, &mut seen);
+        }
+        stats
+    }
+
+    fn add_tree(&mut self, root: &Arc<Cell>, seen: &mut HashSet<[u8; 32]>) {
+        let mut pending = vec![root];
+        while let Some(cell) = pending.pop() {
+            if !seen.insert(cell.hash()) {
+                continue;
+            }
+            self.cells += 1;
+            self.bits += cell.bit_len() as u64;
+            pending.extend(cell.references());
+        }
+    }
+}
+
+/// Forward fee of one outbound message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageFees {
+    /// Full forward fee charged to the sender.
+    pub total: u64,
+    /// Part of `total` collected in the sender's action phase.
+    pub action_fee: u64,
+    /// Part of `total` stored in the message's `fwd_fee` field and paid to
+    /// the validators that forward it.
+    pub fwd_fee: u64,
+}
+
+/// Estimated cost of a wallet transaction driven by an external message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferFees {
+    /// Import fee of the external message.
+    pub in_fwd_fee: u64,
+    /// Storage fee collected before the compute phase.
+    pub storage_fee: u64,
+    pub gas_fee: u64,
+    /// Forward fees of the sent messages, in the order given.
+    pub out_msgs: Vec<MessageFees>,
+    /// Sum of every fee above; the balance spent besides the values sent.
+    pub total: u64,
+}
+
+/// Storage of an account since it last paid storage fees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageUsage {
+    /// Account storage as reported by `storage_stat.used`.
+    pub stats: CellStats,
+    /// `storage_stat.last_paid` of the account.
+    pub last_paid: u32,
+    /// Time of the estimated transaction.
+    pub now: u32,
+}
+
+/// Fee prices from blockchain config parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeConfig {
+    /// Config parameter 18, ordered by `utime_since`.
+    pub storage_prices: Vec<StoragePrices>,
+    /// Config parameter 20.
+    pub masterchain_gas: GasLimitsPrices,
+    /// Config parameter 21.
+    pub basechain_gas: GasLimitsPrices,
+    /// Config parameter 24.
+    pub masterchain_fwd: MsgForwardPrices,
+    /// Config parameter 25.
+    pub basechain_fwd: MsgForwardPrices,
+}
+
+impl FeeConfig {
+    /// Reads parameters 18, 20, 21, 24 and 25 from `config`.
+    pub fn from_config(config: &ConfigParams) -> Result<Self, FeeError> {
+        fn required<T>(id: u32, value: crate::tlb::Result<Option<T>>) -> Result<T, FeeError> {
+            value
+                .map_err(|source| FeeError::Config { id, source })?
+                .ok_or(FeeError::MissingParam(id))
+        }
+
+        let mut storage_prices = required(18, config.storage_prices())?.prices;
+        storage_prices.sort_by_key(|prices| prices.utime_since);
+        Ok(Self {
+            storage_prices,
+            masterchain_gas: required(20, config.gas_prices(true))?,
+            basechain_gas: required(21, config.gas_prices(false))?,
+            masterchain_fwd: required(24, config.msg_forward_prices(true))?,
+            basechain_fwd: required(25, config.msg_forward_prices(false))?,
+        })
+    }
+
+    pub fn gas_prices(&self, workchain: i32) -> &GasLimitsPrices {
+        if workchain == MASTERCHAIN {
+            &self.masterchain_gas
+        } else {
+            &self.basechain_gas
+        }
+    }
+
+    pub fn msg_forward_prices(&self, workchain: i32) -> &MsgForwardPrices {
+        if workchain == MASTERCHAIN {
+            &self.masterchain_fwd
+        } else {
+            &self.basechain_fwd
+        }
+    }
+
+    /// Fee for `gas_used` gas units in `workchain`, including the flat price.
+    pub fn gas_fee(&self, workchain: i32, gas_used: u64) -> u64 {
+        let prices = self.gas_prices(workchain);
+        let (flat_limit, flat_price) = prices
+            .flat
+            .map_or((0, 0), |flat| (flat.gas_limit, flat.gas_price));
+        if gas_used <= flat_limit {
+            return flat_price;
+        }
+        let scaled = u128::from(gas_used - flat_limit) * u128::from(prices.gas_price);
+        saturate(u128::from(flat_price) + shift_ceil(scaled))
+    }
+
+    /// Gas units that `nanotons` pay for in `workchain`, capped at the
+    /// transaction gas limit.
+    pub fn gas_bought_for(&self, workchain: i32, nanotons: u128) -> u64 {
+        let prices = self.gas_prices(workchain);
+        let (flat_limit, flat_price) = prices
+            .flat
+            .map_or((0, 0), |flat| (flat.gas_limit, flat.gas_price));
+        if nanotons < u128::from(flat_price) || prices.gas_price == 0 {
+            return 0;
+        }
+        let scaled = (nanotons - u128::from(flat_price)).saturating_mul(1 << 16)
+            / u128::from(prices.gas_price);
+        saturate(scaled.saturating_add(u128::from(flat_limit))).min(prices.gas_limit)
+    }
+
+    /// Forward fee for a message of `stats` size priced in `workchain`.
+    pub fn forward_fee_for(&self, workchain: i32, stats: CellStats) -> u64 {
+        let prices = self.msg_forward_prices(workchain);
+        let scaled = u128::from(prices.bit_price) * u128::from(stats.bits)
+            + u128::from(prices.cell_price) * u128::from(stats.cells);
+        saturate(u128::from(prices.lump_price) + shift_ceil(scaled))
+    }
+
+    /// Forward fee of an outbound message cell.
+    ///
+    /// Masterchain prices apply when the source or the destination is in the
+    /// masterchain.
+    pub fn forward_fee(&self, message: &Arc<Cell>) -> Result<MessageFees, FeeError> {
+        let decoded = Message::from_cell(message.clone()).map_err(FeeError::InvalidMessage)?;
+        let workchain = match &decoded.info {
+            CommonMsgInfo::Internal { src, dest, .. } => {
+                if workchain(src) == MASTERCHAIN {
+                    MASTERCHAIN
+                } else {
+                    workchain(dest)
+                }
+            }
+            CommonMsgInfo::ExternalOut { src, .. } => workchain(src),
+            CommonMsgInfo::ExternalIn { dest, .. } => workchain(dest),
+        };
+        let total = self.forward_fee_for(workchain, CellStats::of_message(message));
+        let first_frac = self.msg_forward_prices(workchain).first_frac;
+        let action_fee = ((u128::from(total) * u128::from(first_frac)) >> 16) as u64;
+        Ok(MessageFees {
+            total,
+            action_fee,
+            fwd_fee: total - action_fee,
+        })
+    }
+
+    /// Import fee (`in_fwd_fee`) of an inbound external message cell.
+    pub fn import_fee(&self, message: &Arc<Cell>) -> Result<u64, FeeError> {
+        let workchain = external_destination(message)?;
+        Ok(self.forward_fee_for(workchain, CellStats::of_message(message)))
+    }
+
+    /// Storage fee for keeping `stats` in `workchain` from `since` until
+    /// `until`, following price changes in between.
+    pub fn storage_fee(&self, workchain: i32, stats: CellStats, since: u32, until: u32) -> u64 {
+        let mut scaled = 0u128;
+        for (index, prices) in self.storage_prices.iter().enumerate() {
+            let start = since.max(prices.utime_since);
+            let end = self
+                .storage_prices
+                .get(index + 1)
+                .map_or(until, |next| until.min(next.utime_since));
+            if end <= start {
+                continue;
+            }
+            let (bit_price, cell_price) = if workchain == MASTERCHAIN {
+                (prices.mc_bit_price_ps, prices.mc_cell_price_ps)
+            } else {
+                (prices.bit_price_ps, prices.cell_price_ps)
+            };
+            let per_second = u128::from(stats.bits) * u128::from(bit_price)
+                + u128::from(stats.cells) * u128::from(cell_price);
+            scaled += per_second * u128::from(end - start);
+        }
+        saturate(shift_ceil(scaled))
+    }
+
+    /// Estimates a wallet transaction that imports `external`, runs
+    /// `gas_used` gas, and sends `out_msgs`.
+    ///
+    /// `storage` adds the storage fee due since the wallet last paid.
+    pub fn wallet_transfer(
+        &self,
+        external: &Arc<Cell>,
+        gas_used: u64,
+        out_msgs: &[Arc<Cell>],
+        storage: Option<StorageUsage>,
+    ) -> Result<TransferFees, FeeError> {
+        let wallet = external_destination(external)?;
+        let in_fwd_fee = self.forward_fee_for(wallet, CellStats::of_message(external));
+        let storage_fee = storage.map_or(0, |storage| {
+            self.storage_fee(wallet, storage.stats, storage.last_paid, storage.now)
+        });
+        let gas_fee = self.gas_fee(wallet, gas_used);
+        let out_msgs = out_msgs
+            .iter()
+            .map(|message| self.forward_fee(message))
+            .collect::<Result<Vec<_>, _>>()?;
+        let total = out_msgs.iter().fold(
+            in_fwd_fee
+                .saturating_add(storage_fee)
+                .saturating_add(gas_fee),
+            |total, fees| total.saturating_add(fees.total),
+        );
+        Ok(TransferFees {
+            in_fwd_fee,
+            storage_fee,
+            gas_fee,
+            out_msgs,
+            total,
+        })
+    }
+}
+
+/// Workchain of the destination of an inbound external message.
+fn external_destination(message: &Arc<Cell>) -> Result<i32, FeeError> {
+    let decoded = Message::from_cell(message.clone()).map_err(FeeError::InvalidMessage)?;
+    match &decoded.info {
+        CommonMsgInfo::ExternalIn { dest, .. } => Ok(workchain(dest)),
+        _ => Err(FeeError::NotExternalIn),
+    }
+}
+
+fn workchain(address: &MsgAddressInt) -> i32 {
+    match address {
+        MsgAddressInt::Std { address, .. } => i32::from(address.workchain),
+        MsgAddressInt::Var { workchain_id, .. } => *workchain_id,
+    }
+}
+
+/// Divides a `2^16`-scaled amount, rounding up.
+fn shift_ceil(scaled: u128) -> u128 {
+    scaled.div_ceil(1 << 16)
+}
+
+fn saturate(value: u128) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+pub(crate) mod test_config;
+#[cfg(test)]
+mod tests;
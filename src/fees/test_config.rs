@@ -0,0 +1,94 @@
+//! Price fixtures shared by the fee and emulator tests.
+
+use std::sync::Arc;
+
+use crate::tlb::{
+    ConfigParams, FlatGasPrice, GasLimitsPrices, MsgForwardPrices, StoragePrices,
+    StoragePricesConfig, TlbSerialize,
+};
+use crate::tvm::{BitKey, Builder, Cell, HashmapE};
+
+pub(crate) fn storage_prices(
+    utime_since: u32,
+    bit_price_ps: u64,
+    cell_price_ps: u64,
+) -> StoragePrices {
+    StoragePrices {
+        utime_since,
+        bit_price_ps,
+        cell_price_ps,
+        mc_bit_price_ps: bit_price_ps * 1000,
+        mc_cell_price_ps: cell_price_ps * 1000,
+    }
+}
+
+pub(crate) fn gas_prices(flat_gas_price: u64, gas_price: u64) -> GasLimitsPrices {
+    GasLimitsPrices {
+        flat: Some(FlatGasPrice {
+            gas_limit: 100,
+            gas_price: flat_gas_price,
+        }),
+        gas_price,
+        gas_limit: 1_000_000,
+        special_gas_limit: Some(35_000_000),
+        gas_credit: 10_000,
+        block_gas_limit: 37_000_000,
+        freeze_due_limit: 100_000_000,
+        delete_due_limit: 1_000_000_000,
+    }
+}
+
+pub(crate) fn fwd_prices(lump_price: u64, bit_price: u64, cell_price: u64) -> MsgForwardPrices {
+    MsgForwardPrices {
+        lump_price,
+        bit_price,
+        cell_price,
+        ihr_price_factor: 98_304,
+        first_frac: 21_845,
+        next_frac: 21_845,
+    }
+}
+
+/// Config dictionary with mainnet prices.
+pub(crate) fn config_params() -> ConfigParams {
+    let params: Vec<(u32, Arc<Cell>)> = vec![
+        (
+            18,
+            StoragePricesConfig {
+                prices: vec![storage_prices(0, 1, 500)],
+            }
+            .to_cell()
+            .unwrap(),
+        ),
+        (20, gas_prices(1_000_000, 655_360_000).to_cell().unwrap()),
+        (21, gas_prices(40_000, 26_214_400).to_cell().unwrap()),
+        (
+            24,
+            fwd_prices(10_000_000, 655_360_000, 65_536_000_000)
+                .to_cell()
+                .unwrap(),
+        ),
+        (
+            25,
+            fwd_prices(400_000, 26_214_400, 2_621_440_000)
+                .to_cell()
+                .unwrap(),
+        ),
+    ];
+    let mut dict = HashmapE::new(32);
+    for (id, cell) in params {
+        dict.insert_bit_key(BitKey::from_u64(id as u64, 32).unwrap(), cell)
+            .unwrap();
+    }
+    let mut config = Builder::new();
+    config
+        .store_hashmap_e_with(&dict, |builder, value| {
+            builder.store_ref(value.clone())?;
+            Ok(())
+        })
+        .unwrap();
+    ConfigParams {
+        config_addr: [0x55; 32],
+        config: config.build().unwrap(),
+    }
+}
@@ -0,0 +1,199 @@
+use super::*;
+
+use num_bigint::BigUint;
+
+use crate::fees::test_config::{config_params, storage_prices};
+use crate::tlb::{CurrencyCollection, Either, Grams, MsgAddressExt, TlbSerialize};
+use crate::tvm::{Address, Builder};
+
+fn comment(text: &str) -> Arc<Cell> {
+    let mut body = Builder::new();
+    body.store_u32(0).unwrap();
+    body.store_bytes(text.as_bytes()).unwrap();
+    body.build().unwrap()
+}
+
+fn internal(src: i8, dest: i8, body: Arc<Cell>) -> Arc<Cell> {
+    Message {
+        info: CommonMsgInfo::Internal {
+            ihr_disabled: true,
+            bounce: false,
+            bounced: false,
+            src: MsgAddressInt::std(Address::new(src, [0x11; 32])),
+            dest: MsgAddressInt::std(Address::new(dest, [0x22; 32])),
+            value: CurrencyCollection::grams(Grams::from(1_000_000_000)),
+            extra_flags: BigUint::from(0u8),
+            fwd_fee: Grams::from(0),
+            created_lt: 0,
+            created_at: 0,
+        },
+        init: None,
+        body: Either::Right(body),
+    }
+    .to_cell()
+    .unwrap()
+}
+
+fn external(body: Arc<Cell>) -> Arc<Cell> {
+    Message {
+        info: CommonMsgInfo::ExternalIn {
+            src: MsgAddressExt::None,
+            dest: MsgAddressInt::std(Address::new(0, [0x11; 32])),
+            import_fee: Grams::from(0),
+        },
+        init: None,
+        body: Either::Right(body),
+    }
+    .to_cell()
+    .unwrap()
+}
+
+#[test]
+fn reads_prices_from_config_params() {
+    let fees = FeeConfig::from_config(&config_params()).unwrap();
+
+    assert_eq!(fees.storage_prices, vec![storage_prices(0, 1, 500)]);
+    assert_eq!(fees.gas_prices(-1).gas_price, 655_360_000);
+    assert_eq!(fees.gas_prices(0).flat.unwrap().gas_price, 40_000);
+    assert_eq!(fees.msg_forward_prices(-1).lump_price, 10_000_000);
+    assert_eq!(fees.msg_forward_prices(0).lump_price, 400_000);
+
+    let mut config = Builder::new();
+    config.store_bit(false).unwrap();
+    let empty = ConfigParams {
+        config_addr: [0; 32],
+        config: config.build().unwrap(),
+    };
+    assert!(matches!(
+        FeeConfig::from_config(&empty),
+        Err(FeeError::MissingParam(18))
+    ));
+}
+
+#[test]
+fn gas_fee_applies_flat_price_then_scaled_price() {
+    let fees = FeeConfig::from_config(&config_params()).unwrap();
+
+    assert_eq!(fees.gas_fee(0, 0), 40_000);
+    assert_eq!(fees.gas_fee(0, 100), 40_000);
+    assert_eq!(fees.gas_fee(0, 3_308), 40_000 + 3_208 * 400);
+    assert_eq!(fees.gas_fee(-1, 101), 1_000_000 + 10_000);
+}
+
+#[test]
+fn message_stats_skip_the_root_and_shared_cells() {
+    let leaf = comment("hi");
+    let mut middle = Builder::new();
+    middle.store_u8(7).unwrap();
+    middle.store_ref(leaf.clone()).unwrap();
+    let middle = middle.build().unwrap();
+    let mut root = Builder::new();
+    root.store_u64(1).unwrap();
+    root.store_ref(middle).unwrap();
+    root.store_ref(leaf).unwrap();
+    let root = root.build().unwrap();
+
+    assert_eq!(
+        CellStats::of_message(&root),
+        CellStats {
+            cells: 2,
+            bits: 8 + 48
+        }
+    );
+    assert_eq!(
+        CellStats::of_tree(&root),
+        CellStats {
+            cells: 3,
+            bits: 64 + 8 + 48
+        }
+    );
+}
+
+#[test]
+fn forward_fee_splits_action_and_forwarded_parts() {
+    let fees = FeeConfig::from_config(&config_params()).unwrap();
+    // One referenced body cell of 72 bits.
+    let body = comment("hello");
+
+    let basechain = fees.forward_fee(&internal(0, 0, body.clone())).unwrap();
+    assert_eq!(basechain.total, 400_000 + 72 * 400 + 40_000);
+    assert_eq!(basechain.action_fee, 156_264);
+    assert_eq!(basechain.fwd_fee, basechain.total - 156_264);
+
+    // Masterchain prices apply when either end is in the masterchain.
+    let to_masterchain = fees.forward_fee(&internal(0, -1, body.clone())).unwrap();
+    let from_masterchain = fees.forward_fee(&internal(-1, 0, body)).unwrap();
+    assert_eq!(to_masterchain.total, 10_000_000 + 72 * 10_000 + 1_000_000);
+    assert_eq!(from_masterchain, to_masterchain);
+}
+
+#[test]
+fn storage_fee_follows_price_periods() {
+    let mut fees = FeeConfig::from_config(&config_params()).unwrap();
+    let stats = CellStats {
+        cells: 3,
+        bits: 1_000,
+    };
+
+    // (1000 * 1 + 3 * 500) * 86400 / 2^16, rounded up.
+    assert_eq!(fees.storage_fee(0, stats, 0, 86_400), 3_296);
+    assert_eq!(fees.storage_fee(0, stats, 100, 100), 0);
+
+    fees.storage_prices.push(storage_prices(1_000, 2, 1_000));
+    // 500s at 2500/s then 500s at 5000/s.
+    assert_eq!(
+        fees.storage_fee(0, stats, 500, 1_500),
+        3_750_000u64.div_ceil(1 << 16)
+    );
+    assert_eq!(
+        fees.storage_fee(-1, stats, 0, 1_000),
+        2_500_000_000u64.div_ceil(1 << 16)
+    );
+}
+
+#[test]
+fn wallet_transfer_adds_up_every_fee() {
+    let fees = FeeConfig::from_config(&config_params()).unwrap();
+    let out_msg = internal(0, 0, comment("hello"));
+    let mut body = Builder::new();
+    body.store_bytes(&[0xAA; 64]).unwrap();
+    body.store_u32(7).unwrap();
+    body.store_u8(3).unwrap();
+    body.store_ref(out_msg.clone()).unwrap();
+    let external = external(body.build().unwrap());
+    let storage = StorageUsage {
+        stats: CellStats {
+            cells: 3,
+            bits: 1_000,
+        },
+        last_paid: 0,
+        now: 86_400,
+    };
+
+    let transfer = fees
+        .wallet_transfer(
+            &external,
+            3_308,
+            std::slice::from_ref(&out_msg),
+            Some(storage),
+        )
+        .unwrap();
+
+    assert_eq!(
+        transfer.in_fwd_fee,
+        fees.forward_fee_for(0, CellStats::of_message(&external))
+    );
+    assert_eq!(transfer.in_fwd_fee, fees.import_fee(&external).unwrap());
+    assert_eq!(transfer.storage_fee, 3_296);
+    assert_eq!(transfer.gas_fee, 1_323_200);
+    assert_eq!(transfer.out_msgs, vec![fees.forward_fee(&out_msg).unwrap()]);
+    assert_eq!(
+        transfer.total,
+        transfer.in_fwd_fee + 3_296 + 1_323_200 + transfer.out_msgs[0].total
+    );
+
+    assert!(matches!(
+        fees.wallet_transfer(&out_msg, 0, &[], None),
+        Err(FeeError::NotExternalIn)
+    ));
+}
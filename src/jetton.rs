@@ -1,9 +1,15 @@
 //! TEP-74 jetton metadata helpers.
 //!
-//! This module decodes the `get_jetton_data()` stack returned by jetton master
-//! contracts and maps the contained TEP-64 content into jetton-oriented fields.
-//! Off-chain JSON fetching is intentionally outside this layer.
+//! This module decodes the `get_jetton_data()` and `get_wallet_data()` stacks
+//! returned by jetton master and wallet contracts and maps the contained TEP-64
+//! content into jetton-oriented fields. With `liteclient`, [`JettonMaster`] and
+//! [`JettonWallet`] bind those get-methods to a provider. Off-chain JSON
+//! fetching is intentionally outside this layer.
 
+#[cfg(feature = "liteclient")]
+mod client;
+#[cfg(all(test, feature = "liteclient"))]
+mod client_tests;
 mod payload;
 #[cfg(test)]
 mod payload_tests;
@@ -15,9 +21,12 @@ use num_bigint::{BigInt, BigUint};
 use std::sync::Arc;
 use thiserror::Error;
 
+#[cfg(feature = "liteclient")]
+pub use client::*;
 pub use payload::*;
 
 const JETTON_DATA_STACK_LEN: usize = 5;
+const JETTON_WALLET_DATA_STACK_LEN: usize = 4;
 
 /// Typed result of a TEP-74 jetton master `get_jetton_data()` call.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -250,6 +259,36 @@ pub enum JettonMetadataError {
     MalformedContent(#[from] MetadataError),
 }
 
+/// Typed result of a TEP-74 jetton wallet `get_wallet_data()` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JettonWalletData {
+    /// Wallet balance in indivisible jetton units.
+    pub balance: BigUint,
+    /// Standard internal address of the wallet owner.
+    pub owner_address: Address,
+    /// Standard internal address of the jetton master.
+    pub master_address: Address,
+    /// Jetton wallet code cell returned by the wallet.
+    pub jetton_wallet_code: Arc<Cell>,
+}
+
+/// Errors returned while decoding TEP-74 jetton wallet data.
+#[derive(Debug, Error)]
+pub enum JettonWalletDataError {
+    #[error("get_wallet_data stack has {actual} entries, expected {expected}")]
+    StackLength { actual: usize, expected: usize },
+    #[error("get_wallet_data stack entry {index} has type {actual}, expected {expected}")]
+    StackType {
+        index: usize,
+        expected: &'static str,
+        actual: &'static str,
+    },
+    #[error("get_wallet_data stack integer {field} is invalid: {reason}")]
+    InvalidInteger { field: &'static str, reason: String },
+    #[error("get_wallet_data {field} address is malformed: {reason}")]
+    MalformedAddress { field: &'static str, reason: String },
+}
+
 /// Decodes the successful stack returned by TEP-74 `get_jetton_data()`.
 pub fn decode_jetton_master_data(
     stack: Vec<TvmStackEntry>,
@@ -283,6 +322,92 @@ pub fn decode_jetton_master_data_stack(
     decode_jetton_master_data(stack.entries().to_vec())
 }
 
+/// Decodes the successful stack returned by TEP-74 `get_wallet_data()`.
+pub fn decode_jetton_wallet_data(
+    stack: Vec<TvmStackEntry>,
+) -> Result<JettonWalletData, JettonWalletDataError> {
+    if stack.len() != JETTON_WALLET_DATA_STACK_LEN {
+        return Err(JettonWalletDataError::StackLength {
+            actual: stack.len(),
+            expected: JETTON_WALLET_DATA_STACK_LEN,
+        });
+    }
+
+    let balance = match &stack[0] {
+        TvmStackEntry::Int(value) => {
+            value
+                .to_biguint()
+                .ok_or_else(|| JettonWalletDataError::InvalidInteger {
+                    field: "balance",
+                    reason: "value must be non-negative".to_string(),
+                })?
+        }
+        entry => {
+            return Err(JettonWalletDataError::StackType {
+                index: 0,
+                expected: "integer",
+                actual: stack_entry_type(entry),
+            });
+        }
+    };
+    let owner_address = parse_wallet_address(&stack, 1, "owner")?;
+    let master_address = parse_wallet_address(&stack, 2, "master")?;
+    let jetton_wallet_code = match &stack[3] {
+        TvmStackEntry::Cell(cell) => cell.clone(),
+        entry => {
+            return Err(JettonWalletDataError::StackType {
+                index: 3,
+                expected: "cell",
+                actual: stack_entry_type(entry),
+            });
+        }
+    };
+
+    Ok(JettonWalletData {
+        balance,
+        owner_address,
+        master_address,
+        jetton_wallet_code,
+    })
+}
+
+/// Decodes a `TvmStack` containing `get_wallet_data()` output.
+pub fn decode_jetton_wallet_data_stack(
+    stack: TvmStack,
+) -> Result<JettonWalletData, JettonWalletDataError> {
+    decode_jetton_wallet_data(stack.entries().to_vec())
+}
+
+fn parse_wallet_address(
+    stack: &[TvmStackEntry],
+    index: usize,
+    field: &'static str,
+) -> Result<Address, JettonWalletDataError> {
+    let cell = match &stack[index] {
+        TvmStackEntry::Slice(cell) | TvmStackEntry::Cell(cell) => cell.clone(),
+        entry => {
+            return Err(JettonWalletDataError::StackType {
+                index,
+                expected: "slice/cell",
+                actual: stack_entry_type(entry),
+            });
+        }
+    };
+    let malformed = |reason: String| JettonWalletDataError::MalformedAddress { field, reason };
+    let mut slice = Slice::new(cell);
+    let address = MsgAddress::load_tlb(&mut slice).map_err(|error| malformed(error.to_string()))?;
+    ensure_empty(&slice).map_err(|error| malformed(error.to_string()))?;
+    match address {
+        MsgAddress::Int(MsgAddressInt::Std {
+            anycast: None,
+            address,
+        }) => Ok(address),
+        _ => Err(malformed(
+            "expected a standard internal address without anycast".to_string(),
+        )),
+    }
+}
+
 fn parse_supply(value: &BigInt) -> Result<BigUint, JettonMetadataError> {
     value
         .to_biguint()
@@ -413,6 +538,27 @@ impl<'a, P: crate::contracts::ContractProvider + ?Sized> crate::contracts::Contr
     ) -> Result<JettonMetadata, crate::contracts::ContractError<P::Error>> {
         Ok(self.jetton_master_data_latest().await?.metadata())
     }
+
+    /// Runs `get_wallet_address(owner)` on a jetton master at the provider's
+    /// latest masterchain block.
+    pub async fn jetton_wallet_address_latest(
+        &mut self,
+        owner: Address,
+    ) -> Result<Address, crate::contracts::ContractError<P::Error>> {
+        self.run_get_method_by_name_latest_as("get_wallet_address", owner)
+            .await
+    }
+
+    /// Runs `get_wallet_data` on a jetton wallet at the provider's latest
+    /// masterchain block and decodes the returned TEP-74 stack.
+    pub async fn jetton_wallet_data_latest(
+        &mut self,
+    ) -> Result<JettonWalletData, crate::contracts::ContractError<P::Error>> {
+        let stack = self
+            .run_get_method_by_name_typed_latest("get_wallet_data", TvmStack::empty())
+            .await?;
+        decode_jetton_wallet_data(stack).map_err(crate::contracts::ContractError::decode)
+    }
 }
 
 #[cfg(test)]
@@ -752,6 +898,14 @@ mod tests {
             ) -> Result<TransactionList, Self::Error> {
                 unreachable!("jetton metadata helper must not fetch transactions")
             }
+
+            async fn get_config_param(
+                &mut self,
+                _block: BlockIdExt,
+                _id: u32,
+            ) -> Result<Option<Arc<Cell>>, Self::Error> {
+                unreachable!("jetton metadata helper must not fetch config params")
+            }
         }
 
         fn block(seqno: i32) -> BlockIdExt {
@@ -0,0 +1,112 @@
+//! Address-bound jetton master and wallet clients.
+
+use super::{JettonMasterData, JettonMetadata, JettonTransferPayload, JettonWalletData};
+use crate::contracts::{Contract, ContractError, ContractProvider};
+use crate::tvm::Address;
+use crate::wallet::{WalletMessage, WalletSendError, WalletV5R1};
+use ed25519_dalek::SigningKey;
+
+/// TEP-74 jetton master bound to an address and a provider.
+pub struct JettonMaster<'a, P: ContractProvider + ?Sized> {
+    inner: Contract<'a, P>,
+}
+
+impl<'a, P: ContractProvider + ?Sized> JettonMaster<'a, P> {
+    pub fn new(provider: &'a mut P, address: Address) -> Self {
+        Self {
+            inner: Contract::new(provider, address),
+        }
+    }
+
+    pub fn address(&self) -> &Address {
+        self.inner.address()
+    }
+
+    /// Jetton master contract, for get-methods without a typed wrapper.
+    pub fn contract(&mut self) -> &mut Contract<'a, P> {
+        &mut self.inner
+    }
+
+    /// Runs `get_jetton_data` at the provider's latest masterchain block.
+    pub async fn jetton_data(&mut self) -> Result<JettonMasterData, ContractError<P::Error>> {
+        self.inner.jetton_master_data_latest().await
+    }
+
+    /// Runs `get_jetton_data` and returns parsed jetton metadata fields.
+    pub async fn metadata(&mut self) -> Result<JettonMetadata, ContractError<P::Error>> {
+        self.inner.jetton_metadata_latest().await
+    }
+
+    /// Runs `get_wallet_address(owner)` and returns the owner's jetton wallet
+    /// address.
+    pub async fn wallet_address(
+        &mut self,
+        owner: Address,
+    ) -> Result<Address, ContractError<P::Error>> {
+        self.inner.jetton_wallet_address_latest(owner).await
+    }
+}
+
+/// TEP-74 jetton wallet bound to an address and a provider.
+pub struct JettonWallet<'a, P: ContractProvider + ?Sized> {
+    inner: Contract<'a, P>,
+}
+
+impl<'a, P: ContractProvider + ?Sized> JettonWallet<'a, P> {
+    pub fn new(provider: &'a mut P, address: Address) -> Self {
+        Self {
+            inner: Contract::new(provider, address),
+        }
+    }
+
+    pub fn address(&self) -> &Address {
+        self.inner.address()
+    }
+
+    /// Jetton wallet contract, for calling its get-methods directly.
+    pub fn contract(&mut self) -> &mut Contract<'a, P> {
+        &mut self.inner
+    }
+
+    /// Runs `get_wallet_data` at the provider's latest masterchain block.
+    pub async fn wallet_data(&mut self) -> Result<JettonWalletData, ContractError<P::Error>> {
+        self.inner.jetton_wallet_data_latest().await
+    }
+
+    /// Builds the wallet message that asks this jetton wallet to execute
+    /// `payload`, attaching `value` nanotons for fees and forwarding.
+    pub fn transfer_message(
+        &self,
+        value: u64,
+        payload: &JettonTransferPayload,
+    ) -> crate::tlb::Result<WalletMessage> {
+        payload.to_wallet_message(self.address().clone(), value)
+    }
+
+    /// Signs a `WalletV5R1` transfer carrying `payload` to this jetton wallet
+    /// and submits it through the bound provider.
+    ///
+    /// `wallet` must be the owner of this jetton wallet. The returned status
+    /// is the opaque `liteServer.SendMsgStatus.status` value, not proof of
+    /// inclusion.
+    pub async fn send_transfer(
+        &mut self,
+        wallet: &WalletV5R1,
+        seqno: u32,
+        valid_until: u32,
+        value: u64,
+        payload: &JettonTransferPayload,
+        signing_key: &SigningKey,
+    ) -> Result<u32, WalletSendError<P::Error>> {
+        let message = self
+            .transfer_message(value, payload)
+            .map_err(|error| WalletSendError::Build(error.into()))?;
+        let boc = wallet
+            .build_external_message_boc(seqno, valid_until, vec![message], signing_key, false)
+            .map_err(WalletSendError::Build)?;
+        self.inner
+            .send_external_message_boc(boc)
+            .await
+            .map_err(WalletSendError::Provider)
+    }
+}
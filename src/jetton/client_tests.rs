@@ -0,0 +1,348 @@
+use super::*;
+use crate::contracts::{ContractError, ContractProvider};
+use crate::liteclient::boc::{DecodedAccountState, SimpleAccount};
+use crate::tl::{
+    BlockIdExt, Int256,
+    common::{AccountId, ZeroStateIdExt},
+    response::{AccountState, MasterchainInfo, RunMethodResult, TransactionList},
+};
+use crate::tlb::{CommonMsgInfo, Either, Message, MessageRelaxed, StateInit, TlbSerialize};
+use crate::tvm::{Builder, deserialize_boc};
+use crate::wallet::{WALLET_V5R1_MAINNET_DEFAULT_ID, WalletV5R1, WalletV5R1ExternalBody};
+use async_trait::async_trait;
+use ed25519_dalek::SigningKey;
+
+#[derive(Debug, Error)]
+#[error("mock jetton client provider error")]
+struct MockProviderError;
+
+struct ExpectedCall {
+    account: Address,
+    method: &'static str,
+    stack: TvmStack,
+    result: TvmStack,
+}
+
+struct MockProvider {
+    latest: BlockIdExt,
+    calls: Vec<ExpectedCall>,
+    sent: Vec<Vec<u8>>,
+}
+
+#[async_trait]
+impl ContractProvider for MockProvider {
+    type Error = MockProviderError;
+
+    async fn get_masterchain_info(&mut self) -> Result<MasterchainInfo, Self::Error> {
+        Ok(MasterchainInfo {
+            last: self.latest.clone(),
+            state_root_hash: Int256([1; 32]),
+            init: ZeroStateIdExt {
+                workchain: -1,
+                root_hash: Int256([2; 32]),
+                file_hash: Int256([3; 32]),
+            },
+        })
+    }
+
+    async fn get_account_state(
+        &mut self,
+        _block: BlockIdExt,
+        _account: AccountId,
+    ) -> Result<AccountState, Self::Error> {
+        unreachable!("jetton clients must not fetch account state")
+    }
+
+    async fn get_account_state_typed(
+        &mut self,
+        _block: BlockIdExt,
+        _account: Address,
+    ) -> Result<DecodedAccountState, Self::Error> {
+        unreachable!("jetton clients must not fetch account state")
+    }
+
+    async fn get_account_state_simple(
+        &mut self,
+        _block: BlockIdExt,
+        _account: Address,
+    ) -> Result<SimpleAccount, Self::Error> {
+        unreachable!("jetton clients must not fetch account state")
+    }
+
+    async fn run_get_method(
+        &mut self,
+        mode: u32,
+        block: BlockIdExt,
+        account: Address,
+        method_id: u64,
+        stack: TvmStack,
+    ) -> Result<RunMethodResult, Self::Error> {
+        assert_eq!(mode, 0);
+        assert_eq!(block, self.latest);
+        let expected = self.calls.remove(0);
+        assert_eq!(account, expected.account);
+        assert_eq!(method_id, crate::utils::method_name_to_id(expected.method));
+        assert_eq!(stack, expected.stack);
+        Ok(RunMethodResult {
+            mode: (),
+            id: self.latest.clone(),
+            shardblk: self.latest.clone(),
+            shard_proof: None,
+            proof: None,
+            state_proof: None,
+            init_c7: None,
+            lib_extras: None,
+            exit_code: 0,
+            result: Some(expected.result.to_boc().unwrap()),
+        })
+    }
+
+    async fn send_external_message_boc(&mut self, body: Vec<u8>) -> Result<u32, Self::Error> {
+        self.sent.push(body);
+        Ok(1)
+    }
+
+    async fn get_transactions(
+        &mut self,
+        _count: u32,
+        _account: AccountId,
+        _lt: u64,
+        _hash: Int256,
+    ) -> Result<TransactionList, Self::Error> {
+        unreachable!("jetton clients must not fetch transactions")
+    }
+
+    async fn get_config_param(
+        &mut self,
+        _block: BlockIdExt,
+        _id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error> {
+        unreachable!("jetton clients must not fetch config params")
+    }
+}
+
+fn address(byte: u8) -> Address {
+    Address::new(0, [byte; 32])
+}
+
+fn address_slice(address: Address) -> TvmStackEntry {
+    TvmStackEntry::Slice(MsgAddressInt::std(address).to_cell().unwrap())
+}
+
+fn empty() -> Arc<Cell> {
+    Builder::new().build().unwrap()
+}
+
+fn provider(calls: Vec<ExpectedCall>) -> MockProvider {
+    MockProvider {
+        latest: BlockIdExt {
+            workchain: -1,
+            shard: i64::MIN,
+            seqno: 10,
+            root_hash: Int256([4; 32]),
+            file_hash: Int256([5; 32]),
+        },
+        calls,
+        sent: Vec::new(),
+    }
+}
+
+fn wallet_data_stack(balance: i64) -> TvmStack {
+    TvmStack::new(vec![
+        TvmStackEntry::int(balance),
+        address_slice(address(0x01)),
+        address_slice(address(0x02)),
+        TvmStackEntry::Cell(empty()),
+    ])
+}
+
+#[test]
+fn decodes_get_wallet_data_stack() {
+    let data = decode_jetton_wallet_data_stack(wallet_data_stack(5_000)).unwrap();
+    assert_eq!(data.balance, BigUint::from(5_000u32));
+    assert_eq!(data.owner_address, address(0x01));
+    assert_eq!(data.master_address, address(0x02));
+}
+
+#[test]
+fn rejects_malformed_get_wallet_data_stack() {
+    assert!(matches!(
+        decode_jetton_wallet_data(vec![]),
+        Err(JettonWalletDataError::StackLength {
+            actual: 0,
+            expected: 4
+        })
+    ));
+    assert!(matches!(
+        decode_jetton_wallet_data_stack(wallet_data_stack(-1)),
+        Err(JettonWalletDataError::InvalidInteger {
+            field: "balance",
+            ..
+        })
+    ));
+
+    let mut entries = wallet_data_stack(1).entries().to_vec();
+    entries[1] = TvmStackEntry::Slice(MsgAddress::Ext(MsgAddressExt::None).to_cell().unwrap());
+    assert!(matches!(
+        decode_jetton_wallet_data(entries),
+        Err(JettonWalletDataError::MalformedAddress { field: "owner", .. })
+    ));
+}
+
+#[tokio::test]
+async fn jetton_master_resolves_wallet_address_for_owner() {
+    let master = address(0xaa);
+    let mut provider = provider(vec![ExpectedCall {
+        account: master.clone(),
+        method: "get_wallet_address",
+        stack: TvmStack::new(vec![address_slice(address(0x01))]),
+        result: TvmStack::new(vec![address_slice(address(0xbb))]),
+    }]);
+    let mut client = JettonMaster::new(&mut provider, master);
+
+    assert_eq!(
+        client.wallet_address(address(0x01)).await.unwrap(),
+        address(0xbb)
+    );
+    assert!(provider.calls.is_empty());
+}
+
+#[tokio::test]
+async fn jetton_master_surfaces_decode_errors() {
+    let master = address(0xaa);
+    let mut provider = provider(vec![ExpectedCall {
+        account: master.clone(),
+        method: "get_jetton_data",
+        stack: TvmStack::empty(),
+        result: TvmStack::new(vec![TvmStackEntry::int(1)]),
+    }]);
+    let mut client = JettonMaster::new(&mut provider, master);
+
+    assert!(matches!(
+        client.jetton_data().await,
+        Err(ContractError::Decode(_))
+    ));
+}
+
+#[tokio::test]
+async fn jetton_master_reads_data_from_local_contract() {
+    // Minimal minter: `get_jetton_data` returns the stored supply, `-1`, the
+    // admin address, the content cell and the wallet code.
+    // DROP, PUSHCTR c4, CTOS, LDGRAMS, PUSHINT -1, SWAP, LDMSGADDR, LDREF,
+    // LDREF, ENDS.
+    let mut code = Builder::new();
+    code.store_bytes(&hex::decode("30ed44d0fa007f01fa40d4d4d1").unwrap())
+        .unwrap();
+    let mut content = Builder::new();
+    content.store_u8(0x01).unwrap();
+    content
+        .store_bytes(b"https://example.com/jetton.json")
+        .unwrap();
+    let content = content.build().unwrap();
+    let mut wallet_code = Builder::new();
+    wallet_code.store_u32(0xdead_beef).unwrap();
+    let wallet_code = wallet_code.build().unwrap();
+    let mut data = Builder::new();
+    data.store_coins(21_000_000).unwrap();
+    MsgAddressInt::std(address(0xad))
+        .store_tlb(&mut data)
+        .unwrap();
+    data.store_ref(content.clone()).unwrap();
+    data.store_ref(wallet_code.clone()).unwrap();
+
+    let mut provider = crate::contracts::LocalContractProvider::new();
+    let master = provider
+        .insert_state_init(
+            0,
+            &StateInit {
+                code: Some(code.build().unwrap()),
+                data: Some(data.build().unwrap()),
+                ..StateInit::empty()
+            },
+        )
+        .unwrap();
+    let data = JettonMaster::new(&mut provider, master)
+        .jetton_data()
+        .await
+        .unwrap();
+
+    assert_eq!(data.total_supply, BigUint::from(21_000_000u32));
+    assert!(data.mintable);
+    assert_eq!(data.admin_address, Some(address(0xad)));
+    assert_eq!(
+        data.jetton_content,
+        Tep64Content::OffChain {
+            uri: b"https://example.com/jetton.json".to_vec(),
+            raw: content,
+        }
+    );
+    assert_eq!(data.jetton_wallet_code, wallet_code);
+}
+
+#[tokio::test]
+async fn jetton_wallet_reads_wallet_data() {
+    let jetton_wallet = address(0xbb);
+    let mut provider = provider(vec![ExpectedCall {
+        account: jetton_wallet.clone(),
+        method: "get_wallet_data",
+        stack: TvmStack::empty(),
+        result: wallet_data_stack(42),
+    }]);
+    let mut client = JettonWallet::new(&mut provider, jetton_wallet);
+
+    let data = client.wallet_data().await.unwrap();
+    assert_eq!(data.balance, BigUint::from(42u32));
+    assert_eq!(data.master_address, address(0x02));
+}
+
+#[tokio::test]
+async fn jetton_wallet_sends_v5r1_transfer_carrying_payload() {
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let owner = WalletV5R1::new(
+        key.verifying_key().to_bytes(),
+        WALLET_V5R1_MAINNET_DEFAULT_ID,
+        crate::wallet::wallet_v5r1_code().unwrap(),
+        0,
+    );
+    let owner_address = owner.address().unwrap();
+    let payload = JettonTransferPayload::new(9, 1_000u32, address(0x33), owner_address.clone());
+
+    let jetton_wallet = address(0xbb);
+    let mut provider = provider(Vec::new());
+    let status = JettonWallet::new(&mut provider, jetton_wallet.clone())
+        .send_transfer(&owner, 3, 1_700_000_000, 50_000_000, &payload, &key)
+        .await
+        .unwrap();
+    assert_eq!(status, 1);
+    assert_eq!(provider.sent.len(), 1);
+
+    let message = Message::from_cell(deserialize_boc(&provider.sent[0]).unwrap()).unwrap();
+    let CommonMsgInfo::ExternalIn { dest, .. } = message.info else {
+        panic!("expected external-in message");
+    };
+    assert_eq!(dest, MsgAddressInt::std(owner_address));
+    assert!(message.init.is_none());
+    let Either::Right(body) = message.body else {
+        panic!("wallet body must be referenced");
+    };
+    let body = WalletV5R1ExternalBody::from_cell(body).unwrap();
+    assert_eq!(body.seqno, 3);
+    let actions = body.out_list.unwrap().actions;
+    assert_eq!(actions.len(), 1);
+    let crate::tlb::OutAction::SendMsg { out_msg, .. } = &actions[0] else {
+        panic!("expected send_msg action");
+    };
+    let MessageRelaxed {
+        info: crate::tlb::CommonMsgInfoRelaxed::Internal { dest, .. },
+        body: Either::Right(inner),
+        ..
+    } = out_msg
+    else {
+        panic!("transfer body does not fit inline and must be referenced");
+    };
+    assert_eq!(dest, &MsgAddressInt::std(jetton_wallet));
+    assert_eq!(
+        JettonTransferPayload::from_cell(inner.clone()).unwrap(),
+        payload
+    );
+}
@@ -1,5 +1,6 @@
 //! TEP-74 jetton message body builders.
 
+use crate::comment::{CommentError, MessageComment, parse_comment, text_comment_cell};
 use crate::tlb::{
     CellRef, Either, Grams, MsgAddress, MsgAddressInt, Result, TlbDeserialize, TlbSerialize,
 };
@@ -70,6 +71,18 @@ impl JettonTransferPayload {
     pub fn to_cell(&self) -> Result<Arc<Cell>> {
         TlbSerialize::to_cell(self)
     }
+
+    /// Wraps this body in a wallet message to the sender's jetton wallet.
+    ///
+    /// `value` must cover `forward_ton_amount` plus the jetton wallet's gas and
+    /// forwarding fees; unspent TON returns to `response_destination`.
+    pub fn to_wallet_message(
+        &self,
+        jetton_wallet: Address,
+        value: u64,
+    ) -> Result<crate::wallet::WalletMessage> {
+        Ok(crate::wallet::WalletMessage::internal(jetton_wallet, value).with_body(self.to_cell()?))
+    }
 }
 
 impl TlbSerialize for JettonTransferPayload {
@@ -260,6 +273,27 @@ pub fn empty_forward_payload() -> ForwardPayload {
     inline_forward_payload(Builder::new().build().expect("empty cell builds"))
 }
 
+/// Builds a referenced forward payload carrying an op `0` text comment.
+///
+/// The same payload type is used by NFT `transfer` and `ownership_assigned`
+/// bodies.
+pub fn comment_forward_payload(text: &str) -> std::result::Result<ForwardPayload, CommentError> {
+    Ok(referenced_forward_payload(text_comment_cell(text)?))
+}
+
+/// Decodes a text or encrypted comment carried by a forward payload.
+///
+/// Returns `Ok(None)` for empty payloads and payloads with another op.
+pub fn forward_payload_comment(
+    payload: &ForwardPayload,
+) -> std::result::Result<Option<MessageComment>, CommentError> {
+    let cell = match payload {
+        Either::Left(cell) => cell.clone(),
+        Either::Right(CellRef(cell)) => cell.clone(),
+    };
+    parse_comment(cell)
+}
+
 pub(crate) fn std_address(address: Address) -> MsgAddress {
     MsgAddress::Int(MsgAddressInt::std(address))
 }
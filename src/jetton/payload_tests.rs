@@ -85,3 +85,23 @@ fn rejects_wrong_jetton_payload_opcode() {
 
     assert!(err.to_string().contains("operation code"));
 }
+
+#[test]
+fn comment_forward_payload_roundtrips_through_transfer() {
+    let payload = JettonTransferPayload::new(7, 1u8, address(1), address(2)).with_forward_payload(
+        BigUint::from(1u8),
+        comment_forward_payload(&"long comment ".repeat(20)).unwrap(),
+    );
+    let decoded = JettonTransferPayload::from_cell(payload.to_cell().unwrap()).unwrap();
+
+    assert_eq!(
+        forward_payload_comment(&decoded.forward_payload).unwrap(),
+        Some(crate::comment::MessageComment::Text(
+            "long comment ".repeat(20)
+        ))
+    );
+    assert_eq!(
+        forward_payload_comment(&empty_forward_payload()).unwrap(),
+        None
+    );
+}
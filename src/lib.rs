@@ -10,9 +10,25 @@
 //! - `tvm`: cells, slices, builders, BoC helpers, addresses, dictionaries,
 //!   TL-B helpers, and TVM stack values.
 //! - `adnl` and `adnl-tcp`: ADNL primitives and the native TCP transport.
+//! - `adnl-udp`: ADNL UDP nodes with channels and signed packets.
+//! - `dht`: Kademlia DHT client for finding nodes, values, and ADNL address
+//!   lists.
+//! - `rldp`: RLDP v1/v2 transfers with RaptorQ FEC for large queries and
+//!   answers over ADNL UDP.
+//! - `overlay`: public overlay membership, peer exchange, and signed
+//!   broadcast streams.
+//! - `mempool`: opt-in stream of pending external messages from shard
+//!   overlays.
 //! - `liteclient`: LiteAPI client, LiteBalancer, and LiteClient BoC helpers.
 //! - `network-config`: TON global config parsing and liteserver extraction.
 //! - `cli`: command-line interface support.
+//! - `comment`: text and encrypted message comment codecs.
+//! - `dns`: TEP-81 domain encoding, DNS record codecs, and, with
+//!   `liteclient`, a recursive `dnsresolve` resolver.
+//! - `emulator`: offline ordinary transactions of inbound messages against
+//!   an account.
+//! - `fees`: forward, import, gas, and storage fee estimates from config
+//!   parameters.
 //! - `wallet`: offline wallet data, signing, and message-construction helpers.
 //!
 //! The crate preserves raw protocol bytes where typed models are incomplete.
@@ -26,21 +42,37 @@ pub mod abi;
 pub mod adnl;
 #[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "tvm")]
+pub mod comment;
 #[cfg(feature = "liteclient")]
 pub mod contracts;
 #[cfg(feature = "contract-derive")]
 pub use tonutils_macros::Contract;
 pub mod crc;
+#[cfg(feature = "dht")]
+pub mod dht;
+#[cfg(feature = "tvm")]
+pub mod dns;
+#[cfg(feature = "tvm")]
+pub mod emulator;
+#[cfg(feature = "tvm")]
+pub mod fees;
 #[cfg(feature = "tvm")]
 pub mod jetton;
 #[cfg(feature = "liteclient")]
 pub mod liteclient;
+#[cfg(feature = "mempool")]
+pub mod mempool;
 #[cfg(feature = "tvm")]
 pub mod metadata;
 #[cfg(feature = "network-config")]
 pub mod network_config;
 #[cfg(feature = "tvm")]
 pub mod nft;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+#[cfg(feature = "rldp")]
+pub mod rldp;
 #[cfg(feature = "tl")]
 pub mod tl;
 #[cfg(feature = "tvm")]
@@ -15,6 +15,7 @@ pub(super) use crate::liteclient::{
         DecodedShardInfo, SimpleAccount,
     },
     client::LiteClient,
+    proof::Verified,
     rate_limit::RateLimiter,
     rate_limit::RequestRateLimit,
     types::LiteError,
@@ -561,10 +561,15 @@ impl LiteBalancer {
             let alive_count = self.alive_peers.read().await.len();
             if alive_count < 12 { 4 } else { alive_count / 3 }
         };
+        self.send_message_to_peers(body, k).await
+    }
 
+    /// Sends `body` to up to `peers` distinct alive peers and returns the
+    /// status of the first one that accepted it.
+    pub async fn send_message_to_peers(&mut self, body: Vec<u8>, peers: usize) -> Result<u32> {
         let mut results = Vec::new();
         let mut attempted = HashSet::new();
-        for _ in 0..k.min(self.peers.len()) {
+        for _ in 0..peers.min(self.peers.len()) {
             let (peer_idx, start) = match self
                 .execute_request_excluding::<u32>(false, &attempted)
                 .await
@@ -643,12 +648,15 @@ impl LiteBalancer {
         )
     }
 
+    /// Balanced [`LiteClient::get_account_state_typed`]. With `block = None`
+    /// the anchor is the head reported by the serving peer, which proves
+    /// consistency with that peer only.
     pub async fn get_account_state_typed(
         &mut self,
         account: Address,
         block: Option<BlockIdExt>,
-    ) -> Result<DecodedAccountState> {
-        balanced_call!(self, DecodedAccountState, false, |client| {
+    ) -> Result<Verified<DecodedAccountState>> {
+        balanced_call!(self, Verified<DecodedAccountState>, false, |client| {
             client.get_account_state_typed(account.clone(), block.clone())
         })
     }
@@ -804,6 +812,31 @@ impl LiteBalancer {
         })
     }
 
+    /// Looks up masterchain block `seqno`, letting the liteserver wait for it
+    /// if it is not there yet.
+    pub async fn wait_masterchain_block(&mut self, seqno: u32) -> Result<BlockHeader> {
+        let id = BlockId {
+            workchain: -1,
+            shard: i64::MIN,
+            seqno: seqno as i32,
+        };
+        balanced_call!(self, BlockHeader, false, |client| {
+            client.set_wait_masterchain_seqno(seqno);
+            client.lookup_block(
+                (),
+                id.clone(),
+                Some(()),
+                None,
+                None,
+                false,
+                false,
+                false,
+                false,
+                false,
+            )
+        })
+    }
+
     pub async fn get_all_shards_info_typed(
         &mut self,
         block: BlockIdExt,
@@ -932,6 +965,16 @@ impl LiteBalancer {
         })
     }
 
+    pub async fn get_config_param(
+        &mut self,
+        block: BlockIdExt,
+        id: u32,
+    ) -> Result<Option<Arc<crate::tvm::Cell>>> {
+        balanced_call!(self, Option<Arc<crate::tvm::Cell>>, false, |client| {
+            client.get_config_param(block.clone(), id)
+        })
+    }
+
     pub async fn get_libraries_typed(
         &mut self,
         library_list: Vec<Int256>,
@@ -229,3 +229,48 @@ async fn send_message_attempts_distinct_peers_and_returns_first_success() {
     assert_eq!(*peer0_calls.lock().await, 1);
     assert_eq!(*peer1_calls.lock().await, 1);
 }
+
+#[tokio::test]
+async fn send_message_to_peers_stops_at_requested_fan_out() {
+    let peer0_calls = Arc::new(Mutex::new(0usize));
+    let peer1_calls = Arc::new(Mutex::new(0usize));
+    let peers = vec![
+        response_client(
+            Arc::clone(&peer0_calls),
+            Err(LiteError::AdnlError(AdnlError::EndOfStream)),
+        ),
+        response_client(
+            Arc::clone(&peer1_calls),
+            Ok(crate::tl::response::Response::SendMsgStatus(
+                crate::tl::response::SendMsgStatus { status: 1 },
+            )),
+        ),
+    ];
+    let mut balancer = LiteBalancer::new(peers, Duration::from_millis(25));
+    balancer.alive_peers.write().await.extend([0, 1]);
+    balancer.peer_stats.write().await.extend([
+        (
+            0,
+            PeerStats {
+                ewma_latency_ms: Some(1),
+                last_observed_seqno: 1,
+                ..Default::default()
+            },
+        ),
+        (
+            1,
+            PeerStats {
+                ewma_latency_ms: Some(2),
+                last_observed_seqno: 1,
+                ..Default::default()
+            },
+        ),
+    ]);
+
+    assert!(matches!(
+        balancer.send_message_to_peers(vec![1, 2, 3], 1).await,
+        Err(BalancerError::Timeout)
+    ));
+    assert_eq!(*peer0_calls.lock().await, 1);
+    assert_eq!(*peer1_calls.lock().await, 0);
+}
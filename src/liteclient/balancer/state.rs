@@ -11,6 +11,7 @@ impl LiteBalancer {
             LiteError::ServerError(_)
             | LiteError::TlError(_)
             | LiteError::UnexpectedMessage
+            | LiteError::ProofError(_)
             | LiteError::UnknownError(_) => None,
         }
     }
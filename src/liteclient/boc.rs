@@ -1,7 +1,8 @@
 //! LiteClient BoC decode helpers.
 //!
 //! Helpers in this module preserve the original bytes and expose decoded cells
-//! or TL-B views. Proof verification is explicit and opt-in.
+//! or TL-B views. Proof verification is explicit and opt-in; see
+//! [`crate::liteclient::proof`] for checks anchored at a masterchain block.
 
 use crate::tl::{
     common::{BlockIdExt, Int256},
@@ -11,16 +12,21 @@ use crate::tl::{
     },
 };
 use crate::tlb::{
-    Account, Block, ConfigParams, MerkleProof, MerkleUpdate, MsgAddressInt, ShardAccount,
-    ShardState, TlbDeserialize, Transaction,
+    Account, Block, ConfigParams, ExtBlkRef, MerkleProof, MerkleUpdate, MsgAddressInt,
+    ShardAccount, ShardState, TlbDeserialize, Transaction,
 };
 use crate::tvm::{
-    Address, BocInspection, Cell, deserialize_boc, deserialize_boc_roots, inspect_boc,
+    Address, BocInspection, Cell, Slice, deserialize_boc, deserialize_boc_roots, inspect_boc,
 };
 use anyhow::{Context, Result, bail};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+const BLOCK_INFO_TAG: u32 = 0x9bc7a987;
+const SHARD_DESCR_TAG: u8 = 0xb;
+const SHARD_DESCR_NEW_TAG: u8 = 0xa;
+const WORKCHAIN_KEY_BITS: usize = 32;
+
 /// Raw BoC bytes with a decoded root cell.
 #[derive(Debug, Clone)]
 pub struct DecodedBoc {
@@ -401,12 +407,17 @@ pub(crate) fn decode_optional_config(raw: &[u8]) -> Result<Option<DecodedConfigP
     }
 }
 
-pub(crate) fn decode_single_transaction_list(raw: &[u8]) -> Result<Vec<Transaction>> {
+/// Decodes every root of a transaction-list BoC; liteservers return one root
+/// per transaction.
+pub(crate) fn decode_transaction_list(raw: &[u8]) -> Result<Vec<Transaction>> {
     if raw.is_empty() {
-        Ok(Vec::new())
-    } else {
-        decode_transaction_boc(raw).map(|transaction| vec![transaction])
+        return Ok(Vec::new());
     }
+    deserialize_boc_roots(raw)
+        .context("failed to decode transaction list BoC")?
+        .into_iter()
+        .map(|root| Transaction::from_cell(root).context("failed to decode Transaction TL-B"))
+        .collect()
 }
 
 impl DecodedAccountState {
@@ -515,6 +526,138 @@ fn simple_account_state(account: &Account) -> SimpleAccountState {
     }
 }
 
+impl DecodedAllShardsInfo {
+    /// Top block of every shard listed in the shard dictionary.
+    pub fn shard_blocks(&self) -> Result<Vec<BlockIdExt>> {
+        decode_shard_hashes(self.data.root.clone())
+    }
+}
+
+impl DecodedBlockHeader {
+    /// Ids of the blocks this block was built on.
+    ///
+    /// Returns two ids after a merge, one otherwise, and none for a zero
+    /// state. The header proof must prove `raw.id`'s root hash; the ids are
+    /// read from its `BlockInfo.prev_ref`.
+    pub fn prev_blocks(&self) -> Result<Vec<BlockIdExt>> {
+        let id = &self.raw.id;
+        if id.seqno == 0 {
+            return Ok(Vec::new());
+        }
+        let proof = MerkleProof::from_exotic_cell(self.header_proof.root.clone())
+            .context("failed to decode block header proof")?;
+        if !proof.verify_virtual_hash() || proof.virtual_hash != id.root_hash.0 {
+            bail!("block header proof does not match block {id}");
+        }
+        let block = Block::from_cell(proof.virtual_root).context("failed to decode Block TL-B")?;
+        let mut info = Slice::new(block.info);
+        if info.load_u32()? != BLOCK_INFO_TAG {
+            bail!("unexpected BlockInfo tag");
+        }
+        info.skip_bits(32)?; // version
+        let not_master = info.load_bit()?;
+        let after_merge = info.load_bit()?;
+        info.skip_bits(1)?; // before_split
+        let after_split = info.load_bit()?;
+        if not_master {
+            info.skip_refs(1)?; // master_ref
+        }
+        let mut prev = Slice::new(info.load_reference()?);
+
+        let shard = id.shard as u64;
+        let low_bit = shard & shard.wrapping_neg();
+        if after_merge {
+            let left = ExtBlkRef::from_cell(prev.load_reference()?)?;
+            let right = ExtBlkRef::from_cell(prev.load_reference()?)?;
+            Ok(vec![
+                prev_block_id(id.workchain, shard - (low_bit >> 1), &left),
+                prev_block_id(id.workchain, shard + (low_bit >> 1), &right),
+            ])
+        } else {
+            let prev = ExtBlkRef::load_tlb(&mut prev)?;
+            let shard = if after_split {
+                (shard - low_bit) | (low_bit << 1)
+            } else {
+                shard
+            };
+            Ok(vec![prev_block_id(id.workchain, shard, &prev)])
+        }
+    }
+}
+
+fn prev_block_id(workchain: i32, shard: u64, prev: &ExtBlkRef) -> BlockIdExt {
+    BlockIdExt {
+        workchain,
+        shard: shard as i64,
+        seqno: prev.seq_no as i32,
+        root_hash: Int256(prev.root_hash),
+        file_hash: Int256(prev.file_hash),
+    }
+}
+
+/// Decodes `ShardHashes` (`HashmapE 32 ^(BinTree ShardDescr)`), as carried by
+/// `liteServer.allShardsInfo.data`, into the top block of every shard.
+pub fn decode_shard_hashes(root: Arc<Cell>) -> Result<Vec<BlockIdExt>> {
+    let workchains = Slice::new(root)
+        .load_hashmap_e_with(WORKCHAIN_KEY_BITS, |slice| slice.load_reference())
+        .context("failed to decode ShardHashes dictionary")?;
+    let mut blocks = Vec::new();
+    for (key, tree) in workchains.iter() {
+        let workchain = key.to_u64()? as u32 as i32;
+        collect_shard_descrs(tree.clone(), workchain, 1 << 63, &mut blocks)?;
+    }
+    Ok(blocks)
+}
+
+/// Walks a `BinTree ShardDescr`; `shard` is the shard the node covers.
+fn collect_shard_descrs(
+    node: Arc<Cell>,
+    workchain: i32,
+    shard: u64,
+    blocks: &mut Vec<BlockIdExt>,
+) -> Result<()> {
+    let mut slice = Slice::new(node);
+    if slice.load_bit()? {
+        let half = (shard & shard.wrapping_neg()) >> 1;
+        if half == 0 {
+            bail!("shard tree is deeper than 63 levels");
+        }
+        let left = slice.load_reference()?;
+        let right = slice.load_reference()?;
+        collect_shard_descrs(left, workchain, shard - half, blocks)?;
+        return collect_shard_descrs(right, workchain, shard + half, blocks);
+    }
+
+    blocks.push(load_shard_descr_id(&mut slice, workchain, shard)?);
+    Ok(())
+}
+
+/// Reads the top block id from a `ShardDescr` leaf of a shard tree covering
+/// `shard` of `workchain`.
+pub(crate) fn load_shard_descr_id(
+    slice: &mut Slice,
+    workchain: i32,
+    shard: u64,
+) -> Result<BlockIdExt> {
+    let tag = slice.load_uint_custom::<u8>(4)?;
+    if tag != SHARD_DESCR_TAG && tag != SHARD_DESCR_NEW_TAG {
+        bail!("unexpected ShardDescr tag {tag:x}");
+    }
+    let seqno = slice.load_u32()?;
+    slice.skip_bits(32 + 64 + 64)?; // reg_mc_seqno, start_lt, end_lt
+    let mut root_hash = [0; 32];
+    root_hash.copy_from_slice(&slice.load_bytes(32)?);
+    let mut file_hash = [0; 32];
+    file_hash.copy_from_slice(&slice.load_bytes(32)?);
+    Ok(BlockIdExt {
+        workchain,
+        shard: shard as i64,
+        seqno: seqno as i32,
+        root_hash: Int256(root_hash),
+        file_hash: Int256(file_hash),
+    })
+}
+
 /// Decodes an exotic Merkle proof cell. This does not verify trust roots.
 pub fn decode_merkle_proof_boc(raw: impl AsRef<[u8]>) -> Result<MerkleProof> {
     let boc = DecodedBoc::decode(raw)?;
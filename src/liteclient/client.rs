@@ -4,10 +4,11 @@ pub(super) use crate::liteclient::{
         DecodedAccountState, DecodedAllShardsInfo, DecodedBlockData, DecodedBlockHeader,
         DecodedBlockTransactionsExt, DecodedConfigInfo, DecodedLibrariesWithProof,
         DecodedShardInfo, DecodedTransactionInfo, SimpleAccount, decode_block_boc,
-        decode_optional_boc, decode_optional_config, decode_single_transaction_list,
+        decode_optional_boc, decode_optional_config, decode_transaction_list,
     },
     layers::WrapRawMessagesLayer,
     peer::LitePeer,
+    proof::{BlockProofChain, ProofError, Verified, verify_account_state},
     rate_limit::{RateLimiter, RequestRateLimit},
     types::LiteError,
 };
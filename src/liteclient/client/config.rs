@@ -1 +1,36 @@
+use super::*;
 
+impl LiteClient {
+    /// Fetches raw config parameter `id` at `block` through
+    /// `liteServer.getConfigParams`, or `None` when the parameter is absent.
+    pub async fn get_config_param(
+        &mut self,
+        block: BlockIdExt,
+        id: u32,
+    ) -> Result<Option<Arc<crate::tvm::Cell>>> {
+        let info = self
+            .get_config_params_typed(
+                block,
+                vec![id as i32],
+                false,
+                false,
+                false,
+                false,
+                false,
+                false,
+                false,
+                false,
+                false,
+                false,
+                false,
+            )
+            .await?;
+        let Some(config) = info.config_proof else {
+            return Ok(None);
+        };
+        config
+            .config
+            .raw_param(id)
+            .map_err(|error| decode_error(error.into()))
+    }
+}
@@ -84,10 +84,16 @@ impl LiteClient {
     }
 
     pub fn wait_masterchain_seqno(mut self, seqno: u32) -> Self {
-        self.wait_seqno = Some(seqno);
+        self.set_wait_masterchain_seqno(seqno);
         self
     }
 
+    /// Makes the next request wait until the liteserver has masterchain
+    /// block `seqno`.
+    pub fn set_wait_masterchain_seqno(&mut self, seqno: u32) {
+        self.wait_seqno = Some(seqno);
+    }
+
     pub fn with_rate_limit(mut self, limit: RequestRateLimit) -> Self {
         self.set_rate_limit(limit);
         self
@@ -290,22 +296,33 @@ impl LiteClient {
         Option<crate::tlb::Account>,
         Option<crate::tlb::ShardAccount>,
     )> {
-        let decoded = self.get_account_state_typed(account, block).await?;
+        let decoded = self
+            .get_account_state_typed(account, block)
+            .await?
+            .into_inner();
         Ok((decoded.account, decoded.shard_account))
     }
 
+    /// Fetches the account state at `block` (the latest masterchain block by
+    /// default) and verifies its shard and account proofs against that block.
+    ///
+    /// With `block = None` the anchor is the `last` block reported by this
+    /// same server, so the result only proves consistency with the server's
+    /// own head. Pass a block from [`LiteClient::sync_block_proof`] to check
+    /// the state without trusting the server.
     pub async fn get_account_state_typed(
         &mut self,
         account: Address,
         block: Option<BlockIdExt>,
-    ) -> Result<DecodedAccountState> {
+    ) -> Result<Verified<DecodedAccountState>> {
         let id = match block {
             Some(block) => block,
             None => self.get_masterchain_info().await?.last,
         };
-        let raw = self.get_account_state(id, account.to_account_id()).await?;
-        let _ = account;
-        DecodedAccountState::from_raw(raw).map_err(decode_error)
+        let raw = self
+            .get_account_state(id.clone(), account.to_account_id())
+            .await?;
+        Ok(verify_account_state(&id, &account, raw)?)
     }
 
     pub async fn get_account_state_simple(&mut self, account: Address) -> Result<SimpleAccount> {
@@ -448,11 +465,10 @@ impl LiteClient {
         &mut self,
         block: BlockIdExt,
     ) -> Result<Vec<BlockIdExt>> {
-        let _ = self.raw_get_all_shards_info(block).await?;
-        Err(LiteError::TlError(crate::tl::TlError::ParseError(
-            "typed all-shards dictionary decode requires full ShardDescr/BinTree TL-B models"
-                .to_string(),
-        )))
+        self.raw_get_all_shards_info(block)
+            .await?
+            .shard_blocks()
+            .map_err(decode_error)
     }
 
     pub async fn get_one_transaction(
@@ -530,8 +546,7 @@ impl LiteClient {
         hash: Int256,
     ) -> Result<(Vec<crate::tlb::Transaction>, Vec<BlockIdExt>)> {
         let raw = self.get_transactions(count, account, lt, hash).await?;
-        let transactions =
-            decode_single_transaction_list(&raw.transactions).map_err(decode_error)?;
+        let transactions = decode_transaction_list(&raw.transactions).map_err(decode_error)?;
         Ok((transactions, raw.ids))
     }
 
@@ -653,8 +668,7 @@ impl LiteClient {
         let raw = self
             .list_block_transactions_ext(id, count, after, reverse_order, want_proof)
             .await?;
-        let transactions =
-            decode_single_transaction_list(&raw.transactions).map_err(decode_error)?;
+        let transactions = decode_transaction_list(&raw.transactions).map_err(decode_error)?;
         let proof = decode_optional_boc(&raw.proof).map_err(decode_error)?;
         Ok(DecodedBlockTransactionsExt {
             raw,
@@ -685,6 +699,31 @@ impl LiteClient {
         Ok(response)
     }
 
+    /// Requests block proofs from `chain.last()` until `target` (the latest
+    /// masterchain block by default) is reached, applying each to `chain`.
+    ///
+    /// Returns the block the server proved last, which is then trusted.
+    pub async fn sync_block_proof(
+        &mut self,
+        chain: &mut BlockProofChain,
+        target: Option<BlockIdExt>,
+    ) -> Result<BlockIdExt> {
+        let mut known = chain.last().clone();
+        loop {
+            let proof = self
+                .get_block_proof(known.clone(), target.clone(), false, false)
+                .await?;
+            let complete = chain.apply(&proof)?;
+            if complete {
+                return Ok(proof.to);
+            }
+            if proof.to == known {
+                return Err(ProofError::Stalled(Box::new(known)).into());
+            }
+            known = proof.to;
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn get_config_all(
         &mut self,
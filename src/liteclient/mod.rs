@@ -5,8 +5,10 @@ pub mod boc;
 pub mod client;
 pub mod layers;
 pub mod peer;
+pub mod proof;
 pub mod rate_limit;
 pub mod server;
+pub mod stream;
 pub mod types;
 
 #[cfg(test)]
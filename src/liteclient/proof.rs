@@ -0,0 +1,477 @@
+//! LiteServer proof verification.
+//!
+//! Verifiers in this module check Merkle proofs returned by a liteserver
+//! against a masterchain block id the caller already trusts. A successful
+//! check yields [`Verified`], which records that anchor. [`BlockProofChain`]
+//! derives trusted masterchain blocks from an init block by checking
+//! validator signatures.
+
+use crate::liteclient::boc::{DecodedAccountState, load_shard_descr_id};
+use crate::tl::{
+    common::{BlockIdExt, Int256},
+    response::AccountState,
+};
+use crate::tlb::{
+    Block, CurrencyCollection, MerkleProof, ShardAccount, ShardIdent, TlbDeserialize,
+};
+use crate::tvm::{
+    Address, BitKey, Cell, ExoticCellKind, Slice, deserialize_boc, deserialize_boc_roots,
+    lookup_hashmap_path,
+};
+use std::ops::Deref;
+use std::sync::Arc;
+
+mod chain;
+
+pub use chain::*;
+
+const SHARD_STATE_TAG: u32 = 0x9023afe2;
+const MC_STATE_EXTRA_TAG: u16 = 0xcc26;
+const MASTERCHAIN: i32 = -1;
+/// Deepest shard split allowed by `ShardIdent.shard_pfx_bits`.
+const MAX_SHARD_PREFIX_BITS: usize = 60;
+
+/// Errors returned when a liteserver proof does not check out.
+#[derive(Debug, thiserror::Error)]
+pub enum ProofError {
+    #[error("failed to decode {what}: {source}")]
+    Decode {
+        what: &'static str,
+        #[source]
+        source: anyhow::Error,
+    },
+    #[error("{what} must have {expected} roots, got {actual}")]
+    RootCount {
+        what: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("response is for block {actual}, requested {expected}")]
+    BlockMismatch {
+        expected: Box<BlockIdExt>,
+        actual: Box<BlockIdExt>,
+    },
+    #[error("proof anchor {0} is not a masterchain block")]
+    NotMasterchain(Box<BlockIdExt>),
+    #[error("{what} does not match block {block}")]
+    RootHashMismatch {
+        what: &'static str,
+        block: Box<BlockIdExt>,
+    },
+    #[error("proof omits the {0} cell")]
+    PrunedCell(&'static str),
+    #[error("invalid {what} in proof: {message}")]
+    Malformed { what: &'static str, message: String },
+    #[error("shard block {shard_block} cannot contain account {account}")]
+    WrongShard {
+        shard_block: Box<BlockIdExt>,
+        account: String,
+    },
+    #[error("shard block {shard_block} is not referenced by masterchain block {anchor}")]
+    UnlinkedShardBlock {
+        shard_block: Box<BlockIdExt>,
+        anchor: Box<BlockIdExt>,
+    },
+    #[error("account state hash does not match the proof")]
+    AccountHashMismatch,
+    #[error("proof shows account {0} is absent, but a state was returned")]
+    UnexpectedState(String),
+    #[error("proof shows account {0} exists, but no state was returned")]
+    MissingState(String),
+    #[error("proof starts at untrusted block {0}")]
+    UntrustedBlock(Box<BlockIdExt>),
+    #[error("proof step starts at {actual}, expected {expected}")]
+    UnlinkedStep {
+        expected: Box<BlockIdExt>,
+        actual: Box<BlockIdExt>,
+    },
+    #[error("block {0} is not a key block")]
+    NotKeyBlock(Box<BlockIdExt>),
+    #[error("header of block {block} does not match the proof link: {message}")]
+    HeaderMismatch {
+        block: Box<BlockIdExt>,
+        message: String,
+    },
+    #[error("block {block} is not referenced by the state of {from}")]
+    MissingPrevBlock {
+        block: Box<BlockIdExt>,
+        from: Box<BlockIdExt>,
+    },
+    #[error("invalid signature on block {block} from validator {node_id}")]
+    InvalidSignature {
+        block: Box<BlockIdExt>,
+        node_id: String,
+    },
+    #[error("block {block} is signed by weight {signed} of {total}")]
+    InsufficientSignatures {
+        block: Box<BlockIdExt>,
+        signed: u128,
+        total: u128,
+    },
+    #[error("{0} signature sets are not supported")]
+    UnsupportedSignatureSet(&'static str),
+    #[error("liteserver block proof does not advance past {0}")]
+    Stalled(Box<BlockIdExt>),
+}
+
+/// Value checked against proofs anchored at a masterchain block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verified<T> {
+    value: T,
+    anchor: BlockIdExt,
+}
+
+impl<T> Verified<T> {
+    pub(crate) fn new(value: T, anchor: BlockIdExt) -> Self {
+        Self { value, anchor }
+    }
+
+    /// Masterchain block the proofs were checked against.
+    ///
+    /// The proofs are only as trustworthy as this block. When it came from
+    /// the same liteserver (for example its reported `last` block), the value
+    /// is consistent with that server's head, not independently proven.
+    pub fn anchor(&self) -> &BlockIdExt {
+        &self.anchor
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Verified<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Shard account fields proven by a `ShardAccounts` dictionary leaf.
+struct ProvenShardAccount {
+    account_hash: [u8; 32],
+    last_trans_hash: [u8; 32],
+    last_trans_lt: u64,
+}
+
+/// Verifies `liteServer.accountState` for `account` at masterchain block
+/// `anchor`.
+///
+/// Checks the shard block linkage through `shard_proof`, the shard block and
+/// state proofs in `proof`, and the `ShardAccounts` path to the account. The
+/// returned state carries the proven `ShardAccount`.
+pub fn verify_account_state(
+    anchor: &BlockIdExt,
+    account: &Address,
+    raw: AccountState,
+) -> Result<Verified<DecodedAccountState>, ProofError> {
+    if raw.id != *anchor {
+        return Err(ProofError::BlockMismatch {
+            expected: Box::new(anchor.clone()),
+            actual: Box::new(raw.id),
+        });
+    }
+    shard_prefix_len(raw.shardblk.shard)?;
+    if !shard_contains_account(&raw.shardblk, account) {
+        return Err(ProofError::WrongShard {
+            shard_block: Box::new(raw.shardblk),
+            account: account.to_raw(),
+        });
+    }
+    verify_shard_block(anchor, &raw.shardblk, &raw.shard_proof)?;
+
+    let [block_proof, state_proof] = decode_proof_roots(&raw.proof, "account state proof")?;
+    let state_root = verify_state_proof(&raw.shardblk, block_proof, state_proof)?;
+    let proven = lookup_shard_account(&state_root, account)?;
+
+    let state = if raw.state.is_empty() {
+        None
+    } else {
+        Some(
+            deserialize_boc(&raw.state).map_err(|source| ProofError::Decode {
+                what: "account state",
+                source,
+            })?,
+        )
+    };
+    let mut decoded = DecodedAccountState::from_raw(raw).map_err(|source| ProofError::Decode {
+        what: "account state",
+        source,
+    })?;
+    match (proven, state, decoded.account.clone()) {
+        (Some(proven), Some(state), Some(account)) => {
+            if proven.account_hash != state.hash() {
+                return Err(ProofError::AccountHashMismatch);
+            }
+            decoded.shard_account = Some(ShardAccount {
+                account,
+                last_trans_hash: proven.last_trans_hash,
+                last_trans_lt: proven.last_trans_lt,
+            });
+        }
+        (Some(_), _, _) => return Err(ProofError::MissingState(account.to_raw())),
+        (None, Some(_), _) => return Err(ProofError::UnexpectedState(account.to_raw())),
+        (None, None, _) => {}
+    }
+    Ok(Verified::new(decoded, anchor.clone()))
+}
+
+/// Verifies that `shard_block` is the top block of its shard recorded in the
+/// state of masterchain block `anchor`.
+///
+/// `shard_proof` holds the anchor block proof and masterchain state proof; it
+/// is ignored when `shard_block` is the anchor itself.
+pub fn verify_shard_block(
+    anchor: &BlockIdExt,
+    shard_block: &BlockIdExt,
+    shard_proof: &[u8],
+) -> Result<(), ProofError> {
+    if anchor.workchain != MASTERCHAIN {
+        return Err(ProofError::NotMasterchain(Box::new(anchor.clone())));
+    }
+    if shard_block == anchor {
+        return Ok(());
+    }
+
+    let [block_proof, state_proof] = decode_proof_roots(shard_proof, "shard proof")?;
+    let mc_state = verify_state_proof(anchor, block_proof, state_proof)?;
+    if find_shard_top_block(&mc_state, shard_block)?.as_ref() != Some(shard_block) {
+        return Err(ProofError::UnlinkedShardBlock {
+            shard_block: Box::new(shard_block.clone()),
+            anchor: Box::new(anchor.clone()),
+        });
+    }
+    Ok(())
+}
+
+/// Checks a block proof against `block` and the state proof against the new
+/// state hash recorded in that block, returning the virtual state root.
+pub fn verify_state_proof(
+    block: &BlockIdExt,
+    block_proof: Arc<Cell>,
+    state_proof: Arc<Cell>,
+) -> Result<Arc<Cell>, ProofError> {
+    let block_root = merkle_proof_root(block_proof, &block.root_hash.0, "block proof", block)?;
+    let parsed = Block::from_cell(ordinary_cell(block_root, "Block")?).map_err(|error| {
+        ProofError::Malformed {
+            what: "Block",
+            message: error.to_string(),
+        }
+    })?;
+    let new_state_hash = match parsed.state_update.exotic_kind() {
+        Some(ExoticCellKind::MerkleUpdate { new_hash, .. }) => *new_hash,
+        _ => {
+            return Err(ProofError::Malformed {
+                what: "Block.state_update",
+                message: "expected a Merkle update cell".to_string(),
+            });
+        }
+    };
+    merkle_proof_root(state_proof, &new_state_hash, "state proof", block)
+}
+
+fn decode_proof_roots(raw: &[u8], what: &'static str) -> Result<[Arc<Cell>; 2], ProofError> {
+    let roots = deserialize_boc_roots(raw).map_err(|source| ProofError::Decode { what, source })?;
+    let actual = roots.len();
+    <[Arc<Cell>; 2]>::try_from(roots).map_err(|_| ProofError::RootCount {
+        what,
+        expected: 2,
+        actual,
+    })
+}
+
+fn merkle_proof_root(
+    proof: Arc<Cell>,
+    expected_hash: &[u8; 32],
+    what: &'static str,
+    block: &BlockIdExt,
+) -> Result<Arc<Cell>, ProofError> {
+    let proof = MerkleProof::from_exotic_cell(proof).map_err(|error| ProofError::Malformed {
+        what,
+        message: error.to_string(),
+    })?;
+    if !proof.verify_virtual_hash() || proof.virtual_hash != *expected_hash {
+        return Err(ProofError::RootHashMismatch {
+            what,
+            block: Box::new(block.clone()),
+        });
+    }
+    Ok(proof.virtual_root)
+}
+
+fn ordinary_cell(cell: Arc<Cell>, what: &'static str) -> Result<Arc<Cell>, ProofError> {
+    if cell.is_exotic() {
+        Err(ProofError::PrunedCell(what))
+    } else {
+        Ok(cell)
+    }
+}
+
+fn malformed<E: std::fmt::Display>(what: &'static str) -> impl Fn(E) -> ProofError {
+    move |error| ProofError::Malformed {
+        what,
+        message: error.to_string(),
+    }
+}
+
+/// `accounts` and `custom` references of a `ShardStateUnsplit` root.
+fn shard_state_refs(state_root: Arc<Cell>) -> Result<(Arc<Cell>, Option<Arc<Cell>>), ProofError> {
+    const WHAT: &str = "ShardStateUnsplit";
+    let mut slice = Slice::new(ordinary_cell(state_root, WHAT)?);
+    let tag = slice.load_u32().map_err(malformed(WHAT))?;
+    if tag != SHARD_STATE_TAG {
+        return Err(ProofError::Malformed {
+            what: WHAT,
+            message: format!("unexpected tag {tag:08x}"),
+        });
+    }
+    slice.load_int(32).map_err(malformed(WHAT))?; // global_id
+    ShardIdent::load_tlb(&mut slice).map_err(malformed(WHAT))?;
+    // seq_no, vert_seq_no, gen_utime, gen_lt, min_ref_mc_seqno
+    slice
+        .skip_bits(32 + 32 + 32 + 64 + 32)
+        .map_err(malformed(WHAT))?;
+    slice.skip_refs(1).map_err(malformed(WHAT))?; // out_msg_queue_info
+    slice.load_bit().map_err(malformed(WHAT))?; // before_split
+    let accounts = slice.load_reference().map_err(malformed(WHAT))?;
+    slice.skip_refs(1).map_err(malformed(WHAT))?;
+    let custom = if slice.load_bit().map_err(malformed(WHAT))? {
+        Some(slice.load_reference().map_err(malformed(WHAT))?)
+    } else {
+        None
+    };
+    Ok((accounts, custom))
+}
+
+fn lookup_shard_account(
+    state_root: &Arc<Cell>,
+    account: &Address,
+) -> Result<Option<ProvenShardAccount>, ProofError> {
+    const WHAT: &str = "ShardAccounts";
+    let (accounts, _) = shard_state_refs(state_root.clone())?;
+    let mut slice = Slice::new(ordinary_cell(accounts, WHAT)?);
+    if !slice.load_bit().map_err(malformed(WHAT))? {
+        return Ok(None);
+    }
+    let root = slice.load_reference().map_err(malformed(WHAT))?;
+    let key = BitKey::new(account.hash_part.to_vec(), 256).map_err(malformed(WHAT))?;
+    let Some(mut leaf) = lookup_hashmap_path(root, &key).map_err(malformed(WHAT))? else {
+        return Ok(None);
+    };
+
+    // depth_balance$_ split_depth:(#<= 30) balance:CurrencyCollection
+    leaf.skip_bits(5).map_err(malformed(WHAT))?;
+    CurrencyCollection::load_tlb(&mut leaf).map_err(malformed(WHAT))?;
+    // account_descr$_ account:^Account last_trans_hash:bits256 last_trans_lt:uint64
+    let account = leaf.load_reference().map_err(malformed(WHAT))?;
+    let mut last_trans_hash = [0; 32];
+    last_trans_hash.copy_from_slice(&leaf.load_bytes(32).map_err(malformed(WHAT))?);
+    Ok(Some(ProvenShardAccount {
+        account_hash: account.hash_at_level(0),
+        last_trans_hash,
+        last_trans_lt: leaf.load_u64().map_err(malformed(WHAT))?,
+    }))
+}
+
+/// `McStateExtra` of a masterchain state, positioned after its tag.
+fn mc_state_extra(mc_state: &Arc<Cell>) -> Result<Slice, ProofError> {
+    const WHAT: &str = "McStateExtra";
+    let (_, custom) = shard_state_refs(mc_state.clone())?;
+    let custom = custom.ok_or_else(|| ProofError::Malformed {
+        what: WHAT,
+        message: "masterchain state has no McStateExtra".to_string(),
+    })?;
+    let mut slice = Slice::new(ordinary_cell(custom, WHAT)?);
+    let tag = slice.load_u16().map_err(malformed(WHAT))?;
+    if tag != MC_STATE_EXTRA_TAG {
+        return Err(ProofError::Malformed {
+            what: WHAT,
+            message: format!("unexpected tag {tag:04x}"),
+        });
+    }
+    Ok(slice)
+}
+
+/// Looks up the top block of `shard_block`'s shard in `McStateExtra.shard_hashes`.
+fn find_shard_top_block(
+    mc_state: &Arc<Cell>,
+    shard_block: &BlockIdExt,
+) -> Result<Option<BlockIdExt>, ProofError> {
+    const WHAT: &str = "McStateExtra";
+    let mut slice = mc_state_extra(mc_state)?;
+    // shard_hashes:(HashmapE 32 ^(BinTree ShardDescr))
+    if !slice.load_bit().map_err(malformed(WHAT))? {
+        return Ok(None);
+    }
+    let root = slice.load_reference().map_err(malformed(WHAT))?;
+    let key = BitKey::from_u64(shard_block.workchain as u32 as u64, 32).map_err(malformed(WHAT))?;
+    let Some(mut leaf) = lookup_hashmap_path(root, &key).map_err(malformed(WHAT))? else {
+        return Ok(None);
+    };
+
+    let shard = shard_block.shard as u64;
+    let prefix_len = shard_prefix_len(shard_block.shard)?;
+    let mut node = leaf.load_reference().map_err(malformed(WHAT))?;
+    for depth in 0..=prefix_len {
+        let mut slice = Slice::new(ordinary_cell(node, "BinTree ShardDescr")?);
+        let is_fork = slice.load_bit().map_err(malformed(WHAT))?;
+        if depth == prefix_len {
+            return if is_fork {
+                Ok(None)
+            } else {
+                load_shard_descr_id(&mut slice, shard_block.workchain, shard)
+                    .map(Some)
+                    .map_err(malformed("ShardDescr"))
+            };
+        }
+        if !is_fork {
+            return Ok(None);
+        }
+        let left = slice.load_reference().map_err(malformed(WHAT))?;
+        let right = slice.load_reference().map_err(malformed(WHAT))?;
+        node = if shard & (1 << (63 - depth)) != 0 {
+            right
+        } else {
+            left
+        };
+    }
+    Ok(None)
+}
+
+/// Number of prefix bits in shard id `shard`, rejecting ids without a tag bit
+/// or deeper than [`MAX_SHARD_PREFIX_BITS`].
+fn shard_prefix_len(shard: i64) -> Result<usize, ProofError> {
+    let shard = shard as u64;
+    if shard == 0 {
+        return Err(ProofError::Malformed {
+            what: "shard id",
+            message: "shard 0 has no tag bit".to_string(),
+        });
+    }
+    let prefix_len = 63 - shard.trailing_zeros() as usize;
+    if prefix_len > MAX_SHARD_PREFIX_BITS {
+        return Err(ProofError::Malformed {
+            what: "shard id",
+            message: format!(
+                "shard {shard:016x} is split deeper than {MAX_SHARD_PREFIX_BITS} bits"
+            ),
+        });
+    }
+    Ok(prefix_len)
+}
+
+fn shard_contains_account(shard_block: &BlockIdExt, account: &Address) -> bool {
+    if shard_block.workchain != account.workchain as i32 {
+        return false;
+    }
+    let shard = shard_block.shard as u64;
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&account.hash_part[..8]);
+    let prefix = u64::from_be_bytes(prefix);
+    let lower_bit = shard & shard.wrapping_neg();
+    (shard ^ prefix) & (lower_bit.wrapping_neg() << 1) == 0
+}
+
+#[cfg(test)]
+mod tests;
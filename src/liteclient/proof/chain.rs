@@ -0,0 +1,595 @@
+//! Masterchain block-proof links.
+//!
+//! Forward links carry the validator set of a trusted key block (config
+//! param 34) and signatures by that set over a newer block. Back links prove
+//! an older block through `prev_blocks` in the state of a trusted block. Both
+//! mirror the checks of the reference lite client.
+
+use super::*;
+
+use crate::tl::common::{BlockLink, SignatureSet};
+use crate::tl::response::PartialBlockProof;
+use crate::tvm::Builder;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+const BLOCK_INFO_TAG: u32 = 0x9bc7a987;
+const BLOCK_EXTRA_TAG: u32 = 0x4a33f6fd;
+const MC_BLOCK_EXTRA_TAG: u16 = 0xcca5;
+const VALIDATOR_SET_TAG: u8 = 0x11;
+const VALIDATOR_SET_EXT_TAG: u8 = 0x12;
+const VALIDATOR_TAG: u8 = 0x53;
+const VALIDATOR_ADDR_TAG: u8 = 0x73;
+const ED25519_PUBKEY_TAG: u32 = 0x8e81278a;
+/// TL id of `pub.ed25519`, hashed with the key into a node id.
+const PUB_ED25519_ID: u32 = 0x4813b4c6;
+/// TL id of `ton.blockId`, the message validators sign.
+const TON_BLOCK_ID: u32 = 0xc50b6e70;
+const VALIDATOR_SET_PARAM: u64 = 34;
+
+/// Trusted masterchain blocks advanced by checked `liteServer.partialBlockProof`
+/// responses.
+///
+/// Starts from an init block taken on trust, normally the `init_block` of the
+/// global config, and only moves to blocks reached through verified links.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockProofChain {
+    last: BlockIdExt,
+    last_key_block: BlockIdExt,
+}
+
+impl BlockProofChain {
+    /// Chain anchored at `init`, which must be a masterchain key block or the
+    /// zero state.
+    pub fn new(init: BlockIdExt) -> Result<Self, ProofError> {
+        if init.workchain != MASTERCHAIN {
+            return Err(ProofError::NotMasterchain(Box::new(init)));
+        }
+        Ok(Self {
+            last: init.clone(),
+            last_key_block: init,
+        })
+    }
+
+    /// Chain anchored at the trusted block of the `validator` config section.
+    #[cfg(feature = "network-config")]
+    pub fn from_config(config: &crate::network_config::ConfigGlobal) -> Result<Self, ProofError> {
+        let validator = config
+            .validator
+            .as_ref()
+            .ok_or_else(|| ProofError::Malformed {
+                what: "global config",
+                message: "missing validator section".to_string(),
+            })?;
+        Self::new(validator.trusted_block().clone().into())
+    }
+
+    /// Latest trusted masterchain block.
+    pub fn last(&self) -> &BlockIdExt {
+        &self.last
+    }
+
+    /// Latest trusted masterchain key block.
+    pub fn last_key_block(&self) -> &BlockIdExt {
+        &self.last_key_block
+    }
+
+    /// Checks every link of `proof` and advances the trusted blocks.
+    ///
+    /// The proof must start at [`last`](Self::last) or
+    /// [`last_key_block`](Self::last_key_block). Returns `proof.complete`;
+    /// an incomplete proof is continued by requesting another one from the
+    /// new [`last`](Self::last).
+    pub fn apply(&mut self, proof: &PartialBlockProof) -> Result<bool, ProofError> {
+        if proof.from != self.last && proof.from != self.last_key_block {
+            return Err(ProofError::UntrustedBlock(Box::new(proof.from.clone())));
+        }
+        let mut current = proof.from.clone();
+        for step in &proof.steps {
+            let (to_key_block, from, to) = link_blocks(step);
+            if *from != current {
+                return Err(ProofError::UnlinkedStep {
+                    expected: Box::new(current),
+                    actual: Box::new(from.clone()),
+                });
+            }
+            verify_block_link(step)?;
+            if to.seqno > self.last.seqno {
+                self.last = to.clone();
+            }
+            if to_key_block && to.seqno > self.last_key_block.seqno {
+                self.last_key_block = to.clone();
+            }
+            current = to.clone();
+        }
+        if current != proof.to {
+            return Err(ProofError::BlockMismatch {
+                expected: Box::new(proof.to.clone()),
+                actual: Box::new(current),
+            });
+        }
+        Ok(proof.complete)
+    }
+}
+
+/// Verifies one block link assuming its `from` block is trusted.
+pub fn verify_block_link(link: &BlockLink) -> Result<(), ProofError> {
+    let (_, from, to) = link_blocks(link);
+    for block in [from, to] {
+        if block.workchain != MASTERCHAIN {
+            return Err(ProofError::NotMasterchain(Box::new(block.clone())));
+        }
+    }
+    match link {
+        BlockLink::BlockLinkBack {
+            to_key_block,
+            from,
+            to,
+            dest_proof,
+            proof,
+            state_proof,
+        } => verify_back_link(*to_key_block, from, to, dest_proof, proof, state_proof),
+        BlockLink::BlockLinkForward {
+            to_key_block,
+            from,
+            to,
+            dest_proof,
+            config_proof,
+            signatures,
+        } => verify_forward_link(
+            *to_key_block,
+            from,
+            to,
+            dest_proof,
+            config_proof,
+            signatures,
+        ),
+    }
+}
+
+fn link_blocks(link: &BlockLink) -> (bool, &BlockIdExt, &BlockIdExt) {
+    match link {
+        BlockLink::BlockLinkBack {
+            to_key_block,
+            from,
+            to,
+            ..
+        }
+        | BlockLink::BlockLinkForward {
+            to_key_block,
+            from,
+            to,
+            ..
+        } => (*to_key_block, from, to),
+    }
+}
+
+fn verify_back_link(
+    to_key_block: bool,
+    from: &BlockIdExt,
+    to: &BlockIdExt,
+    dest_proof: &[u8],
+    proof: &[u8],
+    state_proof: &[u8],
+) -> Result<(), ProofError> {
+    if to.seqno >= from.seqno {
+        return Err(ProofError::HeaderMismatch {
+            block: Box::new(to.clone()),
+            message: format!("back link from seqno {} does not go back", from.seqno),
+        });
+    }
+    let block_proof = decode_single_root(proof, "back link block proof")?;
+    let state_proof = decode_single_root(state_proof, "back link state proof")?;
+    let state = verify_state_proof(from, block_proof, state_proof)?;
+    match find_prev_block(&state, to.seqno as u32)? {
+        Some((key_block, prev)) if key_block == to_key_block && prev == *to => {}
+        _ => {
+            return Err(ProofError::MissingPrevBlock {
+                block: Box::new(to.clone()),
+                from: Box::new(from.clone()),
+            });
+        }
+    }
+    if !dest_proof.is_empty() {
+        let header = block_header(to, dest_proof)?;
+        header.check(to, to_key_block)?;
+    }
+    Ok(())
+}
+
+fn verify_forward_link(
+    to_key_block: bool,
+    from: &BlockIdExt,
+    to: &BlockIdExt,
+    dest_proof: &[u8],
+    config_proof: &[u8],
+    signatures: &SignatureSet,
+) -> Result<(), ProofError> {
+    if to.seqno <= from.seqno {
+        return Err(ProofError::HeaderMismatch {
+            block: Box::new(to.clone()),
+            message: format!("forward link from seqno {} does not go forward", from.seqno),
+        });
+    }
+    let header = block_header(to, dest_proof)?;
+    header.check(to, to_key_block)?;
+    if header.prev_key_block_seqno != from.seqno as u32 {
+        return Err(ProofError::HeaderMismatch {
+            block: Box::new(to.clone()),
+            message: format!(
+                "previous key block is {}, link starts at {}",
+                header.prev_key_block_seqno, from.seqno
+            ),
+        });
+    }
+
+    let config_root = decode_single_root(config_proof, "config proof")?;
+    let config_root = merkle_proof_root(config_root, &from.root_hash.0, "config proof", from)?;
+    let validators = load_validator_set(&config_params(from, config_root)?)?;
+    check_signatures(to, &header, &validators, signatures)
+}
+
+/// Fields of `BlockInfo` bound by block links.
+struct BlockHeader {
+    seqno: u32,
+    key_block: bool,
+    gen_validator_list_hash_short: u32,
+    gen_catchain_seqno: u32,
+    prev_key_block_seqno: u32,
+}
+
+impl BlockHeader {
+    fn check(&self, block: &BlockIdExt, key_block: bool) -> Result<(), ProofError> {
+        let message = if self.seqno != block.seqno as u32 {
+            format!("header seqno is {}", self.seqno)
+        } else if self.key_block != key_block {
+            format!("header key_block is {}", self.key_block)
+        } else {
+            return Ok(());
+        };
+        Err(ProofError::HeaderMismatch {
+            block: Box::new(block.clone()),
+            message,
+        })
+    }
+}
+
+fn decode_single_root(raw: &[u8], what: &'static str) -> Result<Arc<Cell>, ProofError> {
+    let roots = deserialize_boc_roots(raw).map_err(|source| ProofError::Decode { what, source })?;
+    let actual = roots.len();
+    let [root] = <[Arc<Cell>; 1]>::try_from(roots).map_err(|_| ProofError::RootCount {
+        what,
+        expected: 1,
+        actual,
+    })?;
+    Ok(root)
+}
+
+fn block_header(block: &BlockIdExt, dest_proof: &[u8]) -> Result<BlockHeader, ProofError> {
+    const WHAT: &str = "BlockInfo";
+    let root = decode_single_root(dest_proof, "block header proof")?;
+    let root = merkle_proof_root(root, &block.root_hash.0, "block header proof", block)?;
+    let parsed = Block::from_cell(ordinary_cell(root, "Block")?).map_err(malformed("Block"))?;
+    let mut slice = Slice::new(ordinary_cell(parsed.info, WHAT)?);
+    let tag = slice.load_u32().map_err(malformed(WHAT))?;
+    if tag != BLOCK_INFO_TAG {
+        return Err(ProofError::Malformed {
+            what: WHAT,
+            message: format!("unexpected tag {tag:08x}"),
+        });
+    }
+    slice.skip_bits(32).map_err(malformed(WHAT))?; // version
+    // not_master, after_merge, before_split, after_split, want_split, want_merge
+    slice.skip_bits(6).map_err(malformed(WHAT))?;
+    let key_block = slice.load_bit().map_err(malformed(WHAT))?;
+    slice.skip_bits(1 + 8).map_err(malformed(WHAT))?; // vert_seqno_incr, flags
+    let seqno = slice.load_u32().map_err(malformed(WHAT))?;
+    slice.skip_bits(32).map_err(malformed(WHAT))?; // vert_seq_no
+    ShardIdent::load_tlb(&mut slice).map_err(malformed(WHAT))?;
+    // gen_utime, start_lt, end_lt
+    slice.skip_bits(32 + 64 + 64).map_err(malformed(WHAT))?;
+    let gen_validator_list_hash_short = slice.load_u32().map_err(malformed(WHAT))?;
+    let gen_catchain_seqno = slice.load_u32().map_err(malformed(WHAT))?;
+    slice.skip_bits(32).map_err(malformed(WHAT))?; // min_ref_mc_seqno
+    let prev_key_block_seqno = slice.load_u32().map_err(malformed(WHAT))?;
+    Ok(BlockHeader {
+        seqno,
+        key_block,
+        gen_validator_list_hash_short,
+        gen_catchain_seqno,
+        prev_key_block_seqno,
+    })
+}
+
+/// Config dictionary root proven for `block`: from `McStateExtra` of the zero
+/// state, otherwise from `McBlockExtra` of a key block.
+fn config_params(block: &BlockIdExt, root: Arc<Cell>) -> Result<Arc<Cell>, ProofError> {
+    let mut slice = if block.seqno == 0 {
+        let mut slice = mc_state_extra(&root)?;
+        skip_hashmap_e(&mut slice, "McStateExtra")?; // shard_hashes
+        slice
+    } else {
+        mc_block_extra(block, root)?
+    };
+    slice.skip_bits(256).map_err(malformed("ConfigParams"))?; // config_addr
+    slice.load_reference().map_err(malformed("ConfigParams"))
+}
+
+/// `McBlockExtra` of a key block, positioned at its `config` field.
+fn mc_block_extra(block: &BlockIdExt, root: Arc<Cell>) -> Result<Slice, ProofError> {
+    const WHAT: &str = "McBlockExtra";
+    let parsed = Block::from_cell(ordinary_cell(root, "Block")?).map_err(malformed("Block"))?;
+    let mut extra = Slice::new(ordinary_cell(parsed.extra, "BlockExtra")?);
+    let tag = extra.load_u32().map_err(malformed("BlockExtra"))?;
+    if tag != BLOCK_EXTRA_TAG {
+        return Err(ProofError::Malformed {
+            what: "BlockExtra",
+            message: format!("unexpected tag {tag:08x}"),
+        });
+    }
+    // in_msg_descr, out_msg_descr, account_blocks, rand_seed, created_by
+    extra.skip_refs(3).map_err(malformed("BlockExtra"))?;
+    extra
+        .skip_bits(256 + 256)
+        .map_err(malformed("BlockExtra"))?;
+    if !extra.load_bit().map_err(malformed("BlockExtra"))? {
+        return Err(ProofError::NotKeyBlock(Box::new(block.clone())));
+    }
+    let custom = extra.load_reference().map_err(malformed("BlockExtra"))?;
+
+    let mut slice = Slice::new(ordinary_cell(custom, WHAT)?);
+    let tag = slice.load_u16().map_err(malformed(WHAT))?;
+    if tag != MC_BLOCK_EXTRA_TAG {
+        return Err(ProofError::Malformed {
+            what: WHAT,
+            message: format!("unexpected tag {tag:04x}"),
+        });
+    }
+    if !slice.load_bit().map_err(malformed(WHAT))? {
+        return Err(ProofError::NotKeyBlock(Box::new(block.clone())));
+    }
+    skip_hashmap_e(&mut slice, WHAT)?; // shard_hashes
+    skip_hashmap_e(&mut slice, WHAT)?; // shard_fees
+    // shard_fees extra: ShardFeeCreated fees:CurrencyCollection create:CurrencyCollection
+    skip_currency_collection(&mut slice, WHAT)?;
+    skip_currency_collection(&mut slice, WHAT)?;
+    slice.skip_refs(1).map_err(malformed(WHAT))?; // ^[ prev_blk_signatures ... ]
+    Ok(slice)
+}
+
+fn skip_hashmap_e(slice: &mut Slice, what: &'static str) -> Result<(), ProofError> {
+    if slice.load_bit().map_err(malformed(what))? {
+        slice.skip_refs(1).map_err(malformed(what))?;
+    }
+    Ok(())
+}
+
+/// Skips a `CurrencyCollection` without loading its extra currencies, which
+/// proofs usually prune.
+fn skip_currency_collection(slice: &mut Slice, what: &'static str) -> Result<(), ProofError> {
+    let len = slice.load_uint_custom::<u8>(4).map_err(malformed(what))?;
+    slice.skip_bits(len as usize * 8).map_err(malformed(what))?;
+    skip_hashmap_e(slice, what)
+}
+
+/// Looks up `seqno` in `prev_blocks` of a masterchain state, returning the
+/// key block flag and block id recorded there.
+fn find_prev_block(
+    mc_state: &Arc<Cell>,
+    seqno: u32,
+) -> Result<Option<(bool, BlockIdExt)>, ProofError> {
+    const WHAT: &str = "OldMcBlocksInfo";
+    let mut slice = mc_state_extra(mc_state)?;
+    skip_hashmap_e(&mut slice, "McStateExtra")?; // shard_hashes
+    // config:ConfigParams
+    slice.skip_bits(256).map_err(malformed("McStateExtra"))?;
+    slice.skip_refs(1).map_err(malformed("McStateExtra"))?;
+    let info = slice.load_reference().map_err(malformed("McStateExtra"))?;
+
+    let mut info = Slice::new(ordinary_cell(info, "McStateExtra")?);
+    // flags, validator_info
+    info.skip_bits(16 + 65).map_err(malformed(WHAT))?;
+    if !info.load_bit().map_err(malformed(WHAT))? {
+        return Ok(None);
+    }
+    let root = info.load_reference().map_err(malformed(WHAT))?;
+    let key = BitKey::from_u64(seqno as u64, 32).map_err(malformed(WHAT))?;
+    let Some(mut leaf) = lookup_hashmap_path(root, &key).map_err(malformed(WHAT))? else {
+        return Ok(None);
+    };
+    // extra:KeyMaxLt, then value:KeyExtBlkRef
+    leaf.skip_bits(1 + 64).map_err(malformed(WHAT))?;
+    let key_block = leaf.load_bit().map_err(malformed(WHAT))?;
+    leaf.skip_bits(64).map_err(malformed(WHAT))?; // end_lt
+    let seq_no = leaf.load_u32().map_err(malformed(WHAT))?;
+    let mut root_hash = [0; 32];
+    root_hash.copy_from_slice(&leaf.load_bytes(32).map_err(malformed(WHAT))?);
+    let mut file_hash = [0; 32];
+    file_hash.copy_from_slice(&leaf.load_bytes(32).map_err(malformed(WHAT))?);
+    Ok(Some((
+        key_block,
+        BlockIdExt {
+            workchain: MASTERCHAIN,
+            shard: i64::MIN,
+            seqno: seq_no as i32,
+            root_hash: Int256(root_hash),
+            file_hash: Int256(file_hash),
+        },
+    )))
+}
+
+/// Masterchain validator entry with the short node id signatures refer to.
+struct Validator {
+    node_id_short: [u8; 32],
+    public_key: [u8; 32],
+    weight: u64,
+}
+
+/// Masterchain validator subset from config param 34.
+///
+/// The subset is the first `main` validators of the set; shuffling only
+/// changes their order, which signature checks do not depend on.
+fn load_validator_set(config: &Arc<Cell>) -> Result<Vec<Validator>, ProofError> {
+    const WHAT: &str = "ValidatorSet";
+    let key = BitKey::from_u64(VALIDATOR_SET_PARAM, 32).map_err(malformed("ConfigParams"))?;
+    let mut param = lookup_hashmap_path(ordinary_cell(config.clone(), "ConfigParams")?, &key)
+        .map_err(malformed("ConfigParams"))?
+        .ok_or_else(|| ProofError::Malformed {
+            what: "ConfigParams",
+            message: "config param 34 is missing".to_string(),
+        })?;
+    let param = param.load_reference().map_err(malformed("ConfigParams"))?;
+
+    let mut slice = Slice::new(ordinary_cell(param, WHAT)?);
+    let tag = slice.load_u8().map_err(malformed(WHAT))?;
+    // utime_since, utime_until, total
+    slice.skip_bits(32 + 32 + 16).map_err(malformed(WHAT))?;
+    let main = slice.load_u16().map_err(malformed(WHAT))?;
+    let list = match tag {
+        VALIDATOR_SET_TAG => {
+            // list:(Hashmap 16 ValidatorDescr) is stored inline.
+            let mut root = Builder::new();
+            let bits = slice.remaining_bits();
+            root.store_bits(&slice.load_bits(bits).map_err(malformed(WHAT))?, bits)
+                .map_err(malformed(WHAT))?;
+            for reference in slice.load_remaining_refs().map_err(malformed(WHAT))? {
+                root.store_ref(reference).map_err(malformed(WHAT))?;
+            }
+            root.build().map_err(malformed(WHAT))?
+        }
+        VALIDATOR_SET_EXT_TAG => {
+            slice.skip_bits(64).map_err(malformed(WHAT))?; // total_weight
+            if !slice.load_bit().map_err(malformed(WHAT))? {
+                return Err(ProofError::Malformed {
+                    what: WHAT,
+                    message: "empty validator list".to_string(),
+                });
+            }
+            slice.load_reference().map_err(malformed(WHAT))?
+        }
+        _ => {
+            return Err(ProofError::Malformed {
+                what: WHAT,
+                message: format!("unexpected tag {tag:02x}"),
+            });
+        }
+    };
+
+    (0..main)
+        .map(|index| {
+            let key = BitKey::from_u64(index as u64, 16).map_err(malformed(WHAT))?;
+            let mut descr = lookup_hashmap_path(list.clone(), &key)
+                .map_err(malformed(WHAT))?
+                .ok_or_else(|| ProofError::Malformed {
+                    what: WHAT,
+                    message: format!("validator {index} is missing"),
+                })?;
+            load_validator(&mut descr)
+        })
+        .collect()
+}
+
+fn load_validator(slice: &mut Slice) -> Result<Validator, ProofError> {
+    const WHAT: &str = "ValidatorDescr";
+    let tag = slice.load_u8().map_err(malformed(WHAT))?;
+    if tag != VALIDATOR_TAG && tag != VALIDATOR_ADDR_TAG {
+        return Err(ProofError::Malformed {
+            what: WHAT,
+            message: format!("unexpected tag {tag:02x}"),
+        });
+    }
+    let key_tag = slice.load_u32().map_err(malformed(WHAT))?;
+    if key_tag != ED25519_PUBKEY_TAG {
+        return Err(ProofError::Malformed {
+            what: WHAT,
+            message: format!("unexpected public key tag {key_tag:08x}"),
+        });
+    }
+    let mut public_key = [0; 32];
+    public_key.copy_from_slice(&slice.load_bytes(32).map_err(malformed(WHAT))?);
+    let weight = slice.load_u64().map_err(malformed(WHAT))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(PUB_ED25519_ID.to_le_bytes());
+    hasher.update(public_key);
+    Ok(Validator {
+        node_id_short: hasher.finalize().into(),
+        public_key,
+        weight,
+    })
+}
+
+/// Requires signatures by more than two thirds of the validator weight over
+/// `ton.blockId` of `block`.
+fn check_signatures(
+    block: &BlockIdExt,
+    header: &BlockHeader,
+    validators: &[Validator],
+    signatures: &SignatureSet,
+) -> Result<(), ProofError> {
+    let (validator_set_hash, catchain_seqno, signatures) = match signatures {
+        SignatureSet::Ordinary {
+            validator_set_hash,
+            catchain_seqno,
+            signatures,
+        } => (*validator_set_hash, *catchain_seqno, signatures),
+        SignatureSet::Simplex { .. } => {
+            return Err(ProofError::UnsupportedSignatureSet("simplex"));
+        }
+    };
+    if catchain_seqno as u32 != header.gen_catchain_seqno {
+        return Err(ProofError::HeaderMismatch {
+            block: Box::new(block.clone()),
+            message: format!(
+                "signatures are for catchain {catchain_seqno}, header has {}",
+                header.gen_catchain_seqno
+            ),
+        });
+    }
+    if validator_set_hash as u32 != header.gen_validator_list_hash_short {
+        return Err(ProofError::HeaderMismatch {
+            block: Box::new(block.clone()),
+            message: format!(
+                "signatures are for validator set {:08x}, header has {:08x}",
+                validator_set_hash as u32, header.gen_validator_list_hash_short
+            ),
+        });
+    }
+
+    let mut message = Vec::with_capacity(68);
+    message.extend_from_slice(&TON_BLOCK_ID.to_le_bytes());
+    message.extend_from_slice(&block.root_hash.0);
+    message.extend_from_slice(&block.file_hash.0);
+
+    let total: u128 = validators.iter().map(|v| v.weight as u128).sum();
+    let mut signed = 0u128;
+    let mut seen = vec![false; validators.len()];
+    for signature in signatures {
+        let Some(index) = validators
+            .iter()
+            .position(|v| v.node_id_short == signature.node_id_short.0)
+        else {
+            continue;
+        };
+        if std::mem::replace(&mut seen[index], true) {
+            continue;
+        }
+        let invalid = || ProofError::InvalidSignature {
+            block: Box::new(block.clone()),
+            node_id: hex::encode(signature.node_id_short.0),
+        };
+        let key = VerifyingKey::from_bytes(&validators[index].public_key).map_err(|_| invalid())?;
+        let bytes = <[u8; 64]>::try_from(signature.signature.as_slice()).map_err(|_| invalid())?;
+        key.verify(&message, &Signature::from_bytes(&bytes))
+            .map_err(|_| invalid())?;
+        signed += validators[index].weight as u128;
+    }
+    if signed * 3 <= total * 2 {
+        return Err(ProofError::InsufficientSignatures {
+            block: Box::new(block.clone()),
+            signed,
+            total,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,785 @@
+use super::*;
+
+use num_bigint::BigUint;
+use sha2::Digest;
+
+use crate::liteclient::server::LiteServerFixtures;
+use crate::tl::common::{BlockLink, SignatureSet};
+use crate::tl::request::{GetAccountState, Request, WrappedRequest};
+use crate::tl::response::{PartialBlockProof, Response};
+use crate::tlb::{
+    Account, AccountState as TlbAccountState, AccountStorage, Grams, MsgAddressInt,
+    StorageExtraInfo, StorageInfo, StorageUsed, TlbSerialize,
+};
+use crate::tvm::serialize_boc_roots;
+
+fn masterchain_block() -> BlockIdExt {
+    BlockIdExt {
+        workchain: -1,
+        shard: i64::MIN,
+        seqno: 7,
+        root_hash: Int256([0; 32]),
+        file_hash: Int256([0xF7; 32]),
+    }
+}
+
+fn account(address: &Address, balance: u64) -> Account {
+    Account::Full {
+        addr: MsgAddressInt::std(address.clone()),
+        storage_stat: StorageInfo {
+            used: StorageUsed::new(BigUint::from(1u8), BigUint::from(8u8)),
+            last_paid: 1_700_000_000,
+            due_payment: None,
+            extra: StorageExtraInfo::None,
+        },
+        storage: AccountStorage {
+            last_trans_lt: 42,
+            balance: CurrencyCollection::grams(Grams::from(balance)),
+            state: TlbAccountState::Uninit,
+        },
+    }
+}
+
+fn master_account() -> Address {
+    Address::new(-1, [0x33; 32])
+}
+
+fn basechain_account() -> Address {
+    Address::new(0, [0x44; 32])
+}
+
+fn fixtures() -> LiteServerFixtures {
+    let mut fixtures = LiteServerFixtures::new(masterchain_block());
+    for (address, balance) in [
+        (master_account(), 1_000),
+        (Address::new(-1, [0x35; 32]), 2_000),
+        (basechain_account(), 3_000),
+        (Address::new(0, [0xC4; 32]), 4_000),
+    ] {
+        fixtures
+            .insert_account(&address, &account(&address, balance))
+            .unwrap();
+    }
+    fixtures
+}
+
+fn answer(fixtures: &mut LiteServerFixtures, request: Request) -> Response {
+    fixtures.answer(WrappedRequest {
+        wait_masterchain_seqno: None,
+        request,
+    })
+}
+
+/// Latest masterchain block and the fixture answer for `address` at it.
+fn account_state(address: &Address) -> (BlockIdExt, AccountState) {
+    let mut fixtures = fixtures();
+    let Response::MasterchainInfo(info) = answer(&mut fixtures, Request::GetMasterchainInfo) else {
+        panic!("expected masterchain info");
+    };
+    let request = Request::GetAccountState(GetAccountState {
+        id: info.last.clone(),
+        account: address.to_account_id(),
+    });
+    match answer(&mut fixtures, request) {
+        Response::AccountState(state) => (info.last, state),
+        other => panic!("expected account state, got {other:?}"),
+    }
+}
+
+/// Level-1 pruned branch standing in for `cell`.
+fn pruned(cell: &Arc<Cell>) -> Arc<Cell> {
+    let mut data = vec![0x01, 0x01];
+    data.extend_from_slice(&cell.hash_at_level(0));
+    data.extend_from_slice(&cell.depth_at_level(0).to_be_bytes());
+    Arc::new(Cell::with_exotic_data(data, 288, Vec::new()).unwrap())
+}
+
+/// Copy of `cell` with reference `index` replaced by `replacement`.
+fn replace_ref(cell: &Arc<Cell>, index: usize, replacement: Arc<Cell>) -> Arc<Cell> {
+    let mut copy = Cell::with_data(cell.data().to_vec(), cell.bit_len()).unwrap();
+    for (position, child) in cell.references().iter().enumerate() {
+        let child = if position == index {
+            replacement.clone()
+        } else {
+            child.clone()
+        };
+        copy.add_reference(child).unwrap();
+    }
+    Arc::new(copy)
+}
+
+fn merkle_proof(root: Arc<Cell>) -> Arc<Cell> {
+    let mut data = vec![0x03];
+    data.extend_from_slice(&root.hash_at_level(0));
+    data.extend_from_slice(&root.depth_at_level(0).to_be_bytes());
+    Arc::new(Cell::with_exotic_data(data, 280, vec![root]).unwrap())
+}
+
+/// Rewrites the state proof of `raw.proof` with `rewrite` applied to the
+/// virtual state root.
+fn rewrite_state_proof(raw: &mut AccountState, rewrite: impl Fn(&Arc<Cell>) -> Arc<Cell>) {
+    let roots = deserialize_boc_roots(&raw.proof).unwrap();
+    let state_root = roots[1].reference(0).unwrap();
+    let proof = merkle_proof(rewrite(state_root));
+    raw.proof = serialize_boc_roots(&[roots[0].clone(), proof], true).unwrap();
+}
+
+#[test]
+fn verifies_masterchain_account_state() {
+    let address = master_account();
+    let (anchor, raw) = account_state(&address);
+    assert!(raw.shard_proof.is_empty());
+
+    let verified = verify_account_state(&anchor, &address, raw).unwrap();
+    assert_eq!(verified.anchor(), &anchor);
+    let shard_account = verified.shard_account.as_ref().unwrap();
+    assert_eq!(shard_account.last_trans_lt, 0);
+    assert_eq!(
+        verified.into_inner().account.unwrap(),
+        account(&address, 1_000)
+    );
+}
+
+#[test]
+fn verifies_basechain_account_through_shard_proof() {
+    let address = basechain_account();
+    let (anchor, raw) = account_state(&address);
+    assert_eq!(raw.shardblk.workchain, 0);
+    assert!(!raw.shard_proof.is_empty());
+
+    let verified = verify_account_state(&anchor, &address, raw).unwrap();
+    assert_eq!(
+        verified.account.as_ref().unwrap(),
+        &account(&address, 3_000)
+    );
+}
+
+#[test]
+fn verifies_absent_account() {
+    let address = Address::new(0, [0x45; 32]);
+    let (anchor, raw) = account_state(&address);
+    assert!(raw.state.is_empty());
+
+    let verified = verify_account_state(&anchor, &address, raw).unwrap();
+    assert!(verified.account.is_none());
+    assert!(verified.shard_account.is_none());
+}
+
+#[test]
+fn rejects_response_for_another_block() {
+    let address = master_account();
+    let (mut anchor, raw) = account_state(&address);
+    anchor.root_hash = Int256([0x99; 32]);
+
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(error, ProofError::BlockMismatch { .. }));
+}
+
+#[test]
+fn rejects_proof_for_another_block() {
+    let address = master_account();
+    let (anchor, mut raw) = account_state(&address);
+    let (_, other) = account_state(&basechain_account());
+    raw.proof = other.proof;
+
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(
+        error,
+        ProofError::RootHashMismatch {
+            what: "block proof",
+            ..
+        }
+    ));
+}
+
+#[test]
+fn rejects_state_that_does_not_match_proof() {
+    let address = master_account();
+    let (anchor, mut raw) = account_state(&address);
+    let (_, other) = account_state(&Address::new(-1, [0x35; 32]));
+    raw.state = other.state.clone();
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(error, ProofError::AccountHashMismatch));
+
+    let (anchor, mut raw) = account_state(&address);
+    raw.state.clear();
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(error, ProofError::MissingState(_)));
+
+    let absent = Address::new(-1, [0x34; 32]);
+    let (anchor, mut raw) = account_state(&absent);
+    raw.state = other.state;
+    let error = verify_account_state(&anchor, &absent, raw).unwrap_err();
+    assert!(matches!(error, ProofError::UnexpectedState(_)));
+}
+
+#[test]
+fn rejects_missing_proofs() {
+    let address = master_account();
+    let (anchor, mut raw) = account_state(&address);
+    raw.proof.clear();
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(
+        error,
+        ProofError::Decode {
+            what: "account state proof",
+            ..
+        }
+    ));
+
+    let address = basechain_account();
+    let (anchor, mut raw) = account_state(&address);
+    raw.shard_proof.clear();
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(
+        error,
+        ProofError::Decode {
+            what: "shard proof",
+            ..
+        }
+    ));
+}
+
+#[test]
+fn rejects_shard_block_outside_masterchain_state() {
+    let address = basechain_account();
+    let (anchor, mut raw) = account_state(&address);
+    raw.shardblk.seqno += 1;
+
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(error, ProofError::UnlinkedShardBlock { .. }));
+}
+
+#[test]
+fn rejects_account_outside_shard_block() {
+    let address = basechain_account();
+    let (anchor, raw) = account_state(&address);
+
+    let error = verify_account_state(&anchor, &Address::new(-1, [0x44; 32]), raw).unwrap_err();
+    assert!(matches!(error, ProofError::WrongShard { .. }));
+
+    // Shard 0x2000... covers account ids starting with bits 00.
+    let left = BlockIdExt {
+        workchain: 0,
+        shard: 0x2000_0000_0000_0000,
+        ..masterchain_block()
+    };
+    assert!(!shard_contains_account(&left, &address));
+    assert!(shard_contains_account(&left, &Address::new(0, [0x14; 32])));
+}
+
+#[test]
+fn rejects_shard_without_tag_bit() {
+    let address = basechain_account();
+    let (anchor, mut raw) = account_state(&address);
+    raw.shardblk.shard = 0;
+
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(
+        error,
+        ProofError::Malformed {
+            what: "shard id",
+            ..
+        }
+    ));
+
+    let (anchor, raw) = account_state(&address);
+    let zero = BlockIdExt {
+        shard: 0,
+        ..raw.shardblk.clone()
+    };
+    let error = verify_shard_block(&anchor, &zero, &raw.shard_proof).unwrap_err();
+    assert!(matches!(
+        error,
+        ProofError::Malformed {
+            what: "shard id",
+            ..
+        }
+    ));
+    assert!(shard_prefix_len(1 << 2).is_err());
+    assert_eq!(shard_prefix_len(1 << 3).unwrap(), 60);
+}
+
+#[test]
+fn rejects_pruned_account_path() {
+    let address = master_account();
+    let (anchor, mut raw) = account_state(&address);
+    rewrite_state_proof(&mut raw, |state| {
+        replace_ref(state, 1, pruned(state.reference(1).unwrap()))
+    });
+
+    let error = verify_account_state(&anchor, &address, raw).unwrap_err();
+    assert!(matches!(error, ProofError::PrunedCell("ShardAccounts")));
+}
+
+#[test]
+fn accepts_pruned_siblings_outside_account_path() {
+    let address = master_account();
+    let (anchor, mut raw) = account_state(&address);
+    // Prune out_msg_queue_info and the `^[...]` totals of the state.
+    rewrite_state_proof(&mut raw, |state| {
+        let state = replace_ref(state, 0, pruned(state.reference(0).unwrap()));
+        replace_ref(&state, 2, pruned(state.reference(2).unwrap()))
+    });
+
+    let verified = verify_account_state(&anchor, &address, raw).unwrap();
+    assert!(verified.shard_account.is_some());
+}
+
+const BLOCK_TAG: u32 = 0x11ef55aa;
+const VALIDATOR_LIST_HASH: u32 = 0x5eed_1157;
+const CATCHAIN_SEQNO: u32 = 3;
+const VALIDATOR_WEIGHT: u64 = 10;
+
+/// Masterchain block of a synthetic key-block chain with its proof tree.
+struct ChainBlock {
+    id: BlockIdExt,
+    block: Arc<Cell>,
+}
+
+fn signing_key(index: usize) -> ed25519_dalek::SigningKey {
+    ed25519_dalek::SigningKey::from_bytes(&[index as u8 + 1; 32])
+}
+
+fn node_id_short(index: usize) -> Int256 {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update([0xc6, 0xb4, 0x13, 0x48]);
+    hasher.update(signing_key(index).verifying_key().as_bytes());
+    Int256(hasher.finalize().into())
+}
+
+/// Root of a non-empty `Hashmap` built from `entries`, storing each value
+/// inline or by reference.
+fn dict_root(key_bits: usize, entries: Vec<(u64, Arc<Cell>)>, by_ref: bool) -> Arc<Cell> {
+    let mut dict = crate::tvm::HashmapE::new(key_bits);
+    for (key, value) in entries {
+        dict.insert_bit_key(BitKey::from_u64(key, key_bits).unwrap(), value)
+            .unwrap();
+    }
+    let mut builder = crate::tvm::Builder::new();
+    builder
+        .store_hashmap_e_with(&dict, |builder, value| {
+            if by_ref {
+                builder.store_ref(value.clone())?;
+            } else {
+                builder.store_cell(value)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+    builder.end_cell().unwrap().reference(0).unwrap().clone()
+}
+
+fn empty_cell() -> Arc<Cell> {
+    crate::tvm::Builder::new().end_cell().unwrap()
+}
+
+/// Config dictionary whose param 34 holds `validators` test keys, of which
+/// the first `main` form the masterchain subset.
+fn validator_config(validators: usize, main: u16) -> Arc<Cell> {
+    let descrs = (0..validators)
+        .map(|index| {
+            let mut descr = crate::tvm::Builder::new();
+            descr.store_u8(0x53).unwrap();
+            descr.store_u32(0x8e81278a).unwrap();
+            descr
+                .store_bytes(signing_key(index).verifying_key().as_bytes())
+                .unwrap();
+            descr.store_u64(VALIDATOR_WEIGHT).unwrap();
+            (index as u64, descr.end_cell().unwrap())
+        })
+        .collect();
+    let mut set = crate::tvm::Builder::new();
+    set.store_u8(0x12).unwrap();
+    set.store_u32(0).unwrap(); // utime_since
+    set.store_u32(u32::MAX).unwrap(); // utime_until
+    set.store_u16(validators as u16).unwrap();
+    set.store_u16(main).unwrap();
+    set.store_u64(VALIDATOR_WEIGHT * validators as u64).unwrap();
+    set.store_maybe_ref(Some(dict_root(16, descrs, false)))
+        .unwrap();
+    dict_root(32, vec![(34, set.end_cell().unwrap())], true)
+}
+
+fn store_zero_currency(builder: &mut crate::tvm::Builder) {
+    builder.store_uint_custom::<u8>(0, 4).unwrap();
+    builder.store_bit(false).unwrap();
+}
+
+/// `McBlockExtra` of a key block carrying `config`.
+fn mc_block_extra(key_block: bool, config: Arc<Cell>) -> Arc<Cell> {
+    let mut extra = crate::tvm::Builder::new();
+    extra.store_u16(0xcca5).unwrap();
+    extra.store_bit(key_block).unwrap();
+    extra.store_bit(false).unwrap(); // shard_hashes
+    extra.store_bit(false).unwrap(); // shard_fees
+    store_zero_currency(&mut extra);
+    store_zero_currency(&mut extra);
+    extra.store_ref(empty_cell()).unwrap();
+    if key_block {
+        extra.store_bytes(&[0x55; 32]).unwrap();
+        extra.store_ref(config).unwrap();
+    }
+    extra.end_cell().unwrap()
+}
+
+/// Masterchain state whose `prev_blocks` records `prev`.
+fn mc_state(prev: &BlockIdExt, prev_key_block: bool) -> Arc<Cell> {
+    let mut leaf = crate::tvm::Builder::new();
+    leaf.store_bit(prev_key_block).unwrap();
+    leaf.store_u64(0).unwrap(); // max_end_lt
+    leaf.store_bit(prev_key_block).unwrap();
+    leaf.store_u64(0).unwrap(); // end_lt
+    leaf.store_u32(prev.seqno as u32).unwrap();
+    leaf.store_bytes(&prev.root_hash.0).unwrap();
+    leaf.store_bytes(&prev.file_hash.0).unwrap();
+    let prev_blocks = dict_root(
+        32,
+        vec![(prev.seqno as u64, leaf.end_cell().unwrap())],
+        false,
+    );
+
+    let mut info = crate::tvm::Builder::new();
+    info.store_u16(0).unwrap(); // flags
+    info.store_u32(VALIDATOR_LIST_HASH).unwrap();
+    info.store_u32(CATCHAIN_SEQNO).unwrap();
+    info.store_bit(false).unwrap();
+    info.store_maybe_ref(Some(prev_blocks)).unwrap();
+    info.store_bit(false).unwrap(); // after_key_block
+    info.store_bit(false).unwrap(); // last_key_block
+
+    let mut extra = crate::tvm::Builder::new();
+    extra.store_u16(0xcc26).unwrap();
+    extra.store_bit(false).unwrap(); // shard_hashes
+    extra.store_bytes(&[0x55; 32]).unwrap();
+    extra.store_ref(validator_config(4, 4)).unwrap();
+    extra.store_ref(info.end_cell().unwrap()).unwrap();
+    store_zero_currency(&mut extra);
+
+    let mut state = crate::tvm::Builder::new();
+    state.store_u32(0x9023afe2).unwrap();
+    state.store_int(-239, 32).unwrap();
+    ShardIdent {
+        shard_pfx_bits: 0,
+        workchain_id: -1,
+        shard_prefix: 0,
+    }
+    .store_tlb(&mut state)
+    .unwrap();
+    // seq_no, vert_seq_no, gen_utime
+    state.store_bytes(&[0; 12]).unwrap();
+    state.store_u64(0).unwrap(); // gen_lt
+    state.store_u32(0).unwrap(); // min_ref_mc_seqno
+    state.store_ref(empty_cell()).unwrap();
+    state.store_bit(false).unwrap(); // before_split
+    state.store_ref(empty_cell()).unwrap();
+    state.store_ref(empty_cell()).unwrap();
+    state
+        .store_maybe_ref(Some(extra.end_cell().unwrap()))
+        .unwrap();
+    state.end_cell().unwrap()
+}
+
+fn merkle_update(state: &Arc<Cell>) -> Arc<Cell> {
+    let mut data = vec![0x04];
+    data.extend_from_slice(&state.hash_at_level(0));
+    data.extend_from_slice(&state.hash_at_level(0));
+    data.extend_from_slice(&state.depth_at_level(0).to_be_bytes());
+    data.extend_from_slice(&state.depth_at_level(0).to_be_bytes());
+    Arc::new(Cell::with_exotic_data(data, 552, vec![state.clone(), state.clone()]).unwrap())
+}
+
+/// Masterchain block `seqno`; key blocks carry a four-validator config.
+fn chain_block(
+    seqno: u32,
+    key_block: bool,
+    prev_key_block_seqno: u32,
+    state: Arc<Cell>,
+) -> ChainBlock {
+    let mut info = crate::tvm::Builder::new();
+    info.store_u32(0x9bc7a987).unwrap();
+    info.store_u32(0).unwrap(); // version
+    info.store_uint_custom::<u8>(0, 6).unwrap();
+    info.store_bit(key_block).unwrap();
+    info.store_bit(false).unwrap(); // vert_seqno_incr
+    info.store_u8(0).unwrap(); // flags
+    info.store_u32(seqno).unwrap();
+    info.store_u32(0).unwrap(); // vert_seq_no
+    ShardIdent {
+        shard_pfx_bits: 0,
+        workchain_id: -1,
+        shard_prefix: 0,
+    }
+    .store_tlb(&mut info)
+    .unwrap();
+    info.store_u32(1_700_000_000).unwrap();
+    info.store_u64(0).unwrap(); // start_lt
+    info.store_u64(0).unwrap(); // end_lt
+    info.store_u32(VALIDATOR_LIST_HASH).unwrap();
+    info.store_u32(CATCHAIN_SEQNO).unwrap();
+    info.store_u32(0).unwrap(); // min_ref_mc_seqno
+    info.store_u32(prev_key_block_seqno).unwrap();
+
+    let mut extra = crate::tvm::Builder::new();
+    extra.store_u32(0x4a33f6fd).unwrap();
+    for _ in 0..3 {
+        extra.store_ref(empty_cell()).unwrap();
+    }
+    extra.store_bytes(&[0; 64]).unwrap();
+    extra
+        .store_maybe_ref(Some(mc_block_extra(key_block, validator_config(4, 4))))
+        .unwrap();
+
+    let mut block = crate::tvm::Builder::new();
+    block.store_u32(BLOCK_TAG).unwrap();
+    block.store_int(-239, 32).unwrap();
+    block.store_ref(info.end_cell().unwrap()).unwrap();
+    block.store_ref(empty_cell()).unwrap();
+    block.store_ref(merkle_update(&state)).unwrap();
+    block.store_ref(extra.end_cell().unwrap()).unwrap();
+    let block = block.end_cell().unwrap();
+    ChainBlock {
+        id: BlockIdExt {
+            workchain: -1,
+            shard: i64::MIN,
+            seqno: seqno as i32,
+            root_hash: Int256(block.hash()),
+            file_hash: Int256([seqno as u8; 32]),
+        },
+        block,
+    }
+}
+
+fn proof_boc(root: &Arc<Cell>) -> Vec<u8> {
+    serialize_boc_roots(&[merkle_proof(root.clone())], true).unwrap()
+}
+
+fn signatures(block: &BlockIdExt, signers: &[usize]) -> SignatureSet {
+    let mut message = 0xc50b6e70u32.to_le_bytes().to_vec();
+    message.extend_from_slice(&block.root_hash.0);
+    message.extend_from_slice(&block.file_hash.0);
+    SignatureSet::Ordinary {
+        validator_set_hash: VALIDATOR_LIST_HASH as i32,
+        catchain_seqno: CATCHAIN_SEQNO as i32,
+        signatures: signers
+            .iter()
+            .map(|&index| crate::tl::common::Signature {
+                node_id_short: node_id_short(index),
+                signature: ed25519_dalek::Signer::sign(&signing_key(index), &message)
+                    .to_bytes()
+                    .to_vec(),
+            })
+            .collect(),
+    }
+}
+
+fn forward_link(
+    from: &ChainBlock,
+    to: &ChainBlock,
+    to_key_block: bool,
+    signers: &[usize],
+) -> BlockLink {
+    BlockLink::BlockLinkForward {
+        to_key_block,
+        from: from.id.clone(),
+        to: to.id.clone(),
+        dest_proof: proof_boc(&to.block),
+        config_proof: proof_boc(&from.block),
+        signatures: signatures(&to.id, signers),
+    }
+}
+
+fn block_proof(from: &ChainBlock, to: &ChainBlock, steps: Vec<BlockLink>) -> PartialBlockProof {
+    PartialBlockProof {
+        complete: true,
+        from: from.id.clone(),
+        to: to.id.clone(),
+        steps,
+    }
+}
+
+/// Key block 10 and the following block 15 it links to.
+fn key_block_pair() -> (ChainBlock, ChainBlock) {
+    let key = chain_block(10, true, 0, empty_cell());
+    let next = chain_block(15, false, 10, mc_state(&key.id, true));
+    (key, next)
+}
+
+#[test]
+fn chain_follows_signed_forward_links() {
+    let (key, next) = key_block_pair();
+    let mut chain = BlockProofChain::new(key.id.clone()).unwrap();
+    let proof = block_proof(
+        &key,
+        &next,
+        vec![forward_link(&key, &next, false, &[0, 1, 2])],
+    );
+    assert!(chain.apply(&proof).unwrap());
+    assert_eq!(chain.last(), &next.id);
+    assert_eq!(chain.last_key_block(), &key.id);
+
+    // A new key block becomes the base for later forward links.
+    let next_key = chain_block(20, true, 10, empty_cell());
+    let tip = chain_block(25, false, 20, empty_cell());
+    let proof = block_proof(
+        &key,
+        &tip,
+        vec![
+            forward_link(&key, &next_key, true, &[0, 1, 2, 3]),
+            forward_link(&next_key, &tip, false, &[1, 2, 3]),
+        ],
+    );
+    assert!(chain.apply(&proof).unwrap());
+    assert_eq!(chain.last(), &tip.id);
+    assert_eq!(chain.last_key_block(), &next_key.id);
+}
+
+#[test]
+fn rejects_forward_link_without_two_thirds() {
+    let (key, next) = key_block_pair();
+    let error = verify_block_link(&forward_link(&key, &next, false, &[0, 1, 1])).unwrap_err();
+    assert!(matches!(
+        error,
+        ProofError::InsufficientSignatures {
+            signed: 20,
+            total: 40,
+            ..
+        }
+    ));
+
+    // Signers outside the validator set do not count.
+    let link = forward_link(&key, &next, false, &[0, 1, 4]);
+    assert!(matches!(
+        verify_block_link(&link).unwrap_err(),
+        ProofError::InsufficientSignatures { signed: 20, .. }
+    ));
+}
+
+#[test]
+fn rejects_forged_signature() {
+    let (key, next) = key_block_pair();
+    let mut link = forward_link(&key, &next, false, &[0, 1, 2]);
+    let BlockLink::BlockLinkForward {
+        signatures: SignatureSet::Ordinary { signatures, .. },
+        ..
+    } = &mut link
+    else {
+        unreachable!();
+    };
+    signatures[1].signature[0] ^= 1;
+
+    let error = verify_block_link(&link).unwrap_err();
+    assert!(matches!(error, ProofError::InvalidSignature { .. }));
+}
+
+#[test]
+fn rejects_forward_link_with_mismatched_header() {
+    let (key, next) = key_block_pair();
+    let error = verify_block_link(&forward_link(&key, &next, true, &[0, 1, 2])).unwrap_err();
+    assert!(matches!(error, ProofError::HeaderMismatch { .. }));
+
+    let mut forged = chain_block(15, false, 10, empty_cell());
+    forged.id.root_hash = next.id.root_hash.clone();
+    let error = verify_block_link(&forward_link(&key, &forged, false, &[0, 1, 2])).unwrap_err();
+    assert!(matches!(
+        error,
+        ProofError::RootHashMismatch {
+            what: "block header proof",
+            ..
+        }
+    ));
+
+    // Forward links start at key blocks, whose config holds the validator set.
+    let later = chain_block(16, false, 15, empty_cell());
+    let error = verify_block_link(&forward_link(&next, &later, false, &[0, 1, 2])).unwrap_err();
+    assert!(matches!(error, ProofError::NotKeyBlock(_)));
+}
+
+#[test]
+fn chain_rejects_untrusted_or_unlinked_proofs() {
+    let (key, next) = key_block_pair();
+    let mut chain = BlockProofChain::new(key.id.clone()).unwrap();
+
+    let proof = block_proof(&next, &next, Vec::new());
+    assert!(matches!(
+        chain.apply(&proof).unwrap_err(),
+        ProofError::UntrustedBlock(_)
+    ));
+
+    let tip = chain_block(25, false, 15, empty_cell());
+    let proof = block_proof(
+        &key,
+        &tip,
+        vec![forward_link(&next, &tip, false, &[0, 1, 2])],
+    );
+    assert!(matches!(
+        chain.apply(&proof).unwrap_err(),
+        ProofError::UnlinkedStep { .. }
+    ));
+
+    let proof = block_proof(
+        &key,
+        &tip,
+        vec![forward_link(&key, &next, false, &[0, 1, 2])],
+    );
+    assert!(matches!(
+        chain.apply(&proof).unwrap_err(),
+        ProofError::BlockMismatch { .. }
+    ));
+    assert!(BlockProofChain::new(masterchain_block()).is_ok());
+    assert!(matches!(
+        BlockProofChain::new(BlockIdExt {
+            workchain: 0,
+            ..masterchain_block()
+        })
+        .unwrap_err(),
+        ProofError::NotMasterchain(_)
+    ));
+}
+
+#[test]
+fn verifies_back_link_through_prev_blocks() {
+    let (key, next) = key_block_pair();
+    let state = mc_state(&key.id, true);
+    let back = |to: &BlockIdExt, to_key_block: bool| BlockLink::BlockLinkBack {
+        to_key_block,
+        from: next.id.clone(),
+        to: to.clone(),
+        dest_proof: proof_boc(&key.block),
+        proof: proof_boc(&next.block),
+        state_proof: proof_boc(&state),
+    };
+    verify_block_link(&back(&key.id, true)).unwrap();
+
+    let error = verify_block_link(&back(&key.id, false)).unwrap_err();
+    assert!(matches!(error, ProofError::MissingPrevBlock { .. }));
+
+    let mut other = key.id.clone();
+    other.file_hash = Int256([0xEE; 32]);
+    let error = verify_block_link(&back(&other, true)).unwrap_err();
+    assert!(matches!(error, ProofError::MissingPrevBlock { .. }));
+}
+
+#[cfg(feature = "network-config")]
+#[test]
+fn chain_starts_at_config_init_block() {
+    let json = r#"{
+        "liteservers": [],
+        "validator": {
+            "zero_state": {
+                "workchain": -1,
+                "shard": -9223372036854775808,
+                "seqno": 0,
+                "root_hash": "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=",
+                "file_hash": "AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI="
+            }
+        }
+    }"#;
+    let config: crate::network_config::ConfigGlobal = json.parse().unwrap();
+    let chain = BlockProofChain::from_config(&config).unwrap();
+    assert_eq!(chain.last().seqno, 0);
+    assert_eq!(chain.last_key_block().root_hash, Int256([1; 32]));
+
+    let config: crate::network_config::ConfigGlobal = r#"{"liteservers": []}"#.parse().unwrap();
+    assert!(BlockProofChain::from_config(&config).is_err());
+}
@@ -1,23 +1,34 @@
-use std::future::poll_fn;
-use std::io::ErrorKind;
-use std::net::SocketAddr;
-use std::time::Duration;
+//! LiteAPI server side: a generic ADNL TCP accept loop and a fixture-backed
+//! in-process LiteServer for offline integration tests.
 
-use crate::adnl::AdnlPeer;
-use crate::adnl::crypto::KeyPair;
-use tokio::net::TcpListener;
-use tokio::net::ToSocketAddrs;
-use tokio_tower::multiplex::Server;
-use tower::MakeService;
-use tower::Service;
+pub(super) use std::collections::{BTreeMap, HashMap};
+pub(super) use std::future::poll_fn;
+pub(super) use std::io::ErrorKind;
+pub(super) use std::net::SocketAddr;
+pub(super) use std::sync::{Arc, Mutex};
+pub(super) use std::time::Duration;
 
-use crate::liteclient::peer::LitePeer;
-use crate::tl::adnl::Message;
+pub(super) use crate::adnl::AdnlPeer;
+pub(super) use crate::adnl::crypto::KeyPair;
+pub(super) use tokio::net::TcpListener;
+pub(super) use tokio::net::ToSocketAddrs;
+pub(super) use tokio_tower::multiplex::Server;
+pub(super) use tower::MakeService;
+pub(super) use tower::Service;
+
+pub(super) use crate::liteclient::peer::LitePeer;
+pub(super) use crate::tl::adnl::Message;
+
+mod fixture;
+#[cfg(test)]
+mod tests;
+
+pub use fixture::*;
 
 pub async fn serve<A, M>(
     addr: &A,
     private_key: KeyPair,
-    mut service_maker: M,
+    service_maker: M,
 ) -> Result<(), Box<dyn std::error::Error>>
 where
     A: ToSocketAddrs,
@@ -28,7 +39,25 @@ where
     <M::Service as Service<Message>>::Future: Send,
 {
     let listener = TcpListener::bind(addr).await?;
+    serve_listener(listener, private_key, service_maker).await
+}
 
+/// Runs the accept loop on an already bound listener.
+///
+/// Useful when the caller needs the bound address first, e.g. after binding
+/// `127.0.0.1:0`.
+pub async fn serve_listener<M>(
+    listener: TcpListener,
+    private_key: KeyPair,
+    mut service_maker: M,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    M: MakeService<SocketAddr, Message, Response = Message> + Send,
+    M::Error: std::fmt::Debug,
+    M::MakeError: std::error::Error,
+    M::Service: Send + 'static,
+    <M::Service as Service<Message>>::Future: Send,
+{
     loop {
         let (socket, addr) = match listener.accept().await {
             Ok((socket, addr)) => (socket, addr),
@@ -0,0 +1,659 @@
+use super::*;
+
+use futures::future::{self, Ready};
+use std::convert::Infallible;
+use std::task::{Context, Poll};
+use tokio::task::JoinHandle;
+use tower::Layer;
+
+use crate::adnl::crypto::SecretKey;
+use crate::liteclient::client::LiteClient;
+use crate::liteclient::layers::UnwrapMessagesLayer;
+use crate::liteclient::types::LiteError;
+use crate::tl::common::{AccountId, BlockId, BlockIdExt, Int256, ZeroStateIdExt};
+use crate::tl::request::{
+    GetConfigAll, GetConfigParams, GetTransactions, LookupBlock, Request, RunSmcMethod,
+    WrappedRequest,
+};
+use crate::tl::response::{
+    AccountState, BlockHeader, ConfigInfo, CurrentTime, Error, MasterchainInfo, Response,
+    SendMsgStatus, TransactionList,
+};
+use crate::tlb::{Account, AccountState as TlbAccountState, TlbSerialize, Transaction};
+use crate::tvm::vm::{GetMethodRunner, SmartContractContext};
+use crate::tvm::{
+    Address, BitKey, Builder, Cell, HashmapE, TvmStack, deserialize_boc, serialize_boc,
+    serialize_boc_roots,
+};
+
+mod chain;
+
+use chain::ProvenChain;
+
+/// `ErrorCode::error` from the TON node sources.
+pub const LITE_SERVER_ERROR: i32 = 602;
+/// `ErrorCode::protoviolation`, returned for queries the fixtures cannot answer.
+pub const LITE_SERVER_PROTOVIOLATION: i32 = 621;
+/// `ErrorCode::notready`, returned for blocks and seqnos outside the fixtures.
+pub const LITE_SERVER_NOT_READY: i32 = 651;
+
+/// Upper bound applied to `getTransactions.count`, matching liteservers.
+const MAX_TRANSACTIONS_PER_QUERY: u32 = 16;
+/// Key width of the `ConfigParams` dictionary.
+const CONFIG_PARAM_KEY_BITS: usize = 32;
+
+type AccountKey = (i32, [u8; 32]);
+
+fn account_key(account: &AccountId) -> AccountKey {
+    (account.workchain, account.id.0)
+}
+
+fn lite_error(code: i32, message: impl AsRef<str>) -> Error {
+    Error {
+        code,
+        message: message.as_ref().into(),
+    }
+}
+
+fn fixture_error(error: anyhow::Error) -> Error {
+    lite_error(LITE_SERVER_ERROR, error.to_string())
+}
+
+/// Block known to a fixture server, used by `lookupBlock` and
+/// `getBlockHeader`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureBlock {
+    /// Full block id.
+    pub id: BlockIdExt,
+    /// First logical time inside the block.
+    pub start_lt: u64,
+    /// Logical time after the last transaction of the block.
+    pub end_lt: u64,
+    /// Block generation unix time.
+    pub gen_utime: u32,
+    /// Header proof BoC returned as `liteServer.blockHeader.header_proof`.
+    pub header_proof: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+struct FixtureAccount {
+    account: Account,
+    state: Vec<u8>,
+    runner: Option<GetMethodRunner>,
+}
+
+#[derive(Debug, Clone)]
+struct FixtureTransaction {
+    block: BlockIdExt,
+    hash: [u8; 32],
+    prev_lt: u64,
+    cell: Arc<Cell>,
+}
+
+/// Deterministic chain data answered by [`LocalLiteServer`].
+///
+/// Every answer is computed from the registered fixtures: account states come
+/// with proofs against a synthesized masterchain block whose root hash
+/// replaces the one in `last`, get-methods run on the offline TVM, and
+/// transaction lists follow the `prev_trans_lt` chain of the registered
+/// transactions. Queries outside the fixtures return LiteAPI errors.
+#[derive(Debug, Clone)]
+pub struct LiteServerFixtures {
+    last: BlockIdExt,
+    state_root_hash: Int256,
+    init: ZeroStateIdExt,
+    now: u32,
+    accounts: HashMap<AccountKey, FixtureAccount>,
+    transactions: HashMap<AccountKey, BTreeMap<u64, FixtureTransaction>>,
+    blocks: Vec<FixtureBlock>,
+    config_addr: [u8; 32],
+    config: HashmapE<Arc<Cell>>,
+    sent_messages: Vec<Vec<u8>>,
+}
+
+impl LiteServerFixtures {
+    /// Creates fixtures whose latest masterchain block is `last`.
+    pub fn new(last: BlockIdExt) -> Self {
+        Self {
+            init: ZeroStateIdExt {
+                workchain: -1,
+                root_hash: Int256([0; 32]),
+                file_hash: Int256([0; 32]),
+            },
+            last,
+            state_root_hash: Int256([0; 32]),
+            now: 0,
+            accounts: HashMap::new(),
+            transactions: HashMap::new(),
+            blocks: Vec::new(),
+            config_addr: [0; 32],
+            config: HashmapE::new(CONFIG_PARAM_KEY_BITS),
+            sent_messages: Vec::new(),
+        }
+    }
+
+    pub fn with_state_root_hash(mut self, hash: [u8; 32]) -> Self {
+        self.state_root_hash = Int256(hash);
+        self
+    }
+
+    pub fn with_zero_state(mut self, init: ZeroStateIdExt) -> Self {
+        self.init = init;
+        self
+    }
+
+    /// Unix time returned by `getTime` and exposed to get-methods as `NOW`.
+    pub fn with_time(mut self, now: u32) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Registers an account state; active accounts also answer get-methods.
+    pub fn with_account(mut self, address: &Address, account: &Account) -> anyhow::Result<Self> {
+        self.insert_account(address, account)?;
+        Ok(self)
+    }
+
+    /// Registers a transaction included in `block`.
+    pub fn with_transaction(
+        mut self,
+        block: BlockIdExt,
+        transaction: &Transaction,
+    ) -> anyhow::Result<Self> {
+        self.insert_transaction(block, transaction)?;
+        Ok(self)
+    }
+
+    pub fn with_block(mut self, block: FixtureBlock) -> Self {
+        self.insert_block(block);
+        self
+    }
+
+    /// Sets the config contract address hash reported in `ConfigParams`.
+    pub fn with_config_address(mut self, config_addr: [u8; 32]) -> Self {
+        self.config_addr = config_addr;
+        self
+    }
+
+    /// Registers config parameter `id`.
+    pub fn with_config_param(mut self, id: u32, value: Arc<Cell>) -> anyhow::Result<Self> {
+        self.insert_config_param(id, value)?;
+        Ok(self)
+    }
+
+    /// Registers an account state; `Account::None` is reported as absent.
+    pub fn insert_account(&mut self, address: &Address, account: &Account) -> anyhow::Result<()> {
+        let state = match account {
+            Account::Full { .. } => serialize_boc(&account.to_cell()?, true)?,
+            Account::None => Vec::new(),
+        };
+        let runner = match account {
+            Account::Full { storage, .. } => match &storage.state {
+                TlbAccountState::Active { state_init } if state_init.code.is_some() => {
+                    let context = SmartContractContext {
+                        balance: storage.balance.grams.0.clone(),
+                        address: address.clone(),
+                        ..SmartContractContext::default()
+                    };
+                    Some(GetMethodRunner::from_state_init(state_init)?.with_context(context))
+                }
+                _ => None,
+            },
+            Account::None => None,
+        };
+        self.accounts.insert(
+            account_key(&address.to_account_id()),
+            FixtureAccount {
+                account: account.clone(),
+                state,
+                runner,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn insert_transaction(
+        &mut self,
+        block: BlockIdExt,
+        transaction: &Transaction,
+    ) -> anyhow::Result<()> {
+        let cell = transaction.to_cell()?;
+        self.transactions
+            .entry((block.workchain, transaction.account_addr))
+            .or_default()
+            .insert(
+                transaction.lt,
+                FixtureTransaction {
+                    block,
+                    hash: cell.hash(),
+                    prev_lt: transaction.prev_trans_lt,
+                    cell,
+                },
+            );
+        Ok(())
+    }
+
+    pub fn insert_block(&mut self, block: FixtureBlock) {
+        self.blocks.retain(|known| known.id != block.id);
+        self.blocks.push(block);
+    }
+
+    pub fn insert_config_param(&mut self, id: u32, value: Arc<Cell>) -> anyhow::Result<()> {
+        self.config
+            .insert_bit_key(BitKey::from_u64(id as u64, CONFIG_PARAM_KEY_BITS)?, value)?;
+        Ok(())
+    }
+
+    /// Moves the latest masterchain block forward, e.g. to test
+    /// `waitMasterchainSeqno`.
+    pub fn set_last_block(&mut self, last: BlockIdExt) {
+        self.last = last;
+    }
+
+    pub fn last_block(&self) -> &BlockIdExt {
+        &self.last
+    }
+
+    /// External message BoCs accepted by `sendMessage`, oldest first.
+    pub fn sent_messages(&self) -> &[Vec<u8>] {
+        &self.sent_messages
+    }
+
+    /// Answers one LiteAPI query exactly as the server would.
+    pub fn answer(&mut self, request: WrappedRequest) -> Response {
+        if let Some(wait) = &request.wait_masterchain_seqno
+            && wait.seqno > self.last.seqno as u32
+        {
+            return Response::Error(lite_error(
+                LITE_SERVER_NOT_READY,
+                format!(
+                    "masterchain seqno {} is not available, last is {}",
+                    wait.seqno, self.last.seqno
+                ),
+            ));
+        }
+        self.answer_request(request.request)
+            .unwrap_or_else(Response::Error)
+    }
+
+    fn answer_request(&mut self, request: Request) -> Result<Response, Error> {
+        match request {
+            Request::GetMasterchainInfo => Ok(Response::MasterchainInfo(MasterchainInfo {
+                last: self.proven_chain()?.last().clone(),
+                state_root_hash: self.state_root_hash.clone(),
+                init: self.init.clone(),
+            })),
+            Request::GetTime => Ok(Response::CurrentTime(CurrentTime { now: self.now })),
+            Request::GetAccountState(query) | Request::GetAccountStatePrunned(query) => {
+                let chain = self.proven_chain()?;
+                if query.id != *chain.last() {
+                    return Err(lite_error(
+                        LITE_SERVER_NOT_READY,
+                        format!("block {} not found", query.id),
+                    ));
+                }
+                let state = self
+                    .accounts
+                    .get(&account_key(&query.account))
+                    .map(|account| account.state.clone())
+                    .unwrap_or_default();
+                chain
+                    .account_state(&query.account, state)
+                    .map(Response::AccountState)
+            }
+            Request::RunSmcMethod(query) => self.run_smc_method(query),
+            Request::SendMessage(query) => {
+                deserialize_boc(&query.body).map_err(|error| {
+                    lite_error(
+                        LITE_SERVER_ERROR,
+                        format!("cannot deserialize external message: {error}"),
+                    )
+                })?;
+                self.sent_messages.push(query.body);
+                Ok(Response::SendMsgStatus(SendMsgStatus { status: 1 }))
+            }
+            Request::GetTransactions(query) => self.get_transactions(query),
+            Request::LookupBlock(query) => self.lookup_block(query),
+            Request::GetBlockHeader(query) => {
+                let block = self
+                    .blocks
+                    .iter()
+                    .find(|block| block.id == query.id)
+                    .ok_or_else(|| {
+                        lite_error(
+                            LITE_SERVER_NOT_READY,
+                            format!("block {} not found", query.id),
+                        )
+                    })?;
+                Ok(Response::BlockHeader(BlockHeader {
+                    id: block.id.clone(),
+                    mode: (),
+                    with_state_update: query.with_state_update,
+                    with_value_flow: query.with_value_flow,
+                    with_extra: query.with_extra,
+                    with_shard_hashes: query.with_shard_hashes,
+                    with_prev_blk_signatures: query.with_prev_blk_signatures,
+                    header_proof: block.header_proof.clone(),
+                }))
+            }
+            Request::GetConfigParams(query) => self.get_config_params(query),
+            Request::GetConfigAll(query) => self.get_config_all(query),
+            _ => Err(lite_error(
+                LITE_SERVER_PROTOVIOLATION,
+                "query is not supported by the fixture liteserver",
+            )),
+        }
+    }
+
+    fn proven_chain(&self) -> Result<ProvenChain, Error> {
+        ProvenChain::build(self).map_err(fixture_error)
+    }
+
+    fn run_smc_method(&self, query: RunSmcMethod) -> Result<Response, Error> {
+        let runner = self
+            .accounts
+            .get(&account_key(&query.account))
+            .and_then(|account| account.runner.as_ref())
+            .ok_or_else(|| {
+                lite_error(
+                    LITE_SERVER_ERROR,
+                    "cannot run any methods on inactive account",
+                )
+            })?;
+        let stack = if query.params.is_empty() {
+            TvmStack::empty()
+        } else {
+            TvmStack::from_boc(&query.params).map_err(fixture_error)?
+        };
+        let context = SmartContractContext {
+            now: self.now,
+            ..runner.context().clone()
+        };
+        // The result stack is returned regardless of `mode.2`: the crate's
+        // contract helpers query with mode 0 and expect a stack back.
+        let result = runner
+            .clone()
+            .with_context(context)
+            .run(query.method_id, stack)
+            .and_then(|run| run.into_run_method_result(query.id))
+            .map_err(fixture_error)?;
+        Ok(Response::RunMethodResult(result))
+    }
+
+    fn get_transactions(&self, query: GetTransactions) -> Result<Response, Error> {
+        let not_found = || {
+            lite_error(
+                LITE_SERVER_ERROR,
+                format!("cannot locate transaction with lt {}", query.lt),
+            )
+        };
+        let chain = self
+            .transactions
+            .get(&account_key(&query.account))
+            .ok_or_else(not_found)?;
+        let first = chain.get(&query.lt).ok_or_else(not_found)?;
+        if first.hash != query.hash.0 {
+            return Err(lite_error(
+                LITE_SERVER_ERROR,
+                format!("transaction hash mismatch for lt {}", query.lt),
+            ));
+        }
+
+        let mut ids = Vec::new();
+        let mut roots = Vec::new();
+        let mut next = Some(first);
+        while let Some(transaction) = next {
+            if roots.len() as u32 >= query.count.min(MAX_TRANSACTIONS_PER_QUERY) {
+                break;
+            }
+            ids.push(transaction.block.clone());
+            roots.push(transaction.cell.clone());
+            next = chain.get(&transaction.prev_lt);
+        }
+        let transactions = if roots.is_empty() {
+            Vec::new()
+        } else {
+            serialize_boc_roots(&roots, true).map_err(fixture_error)?
+        };
+        Ok(Response::TransactionList(TransactionList {
+            ids,
+            transactions,
+        }))
+    }
+
+    fn lookup_block(&self, query: LookupBlock) -> Result<Response, Error> {
+        let BlockId {
+            workchain,
+            shard,
+            seqno,
+        } = query.id.clone();
+        let same_shard = self
+            .blocks
+            .iter()
+            .filter(|block| block.id.workchain == workchain && block.id.shard == shard);
+        let found = if query.seqno.is_some() {
+            same_shard.into_iter().find(|block| block.id.seqno == seqno)
+        } else if let Some(lt) = query.lt {
+            same_shard
+                .into_iter()
+                .find(|block| block.start_lt <= lt && lt < block.end_lt)
+        } else if let Some(utime) = query.utime {
+            same_shard
+                .filter(|block| block.gen_utime <= utime)
+                .max_by_key(|block| block.id.seqno)
+        } else {
+            return Err(lite_error(
+                LITE_SERVER_PROTOVIOLATION,
+                "exactly one of seqno, lt or utime must be set",
+            ));
+        };
+        let block = found.ok_or_else(|| {
+            lite_error(
+                LITE_SERVER_NOT_READY,
+                format!("block {workchain}:{shard:016x}:{seqno} not found"),
+            )
+        })?;
+        Ok(Response::BlockHeader(BlockHeader {
+            id: block.id.clone(),
+            mode: (),
+            with_state_update: query.with_state_update,
+            with_value_flow: query.with_value_flow,
+            with_extra: query.with_extra,
+            with_shard_hashes: query.with_shard_hashes,
+            with_prev_blk_signatures: query.with_prev_blk_signatures,
+            header_proof: block.header_proof.clone(),
+        }))
+    }
+
+    fn get_config_params(&self, query: GetConfigParams) -> Result<Response, Error> {
+        let mut subset = HashmapE::new(CONFIG_PARAM_KEY_BITS);
+        for id in &query.param_list {
+            let key = BitKey::from_u64(*id as u32 as u64, CONFIG_PARAM_KEY_BITS)
+                .map_err(fixture_error)?;
+            if let Some(value) = self.config.get_bit_key(&key).map_err(fixture_error)? {
+                subset
+                    .insert_bit_key(key, value.clone())
+                    .map_err(fixture_error)?;
+            }
+        }
+        Ok(Response::ConfigInfo(ConfigInfo {
+            mode: (),
+            id: query.id,
+            state_proof: Vec::new(),
+            config_proof: self.config_params_boc(&subset)?,
+            with_state_root: query.with_state_root,
+            with_libraries: query.with_libraries,
+            with_state_extra_root: query.with_state_extra_root,
+            with_shard_hashes: query.with_shard_hashes,
+            with_validator_set: query.with_validator_set,
+            with_special_smc: query.with_special_smc,
+            with_accounts_root: query.with_accounts_root,
+            with_prev_blocks: query.with_prev_blocks,
+            with_workchain_info: query.with_workchain_info,
+            with_capabilities: query.with_capabilities,
+            extract_from_key_block: query.extract_from_key_block,
+        }))
+    }
+
+    fn get_config_all(&self, query: GetConfigAll) -> Result<Response, Error> {
+        Ok(Response::ConfigInfo(ConfigInfo {
+            mode: (),
+            id: query.id,
+            state_proof: Vec::new(),
+            config_proof: self.config_params_boc(&self.config)?,
+            with_state_root: query.with_state_root,
+            with_libraries: query.with_libraries,
+            with_state_extra_root: query.with_state_extra_root,
+            with_shard_hashes: query.with_shard_hashes,
+            with_validator_set: query.with_validator_set,
+            with_special_smc: query.with_special_smc,
+            with_accounts_root: query.with_accounts_root,
+            with_prev_blocks: query.with_prev_blocks,
+            with_workchain_info: query.with_workchain_info,
+            with_capabilities: query.with_capabilities,
+            extract_from_key_block: query.extract_from_key_block,
+        }))
+    }
+
+    /// Serializes `ConfigParams` over `params` in the layout read by
+    /// `ConfigParams::config_entries`.
+    fn config_params_boc(&self, params: &HashmapE<Arc<Cell>>) -> Result<Vec<u8>, Error> {
+        let build = || -> anyhow::Result<Vec<u8>> {
+            let mut config = Builder::new();
+            config.store_hashmap_e_with(params, |builder, value| {
+                builder.store_ref(value.clone())?;
+                Ok(())
+            })?;
+            let config = crate::tlb::ConfigParams {
+                config_addr: self.config_addr,
+                config: config.end_cell()?,
+            };
+            serialize_boc(&config.to_cell()?, true)
+        };
+        build().map_err(fixture_error)
+    }
+}
+
+/// Tower service answering decoded LiteAPI queries from shared fixtures.
+#[derive(Debug, Clone)]
+pub struct FixtureLiteService {
+    fixtures: Arc<Mutex<LiteServerFixtures>>,
+}
+
+impl FixtureLiteService {
+    pub fn new(fixtures: Arc<Mutex<LiteServerFixtures>>) -> Self {
+        Self { fixtures }
+    }
+}
+
+impl Service<WrappedRequest> for FixtureLiteService {
+    type Response = Response;
+    type Error = LiteError;
+    type Future = Ready<Result<Response, LiteError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+        let mut fixtures = self
+            .fixtures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        future::ready(Ok(fixtures.answer(request)))
+    }
+}
+
+/// In-process LiteServer speaking LiteAPI over ADNL TCP.
+///
+/// The server performs the real ADNL handshake, frame encryption and TL
+/// encoding, so `LiteClient` and `LiteBalancer` can be tested against
+/// `127.0.0.1` without network access. The accept loop stops when the server
+/// is dropped.
+///
+/// ```no_run
+/// # async fn example(fixtures: tonutils::liteclient::server::LiteServerFixtures) -> anyhow::Result<()> {
+/// use tonutils::liteclient::server::LocalLiteServer;
+///
+/// let server = LocalLiteServer::start(fixtures).await?;
+/// let mut client = server.connect().await?;
+/// let info = client.get_masterchain_info().await?;
+/// println!("{}", info.last);
+/// # Ok(())
+/// # }
+/// ```
+pub struct LocalLiteServer {
+    address: SocketAddr,
+    public_key: [u8; 32],
+    fixtures: Arc<Mutex<LiteServerFixtures>>,
+    handle: JoinHandle<()>,
+}
+
+impl LocalLiteServer {
+    /// Starts a server on an ephemeral `127.0.0.1` port with a random key.
+    pub async fn start(fixtures: LiteServerFixtures) -> std::io::Result<Self> {
+        let key = KeyPair::from(&SecretKey::from_bytes(rand::random()));
+        Self::bind("127.0.0.1:0", key, fixtures).await
+    }
+
+    /// Starts a server on `addr` using `key` as the ADNL server key.
+    pub async fn bind<A: ToSocketAddrs>(
+        addr: A,
+        key: KeyPair,
+        fixtures: LiteServerFixtures,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let address = listener.local_addr()?;
+        let fixtures = Arc::new(Mutex::new(fixtures));
+        let service = FixtureLiteService::new(fixtures.clone());
+        let make_service = tower::service_fn(move |_: SocketAddr| {
+            let service = UnwrapMessagesLayer.layer(service.clone());
+            future::ok::<_, Infallible>(service)
+        });
+        let handle = tokio::spawn(async move {
+            if let Err(error) = serve_listener(listener, key, make_service).await {
+                log::error!("fixture liteserver stopped: {error}");
+            }
+        });
+        Ok(Self {
+            address,
+            public_key: key.public_key.to_bytes(),
+            fixtures,
+            handle,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Ed25519 public key clients pass to `LiteClient::connect`.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    /// Opens a new `LiteClient` connection to this server.
+    pub async fn connect(&self) -> Result<LiteClient, LiteError> {
+        LiteClient::connect(self.address, self.public_key).await
+    }
+
+    /// Runs `update` against the live fixtures.
+    pub fn update<T>(&self, update: impl FnOnce(&mut LiteServerFixtures) -> T) -> T {
+        let mut fixtures = self
+            .fixtures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        update(&mut fixtures)
+    }
+
+    /// External message BoCs accepted so far.
+    pub fn sent_messages(&self) -> Vec<Vec<u8>> {
+        self.update(|fixtures| fixtures.sent_messages().to_vec())
+    }
+}
+
+impl Drop for LocalLiteServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
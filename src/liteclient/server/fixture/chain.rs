@@ -0,0 +1,378 @@
+//! Synthetic masterchain and basechain blocks backing fixture account proofs.
+//!
+//! The fixtures only know account states, so the server derives one shard
+//! state per workchain, wraps each in a block whose `state_update` points at
+//! it, and links the basechain block from `McStateExtra.shard_hashes`. Proofs
+//! are unpruned Merkle proofs of those trees.
+
+use super::*;
+
+use crate::tlb::{ConfigParams, CurrencyCollection, ExtBlkRef, Grams, ShardAccount, ShardIdent};
+use crate::tvm::{HashmapAug, HashmapAugE, HashmapAugLeaf, merkle_proof, merkle_update};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/// Mainnet global id stored in synthesized blocks and states.
+const GLOBAL_ID: i32 = -239;
+const BLOCK_TAG: u32 = 0x11ef55aa;
+const BLOCK_INFO_TAG: u32 = 0x9bc7a987;
+const VALUE_FLOW_TAG: u32 = 0xb8e48dfb;
+const BLOCK_EXTRA_TAG: u32 = 0x4a33f6fd;
+const SHARD_STATE_TAG: u32 = 0x9023afe2;
+const MC_STATE_EXTRA_TAG: u16 = 0xcc26;
+const SHARD_DESCR_TAG: u8 = 0xb;
+const BASECHAIN: i32 = 0;
+const FULL_SHARD: i64 = i64::MIN;
+
+/// Block together with the shard state it commits to.
+struct ProvenBlock {
+    id: BlockIdExt,
+    block: Arc<Cell>,
+    state: Arc<Cell>,
+}
+
+impl ProvenBlock {
+    /// `[block proof, state proof]` BoC as returned by `getAccountState`.
+    fn proof_boc(&self) -> anyhow::Result<Vec<u8>> {
+        serialize_boc_roots(
+            &[merkle_proof(&self.block)?, merkle_proof(&self.state)?],
+            true,
+        )
+    }
+}
+
+/// Masterchain block and the single basechain block it references.
+pub(super) struct ProvenChain {
+    master: ProvenBlock,
+    basechain: ProvenBlock,
+}
+
+impl ProvenChain {
+    pub(super) fn build(fixtures: &LiteServerFixtures) -> anyhow::Result<Self> {
+        let last = &fixtures.last;
+        let basechain_state = shard_state(
+            fixtures,
+            BASECHAIN,
+            last.seqno as u32,
+            shard_accounts(fixtures, BASECHAIN)?,
+            None,
+        )?;
+        let basechain_id = BlockIdExt {
+            workchain: BASECHAIN,
+            shard: FULL_SHARD,
+            seqno: last.seqno,
+            root_hash: Int256([0; 32]),
+            file_hash: Int256([0; 32]),
+        };
+        let basechain = seal_block(fixtures, basechain_id, basechain_state)?;
+
+        let master_state = shard_state(
+            fixtures,
+            last.workchain,
+            last.seqno as u32,
+            shard_accounts(fixtures, last.workchain)?,
+            Some(mc_state_extra(fixtures, &basechain.id)?),
+        )?;
+        let mut master = seal_block(fixtures, last.clone(), master_state)?;
+        // The masterchain file hash is whatever the fixtures were given.
+        master.id.file_hash = last.file_hash.clone();
+        Ok(Self { master, basechain })
+    }
+
+    /// Masterchain block id whose root hash commits to the fixture state.
+    pub(super) fn last(&self) -> &BlockIdExt {
+        &self.master.id
+    }
+
+    /// `liteServer.accountState` with shard and account proofs for `account`.
+    pub(super) fn account_state(
+        &self,
+        account: &AccountId,
+        state: Vec<u8>,
+    ) -> Result<AccountState, Error> {
+        if account.workchain != self.master.id.workchain && account.workchain != BASECHAIN {
+            return Err(lite_error(
+                LITE_SERVER_NOT_READY,
+                format!("workchain {} is not known", account.workchain),
+            ));
+        }
+        let build = || -> anyhow::Result<AccountState> {
+            let (shardblk, shard_proof, proof) = if account.workchain == self.master.id.workchain {
+                (self.master.id.clone(), Vec::new(), self.master.proof_boc()?)
+            } else {
+                (
+                    self.basechain.id.clone(),
+                    self.master.proof_boc()?,
+                    self.basechain.proof_boc()?,
+                )
+            };
+            Ok(AccountState {
+                id: self.master.id.clone(),
+                shardblk,
+                shard_proof,
+                proof,
+                state,
+            })
+        };
+        build().map_err(fixture_error)
+    }
+}
+
+fn shard_ident(workchain: i32) -> ShardIdent {
+    ShardIdent {
+        shard_pfx_bits: 0,
+        workchain_id: workchain,
+        shard_prefix: 0,
+    }
+}
+
+fn zero_currency() -> CurrencyCollection {
+    CurrencyCollection::grams(Grams::from(0))
+}
+
+fn store_zero_currency(builder: &mut Builder) -> anyhow::Result<()> {
+    zero_currency().store_tlb(builder)?;
+    Ok(())
+}
+
+/// `ShardAccounts` of one workchain with the total balance of its accounts.
+///
+/// Fork augmentations are left at zero; verifiers only follow the key path.
+fn shard_accounts(
+    fixtures: &LiteServerFixtures,
+    workchain: i32,
+) -> anyhow::Result<HashmapAugE<ShardAccount, CurrencyCollection>> {
+    let mut total = BigUint::from(0u8);
+    let mut leaves = Vec::new();
+    for ((account_workchain, hash), fixture) in &fixtures.accounts {
+        let Account::Full { storage, .. } = &fixture.account else {
+            continue;
+        };
+        if *account_workchain != workchain {
+            continue;
+        }
+        let (last_trans_hash, last_trans_lt) = fixtures
+            .transactions
+            .get(&(workchain, *hash))
+            .and_then(|chain| chain.iter().next_back())
+            .map(|(lt, transaction)| (transaction.hash, *lt))
+            .unwrap_or_default();
+        total += &storage.balance.grams.0;
+        leaves.push(HashmapAugLeaf {
+            key: BitKey::new(hash.to_vec(), 256)?,
+            value: ShardAccount {
+                account: fixture.account.clone(),
+                last_trans_hash,
+                last_trans_lt,
+            },
+            extra: storage.balance.clone(),
+        });
+    }
+    let total = CurrencyCollection::grams(Grams(total));
+    if leaves.is_empty() {
+        return Ok(HashmapAugE::empty(256, total));
+    }
+    let root = HashmapAug::from_entries(256, leaves, zero_currency())?;
+    HashmapAugE::with_root(256, root, total)
+}
+
+fn shard_state(
+    fixtures: &LiteServerFixtures,
+    workchain: i32,
+    seqno: u32,
+    accounts: HashmapAugE<ShardAccount, CurrencyCollection>,
+    custom: Option<Arc<Cell>>,
+) -> anyhow::Result<Arc<Cell>> {
+    // out_msg_queue_info: empty OutMsgQueue, ProcessedInfo and IhrPendingInfo.
+    let mut out_msg_queue_info = Builder::new();
+    out_msg_queue_info.store_uint_custom::<u8>(0, 3)?;
+
+    let mut accounts_cell = Builder::new();
+    accounts_cell.store_hashmap_aug_e_with(
+        &accounts,
+        |builder, account| Ok(account.store_tlb(builder)?),
+        |builder, balance| {
+            // depth_balance$_ split_depth:(#<= 30) balance:CurrencyCollection
+            builder.store_uint_custom::<u8>(0, 5)?;
+            Ok(balance.store_tlb(builder)?)
+        },
+    )?;
+
+    let mut totals = Builder::new();
+    totals.store_u64(0)?; // overload_history
+    totals.store_u64(0)?; // underload_history
+    accounts.extra().store_tlb(&mut totals)?;
+    store_zero_currency(&mut totals)?; // total_validator_fees
+    totals.store_bit(false)?; // libraries
+    totals.store_bit(false)?; // master_ref
+
+    let mut state = Builder::new();
+    state.store_u32(SHARD_STATE_TAG)?;
+    state.store_int(GLOBAL_ID as i64, 32)?;
+    shard_ident(workchain).store_tlb(&mut state)?;
+    state.store_u32(seqno)?;
+    state.store_u32(0)?; // vert_seq_no
+    state.store_u32(fixtures.now)?;
+    state.store_u64(0)?; // gen_lt
+    state.store_u32(0)?; // min_ref_mc_seqno
+    state.store_ref(out_msg_queue_info.end_cell()?)?;
+    state.store_bit(false)?; // before_split
+    state.store_ref(accounts_cell.end_cell()?)?;
+    state.store_ref(totals.end_cell()?)?;
+    state.store_maybe_ref(custom)?;
+    state.end_cell()
+}
+
+/// `McStateExtra` whose `shard_hashes` hold `basechain` as the only shard.
+fn mc_state_extra(
+    fixtures: &LiteServerFixtures,
+    basechain: &BlockIdExt,
+) -> anyhow::Result<Arc<Cell>> {
+    // bt_leaf$0 leaf:ShardDescr, using the shard_descr#b layout.
+    let mut descr = Builder::new();
+    descr.store_bit(false)?;
+    descr.store_uint_custom::<u8>(SHARD_DESCR_TAG, 4)?;
+    descr.store_u32(basechain.seqno as u32)?;
+    descr.store_u32(fixtures.last.seqno as u32)?; // reg_mc_seqno
+    descr.store_u64(0)?; // start_lt
+    descr.store_u64(0)?; // end_lt
+    descr.store_bytes(&basechain.root_hash.0)?;
+    descr.store_bytes(&basechain.file_hash.0)?;
+    // before_split, before_merge, want_split, want_merge, nx_cc_updated, flags
+    descr.store_u8(0)?;
+    descr.store_u32(0)?; // next_catchain_seqno
+    descr.store_u64(basechain.shard as u64)?; // next_validator_shard
+    descr.store_u32(0)?; // min_ref_mc_seqno
+    descr.store_u32(fixtures.now)?;
+    descr.store_bit(false)?; // split_merge_at: fsm_none
+    store_zero_currency(&mut descr)?; // fees_collected
+    store_zero_currency(&mut descr)?; // funds_created
+
+    let mut shard_hashes = HashmapE::new(32);
+    shard_hashes.insert_bit_key(
+        BitKey::from_u64(basechain.workchain as u32 as u64, 32)?,
+        descr.end_cell()?,
+    )?;
+
+    let mut config = Builder::new();
+    config.store_hashmap_e_with(&fixtures.config, |builder, value| {
+        builder.store_ref(value.clone())?;
+        Ok(())
+    })?;
+    let config = ConfigParams {
+        config_addr: fixtures.config_addr,
+        config: config.end_cell()?,
+    };
+
+    let mut flags = Builder::new();
+    flags.store_u16(0)?;
+    // validator_info: validator_list_hash_short, catchain_seqno, nx_cc_updated
+    flags.store_u32(0)?;
+    flags.store_u32(0)?;
+    flags.store_bit(false)?;
+    // prev_blocks: empty OldMcBlocksInfo with a zero KeyMaxLt
+    flags.store_bit(false)?;
+    flags.store_bit(false)?;
+    flags.store_u64(0)?;
+    flags.store_bit(false)?; // after_key_block
+    flags.store_bit(false)?; // last_key_block
+
+    let mut extra = Builder::new();
+    extra.store_u16(MC_STATE_EXTRA_TAG)?;
+    extra.store_hashmap_e_with(&shard_hashes, |builder, tree| {
+        builder.store_ref(tree.clone())?;
+        Ok(())
+    })?;
+    config.store_tlb(&mut extra)?;
+    extra.store_ref(flags.end_cell()?)?;
+    store_zero_currency(&mut extra)?; // global_balance
+    extra.end_cell()
+}
+
+/// Builds the block committing to `state` and fills in its root and file
+/// hashes.
+fn seal_block(
+    fixtures: &LiteServerFixtures,
+    mut id: BlockIdExt,
+    state: Arc<Cell>,
+) -> anyhow::Result<ProvenBlock> {
+    let not_master = id.workchain != fixtures.last.workchain;
+    let prev = ExtBlkRef {
+        end_lt: 0,
+        seq_no: (id.seqno as u32).saturating_sub(1),
+        root_hash: [0; 32],
+        file_hash: [0; 32],
+    };
+
+    let mut info = Builder::new();
+    info.store_u32(BLOCK_INFO_TAG)?;
+    info.store_u32(0)?; // version
+    info.store_bit(not_master)?;
+    // after_merge, before_split, after_split, want_split, want_merge,
+    // key_block, vert_seqno_incr
+    info.store_uint_custom::<u8>(0, 7)?;
+    info.store_u8(0)?; // flags
+    info.store_u32(id.seqno as u32)?;
+    info.store_u32(0)?; // vert_seq_no
+    shard_ident(id.workchain).store_tlb(&mut info)?;
+    info.store_u32(fixtures.now)?;
+    info.store_u64(0)?; // start_lt
+    info.store_u64(0)?; // end_lt
+    info.store_u32(0)?; // gen_validator_list_hash_short
+    info.store_u32(0)?; // gen_catchain_seqno
+    info.store_u32(0)?; // min_ref_mc_seqno
+    info.store_u32(0)?; // prev_key_block_seqno
+    if not_master {
+        let master_ref = ExtBlkRef {
+            seq_no: (fixtures.last.seqno as u32).saturating_sub(1),
+            ..prev.clone()
+        };
+        info.store_ref(master_ref.to_cell()?)?;
+    }
+    info.store_ref(prev.to_cell()?)?;
+
+    let mut imported = Builder::new();
+    for _ in 0..4 {
+        store_zero_currency(&mut imported)?;
+    }
+    let mut value_flow = Builder::new();
+    value_flow.store_u32(VALUE_FLOW_TAG)?;
+    value_flow.store_ref(imported.end_cell()?)?;
+    store_zero_currency(&mut value_flow)?; // fees_collected
+    let mut created = Builder::new();
+    for _ in 0..4 {
+        store_zero_currency(&mut created)?;
+    }
+    value_flow.store_ref(created.end_cell()?)?;
+
+    // Empty InMsgDescr (ImportFees extra), OutMsgDescr and ShardAccountBlocks.
+    let mut in_msg_descr = Builder::new();
+    in_msg_descr.store_bit(false)?;
+    in_msg_descr.store_uint_custom::<u8>(0, 4)?;
+    store_zero_currency(&mut in_msg_descr)?;
+    let mut block_extra = Builder::new();
+    block_extra.store_u32(BLOCK_EXTRA_TAG)?;
+    block_extra.store_ref(in_msg_descr.end_cell()?)?;
+    for _ in 0..2 {
+        let mut descr = Builder::new();
+        descr.store_bit(false)?;
+        store_zero_currency(&mut descr)?;
+        block_extra.store_ref(descr.end_cell()?)?;
+    }
+    block_extra.store_bytes(&[0; 32])?; // rand_seed
+    block_extra.store_bytes(&[0; 32])?; // created_by
+    block_extra.store_bit(false)?; // custom
+
+    let mut block = Builder::new();
+    block.store_u32(BLOCK_TAG)?;
+    block.store_int(GLOBAL_ID as i64, 32)?;
+    block.store_ref(info.end_cell()?)?;
+    block.store_ref(value_flow.end_cell()?)?;
+    block.store_ref(merkle_update(&state, &state)?)?;
+    block.store_ref(block_extra.end_cell()?)?;
+    let block = block.end_cell()?;
+
+    id.root_hash = Int256(block.hash());
+    id.file_hash = Int256(Sha256::digest(serialize_boc(&block, true)?).into());
+    Ok(ProvenBlock { id, block, state })
+}
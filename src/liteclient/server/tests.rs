@@ -0,0 +1,423 @@
+use super::*;
+
+use num_bigint::BigUint;
+use std::time::Duration;
+
+use crate::contracts::test_fixtures::block;
+use crate::liteclient::balancer::LiteBalancer;
+use crate::tl::common::{BlockId, Int256};
+use crate::tl::request::{
+    GetConfigParams, GetTransactions, LookupBlock, Request, WaitMasterchainSeqno, WrappedRequest,
+};
+use crate::tl::response::Response;
+use crate::tlb::{
+    Account, AccountState, AccountStatus, AccountStorage, CurrencyCollection, Grams,
+    HashUpdateAccount, MsgAddressInt, StorageExtraInfo, StorageInfo, StorageUsed, TlbSerialize,
+    TrStoragePhase, Transaction, TransactionDescr,
+};
+use crate::tvm::{Address, Builder, HashmapE, TvmStack, TvmStackEntry, serialize_boc};
+use crate::wallet::{WALLET_V5R1_MAINNET_DEFAULT_ID, WalletV5R1, wallet_v5r1_code};
+
+fn fixture_block(seqno: i32, start_lt: u64, gen_utime: u32) -> FixtureBlock {
+    FixtureBlock {
+        id: block(-1, seqno),
+        start_lt,
+        end_lt: start_lt + 100,
+        gen_utime,
+        header_proof: vec![seqno as u8],
+    }
+}
+
+fn wallet() -> WalletV5R1 {
+    WalletV5R1::new(
+        [0x11; 32],
+        WALLET_V5R1_MAINNET_DEFAULT_ID,
+        wallet_v5r1_code().unwrap(),
+        0,
+    )
+}
+
+fn active_account(address: &Address, wallet: &WalletV5R1) -> Account {
+    Account::Full {
+        addr: MsgAddressInt::std(address.clone()),
+        storage_stat: StorageInfo {
+            used: StorageUsed::new(BigUint::from(1u8), BigUint::from(8u8)),
+            last_paid: 1_700_000_000,
+            due_payment: None,
+            extra: StorageExtraInfo::None,
+        },
+        storage: AccountStorage {
+            last_trans_lt: 300,
+            balance: CurrencyCollection::grams(Grams::from(5_000_000_000)),
+            state: AccountState::Active {
+                state_init: wallet.state_init().unwrap(),
+            },
+        },
+    }
+}
+
+fn transaction(account: [u8; 32], lt: u64, prev_lt: u64, prev_hash: [u8; 32]) -> Transaction {
+    Transaction {
+        account_addr: account,
+        lt,
+        prev_trans_hash: prev_hash,
+        prev_trans_lt: prev_lt,
+        now: 1_700_000_000 + lt as u32,
+        outmsg_cnt: 0,
+        orig_status: AccountStatus::Active,
+        end_status: AccountStatus::Active,
+        in_msg: None,
+        out_msgs: HashmapE::new(15),
+        total_fees: CurrencyCollection::grams(Grams::from(lt)),
+        state_update: HashUpdateAccount {
+            old_hash: [0xAA; 32],
+            new_hash: [0xBB; 32],
+        },
+        description: TransactionDescr::Storage {
+            storage_ph: TrStoragePhase {
+                storage_fees_collected: Grams::from(1),
+                storage_fees_due: None,
+                status_change: crate::tlb::AccStatusChange::Unchanged,
+            },
+        },
+    }
+}
+
+/// Two chained transactions for `account`, newest first.
+fn transaction_chain(account: [u8; 32]) -> Vec<Transaction> {
+    let older = transaction(account, 200, 0, [0; 32]);
+    let newer = transaction(account, 300, 200, older.to_cell().unwrap().hash());
+    vec![newer, older]
+}
+
+fn config_param(value: u8) -> Arc<crate::tvm::Cell> {
+    let mut builder = Builder::new();
+    builder.store_byte(value).unwrap();
+    builder.end_cell().unwrap()
+}
+
+fn fixtures() -> (LiteServerFixtures, Address) {
+    let wallet = wallet();
+    let address = wallet.address().unwrap();
+    let mut fixtures = LiteServerFixtures::new(block(-1, 3))
+        .with_time(1_700_000_500)
+        .with_account(&address, &active_account(&address, &wallet))
+        .unwrap()
+        .with_block(fixture_block(1, 0, 1_700_000_000))
+        .with_block(fixture_block(2, 100, 1_700_000_100))
+        .with_block(fixture_block(3, 200, 1_700_000_200))
+        .with_config_address([0x55; 32])
+        .with_config_param(1, config_param(1))
+        .unwrap()
+        .with_config_param(34, config_param(34))
+        .unwrap();
+    for (index, transaction) in transaction_chain(address.hash_part).iter().enumerate() {
+        fixtures
+            .insert_transaction(block(0, 10 - index as i32), transaction)
+            .unwrap();
+    }
+    (fixtures, address)
+}
+
+fn wrapped(request: Request) -> WrappedRequest {
+    WrappedRequest {
+        wait_masterchain_seqno: None,
+        request,
+    }
+}
+
+fn lookup(seqno: Option<i32>, lt: Option<u64>, utime: Option<u32>) -> Request {
+    Request::LookupBlock(LookupBlock {
+        mode: (),
+        id: BlockId {
+            workchain: -1,
+            shard: i64::MIN,
+            seqno: seqno.unwrap_or(0),
+        },
+        seqno: seqno.map(|_| ()),
+        lt,
+        utime,
+        with_state_update: None,
+        with_value_flow: None,
+        with_extra: None,
+        with_shard_hashes: None,
+        with_prev_blk_signatures: None,
+    })
+}
+
+fn expect_error(response: Response) -> crate::tl::response::Error {
+    match response {
+        Response::Error(error) => error,
+        other => panic!("expected error response, got {other:?}"),
+    }
+}
+
+#[test]
+fn fixtures_lookup_block_by_seqno_lt_and_utime() {
+    let (mut fixtures, _) = fixtures();
+    let header = |response| match response {
+        Response::BlockHeader(header) => header.id.seqno,
+        other => panic!("unexpected response {other:?}"),
+    };
+
+    assert_eq!(
+        header(fixtures.answer(wrapped(lookup(Some(2), None, None)))),
+        2
+    );
+    assert_eq!(
+        header(fixtures.answer(wrapped(lookup(None, Some(250), None)))),
+        3
+    );
+    assert_eq!(
+        header(fixtures.answer(wrapped(lookup(None, None, Some(1_700_000_150))))),
+        2
+    );
+
+    let missing = expect_error(fixtures.answer(wrapped(lookup(Some(900), None, None))));
+    assert_eq!(missing.code, LITE_SERVER_NOT_READY);
+}
+
+#[test]
+fn fixtures_follow_transaction_chain_and_check_start_hash() {
+    let (mut fixtures, address) = fixtures();
+    let chain = transaction_chain(address.hash_part);
+    let query = |count, hash| {
+        wrapped(Request::GetTransactions(GetTransactions {
+            count,
+            account: address.to_account_id(),
+            lt: 300,
+            hash: Int256(hash),
+        }))
+    };
+    let newest_hash = chain[0].to_cell().unwrap().hash();
+
+    match fixtures.answer(query(10, newest_hash)) {
+        Response::TransactionList(list) => {
+            assert_eq!(list.ids, vec![block(0, 10), block(0, 9)]);
+            let roots = crate::tvm::deserialize_boc_roots(&list.transactions).unwrap();
+            assert_eq!(roots.len(), 2);
+            assert_eq!(roots[1].hash(), chain[1].to_cell().unwrap().hash());
+        }
+        other => panic!("unexpected response {other:?}"),
+    }
+    match fixtures.answer(query(1, newest_hash)) {
+        Response::TransactionList(list) => assert_eq!(list.ids.len(), 1),
+        other => panic!("unexpected response {other:?}"),
+    }
+
+    let mismatch = expect_error(fixtures.answer(query(1, [0; 32])));
+    assert_eq!(mismatch.code, LITE_SERVER_ERROR);
+}
+
+#[test]
+fn fixtures_reject_unknown_queries_and_future_seqnos() {
+    let (mut fixtures, _) = fixtures();
+
+    let unsupported = expect_error(fixtures.answer(wrapped(Request::GetVersion)));
+    assert_eq!(unsupported.code, LITE_SERVER_PROTOVIOLATION);
+
+    let waiting = expect_error(fixtures.answer(WrappedRequest {
+        wait_masterchain_seqno: Some(WaitMasterchainSeqno {
+            seqno: 4,
+            timeout_ms: 100,
+        }),
+        request: Request::GetMasterchainInfo,
+    }));
+    assert_eq!(waiting.code, LITE_SERVER_NOT_READY);
+
+    fixtures.set_last_block(block(-1, 4));
+    let response = fixtures.answer(WrappedRequest {
+        wait_masterchain_seqno: Some(WaitMasterchainSeqno {
+            seqno: 4,
+            timeout_ms: 100,
+        }),
+        request: Request::GetMasterchainInfo,
+    });
+    assert!(matches!(response, Response::MasterchainInfo(info) if info.last.seqno == 4));
+}
+
+#[test]
+fn fixtures_return_only_requested_config_params() {
+    let (mut fixtures, _) = fixtures();
+    let response = fixtures.answer(wrapped(Request::GetConfigParams(GetConfigParams {
+        mode: (),
+        id: block(-1, 3),
+        param_list: vec![34, 7],
+        with_state_root: None,
+        with_libraries: None,
+        with_state_extra_root: None,
+        with_shard_hashes: None,
+        with_validator_set: None,
+        with_special_smc: None,
+        with_accounts_root: None,
+        with_prev_blocks: None,
+        with_workchain_info: None,
+        with_capabilities: None,
+        extract_from_key_block: None,
+    })));
+    let Response::ConfigInfo(info) = response else {
+        panic!("unexpected response {response:?}");
+    };
+    let config = crate::liteclient::boc::decode_config_params_boc(&info.config_proof)
+        .unwrap()
+        .config;
+    assert_eq!(config.config_addr, [0x55; 32]);
+    assert_eq!(config.raw_param(34).unwrap(), Some(config_param(34)));
+    assert_eq!(config.raw_param(1).unwrap(), None);
+}
+
+#[tokio::test]
+async fn liteclient_queries_local_server_over_adnl_tcp() {
+    let (fixtures, address) = fixtures();
+    let server = LocalLiteServer::start(fixtures).await.unwrap();
+    let mut client = server.connect().await.unwrap();
+
+    let info = client.get_masterchain_info().await.unwrap();
+    assert_eq!(info.last.seqno, 3);
+    assert_eq!(info.last.file_hash, block(-1, 3).file_hash);
+    assert_eq!(client.get_time().await.unwrap(), 1_700_000_500);
+
+    let state = client
+        .get_account_state_typed(address.clone(), None)
+        .await
+        .unwrap();
+    assert_eq!(state.anchor(), &info.last);
+    assert_eq!(state.shard_account.as_ref().unwrap().last_trans_lt, 300);
+    assert_eq!(state.simple().last_transaction_lt, Some(300));
+
+    let seqno = client
+        .run_get_method_typed(
+            4,
+            info.last.clone(),
+            address.clone(),
+            crate::utils::method_name_to_id("seqno"),
+            TvmStack::empty(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(seqno, vec![TvmStackEntry::int(0)]);
+
+    let chain = transaction_chain(address.hash_part);
+    let (transactions, ids) = client
+        .raw_get_transactions(
+            16,
+            address.to_account_id(),
+            300,
+            Int256(chain[0].to_cell().unwrap().hash()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(transactions, chain);
+    assert_eq!(ids.len(), 2);
+
+    let header = client
+        .lookup_block(
+            (),
+            BlockId {
+                workchain: -1,
+                shard: i64::MIN,
+                seqno: 2,
+            },
+            Some(()),
+            None,
+            None,
+            false,
+            false,
+            false,
+            false,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(header.id, block(-1, 2));
+
+    let config = client
+        .get_config_params_typed(
+            info.last,
+            vec![1],
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+        )
+        .await
+        .unwrap();
+    let params = config.config_proof.unwrap().config;
+    assert_eq!(params.raw_param(1).unwrap(), Some(config_param(1)));
+
+    let message = serialize_boc(&config_param(9), true).unwrap();
+    assert_eq!(client.send_message(message.clone()).await.unwrap(), 1);
+    assert_eq!(server.sent_messages(), vec![message]);
+
+    let error = client.send_message(vec![1, 2, 3]).await.unwrap_err();
+    assert!(matches!(
+        error,
+        crate::liteclient::types::LiteError::ServerError(ref error) if error.code == LITE_SERVER_ERROR
+    ));
+}
+
+#[tokio::test]
+async fn wallet_helpers_and_balancer_use_local_server() {
+    let (fixtures, _) = fixtures();
+    let server = LocalLiteServer::start(fixtures).await.unwrap();
+    let wallet = wallet();
+
+    let mut client = server.connect().await.unwrap();
+    assert_eq!(wallet.seqno(&mut client).await.unwrap(), 0);
+    assert_eq!(
+        wallet.public_key_onchain(&mut client).await.unwrap(),
+        [0x11; 32]
+    );
+
+    let peers = vec![
+        server.connect().await.unwrap(),
+        server.connect().await.unwrap(),
+    ];
+    let mut balancer = LiteBalancer::new(peers, Duration::from_secs(5));
+    balancer.start_up().await.unwrap();
+    assert_eq!(balancer.archival_peers_num().await, 0);
+    let info = balancer.get_masterchain_info().await.unwrap();
+    assert_eq!(info.last.seqno, 3);
+    assert_eq!(wallet.seqno(&mut balancer).await.unwrap(), 0);
+
+    server.update(|fixtures| fixtures.set_last_block(block(-1, 4)));
+    let info = balancer.get_masterchain_info().await.unwrap();
+    assert_eq!(info.last.seqno, 4);
+    balancer.close_all().await.unwrap();
+}
+
+#[tokio::test]
+async fn unknown_account_state_is_empty_and_get_methods_fail() {
+    let (fixtures, _) = fixtures();
+    let server = LocalLiteServer::start(fixtures).await.unwrap();
+    let mut client = server.connect().await.unwrap();
+    let unknown = Address::new(0, [0x77; 32]);
+
+    let error = client
+        .get_account_state(block(-1, 3), unknown.to_account_id())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, crate::liteclient::types::LiteError::ServerError(ref error) if error.code == LITE_SERVER_NOT_READY)
+    );
+
+    let state = client
+        .get_account_state_typed(unknown.clone(), None)
+        .await
+        .unwrap();
+    assert!(state.raw.state.is_empty());
+    assert!(state.shard_account.is_none());
+
+    let error = client
+        .run_get_method(4, block(-1, 3), unknown, 85143, TvmStack::empty())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("inactive account"));
+}
@@ -0,0 +1,209 @@
+//! Gap-free feed of new masterchain and shard blocks.
+//!
+//! [`BlockStream`] walks the masterchain one block at a time over a
+//! [`LiteBalancer`]. Each masterchain block comes with the shard blocks it
+//! commits for the first time: the stream follows `prev` links back from the
+//! new shard tops until it reaches the tops of the previous masterchain block,
+//! so shard blocks between two masterchain blocks and blocks around shard
+//! splits and merges are all reported.
+
+use std::collections::HashSet;
+
+use futures::Stream;
+
+use crate::liteclient::balancer::{BalancerError, LiteBalancer};
+use crate::liteclient::types::LiteError;
+use crate::tl::TlError;
+use crate::tl::common::{BlockIdExt, Int256, TransactionId3};
+use crate::tlb::Transaction;
+
+type Result<T> = std::result::Result<T, BalancerError>;
+
+const MASTERCHAIN: i32 = -1;
+
+/// Transactions requested per `listBlockTransactionsExt` page.
+const TRANSACTIONS_PAGE: u32 = 256;
+
+/// A masterchain block and the shard blocks it commits for the first time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewBlocks {
+    pub masterchain: BlockIdExt,
+    /// Shard blocks in an order where every block follows the blocks it was
+    /// built on.
+    pub shard_blocks: Vec<BlockIdExt>,
+}
+
+/// Masterchain-driven block subscriber.
+///
+/// Store [`BlockStream::next_seqno`] after handling a batch to resume from
+/// the same point later. A failed [`BlockStream::next_blocks`] leaves the
+/// stream where it was, so calling it again retries the same block.
+pub struct BlockStream {
+    balancer: LiteBalancer,
+    next_seqno: u32,
+    /// Shard tops of masterchain block `next_seqno - 1`, once fetched.
+    shard_tops: Option<Vec<BlockIdExt>>,
+}
+
+impl BlockStream {
+    /// Streams from masterchain block `seqno` on.
+    pub fn new(balancer: LiteBalancer, seqno: u32) -> Self {
+        Self {
+            balancer,
+            next_seqno: seqno,
+            shard_tops: None,
+        }
+    }
+
+    /// Streams from the last masterchain block the balancer knows about.
+    pub async fn from_last(mut balancer: LiteBalancer) -> Result<Self> {
+        let last = balancer.get_masterchain_info().await?.last;
+        Ok(Self::new(balancer, last.seqno as u32))
+    }
+
+    /// Seqno of the masterchain block the next batch is for.
+    pub fn next_seqno(&self) -> u32 {
+        self.next_seqno
+    }
+
+    pub fn balancer(&mut self) -> &mut LiteBalancer {
+        &mut self.balancer
+    }
+
+    pub fn into_balancer(self) -> LiteBalancer {
+        self.balancer
+    }
+
+    /// Waits for the next masterchain block and returns it with its new
+    /// shard blocks.
+    ///
+    /// The liteserver waits for a masterchain block that does not exist yet
+    /// only for a limited time; a wait that runs out is returned as an error.
+    pub async fn next_blocks(&mut self) -> Result<NewBlocks> {
+        if self.shard_tops.is_none() {
+            let tops = match self.next_seqno.checked_sub(1) {
+                Some(seqno) => {
+                    let block = self.masterchain_block(seqno).await?;
+                    self.balancer.get_all_shards_info_typed(block).await?
+                }
+                None => Vec::new(),
+            };
+            self.shard_tops = Some(tops);
+        }
+
+        let masterchain = self.masterchain_block(self.next_seqno).await?;
+        let tops = self
+            .balancer
+            .get_all_shards_info_typed(masterchain.clone())
+            .await?;
+        let known = self.shard_tops.as_deref().unwrap_or_default();
+        let shard_blocks = new_shard_blocks(&mut self.balancer, known, &tops).await?;
+
+        self.shard_tops = Some(tops);
+        self.next_seqno += 1;
+        Ok(NewBlocks {
+            masterchain,
+            shard_blocks,
+        })
+    }
+
+    /// All transactions of `block`, in the order the liteserver lists them.
+    pub async fn block_transactions(&mut self, block: &BlockIdExt) -> Result<Vec<Transaction>> {
+        let mut transactions: Vec<Transaction> = Vec::new();
+        loop {
+            let after = transactions.last().map(|last| TransactionId3 {
+                account: Int256(last.account_addr),
+                lt: last.lt,
+            });
+            let page = self
+                .balancer
+                .list_block_transactions_ext_decoded(
+                    block.clone(),
+                    TRANSACTIONS_PAGE,
+                    after,
+                    false,
+                    false,
+                )
+                .await?;
+            let incomplete = page.raw.incomplete && !page.transactions.is_empty();
+            transactions.extend(page.transactions);
+            if !incomplete {
+                return Ok(transactions);
+            }
+        }
+    }
+
+    /// Turns the subscriber into an endless `Stream` of batches.
+    ///
+    /// Errors are yielded and the following item retries the same block, so
+    /// consumers should back off or stop on repeated errors.
+    pub fn into_stream(self) -> impl Stream<Item = Result<NewBlocks>> {
+        futures::stream::unfold(self, |mut stream| async move {
+            let item = stream.next_blocks().await;
+            Some((item, stream))
+        })
+    }
+
+    async fn masterchain_block(&mut self, seqno: u32) -> Result<BlockIdExt> {
+        let id = self.balancer.wait_masterchain_block(seqno).await?.id;
+        if id.workchain != MASTERCHAIN || id.seqno as u32 != seqno {
+            return Err(parse_error(format!(
+                "asked for masterchain block {seqno}, got {id}"
+            )));
+        }
+        Ok(id)
+    }
+}
+
+/// Blocks reachable from `tops` through `prev` links that `known` tops do not
+/// cover, parents first.
+async fn new_shard_blocks(
+    balancer: &mut LiteBalancer,
+    known: &[BlockIdExt],
+    tops: &[BlockIdExt],
+) -> Result<Vec<BlockIdExt>> {
+    let mut blocks = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending: Vec<(BlockIdExt, bool)> =
+        tops.iter().rev().map(|top| (top.clone(), false)).collect();
+    while let Some((block, expanded)) = pending.pop() {
+        if expanded {
+            blocks.push(block);
+            continue;
+        }
+        if block.seqno == 0 || is_committed(known, &block) || !visited.insert(block.clone()) {
+            continue;
+        }
+        let prev = balancer
+            .raw_get_block_header(block.clone(), false, false, false, false, false)
+            .await?
+            .prev_blocks()
+            .map_err(|error| parse_error(error.to_string()))?;
+        pending.push((block, true));
+        pending.extend(prev.into_iter().rev().map(|prev| (prev, false)));
+    }
+    Ok(blocks)
+}
+
+/// Seqnos grow along every shard lineage, so a block is old once a known top
+/// of an overlapping shard has reached its seqno.
+fn is_committed(known: &[BlockIdExt], block: &BlockIdExt) -> bool {
+    known.iter().any(|top| {
+        top.workchain == block.workchain
+            && shards_intersect(top.shard as u64, block.shard as u64)
+            && top.seqno >= block.seqno
+    })
+}
+
+fn shards_intersect(left: u64, right: u64) -> bool {
+    let low_bit = (left & left.wrapping_neg()).max(right & right.wrapping_neg());
+    let prefix_mask = !(low_bit << 1).wrapping_sub(1);
+    left & prefix_mask == right & prefix_mask
+}
+
+fn parse_error(message: String) -> BalancerError {
+    BalancerError::LiteError(LiteError::TlError(TlError::ParseError(message)))
+}
+
+#[cfg(test)]
+mod tests;
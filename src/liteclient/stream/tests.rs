@@ -0,0 +1,326 @@
+use super::*;
+use crate::liteclient::client::LiteClient;
+use crate::tl::common::ZeroStateIdExt;
+use crate::tl::request::{RawWrappedRequest, Request};
+use crate::tl::response::{AllShardsInfo, BlockHeader, Error, MasterchainInfo, Response};
+use crate::tlb::{ExtBlkRef, TlbSerialize};
+use crate::tvm::{BitKey, Builder, Cell, HashmapE, serialize_boc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tower::service_fn;
+
+const BLOCK_TAG: u32 = 0x11ef55aa;
+const BLOCK_INFO_TAG: u32 = 0x9bc7a987;
+const MERKLE_PROOF_TAG: u8 = 0x03;
+const FULL_SHARD: u64 = 1 << 63;
+const LEFT_SHARD: u64 = 1 << 62;
+const RIGHT_SHARD: u64 = 3 << 62;
+
+#[derive(Clone, Copy, Default)]
+struct Links {
+    after_merge: bool,
+    after_split: bool,
+}
+
+#[derive(Default)]
+struct Chain {
+    /// Header proof BoC by block id.
+    headers: HashMap<BlockIdExt, Vec<u8>>,
+    /// Masterchain block id and shard tops by seqno.
+    masterchain: HashMap<u32, (BlockIdExt, Vec<BlockIdExt>)>,
+    waits: Vec<Option<u32>>,
+}
+
+impl Chain {
+    /// Seals a block on top of `prev` and returns its id.
+    fn block(
+        &mut self,
+        workchain: i32,
+        shard: u64,
+        seqno: u32,
+        prev: &[BlockIdExt],
+        links: Links,
+    ) -> BlockIdExt {
+        let not_master = workchain != MASTERCHAIN;
+        let mut info = Builder::new();
+        info.store_u32(BLOCK_INFO_TAG).unwrap();
+        info.store_u32(0).unwrap(); // version
+        info.store_bit(not_master).unwrap();
+        info.store_bit(links.after_merge).unwrap();
+        info.store_bit(false).unwrap(); // before_split
+        info.store_bit(links.after_split).unwrap();
+        // want_split, want_merge, key_block, vert_seqno_incr
+        info.store_uint_custom::<u8>(0, 4).unwrap();
+        info.store_u8(0).unwrap(); // flags
+        info.store_u32(seqno).unwrap();
+        info.store_u32(0).unwrap(); // vert_seq_no
+        if not_master {
+            info.store_ref(ext_blk_ref(&masterchain_id(0))).unwrap();
+        }
+        let prev_ref = match prev {
+            [prev] => ext_blk_ref(prev),
+            [left, right] => {
+                let mut both = Builder::new();
+                both.store_ref(ext_blk_ref(left)).unwrap();
+                both.store_ref(ext_blk_ref(right)).unwrap();
+                both.end_cell().unwrap()
+            }
+            _ => Builder::new().end_cell().unwrap(),
+        };
+        info.store_ref(prev_ref).unwrap();
+
+        let empty = Builder::new().end_cell().unwrap();
+        let mut block = Builder::new();
+        block.store_u32(BLOCK_TAG).unwrap();
+        block.store_int(-239, 32).unwrap();
+        block.store_ref(info.end_cell().unwrap()).unwrap();
+        for _ in 0..3 {
+            block.store_ref(empty.clone()).unwrap();
+        }
+        let block = block.end_cell().unwrap();
+
+        let id = BlockIdExt {
+            workchain,
+            shard: shard as i64,
+            seqno: seqno as i32,
+            root_hash: Int256(block.hash()),
+            file_hash: Int256([seqno as u8; 32]),
+        };
+        let proof = serialize_boc(&merkle_proof(&block), false).unwrap();
+        self.headers.insert(id.clone(), proof);
+        id
+    }
+
+    fn masterchain(&mut self, seqno: u32, tops: Vec<BlockIdExt>) -> BlockIdExt {
+        let id = self.block(MASTERCHAIN, FULL_SHARD, seqno, &[], Links::default());
+        self.masterchain.insert(seqno, (id.clone(), tops));
+        id
+    }
+
+    fn answer(&mut self, request: RawWrappedRequest) -> Response {
+        self.waits
+            .push(request.wait_masterchain_seqno.map(|wait| wait.seqno));
+        let request: Request = tl_proto::deserialize(&request.request).unwrap();
+        let found = match request {
+            Request::GetMasterchainInfo => {
+                let last = self.masterchain.keys().max().copied().unwrap_or(0);
+                self.masterchain.get(&last).map(|(id, _)| {
+                    Response::MasterchainInfo(MasterchainInfo {
+                        last: id.clone(),
+                        state_root_hash: Int256([0; 32]),
+                        init: ZeroStateIdExt {
+                            workchain: MASTERCHAIN,
+                            root_hash: Int256([0; 32]),
+                            file_hash: Int256([0; 32]),
+                        },
+                    })
+                })
+            }
+            Request::LookupBlock(query) => self
+                .masterchain
+                .get(&(query.id.seqno as u32))
+                .map(|(id, _)| header(id.clone(), Vec::new())),
+            Request::GetAllShardsInfo(query) => self
+                .masterchain
+                .get(&(query.id.seqno as u32))
+                .filter(|(id, _)| *id == query.id)
+                .map(|(id, tops)| {
+                    Response::AllShardsInfo(AllShardsInfo {
+                        id: id.clone(),
+                        proof: Vec::new(),
+                        data: serialize_boc(&shard_hashes(tops), false).unwrap(),
+                    })
+                }),
+            Request::GetBlockHeader(query) => self
+                .headers
+                .get(&query.id)
+                .map(|proof| header(query.id.clone(), proof.clone())),
+            _ => None,
+        };
+        found.unwrap_or_else(|| {
+            Response::Error(Error {
+                code: 651,
+                message: "block not found".into(),
+            })
+        })
+    }
+}
+
+fn header(id: BlockIdExt, header_proof: Vec<u8>) -> Response {
+    Response::BlockHeader(BlockHeader {
+        id,
+        mode: (),
+        with_state_update: None,
+        with_value_flow: None,
+        with_extra: None,
+        with_shard_hashes: None,
+        with_prev_blk_signatures: None,
+        header_proof,
+    })
+}
+
+fn masterchain_id(seqno: i32) -> BlockIdExt {
+    BlockIdExt {
+        workchain: MASTERCHAIN,
+        shard: FULL_SHARD as i64,
+        seqno,
+        root_hash: Int256([0; 32]),
+        file_hash: Int256([0; 32]),
+    }
+}
+
+fn ext_blk_ref(id: &BlockIdExt) -> Arc<Cell> {
+    ExtBlkRef {
+        end_lt: 0,
+        seq_no: id.seqno as u32,
+        root_hash: id.root_hash.0,
+        file_hash: id.file_hash.0,
+    }
+    .to_cell()
+    .unwrap()
+}
+
+fn merkle_proof(root: &Arc<Cell>) -> Arc<Cell> {
+    let mut data = vec![MERKLE_PROOF_TAG];
+    data.extend_from_slice(&root.hash_at_level(0));
+    data.extend_from_slice(&root.depth_at_level(0).to_be_bytes());
+    let bit_len = data.len() * 8;
+    Arc::new(Cell::with_exotic_data(data, bit_len, vec![root.clone()]).unwrap())
+}
+
+/// `ShardHashes` holding `tops` of workchain 0, which have to be either the
+/// full shard or its two halves.
+fn shard_hashes(tops: &[BlockIdExt]) -> Arc<Cell> {
+    let leaf = |id: &BlockIdExt| {
+        let mut descr = Builder::new();
+        descr.store_bit(false).unwrap(); // bt_leaf
+        descr.store_uint_custom::<u8>(0xb, 4).unwrap();
+        descr.store_u32(id.seqno as u32).unwrap();
+        descr.store_u32(0).unwrap(); // reg_mc_seqno
+        descr.store_u64(0).unwrap(); // start_lt
+        descr.store_u64(0).unwrap(); // end_lt
+        descr.store_bytes(&id.root_hash.0).unwrap();
+        descr.store_bytes(&id.file_hash.0).unwrap();
+        descr.end_cell().unwrap()
+    };
+    let tree = match tops {
+        [top] => leaf(top),
+        [left, right] => {
+            let mut fork = Builder::new();
+            fork.store_bit(true).unwrap();
+            fork.store_ref(leaf(left)).unwrap();
+            fork.store_ref(leaf(right)).unwrap();
+            fork.end_cell().unwrap()
+        }
+        _ => unreachable!("test chains have at most two shards"),
+    };
+    let mut workchains = HashmapE::new(32);
+    workchains
+        .insert_bit_key(BitKey::from_u64(0, 32).unwrap(), tree)
+        .unwrap();
+    let mut root = Builder::new();
+    root.store_hashmap_e_with(&workchains, |builder, tree| {
+        builder.store_ref(tree.clone())?;
+        Ok(())
+    })
+    .unwrap();
+    root.end_cell().unwrap()
+}
+
+async fn balancer(chain: Arc<Mutex<Chain>>) -> LiteBalancer {
+    let client = LiteClient::from_service(service_fn(move |request: RawWrappedRequest| {
+        let response = chain.lock().unwrap().answer(request);
+        async move { Ok::<_, LiteError>(tl_proto::serialize(response)) }
+    }));
+    let mut balancer = LiteBalancer::new(vec![client], Duration::from_secs(1));
+    balancer.start_up().await.unwrap();
+    balancer
+}
+
+/// Basechain that advances twice between masterchain blocks 1 and 2, splits
+/// before masterchain block 3, and merges again before block 4.
+fn split_and_merge_chain() -> (Chain, Vec<BlockIdExt>) {
+    let mut chain = Chain::default();
+    let one = Links::default();
+    let b10 = chain.block(0, FULL_SHARD, 10, &[], one);
+    let b11 = chain.block(0, FULL_SHARD, 11, std::slice::from_ref(&b10), one);
+    let b12 = chain.block(0, FULL_SHARD, 12, std::slice::from_ref(&b11), one);
+    let split = Links {
+        after_split: true,
+        ..one
+    };
+    let left = chain.block(0, LEFT_SHARD, 13, std::slice::from_ref(&b12), split);
+    let right = chain.block(0, RIGHT_SHARD, 13, std::slice::from_ref(&b12), split);
+    let right14 = chain.block(0, RIGHT_SHARD, 14, std::slice::from_ref(&right), one);
+    let merge = Links {
+        after_merge: true,
+        ..one
+    };
+    let merged = chain.block(0, FULL_SHARD, 15, &[left.clone(), right14.clone()], merge);
+
+    chain.masterchain(1, vec![b10.clone()]);
+    chain.masterchain(2, vec![b12.clone()]);
+    chain.masterchain(3, vec![left.clone(), right.clone()]);
+    chain.masterchain(4, vec![merged.clone()]);
+    (chain, vec![b10, b11, b12, left, right, right14, merged])
+}
+
+#[test]
+fn shard_intersection_follows_prefixes() {
+    assert!(shards_intersect(FULL_SHARD, LEFT_SHARD));
+    assert!(shards_intersect(RIGHT_SHARD, FULL_SHARD));
+    assert!(shards_intersect(LEFT_SHARD, 1 << 61));
+    assert!(!shards_intersect(LEFT_SHARD, RIGHT_SHARD));
+    assert!(!shards_intersect(RIGHT_SHARD, 1 << 61));
+}
+
+#[tokio::test]
+async fn stream_follows_prev_links_across_split_and_merge() {
+    let (chain, blocks) = split_and_merge_chain();
+    let [_, b11, b12, left, right, right14, merged] = <[BlockIdExt; 7]>::try_from(blocks).unwrap();
+    let chain = Arc::new(Mutex::new(chain));
+    let mut stream = BlockStream::new(balancer(chain.clone()).await, 2);
+
+    let batch = stream.next_blocks().await.unwrap();
+    assert_eq!(batch.masterchain.seqno, 2);
+    assert_eq!(batch.shard_blocks, vec![b11, b12]);
+
+    let batch = stream.next_blocks().await.unwrap();
+    assert_eq!(batch.masterchain.seqno, 3);
+    assert_eq!(batch.shard_blocks, vec![left, right]);
+
+    let batch = stream.next_blocks().await.unwrap();
+    assert_eq!(batch.masterchain.seqno, 4);
+    assert_eq!(batch.shard_blocks, vec![right14, merged]);
+    assert_eq!(stream.next_seqno(), 5);
+
+    let waits = chain.lock().unwrap().waits.clone();
+    for seqno in 1..=4 {
+        assert!(waits.contains(&Some(seqno)), "no wait for seqno {seqno}");
+    }
+}
+
+#[tokio::test]
+async fn failed_wait_leaves_the_stream_in_place() {
+    let (chain, _) = split_and_merge_chain();
+    let chain = Arc::new(Mutex::new(chain));
+    let mut stream = BlockStream::from_last(balancer(chain.clone()).await)
+        .await
+        .unwrap();
+    assert_eq!(stream.next_seqno(), 4);
+    stream.next_blocks().await.unwrap();
+
+    assert!(stream.next_blocks().await.is_err());
+    assert_eq!(stream.next_seqno(), 5);
+
+    let merged = chain.lock().unwrap().masterchain[&4].1.clone();
+    let b16 = chain
+        .lock()
+        .unwrap()
+        .block(0, FULL_SHARD, 16, &merged, Links::default());
+    chain.lock().unwrap().masterchain(5, vec![b16.clone()]);
+    let batch = stream.next_blocks().await.unwrap();
+    assert_eq!(batch.masterchain.seqno, 5);
+    assert_eq!(batch.shard_blocks, vec![b16]);
+}
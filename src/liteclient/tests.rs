@@ -789,7 +789,7 @@ async fn typed_boc_helpers_return_decode_errors_for_malformed_payloads() {
 }
 
 #[tokio::test]
-async fn get_account_state_typed_extracts_simple_account() {
+async fn get_account_state_typed_rejects_unproven_state() {
     use crate::tlb::{Account, TlbSerialize};
     use crate::tvm::{Address, serialize_boc};
 
@@ -806,14 +806,17 @@ async fn get_account_state_typed_extracts_simple_account() {
         },
     ));
 
-    let decoded = client
-        .get_account_state_typed(Address::new(0, [0; 32]), Some(test_block_id()))
+    let error = client
+        .get_account_state_typed(Address::new(-1, [0; 32]), Some(test_block_id()))
         .await
-        .unwrap();
-    let simple = decoded.simple();
-    assert_eq!(simple.state, super::boc::SimpleAccountState::None);
-    assert_eq!(simple.last_transaction_lt, None);
-    assert_eq!(simple.last_transaction_hash, None);
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        LiteError::ProofError(super::proof::ProofError::Decode {
+            what: "account state proof",
+            ..
+        })
+    ));
 }
 
 #[tokio::test]
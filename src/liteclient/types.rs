@@ -21,6 +21,8 @@ pub enum LiteError {
     UnexpectedMessage,
     #[error("ADNL error: {0}")]
     AdnlError(#[from] AdnlError),
+    #[error("Proof verification failed: {0}")]
+    ProofError(#[from] crate::liteclient::proof::ProofError),
     #[error("Unknown error: {0}")]
     UnknownError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
@@ -0,0 +1,35 @@
+//! Pending external messages seen on shard overlays.
+//!
+//! Nodes spread inbound external messages as `tonNode.externalMessageBroadcast`
+//! broadcasts in the public overlay of the destination shard.
+//! `MempoolScanner` joins those overlays through an `OverlayManager` and turns
+//! the broadcasts into a deduplicated, filtered stream of [`PendingMessage`]s.
+//!
+//! A pending message is only an observation: it may never be included in a
+//! block, and nothing here checks that it was.
+
+mod scanner;
+mod types;
+
+#[cfg(test)]
+mod tests;
+
+use thiserror::Error;
+
+use crate::tlb::TlbError;
+
+pub use scanner::{
+    BASECHAIN_SHARD, DEFAULT_MEMPOOL_BUFFER, MASTERCHAIN_SHARD, MempoolFilter, MempoolScanner,
+    PendingMessage, PendingMessages, parse_external_message,
+};
+pub use types::{ExternalMessage, ExternalMessageBroadcast};
+
+#[derive(Debug, Error)]
+pub enum MempoolError {
+    #[error("invalid external message broadcast: {0}")]
+    InvalidBroadcast(#[from] tl_proto::TlError),
+    #[error("invalid external message: {0}")]
+    InvalidMessage(#[from] TlbError),
+    #[error("message is not an inbound external message")]
+    NotExternalIn,
+}
@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::Stream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::adnl::AdnlAddress;
+use crate::overlay::{OverlayError, OverlayIdFull, OverlayManager, ReceivedBroadcast};
+use crate::tlb::message::{CommonMsgInfo, Message, MsgAddressInt};
+use crate::tlb::{Either, TlbDeserialize, TlbError};
+use crate::tvm::{Address, Slice, deserialize_boc};
+
+use super::MempoolError;
+use super::types::ExternalMessageBroadcast;
+
+/// Shard of the basechain while it is not split.
+pub const BASECHAIN_SHARD: (i32, u64) = (0, 1 << 63);
+pub const MASTERCHAIN_SHARD: (i32, u64) = (-1, 1 << 63);
+/// Pending messages queued for the application before new ones are dropped.
+pub const DEFAULT_MEMPOOL_BUFFER: usize = 1024;
+/// Message hashes are remembered this long to drop repeats from other
+/// senders and overlays.
+const SEEN_TTL: Duration = Duration::from_secs(600);
+const MAX_SEEN_MESSAGES: usize = 65_536;
+
+/// An inbound external message observed before inclusion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingMessage {
+    /// Representation hash of the message cell.
+    pub hash: [u8; 32],
+    /// BoC as broadcast.
+    pub boc: Vec<u8>,
+    pub message: Message,
+    /// When the scanner first saw the message.
+    pub first_seen: SystemTime,
+    /// Overlay member that delivered the broadcast.
+    pub peer: AdnlAddress,
+    /// Workchain and shard of the overlay the message arrived on.
+    pub shard: (i32, u64),
+}
+
+/// Destination and op code filter for pending messages.
+///
+/// Empty sets match everything; otherwise a message has to match both sets.
+#[derive(Debug, Clone, Default)]
+pub struct MempoolFilter {
+    destinations: HashSet<(i8, [u8; 32])>,
+    op_codes: HashSet<u32>,
+}
+
+impl MempoolFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps messages to `address`; its bounceable and testnet flags are
+    /// ignored.
+    pub fn with_destination(mut self, address: &Address) -> Self {
+        self.destinations
+            .insert((address.workchain, address.hash_part));
+        self
+    }
+
+    /// Keeps messages whose body starts with `op_code`.
+    ///
+    /// Wallets before v5 start external bodies with the signature, so their
+    /// messages do not have a meaningful op code.
+    pub fn with_op_code(mut self, op_code: u32) -> Self {
+        self.op_codes.insert(op_code);
+        self
+    }
+
+    pub fn matches(&self, message: &Message) -> bool {
+        if !self.destinations.is_empty() {
+            let destination = match &message.info {
+                CommonMsgInfo::ExternalIn {
+                    dest: MsgAddressInt::Std { address, .. },
+                    ..
+                } => (address.workchain, address.hash_part),
+                _ => return false,
+            };
+            if !self.destinations.contains(&destination) {
+                return false;
+            }
+        }
+        if !self.op_codes.is_empty() {
+            let body = match &message.body {
+                Either::Left(cell) | Either::Right(cell) => cell.clone(),
+            };
+            let mut body = Slice::new(body);
+            match body.load_u32() {
+                Ok(op_code) if self.op_codes.contains(&op_code) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Parses the BoC of an inbound external message.
+pub fn parse_external_message(boc: &[u8]) -> Result<Message, MempoolError> {
+    parse_with_hash(boc).map(|(message, _)| message)
+}
+
+fn parse_with_hash(boc: &[u8]) -> Result<(Message, [u8; 32]), MempoolError> {
+    let cell = deserialize_boc(boc).map_err(TlbError::from)?;
+    let hash = cell.hash();
+    let message = Message::from_cell(cell)?;
+    if !matches!(message.info, CommonMsgInfo::ExternalIn { .. }) {
+        return Err(MempoolError::NotExternalIn);
+    }
+    Ok((message, hash))
+}
+
+/// Subscribes to external message broadcasts of shard overlays.
+///
+/// The `OverlayManager` has to be the ADNL handler of its node and should
+/// have a DHT client, which [`MempoolScanner::subscribe`] uses to find
+/// overlay members.
+pub struct MempoolScanner {
+    overlays: Arc<OverlayManager>,
+    zero_state_file_hash: [u8; 32],
+    filter: MempoolFilter,
+    buffer: usize,
+}
+
+impl MempoolScanner {
+    /// `zero_state_file_hash` names the network, as `validator.zero_state` of
+    /// the global config does.
+    pub fn new(overlays: Arc<OverlayManager>, zero_state_file_hash: [u8; 32]) -> Self {
+        Self {
+            overlays,
+            zero_state_file_hash,
+            filter: MempoolFilter::default(),
+            buffer: DEFAULT_MEMPOOL_BUFFER,
+        }
+    }
+
+    pub fn with_filter(mut self, filter: MempoolFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets how many pending messages are queued for the application; while
+    /// the queue is full new messages are dropped.
+    pub fn with_buffer(mut self, buffer: usize) -> Self {
+        self.buffer = buffer.max(1);
+        self
+    }
+
+    /// Public overlay of a shard on this network.
+    pub fn overlay_id(&self, (workchain, shard): (i32, u64)) -> OverlayIdFull {
+        OverlayIdFull::for_shard(workchain, shard, self.zero_state_file_hash)
+    }
+
+    /// Joins the overlays of `shards`, looks up their members in the DHT, and
+    /// returns the stream of pending messages sent to them.
+    ///
+    /// Joining takes over the broadcast streams of the overlays, so only the
+    /// latest subscription to a shard receives its messages. A failed member
+    /// lookup is not fatal, as members also arrive through peer exchange.
+    pub async fn subscribe(&self, shards: &[(i32, u64)]) -> PendingMessages {
+        let (sender, receiver) = mpsc::channel(self.buffer);
+        let seen = Arc::new(Mutex::new(HashMap::new()));
+        let mut tasks = Vec::with_capacity(shards.len());
+        for &shard in shards {
+            let id = self.overlay_id(shard);
+            let broadcasts = self.overlays.join(&id);
+            match self.overlays.discover_peers(&id.short_id()).await {
+                Ok(_) | Err(OverlayError::NoDht) => {}
+                Err(error) => log::debug!("no overlay members found for shard {shard:?}: {error}"),
+            }
+            tasks.push(tokio::spawn(forward(
+                broadcasts,
+                sender.clone(),
+                self.filter.clone(),
+                seen.clone(),
+                shard,
+            )));
+        }
+        PendingMessages { receiver, tasks }
+    }
+}
+
+/// Stream of pending messages from [`MempoolScanner::subscribe`].
+///
+/// Dropping the stream stops the subscription; the overlays stay joined.
+pub struct PendingMessages {
+    receiver: mpsc::Receiver<PendingMessage>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl PendingMessages {
+    pub async fn recv(&mut self) -> Option<PendingMessage> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for PendingMessages {
+    type Item = PendingMessage;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for PendingMessages {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn forward(
+    mut broadcasts: mpsc::Receiver<ReceivedBroadcast>,
+    sender: mpsc::Sender<PendingMessage>,
+    filter: MempoolFilter,
+    seen: Arc<Mutex<HashMap<[u8; 32], Instant>>>,
+    shard: (i32, u64),
+) {
+    while let Some(broadcast) = broadcasts.recv().await {
+        let pending = match decode(broadcast, shard) {
+            Ok(Some(pending)) => pending,
+            Ok(None) => continue,
+            Err(error) => {
+                log::debug!("dropping external message broadcast: {error}");
+                continue;
+            }
+        };
+        if !filter.matches(&pending.message) || !first_sighting(&seen, pending.hash) {
+            continue;
+        }
+        match sender.try_send(pending) {
+            Ok(()) | Err(mpsc::error::TrySendError::Full(_)) => {}
+            Err(mpsc::error::TrySendError::Closed(_)) => break,
+        }
+    }
+}
+
+/// Decodes an external message broadcast; other broadcasts yield `None`.
+fn decode(
+    broadcast: ReceivedBroadcast,
+    shard: (i32, u64),
+) -> Result<Option<PendingMessage>, MempoolError> {
+    let boc = match tl_proto::deserialize::<ExternalMessageBroadcast>(&broadcast.data) {
+        Ok(external) => external.message.data,
+        Err(tl_proto::TlError::UnknownConstructor) => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let (message, hash) = parse_with_hash(&boc)?;
+    Ok(Some(PendingMessage {
+        hash,
+        boc,
+        message,
+        first_seen: SystemTime::now(),
+        peer: broadcast.peer,
+        shard,
+    }))
+}
+
+fn first_sighting(seen: &Mutex<HashMap<[u8; 32], Instant>>, hash: [u8; 32]) -> bool {
+    let mut seen = seen.lock().unwrap();
+    let now = Instant::now();
+    if seen.len() >= MAX_SEEN_MESSAGES {
+        seen.retain(|_, at| now.duration_since(*at) < SEEN_TTL);
+        if seen.len() >= MAX_SEEN_MESSAGES {
+            seen.clear();
+        }
+    }
+    match seen.get(&hash) {
+        Some(at) if now.duration_since(*at) < SEEN_TTL => false,
+        _ => {
+            seen.insert(hash, now);
+            true
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use super::*;
+use crate::adnl::crypto::KeyPair;
+use crate::adnl::udp::unix_now;
+use crate::adnl::{AdnlAddress, AdnlNode};
+use crate::overlay::{OverlayBroadcast, OverlayManager, OverlayMessage};
+use crate::tlb::message::{CommonMsgInfo, Grams, Message, MsgAddressExt, MsgAddressInt};
+use crate::tlb::{Either, TlbSerialize};
+use crate::tvm::{Address, Builder, serialize_boc};
+use futures::StreamExt;
+use rand::rngs::OsRng;
+use std::sync::Arc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+const ZERO_STATE: [u8; 32] = [5; 32];
+
+fn external(dest: u8, op_code: u32) -> Message {
+    let mut body = Builder::new();
+    body.store_u32(op_code).unwrap();
+    Message {
+        info: CommonMsgInfo::ExternalIn {
+            src: MsgAddressExt::None,
+            dest: MsgAddressInt::std(Address::new(0, [dest; 32])),
+            import_fee: Grams::from(0),
+        },
+        init: None,
+        body: Either::Left(body.build().unwrap()),
+    }
+}
+
+fn boc(message: &Message) -> Vec<u8> {
+    serialize_boc(&message.to_cell().unwrap(), false).unwrap()
+}
+
+fn broadcast_data(message: &Message) -> Vec<u8> {
+    tl_proto::serialize(ExternalMessageBroadcast {
+        message: ExternalMessage { data: boc(message) },
+    })
+}
+
+async fn manager() -> Arc<OverlayManager> {
+    let adnl = AdnlNode::bind("127.0.0.1:0", KeyPair::generate(&mut OsRng))
+        .await
+        .unwrap();
+    let manager = Arc::new(OverlayManager::new(Arc::new(adnl)));
+    manager.adnl().set_handler(manager.clone());
+    manager
+}
+
+#[test]
+fn external_messages_are_parsed_and_filtered() {
+    let message = external(1, 0x7369676e);
+    assert_eq!(parse_external_message(&boc(&message)).unwrap(), message);
+    assert!(matches!(
+        parse_external_message(b"not a boc"),
+        Err(MempoolError::InvalidMessage(_))
+    ));
+    let outbound = Message {
+        info: CommonMsgInfo::ExternalOut {
+            src: MsgAddressInt::std(Address::new(0, [1; 32])),
+            dest: MsgAddressExt::None,
+            created_lt: 1,
+            created_at: 2,
+        },
+        init: None,
+        body: Either::Left(Builder::new().build().unwrap()),
+    };
+    assert!(matches!(
+        parse_external_message(&boc(&outbound)),
+        Err(MempoolError::NotExternalIn)
+    ));
+
+    let mut destination = Address::new(0, [1; 32]);
+    destination.is_bounceable = false;
+    let filter = MempoolFilter::new()
+        .with_destination(&destination)
+        .with_op_code(0x7369676e);
+    assert!(MempoolFilter::new().matches(&message));
+    assert!(filter.matches(&message));
+    assert!(!filter.matches(&external(2, 0x7369676e)));
+    assert!(!filter.matches(&external(1, 0x0f8a7ea5)));
+
+    let mut empty_body = message.clone();
+    empty_body.body = Either::Left(Builder::new().build().unwrap());
+    assert!(!filter.matches(&empty_body));
+    assert!(
+        MempoolFilter::new()
+            .with_destination(&destination)
+            .matches(&empty_body)
+    );
+}
+
+#[test]
+fn broadcast_constructor_id_matches_schema() {
+    let data = broadcast_data(&external(1, 0));
+    assert_eq!(&data[..4], &0x3d1b1867u32.to_le_bytes());
+    let decoded = tl_proto::deserialize::<ExternalMessageBroadcast>(&data).unwrap();
+    assert_eq!(decoded.message.data, boc(&external(1, 0)));
+}
+
+#[tokio::test]
+async fn scanner_streams_new_matching_external_messages() {
+    let sender = manager().await;
+    let receiver = manager().await;
+    let scanner = MempoolScanner::new(receiver.clone(), ZERO_STATE)
+        .with_filter(MempoolFilter::new().with_destination(&Address::new(0, [1; 32])));
+    let mut pending = scanner.subscribe(&[BASECHAIN_SHARD]).await;
+
+    let node = receiver.adnl();
+    let peer: AdnlAddress = sender
+        .adnl()
+        .add_peer(*node.public_key(), node.local_addr().unwrap());
+    let overlay = scanner.overlay_id(BASECHAIN_SHARD).short_id();
+    let message = external(1, 7);
+    let now = unix_now();
+    let broadcasts = [
+        // Not an external message broadcast.
+        b"\x01\x02\x03\x04data".to_vec(),
+        broadcast_data(&external(2, 7)),
+        broadcast_data(&message),
+        // The same message from another source, so the broadcast hash differs.
+        broadcast_data(&message),
+    ];
+    for data in broadcasts {
+        let broadcast = OverlayBroadcast::simple(&KeyPair::generate(&mut OsRng), data, 0, now);
+        let mut packet = tl_proto::serialize(OverlayMessage {
+            overlay: overlay.clone(),
+        });
+        packet.extend_from_slice(&tl_proto::serialize(&broadcast));
+        sender.adnl().send_custom(&peer, packet).await.unwrap();
+    }
+
+    let received = tokio::time::timeout(TIMEOUT, pending.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(received.message, message);
+    assert_eq!(received.hash, message.to_cell().unwrap().hash());
+    assert_eq!(received.boc, boc(&message));
+    assert_eq!(received.peer, *sender.adnl().id());
+    assert_eq!(received.shard, BASECHAIN_SHARD);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), pending.recv())
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,23 @@
+use derivative::Derivative;
+use tl_proto::{TlRead, TlWrite};
+
+use crate::tl::utils::fmt_bytes;
+
+/// tonNode.externalMessage data:bytes = tonNode.ExternalMessage;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct ExternalMessage {
+    /// BoC of the `Message` cell.
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub data: Vec<u8>,
+}
+
+/// tonNode.externalMessageBroadcast message:tonNode.externalMessage = tonNode.Broadcast;
+///
+/// Payload of the overlay broadcasts that spread inbound external messages.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed, id = 0x3d1b1867)]
+pub struct ExternalMessageBroadcast {
+    pub message: ExternalMessage,
+}
@@ -46,9 +46,87 @@ pub struct ConfigLiteServer {
     pub id: ConfigPublicKey,
 }
 
+/// Block id as written in the global config, with base64 hashes.
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigBlockId {
+    pub workchain: i32,
+    pub shard: i64,
+    pub seqno: i32,
+    #[serde_as(as = "serde_with::base64::Base64")]
+    pub root_hash: [u8; 32],
+    #[serde_as(as = "serde_with::base64::Base64")]
+    pub file_hash: [u8; 32],
+}
+
+/// `validator.config.global` section of the global config.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigValidator {
+    pub zero_state: ConfigBlockId,
+    /// Latest key block known when the config was published.
+    #[serde(default)]
+    pub init_block: Option<ConfigBlockId>,
+    #[serde(default)]
+    pub hardforks: Vec<ConfigBlockId>,
+}
+
+/// `adnl.Address` entry of a static node address list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "@type")]
+pub enum ConfigAdnlAddress {
+    #[serde(rename = "adnl.address.udp")]
+    Udp { ip: i32, port: i32 },
+}
+
+/// `adnl.addressList` of a static DHT node.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigAddressList {
+    #[serde(default)]
+    pub addrs: Vec<ConfigAdnlAddress>,
+    #[serde(default)]
+    pub version: i32,
+    #[serde(default)]
+    pub reinit_date: i32,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub expire_at: i32,
+}
+
+/// Signed `dht.node` of the `static_nodes` list.
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigDhtNode {
+    pub id: ConfigPublicKey,
+    pub addr_list: ConfigAddressList,
+    pub version: i32,
+    #[serde_as(as = "serde_with::base64::Base64")]
+    pub signature: Vec<u8>,
+}
+
+/// `dht.nodes` wrapper of the static node list.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ConfigDhtNodes {
+    pub nodes: Vec<ConfigDhtNode>,
+}
+
+/// `dht.config.global` section of the global config.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigDht {
+    /// Replication factor: how many nodes closest to a key are asked.
+    pub k: i32,
+    /// Lookup parallelism.
+    pub a: i32,
+    pub static_nodes: ConfigDhtNodes,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConfigGlobal {
     pub liteservers: Vec<ConfigLiteServer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validator: Option<ConfigValidator>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dht: Option<ConfigDht>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -121,6 +199,27 @@ impl ConfigLiteServer {
     }
 }
 
+#[cfg(feature = "tl")]
+impl From<ConfigBlockId> for crate::tl::common::BlockIdExt {
+    fn from(value: ConfigBlockId) -> Self {
+        Self {
+            workchain: value.workchain,
+            shard: value.shard,
+            seqno: value.seqno,
+            root_hash: crate::tl::common::Int256(value.root_hash),
+            file_hash: crate::tl::common::Int256(value.file_hash),
+        }
+    }
+}
+
+impl ConfigValidator {
+    /// Block a light client should trust first: `init_block`, or the zero
+    /// state when the config has none.
+    pub fn trusted_block(&self) -> &ConfigBlockId {
+        self.init_block.as_ref().unwrap_or(&self.zero_state)
+    }
+}
+
 impl ConfigGlobal {
     pub fn liteserver(&self, index: usize) -> Result<&ConfigLiteServer, ConfigError> {
         self.liteservers
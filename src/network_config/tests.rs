@@ -150,6 +150,97 @@ fn test_config_global_multiple_servers() {
     assert_eq!(config.liteservers[1].port, 8002);
 }
 
+#[test]
+fn test_config_global_validator_init_block() {
+    let json = r#"{
+        "liteservers": [],
+        "validator": {
+            "@type": "validator.config.global",
+            "zero_state": {
+                "workchain": -1,
+                "shard": -9223372036854775808,
+                "seqno": 0,
+                "root_hash": "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=",
+                "file_hash": "AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI="
+            },
+            "init_block": {
+                "workchain": -1,
+                "shard": -9223372036854775808,
+                "seqno": 42,
+                "root_hash": "AwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwM=",
+                "file_hash": "BAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQ="
+            },
+            "hardforks": []
+        }
+    }"#;
+
+    let config: ConfigGlobal = json.parse().unwrap();
+    let validator = config.validator.unwrap();
+
+    assert_eq!(validator.zero_state.seqno, 0);
+    assert_eq!(validator.zero_state.shard, i64::MIN);
+    let trusted = validator.trusted_block();
+    assert_eq!(trusted.seqno, 42);
+    assert_eq!(trusted.root_hash, [3; 32]);
+    assert_eq!(trusted.file_hash, [4; 32]);
+}
+
+#[test]
+fn test_config_global_dht_static_nodes() {
+    let json = r#"{
+        "liteservers": [],
+        "dht": {
+            "@type": "dht.config.global",
+            "k": 6,
+            "a": 3,
+            "static_nodes": {
+                "@type": "dht.nodes",
+                "nodes": [
+                    {
+                        "@type": "dht.node",
+                        "id": {
+                            "@type": "pub.ed25519",
+                            "key": "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE="
+                        },
+                        "addr_list": {
+                            "@type": "adnl.addressList",
+                            "addrs": [
+                                {
+                                    "@type": "adnl.address.udp",
+                                    "ip": -1185526007,
+                                    "port": 22096
+                                }
+                            ],
+                            "version": 0,
+                            "reinit_date": 0,
+                            "priority": 0,
+                            "expire_at": 0
+                        },
+                        "version": -1,
+                        "signature": "AgI="
+                    }
+                ]
+            }
+        }
+    }"#;
+
+    let config: ConfigGlobal = json.parse().unwrap();
+    let dht = config.dht.unwrap();
+
+    assert_eq!((dht.k, dht.a), (6, 3));
+    let node = &dht.static_nodes.nodes[0];
+    assert_eq!(node.id.to_bytes(), [1; 32]);
+    assert_eq!(
+        node.addr_list.addrs,
+        vec![ConfigAdnlAddress::Udp {
+            ip: -1185526007,
+            port: 22096
+        }]
+    );
+    assert_eq!(node.version, -1);
+    assert_eq!(node.signature, vec![2, 2]);
+}
+
 #[test]
 fn test_config_global_liteserver_selection() {
     let config = ConfigGlobal {
@@ -165,6 +256,8 @@ fn test_config_global_liteserver_selection() {
                 id: ConfigPublicKey::Ed25519 { key: [2u8; 32] },
             },
         ],
+        validator: None,
+        dht: None,
     };
 
     assert_eq!(config.first_liteserver().unwrap().port, 8001);
@@ -175,6 +268,8 @@ fn test_config_global_liteserver_selection() {
 fn test_config_global_liteserver_selection_errors() {
     let config = ConfigGlobal {
         liteservers: Vec::new(),
+        validator: None,
+        dht: None,
     };
 
     assert!(matches!(
@@ -278,6 +373,8 @@ fn test_select_liteservers_preserves_indexes_and_filters_first_allowed() {
                 id: ConfigPublicKey::Ed25519 { key: [4u8; 32] },
             },
         ],
+        validator: None,
+        dht: None,
     };
     let blacklist = LiteServerBlacklist::parse_tokens(["0", &hex::encode([2u8; 32])]).unwrap();
 
@@ -325,6 +422,8 @@ fn test_config_global_serialization_roundtrip() {
             port: 443,
             id: ConfigPublicKey::Ed25519 { key: [1u8; 32] },
         }],
+        validator: None,
+        dht: None,
     };
 
     let json = serde_json::to_string(&config).unwrap();
@@ -442,6 +541,8 @@ fn test_config_global_clone() {
             port: 8080,
             id: ConfigPublicKey::Ed25519 { key: [1u8; 32] },
         }],
+        validator: None,
+        dht: None,
     };
 
     let cloned = config.clone();
@@ -1,9 +1,11 @@
-//! TEP-62 NFT metadata helpers.
+//! TEP-62 NFT metadata helpers and clients.
 //!
-//! This module decodes the `get_collection_data()`, `get_nft_data()`, and
-//! `get_nft_content()` stack layouts used by TEP-62 NFT contracts, then maps
-//! full TEP-64 content into NFT-oriented metadata fields. Off-chain JSON
-//! fetching, transfers, royalty helpers, and indexer integration are
+//! This module decodes the `get_collection_data()`, `get_nft_data()`,
+//! `get_nft_content()`, `get_nft_address_by_index()`, and TEP-66
+//! `royalty_params()` stack layouts used by NFT contracts, then maps full
+//! TEP-64 content into NFT-oriented metadata fields. With the `liteclient`
+//! feature, `NftCollection` and `NftItem` bind those helpers to an address and
+//! a provider. Off-chain JSON fetching and indexer integration are
 //! intentionally outside this layer.
 
 mod decode;
@@ -11,10 +13,13 @@ mod metadata;
 mod payload;
 #[cfg(test)]
 mod payload_tests;
+#[cfg(feature = "liteclient")]
 mod provider;
 #[cfg(test)]
 mod tests;
 mod types;
 
 pub use payload::*;
+#[cfg(feature = "liteclient")]
+pub use provider::*;
 pub use types::*;
@@ -63,6 +63,18 @@ impl NftTransferPayload {
     pub fn to_cell(&self) -> Result<Arc<Cell>> {
         TlbSerialize::to_cell(self)
     }
+
+    /// Wraps this body in a wallet message to the NFT item being transferred.
+    ///
+    /// `value` must cover `forward_amount` plus the item's gas and forwarding
+    /// fees; unspent TON returns to `response_destination`.
+    pub fn to_wallet_message(
+        &self,
+        nft_item: Address,
+        value: u64,
+    ) -> Result<crate::wallet::WalletMessage> {
+        Ok(crate::wallet::WalletMessage::internal(nft_item, value).with_body(self.to_cell()?))
+    }
 }
 
 impl TlbSerialize for NftTransferPayload {
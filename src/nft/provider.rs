@@ -1 +1,148 @@
+//! Address-bound NFT collection and item clients.
 
+use super::{
+    NftCollectionData, NftItemData, NftMetadata, NftRoyaltyParams, NftTransferPayload,
+    parse_nft_metadata_cell,
+};
+use crate::contracts::{Contract, ContractError, ContractProvider};
+use crate::tvm::Address;
+use crate::wallet::{WalletMessage, WalletSendError, WalletV5R1};
+use ed25519_dalek::SigningKey;
+use num_bigint::BigUint;
+
+/// TEP-62 NFT collection bound to an address and a provider.
+pub struct NftCollection<'a, P: ContractProvider + ?Sized> {
+    inner: Contract<'a, P>,
+}
+
+impl<'a, P: ContractProvider + ?Sized> NftCollection<'a, P> {
+    pub fn new(provider: &'a mut P, address: Address) -> Self {
+        Self {
+            inner: Contract::new(provider, address),
+        }
+    }
+
+    pub fn address(&self) -> &Address {
+        self.inner.address()
+    }
+
+    /// NFT collection contract, for collection-specific get-methods.
+    pub fn contract(&mut self) -> &mut Contract<'a, P> {
+        &mut self.inner
+    }
+
+    /// Runs `get_collection_data` at the provider's latest masterchain block.
+    pub async fn collection_data(&mut self) -> Result<NftCollectionData, ContractError<P::Error>> {
+        self.inner.nft_collection_data_latest().await
+    }
+
+    /// Runs `get_collection_data` and returns parsed collection metadata.
+    pub async fn metadata(&mut self) -> Result<NftMetadata, ContractError<P::Error>> {
+        self.inner.nft_collection_metadata_latest().await
+    }
+
+    /// Runs `get_nft_address_by_index(index)` and returns the item address.
+    pub async fn nft_address_by_index(
+        &mut self,
+        index: impl Into<BigUint>,
+    ) -> Result<Address, ContractError<P::Error>> {
+        self.inner.nft_address_by_index_latest(index.into()).await
+    }
+
+    /// Runs TEP-66 `royalty_params` at the provider's latest masterchain block.
+    pub async fn royalty_params(&mut self) -> Result<NftRoyaltyParams, ContractError<P::Error>> {
+        self.inner.nft_royalty_params_latest().await
+    }
+
+    /// Runs `get_nft_content(index, individual_content)` for an item of this
+    /// collection, merging the collection common content with the item's
+    /// individual content.
+    pub async fn nft_content(
+        &mut self,
+        item_data: &NftItemData,
+    ) -> Result<NftMetadata, ContractError<P::Error>> {
+        self.inner.nft_full_item_metadata_latest(item_data).await
+    }
+}
+
+/// TEP-62 NFT item bound to an address and a provider.
+pub struct NftItem<'a, P: ContractProvider + ?Sized> {
+    inner: Contract<'a, P>,
+}
+
+impl<'a, P: ContractProvider + ?Sized> NftItem<'a, P> {
+    pub fn new(provider: &'a mut P, address: Address) -> Self {
+        Self {
+            inner: Contract::new(provider, address),
+        }
+    }
+
+    pub fn address(&self) -> &Address {
+        self.inner.address()
+    }
+
+    /// NFT item contract, for item-specific get-methods.
+    pub fn contract(&mut self) -> &mut Contract<'a, P> {
+        &mut self.inner
+    }
+
+    /// Runs `get_nft_data` at the provider's latest masterchain block.
+    pub async fn item_data(&mut self) -> Result<NftItemData, ContractError<P::Error>> {
+        self.inner.nft_item_data_latest().await
+    }
+
+    /// Runs `get_nft_data` and resolves the item's full TEP-64 metadata.
+    ///
+    /// Collection-backed items query the collection's `get_nft_content` with
+    /// the item index and individual content; standalone items parse their
+    /// individual content directly.
+    pub async fn metadata(&mut self) -> Result<NftMetadata, ContractError<P::Error>> {
+        let data = self.item_data().await?;
+        match data.collection_address.clone() {
+            Some(collection) => {
+                self.inner
+                    .at(collection)
+                    .nft_full_item_metadata_latest(&data)
+                    .await
+            }
+            None => parse_nft_metadata_cell(data.individual_content).map_err(ContractError::decode),
+        }
+    }
+
+    /// Builds the wallet message that asks this item to execute `payload`,
+    /// attaching `value` nanotons for fees and forwarding.
+    pub fn transfer_message(
+        &self,
+        value: u64,
+        payload: &NftTransferPayload,
+    ) -> crate::tlb::Result<WalletMessage> {
+        payload.to_wallet_message(self.address().clone(), value)
+    }
+
+    /// Signs a `WalletV5R1` transfer carrying `payload` to this item and
+    /// submits it through the bound provider.
+    ///
+    /// `wallet` must be the current owner of this item. The returned status is
+    /// the opaque `liteServer.SendMsgStatus.status` value, not proof of
+    /// inclusion.
+    pub async fn send_transfer(
+        &mut self,
+        wallet: &WalletV5R1,
+        seqno: u32,
+        valid_until: u32,
+        value: u64,
+        payload: &NftTransferPayload,
+        signing_key: &SigningKey,
+    ) -> Result<u32, WalletSendError<P::Error>> {
+        let message = self
+            .transfer_message(value, payload)
+            .map_err(|error| WalletSendError::Build(error.into()))?;
+        let boc = wallet
+            .build_external_message_boc(seqno, valid_until, vec![message], signing_key, false)
+            .map_err(WalletSendError::Build)?;
+        self.inner
+            .send_external_message_boc(boc)
+            .await
+            .map_err(WalletSendError::Provider)
+    }
+}
@@ -106,6 +106,34 @@ mod tests {
         );
     }
 
+    fn royalty_stack() -> Vec<TvmStackEntry> {
+        vec![
+            TvmStackEntry::int(5),
+            TvmStackEntry::int(100),
+            TvmStackEntry::Slice(address_cell(address(0x44))),
+        ]
+    }
+
+    #[test]
+    fn decodes_royalty_params_stack() {
+        let royalty = decode_nft_royalty_params(royalty_stack()).unwrap();
+
+        assert_eq!((royalty.numerator, royalty.denominator), (5, 100));
+        assert_eq!(royalty.destination, Some(address(0x44)));
+        assert_eq!(royalty.royalty_amount(1_000), Some(50));
+
+        let mut stack = royalty_stack();
+        stack[1] = TvmStackEntry::int(70_000);
+        assert!(matches!(
+            decode_nft_royalty_params(stack),
+            Err(NftMetadataError::InvalidInteger {
+                method: "royalty_params",
+                field: "denominator",
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn decodes_valid_item_data_stack() {
         let data = decode_nft_item_data(item_stack(empty())).unwrap();
@@ -443,6 +471,14 @@ mod tests {
             ) -> Result<TransactionList, Self::Error> {
                 unreachable!("nft metadata helper must not fetch transactions")
             }
+
+            async fn get_config_param(
+                &mut self,
+                _block: BlockIdExt,
+                _id: u32,
+            ) -> Result<Option<Arc<Cell>>, Self::Error> {
+                unreachable!("nft metadata helper must not fetch config params")
+            }
         }
 
         fn block(seqno: i32) -> BlockIdExt {
@@ -611,5 +647,88 @@ mod tests {
                 Err(ContractError::NonZeroExitCode { exit_code: 13 })
             ));
         }
+
+        #[tokio::test]
+        async fn collection_client_resolves_item_address_and_royalty_params() {
+            let collection = address(0xb0);
+            let mut provider = mock_provider(vec![
+                call(
+                    collection.clone(),
+                    "get_nft_address_by_index",
+                    TvmStack::new(vec![TvmStackEntry::int(7)]),
+                    TvmStack::new(vec![TvmStackEntry::Slice(address_cell(address(0xb1)))]),
+                    0,
+                ),
+                call(
+                    collection.clone(),
+                    "royalty_params",
+                    TvmStack::empty(),
+                    TvmStack::new(royalty_stack()),
+                    0,
+                ),
+            ]);
+
+            let mut client = NftCollection::new(&mut provider, collection);
+            assert_eq!(
+                client.nft_address_by_index(7u32).await.unwrap(),
+                address(0xb1)
+            );
+            let royalty = client.royalty_params().await.unwrap();
+            assert_eq!(royalty.numerator, 5);
+            assert_eq!(royalty.destination, Some(address(0x44)));
+            assert!(provider.calls.is_empty());
+        }
+
+        #[tokio::test]
+        async fn item_client_merges_collection_content_for_collection_items() {
+            let item = address(0xb2);
+            let individual_content = offchain_content(b"7.json");
+            let mut provider = mock_provider(vec![
+                call(
+                    item.clone(),
+                    "get_nft_data",
+                    TvmStack::empty(),
+                    TvmStack::new(item_stack(individual_content.clone())),
+                    0,
+                ),
+                call(
+                    address(0x22),
+                    "get_nft_content",
+                    TvmStack::new(vec![
+                        TvmStackEntry::int(7),
+                        TvmStackEntry::Cell(individual_content),
+                    ]),
+                    TvmStack::new(vec![TvmStackEntry::Cell(offchain_content(
+                        b"https://example.test/item/7.json",
+                    ))]),
+                    0,
+                ),
+            ]);
+
+            let metadata = NftItem::new(&mut provider, item).metadata().await.unwrap();
+
+            assert_eq!(
+                metadata.uri.as_deref(),
+                Some("https://example.test/item/7.json")
+            );
+            assert!(provider.calls.is_empty());
+        }
+
+        #[test]
+        fn item_client_wraps_transfer_payload_in_wallet_message() {
+            let item = address(0xb3);
+            let payload = NftTransferPayload::new(1, address(0x55), address(0x66));
+            let mut provider = mock_provider(Vec::new());
+            let client = NftItem::new(&mut provider, item.clone());
+
+            let message = client.transfer_message(50_000_000, &payload).unwrap();
+            assert_eq!(message.destination, item);
+            assert_eq!(message.value, 50_000_000);
+            assert_eq!(
+                super::super::payload::exact_from_cell::<NftTransferPayload>(message.body.unwrap())
+                    .unwrap(),
+                payload
+            );
+        }
     }
 }
@@ -8,6 +8,7 @@ use thiserror::Error;
 pub(super) const COLLECTION_DATA_STACK_LEN: usize = 3;
 pub(super) const NFT_DATA_STACK_LEN: usize = 5;
 pub(super) const NFT_CONTENT_STACK_LEN: usize = 1;
+pub(super) const ROYALTY_PARAMS_STACK_LEN: usize = 3;
 
 /// Typed result of a TEP-62 collection `get_collection_data()` call.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -42,6 +43,25 @@ pub struct NftItemData {
     pub individual_content: Arc<Cell>,
 }
 
+/// Typed result of a TEP-66 `royalty_params()` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NftRoyaltyParams {
+    pub numerator: u16,
+    pub denominator: u16,
+    /// Standard internal royalty destination, or `None` for `addr_none`.
+    pub destination: Option<Address>,
+}
+
+impl NftRoyaltyParams {
+    /// Returns the royalty share of `amount`, or `None` for a zero denominator.
+    pub fn royalty_amount(&self, amount: u64) -> Option<u64> {
+        if self.denominator == 0 {
+            return None;
+        }
+        Some((amount as u128 * self.numerator as u128 / self.denominator as u128) as u64)
+    }
+}
+
 /// NFT metadata fields recognized by common TEP-64 keys.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NftMetadata {
@@ -352,6 +372,41 @@ pub fn decode_nft_full_content_metadata(
     parse_nft_metadata_cell(stack_entry_cell(&stack, 0, METHOD)?)
 }
 
+/// Decodes a successful TEP-66 `royalty_params()` stack.
+pub fn decode_nft_royalty_params(
+    stack: Vec<TvmStackEntry>,
+) -> Result<NftRoyaltyParams, NftMetadataError> {
+    const METHOD: &str = "royalty_params";
+    if stack.len() != ROYALTY_PARAMS_STACK_LEN {
+        return Err(NftMetadataError::StackLength {
+            method: METHOD,
+            actual: stack.len(),
+            expected: ROYALTY_PARAMS_STACK_LEN,
+        });
+    }
+
+    let numerator = parse_u16(stack_entry_int(&stack, 0, METHOD)?, METHOD, "numerator")?;
+    let denominator = parse_u16(stack_entry_int(&stack, 1, METHOD)?, METHOD, "denominator")?;
+    let destination = parse_optional_standard_address(
+        stack_entry_cell_like(&stack, 2, METHOD, "slice/cell")?,
+        METHOD,
+        "destination",
+    )?;
+
+    Ok(NftRoyaltyParams {
+        numerator,
+        denominator,
+        destination,
+    })
+}
+
+/// Decodes a `TvmStack` containing `royalty_params()` output.
+pub fn decode_nft_royalty_params_stack(
+    stack: TvmStack,
+) -> Result<NftRoyaltyParams, NftMetadataError> {
+    decode_nft_royalty_params(stack.entries().to_vec())
+}
+
 /// Parses a TEP-64 full content cell into NFT metadata fields.
 pub fn parse_nft_metadata_cell(cell: Arc<Cell>) -> Result<NftMetadata, NftMetadataError> {
     crate::metadata::parse_tep64_content(cell)
@@ -376,6 +431,18 @@ pub(super) fn parse_non_negative_int(
         })
 }
 
+pub(super) fn parse_u16(
+    value: &BigInt,
+    method: &'static str,
+    field: &'static str,
+) -> Result<u16, NftMetadataError> {
+    u16::try_from(value).map_err(|_| NftMetadataError::InvalidInteger {
+        method,
+        field,
+        reason: "value must fit in uint16".to_string(),
+    })
+}
+
 pub(super) fn parse_optional_standard_address(
     cell: Arc<Cell>,
     method: &'static str,
@@ -507,6 +574,26 @@ impl<'a, P: crate::contracts::ContractProvider + ?Sized> crate::contracts::Contr
         decode_nft_item_data(stack).map_err(crate::contracts::ContractError::decode)
     }
 
+    /// Runs collection `get_nft_address_by_index(index)` at the provider's
+    /// latest masterchain block.
+    pub async fn nft_address_by_index_latest(
+        &mut self,
+        index: BigUint,
+    ) -> Result<Address, crate::contracts::ContractError<P::Error>> {
+        self.run_get_method_by_name_latest_as("get_nft_address_by_index", index)
+            .await
+    }
+
+    /// Runs TEP-66 `royalty_params` at the provider's latest masterchain block.
+    pub async fn nft_royalty_params_latest(
+        &mut self,
+    ) -> Result<NftRoyaltyParams, crate::contracts::ContractError<P::Error>> {
+        let stack = self
+            .run_get_method_by_name_typed_latest("royalty_params", TvmStack::empty())
+            .await?;
+        decode_nft_royalty_params(stack).map_err(crate::contracts::ContractError::decode)
+    }
+
     /// Runs `get_collection_data` and returns parsed collection metadata.
     pub async fn nft_collection_metadata_latest(
         &mut self,
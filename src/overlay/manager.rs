@@ -0,0 +1,598 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use rand::seq::{IteratorRandom, SliceRandom};
+use sha2::{Digest, Sha256};
+use tl_proto::{BoxedConstructor, BoxedWrapper, TlRead};
+use tokio::sync::mpsc;
+
+use crate::adnl::crypto::PublicKey;
+use crate::adnl::crypto::tl::PublicKeyOwned;
+use crate::adnl::udp::{AdnlMessageHandler, AdnlNode, unix_now};
+use crate::adnl::{AdnlAddress, AdnlError};
+use crate::dht::{DhtClient, DhtKey, OverlayNode, OverlayNodes};
+use crate::rldp::{FecDecoder, FecType};
+use crate::tl::common::Int256;
+
+use super::OverlayError;
+use super::types::{
+    BROADCAST_MAX_CLOCK_SKEW, OverlayBroadcast, OverlayGetRandomPeers, OverlayIdFull,
+    OverlayMessage, OverlayQuery, broadcast_hash, fec_broadcast_hash, fec_part_hash,
+    verify_broadcast,
+};
+
+/// Default size of the random peer set kept per overlay.
+pub const DEFAULT_OVERLAY_MAX_PEERS: usize = 20;
+/// Largest `overlay.broadcastFec` payload reassembled.
+pub const MAX_FEC_BROADCAST_BYTES: usize = 16 << 20;
+/// Peers returned in an `overlay.getRandomPeers` answer besides ourselves.
+const RANDOM_PEERS_ANSWER: usize = 10;
+/// Broadcasts queued for the application before new ones are dropped.
+const BROADCAST_QUEUE: usize = 1024;
+/// Broadcast hashes are remembered this long to drop repeats.
+const SEEN_TTL: Duration = Duration::from_secs(120);
+pub(super) const MAX_SEEN_BROADCASTS: usize = 16_384;
+const MAX_PENDING_FEC: usize = 64;
+const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+/// DHT key name under which overlay members are published.
+const OVERLAY_NODES_KEY_NAME: &str = "nodes";
+
+/// A broadcast that passed signature, date, and duplicate checks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedBroadcast {
+    /// Short id of the overlay.
+    pub overlay: Int256,
+    /// Broadcast hash used for deduplication.
+    pub hash: [u8; 32],
+    /// Key that signed the broadcast.
+    pub source: PublicKey,
+    /// Member that delivered the broadcast, which is not always the source.
+    pub peer: AdnlAddress,
+    pub flags: i32,
+    pub date: i32,
+    pub data: Vec<u8>,
+}
+
+/// Member of public overlays on an `AdnlNode`.
+///
+/// The manager handles `overlay.query` and `overlay.message` traffic, so it
+/// has to be installed as the ADNL handler, either directly with
+/// `AdnlNode::set_handler` or behind RLDP with `RldpNode::set_adnl_handler`.
+/// Traffic for overlays that were not joined is ignored.
+///
+/// Each joined overlay keeps at most `max_peers` members whose ADNL address is
+/// known; when the set is full a random member makes room for a new one. The
+/// set is filled by [`OverlayManager::discover_peers`] (DHT `nodes` key),
+/// [`OverlayManager::exchange_peers`] (`overlay.getRandomPeers`), and the
+/// peer lists other members send in their own `overlay.getRandomPeers`.
+///
+/// Broadcasts are not relayed to other members.
+pub struct OverlayManager {
+    adnl: Arc<AdnlNode>,
+    dht: Option<Arc<DhtClient>>,
+    max_peers: usize,
+    timeout: Duration,
+    overlays: Mutex<HashMap<[u8; 32], OverlayState>>,
+}
+
+struct OverlayState {
+    local_node: OverlayNode,
+    peers: HashMap<AdnlAddress, OverlayNode>,
+    seen: HashMap<[u8; 32], Instant>,
+    fec: HashMap<[u8; 32], PendingFec>,
+    sender: mpsc::Sender<ReceivedBroadcast>,
+}
+
+/// An `overlay.broadcastFec` still being reassembled.
+struct PendingFec {
+    decoder: FecDecoder,
+    source: PublicKey,
+    data_hash: [u8; 32],
+    flags: i32,
+    date: i32,
+    started: Instant,
+}
+
+impl OverlayManager {
+    /// Creates a manager without a DHT client; peers have to be added with
+    /// [`OverlayManager::add_peer`] or learned from peer exchange.
+    pub fn new(adnl: Arc<AdnlNode>) -> Self {
+        Self {
+            adnl,
+            dht: None,
+            max_peers: DEFAULT_OVERLAY_MAX_PEERS,
+            timeout: DEFAULT_QUERY_TIMEOUT,
+            overlays: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Uses `dht` to find overlay members and resolve their addresses.
+    pub fn with_dht(mut self, dht: Arc<DhtClient>) -> Self {
+        self.dht = Some(dht);
+        self
+    }
+
+    pub fn with_max_peers(mut self, max_peers: usize) -> Self {
+        self.max_peers = max_peers.max(1);
+        self
+    }
+
+    /// Sets the timeout of peer exchange queries.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn adnl(&self) -> &Arc<AdnlNode> {
+        &self.adnl
+    }
+
+    /// Joins the overlay and returns the stream of its broadcasts.
+    ///
+    /// Joining again keeps the peers and replaces the stream. Broadcasts that
+    /// arrive while the stream is full are dropped.
+    pub fn join(&self, id: &OverlayIdFull) -> mpsc::Receiver<ReceivedBroadcast> {
+        let overlay = id.short_id();
+        let (sender, receiver) = mpsc::channel(BROADCAST_QUEUE);
+        let mut overlays = self.overlays.lock().unwrap();
+        match overlays.entry(overlay.0) {
+            Entry::Occupied(mut entry) => entry.get_mut().sender = sender,
+            Entry::Vacant(entry) => {
+                entry.insert(OverlayState {
+                    local_node: OverlayNode::signed(self.adnl.keypair(), overlay, unix_now()),
+                    peers: HashMap::new(),
+                    seen: HashMap::new(),
+                    fec: HashMap::new(),
+                    sender,
+                });
+            }
+        }
+        receiver
+    }
+
+    /// Leaves the overlay and closes its broadcast stream.
+    pub fn leave(&self, overlay: &Int256) -> bool {
+        self.overlays.lock().unwrap().remove(&overlay.0).is_some()
+    }
+
+    /// Signed `overlay.node` record this node announces in the overlay.
+    pub fn local_node(&self, overlay: &Int256) -> Result<OverlayNode, OverlayError> {
+        self.with_overlay(overlay, |state| state.local_node.clone())
+    }
+
+    pub fn peers(&self, overlay: &Int256) -> Result<Vec<OverlayNode>, OverlayError> {
+        self.with_overlay(overlay, |state| state.peers.values().cloned().collect())
+    }
+
+    /// Adds a member whose ADNL address is already known to the node.
+    ///
+    /// Returns `false` for this node itself and for records not newer than
+    /// the known one.
+    pub fn add_peer(&self, overlay: &Int256, node: OverlayNode) -> Result<bool, OverlayError> {
+        node.verify(overlay)?;
+        let adnl_id = node.adnl_id();
+        if !self.adnl.has_peer(&adnl_id) {
+            return Err(AdnlError::UnknownPeer(adnl_id).into());
+        }
+        self.with_overlay(overlay, |state| {
+            state.insert_peer(&self.adnl, node, self.max_peers)
+        })
+    }
+
+    /// Sends an overlay query to a member and returns the raw answer.
+    pub async fn query(
+        &self,
+        overlay: &Int256,
+        peer: &AdnlAddress,
+        query: &[u8],
+        timeout: Duration,
+    ) -> Result<Vec<u8>, OverlayError> {
+        self.with_overlay(overlay, |_| ())?;
+        let mut data = tl_proto::serialize(OverlayQuery {
+            overlay: overlay.clone(),
+        });
+        data.extend_from_slice(query);
+        Ok(self.adnl.query(peer, data, timeout).await?)
+    }
+
+    /// Asks `peer` for random members of the overlay, sending a sample of our
+    /// own peers and our record in exchange. Invalid records are skipped.
+    pub async fn get_random_peers(
+        &self,
+        overlay: &Int256,
+        peer: &AdnlAddress,
+    ) -> Result<Vec<OverlayNode>, OverlayError> {
+        let peers = self.with_overlay(overlay, |state| state.random_peers(Some(peer)))?;
+        let query = tl_proto::serialize(OverlayGetRandomPeers {
+            peers: OverlayNodes { nodes: peers },
+        });
+        let answer = self.query(overlay, peer, &query, self.timeout).await?;
+        let nodes = tl_proto::deserialize::<BoxedWrapper<OverlayNodes>>(&answer)?.0;
+        Ok(nodes
+            .nodes
+            .into_iter()
+            .filter(|node| node.verify(overlay).is_ok())
+            .collect())
+    }
+
+    /// Runs `overlay.getRandomPeers` against a random member and adds the
+    /// members it returns, resolving unknown addresses through the DHT when
+    /// one is configured. Returns the number of new members.
+    pub async fn exchange_peers(&self, overlay: &Int256) -> Result<usize, OverlayError> {
+        let peer = self
+            .with_overlay(overlay, |state| {
+                state.peers.keys().choose(&mut rand::thread_rng()).cloned()
+            })?
+            .ok_or(OverlayError::NoPeers)?;
+        let nodes = self.get_random_peers(overlay, &peer).await?;
+        self.add_resolved(overlay, nodes).await
+    }
+
+    /// Looks up the members published under the DHT `nodes` key of the
+    /// overlay and adds those whose address resolves. Returns the number of
+    /// new members.
+    pub async fn discover_peers(&self, overlay: &Int256) -> Result<usize, OverlayError> {
+        let dht = self.dht.as_ref().ok_or(OverlayError::NoDht)?;
+        self.with_overlay(overlay, |_| ())?;
+        let value = dht
+            .find_value(&DhtKey::new(overlay.0, OVERLAY_NODES_KEY_NAME, 0))
+            .await?;
+        let nodes = tl_proto::deserialize::<BoxedWrapper<OverlayNodes>>(&value.value)?.0;
+        let nodes = nodes
+            .nodes
+            .into_iter()
+            .filter(|node| node.verify(overlay).is_ok())
+            .collect();
+        self.add_resolved(overlay, nodes).await
+    }
+
+    async fn add_resolved(
+        &self,
+        overlay: &Int256,
+        nodes: Vec<OverlayNode>,
+    ) -> Result<usize, OverlayError> {
+        let local_id = self.adnl.id();
+        let nodes = nodes
+            .into_iter()
+            .filter(|node| node.adnl_id() != *local_id)
+            .take(self.max_peers);
+        let resolved = join_all(nodes.map(|node| async move {
+            let adnl_id = node.adnl_id();
+            if self.adnl.has_peer(&adnl_id) {
+                return Some(node);
+            }
+            let (public_key, addr_list) =
+                self.dht.as_ref()?.resolve_address(&adnl_id).await.ok()?;
+            let addr = *addr_list.socket_addrs().first()?;
+            self.adnl.add_peer(public_key, addr);
+            Some(node)
+        }))
+        .await;
+        self.with_overlay(overlay, |state| {
+            resolved
+                .into_iter()
+                .flatten()
+                .filter(|node| state.insert_peer(&self.adnl, node.clone(), self.max_peers))
+                .count()
+        })
+    }
+
+    fn with_overlay<T>(
+        &self,
+        overlay: &Int256,
+        f: impl FnOnce(&mut OverlayState) -> T,
+    ) -> Result<T, OverlayError> {
+        let mut overlays = self.overlays.lock().unwrap();
+        let state = overlays
+            .get_mut(&overlay.0)
+            .ok_or_else(|| OverlayError::NotJoined(overlay.clone()))?;
+        Ok(f(state))
+    }
+
+    fn answer_random_peers(
+        &self,
+        overlay: &Int256,
+        peer: &AdnlAddress,
+        query: &[u8],
+    ) -> Option<Vec<u8>> {
+        let request = tl_proto::deserialize::<OverlayGetRandomPeers>(query).ok()?;
+        self.with_overlay(overlay, |state| {
+            for node in request.peers.nodes {
+                if node.verify(overlay).is_ok() && self.adnl.has_peer(&node.adnl_id()) {
+                    state.insert_peer(&self.adnl, node, self.max_peers);
+                }
+            }
+            tl_proto::serialize(
+                OverlayNodes {
+                    nodes: state.random_peers(Some(peer)),
+                }
+                .as_boxed(),
+            )
+        })
+        .ok()
+    }
+
+    fn handle_broadcast(&self, overlay: &Int256, peer: &AdnlAddress, broadcast: OverlayBroadcast) {
+        let now = unix_now();
+        let result = match broadcast {
+            OverlayBroadcast::Broadcast {
+                src,
+                flags,
+                data,
+                date,
+                signature,
+                ..
+            } => self.accept_simple(overlay, peer, now, src, flags, data, date, &signature),
+            OverlayBroadcast::BroadcastFec {
+                src,
+                data_hash,
+                data_size,
+                flags,
+                data,
+                seqno,
+                fec,
+                date,
+                signature,
+                ..
+            } => self.accept_fec_part(
+                overlay,
+                peer,
+                now,
+                FecPart {
+                    src,
+                    data_hash: data_hash.0,
+                    data_size,
+                    flags,
+                    data,
+                    seqno,
+                    fec,
+                    date,
+                    signature,
+                },
+            ),
+            _ => Ok(()),
+        };
+        if let Err(error) = result {
+            log::debug!("dropping overlay broadcast from {peer:?}: {error}");
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn accept_simple(
+        &self,
+        overlay: &Int256,
+        peer: &AdnlAddress,
+        now: i32,
+        src: PublicKeyOwned,
+        flags: i32,
+        data: Vec<u8>,
+        date: i32,
+        signature: &[u8],
+    ) -> Result<(), OverlayError> {
+        check_date(date, now)?;
+        let hash = broadcast_hash(&src, &data, flags);
+        if self.with_overlay(overlay, |state| state.seen.contains_key(&hash))? {
+            return Ok(());
+        }
+        let source = verify_broadcast(&src, hash, date, signature)?;
+        self.with_overlay(overlay, |state| {
+            state.deliver(ReceivedBroadcast {
+                overlay: overlay.clone(),
+                hash,
+                source,
+                peer: peer.clone(),
+                flags,
+                date,
+                data,
+            })
+        })
+    }
+
+    fn accept_fec_part(
+        &self,
+        overlay: &Int256,
+        peer: &AdnlAddress,
+        now: i32,
+        part: FecPart,
+    ) -> Result<(), OverlayError> {
+        check_date(part.date, now)?;
+        if part.data_size != part.fec.data_size() {
+            return Err(OverlayError::InvalidBroadcast(format!(
+                "data size {} does not match the FEC parameters",
+                part.data_size
+            )));
+        }
+        let seqno = u32::try_from(part.seqno).map_err(|_| {
+            OverlayError::InvalidBroadcast(format!("negative seqno {}", part.seqno))
+        })?;
+        // The FEC parameters, data hash, and flags are all covered by the
+        // broadcast hash, so parts with the same hash agree on them.
+        let hash = fec_broadcast_hash(
+            &part.src,
+            &part.fec,
+            &part.data_hash,
+            part.data_size,
+            part.flags,
+        );
+        let part_hash = fec_part_hash(&hash, &part.data, part.seqno);
+        let source = verify_broadcast(&part.src, part_hash, part.date, &part.signature)?;
+
+        let mut overlays = self.overlays.lock().unwrap();
+        let state = overlays
+            .get_mut(&overlay.0)
+            .ok_or_else(|| OverlayError::NotJoined(overlay.clone()))?;
+        if state.seen.contains_key(&hash) {
+            return Ok(());
+        }
+        if !state.fec.contains_key(&hash) {
+            let decoder = FecDecoder::new(&part.fec, MAX_FEC_BROADCAST_BYTES)?;
+            if state.fec.len() >= MAX_PENDING_FEC {
+                let oldest = state
+                    .fec
+                    .iter()
+                    .min_by_key(|(_, pending)| pending.started)
+                    .map(|(hash, _)| *hash);
+                if let Some(oldest) = oldest {
+                    state.fec.remove(&oldest);
+                }
+            }
+            state.fec.insert(
+                hash,
+                PendingFec {
+                    decoder,
+                    source,
+                    data_hash: part.data_hash,
+                    flags: part.flags,
+                    date: part.date,
+                    started: Instant::now(),
+                },
+            );
+        }
+        let pending = state
+            .fec
+            .get_mut(&hash)
+            .expect("pending broadcast is present");
+        let Some(data) = pending.decoder.add_symbol(seqno, part.data) else {
+            return Ok(());
+        };
+        let pending = state
+            .fec
+            .remove(&hash)
+            .expect("pending broadcast is present");
+        if <[u8; 32]>::from(Sha256::digest(&data)) != pending.data_hash {
+            state.mark_seen(hash);
+            return Err(OverlayError::InvalidBroadcast(
+                "decoded data does not match its hash".to_string(),
+            ));
+        }
+        state.deliver(ReceivedBroadcast {
+            overlay: overlay.clone(),
+            hash,
+            source: pending.source,
+            peer: peer.clone(),
+            flags: pending.flags,
+            date: pending.date,
+            data,
+        });
+        Ok(())
+    }
+}
+
+/// Fields of an `overlay.broadcastFec` part.
+struct FecPart {
+    src: PublicKeyOwned,
+    data_hash: [u8; 32],
+    data_size: i32,
+    flags: i32,
+    data: Vec<u8>,
+    seqno: i32,
+    fec: FecType,
+    date: i32,
+    signature: Vec<u8>,
+}
+
+impl OverlayState {
+    /// Adds a verified member, evicting a random one when the set is full.
+    fn insert_peer(&mut self, adnl: &AdnlNode, node: OverlayNode, max_peers: usize) -> bool {
+        let adnl_id = node.adnl_id();
+        if adnl_id == *adnl.id() {
+            return false;
+        }
+        if let Some(known) = self.peers.get(&adnl_id) {
+            if known.version >= node.version {
+                return false;
+            }
+        } else if self.peers.len() >= max_peers {
+            let evicted = self.peers.keys().choose(&mut rand::thread_rng()).cloned();
+            if let Some(evicted) = evicted {
+                self.peers.remove(&evicted);
+            }
+        }
+        self.peers.insert(adnl_id, node);
+        true
+    }
+
+    /// Our record plus a random sample of members other than `except`.
+    fn random_peers(&self, except: Option<&AdnlAddress>) -> Vec<OverlayNode> {
+        let peers = self
+            .peers
+            .iter()
+            .filter(|(id, _)| Some(*id) != except)
+            .map(|(_, node)| node.clone())
+            .collect::<Vec<_>>();
+        let mut nodes = peers
+            .choose_multiple(&mut rand::thread_rng(), RANDOM_PEERS_ANSWER)
+            .cloned()
+            .collect::<Vec<_>>();
+        nodes.push(self.local_node.clone());
+        nodes
+    }
+
+    fn mark_seen(&mut self, hash: [u8; 32]) {
+        let now = Instant::now();
+        if self.seen.len() >= MAX_SEEN_BROADCASTS {
+            self.fec
+                .retain(|_, pending| now.duration_since(pending.started) < SEEN_TTL);
+        }
+        insert_seen(&mut self.seen, hash, now);
+    }
+
+    fn deliver(&mut self, broadcast: ReceivedBroadcast) {
+        self.mark_seen(broadcast.hash);
+        if let Err(error) = self.sender.try_send(broadcast) {
+            log::debug!("dropping overlay broadcast: {error}");
+        }
+    }
+}
+
+/// Records `hash` in `seen`, dropping expired entries and then the oldest
+/// ones so the set never exceeds [`MAX_SEEN_BROADCASTS`].
+pub(super) fn insert_seen(seen: &mut HashMap<[u8; 32], Instant>, hash: [u8; 32], now: Instant) {
+    if seen.len() >= MAX_SEEN_BROADCASTS {
+        seen.retain(|_, seen| now.duration_since(*seen) < SEEN_TTL);
+    }
+    while seen.len() >= MAX_SEEN_BROADCASTS {
+        let Some(oldest) = seen
+            .iter()
+            .min_by_key(|(_, seen)| **seen)
+            .map(|(hash, _)| *hash)
+        else {
+            break;
+        };
+        seen.remove(&oldest);
+    }
+    seen.insert(hash, now);
+}
+
+pub(super) fn check_date(date: i32, now: i32) -> Result<(), OverlayError> {
+    if date.abs_diff(now) > BROADCAST_MAX_CLOCK_SKEW as u32 {
+        return Err(OverlayError::InvalidBroadcast(format!(
+            "broadcast date {date} is too far from {now}"
+        )));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AdnlMessageHandler for OverlayManager {
+    async fn handle_query(&self, peer: &AdnlAddress, query: &[u8]) -> Option<Vec<u8>> {
+        let mut query = query;
+        let prefix = OverlayQuery::read_from(&mut query).ok()?;
+        self.answer_random_peers(&prefix.overlay, peer, query)
+    }
+
+    async fn handle_custom(&self, peer: &AdnlAddress, data: &[u8]) {
+        let mut data = data;
+        let Ok(prefix) = OverlayMessage::read_from(&mut data) else {
+            return;
+        };
+        match tl_proto::deserialize::<OverlayBroadcast>(data) {
+            Ok(broadcast) => self.handle_broadcast(&prefix.overlay, peer, broadcast),
+            Err(error) => log::debug!("dropping overlay message from {peer:?}: {error}"),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! TON overlays over ADNL UDP.
+//!
+//! An overlay is a named peer group inside the ADNL network. `OverlayManager`
+//! joins public overlays by id, keeps a bounded random set of members found
+//! through the DHT and `overlay.getRandomPeers`, answers peer exchange
+//! queries, and turns signed `overlay.broadcast`/`overlay.broadcastFec`
+//! messages into a per-overlay stream of [`ReceivedBroadcast`]s.
+
+mod manager;
+mod types;
+
+#[cfg(test)]
+mod tests;
+
+use thiserror::Error;
+
+use crate::adnl::AdnlError;
+use crate::dht::DhtError;
+use crate::rldp::RldpError;
+use crate::tl::common::Int256;
+
+pub use manager::{
+    DEFAULT_OVERLAY_MAX_PEERS, MAX_FEC_BROADCAST_BYTES, OverlayManager, ReceivedBroadcast,
+};
+pub use types::{
+    BROADCAST_FLAG_ANY_SENDER, BROADCAST_MAX_CLOCK_SKEW, OverlayBroadcast, OverlayCertificate,
+    OverlayGetRandomPeers, OverlayIdFull, OverlayMessage, OverlayQuery, ShardPublicOverlayId,
+};
+
+#[derive(Debug, Error)]
+pub enum OverlayError {
+    #[error(transparent)]
+    Adnl(#[from] AdnlError),
+    #[error(transparent)]
+    Dht(#[from] DhtError),
+    #[error(transparent)]
+    Fec(#[from] RldpError),
+    #[error("invalid overlay message: {0}")]
+    InvalidMessage(#[from] tl_proto::TlError),
+    #[error("invalid overlay broadcast: {0}")]
+    InvalidBroadcast(String),
+    #[error("overlay {0:?} is not joined")]
+    NotJoined(Int256),
+    #[error("overlay has no known peers")]
+    NoPeers,
+    #[error("peer discovery needs a DHT client")]
+    NoDht,
+}
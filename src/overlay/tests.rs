@@ -0,0 +1,277 @@
+use super::manager::{MAX_SEEN_BROADCASTS, check_date, insert_seen};
+use super::*;
+use crate::adnl::crypto::KeyPair;
+use crate::adnl::udp::unix_now;
+use crate::adnl::{AdnlAddress, AdnlNode};
+use crate::dht::OverlayNode;
+use crate::tl::common::Int256;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+struct Member {
+    manager: Arc<OverlayManager>,
+    broadcasts: mpsc::Receiver<ReceivedBroadcast>,
+}
+
+async fn member(id: &OverlayIdFull) -> Member {
+    let adnl = AdnlNode::bind("127.0.0.1:0", KeyPair::generate(&mut OsRng))
+        .await
+        .unwrap();
+    let manager = Arc::new(OverlayManager::new(Arc::new(adnl)));
+    manager.adnl().set_handler(manager.clone());
+    let broadcasts = manager.join(id);
+    Member {
+        manager,
+        broadcasts,
+    }
+}
+
+/// Registers `to` as an ADNL peer of `from` and returns its id.
+fn connect(from: &Member, to: &Member) -> AdnlAddress {
+    let to = to.manager.adnl();
+    from.manager
+        .adnl()
+        .add_peer(*to.public_key(), to.local_addr().unwrap())
+}
+
+fn overlay_id() -> OverlayIdFull {
+    OverlayIdFull::masterchain([7; 32])
+}
+
+fn message(overlay: &Int256, broadcast: &OverlayBroadcast) -> Vec<u8> {
+    let mut data = tl_proto::serialize(OverlayMessage {
+        overlay: overlay.clone(),
+    });
+    data.extend_from_slice(&tl_proto::serialize(broadcast));
+    data
+}
+
+async fn next(member: &mut Member) -> ReceivedBroadcast {
+    tokio::time::timeout(TIMEOUT, member.broadcasts.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn shard_overlay_ids_follow_ton_derivation() {
+    let id = OverlayIdFull::masterchain([7; 32]);
+    let name = id.name();
+    assert_eq!(&name[..4], &0x4d9ed329u32.to_le_bytes());
+    assert_eq!(&name[4..8], &(-1i32).to_le_bytes());
+    assert_eq!(&name[8..16], &(1u64 << 63).to_le_bytes());
+    assert_eq!(&name[16..], &[7; 32]);
+    assert_eq!(id, OverlayIdFull::for_shard(-1, 1 << 63, [7; 32]));
+
+    let mut key = 0x34ba45cbu32.to_le_bytes().to_vec();
+    key.push(name.len() as u8);
+    key.extend_from_slice(name);
+    key.resize(key.len().next_multiple_of(4), 0);
+    assert_eq!(id.short_id().0, <[u8; 32]>::from(Sha256::digest(&key)));
+    assert_ne!(
+        id.short_id(),
+        OverlayIdFull::for_shard(0, 1 << 63, [7; 32]).short_id()
+    );
+}
+
+#[test]
+fn broadcast_constructor_ids_match_schema() {
+    let keypair = KeyPair::generate(&mut OsRng);
+    let broadcast = OverlayBroadcast::simple(&keypair, b"data".to_vec(), 0, 1);
+    let bytes = tl_proto::serialize(&broadcast);
+    assert_eq!(&bytes[..4], &0xb15a2b6bu32.to_le_bytes());
+    // `src` is a boxed `pub.ed25519` followed by `overlay.emptyCertificate`.
+    assert_eq!(&bytes[40..44], &0x32dabccfu32.to_le_bytes());
+    assert_eq!(
+        tl_proto::deserialize::<OverlayBroadcast>(&bytes).unwrap(),
+        broadcast
+    );
+
+    let query = tl_proto::serialize(OverlayQuery {
+        overlay: Int256([1; 32]),
+    });
+    assert_eq!(&query[..4], &0xccfd8443u32.to_le_bytes());
+}
+
+#[tokio::test]
+async fn members_exchange_random_peers() {
+    let id = overlay_id();
+    let overlay = id.short_id();
+    let a = member(&id).await;
+    let b = member(&id).await;
+    let c = member(&id).await;
+    let b_id = connect(&a, &b);
+    connect(&b, &c);
+    let b_node = b.manager.local_node(&overlay).unwrap();
+    let c_node = c.manager.local_node(&overlay).unwrap();
+    assert!(a.manager.add_peer(&overlay, b_node.clone()).unwrap());
+    assert!(!a.manager.add_peer(&overlay, b_node).unwrap());
+    assert!(b.manager.add_peer(&overlay, c_node.clone()).unwrap());
+
+    let peers = a.manager.get_random_peers(&overlay, &b_id).await.unwrap();
+    let ids = peers.iter().map(OverlayNode::adnl_id).collect::<Vec<_>>();
+    assert!(ids.contains(&b_id));
+    assert!(ids.contains(&c_node.adnl_id()));
+
+    // B learned A from the peers sent with the query. C is not reachable for
+    // A without a DHT, so the exchange adds nothing new.
+    let b_peers = b.manager.peers(&overlay).unwrap();
+    assert!(
+        b_peers
+            .iter()
+            .any(|node| node.adnl_id() == *a.manager.adnl().id())
+    );
+    assert_eq!(a.manager.exchange_peers(&overlay).await.unwrap(), 0);
+    assert_eq!(a.manager.peers(&overlay).unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn peer_set_is_bounded_and_checks_records() {
+    let id = overlay_id();
+    let overlay = id.short_id();
+    let a = member(&id).await;
+    let manager = OverlayManager::new(a.manager.adnl().clone()).with_max_peers(2);
+    manager.join(&id);
+
+    for _ in 0..4 {
+        let keypair = KeyPair::generate(&mut OsRng);
+        a.manager
+            .adnl()
+            .add_peer(keypair.public_key, "127.0.0.1:1".parse().unwrap());
+        let node = OverlayNode::signed(&keypair, overlay.clone(), unix_now());
+        assert!(manager.add_peer(&overlay, node).unwrap());
+    }
+    assert_eq!(manager.peers(&overlay).unwrap().len(), 2);
+
+    let stranger = OverlayNode::signed(&KeyPair::generate(&mut OsRng), overlay.clone(), 1);
+    assert!(matches!(
+        manager.add_peer(&overlay, stranger),
+        Err(OverlayError::Adnl(crate::adnl::AdnlError::UnknownPeer(_)))
+    ));
+    let keypair = KeyPair::generate(&mut OsRng);
+    let mut forged = OverlayNode::signed(&keypair, overlay.clone(), 1);
+    forged.version = 2;
+    assert!(matches!(
+        manager.add_peer(&overlay, forged),
+        Err(OverlayError::Dht(_))
+    ));
+    let other = OverlayNode::signed(&keypair, Int256([1; 32]), 1);
+    assert!(manager.add_peer(&overlay, other).is_err());
+
+    assert!(matches!(
+        manager.discover_peers(&overlay).await,
+        Err(OverlayError::NoDht)
+    ));
+    assert!(matches!(
+        OverlayManager::new(a.manager.adnl().clone())
+            .exchange_peers(&overlay)
+            .await,
+        Err(OverlayError::NotJoined(_))
+    ));
+    assert!(manager.leave(&overlay));
+    assert!(matches!(
+        manager.peers(&overlay),
+        Err(OverlayError::NotJoined(_))
+    ));
+}
+
+#[tokio::test]
+async fn broadcasts_are_checked_and_deduplicated() {
+    let id = overlay_id();
+    let overlay = id.short_id();
+    let a = member(&id).await;
+    let mut b = member(&id).await;
+    let b_id = connect(&a, &b);
+    let adnl = a.manager.adnl();
+    let keypair = KeyPair::generate(&mut OsRng);
+    let now = unix_now();
+
+    let first = OverlayBroadcast::simple(&keypair, b"first".to_vec(), 0, now);
+    adnl.send_custom(&b_id, message(&overlay, &first))
+        .await
+        .unwrap();
+    let received = next(&mut b).await;
+    assert_eq!(received.data, b"first");
+    assert_eq!(received.source, keypair.public_key);
+    assert_eq!(received.peer, *adnl.id());
+    assert_eq!(received.overlay, overlay);
+
+    let stale = OverlayBroadcast::simple(&keypair, b"stale".to_vec(), 0, now - 600);
+    let mut forged = OverlayBroadcast::simple(&keypair, b"forged".to_vec(), 0, now);
+    if let OverlayBroadcast::Broadcast { data, .. } = &mut forged {
+        *data = b"tampered".to_vec();
+    }
+    let ancient = OverlayBroadcast::simple(&keypair, b"ancient".to_vec(), 0, i32::MIN);
+    let elsewhere = OverlayBroadcast::simple(&keypair, b"elsewhere".to_vec(), 0, now);
+    let last = OverlayBroadcast::simple(&keypair, b"last".to_vec(), BROADCAST_FLAG_ANY_SENDER, now);
+    for data in [
+        message(&overlay, &first),
+        message(&overlay, &stale),
+        message(&overlay, &ancient),
+        message(&overlay, &forged),
+        message(&Int256([9; 32]), &elsewhere),
+        message(&overlay, &last),
+    ] {
+        adnl.send_custom(&b_id, data).await.unwrap();
+    }
+    let received = next(&mut b).await;
+    assert_eq!(received.data, b"last");
+    assert_eq!(received.flags, BROADCAST_FLAG_ANY_SENDER);
+    assert!(b.broadcasts.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn fec_broadcasts_are_reassembled_from_any_parts() {
+    let id = overlay_id();
+    let overlay = id.short_id();
+    let a = member(&id).await;
+    let mut b = member(&id).await;
+    let b_id = connect(&a, &b);
+    let keypair = KeyPair::generate(&mut OsRng);
+
+    let data = (0..50_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let parts = OverlayBroadcast::fec_parts(&keypair, &data, 0, unix_now(), 90).unwrap();
+    // 66 source symbols; skip the first ten and rely on repair symbols.
+    for part in &parts[10..] {
+        a.manager
+            .adnl()
+            .send_custom(&b_id, message(&overlay, part))
+            .await
+            .unwrap();
+    }
+    let received = next(&mut b).await;
+    assert_eq!(received.data, data);
+    assert_eq!(received.source, keypair.public_key);
+    assert!(b.broadcasts.try_recv().is_err());
+}
+
+#[test]
+fn broadcast_dates_far_from_now_are_rejected_without_overflow() {
+    let now = 1_700_000_000;
+    assert!(check_date(now + BROADCAST_MAX_CLOCK_SKEW, now).is_ok());
+    assert!(check_date(now - BROADCAST_MAX_CLOCK_SKEW - 1, now).is_err());
+    assert!(check_date(i32::MIN, now).is_err());
+    assert!(check_date(i32::MAX, i32::MIN).is_err());
+}
+
+#[test]
+fn seen_broadcasts_stay_within_the_cap() {
+    let start = std::time::Instant::now();
+    let mut seen = std::collections::HashMap::new();
+    for index in 0..MAX_SEEN_BROADCASTS {
+        let mut hash = [0; 32];
+        hash[..8].copy_from_slice(&(index as u64).to_le_bytes());
+        insert_seen(&mut seen, hash, start + Duration::from_millis(index as u64));
+    }
+    assert_eq!(seen.len(), MAX_SEEN_BROADCASTS);
+
+    insert_seen(&mut seen, [0xFF; 32], start + Duration::from_secs(60));
+    assert_eq!(seen.len(), MAX_SEEN_BROADCASTS);
+    assert!(seen.contains_key(&[0xFF; 32]));
+    assert!(!seen.contains_key(&[0; 32]));
+}
@@ -0,0 +1,371 @@
+use derivative::Derivative;
+use sha2::{Digest, Sha256};
+use tl_proto::{BoxedConstructor, TlRead, TlWrite};
+
+use crate::adnl::crypto::tl::PublicKeyOwned;
+use crate::adnl::crypto::{KeyPair, PublicKey};
+use crate::dht::OverlayNodes;
+use crate::rldp::{FecType, RaptorQEncoder};
+use crate::tl::common::Int256;
+use crate::tl::utils::fmt_bytes;
+
+use super::OverlayError;
+
+/// Broadcast flag that leaves the source out of the broadcast hash, so any
+/// member may re-sign it.
+pub const BROADCAST_FLAG_ANY_SENDER: i32 = 1;
+/// Broadcasts dated further than this from the local clock are rejected, as
+/// TON nodes do.
+pub const BROADCAST_MAX_CLOCK_SKEW: i32 = 20;
+
+/// Full id of an overlay: the `pub.overlay` key whose name identifies it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlayIdFull {
+    name: Vec<u8>,
+}
+
+impl OverlayIdFull {
+    pub fn new(name: Vec<u8>) -> Self {
+        Self { name }
+    }
+
+    /// Public overlay of a shard, named by a boxed
+    /// `tonNode.shardPublicOverlayId`.
+    pub fn for_shard(workchain: i32, shard: u64, zero_state_file_hash: [u8; 32]) -> Self {
+        Self::new(tl_proto::serialize(
+            ShardPublicOverlayId {
+                workchain,
+                shard: shard as i64,
+                zero_state_file_hash: Int256(zero_state_file_hash),
+            }
+            .as_boxed(),
+        ))
+    }
+
+    /// Public overlay of the masterchain.
+    pub fn masterchain(zero_state_file_hash: [u8; 32]) -> Self {
+        Self::for_shard(-1, 1 << 63, zero_state_file_hash)
+    }
+
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    /// The `pub.overlay` key of the overlay.
+    pub fn public_key(&self) -> PublicKeyOwned {
+        PublicKeyOwned::Overlay {
+            name: self.name.clone(),
+        }
+    }
+
+    /// Short id used in `overlay.query`, `overlay.message`, and
+    /// `overlay.node`: `sha256` of the boxed `pub.overlay` key.
+    pub fn short_id(&self) -> Int256 {
+        Int256(Sha256::digest(tl_proto::serialize(self.public_key())).into())
+    }
+}
+
+/// tonNode.shardPublicOverlayId workchain:int shard:long zero_state_file_hash:int256 = tonNode.ShardPublicOverlayId;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct ShardPublicOverlayId {
+    pub workchain: i32,
+    pub shard: i64,
+    pub zero_state_file_hash: Int256,
+}
+
+impl BoxedConstructor for ShardPublicOverlayId {
+    const TL_ID: u32 = 0x4d9ed329;
+}
+
+/// overlay.query overlay:int256 = True;
+///
+/// Prefix of every ADNL query addressed to an overlay; the overlay query
+/// itself follows.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed, id = 0xccfd8443)]
+pub struct OverlayQuery {
+    pub overlay: Int256,
+}
+
+/// overlay.message overlay:int256 = overlay.Message;
+///
+/// Prefix of every ADNL custom message addressed to an overlay; an
+/// `overlay.Broadcast` follows.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed, id = 0x75252420)]
+pub struct OverlayMessage {
+    pub overlay: Int256,
+}
+
+/// overlay.getRandomPeers peers:overlay.nodes = overlay.Nodes;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed, id = 0x48ee64ab)]
+pub struct OverlayGetRandomPeers {
+    pub peers: OverlayNodes,
+}
+
+/// `overlay.Certificate`: permission to broadcast in a private overlay.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum OverlayCertificate {
+    /// overlay.certificate issued_by:PublicKey expire_at:int max_size:int signature:bytes = overlay.Certificate;
+    #[tl(id = 0xe09ed731)]
+    Certificate {
+        issued_by: PublicKeyOwned,
+        expire_at: i32,
+        max_size: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        signature: Vec<u8>,
+    },
+    /// overlay.certificateV2 issued_by:PublicKey expire_at:int max_size:int flags:int signature:bytes = overlay.Certificate;
+    #[tl(id = 0xb43f9c83)]
+    CertificateV2 {
+        issued_by: PublicKeyOwned,
+        expire_at: i32,
+        max_size: i32,
+        flags: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        signature: Vec<u8>,
+    },
+    /// overlay.emptyCertificate = overlay.Certificate;
+    #[tl(id = 0x32dabccf)]
+    Empty,
+}
+
+/// `overlay.Broadcast`: payloads of `overlay.message`.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum OverlayBroadcast {
+    /// overlay.broadcast src:PublicKey certificate:overlay.Certificate flags:int data:bytes date:int signature:bytes = overlay.Broadcast;
+    #[tl(id = 0xb15a2b6b)]
+    Broadcast {
+        src: PublicKeyOwned,
+        certificate: OverlayCertificate,
+        flags: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+        date: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        signature: Vec<u8>,
+    },
+    /// overlay.broadcastFec src:PublicKey certificate:overlay.Certificate data_hash:int256 data_size:int flags:int
+    ///           data:bytes seqno:int fec:fec.Type date:int signature:bytes = overlay.Broadcast;
+    #[tl(id = 0xbad7c36a)]
+    BroadcastFec {
+        src: PublicKeyOwned,
+        certificate: OverlayCertificate,
+        data_hash: Int256,
+        data_size: i32,
+        flags: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+        seqno: i32,
+        fec: FecType,
+        date: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        signature: Vec<u8>,
+    },
+    /// overlay.broadcastFecShort src:PublicKey certificate:overlay.Certificate broadcast_hash:int256 part_data_hash:int256 seqno:int signature:bytes = overlay.Broadcast;
+    #[tl(id = 0xf1881342)]
+    BroadcastFecShort {
+        src: PublicKeyOwned,
+        certificate: OverlayCertificate,
+        broadcast_hash: Int256,
+        part_data_hash: Int256,
+        seqno: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        signature: Vec<u8>,
+    },
+    /// overlay.unicast data:bytes = overlay.Broadcast;
+    #[tl(id = 0x33534e24)]
+    Unicast {
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+    },
+    /// overlay.fec.received hash:int256 = overlay.Broadcast;
+    #[tl(id = 0xd55c14ec)]
+    FecReceived { hash: Int256 },
+    /// overlay.fec.completed hash:int256 = overlay.Broadcast;
+    #[tl(id = 0x09d76914)]
+    FecCompleted { hash: Int256 },
+    /// overlay.broadcastNotFound = overlay.Broadcast;
+    #[tl(id = 0x95863624)]
+    NotFound,
+}
+
+impl OverlayBroadcast {
+    /// Creates an `overlay.broadcast` of `data` signed by `keypair`.
+    pub fn simple(keypair: &KeyPair, data: Vec<u8>, flags: i32, date: i32) -> Self {
+        let src = keypair.public_key.as_tl().as_equivalent_owned();
+        let hash = broadcast_hash(&src, &data, flags);
+        Self::Broadcast {
+            src,
+            certificate: OverlayCertificate::Empty,
+            flags,
+            data,
+            date,
+            signature: sign_hash(keypair, hash, date),
+        }
+    }
+
+    /// Creates the first `count` `overlay.broadcastFec` parts of `data`
+    /// signed by `keypair`.
+    pub fn fec_parts(
+        keypair: &KeyPair,
+        data: &[u8],
+        flags: i32,
+        date: i32,
+        count: u32,
+    ) -> Result<Vec<Self>, OverlayError> {
+        let src = keypair.public_key.as_tl().as_equivalent_owned();
+        let encoder = RaptorQEncoder::new(data)?;
+        let fec = encoder.fec_type();
+        let data_hash: [u8; 32] = Sha256::digest(data).into();
+        let hash = fec_broadcast_hash(&src, &fec, &data_hash, data.len() as i32, flags);
+        Ok((0..count)
+            .map(|seqno| {
+                let part = encoder.symbol(seqno);
+                let part_hash = fec_part_hash(&hash, &part, seqno as i32);
+                Self::BroadcastFec {
+                    src: src.clone(),
+                    certificate: OverlayCertificate::Empty,
+                    data_hash: Int256(data_hash),
+                    data_size: data.len() as i32,
+                    flags,
+                    data: part,
+                    seqno: seqno as i32,
+                    fec,
+                    date,
+                    signature: sign_hash(keypair, part_hash, date),
+                }
+            })
+            .collect())
+    }
+}
+
+/// overlay.broadcast.id src:int256 data_hash:int256 flags:int = overlay.broadcast.Id;
+#[derive(TlWrite)]
+#[tl(boxed, id = 0x51fd789a)]
+struct BroadcastId {
+    src: Int256,
+    data_hash: Int256,
+    flags: i32,
+}
+
+/// overlay.broadcastFec.id src:int256 type:int256 data_hash:int256 size:int flags:int = overlay.broadcastFec.Id;
+#[derive(TlWrite)]
+#[tl(boxed, id = 0xfb3155a6)]
+struct BroadcastFecId {
+    src: Int256,
+    fec_type: Int256,
+    data_hash: Int256,
+    size: i32,
+    flags: i32,
+}
+
+/// overlay.broadcastFec.partId broadcast_hash:int256 data_hash:int256 seqno:int = overlay.broadcastFec.PartId;
+#[derive(TlWrite)]
+#[tl(boxed, id = 0xa46962d0)]
+struct BroadcastFecPartId {
+    broadcast_hash: Int256,
+    data_hash: Int256,
+    seqno: i32,
+}
+
+/// overlay.broadcast.toSign hash:int256 date:int = overlay.broadcast.ToSign;
+#[derive(TlWrite)]
+#[tl(boxed, id = 0xfa374e7c)]
+struct BroadcastToSign {
+    hash: Int256,
+    date: i32,
+}
+
+/// Source field of broadcast ids: the short id of `src`, or zero for
+/// [`BROADCAST_FLAG_ANY_SENDER`].
+fn broadcast_source(src: &PublicKeyOwned, flags: i32) -> Int256 {
+    if flags & BROADCAST_FLAG_ANY_SENDER != 0 {
+        Int256::default()
+    } else {
+        Int256(Sha256::digest(tl_proto::serialize(src)).into())
+    }
+}
+
+fn tl_hash<T: TlWrite>(data: T) -> [u8; 32] {
+    Sha256::digest(tl_proto::serialize(data)).into()
+}
+
+/// Hash identifying an `overlay.broadcast`.
+pub(crate) fn broadcast_hash(src: &PublicKeyOwned, data: &[u8], flags: i32) -> [u8; 32] {
+    tl_hash(BroadcastId {
+        src: broadcast_source(src, flags),
+        data_hash: Int256(Sha256::digest(data).into()),
+        flags,
+    })
+}
+
+/// Hash identifying the whole of an `overlay.broadcastFec`.
+pub(crate) fn fec_broadcast_hash(
+    src: &PublicKeyOwned,
+    fec: &FecType,
+    data_hash: &[u8; 32],
+    size: i32,
+    flags: i32,
+) -> [u8; 32] {
+    tl_hash(BroadcastFecId {
+        src: broadcast_source(src, flags),
+        fec_type: Int256(tl_hash(fec)),
+        data_hash: Int256(*data_hash),
+        size,
+        flags,
+    })
+}
+
+/// Hash of one `overlay.broadcastFec` part, which its signature covers.
+pub(crate) fn fec_part_hash(broadcast_hash: &[u8; 32], part: &[u8], seqno: i32) -> [u8; 32] {
+    tl_hash(BroadcastFecPartId {
+        broadcast_hash: Int256(*broadcast_hash),
+        data_hash: Int256(Sha256::digest(part).into()),
+        seqno,
+    })
+}
+
+fn sign_hash(keypair: &KeyPair, hash: [u8; 32], date: i32) -> Vec<u8> {
+    let to_sign = BroadcastToSign {
+        hash: Int256(hash),
+        date,
+    };
+    keypair.sign_raw(&tl_proto::serialize(to_sign)).to_vec()
+}
+
+/// Checks a broadcast signature over `overlay.broadcast.toSign` and returns
+/// the source key.
+pub(crate) fn verify_broadcast(
+    src: &PublicKeyOwned,
+    hash: [u8; 32],
+    date: i32,
+    signature: &[u8],
+) -> Result<PublicKey, OverlayError> {
+    let public_key = PublicKey::from_tl(src.as_equivalent_ref()).ok_or_else(|| {
+        OverlayError::InvalidBroadcast("broadcast source is not an ed25519 key".to_string())
+    })?;
+    let to_sign = tl_proto::serialize(BroadcastToSign {
+        hash: Int256(hash),
+        date,
+    });
+    let valid = signature
+        .try_into()
+        .is_ok_and(|signature| public_key.verify_raw(&to_sign, signature));
+    if valid {
+        Ok(public_key)
+    } else {
+        Err(OverlayError::InvalidBroadcast(
+            "bad broadcast signature".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,209 @@
+use raptorq::{
+    EncodingPacket, ObjectTransmissionInformation, PayloadId, SourceBlockDecoder,
+    SourceBlockEncoder, extended_source_block_symbols,
+};
+
+use super::RldpError;
+use super::types::FecType;
+
+/// Symbol size used by TON nodes for RaptorQ-encoded parts.
+pub const RLDP_SYMBOL_BYTES: usize = 768;
+/// Largest symbol count of a single RaptorQ source block (RFC 6330 `K'_max`).
+const MAX_SOURCE_SYMBOLS: usize = 56_403;
+
+/// RaptorQ (RFC 6330) encoder for one transfer part.
+///
+/// Symbols are numbered by their encoding symbol id, which RLDP sends as
+/// `seqno`: ids below `symbols_count` are the source symbols, and every later
+/// id is a distinct repair symbol, so a sender can keep producing new symbols
+/// until the receiver reports completion.
+pub struct RaptorQEncoder {
+    data: Vec<u8>,
+    data_size: usize,
+    symbols_count: u32,
+    encoder: SourceBlockEncoder,
+}
+
+impl RaptorQEncoder {
+    /// Prepares `data` for encoding with [`RLDP_SYMBOL_BYTES`] symbols.
+    pub fn new(data: &[u8]) -> Result<Self, RldpError> {
+        let symbols_count = data.len().div_ceil(RLDP_SYMBOL_BYTES).max(1);
+        if symbols_count > MAX_SOURCE_SYMBOLS {
+            return Err(RldpError::Fec(format!(
+                "{} bytes need more than {MAX_SOURCE_SYMBOLS} symbols",
+                data.len()
+            )));
+        }
+        let mut padded = data.to_vec();
+        padded.resize(symbols_count * RLDP_SYMBOL_BYTES, 0);
+        let config = transmission_info(data.len(), RLDP_SYMBOL_BYTES);
+        Ok(Self {
+            encoder: SourceBlockEncoder::new2(0, &config, &padded),
+            data: padded,
+            data_size: data.len(),
+            symbols_count: symbols_count as u32,
+        })
+    }
+
+    pub fn fec_type(&self) -> FecType {
+        FecType::RaptorQ {
+            data_size: self.data_size as i32,
+            symbol_size: RLDP_SYMBOL_BYTES as i32,
+            symbols_count: self.symbols_count as i32,
+        }
+    }
+
+    pub fn symbols_count(&self) -> u32 {
+        self.symbols_count
+    }
+
+    /// Returns the symbol with encoding symbol id `seqno`.
+    pub fn symbol(&self, seqno: u32) -> Vec<u8> {
+        if seqno < self.symbols_count {
+            let start = seqno as usize * RLDP_SYMBOL_BYTES;
+            return self.data[start..start + RLDP_SYMBOL_BYTES].to_vec();
+        }
+        // `raptorq` numbers repair symbols from `K'` rather than `K`.
+        self.encoder
+            .repair_packets(seqno - self.symbols_count, 1)
+            .pop()
+            .expect("one repair packet was requested")
+            .split()
+            .1
+    }
+}
+
+/// Decoder for one transfer part.
+pub enum FecDecoder {
+    RaptorQ {
+        decoder: Box<SourceBlockDecoder>,
+        data_size: usize,
+        symbol_size: usize,
+        symbols_count: u32,
+        extended_symbols: u32,
+    },
+    /// `fec.roundRobin` repeats the source symbols in order.
+    RoundRobin {
+        symbols: Vec<Option<Vec<u8>>>,
+        missing: usize,
+        data_size: usize,
+        symbol_size: usize,
+    },
+}
+
+impl FecDecoder {
+    /// Creates a decoder for `fec_type` after checking that its parameters
+    /// are consistent and that the part is at most `max_data_size` bytes.
+    pub fn new(fec_type: &FecType, max_data_size: usize) -> Result<Self, RldpError> {
+        let (data_size, symbol_size, symbols_count) = match *fec_type {
+            FecType::RaptorQ {
+                data_size,
+                symbol_size,
+                symbols_count,
+            }
+            | FecType::RoundRobin {
+                data_size,
+                symbol_size,
+                symbols_count,
+            } => (data_size, symbol_size, symbols_count),
+            FecType::Online { .. } => {
+                return Err(RldpError::Fec("`fec.online` is not supported".to_string()));
+            }
+        };
+        let invalid = || {
+            RldpError::Fec(format!(
+                "invalid parameters: data_size {data_size}, symbol_size {symbol_size}, symbols_count {symbols_count}"
+            ))
+        };
+        let data_size = usize::try_from(data_size).map_err(|_| invalid())?;
+        let symbol_size = usize::try_from(symbol_size)
+            .ok()
+            .filter(|size| (1..=u16::MAX as usize).contains(size))
+            .ok_or_else(invalid)?;
+        let symbols_count = usize::try_from(symbols_count).map_err(|_| invalid())?;
+        if data_size == 0
+            || data_size > max_data_size
+            || symbols_count != data_size.div_ceil(symbol_size)
+            || symbols_count > MAX_SOURCE_SYMBOLS
+        {
+            return Err(invalid());
+        }
+
+        Ok(match fec_type {
+            FecType::RaptorQ { .. } => {
+                let config = transmission_info(data_size, symbol_size);
+                Self::RaptorQ {
+                    decoder: Box::new(SourceBlockDecoder::new2(0, &config, data_size as u64)),
+                    data_size,
+                    symbol_size,
+                    symbols_count: symbols_count as u32,
+                    extended_symbols: extended_source_block_symbols(symbols_count as u32),
+                }
+            }
+            _ => Self::RoundRobin {
+                symbols: vec![None; symbols_count],
+                missing: symbols_count,
+                data_size,
+                symbol_size,
+            },
+        })
+    }
+
+    /// Adds a received symbol and returns the part data once it can be
+    /// recovered. Symbols of the wrong size are ignored.
+    pub fn add_symbol(&mut self, seqno: u32, symbol: Vec<u8>) -> Option<Vec<u8>> {
+        match self {
+            Self::RaptorQ {
+                decoder,
+                data_size,
+                symbol_size,
+                symbols_count,
+                extended_symbols,
+            } => {
+                if symbol.len() != *symbol_size {
+                    return None;
+                }
+                let id = if seqno < *symbols_count {
+                    seqno
+                } else {
+                    seqno.checked_add(*extended_symbols - *symbols_count)?
+                };
+                let mut data =
+                    decoder.decode([EncodingPacket::new(PayloadId::new(0, id), symbol)])?;
+                data.truncate(*data_size);
+                Some(data)
+            }
+            Self::RoundRobin {
+                symbols,
+                missing,
+                data_size,
+                symbol_size,
+            } => {
+                if symbol.len() != *symbol_size {
+                    return None;
+                }
+                let count = symbols.len();
+                let slot = &mut symbols[seqno as usize % count];
+                if slot.is_none() {
+                    *slot = Some(symbol);
+                    *missing -= 1;
+                }
+                if *missing > 0 {
+                    return None;
+                }
+                let mut data = symbols
+                    .iter()
+                    .flatten()
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>();
+                data.truncate(*data_size);
+                Some(data)
+            }
+        }
+    }
+}
+
+fn transmission_info(data_size: usize, symbol_size: usize) -> ObjectTransmissionInformation {
+    ObjectTransmissionInformation::new(data_size as u64, symbol_size as u16, 1, 1, 1)
+}
@@ -0,0 +1,38 @@
+//! RLDP over ADNL UDP.
+//!
+//! RLDP moves payloads too large for a single ADNL message. A transfer is cut
+//! into parts, each part is RaptorQ-encoded into 768-byte symbols, and the
+//! symbols are sent as `rldp.messagePart` (or `rldp2.messagePart`) custom
+//! messages until the receiver answers `rldp.complete`. Lost datagrams are
+//! covered by sending more repair symbols instead of retransmitting, so a
+//! multi-megabyte answer survives packet loss without per-packet acks.
+//!
+//! `RldpNode` wraps an `AdnlNode` and exchanges `rldp.query`/`rldp.answer`
+//! and `rldp.message` payloads over such transfers.
+
+mod fec;
+mod node;
+mod types;
+
+#[cfg(test)]
+mod tests;
+
+use thiserror::Error;
+
+use crate::adnl::AdnlError;
+
+pub use fec::{FecDecoder, RLDP_SYMBOL_BYTES, RaptorQEncoder};
+pub use node::{DEFAULT_RLDP_MAX_MESSAGE_BYTES, RLDP_PART_BYTES, RldpNode, RldpVersion};
+pub use types::{FecType, Rldp2MessagePart, RldpMessage, RldpMessagePart};
+
+#[derive(Debug, Error)]
+pub enum RldpError {
+    #[error(transparent)]
+    Adnl(#[from] AdnlError),
+    #[error("invalid RLDP message: {0}")]
+    InvalidMessage(#[from] tl_proto::TlError),
+    #[error("RLDP payload of {size} bytes exceeds the {max} byte limit")]
+    TooLarge { size: usize, max: usize },
+    #[error("RLDP FEC error: {0}")]
+    Fec(String),
+}
@@ -0,0 +1,818 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::adnl::udp::{AdnlMessageHandler, AdnlNode, unix_now};
+use crate::adnl::{AdnlAddress, AdnlError};
+use crate::tl::common::Int256;
+
+use super::RldpError;
+use super::fec::{FecDecoder, RaptorQEncoder};
+use super::types::{FecType, Rldp2MessagePart, RldpMessage, RldpMessagePart};
+
+/// Size of the parts a transfer is split into before encoding.
+pub const RLDP_PART_BYTES: usize = 1 << 20;
+/// Default limit for incoming queries and messages.
+pub const DEFAULT_RLDP_MAX_MESSAGE_BYTES: usize = 1 << 20;
+/// Symbols sent ahead of the last seqno confirmed by the receiver.
+const SEND_WINDOW: u32 = 128;
+/// Extra symbols sent when no confirm arrives for `RESEND_INTERVAL`.
+const RESEND_SYMBOLS: u32 = 16;
+const RESEND_INTERVAL: Duration = Duration::from_millis(10);
+/// A receiver sends `rldp.confirm` after this many symbols of a part.
+const CONFIRM_EVERY: i32 = 16;
+/// Incoming transfers idle for this long are dropped, and finished transfers
+/// are remembered this long to repeat `rldp.complete`.
+const TRANSFER_TTL: Duration = Duration::from_secs(60);
+/// Incomplete incoming transfers kept at once. Beyond this, new unsolicited
+/// transfers are refused and answers to our queries evict the idlest one.
+pub(super) const MAX_INBOUND_TRANSFERS: usize = 256;
+/// Longest time spent sending an answer, whatever timeout the query carries.
+pub(super) const MAX_ANSWER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// RLDP protocol version used for outgoing transfers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RldpVersion {
+    /// `rldp.messagePart`/`rldp.confirm`/`rldp.complete`.
+    V1,
+    /// `rldp2.messagePart`/`rldp2.confirm`/`rldp2.complete`.
+    #[default]
+    V2,
+}
+
+/// RLDP endpoint on top of an `AdnlNode`.
+///
+/// The node installs itself as the ADNL message handler: custom messages
+/// carrying RLDP transfer traffic are consumed here, everything else goes to
+/// the handler set with [`RldpNode::set_adnl_handler`]. Reassembled
+/// `rldp.query` and `rldp.message` payloads are passed to the handler set with
+/// [`RldpNode::set_handler`]; answers are sent back with the RLDP version of
+/// the query and dropped when they exceed its `max_answer_size`.
+///
+/// Both v1 and v2 transfers are accepted; [`RldpNode::with_version`] selects
+/// the version of outgoing queries and messages.
+pub struct RldpNode {
+    inner: Arc<RldpInner>,
+}
+
+struct RldpInner {
+    adnl: Arc<AdnlNode>,
+    version: RldpVersion,
+    max_message_bytes: usize,
+    handler: RwLock<Option<Arc<dyn AdnlMessageHandler>>>,
+    adnl_handler: RwLock<Option<Arc<dyn AdnlMessageHandler>>>,
+    state: Mutex<RldpState>,
+}
+
+#[derive(Default)]
+struct RldpState {
+    outbound: HashMap<[u8; 32], (AdnlAddress, mpsc::UnboundedSender<Ack>)>,
+    inbound: HashMap<[u8; 32], InboundTransfer>,
+    completed: HashMap<[u8; 32], Instant>,
+    /// Pending queries keyed by the transfer id of their answer.
+    queries: HashMap<[u8; 32], PendingQuery>,
+}
+
+struct PendingQuery {
+    peer: AdnlAddress,
+    query_id: [u8; 32],
+    max_answer_size: usize,
+    sender: oneshot::Sender<Vec<u8>>,
+}
+
+struct InboundTransfer {
+    peer: AdnlAddress,
+    total_size: usize,
+    data: Vec<u8>,
+    part: i32,
+    decoder: Option<FecDecoder>,
+    max_seqno: i32,
+    received_mask: u32,
+    received_count: i32,
+    updated: Instant,
+}
+
+/// Receiver feedback routed to the task sending a transfer.
+enum Ack {
+    Confirm { part: i32, seqno: i32 },
+    Complete { part: i32 },
+}
+
+/// Transfer traffic of either protocol version.
+enum Part {
+    Data {
+        transfer_id: [u8; 32],
+        fec_type: FecType,
+        part: i32,
+        total_size: i64,
+        seqno: i32,
+        data: Vec<u8>,
+    },
+    Confirm {
+        transfer_id: [u8; 32],
+        part: i32,
+        max_seqno: i32,
+        received_mask: i32,
+        received_count: i32,
+    },
+    Complete {
+        transfer_id: [u8; 32],
+        part: i32,
+    },
+}
+
+impl RldpNode {
+    /// Creates an RLDP node and installs it as the handler of `adnl`.
+    pub fn new(adnl: Arc<AdnlNode>) -> Self {
+        Self::build(adnl, RldpVersion::default(), DEFAULT_RLDP_MAX_MESSAGE_BYTES)
+    }
+
+    fn build(adnl: Arc<AdnlNode>, version: RldpVersion, max_message_bytes: usize) -> Self {
+        let inner = Arc::new(RldpInner {
+            adnl,
+            version,
+            max_message_bytes,
+            handler: RwLock::new(None),
+            adnl_handler: RwLock::new(None),
+            state: Mutex::new(RldpState::default()),
+        });
+        inner
+            .adnl
+            .set_handler(Arc::new(Dispatcher(Arc::downgrade(&inner))));
+        Self { inner }
+    }
+
+    /// Sets the protocol version of outgoing queries and messages.
+    ///
+    /// Handlers are not carried over, so configure the node before calling
+    /// [`RldpNode::set_handler`].
+    pub fn with_version(self, version: RldpVersion) -> Self {
+        Self::build(
+            self.inner.adnl.clone(),
+            version,
+            self.inner.max_message_bytes,
+        )
+    }
+
+    /// Sets the largest incoming query or message accepted. Answers are
+    /// limited by the `max_answer_size` of each query instead.
+    pub fn with_max_message_bytes(self, max_message_bytes: usize) -> Self {
+        Self::build(
+            self.inner.adnl.clone(),
+            self.inner.version,
+            max_message_bytes,
+        )
+    }
+
+    pub fn adnl(&self) -> &Arc<AdnlNode> {
+        &self.inner.adnl
+    }
+
+    pub fn version(&self) -> RldpVersion {
+        self.inner.version
+    }
+
+    /// Sets the handler for incoming `rldp.query` and `rldp.message`
+    /// payloads.
+    pub fn set_handler(&self, handler: Arc<dyn AdnlMessageHandler>) {
+        *self.inner.handler.write().unwrap() = Some(handler);
+    }
+
+    /// Sets the handler for ADNL queries and custom messages that are not
+    /// RLDP traffic.
+    pub fn set_adnl_handler(&self, handler: Arc<dyn AdnlMessageHandler>) {
+        *self.inner.adnl_handler.write().unwrap() = Some(handler);
+    }
+
+    /// Sends `rldp.query` to a known peer and waits for its answer.
+    ///
+    /// The peer drops the query if its answer is larger than
+    /// `max_answer_size` bytes, which surfaces here as a timeout.
+    pub async fn query(
+        &self,
+        peer: &AdnlAddress,
+        data: Vec<u8>,
+        max_answer_size: usize,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, RldpError> {
+        let query_id = Int256::random();
+        let transfer_id = Int256::random().0;
+        let answer_id = answer_transfer_id(&transfer_id);
+        let query = tl_proto::serialize(RldpMessage::Query {
+            query_id: query_id.clone(),
+            max_answer_size: max_answer_size as i64,
+            timeout: unix_now() + timeout.as_secs().max(1) as i32,
+            data,
+        });
+        let (sender, mut receiver) = oneshot::channel();
+        self.inner.state.lock().unwrap().queries.insert(
+            answer_id,
+            PendingQuery {
+                peer: peer.clone(),
+                query_id: query_id.0,
+                max_answer_size,
+                sender,
+            },
+        );
+
+        let exchange = async {
+            let send =
+                self.inner
+                    .send_transfer(peer, transfer_id, &query, self.inner.version, timeout);
+            tokio::pin!(send);
+            tokio::select! {
+                sent = &mut send => {
+                    sent?;
+                    receiver.await.map_err(|_| AdnlError::EndOfStream.into())
+                }
+                answer = &mut receiver => answer.map_err(|_| AdnlError::EndOfStream.into()),
+            }
+        };
+        let result = tokio::time::timeout(timeout, exchange)
+            .await
+            .unwrap_or_else(|_| {
+                Err(AdnlError::Timeout {
+                    operation: "rldp_query",
+                    timeout,
+                }
+                .into())
+            });
+        if result.is_err() {
+            self.inner.state.lock().unwrap().queries.remove(&answer_id);
+        }
+        result
+    }
+
+    /// Sends `rldp.message` to a known peer and waits until the peer has
+    /// received all of it.
+    pub async fn send_message(
+        &self,
+        peer: &AdnlAddress,
+        data: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<(), RldpError> {
+        let message = tl_proto::serialize(RldpMessage::Message {
+            id: Int256::random(),
+            data,
+        });
+        self.inner
+            .send_transfer(
+                peer,
+                Int256::random().0,
+                &message,
+                self.inner.version,
+                timeout,
+            )
+            .await
+    }
+}
+
+impl RldpInner {
+    fn handler(&self) -> Option<Arc<dyn AdnlMessageHandler>> {
+        self.handler.read().unwrap().clone()
+    }
+
+    fn adnl_handler(&self) -> Option<Arc<dyn AdnlMessageHandler>> {
+        self.adnl_handler.read().unwrap().clone()
+    }
+
+    async fn send_transfer(
+        &self,
+        peer: &AdnlAddress,
+        transfer_id: [u8; 32],
+        data: &[u8],
+        version: RldpVersion,
+        timeout: Duration,
+    ) -> Result<(), RldpError> {
+        let (sender, mut acks) = mpsc::unbounded_channel();
+        self.state
+            .lock()
+            .unwrap()
+            .outbound
+            .insert(transfer_id, (peer.clone(), sender));
+        let result = tokio::time::timeout(
+            timeout,
+            self.send_parts(peer, transfer_id, data, version, &mut acks),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(AdnlError::Timeout {
+                operation: "rldp_transfer",
+                timeout,
+            }
+            .into())
+        });
+        self.state.lock().unwrap().outbound.remove(&transfer_id);
+        result
+    }
+
+    /// Streams the symbols of each part until the receiver completes it.
+    async fn send_parts(
+        &self,
+        peer: &AdnlAddress,
+        transfer_id: [u8; 32],
+        data: &[u8],
+        version: RldpVersion,
+        acks: &mut mpsc::UnboundedReceiver<Ack>,
+    ) -> Result<(), RldpError> {
+        let total_size = data.len() as i64;
+        for (part, chunk) in data.chunks(RLDP_PART_BYTES).enumerate() {
+            let part = part as i32;
+            let encoder = RaptorQEncoder::new(chunk)?;
+            let fec_type = encoder.fec_type();
+            let mut seqno = 0;
+            let mut allowed = SEND_WINDOW;
+            loop {
+                while seqno < allowed {
+                    let symbol = Part::Data {
+                        transfer_id,
+                        fec_type,
+                        part,
+                        total_size,
+                        seqno: seqno as i32,
+                        data: encoder.symbol(seqno),
+                    };
+                    self.adnl
+                        .send_custom(peer, symbol.serialize(version))
+                        .await?;
+                    seqno += 1;
+                }
+                match tokio::time::timeout(RESEND_INTERVAL, acks.recv()).await {
+                    Ok(Some(Ack::Complete { part: completed })) if completed == part => break,
+                    Ok(Some(Ack::Confirm {
+                        part: confirmed,
+                        seqno,
+                    })) if confirmed == part => {
+                        allowed = allowed.max(seqno.max(0) as u32 + 1 + SEND_WINDOW);
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) => return Err(AdnlError::EndOfStream.into()),
+                    Err(_) => allowed = seqno + RESEND_SYMBOLS,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_part(self: &Arc<Self>, peer: &AdnlAddress, version: RldpVersion, part: Part) {
+        let (transfer_id, ack) = match part {
+            Part::Confirm {
+                transfer_id,
+                part,
+                max_seqno,
+                ..
+            } => (
+                transfer_id,
+                Ack::Confirm {
+                    part,
+                    seqno: max_seqno,
+                },
+            ),
+            Part::Complete { transfer_id, part } => (transfer_id, Ack::Complete { part }),
+            Part::Data {
+                transfer_id,
+                fec_type,
+                part,
+                total_size,
+                seqno,
+                data,
+            } => {
+                let (replies, payload) =
+                    self.accept_symbol(peer, transfer_id, &fec_type, part, total_size, seqno, data);
+                for reply in replies {
+                    if let Err(error) = self.adnl.send_custom(peer, reply.serialize(version)).await
+                    {
+                        log::debug!("failed to answer RLDP transfer from {peer:?}: {error}");
+                    }
+                }
+                if let Some(payload) = payload {
+                    self.deliver(peer, transfer_id, version, payload);
+                }
+                return;
+            }
+        };
+        let state = self.state.lock().unwrap();
+        if let Some((receiver, sender)) = state.outbound.get(&transfer_id)
+            && receiver == peer
+        {
+            let _ = sender.send(ack);
+        }
+    }
+
+    /// Feeds one symbol to its transfer and returns the messages to send
+    /// back, plus the payload once the whole transfer is received.
+    #[allow(clippy::too_many_arguments)]
+    fn accept_symbol(
+        &self,
+        peer: &AdnlAddress,
+        transfer_id: [u8; 32],
+        fec_type: &FecType,
+        part: i32,
+        total_size: i64,
+        seqno: i32,
+        symbol: Vec<u8>,
+    ) -> (Vec<Part>, Option<Vec<u8>>) {
+        let complete = || vec![Part::Complete { transfer_id, part }];
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let RldpState {
+            inbound,
+            completed,
+            queries,
+            ..
+        } = &mut *state;
+        if completed.contains_key(&transfer_id) {
+            return (complete(), None);
+        }
+        if !inbound.contains_key(&transfer_id) {
+            inbound.retain(|_, transfer| now.duration_since(transfer.updated) < TRANSFER_TTL);
+            if inbound.len() >= MAX_INBOUND_TRANSFERS {
+                if !queries.contains_key(&transfer_id) {
+                    log::debug!("refusing RLDP transfer from {peer:?}: too many in flight");
+                    return (Vec::new(), None);
+                }
+                let idlest = inbound
+                    .iter()
+                    .min_by_key(|(_, transfer)| transfer.updated)
+                    .map(|(id, _)| *id);
+                if let Some(idlest) = idlest {
+                    inbound.remove(&idlest);
+                }
+            }
+        }
+        let transfer = match inbound.entry(transfer_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let limit = match queries.get(&transfer_id) {
+                    Some(query) if query.peer == *peer => query.max_answer_size,
+                    Some(_) => return (Vec::new(), None),
+                    None => self.max_message_bytes,
+                };
+                let total_size = match usize::try_from(total_size) {
+                    Ok(size) if size > 0 && size <= limit => size,
+                    _ => {
+                        log::debug!(
+                            "refusing RLDP transfer of {total_size} bytes from {peer:?}, limit is {limit}"
+                        );
+                        return (Vec::new(), None);
+                    }
+                };
+                entry.insert(InboundTransfer {
+                    peer: peer.clone(),
+                    total_size,
+                    data: Vec::new(),
+                    part: 0,
+                    decoder: None,
+                    max_seqno: 0,
+                    received_mask: 0,
+                    received_count: 0,
+                    updated: now,
+                })
+            }
+        };
+        if transfer.peer != *peer || transfer.total_size as i64 != total_size || seqno < 0 {
+            return (Vec::new(), None);
+        }
+        transfer.updated = now;
+        if part < transfer.part {
+            return (complete(), None);
+        }
+        if part > transfer.part {
+            return (Vec::new(), None);
+        }
+
+        let decoder = match &mut transfer.decoder {
+            Some(decoder) => decoder,
+            None => {
+                let remaining = transfer.total_size - transfer.data.len();
+                match FecDecoder::new(fec_type, remaining) {
+                    Ok(decoder) => transfer.decoder.insert(decoder),
+                    Err(error) => {
+                        log::debug!("dropping RLDP symbol from {peer:?}: {error}");
+                        return (Vec::new(), None);
+                    }
+                }
+            }
+        };
+        let Some(data) = decoder.add_symbol(seqno as u32, symbol) else {
+            transfer.mark_received(seqno);
+            if transfer.received_count % CONFIRM_EVERY != 0 {
+                return (Vec::new(), None);
+            }
+            let confirm = Part::Confirm {
+                transfer_id,
+                part,
+                max_seqno: transfer.max_seqno,
+                received_mask: transfer.received_mask as i32,
+                received_count: transfer.received_count,
+            };
+            return (vec![confirm], None);
+        };
+
+        transfer.data.extend_from_slice(&data);
+        transfer.part += 1;
+        transfer.decoder = None;
+        transfer.max_seqno = 0;
+        transfer.received_mask = 0;
+        transfer.received_count = 0;
+        if transfer.data.len() < transfer.total_size {
+            return (complete(), None);
+        }
+
+        let transfer = inbound.remove(&transfer_id).expect("transfer is present");
+        completed.retain(|_, finished| now.duration_since(*finished) < TRANSFER_TTL);
+        completed.insert(transfer_id, now);
+        (complete(), Some(transfer.data))
+    }
+
+    /// Dispatches a received payload without blocking the ADNL receive loop.
+    fn deliver(
+        self: &Arc<Self>,
+        peer: &AdnlAddress,
+        transfer_id: [u8; 32],
+        version: RldpVersion,
+        payload: Vec<u8>,
+    ) {
+        let message = match tl_proto::deserialize::<RldpMessage>(&payload) {
+            Ok(message) => message,
+            Err(error) => {
+                log::debug!("dropping invalid RLDP payload from {peer:?}: {error}");
+                return;
+            }
+        };
+        match message {
+            RldpMessage::Answer { query_id, data } => {
+                let mut state = self.state.lock().unwrap();
+                if let Entry::Occupied(entry) = state.queries.entry(transfer_id)
+                    && entry.get().peer == *peer
+                    && entry.get().query_id == query_id.0
+                {
+                    let _ = entry.remove().sender.send(data);
+                }
+            }
+            RldpMessage::Query {
+                query_id,
+                max_answer_size,
+                timeout,
+                data,
+            } => {
+                let now = unix_now();
+                if timeout < now {
+                    log::debug!("dropping expired RLDP query from {peer:?}");
+                    return;
+                }
+                let Some(handler) = self.handler() else {
+                    return;
+                };
+                let inner = self.clone();
+                let peer = peer.clone();
+                tokio::spawn(async move {
+                    let Some(answer) = handler.handle_query(&peer, &data).await else {
+                        return;
+                    };
+                    let answer = tl_proto::serialize(RldpMessage::Answer {
+                        query_id,
+                        data: answer,
+                    });
+                    if answer.len() as i64 > max_answer_size {
+                        log::debug!(
+                            "dropping RLDP answer of {} bytes to {peer:?}, limit is {max_answer_size}",
+                            answer.len()
+                        );
+                        return;
+                    }
+                    let timeout = answer_timeout(timeout, now);
+                    let answer_id = answer_transfer_id(&transfer_id);
+                    if let Err(error) = inner
+                        .send_transfer(&peer, answer_id, &answer, version, timeout)
+                        .await
+                    {
+                        log::debug!("failed to send RLDP answer to {peer:?}: {error}");
+                    }
+                });
+            }
+            RldpMessage::Message { data, .. } => {
+                let Some(handler) = self.handler() else {
+                    return;
+                };
+                let peer = peer.clone();
+                tokio::spawn(async move { handler.handle_custom(&peer, &data).await });
+            }
+        }
+    }
+}
+
+/// Time left to send an answer to a query due at `deadline`, capped by
+/// [`MAX_ANSWER_TIMEOUT`] so a peer cannot keep the transfer alive for long.
+pub(super) fn answer_timeout(deadline: i32, now: i32) -> Duration {
+    let left = (i64::from(deadline) - i64::from(now)).max(1) as u64;
+    Duration::from_secs(left).min(MAX_ANSWER_TIMEOUT)
+}
+
+impl InboundTransfer {
+    /// Updates the `rldp2.confirm` window with a symbol that did not finish
+    /// the part.
+    fn mark_received(&mut self, seqno: i32) {
+        if seqno > self.max_seqno {
+            let shift = (seqno - self.max_seqno) as u32;
+            self.received_mask = self.received_mask.checked_shl(shift).unwrap_or(0);
+            self.max_seqno = seqno;
+        }
+        if let Some(bit) = 1u32.checked_shl((self.max_seqno - seqno) as u32) {
+            self.received_mask |= bit;
+        }
+        self.received_count += 1;
+    }
+}
+
+impl Part {
+    fn parse(data: &[u8]) -> Option<(RldpVersion, Self)> {
+        if let Ok(part) = tl_proto::deserialize::<RldpMessagePart>(data) {
+            let part = match part {
+                RldpMessagePart::MessagePart {
+                    transfer_id,
+                    fec_type,
+                    part,
+                    total_size,
+                    seqno,
+                    data,
+                } => Self::Data {
+                    transfer_id: transfer_id.0,
+                    fec_type,
+                    part,
+                    total_size,
+                    seqno,
+                    data,
+                },
+                RldpMessagePart::Confirm {
+                    transfer_id,
+                    part,
+                    seqno,
+                } => Self::Confirm {
+                    transfer_id: transfer_id.0,
+                    part,
+                    max_seqno: seqno,
+                    received_mask: 0,
+                    received_count: 0,
+                },
+                RldpMessagePart::Complete { transfer_id, part } => Self::Complete {
+                    transfer_id: transfer_id.0,
+                    part,
+                },
+            };
+            return Some((RldpVersion::V1, part));
+        }
+        let part = match tl_proto::deserialize::<Rldp2MessagePart>(data).ok()? {
+            Rldp2MessagePart::MessagePart {
+                transfer_id,
+                fec_type,
+                part,
+                total_size,
+                seqno,
+                data,
+            } => Self::Data {
+                transfer_id: transfer_id.0,
+                fec_type,
+                part,
+                total_size,
+                seqno,
+                data,
+            },
+            Rldp2MessagePart::Confirm {
+                transfer_id,
+                part,
+                max_seqno,
+                received_mask,
+                received_count,
+            } => Self::Confirm {
+                transfer_id: transfer_id.0,
+                part,
+                max_seqno,
+                received_mask,
+                received_count,
+            },
+            Rldp2MessagePart::Complete { transfer_id, part } => Self::Complete {
+                transfer_id: transfer_id.0,
+                part,
+            },
+        };
+        Some((RldpVersion::V2, part))
+    }
+
+    fn serialize(self, version: RldpVersion) -> Vec<u8> {
+        match (version, self) {
+            (
+                RldpVersion::V1,
+                Self::Data {
+                    transfer_id,
+                    fec_type,
+                    part,
+                    total_size,
+                    seqno,
+                    data,
+                },
+            ) => tl_proto::serialize(RldpMessagePart::MessagePart {
+                transfer_id: Int256(transfer_id),
+                fec_type,
+                part,
+                total_size,
+                seqno,
+                data,
+            }),
+            (
+                RldpVersion::V1,
+                Self::Confirm {
+                    transfer_id,
+                    part,
+                    max_seqno,
+                    ..
+                },
+            ) => tl_proto::serialize(RldpMessagePart::Confirm {
+                transfer_id: Int256(transfer_id),
+                part,
+                seqno: max_seqno,
+            }),
+            (RldpVersion::V1, Self::Complete { transfer_id, part }) => {
+                tl_proto::serialize(RldpMessagePart::Complete {
+                    transfer_id: Int256(transfer_id),
+                    part,
+                })
+            }
+            (
+                RldpVersion::V2,
+                Self::Data {
+                    transfer_id,
+                    fec_type,
+                    part,
+                    total_size,
+                    seqno,
+                    data,
+                },
+            ) => tl_proto::serialize(Rldp2MessagePart::MessagePart {
+                transfer_id: Int256(transfer_id),
+                fec_type,
+                part,
+                total_size,
+                seqno,
+                data,
+            }),
+            (
+                RldpVersion::V2,
+                Self::Confirm {
+                    transfer_id,
+                    part,
+                    max_seqno,
+                    received_mask,
+                    received_count,
+                },
+            ) => tl_proto::serialize(Rldp2MessagePart::Confirm {
+                transfer_id: Int256(transfer_id),
+                part,
+                max_seqno,
+                received_mask,
+                received_count,
+            }),
+            (RldpVersion::V2, Self::Complete { transfer_id, part }) => {
+                tl_proto::serialize(Rldp2MessagePart::Complete {
+                    transfer_id: Int256(transfer_id),
+                    part,
+                })
+            }
+        }
+    }
+}
+
+/// Answers travel in a transfer whose id is the query transfer id with every
+/// bit flipped.
+fn answer_transfer_id(transfer_id: &[u8; 32]) -> [u8; 32] {
+    transfer_id.map(|byte| byte ^ 0xff)
+}
+
+/// ADNL handler that feeds RLDP traffic to its node.
+struct Dispatcher(Weak<RldpInner>);
+
+#[async_trait]
+impl AdnlMessageHandler for Dispatcher {
+    async fn handle_query(&self, peer: &AdnlAddress, query: &[u8]) -> Option<Vec<u8>> {
+        let handler = self.0.upgrade()?.adnl_handler()?;
+        handler.handle_query(peer, query).await
+    }
+
+    async fn handle_custom(&self, peer: &AdnlAddress, data: &[u8]) {
+        let Some(inner) = self.0.upgrade() else {
+            return;
+        };
+        match Part::parse(data) {
+            Some((version, part)) => inner.handle_part(peer, version, part).await,
+            None => {
+                if let Some(handler) = inner.adnl_handler() {
+                    handler.handle_custom(peer, data).await;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use super::node::{MAX_ANSWER_TIMEOUT, MAX_INBOUND_TRANSFERS, answer_timeout};
+use super::*;
+use crate::adnl::crypto::KeyPair;
+use crate::adnl::{AdnlAddress, AdnlMessageHandler, AdnlNode};
+use crate::tl::common::Int256;
+use async_trait::async_trait;
+use rand::rngs::OsRng;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Answers every query with `size` bytes derived from the query.
+struct Repeat {
+    size: usize,
+    messages: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+#[async_trait]
+impl AdnlMessageHandler for Repeat {
+    async fn handle_query(&self, _peer: &AdnlAddress, query: &[u8]) -> Option<Vec<u8>> {
+        Some(query.iter().copied().cycle().take(self.size).collect())
+    }
+
+    async fn handle_custom(&self, _peer: &AdnlAddress, data: &[u8]) {
+        self.messages.send(data.to_vec()).unwrap();
+    }
+}
+
+async fn pair(version: RldpVersion) -> (RldpNode, RldpNode, AdnlAddress) {
+    let client = AdnlNode::bind("127.0.0.1:0", KeyPair::generate(&mut OsRng))
+        .await
+        .unwrap();
+    let server = AdnlNode::bind("127.0.0.1:0", KeyPair::generate(&mut OsRng))
+        .await
+        .unwrap();
+    let server_id = client.add_peer(*server.public_key(), server.local_addr().unwrap());
+    let client = RldpNode::new(Arc::new(client)).with_version(version);
+    let server = RldpNode::new(Arc::new(server));
+    (client, server, server_id)
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 + i / 251) as u8).collect()
+}
+
+#[test]
+fn raptorq_recovers_part_from_repair_symbols() {
+    let data = pattern(100_000);
+    let encoder = RaptorQEncoder::new(&data).unwrap();
+    let count = encoder.symbols_count();
+    assert_eq!(count, 131);
+    assert_eq!(
+        encoder.fec_type(),
+        FecType::RaptorQ {
+            data_size: 100_000,
+            symbol_size: 768,
+            symbols_count: 131,
+        }
+    );
+
+    // Lose every third source symbol and make up for it with repair symbols.
+    let mut decoder = FecDecoder::new(&encoder.fec_type(), data.len()).unwrap();
+    let mut decoded = None;
+    for seqno in (0..count)
+        .filter(|seqno| seqno % 3 != 0)
+        .chain(count..count * 2)
+    {
+        if let Some(part) = decoder.add_symbol(seqno, encoder.symbol(seqno)) {
+            decoded = Some(part);
+            break;
+        }
+    }
+    assert_eq!(decoded.unwrap(), data);
+}
+
+#[test]
+fn round_robin_decoder_collects_source_symbols() {
+    let fec_type = FecType::RoundRobin {
+        data_size: 5,
+        symbol_size: 2,
+        symbols_count: 3,
+    };
+    let mut decoder = FecDecoder::new(&fec_type, 5).unwrap();
+    assert_eq!(decoder.add_symbol(4, b"cd".to_vec()), None);
+    assert_eq!(decoder.add_symbol(0, b"ab".to_vec()), None);
+    assert_eq!(decoder.add_symbol(1, b"xx".to_vec()), None);
+    assert_eq!(
+        decoder.add_symbol(5, b"e\0".to_vec()),
+        Some(b"abcde".to_vec())
+    );
+}
+
+#[test]
+fn fec_decoder_rejects_inconsistent_parameters() {
+    let raptorq = |data_size, symbol_size, symbols_count| FecType::RaptorQ {
+        data_size,
+        symbol_size,
+        symbols_count,
+    };
+    assert!(FecDecoder::new(&raptorq(1000, 768, 2), 1000).is_ok());
+    for fec_type in [
+        raptorq(1000, 768, 3),
+        raptorq(0, 768, 0),
+        raptorq(1000, 0, 2),
+        raptorq(-1, 768, 1),
+        raptorq(2000, 768, 3),
+        FecType::Online {
+            data_size: 1000,
+            symbol_size: 768,
+            symbols_count: 2,
+        },
+    ] {
+        assert!(
+            matches!(FecDecoder::new(&fec_type, 1000), Err(RldpError::Fec(_))),
+            "{fec_type:?}"
+        );
+    }
+}
+
+#[test]
+fn answer_timeout_is_capped_by_the_node() {
+    let now = 1_700_000_000;
+    assert_eq!(answer_timeout(now + 5, now), Duration::from_secs(5));
+    assert_eq!(answer_timeout(now, now), Duration::from_secs(1));
+    assert_eq!(answer_timeout(now + 86_400, now), MAX_ANSWER_TIMEOUT);
+    assert_eq!(answer_timeout(i32::MAX, i32::MIN), MAX_ANSWER_TIMEOUT);
+}
+
+#[test]
+fn part_constructor_ids_match_schema() {
+    let transfer_id = Int256([1; 32]);
+    let complete = tl_proto::serialize(RldpMessagePart::Complete {
+        transfer_id: transfer_id.clone(),
+        part: 2,
+    });
+    assert_eq!(&complete[..4], &0xbc0cb2bfu32.to_le_bytes());
+    assert_eq!(&complete[36..], &2i32.to_le_bytes());
+
+    let part = Rldp2MessagePart::MessagePart {
+        transfer_id,
+        fec_type: FecType::RaptorQ {
+            data_size: 1,
+            symbol_size: 768,
+            symbols_count: 1,
+        },
+        part: 0,
+        total_size: 1,
+        seqno: 0,
+        data: vec![0; 768],
+    };
+    let bytes = tl_proto::serialize(&part);
+    assert_eq!(&bytes[..4], &0x11480b6eu32.to_le_bytes());
+    assert_eq!(&bytes[36..40], &0x8b93a7e0u32.to_le_bytes());
+    assert_eq!(
+        tl_proto::deserialize::<Rldp2MessagePart>(&bytes).unwrap(),
+        part
+    );
+}
+
+async fn query_large_answer(version: RldpVersion) {
+    let (client, server, server_id) = pair(version).await;
+    let (messages, _messages) = mpsc::unbounded_channel();
+    let size = 2 * RLDP_PART_BYTES + 12_345;
+    server.set_handler(Arc::new(Repeat { size, messages }));
+
+    let answer = client
+        .query(&server_id, b"abc".to_vec(), size + 64, TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(answer.len(), size);
+    assert!(answer.starts_with(b"abcabc"));
+    assert!(
+        client
+            .adnl()
+            .has_channel(&AdnlAddress::from(server.adnl().public_key()))
+    );
+}
+
+#[tokio::test]
+async fn v1_query_transfers_multi_part_answer() {
+    query_large_answer(RldpVersion::V1).await;
+}
+
+#[tokio::test]
+async fn v2_query_transfers_multi_part_answer() {
+    query_large_answer(RldpVersion::V2).await;
+}
+
+#[tokio::test]
+async fn answers_above_max_answer_size_are_dropped() {
+    let (client, server, server_id) = pair(RldpVersion::V2).await;
+    let (messages, _messages) = mpsc::unbounded_channel();
+    server.set_handler(Arc::new(Repeat {
+        size: 10_000,
+        messages,
+    }));
+
+    let result = client
+        .query(&server_id, b"x".to_vec(), 1_000, Duration::from_secs(1))
+        .await;
+    assert!(matches!(
+        result,
+        Err(RldpError::Adnl(AdnlError::Timeout { .. }))
+    ));
+}
+
+#[tokio::test]
+async fn messages_and_plain_adnl_traffic_reach_their_handlers() {
+    let (client, server, server_id) = pair(RldpVersion::V1).await;
+    let (messages, mut received) = mpsc::unbounded_channel();
+    server.set_handler(Arc::new(Repeat { size: 0, messages }));
+    let (plain, mut plain_received) = mpsc::unbounded_channel();
+    server.set_adnl_handler(Arc::new(Repeat {
+        size: 4,
+        messages: plain,
+    }));
+
+    let message = pattern(200_000);
+    client
+        .send_message(&server_id, message.clone(), TIMEOUT)
+        .await
+        .unwrap();
+    let delivered = tokio::time::timeout(TIMEOUT, received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivered, message);
+
+    let answer = client
+        .adnl()
+        .query(&server_id, b"ab".to_vec(), TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(answer, b"abab");
+    client
+        .adnl()
+        .send_custom(&server_id, b"plain".to_vec())
+        .await
+        .unwrap();
+    let delivered = tokio::time::timeout(TIMEOUT, plain_received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivered, b"plain");
+}
+
+#[tokio::test]
+async fn oversized_messages_are_refused() {
+    let (client, server, server_id) = pair(RldpVersion::V2).await;
+    let server = server.with_max_message_bytes(1_000);
+    let (messages, _messages) = mpsc::unbounded_channel();
+    server.set_handler(Arc::new(Repeat { size: 0, messages }));
+
+    let result = client
+        .send_message(&server_id, pattern(10_000), Duration::from_millis(500))
+        .await;
+    assert!(matches!(
+        result,
+        Err(RldpError::Adnl(AdnlError::Timeout { .. }))
+    ));
+}
+
+#[tokio::test]
+async fn stalled_transfers_are_capped_without_dropping_query_answers() {
+    let (client, server, server_id) = pair(RldpVersion::V2).await;
+    let (messages, _messages) = mpsc::unbounded_channel();
+    client.set_handler(Arc::new(Repeat { size: 3, messages }));
+    let (messages, _messages) = mpsc::unbounded_channel();
+    server.set_handler(Arc::new(Repeat { size: 0, messages }));
+
+    // First symbols of two-symbol transfers that never finish.
+    for index in 0..MAX_INBOUND_TRANSFERS + 8 {
+        let mut transfer_id = [0; 32];
+        transfer_id[..8].copy_from_slice(&(index as u64).to_le_bytes());
+        let part = Rldp2MessagePart::MessagePart {
+            transfer_id: Int256(transfer_id),
+            fec_type: FecType::RaptorQ {
+                data_size: 2 * RLDP_SYMBOL_BYTES as i32,
+                symbol_size: RLDP_SYMBOL_BYTES as i32,
+                symbols_count: 2,
+            },
+            part: 0,
+            total_size: 2 * RLDP_SYMBOL_BYTES as i64,
+            seqno: 0,
+            data: vec![0; RLDP_SYMBOL_BYTES],
+        };
+        client
+            .adnl()
+            .send_custom(&server_id, tl_proto::serialize(&part))
+            .await
+            .unwrap();
+        // Paced so the loopback socket buffer does not drop datagrams.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let refused = client
+        .send_message(&server_id, pattern(100), Duration::from_millis(500))
+        .await;
+    assert!(matches!(
+        refused,
+        Err(RldpError::Adnl(AdnlError::Timeout { .. }))
+    ));
+
+    let client_id = AdnlAddress::from(client.adnl().public_key());
+    let answer = server
+        .query(&client_id, b"xy".to_vec(), 64, TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(answer, b"xyx");
+}
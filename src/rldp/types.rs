@@ -0,0 +1,135 @@
+use derivative::Derivative;
+use tl_proto::{TlRead, TlWrite};
+
+use crate::tl::common::Int256;
+use crate::tl::utils::fmt_bytes;
+
+/// `fec.Type`: forward error correction parameters of one transfer part.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, Copy, PartialEq, Eq)]
+#[tl(boxed)]
+pub enum FecType {
+    /// fec.raptorQ data_size:int symbol_size:int symbols_count:int = fec.Type;
+    #[tl(id = 0x8b93a7e0)]
+    RaptorQ {
+        data_size: i32,
+        symbol_size: i32,
+        symbols_count: i32,
+    },
+    /// fec.roundRobin data_size:int symbol_size:int symbols_count:int = fec.Type;
+    #[tl(id = 0x32f528e4)]
+    RoundRobin {
+        data_size: i32,
+        symbol_size: i32,
+        symbols_count: i32,
+    },
+    /// fec.online data_size:int symbol_size:int symbols_count:int = fec.Type;
+    #[tl(id = 0x0127660c)]
+    Online {
+        data_size: i32,
+        symbol_size: i32,
+        symbols_count: i32,
+    },
+}
+
+impl FecType {
+    /// Size of the part data protected by these parameters.
+    pub fn data_size(&self) -> i32 {
+        match *self {
+            Self::RaptorQ { data_size, .. }
+            | Self::RoundRobin { data_size, .. }
+            | Self::Online { data_size, .. } => data_size,
+        }
+    }
+}
+
+/// `rldp.MessagePart`: RLDP v1 transfer traffic carried in
+/// `adnl.message.custom`.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum RldpMessagePart {
+    /// rldp.messagePart transfer_id:int256 fec_type:fec.Type part:int total_size:long seqno:int data:bytes = rldp.MessagePart;
+    #[tl(id = 0x185c22cc)]
+    MessagePart {
+        transfer_id: Int256,
+        fec_type: FecType,
+        part: i32,
+        total_size: i64,
+        seqno: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+    },
+    /// rldp.confirm transfer_id:int256 part:int seqno:int = rldp.MessagePart;
+    #[tl(id = 0xf582dc58)]
+    Confirm {
+        transfer_id: Int256,
+        part: i32,
+        seqno: i32,
+    },
+    /// rldp.complete transfer_id:int256 part:int = rldp.MessagePart;
+    #[tl(id = 0xbc0cb2bf)]
+    Complete { transfer_id: Int256, part: i32 },
+}
+
+/// `rldp2.MessagePart`: RLDP v2 transfer traffic carried in
+/// `adnl.message.custom`.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum Rldp2MessagePart {
+    /// rldp2.messagePart transfer_id:int256 fec_type:fec.Type part:int total_size:long seqno:int data:bytes = rldp2.MessagePart;
+    #[tl(id = 0x11480b6e)]
+    MessagePart {
+        transfer_id: Int256,
+        fec_type: FecType,
+        part: i32,
+        total_size: i64,
+        seqno: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+    },
+    /// rldp2.confirm transfer_id:int256 part:int max_seqno:int received_mask:int received_count:int = rldp2.MessagePart;
+    #[tl(id = 0x23e69945)]
+    Confirm {
+        transfer_id: Int256,
+        part: i32,
+        max_seqno: i32,
+        received_mask: i32,
+        received_count: i32,
+    },
+    /// rldp2.complete transfer_id:int256 part:int = rldp2.MessagePart;
+    #[tl(id = 0x36b9081f)]
+    Complete { transfer_id: Int256, part: i32 },
+}
+
+/// `rldp.Message`: the payload reassembled from a transfer.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum RldpMessage {
+    /// rldp.message id:int256 data:bytes = rldp.Message;
+    #[tl(id = 0x7d1bcd1e)]
+    Message {
+        id: Int256,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+    },
+    /// rldp.query query_id:int256 max_answer_size:long timeout:int data:bytes = rldp.Message;
+    #[tl(id = 0x8a794d69)]
+    Query {
+        query_id: Int256,
+        max_answer_size: i64,
+        /// Unix time after which the query is dropped.
+        timeout: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+    },
+    /// rldp.answer query_id:int256 data:bytes = rldp.Message;
+    #[tl(id = 0xa3fc5c03)]
+    Answer {
+        query_id: Int256,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+    },
+}
@@ -2,6 +2,7 @@ use derivative::Derivative;
 use tl_proto::{TlRead, TlWrite};
 
 use super::common::*;
+use super::utils::fmt_bytes;
 
 #[derive(TlRead, TlWrite, Derivative)]
 #[derivative(Debug, Clone, PartialEq)]
@@ -23,3 +24,66 @@ pub enum Message {
     #[tl(id = 0xdc69fb03)]
     Pong { random_id: u64 },
 }
+
+/// `adnl.Message` constructors carried by UDP `adnl.packetContents`.
+///
+/// Unlike `Message`, which also covers the TCP ping constructors used by
+/// LiteAPI connections, this enum holds the datagram-only channel, custom,
+/// and multipart messages.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(boxed)]
+pub enum AdnlMessage {
+    /// adnl.message.createChannel key:int256 date:int = adnl.Message;
+    #[tl(id = 0xe673c3bb)]
+    CreateChannel { key: Int256, date: i32 },
+
+    /// adnl.message.confirmChannel key:int256 peer_key:int256 date:int = adnl.Message;
+    #[tl(id = 0x60dd1d69)]
+    ConfirmChannel {
+        key: Int256,
+        peer_key: Int256,
+        date: i32,
+    },
+
+    /// adnl.message.custom data:bytes = adnl.Message;
+    #[tl(id = 0x204818f5)]
+    Custom {
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+    },
+
+    /// adnl.message.nop = adnl.Message;
+    #[tl(id = 0x17f8dfda)]
+    Nop,
+
+    /// adnl.message.reinit date:int = adnl.Message;
+    #[tl(id = 0x10c20520)]
+    Reinit { date: i32 },
+
+    /// adnl.message.query query_id:int256 query:bytes = adnl.Message;
+    #[tl(id = 0xb48bf97a)]
+    Query {
+        query_id: Int256,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        query: Vec<u8>,
+    },
+
+    /// adnl.message.answer query_id:int256 answer:bytes = adnl.Message;
+    #[tl(id = 0x0fac8416)]
+    Answer {
+        query_id: Int256,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        answer: Vec<u8>,
+    },
+
+    /// adnl.message.part hash:int256 total_size:int offset:int data:bytes = adnl.Message;
+    #[tl(id = 0xfd452d39)]
+    Part {
+        hash: Int256,
+        total_size: i32,
+        offset: i32,
+        #[derivative(Debug(format_with = "fmt_bytes"))]
+        data: Vec<u8>,
+    },
+}
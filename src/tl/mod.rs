@@ -23,7 +23,7 @@ pub mod utils;
 mod tests;
 
 // Re-export commonly used types
-pub use adnl::Message;
+pub use adnl::{AdnlMessage, Message};
 pub use common::{
     AccountId, BlockId, BlockIdExt, Int256, NonfinalCandidateId, NonfinalCandidateInfo,
     ZeroStateIdExt,
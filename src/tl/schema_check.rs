@@ -14,6 +14,138 @@ mod tests {
             .collect()
     }
 
+    fn ton_api_ids() -> HashMap<&'static str, u32> {
+        let scheme = Scheme::parse(include_str!("schemas/ton_api.tl")).expect("valid ton_api.tl");
+        scheme
+            .functions
+            .values()
+            .chain(scheme.types.values())
+            .map(|constructor| (constructor.variant, constructor.compute_tl_id()))
+            .collect()
+    }
+
+    #[test]
+    fn test_adnl_udp_constructor_ids_match_schema() {
+        let ids = ton_api_ids();
+        let expected = [
+            ("adnl.message.createChannel", 0xe673c3bb),
+            ("adnl.message.confirmChannel", 0x60dd1d69),
+            ("adnl.message.custom", 0x204818f5),
+            ("adnl.message.nop", 0x17f8dfda),
+            ("adnl.message.reinit", 0x10c20520),
+            ("adnl.message.query", 0xb48bf97a),
+            ("adnl.message.answer", 0x0fac8416),
+            ("adnl.message.part", 0xfd452d39),
+            ("adnl.packetContents", 0xd142cd89),
+            ("adnl.address.udp", 0x670da6e7),
+            ("adnl.address.udp6", 0xe31d63fa),
+            ("pub.ed25519", 0x4813b4c6),
+            ("pub.aes", 0x2dbcadd4),
+        ];
+
+        for (name, expected_id) in expected {
+            assert_eq!(ids.get(name).copied(), Some(expected_id), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_dht_constructor_ids_match_schema() {
+        let ids = ton_api_ids();
+        let expected = [
+            ("dht.node", 0x84533248),
+            ("dht.nodes", 0x7974a0be),
+            ("dht.key", 0xf667de8f),
+            ("dht.updateRule.signature", 0xcc9f31f7),
+            ("dht.updateRule.anybody", 0x61578e14),
+            ("dht.updateRule.overlayNodes", 0x26779383),
+            ("dht.keyDescription", 0x281d4e05),
+            ("dht.value", 0x90ad27cb),
+            ("dht.pong", 0x5a8aef81),
+            ("dht.valueNotFound", 0xa2620568),
+            ("dht.valueFound", 0xe40cf774),
+            ("dht.stored", 0x7026fb08),
+            ("dht.ping", 0xcbeb3f18),
+            ("dht.store", 0x34934212),
+            ("dht.findNode", 0x6ce2ce6b),
+            ("dht.findValue", 0xae4b6011),
+            ("dht.getSignedAddressList", 0xa97948ed),
+            ("adnl.addressList", 0x2227e658),
+            ("overlay.node.toSign", 0x03d8a8e1),
+            ("overlay.nodes", 0xe487290e),
+        ];
+
+        for (name, expected_id) in expected {
+            assert_eq!(ids.get(name).copied(), Some(expected_id), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_rldp_constructor_ids_match_schema() {
+        let ids = ton_api_ids();
+        let expected = [
+            ("fec.raptorQ", 0x8b93a7e0),
+            ("fec.roundRobin", 0x32f528e4),
+            ("fec.online", 0x0127660c),
+            ("rldp.messagePart", 0x185c22cc),
+            ("rldp.confirm", 0xf582dc58),
+            ("rldp.complete", 0xbc0cb2bf),
+            ("rldp2.messagePart", 0x11480b6e),
+            ("rldp2.confirm", 0x23e69945),
+            ("rldp2.complete", 0x36b9081f),
+            ("rldp.message", 0x7d1bcd1e),
+            ("rldp.query", 0x8a794d69),
+            ("rldp.answer", 0xa3fc5c03),
+        ];
+
+        for (name, expected_id) in expected {
+            assert_eq!(ids.get(name).copied(), Some(expected_id), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_overlay_constructor_ids_match_schema() {
+        let ids = ton_api_ids();
+        let expected = [
+            ("pub.overlay", 0x34ba45cb),
+            ("tonNode.shardPublicOverlayId", 0x4d9ed329),
+            ("overlay.node", 0xb86b8a83),
+            ("overlay.query", 0xccfd8443),
+            ("overlay.message", 0x75252420),
+            ("overlay.getRandomPeers", 0x48ee64ab),
+            ("overlay.certificate", 0xe09ed731),
+            ("overlay.certificateV2", 0xb43f9c83),
+            ("overlay.emptyCertificate", 0x32dabccf),
+            ("overlay.broadcast", 0xb15a2b6b),
+            ("overlay.broadcastFec", 0xbad7c36a),
+            ("overlay.broadcastFecShort", 0xf1881342),
+            ("overlay.unicast", 0x33534e24),
+            ("overlay.fec.received", 0xd55c14ec),
+            ("overlay.fec.completed", 0x09d76914),
+            ("overlay.broadcastNotFound", 0x95863624),
+            ("overlay.broadcast.id", 0x51fd789a),
+            ("overlay.broadcastFec.id", 0xfb3155a6),
+            ("overlay.broadcastFec.partId", 0xa46962d0),
+            ("overlay.broadcast.toSign", 0xfa374e7c),
+        ];
+
+        for (name, expected_id) in expected {
+            assert_eq!(ids.get(name).copied(), Some(expected_id), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_mempool_constructor_ids_match_schema() {
+        let ids = ton_api_ids();
+        assert_eq!(
+            ids.get("tonNode.externalMessageBroadcast").copied(),
+            Some(0x3d1b1867)
+        );
+        assert_eq!(
+            ids.get("tonNode.externalMessage").copied(),
+            Some(0xdc75a209)
+        );
+    }
+
     #[test]
     fn test_lite_api_request_constructor_ids_match_schema() {
         let ids = lite_api_ids();
@@ -10,6 +10,10 @@ use crate::tlb::{Result, TlbDeserialize, TlbError, TlbSerialize, expect_tag, sto
 use crate::tvm::{BitKey, Builder, Cell, HashmapE, Slice};
 use std::sync::Arc;
 
+mod config;
+
+pub use config::*;
+
 const BLOCK_TAG: u32 = 0x11ef55aa;
 const VALUE_FLOW_TAG: u32 = 0xb8e48dfb;
 const VALUE_FLOW_V2_TAG: u32 = 0x3ebf98b7;
@@ -428,6 +432,8 @@ pub enum ConfigParamValue {
     Param34(Arc<Cell>),
     /// Config parameter 36.
     Param36(Arc<Cell>),
+    /// Config parameter 43.
+    Param43(Arc<Cell>),
     /// Unknown config parameter preserved as raw cell.
     Unknown { id: u32, raw: Arc<Cell> },
 }
@@ -524,6 +530,32 @@ impl ConfigParams {
                 message: error.to_string(),
             })
     }
+
+    /// Decodes config parameter 18.
+    pub fn storage_prices(&self) -> Result<Option<StoragePricesConfig>> {
+        self.typed_param(18)
+    }
+
+    /// Decodes config parameter 20 for the masterchain or 21 for other
+    /// workchains.
+    pub fn gas_prices(&self, masterchain: bool) -> Result<Option<GasLimitsPrices>> {
+        self.typed_param(if masterchain { 20 } else { 21 })
+    }
+
+    /// Decodes config parameter 24 for the masterchain or 25 for other
+    /// workchains.
+    pub fn msg_forward_prices(&self, masterchain: bool) -> Result<Option<MsgForwardPrices>> {
+        self.typed_param(if masterchain { 24 } else { 25 })
+    }
+
+    /// Decodes config parameter 43.
+    pub fn size_limits(&self) -> Result<Option<SizeLimitsConfig>> {
+        self.typed_param(43)
+    }
+
+    fn typed_param<T: TlbDeserialize>(&self, id: u32) -> Result<Option<T>> {
+        self.raw_param(id)?.map(T::from_cell).transpose()
+    }
 }
 
 impl ConfigParamValue {
@@ -542,6 +574,7 @@ impl ConfigParamValue {
             32 => Self::Param32(raw),
             34 => Self::Param34(raw),
             36 => Self::Param36(raw),
+            43 => Self::Param43(raw),
             _ => Self::Unknown { id, raw },
         }
     }
@@ -580,9 +613,9 @@ impl MerkleProof {
         }
     }
 
-    /// Verifies that the child root hash matches the stored virtual hash.
+    /// Verifies that the child level-0 hash matches the stored virtual hash.
     pub fn verify_virtual_hash(&self) -> bool {
-        self.virtual_root.hash() == self.virtual_hash
+        self.virtual_root.hash_at_level(0) == self.virtual_hash
     }
 }
 
@@ -630,9 +663,9 @@ impl MerkleUpdate {
         }
     }
 
-    /// Verifies that child root hashes match the stored virtual hashes.
+    /// Verifies that child level-0 hashes match the stored virtual hashes.
     pub fn verify_virtual_hashes(&self) -> bool {
-        self.old.hash() == self.old_hash && self.new.hash() == self.new_hash
+        self.old.hash_at_level(0) == self.old_hash && self.new.hash_at_level(0) == self.new_hash
     }
 }
 
@@ -733,4 +766,133 @@ mod tests {
         assert_eq!(cell.bit_len(), 8 + 256 + 256);
         assert_eq!(HashUpdate::from_cell(cell).unwrap(), update);
     }
+
+    #[test]
+    fn fee_config_params_roundtrip_every_constructor() {
+        let storage = StoragePricesConfig {
+            prices: vec![
+                StoragePrices {
+                    utime_since: 0,
+                    bit_price_ps: 1,
+                    cell_price_ps: 500,
+                    mc_bit_price_ps: 1000,
+                    mc_cell_price_ps: 500_000,
+                },
+                StoragePrices {
+                    utime_since: 1_700_000_000,
+                    bit_price_ps: 2,
+                    cell_price_ps: 1000,
+                    mc_bit_price_ps: 2000,
+                    mc_cell_price_ps: 1_000_000,
+                },
+            ],
+        };
+        let cell = storage.to_cell().unwrap();
+        assert_eq!(StoragePricesConfig::from_cell(cell).unwrap(), storage);
+
+        let plain = GasLimitsPrices {
+            flat: None,
+            gas_price: 655_360_000,
+            gas_limit: 1_000_000,
+            special_gas_limit: None,
+            gas_credit: 10_000,
+            block_gas_limit: 10_000_000,
+            freeze_due_limit: 100_000_000,
+            delete_due_limit: 1_000_000_000,
+        };
+        let flat_ext = GasLimitsPrices {
+            flat: Some(FlatGasPrice {
+                gas_limit: 100,
+                gas_price: 40_000,
+            }),
+            special_gas_limit: Some(35_000_000),
+            ..plain
+        };
+        let cell = plain.to_cell().unwrap();
+        assert_eq!(cell.bit_len(), 8 + 6 * 64);
+        assert_eq!(GasLimitsPrices::from_cell(cell).unwrap(), plain);
+        let cell = flat_ext.to_cell().unwrap();
+        assert_eq!(cell.bit_len(), 8 + 2 * 64 + 8 + 7 * 64);
+        assert_eq!(GasLimitsPrices::from_cell(cell).unwrap(), flat_ext);
+
+        let forward = MsgForwardPrices {
+            lump_price: 400_000,
+            bit_price: 26_214_400,
+            cell_price: 2_621_440_000,
+            ihr_price_factor: 98_304,
+            first_frac: 21_845,
+            next_frac: 21_845,
+        };
+        let cell = forward.to_cell().unwrap();
+        assert_eq!(MsgForwardPrices::from_cell(cell).unwrap(), forward);
+
+        let limits = SizeLimitsConfig {
+            max_msg_bits: 2_097_152,
+            max_msg_cells: 8192,
+            max_library_cells: 1000,
+            max_vm_data_depth: 512,
+            max_ext_msg_size: 65_535,
+            max_ext_msg_depth: 512,
+            v2: None,
+        };
+        let limits_v2 = SizeLimitsConfig {
+            v2: Some(SizeLimitsV2 {
+                max_acc_state_cells: 65_536,
+                max_acc_state_bits: 67_043_328,
+                max_acc_public_libraries: 256,
+                defer_out_queue_size_limit: 256,
+                max_msg_extra_currencies: 2,
+                max_acc_fixed_prefix_length: 8,
+            }),
+            ..limits
+        };
+        for limits in [limits, limits_v2] {
+            let cell = limits.to_cell().unwrap();
+            assert_eq!(SizeLimitsConfig::from_cell(cell).unwrap(), limits);
+        }
+    }
+
+    #[test]
+    fn gas_limits_prices_rejects_unknown_constructor() {
+        let mut builder = Builder::new();
+        builder.store_u8(0xd1).unwrap();
+        builder.store_u64(100).unwrap();
+        builder.store_u64(40_000).unwrap();
+        builder.store_u8(0xd1).unwrap();
+        let err = GasLimitsPrices::from_cell(builder.build().unwrap()).unwrap_err();
+        assert!(matches!(err, TlbError::TagMismatch { .. }));
+    }
+
+    #[test]
+    fn config_params_decode_typed_fee_params() {
+        let forward = MsgForwardPrices {
+            lump_price: 1,
+            bit_price: 2,
+            cell_price: 3,
+            ihr_price_factor: 4,
+            first_frac: 5,
+            next_frac: 6,
+        };
+        let mut dict = HashmapE::new(CONFIG_PARAMS_KEY_BITS);
+        dict.insert_bit_key(
+            BitKey::from_u64(25, CONFIG_PARAMS_KEY_BITS).unwrap(),
+            forward.to_cell().unwrap(),
+        )
+        .unwrap();
+        let mut config = Builder::new();
+        config
+            .store_hashmap_e_with(&dict, |builder, value| {
+                builder.store_ref(value.clone())?;
+                Ok(())
+            })
+            .unwrap();
+        let params = ConfigParams {
+            config_addr: [0; 32],
+            config: config.build().unwrap(),
+        };
+
+        assert_eq!(params.msg_forward_prices(false).unwrap(), Some(forward));
+        assert_eq!(params.msg_forward_prices(true).unwrap(), None);
+        assert_eq!(params.size_limits().unwrap(), None);
+    }
 }
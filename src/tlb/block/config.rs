@@ -0,0 +1,314 @@
+//! Typed config parameters used for fee and size limit calculations.
+
+use super::{BitKey, Builder, HashmapE, Result, Slice, TlbDeserialize, TlbError, TlbSerialize};
+use crate::tlb::{expect_tag, store_tag};
+
+const STORAGE_PRICES_KEY_BITS: usize = 32;
+
+/// TL-B `storage_prices#cc utime_since:uint32 bit_price_ps:uint64
+/// cell_price_ps:uint64 mc_bit_price_ps:uint64 mc_cell_price_ps:uint64 = StoragePrices`.
+///
+/// Prices are in nanotons per bit or cell per second, scaled by `2^16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoragePrices {
+    /// Unix time from which these prices apply.
+    pub utime_since: u32,
+    pub bit_price_ps: u64,
+    pub cell_price_ps: u64,
+    pub mc_bit_price_ps: u64,
+    pub mc_cell_price_ps: u64,
+}
+
+/// Config parameter 18: `_ (Hashmap 32 StoragePrices) = ConfigParam 18`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoragePricesConfig {
+    /// Price periods in dictionary key order.
+    pub prices: Vec<StoragePrices>,
+}
+
+/// Flat part of `gas_flat_pfx#d1 flat_gas_limit:uint64 flat_gas_price:uint64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlatGasPrice {
+    /// Gas covered by `gas_price`.
+    pub gas_limit: u64,
+    /// Nanotons charged for the first `gas_limit` gas units.
+    pub gas_price: u64,
+}
+
+/// Config parameters 20 (masterchain) and 21 (basechain): `GasLimitsPrices`.
+///
+/// Covers `gas_prices#dd`, `gas_prices_ext#de`, and either of them behind the
+/// `gas_flat_pfx#d1` prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasLimitsPrices {
+    /// Flat price prefix, when present.
+    pub flat: Option<FlatGasPrice>,
+    /// Nanotons per gas unit, scaled by `2^16`.
+    pub gas_price: u64,
+    pub gas_limit: u64,
+    /// Gas limit for special accounts; present in `gas_prices_ext#de`.
+    pub special_gas_limit: Option<u64>,
+    pub gas_credit: u64,
+    pub block_gas_limit: u64,
+    pub freeze_due_limit: u64,
+    pub delete_due_limit: u64,
+}
+
+/// Config parameters 24 (masterchain) and 25 (basechain):
+/// TL-B `msg_forward_prices#ea lump_price:uint64 bit_price:uint64 cell_price:uint64
+/// ihr_price_factor:uint32 first_frac:uint16 next_frac:uint16 = MsgForwardPrices`.
+///
+/// `bit_price` and `cell_price` are in nanotons scaled by `2^16`; the
+/// fractions are parts of `2^16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsgForwardPrices {
+    pub lump_price: u64,
+    pub bit_price: u64,
+    pub cell_price: u64,
+    pub ihr_price_factor: u32,
+    /// Share of the forward fee kept by the sending validators.
+    pub first_frac: u16,
+    /// Share kept by each transit hop.
+    pub next_frac: u16,
+}
+
+/// Config parameter 43: `size_limits_config#01` or `size_limits_config_v2#02`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimitsConfig {
+    pub max_msg_bits: u32,
+    pub max_msg_cells: u32,
+    pub max_library_cells: u32,
+    pub max_vm_data_depth: u16,
+    pub max_ext_msg_size: u32,
+    pub max_ext_msg_depth: u16,
+    /// Fields added by `size_limits_config_v2#02`.
+    pub v2: Option<SizeLimitsV2>,
+}
+
+/// Account and queue limits of `size_limits_config_v2#02`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimitsV2 {
+    pub max_acc_state_cells: u32,
+    pub max_acc_state_bits: u32,
+    pub max_acc_public_libraries: u32,
+    pub defer_out_queue_size_limit: u32,
+    pub max_msg_extra_currencies: u32,
+    pub max_acc_fixed_prefix_length: u8,
+}
+
+impl TlbSerialize for StoragePrices {
+    fn store_tlb(&self, builder: &mut Builder) -> Result<()> {
+        store_tag(builder, "11001100")?;
+        builder.store_u32(self.utime_since)?;
+        builder.store_u64(self.bit_price_ps)?;
+        builder.store_u64(self.cell_price_ps)?;
+        builder.store_u64(self.mc_bit_price_ps)?;
+        builder.store_u64(self.mc_cell_price_ps)?;
+        Ok(())
+    }
+}
+
+impl TlbDeserialize for StoragePrices {
+    fn load_tlb(slice: &mut Slice) -> Result<Self> {
+        expect_tag(slice, "StoragePrices", "11001100")?;
+        Ok(Self {
+            utime_since: slice.load_u32()?,
+            bit_price_ps: slice.load_u64()?,
+            cell_price_ps: slice.load_u64()?,
+            mc_bit_price_ps: slice.load_u64()?,
+            mc_cell_price_ps: slice.load_u64()?,
+        })
+    }
+}
+
+impl TlbSerialize for StoragePricesConfig {
+    /// Stores the non-empty `Hashmap 32` root directly in the builder.
+    fn store_tlb(&self, builder: &mut Builder) -> Result<()> {
+        if self.prices.is_empty() {
+            return Err(TlbError::CustomSchema {
+                schema: "ConfigParam 18",
+                message: "storage prices dictionary must not be empty".to_string(),
+            });
+        }
+        let mut dict = HashmapE::new(STORAGE_PRICES_KEY_BITS);
+        for (index, prices) in self.prices.iter().enumerate() {
+            dict.insert_bit_key(
+                BitKey::from_u64(index as u64, STORAGE_PRICES_KEY_BITS)?,
+                *prices,
+            )?;
+        }
+        let mut wrapper = Builder::new();
+        wrapper.store_hashmap_e_with(&dict, |builder, prices| {
+            prices.store_tlb(builder).map_err(anyhow::Error::from)
+        })?;
+        let root = wrapper.build()?.references()[0].clone();
+        builder.store_cell(&root)?;
+        Ok(())
+    }
+}
+
+impl TlbDeserialize for StoragePricesConfig {
+    fn load_tlb(slice: &mut Slice) -> Result<Self> {
+        // The param cell is the hashmap root; wrap it as a `HashmapE` to reuse
+        // the dictionary decoder.
+        let root = super::consume_remaining_cell(slice)?;
+        let mut wrapper = Builder::new();
+        wrapper.store_bit(true)?;
+        wrapper.store_ref(root)?;
+        let dict = Slice::new(wrapper.build()?)
+            .load_hashmap_e_with(STORAGE_PRICES_KEY_BITS, |slice| {
+                StoragePrices::load_tlb(slice).map_err(anyhow::Error::from)
+            })?;
+        Ok(Self {
+            prices: dict.iter().map(|(_, prices)| *prices).collect(),
+        })
+    }
+}
+
+impl TlbSerialize for GasLimitsPrices {
+    fn store_tlb(&self, builder: &mut Builder) -> Result<()> {
+        if let Some(flat) = &self.flat {
+            store_tag(builder, "11010001")?;
+            builder.store_u64(flat.gas_limit)?;
+            builder.store_u64(flat.gas_price)?;
+        }
+        match self.special_gas_limit {
+            Some(special_gas_limit) => {
+                store_tag(builder, "11011110")?;
+                builder.store_u64(self.gas_price)?;
+                builder.store_u64(self.gas_limit)?;
+                builder.store_u64(special_gas_limit)?;
+            }
+            None => {
+                store_tag(builder, "11011101")?;
+                builder.store_u64(self.gas_price)?;
+                builder.store_u64(self.gas_limit)?;
+            }
+        }
+        builder.store_u64(self.gas_credit)?;
+        builder.store_u64(self.block_gas_limit)?;
+        builder.store_u64(self.freeze_due_limit)?;
+        builder.store_u64(self.delete_due_limit)?;
+        Ok(())
+    }
+}
+
+impl TlbDeserialize for GasLimitsPrices {
+    fn load_tlb(slice: &mut Slice) -> Result<Self> {
+        let mut flat = None;
+        let mut tag = slice.load_u8()?;
+        if tag == 0xd1 {
+            flat = Some(FlatGasPrice {
+                gas_limit: slice.load_u64()?,
+                gas_price: slice.load_u64()?,
+            });
+            tag = slice.load_u8()?;
+        }
+        let (gas_price, gas_limit, special_gas_limit) = match tag {
+            0xdd => (slice.load_u64()?, slice.load_u64()?, None),
+            0xde => (
+                slice.load_u64()?,
+                slice.load_u64()?,
+                Some(slice.load_u64()?),
+            ),
+            _ => {
+                return Err(TlbError::TagMismatch {
+                    constructor: "GasLimitsPrices",
+                    expected_bits: "dd|de",
+                    actual_bits: format!("{tag:02x}"),
+                });
+            }
+        };
+        Ok(Self {
+            flat,
+            gas_price,
+            gas_limit,
+            special_gas_limit,
+            gas_credit: slice.load_u64()?,
+            block_gas_limit: slice.load_u64()?,
+            freeze_due_limit: slice.load_u64()?,
+            delete_due_limit: slice.load_u64()?,
+        })
+    }
+}
+
+impl TlbSerialize for MsgForwardPrices {
+    fn store_tlb(&self, builder: &mut Builder) -> Result<()> {
+        store_tag(builder, "11101010")?;
+        builder.store_u64(self.lump_price)?;
+        builder.store_u64(self.bit_price)?;
+        builder.store_u64(self.cell_price)?;
+        builder.store_u32(self.ihr_price_factor)?;
+        builder.store_u16(self.first_frac)?;
+        builder.store_u16(self.next_frac)?;
+        Ok(())
+    }
+}
+
+impl TlbDeserialize for MsgForwardPrices {
+    fn load_tlb(slice: &mut Slice) -> Result<Self> {
+        expect_tag(slice, "MsgForwardPrices", "11101010")?;
+        Ok(Self {
+            lump_price: slice.load_u64()?,
+            bit_price: slice.load_u64()?,
+            cell_price: slice.load_u64()?,
+            ihr_price_factor: slice.load_u32()?,
+            first_frac: slice.load_u16()?,
+            next_frac: slice.load_u16()?,
+        })
+    }
+}
+
+impl TlbSerialize for SizeLimitsConfig {
+    fn store_tlb(&self, builder: &mut Builder) -> Result<()> {
+        builder.store_u8(if self.v2.is_some() { 0x02 } else { 0x01 })?;
+        builder.store_u32(self.max_msg_bits)?;
+        builder.store_u32(self.max_msg_cells)?;
+        builder.store_u32(self.max_library_cells)?;
+        builder.store_u16(self.max_vm_data_depth)?;
+        builder.store_u32(self.max_ext_msg_size)?;
+        builder.store_u16(self.max_ext_msg_depth)?;
+        if let Some(v2) = &self.v2 {
+            builder.store_u32(v2.max_acc_state_cells)?;
+            builder.store_u32(v2.max_acc_state_bits)?;
+            builder.store_u32(v2.max_acc_public_libraries)?;
+            builder.store_u32(v2.defer_out_queue_size_limit)?;
+            builder.store_u32(v2.max_msg_extra_currencies)?;
+            builder.store_u8(v2.max_acc_fixed_prefix_length)?;
+        }
+        Ok(())
+    }
+}
+
+impl TlbDeserialize for SizeLimitsConfig {
+    fn load_tlb(slice: &mut Slice) -> Result<Self> {
+        let tag = slice.load_u8()?;
+        if tag != 0x01 && tag != 0x02 {
+            return Err(TlbError::TagMismatch {
+                constructor: "SizeLimitsConfig",
+                expected_bits: "01|02",
+                actual_bits: format!("{tag:02x}"),
+            });
+        }
+        let mut limits = Self {
+            max_msg_bits: slice.load_u32()?,
+            max_msg_cells: slice.load_u32()?,
+            max_library_cells: slice.load_u32()?,
+            max_vm_data_depth: slice.load_u16()?,
+            max_ext_msg_size: slice.load_u32()?,
+            max_ext_msg_depth: slice.load_u16()?,
+            v2: None,
+        };
+        if tag == 0x02 {
+            limits.v2 = Some(SizeLimitsV2 {
+                max_acc_state_cells: slice.load_u32()?,
+                max_acc_state_bits: slice.load_u32()?,
+                max_acc_public_libraries: slice.load_u32()?,
+                defer_out_queue_size_limit: slice.load_u32()?,
+                max_msg_extra_currencies: slice.load_u32()?,
+                max_acc_fixed_prefix_length: slice.load_u8()?,
+            });
+        }
+        Ok(limits)
+    }
+}
@@ -1,6 +1,6 @@
 use super::*;
 
-use crate::tvm::{Builder, Cell, HashmapE, Slice};
+use crate::tvm::{Builder, Cell, HashmapE, PrunedBranchError, Slice};
 use num_bigint::BigUint;
 use std::sync::Arc;
 use thiserror::Error;
@@ -10,8 +10,9 @@ pub use tonutils_macros::{Tlb, Tlb as TlbDerive};
 
 pub use block::{
     Block, BlockExtra, BlockIdExtTlb, BlockInfo, BlockPrevInfo, ConfigParams, ExtBlkRef,
-    HashUpdate, McBlockExtra, MerkleProof, MerkleUpdate, ShardIdent, ShardState, ShardStateUnsplit,
-    ValueFlow,
+    FlatGasPrice, GasLimitsPrices, HashUpdate, McBlockExtra, MerkleProof, MerkleUpdate,
+    MsgForwardPrices, ShardIdent, ShardState, ShardStateUnsplit, SizeLimitsConfig, SizeLimitsV2,
+    StoragePrices, StoragePricesConfig, ValueFlow,
 };
 pub use message::{
     AccStatusChange, Anycast, CommonMsgInfo, CommonMsgInfoRelaxed, CurrencyCollection, Grams,
@@ -86,6 +87,18 @@ pub enum TlbError {
     },
 }
 
+impl TlbError {
+    /// Returns the pruned branch that stopped decoding, when a value was
+    /// read from a Merkle proof that left it out.
+    pub fn pruned_branch(&self) -> Option<&PrunedBranchError> {
+        match self {
+            Self::Tvm(error) => error.downcast_ref(),
+            Self::InvalidReferencePayload { source, .. } => source.pruned_branch(),
+            _ => None,
+        }
+    }
+}
+
 /// Trait for values that can be serialized into TL-B cell data.
 pub trait TlbSerialize {
     /// Stores this value into the provided cell builder.
@@ -106,7 +119,11 @@ pub trait TlbDeserialize: Sized {
 
     /// Deserializes this value from a standalone cell and requires exact
     /// consumption of all bits and references.
+    ///
+    /// A pruned branch fails with [`TlbError::pruned_branch`] set rather than
+    /// a tag mismatch.
     fn from_cell(cell: Arc<Cell>) -> Result<Self> {
+        PrunedBranchError::check(&cell).map_err(anyhow::Error::from)?;
         let mut slice = Slice::new(cell);
         let value = Self::load_tlb(&mut slice)?;
         ensure_empty(&slice)?;
@@ -821,6 +821,32 @@ impl TlbDeserialize for Message {
     }
 }
 
+impl Message {
+    /// TEP-467 normalized hash of an external-in message: the hash of the
+    /// message rebuilt with an `addr_none` source, zero import fee, no
+    /// `init`, and the body in a reference.
+    ///
+    /// The hash does not depend on how the sender laid out the message, so it
+    /// identifies the message inside the transaction that processed it.
+    /// Returns `None` for internal and external-out messages.
+    pub fn normalized_hash(&self) -> Result<Option<[u8; 32]>> {
+        let CommonMsgInfo::ExternalIn { dest, .. } = &self.info else {
+            return Ok(None);
+        };
+        let (Either::Left(body) | Either::Right(body)) = &self.body;
+        let normalized = Self {
+            info: CommonMsgInfo::ExternalIn {
+                src: MsgAddressExt::None,
+                dest: dest.clone(),
+                import_fee: Grams::from(0),
+            },
+            init: None,
+            body: Either::Right(body.clone()),
+        };
+        Ok(Some(normalized.to_cell()?.hash()))
+    }
+}
+
 /// Hand-written TL-B `MessageRelaxed Any`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MessageRelaxed {
@@ -380,6 +380,52 @@ pub(super) fn message_with_referenced_body_roundtrips() {
     );
 }
 
+#[test]
+pub(super) fn normalized_hash_ignores_source_import_fee_init_and_body_layout() {
+    let body = cell_with_bits(&[0xAB, 0xC0], 10);
+    let dest = MsgAddressInt::std(std_address(0x11));
+    let mut expected = Builder::new();
+    store_tag(&mut expected, "1000").unwrap();
+    dest.store_tlb(&mut expected).unwrap();
+    store_tag(&mut expected, "0000").unwrap();
+    store_tag(&mut expected, "01").unwrap();
+    expected.store_ref(body.clone()).unwrap();
+    let expected = expected.build().unwrap().hash();
+
+    let plain = Message {
+        info: ext_in_info(),
+        init: None,
+        body: Either::Right(body.clone()),
+    };
+    let decorated = Message {
+        info: CommonMsgInfo::ExternalIn {
+            src: MsgAddressExt::Extern {
+                data: vec![0xF0],
+                bit_len: 4,
+            },
+            dest,
+            import_fee: Grams::from(9),
+        },
+        init: Some(Either::Left(StateInit::empty())),
+        body: Either::Left(body),
+    };
+    assert_eq!(plain.normalized_hash().unwrap(), Some(expected));
+    assert_eq!(decorated.normalized_hash().unwrap(), Some(expected));
+    assert_ne!(decorated.to_cell().unwrap().hash(), expected);
+
+    let external_out = Message {
+        info: CommonMsgInfo::ExternalOut {
+            src: MsgAddressInt::std(std_address(0x11)),
+            dest: MsgAddressExt::None,
+            created_lt: 1,
+            created_at: 2,
+        },
+        init: None,
+        body: Either::Right(cell_with_bits(&[], 0)),
+    };
+    assert_eq!(external_out.normalized_hash().unwrap(), None);
+}
+
 #[test]
 pub(super) fn exact_message_decode_rejects_trailing_data_after_referenced_body() {
     let body = cell_with_bits(&[0xAB], 8);
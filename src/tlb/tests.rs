@@ -143,6 +143,27 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn pruned_reference_reports_the_missing_cell() {
+        let child = Tiny { value: 9 }.to_cell().unwrap();
+        let mut parent = Builder::new();
+        parent
+            .store_ref(crate::tvm::pruned_branch(&child, 0).unwrap())
+            .unwrap();
+        let mut slice = Slice::new(parent.build().unwrap());
+
+        let err = load_ref_tlb::<Tiny>(&mut slice, "tiny_ref").unwrap_err();
+        let pruned = err.pruned_branch().unwrap();
+        assert_eq!(pruned.hash, child.hash());
+        assert_eq!(pruned.depth, 0);
+
+        let mut trailing = Builder::new();
+        Tiny { value: 9 }.store_tlb(&mut trailing).unwrap();
+        trailing.store_bit(false).unwrap();
+        let err = Tiny::from_cell(trailing.build().unwrap()).unwrap_err();
+        assert!(err.pruned_branch().is_none());
+    }
+
     #[test]
     fn var_uint_accepts_canonical_zero_and_non_zero() {
         let mut builder = Builder::new();
@@ -777,6 +777,16 @@ pub enum TrComputePhase {
     },
 }
 
+impl TrComputePhase {
+    /// VM exit code, or `None` when execution was skipped.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            Self::Vm { exit_code, .. } => Some(*exit_code),
+            Self::Skipped { .. } => None,
+        }
+    }
+}
+
 impl TlbSerialize for TrComputePhase {
     fn store_tlb(&self, builder: &mut Builder) -> Result<()> {
         match self {
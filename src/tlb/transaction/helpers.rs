@@ -244,6 +244,43 @@ pub enum TransactionDescr {
     },
 }
 
+impl TransactionDescr {
+    /// Compute phase, for descriptions that run the VM.
+    pub fn compute_phase(&self) -> Option<&TrComputePhase> {
+        match self {
+            Self::Ordinary { compute_ph, .. }
+            | Self::TickTock { compute_ph, .. }
+            | Self::SplitPrepare { compute_ph, .. }
+            | Self::MergeInstall { compute_ph, .. } => Some(compute_ph),
+            Self::Storage { .. } | Self::SplitInstall { .. } | Self::MergePrepare { .. } => None,
+        }
+    }
+
+    /// Action phase, when the compute phase produced one.
+    pub fn action_phase(&self) -> Option<&TrActionPhase> {
+        match self {
+            Self::Ordinary { action, .. }
+            | Self::TickTock { action, .. }
+            | Self::SplitPrepare { action, .. }
+            | Self::MergeInstall { action, .. } => action.as_ref(),
+            Self::Storage { .. } | Self::SplitInstall { .. } | Self::MergePrepare { .. } => None,
+        }
+    }
+
+    /// Whether the transaction was aborted; descriptions without the flag
+    /// never abort.
+    pub fn aborted(&self) -> bool {
+        match self {
+            Self::Ordinary { aborted, .. }
+            | Self::TickTock { aborted, .. }
+            | Self::SplitPrepare { aborted, .. }
+            | Self::MergePrepare { aborted, .. }
+            | Self::MergeInstall { aborted, .. } => *aborted,
+            Self::Storage { .. } | Self::SplitInstall { .. } => false,
+        }
+    }
+}
+
 impl TlbSerialize for TransactionDescr {
     fn store_tlb(&self, builder: &mut Builder) -> Result<()> {
         match self {
@@ -286,6 +286,32 @@ fn ordinary_description_roundtrips_with_referenced_action() {
     assert_eq!(roundtrip(&value), value);
 }
 
+#[test]
+fn description_phase_accessors_cover_vm_and_storage_descriptions() {
+    let ordinary = TransactionDescr::Ordinary {
+        credit_first: false,
+        storage_ph: None,
+        credit_ph: None,
+        compute_ph: compute_vm(),
+        action: Some(action_phase()),
+        aborted: true,
+        bounce: None,
+        destroyed: false,
+    };
+    assert_eq!(ordinary.compute_phase(), Some(&compute_vm()));
+    assert_eq!(compute_vm().exit_code(), Some(-14));
+    assert_eq!(compute_skipped().exit_code(), None);
+    assert_eq!(ordinary.action_phase(), Some(&action_phase()));
+    assert!(ordinary.aborted());
+
+    let storage = TransactionDescr::Storage {
+        storage_ph: storage_phase(),
+    };
+    assert_eq!(storage.compute_phase(), None);
+    assert_eq!(storage.action_phase(), None);
+    assert!(!storage.aborted());
+}
+
 #[test]
 fn storage_only_description_roundtrips() {
     let value = TransactionDescr::Storage {
@@ -11,9 +11,12 @@ pub(super) use std::sync::Arc;
 mod api;
 mod convert;
 mod layout;
+mod lazy;
 mod parse;
 mod serialize;
 #[cfg(test)]
 mod tests;
 
 pub use api::*;
+pub use lazy::{LazyBoc, LazyCell, LazySlice};
+pub use serialize::BocWriter;
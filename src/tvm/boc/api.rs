@@ -34,14 +34,33 @@ impl BocInspection {
 
 /// Serializes a cell and its references into a Bag of Cells (BoC) format
 pub fn serialize_boc(root: &Arc<Cell>, has_crc32: bool) -> Result<Vec<u8>> {
+    serialize_boc_roots(std::slice::from_ref(root), has_crc32)
+}
+
+/// Serializes several root cells into one Bag of Cells, sharing common
+/// subtrees. Roots keep their order in the BoC root index.
+pub fn serialize_boc_roots(roots: &[Arc<Cell>], has_crc32: bool) -> Result<Vec<u8>> {
+    if roots.is_empty() {
+        bail!("BoC must contain at least one root");
+    }
+
     // Collect all unique cells
-    let cells = collect_cells(root)?;
+    let mut cells = Vec::new();
+    let mut visited = HashMap::new();
+    for root in roots {
+        collect_cells_recursive(root, &mut cells, &mut visited)?;
+    }
 
-    // Find the root index in the cells vector
-    let root_index = cells
+    // Find the root indexes in the cells vector
+    let root_indexes = roots
         .iter()
-        .position(|cell| cell.hash() == root.hash())
-        .ok_or_else(|| anyhow::anyhow!("Root cell not found in collected cells"))?;
+        .map(|root| {
+            visited
+                .get(&cell_hash(root))
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Root cell not found in collected cells"))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
     // Serialize each cell
     let mut serialized_cells = Vec::new();
@@ -79,8 +98,8 @@ pub fn serialize_boc(root: &Arc<Cell>, has_crc32: bool) -> Result<Vec<u8>> {
     // Number of cells
     write_uint(&mut result, cells.len(), size_bytes);
 
-    // Number of roots (always 1 for now)
-    write_uint(&mut result, 1, size_bytes);
+    // Number of roots
+    write_uint(&mut result, roots.len(), size_bytes);
 
     // Number of absent cells (always 0)
     write_uint(&mut result, 0, size_bytes);
@@ -88,17 +107,19 @@ pub fn serialize_boc(root: &Arc<Cell>, has_crc32: bool) -> Result<Vec<u8>> {
     // Total cells size
     write_uint(&mut result, cells_size, offset_bytes);
 
-    // Root cell index
-    write_uint(&mut result, root_index, size_bytes);
+    // Root cell indexes
+    for root_index in root_indexes {
+        write_uint(&mut result, root_index, size_bytes);
+    }
 
     // Append serialized cells
     for cell_data in serialized_cells {
         result.extend_from_slice(&cell_data);
     }
 
-    // Add CRC32 if requested
+    // Add CRC32C if requested
     if has_crc32 {
-        let crc = crate::crc::CRC32.checksum(&result);
+        let crc = crate::crc::CRC32C.checksum(&result);
         result.extend_from_slice(&crc.to_le_bytes());
     }
 
@@ -152,7 +173,7 @@ pub fn inspect_boc(data: &[u8]) -> Result<BocInspection> {
 }
 
 pub(super) fn deserialize_boc_generic_roots(data: &[u8]) -> Result<Vec<Arc<Cell>>> {
-    let layout = parse_boc_generic_layout(data, true)?;
+    let layout = parse_boc_generic_layout(data)?;
     let cells = parse_cells(layout.cells_data, layout.cells_count, layout.size_bytes)?;
 
     Ok(layout
@@ -163,7 +184,7 @@ pub(super) fn deserialize_boc_generic_roots(data: &[u8]) -> Result<Vec<Arc<Cell>
 }
 
 pub(super) fn inspect_boc_generic(data: &[u8]) -> Result<BocInspection> {
-    let layout = parse_boc_generic_layout(data, false)?;
+    let layout = parse_boc_generic_layout(data)?;
     let cells = parse_raw_cells(layout.cells_data, layout.cells_count, layout.size_bytes)?;
     let hashes = compute_raw_cell_hashes(&cells)?;
 
@@ -177,16 +198,20 @@ pub(super) fn inspect_boc_generic(data: &[u8]) -> Result<BocInspection> {
 }
 
 pub(super) struct BocGenericLayout<'a> {
-    cells_count: usize,
-    size_bytes: usize,
-    root_indices: Vec<usize>,
-    cells_data: &'a [u8],
+    pub(super) cells_count: usize,
+    pub(super) size_bytes: usize,
+    pub(super) offset_bytes: usize,
+    pub(super) root_indices: Vec<usize>,
+    /// Position of the index table in the BoC, when it has one.
+    pub(super) index_start: Option<usize>,
+    /// Whether index table entries carry a cache bit below the offset.
+    pub(super) has_cache_bits: bool,
+    /// Position of the first cell in the BoC.
+    pub(super) cells_start: usize,
+    pub(super) cells_data: &'a [u8],
 }
 
-pub(super) fn parse_boc_generic_layout(
-    data: &[u8],
-    reject_cache_bits: bool,
-) -> Result<BocGenericLayout<'_>> {
+pub(super) fn parse_boc_generic_layout(data: &[u8]) -> Result<BocGenericLayout<'_>> {
     let mut pos = 4; // Skip magic
 
     if pos >= data.len() {
@@ -202,11 +227,10 @@ pub(super) fn parse_boc_generic_layout(
     let has_cache_bits = (flags_and_size & 0x20) != 0;
     let size_bytes = (flags_and_size & 0x07) as usize;
 
-    if has_cache_bits && reject_cache_bits {
-        bail!("BoC cache bits flag is unsupported for ordinary-cell decoding");
-    }
-    if has_cache_bits {
-        bail!("BoC cache bits flag is unsupported for structural inspection");
+    // Cache bits are stored in the index table, which a BoC without one
+    // cannot carry.
+    if has_cache_bits && !has_idx {
+        bail!("BoC cache bits flag is unsupported without an index table");
     }
 
     if size_bytes == 0 || size_bytes > 8 {
@@ -249,11 +273,13 @@ pub(super) fn parse_boc_generic_layout(
         root_indices.push(root_idx);
     }
 
+    let index_start = has_idx.then_some(pos);
     if has_idx {
         let mut previous_offset = 0usize;
         for index in 0..cells_count {
             let offset = read_uint(data, &mut pos, offset_bytes)
-                .map_err(|_| anyhow::anyhow!("Malformed BoC index table"))?;
+                .map_err(|_| anyhow::anyhow!("Malformed BoC index table"))?
+                >> usize::from(has_cache_bits);
             if offset < previous_offset || offset > cells_size {
                 bail!("Malformed BoC index table");
             }
@@ -274,7 +300,7 @@ pub(super) fn parse_boc_generic_layout(
     let payload_end = data
         .len()
         .checked_sub(checksum_size)
-        .ok_or_else(|| anyhow::anyhow!("Missing CRC32"))?;
+        .ok_or_else(|| anyhow::anyhow!("Missing CRC32C"))?;
 
     if cells_end > payload_end {
         bail!("Invalid cells size");
@@ -285,10 +311,10 @@ pub(super) fn parse_boc_generic_layout(
         bail!("Trailing bytes after BoC cell payload");
     }
 
-    // Verify CRC32 if present
+    // Verify CRC32C if present
     if has_crc32 {
         if data.len() < cells_end + 4 {
-            bail!("Missing CRC32");
+            bail!("Missing CRC32C");
         }
         let expected_crc = u32::from_le_bytes([
             data[cells_end],
@@ -296,10 +322,10 @@ pub(super) fn parse_boc_generic_layout(
             data[cells_end + 2],
             data[cells_end + 3],
         ]);
-        let actual_crc = crate::crc::CRC32.checksum(&data[..cells_end]);
+        let actual_crc = crate::crc::CRC32C.checksum(&data[..cells_end]);
         if expected_crc != actual_crc {
             bail!(
-                "CRC32 mismatch: expected 0x{:08x}, got 0x{:08x}",
+                "CRC32C mismatch: expected 0x{:08x}, got 0x{:08x}",
                 expected_crc,
                 actual_crc
             );
@@ -309,7 +335,11 @@ pub(super) fn parse_boc_generic_layout(
     Ok(BocGenericLayout {
         cells_count,
         size_bytes,
+        offset_bytes,
         root_indices,
+        index_start,
+        has_cache_bits,
+        cells_start,
         cells_data: &data[cells_start..cells_end],
     })
 }
@@ -482,8 +512,8 @@ pub(super) fn parse_cells(
         // Parse descriptor 1
         let ref_count = (d1 & 0x07) as usize;
         let is_exotic = (d1 & 0x08) != 0;
-        let level = (d1 >> 5) & 0x03;
-        if d1 & 0x10 != 0 || d1 & 0x80 != 0 {
+        let level_mask = d1 >> 5;
+        if d1 & 0x10 != 0 {
             bail!("Invalid cell descriptor: reserved bits are set");
         }
 
@@ -510,7 +540,7 @@ pub(super) fn parse_cells(
         }
         cell_refs.push(refs);
         cell_is_exotic.push(is_exotic);
-        cell_levels.push(level);
+        cell_levels.push(level_mask);
 
         // Calculate bit length from descriptor d2
         // d2 = floor(b/8) + ceil(b/8)
@@ -593,51 +623,71 @@ pub(super) fn build_parsed_cell(
         )?);
     }
 
-    let cell = if cell_is_exotic[index] {
-        Cell::with_exotic_data(
-            cell_raw_data[index].clone(),
-            cell_bit_lens[index],
-            references,
-        )
-        .map_err(|err| anyhow::anyhow!("Invalid exotic cell: {}", err))?
+    let cell = assemble_cell(
+        cell_is_exotic[index],
+        cell_levels[index],
+        cell_raw_data[index].clone(),
+        cell_bit_lens[index],
+        references,
+    )?;
+    cells[index] = Some(cell.clone());
+    states[index] = ParsedCellState::Done;
+    Ok(cell)
+}
+
+/// Builds a decoded cell and checks the level mask its descriptor declared.
+pub(super) fn assemble_cell(
+    is_exotic: bool,
+    level_mask: u8,
+    data: Vec<u8>,
+    bit_len: usize,
+    references: Vec<Arc<Cell>>,
+) -> Result<Arc<Cell>> {
+    let cell = if is_exotic {
+        Cell::with_exotic_data(data, bit_len, references)
+            .map_err(|err| anyhow::anyhow!("Invalid exotic cell: {}", err))?
     } else {
-        let mut cell = Cell::with_data(cell_raw_data[index].clone(), cell_bit_lens[index])?;
+        let mut cell = Cell::with_data(data, bit_len)?;
         for reference in references {
             cell.add_reference(reference)?;
         }
         cell
     };
 
-    if cell.level() != cell_levels[index] {
+    if cell.level_mask() != level_mask {
         bail!(
-            "Invalid cell descriptor level: expected {}, got {}",
-            cell.level(),
-            cell_levels[index]
+            "Invalid cell descriptor level mask: expected {}, got {}",
+            cell.level_mask(),
+            level_mask
         );
     }
 
-    let cell = Arc::new(cell);
-    cells[index] = Some(cell.clone());
-    states[index] = ParsedCellState::Done;
-    Ok(cell)
+    Ok(Arc::new(cell))
 }
 
 pub(super) fn decode_cell_data(data: &[u8], d2: u8) -> Result<(Vec<u8>, usize)> {
+    let bit_len = decode_bit_len(data, d2)?;
+    let mut cell_data = data.to_vec();
+    if !bit_len.is_multiple_of(8) {
+        let last_idx = cell_data.len() - 1;
+        cell_data[last_idx] &= 0xFFu8 << (8 - bit_len % 8);
+    }
+    Ok((cell_data, bit_len))
+}
+
+/// Bit length of serialized cell data, found from the top-up bit of a
+/// partial last byte.
+pub(super) fn decode_bit_len(data: &[u8], d2: u8) -> Result<usize> {
     let data_size = (d2 as usize).div_ceil(2);
     if data.len() != data_size {
         bail!("Cell data size does not match descriptor");
     }
 
-    if d2 == 0 {
-        return Ok((Vec::new(), 0));
-    }
-
     if d2.is_multiple_of(2) {
-        return Ok((data.to_vec(), (d2 as usize / 2) * 8));
+        return Ok((d2 as usize / 2) * 8);
     }
 
-    let mut cell_data = data.to_vec();
-    let last_byte = *cell_data
+    let last_byte = *data
         .last()
         .ok_or_else(|| anyhow::anyhow!("Partial cell data is missing top-up byte"))?;
     if last_byte == 0 {
@@ -652,12 +702,7 @@ pub(super) fn decode_cell_data(data: &[u8], d2: u8) -> Result<(Vec<u8>, usize)>
         bail!("Malformed partial cell data: top-up bit without data bits");
     }
 
-    let last_idx = cell_data.len() - 1;
-    let data_mask = 0xFFu8 << (8 - data_bits_in_last_byte);
-    cell_data[last_idx] &= data_mask;
-    let bit_len = last_idx * 8 + data_bits_in_last_byte;
-
-    Ok((cell_data, bit_len))
+    Ok((data.len() - 1) * 8 + data_bits_in_last_byte)
 }
 
 pub(super) fn serialize_cell(
@@ -688,17 +733,6 @@ pub(super) fn serialize_cell(
     Ok(result)
 }
 
-pub(super) fn collect_cells(root: &Arc<Cell>) -> Result<Vec<Arc<Cell>>> {
-    let mut cells = Vec::new();
-    let mut visited = HashMap::new();
-    collect_cells_recursive(root, &mut cells, &mut visited)?;
-
-    // Cells are already in topological order (children before parents)
-    // No reverse needed for BoC serialization
-
-    Ok(cells)
-}
-
 pub(super) fn collect_cells_recursive(
     cell: &Arc<Cell>,
     cells: &mut Vec<Arc<Cell>>,
@@ -0,0 +1,807 @@
+//! On-demand BoC decoding.
+//!
+//! [`LazyBoc`] keeps the serialized bytes and decodes a cell only when it is
+//! reached. Cells are located through the BoC index table when the BoC has
+//! one, or through offsets collected in a single scan otherwise; neither
+//! allocates per cell. Reading through [`LazySlice`] works directly on the
+//! buffer, and [`LazyCell::to_cell`] materializes a subtree as ordinary
+//! [`Cell`] values for typed decoders.
+
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{Result, bail};
+use bytes::Bytes;
+use num_bigint::{BigInt, BigUint};
+use sha2::{Digest, Sha256};
+
+use super::api::{
+    BOC_GENERIC_MAGIC, BOC_INDEXED_CRC32C_MAGIC, BOC_INDEXED_MAGIC, assemble_cell, decode_bit_len,
+    decode_cell_data, parse_boc_generic_layout, read_uint,
+};
+use crate::tvm::cell::{Cell, MAX_CELL_BITS, MAX_CELL_LEVEL};
+use crate::tvm::slice::{PrunedBranchError, Slice};
+use crate::tvm::uint::UnsignedInteger;
+
+/// Hash and depth of a cell.
+type CellHash = ([u8; 32], u16);
+
+const PRUNED_BRANCH_TAG: u8 = 0x01;
+const MERKLE_PROOF_TAG: u8 = 0x03;
+const MERKLE_UPDATE_TAG: u8 = 0x04;
+
+/// Cached hashes of a cell; only cells with a non-zero level mask have more
+/// than one.
+#[derive(Debug)]
+enum LevelHashes {
+    Plain(CellHash),
+    Levels(Box<[CellHash]>),
+}
+
+/// Bag of Cells decoded on demand.
+///
+/// Cloning is cheap and shares the buffer and every cached hash and
+/// materialized cell.
+///
+/// # Example
+///
+/// ```rust
+/// use tonutils::tvm::{Builder, LazyBoc, serialize_boc};
+///
+/// let mut child = Builder::new();
+/// child.store_u32(7).unwrap();
+/// let mut root = Builder::new();
+/// root.store_u8(1).unwrap();
+/// root.store_ref(child.build().unwrap()).unwrap();
+/// let root = root.build().unwrap();
+///
+/// let boc = LazyBoc::new(serialize_boc(&root, false).unwrap()).unwrap();
+/// let mut slice = boc.root(0).unwrap().slice();
+/// assert_eq!(slice.load_u8().unwrap(), 1);
+/// assert_eq!(slice.load_reference().unwrap().slice().load_u32().unwrap(), 7);
+/// assert_eq!(boc.root(0).unwrap().hash().unwrap(), root.hash());
+/// ```
+#[derive(Debug, Clone)]
+pub struct LazyBoc {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    data: Bytes,
+    size_bytes: usize,
+    root_indices: Vec<usize>,
+    cells_start: usize,
+    offsets: Offsets,
+    hashes: Vec<OnceLock<LevelHashes>>,
+    cells: Vec<OnceLock<Arc<Cell>>>,
+}
+
+/// Where cell records end, relative to the first cell.
+#[derive(Debug)]
+enum Offsets {
+    /// End offsets are read from the BoC index table, shifted past the
+    /// cache bit when the BoC has them.
+    Index {
+        start: usize,
+        offset_bytes: usize,
+        cache_bits: bool,
+    },
+    /// End offsets collected by scanning the cell records.
+    Scanned(Vec<usize>),
+}
+
+impl LazyBoc {
+    /// Checks the BoC header, index table, and checksum without decoding
+    /// any cell.
+    pub fn new(data: impl Into<Bytes>) -> Result<Self> {
+        let data = data.into();
+        if data.len() < 4 {
+            bail!("BoC data too short");
+        }
+        let magic = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        match magic {
+            BOC_GENERIC_MAGIC => {}
+            BOC_INDEXED_MAGIC | BOC_INDEXED_CRC32C_MAGIC => {
+                bail!("Indexed BoC format not yet supported");
+            }
+            _ => bail!("Invalid BoC magic number: 0x{:08x}", magic),
+        }
+
+        let layout = parse_boc_generic_layout(&data)?;
+        let offsets = match layout.index_start {
+            Some(start) => Offsets::Index {
+                start,
+                offset_bytes: layout.offset_bytes,
+                cache_bits: layout.has_cache_bits,
+            },
+            None => Offsets::Scanned(scan_cell_ends(
+                layout.cells_data,
+                layout.cells_count,
+                layout.size_bytes,
+            )?),
+        };
+        let cells_count = layout.cells_count;
+        let inner = Inner {
+            size_bytes: layout.size_bytes,
+            root_indices: layout.root_indices,
+            cells_start: layout.cells_start,
+            offsets,
+            hashes: (0..cells_count).map(|_| OnceLock::new()).collect(),
+            cells: (0..cells_count).map(|_| OnceLock::new()).collect(),
+            data,
+        };
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Number of cells in the BoC.
+    pub fn cell_count(&self) -> usize {
+        self.inner.cells.len()
+    }
+
+    /// Number of root cells.
+    pub fn root_count(&self) -> usize {
+        self.inner.root_indices.len()
+    }
+
+    /// Whether cells are located through the BoC index table.
+    pub fn has_index(&self) -> bool {
+        matches!(self.inner.offsets, Offsets::Index { .. })
+    }
+
+    /// Root cell `index`, in BoC root-index order.
+    pub fn root(&self, index: usize) -> Result<LazyCell> {
+        let Some(&cell) = self.inner.root_indices.get(index) else {
+            bail!(
+                "Root {} out of range: BoC has {} roots",
+                index,
+                self.root_count()
+            );
+        };
+        self.cell(cell)
+    }
+
+    /// Every root cell in BoC root-index order.
+    pub fn roots(&self) -> Result<Vec<LazyCell>> {
+        (0..self.root_count())
+            .map(|index| self.root(index))
+            .collect()
+    }
+
+    /// Cell `index` in BoC order.
+    pub fn cell(&self, index: usize) -> Result<LazyCell> {
+        if index >= self.cell_count() {
+            bail!("Invalid reference index: {}", index);
+        }
+        let (start, end) = self.inner.offsets.range(&self.inner.data, index)?;
+        let start = self.inner.cells_start + start;
+        let end = self.inner.cells_start + end;
+        let data = &self.inner.data;
+        if start + 2 > end {
+            bail!("Unexpected end of cells data");
+        }
+
+        let [d1, d2] = [data[start], data[start + 1]];
+        if d1 & 0x10 != 0 {
+            bail!("Invalid cell descriptor: reserved bits are set");
+        }
+        let ref_count = (d1 & 0x07) as usize;
+        if ref_count > 4 {
+            bail!("Invalid cell descriptor: reference count exceeds 4");
+        }
+        let data_start = start + 2;
+        let refs_start = data_start + (d2 as usize).div_ceil(2);
+        if refs_start + ref_count * self.inner.size_bytes != end {
+            bail!("Cell record size does not match the BoC index table");
+        }
+        let bit_len = decode_bit_len(&data[data_start..refs_start], d2)?;
+        if bit_len > MAX_CELL_BITS {
+            bail!(
+                "Cell bit length {} exceeds maximum {}",
+                bit_len,
+                MAX_CELL_BITS
+            );
+        }
+
+        if d1 & 0x08 != 0 && data.get(data_start) == Some(&PRUNED_BRANCH_TAG) {
+            check_pruned_branch(&data[data_start..refs_start], bit_len, ref_count)?;
+        }
+
+        Ok(LazyCell {
+            boc: self.clone(),
+            index,
+            descriptors: [d1, d2],
+            bit_len,
+            data_start,
+            refs_start,
+        })
+    }
+}
+
+impl Offsets {
+    /// Start and end of cell record `index`, relative to the first cell.
+    fn range(&self, data: &[u8], index: usize) -> Result<(usize, usize)> {
+        match self {
+            Self::Index {
+                start,
+                offset_bytes,
+                cache_bits,
+            } => {
+                let end_of = |index: usize| {
+                    let mut pos = start + index * offset_bytes;
+                    read_uint(data, &mut pos, *offset_bytes)
+                        .map(|offset| offset >> usize::from(*cache_bits))
+                };
+                let start = if index == 0 { 0 } else { end_of(index - 1)? };
+                Ok((start, end_of(index)?))
+            }
+            Self::Scanned(ends) => {
+                let start = if index == 0 { 0 } else { ends[index - 1] };
+                Ok((start, ends[index]))
+            }
+        }
+    }
+}
+
+/// End offset of every cell record in `data`.
+fn scan_cell_ends(data: &[u8], count: usize, ref_index_size: usize) -> Result<Vec<usize>> {
+    let mut ends = Vec::with_capacity(count);
+    let mut pos = 0;
+    for _ in 0..count {
+        if pos + 2 > data.len() {
+            bail!("Unexpected end of cells data");
+        }
+        let [d1, d2] = [data[pos], data[pos + 1]];
+        pos += 2 + (d2 as usize).div_ceil(2) + (d1 & 0x07) as usize * ref_index_size;
+        if pos > data.len() {
+            bail!("Cell data exceeds buffer");
+        }
+        ends.push(pos);
+    }
+    if pos != data.len() {
+        bail!("Trailing bytes after parsed cells");
+    }
+    Ok(ends)
+}
+
+/// One cell of a [`LazyBoc`], decoded from its record on demand.
+#[derive(Debug, Clone)]
+pub struct LazyCell {
+    boc: LazyBoc,
+    index: usize,
+    descriptors: [u8; 2],
+    bit_len: usize,
+    data_start: usize,
+    refs_start: usize,
+}
+
+impl LazyCell {
+    /// Position of the cell in BoC order.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the number of data bits.
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    /// Returns the number of references.
+    pub fn reference_count(&self) -> usize {
+        (self.descriptors[0] & 0x07) as usize
+    }
+
+    /// Whether the descriptor marks the cell as exotic.
+    pub fn is_exotic(&self) -> bool {
+        self.descriptors[0] & 0x08 != 0
+    }
+
+    /// Level mask declared by the cell descriptor.
+    pub fn level_mask(&self) -> u8 {
+        self.descriptors[0] >> 5
+    }
+
+    /// Serialized data bytes, including the top-up bit of a partial last
+    /// byte.
+    fn data(&self) -> &[u8] {
+        &self.boc.inner.data[self.data_start..self.refs_start]
+    }
+
+    /// BoC index of reference `index`.
+    fn reference_index(&self, index: usize) -> Result<usize> {
+        if index >= self.reference_count() {
+            bail!("Reference not found at index {}", index);
+        }
+        let mut pos = self.refs_start + index * self.boc.inner.size_bytes;
+        read_uint(&self.boc.inner.data, &mut pos, self.boc.inner.size_bytes)
+    }
+
+    /// Gets a reference by index
+    pub fn reference(&self, index: usize) -> Result<LazyCell> {
+        self.boc.cell(self.reference_index(index)?)
+    }
+
+    /// Returns every reference in order.
+    pub fn references(&self) -> Result<Vec<LazyCell>> {
+        (0..self.reference_count())
+            .map(|index| self.reference(index))
+            .collect()
+    }
+
+    /// Starts reading the cell.
+    pub fn slice(&self) -> LazySlice {
+        LazySlice {
+            cell: self.clone(),
+            bit_pos: 0,
+            ref_pos: 0,
+        }
+    }
+
+    /// Computes the representation hash, caching it with those of the
+    /// subtree.
+    pub fn hash(&self) -> Result<[u8; 32]> {
+        self.hash_at_level(MAX_CELL_LEVEL)
+    }
+
+    /// Computes the depth, caching it with those of the subtree.
+    pub fn depth(&self) -> Result<u16> {
+        self.depth_at_level(MAX_CELL_LEVEL)
+    }
+
+    /// Computes the hash of the cell at `level`, like
+    /// [`Cell::hash_at_level`].
+    pub fn hash_at_level(&self, level: u8) -> Result<[u8; 32]> {
+        Ok(self.level_hash(level, &mut HashSet::new())?.0)
+    }
+
+    /// Computes the depth of the cell at `level`.
+    pub fn depth_at_level(&self, level: u8) -> Result<u16> {
+        Ok(self.level_hash(level, &mut HashSet::new())?.1)
+    }
+
+    fn level_hash(&self, level: u8, visiting: &mut HashSet<usize>) -> Result<CellHash> {
+        let index = hash_index(self.level_mask(), level);
+        Ok(match self.level_hashes(visiting)? {
+            LevelHashes::Plain(hash) => *hash,
+            LevelHashes::Levels(hashes) => hashes[index],
+        })
+    }
+
+    /// Hash and depth for every significant level, computed as
+    /// [`Cell::hash_at_level`] does.
+    fn level_hashes(&self, visiting: &mut HashSet<usize>) -> Result<&LevelHashes> {
+        let cache = &self.boc.inner.hashes[self.index];
+        if let Some(hashes) = cache.get() {
+            return Ok(hashes);
+        }
+        if !visiting.insert(self.index) {
+            bail!("BoC cell graph contains a reference cycle");
+        }
+
+        let level_mask = self.level_mask();
+        let mut hashes = Vec::with_capacity(level_mask.count_ones() as usize + 1);
+        if self.is_exotic() && self.data().first() == Some(&PRUNED_BRANCH_TAG) {
+            // Pruned branches store the hashes of the removed subtree.
+            let data = self.data();
+            check_level_mask(data[1], level_mask)?;
+            let count = level_mask.count_ones() as usize;
+            let depths_start = 2 + count * 32;
+            if data.len() < depths_start + count * 2 {
+                bail!("Invalid pruned branch payload length");
+            }
+            for i in 0..count {
+                let mut hash = [0u8; 32];
+                hash.copy_from_slice(&data[2 + i * 32..2 + (i + 1) * 32]);
+                let depth = &data[depths_start + i * 2..depths_start + (i + 1) * 2];
+                hashes.push((hash, u16::from_be_bytes([depth[0], depth[1]])));
+            }
+            hashes.push(self.compute_level_hash(level_mask, &[], None));
+        } else {
+            let children = self
+                .references()?
+                .into_iter()
+                .map(|child| {
+                    child.level_hashes(visiting)?;
+                    Ok(child)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // Merkle cells reference their children one level deeper.
+            let merkle = self.is_exotic()
+                && matches!(
+                    self.data().first(),
+                    Some(&MERKLE_PROOF_TAG | &MERKLE_UPDATE_TAG)
+                );
+            let children_mask = children
+                .iter()
+                .fold(0, |mask, child| mask | child.level_mask());
+            check_level_mask(
+                if merkle {
+                    children_mask >> 1
+                } else {
+                    children_mask
+                },
+                level_mask,
+            )?;
+            for level in 0..=cell_level(level_mask) {
+                if level > 0 && level_mask & (1 << (level - 1)) == 0 {
+                    continue;
+                }
+                let child_level = if merkle { level + 1 } else { level };
+                let entries = children
+                    .iter()
+                    .map(|child| child.level_hash(child_level, visiting))
+                    .collect::<Result<Vec<_>>>()?;
+                let previous = hashes.last().map(|(hash, _)| *hash);
+                hashes.push(self.compute_level_hash(
+                    level_mask & ((1 << level) - 1),
+                    &entries,
+                    previous,
+                ));
+            }
+        }
+
+        visiting.remove(&self.index);
+        let hashes = match hashes.as_slice() {
+            [hash] => LevelHashes::Plain(*hash),
+            _ => LevelHashes::Levels(hashes.into_boxed_slice()),
+        };
+        Ok(cache.get_or_init(|| hashes))
+    }
+
+    /// Hashes the cell with the descriptor's level mask replaced by
+    /// `level_mask`, and the data by `previous` above the lowest level.
+    fn compute_level_hash(
+        &self,
+        level_mask: u8,
+        children: &[CellHash],
+        previous: Option<[u8; 32]>,
+    ) -> CellHash {
+        let mut hasher = Sha256::new();
+        hasher.update([
+            (self.descriptors[0] & 0x1F) | (level_mask << 5),
+            self.descriptors[1],
+        ]);
+        match previous {
+            Some(hash) => hasher.update(hash),
+            None => hasher.update(self.data()),
+        }
+        for (_, depth) in children {
+            hasher.update(depth.to_be_bytes());
+        }
+        for (hash, _) in children {
+            hasher.update(hash);
+        }
+        let depth = children
+            .iter()
+            .map(|(_, depth)| depth.saturating_add(1))
+            .max()
+            .unwrap_or(0);
+        (hasher.finalize().into(), depth)
+    }
+
+    /// Decodes the cell and its subtree into [`Cell`] values.
+    ///
+    /// Materialized cells are cached, so subtrees shared within the BoC are
+    /// shared in the result and decoded once.
+    pub fn to_cell(&self) -> Result<Arc<Cell>> {
+        self.materialize(&mut HashSet::new())
+    }
+
+    fn materialize(&self, visiting: &mut HashSet<usize>) -> Result<Arc<Cell>> {
+        if let Some(cell) = self.boc.inner.cells[self.index].get() {
+            return Ok(cell.clone());
+        }
+        if !visiting.insert(self.index) {
+            bail!("BoC cell graph contains a reference cycle");
+        }
+
+        let references = self
+            .references()?
+            .iter()
+            .map(|child| child.materialize(visiting))
+            .collect::<Result<Vec<_>>>()?;
+        let (data, bit_len) = decode_cell_data(self.data(), self.descriptors[1])?;
+        let cell = assemble_cell(
+            self.is_exotic(),
+            self.level_mask(),
+            data,
+            bit_len,
+            references,
+        )?;
+
+        visiting.remove(&self.index);
+        Ok(self.boc.inner.cells[self.index]
+            .get_or_init(|| cell)
+            .clone())
+    }
+
+    /// Returns an error when the cell is a pruned branch.
+    fn check_pruned(&self) -> Result<()> {
+        let data = self.data();
+        if !self.is_exotic() || data.first() != Some(&PRUNED_BRANCH_TAG) {
+            return Ok(());
+        }
+        // The payload was validated when the cell was located.
+        let depth_pos = 2 + data[1].count_ones() as usize * 32;
+        let (Some(hash), Some(depth)) = (data.get(2..34), data.get(depth_pos..depth_pos + 2))
+        else {
+            bail!("Invalid pruned branch payload length");
+        };
+        Err(PrunedBranchError {
+            hash: hash.try_into()?,
+            depth: u16::from_be_bytes([depth[0], depth[1]]),
+        }
+        .into())
+    }
+}
+
+/// Reads a [`LazyCell`] sequentially, like [`Slice`] reads a [`Cell`].
+///
+/// Reading a pruned branch fails with [`PrunedBranchError`].
+#[derive(Debug, Clone)]
+pub struct LazySlice {
+    cell: LazyCell,
+    bit_pos: usize,
+    ref_pos: usize,
+}
+
+impl LazySlice {
+    /// Gets the underlying cell
+    pub fn cell(&self) -> &LazyCell {
+        &self.cell
+    }
+
+    /// Returns the number of remaining bits
+    pub fn remaining_bits(&self) -> usize {
+        self.cell.bit_len - self.bit_pos
+    }
+
+    /// Returns the number of remaining references
+    pub fn remaining_refs(&self) -> usize {
+        self.cell.reference_count() - self.ref_pos
+    }
+
+    /// Checks if there are any remaining bits
+    pub fn is_empty(&self) -> bool {
+        self.remaining_bits() == 0 && self.remaining_refs() == 0
+    }
+
+    /// Gets the current bit position
+    pub fn bit_position(&self) -> usize {
+        self.bit_pos
+    }
+
+    /// Gets the current reference position
+    pub fn ref_position(&self) -> usize {
+        self.ref_pos
+    }
+
+    /// Loads a single bit
+    pub fn load_bit(&mut self) -> Result<bool> {
+        self.cell.check_pruned()?;
+        if self.remaining_bits() == 0 {
+            bail!("No more bits to read");
+        }
+        let byte = self.cell.data()[self.bit_pos / 8];
+        let bit = (byte >> (7 - self.bit_pos % 8)) & 1;
+        self.bit_pos += 1;
+        Ok(bit == 1)
+    }
+
+    /// Loads multiple bits into a byte vector
+    pub fn load_bits(&mut self, n: usize) -> Result<Vec<u8>> {
+        self.cell.check_pruned()?;
+        if n > self.remaining_bits() {
+            bail!(
+                "Not enough bits remaining: requested {}, available {}",
+                n,
+                self.remaining_bits()
+            );
+        }
+
+        let data = self.cell.data();
+        let shift = self.bit_pos % 8;
+        let first = self.bit_pos / 8;
+        let mut result = vec![0u8; n.div_ceil(8)];
+        for (offset, byte) in result.iter_mut().enumerate() {
+            let high = data[first + offset] << shift;
+            let low = match data.get(first + offset + 1) {
+                Some(next) if shift > 0 => next >> (8 - shift),
+                _ => 0,
+            };
+            *byte = high | low;
+        }
+        if !n.is_multiple_of(8)
+            && let Some(last) = result.last_mut()
+        {
+            *last &= 0xFFu8 << (8 - n % 8);
+        }
+        self.bit_pos += n;
+        Ok(result)
+    }
+
+    /// Loads multiple bytes
+    pub fn load_bytes(&mut self, n: usize) -> Result<Vec<u8>> {
+        self.load_bits(n * 8)
+    }
+
+    /// Loads a u8 value (8 bits).
+    pub fn load_u8(&mut self) -> Result<u8> {
+        self.load_uint::<u8>()
+    }
+
+    /// Loads a u16 value (16 bits, big-endian)
+    pub fn load_u16(&mut self) -> Result<u16> {
+        self.load_uint::<u16>()
+    }
+
+    /// Loads a u32 value (32 bits, big-endian)
+    pub fn load_u32(&mut self) -> Result<u32> {
+        self.load_uint::<u32>()
+    }
+
+    /// Loads a u64 value (64 bits, big-endian)
+    pub fn load_u64(&mut self) -> Result<u64> {
+        self.load_uint::<u64>()
+    }
+
+    /// Loads an unsigned integer using the natural width of `T`.
+    pub fn load_uint<T: UnsignedInteger>(&mut self) -> Result<T> {
+        self.load_uint_custom::<T>(T::BITS)
+    }
+
+    /// Loads an unsigned integer encoded in `bits` bits into `T`.
+    pub fn load_uint_custom<T: UnsignedInteger>(&mut self, bits: usize) -> Result<T> {
+        if bits > T::BITS {
+            bail!(
+                "Cannot load {} bits into {}-bit unsigned integer",
+                bits,
+                T::BITS
+            );
+        }
+        T::from_big_uint(self.load_big_uint(bits)?)
+    }
+
+    /// Loads an unsigned big integer with a specific number of bits.
+    pub fn load_big_uint(&mut self, bits: usize) -> Result<BigUint> {
+        if bits == 0 {
+            return Ok(BigUint::from(0u8));
+        }
+        let bytes = self.load_bits(bits)?;
+        Ok(BigUint::from_bytes_be(&bytes) >> (bytes.len() * 8 - bits))
+    }
+
+    /// Loads a signed integer with a specific number of bits
+    pub fn load_int(&mut self, bits: usize) -> Result<i64> {
+        if bits > 64 {
+            bail!("Cannot load more than 64 bits into i64");
+        }
+        let value = self.load_big_int(bits)?;
+        i64::try_from(value).map_err(|_| anyhow::anyhow!("Loaded signed integer does not fit i64"))
+    }
+
+    /// Loads a signed big integer with a specific number of bits using two's complement.
+    pub fn load_big_int(&mut self, bits: usize) -> Result<BigInt> {
+        if bits == 0 {
+            return Ok(BigInt::from(0));
+        }
+        let unsigned = self.load_big_uint(bits)?;
+        if unsigned.bit(bits as u64 - 1) {
+            Ok(BigInt::from(unsigned) - BigInt::from(BigUint::from(1u8) << bits))
+        } else {
+            Ok(BigInt::from(unsigned))
+        }
+    }
+
+    /// Loads coins (VarUInteger 16)
+    pub fn load_coins(&mut self) -> Result<u128> {
+        let len = self.load_uint_custom::<u8>(4)? as usize;
+        let bytes = self.load_bytes(len)?;
+        Ok(bytes
+            .iter()
+            .fold(0u128, |value, &byte| (value << 8) | u128::from(byte)))
+    }
+
+    /// Loads a reference to another cell
+    pub fn load_reference(&mut self) -> Result<LazyCell> {
+        self.cell.check_pruned()?;
+        if self.remaining_refs() == 0 {
+            bail!("No more references to read");
+        }
+        let reference = self.cell.reference(self.ref_pos)?;
+        self.ref_pos += 1;
+        Ok(reference)
+    }
+
+    /// Preloads a reference without advancing the position
+    pub fn preload_reference(&self, index: usize) -> Result<LazyCell> {
+        self.cell.check_pruned()?;
+        self.cell.reference(self.ref_pos + index)
+    }
+
+    /// Skips a number of bits
+    pub fn skip_bits(&mut self, n: usize) -> Result<()> {
+        self.cell.check_pruned()?;
+        if n > self.remaining_bits() {
+            bail!(
+                "Cannot skip {} bits: only {} remaining",
+                n,
+                self.remaining_bits()
+            );
+        }
+        self.bit_pos += n;
+        Ok(())
+    }
+
+    /// Skips a number of references
+    pub fn skip_refs(&mut self, n: usize) -> Result<()> {
+        self.cell.check_pruned()?;
+        if n > self.remaining_refs() {
+            bail!(
+                "Cannot skip {} references: only {} remaining",
+                n,
+                self.remaining_refs()
+            );
+        }
+        self.ref_pos += n;
+        Ok(())
+    }
+
+    /// Materializes the cell and returns a [`Slice`] at the same position,
+    /// for decoding the rest with typed decoders.
+    pub fn to_slice(&self) -> Result<Slice> {
+        let mut slice = Slice::new(self.cell.to_cell()?);
+        slice.skip_bits(self.bit_pos)?;
+        slice.skip_refs(self.ref_pos)?;
+        Ok(slice)
+    }
+}
+
+/// Highest significant level of `level_mask`.
+fn cell_level(level_mask: u8) -> u8 {
+    8 - level_mask.leading_zeros() as u8
+}
+
+/// Position of the `level` hash among the significant levels.
+fn hash_index(level_mask: u8, level: u8) -> usize {
+    let level = level.min(MAX_CELL_LEVEL);
+    (level_mask & ((1 << level) - 1)).count_ones() as usize
+}
+
+/// Rejects a descriptor whose level mask differs from the one the cell
+/// content implies, as [`deserialize_boc`](super::deserialize_boc) does.
+fn check_level_mask(expected: u8, declared: u8) -> Result<()> {
+    if expected != declared {
+        bail!(
+            "Invalid cell descriptor level mask: expected {}, got {}",
+            expected,
+            declared
+        );
+    }
+    Ok(())
+}
+
+/// Checks the layout of a pruned branch payload, as
+/// [`Cell::with_exotic_data`] does for decoded cells.
+fn check_pruned_branch(data: &[u8], bit_len: usize, ref_count: usize) -> Result<()> {
+    if ref_count != 0 {
+        bail!("Invalid pruned branch: expected 0 references");
+    }
+    let Some(&level_mask) = data.get(1) else {
+        bail!("Invalid pruned branch: missing level mask");
+    };
+    if !(1..=7).contains(&level_mask) {
+        bail!("Invalid pruned branch level mask: {}", level_mask);
+    }
+    let expected_bytes = 2 + level_mask.count_ones() as usize * 34;
+    if bit_len != expected_bytes * 8 || data.len() != expected_bytes {
+        bail!(
+            "Invalid pruned branch payload length: expected {} bits, got {}",
+            expected_bytes * 8,
+            bit_len
+        );
+    }
+    Ok(())
+}
@@ -1 +1,446 @@
+//! BoC serialization in the layout of the reference node.
+//!
+//! [`BocWriter`] orders cells the way the node's `BagOfCells` does: roots
+//! first, then cells in a depth-first order that keeps small subtrees next
+//! to their parents. With the same roots and flags the output is
+//! byte-identical to the node's, which makes upstream fixtures and
+//! liteserver-style multi-root proofs reproducible.
 
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+
+use super::api::{BOC_GENERIC_MAGIC, write_uint};
+use crate::tvm::cell::Cell;
+
+/// Weight budget the node spreads over a cell's subtree when ordering
+/// cells.
+const MAX_CELL_WEIGHT: i32 = 64;
+
+/// Serializes cells into a Bag of Cells with the reference node's layout.
+///
+/// Cells shared between roots, or reachable through several paths, are
+/// stored once. Roots keep their order in the root list.
+///
+/// # Example
+///
+/// ```rust
+/// use tonutils::tvm::{BocWriter, Builder, deserialize_boc_roots};
+///
+/// let mut shared = Builder::new();
+/// shared.store_u32(7).unwrap();
+/// let shared = shared.build().unwrap();
+/// let mut root = Builder::new();
+/// root.store_ref(shared.clone()).unwrap();
+/// let root = root.build().unwrap();
+///
+/// let boc = BocWriter::new([root.clone(), shared.clone()])
+///     .with_index(true)
+///     .with_crc32c(true)
+///     .to_bytes()
+///     .unwrap();
+/// assert_eq!(deserialize_boc_roots(&boc).unwrap(), vec![root, shared]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BocWriter {
+    roots: Vec<Arc<Cell>>,
+    index: bool,
+    crc32c: bool,
+    cache_bits: bool,
+    size_bytes: Option<usize>,
+    offset_bytes: Option<usize>,
+}
+
+impl BocWriter {
+    /// Starts a BoC of `roots` without index, checksum, or cache bits, and
+    /// with the smallest `size_bytes` and `off_bytes` that fit.
+    pub fn new(roots: impl IntoIterator<Item = Arc<Cell>>) -> Self {
+        Self {
+            roots: roots.into_iter().collect(),
+            index: false,
+            crc32c: false,
+            cache_bits: false,
+            size_bytes: None,
+            offset_bytes: None,
+        }
+    }
+
+    /// Appends another root.
+    pub fn with_root(mut self, root: Arc<Cell>) -> Self {
+        self.roots.push(root);
+        self
+    }
+
+    /// Writes the index table of cell end offsets.
+    pub fn with_index(mut self, index: bool) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Appends a CRC32C checksum of the whole BoC.
+    pub fn with_crc32c(mut self, crc32c: bool) -> Self {
+        self.crc32c = crc32c;
+        self
+    }
+
+    /// Marks cells referenced more than once in the index table. Requires
+    /// the index.
+    pub fn with_cache_bits(mut self, cache_bits: bool) -> Self {
+        self.cache_bits = cache_bits;
+        self
+    }
+
+    /// Sets the width of cell counts and references, 1 to 4 bytes; the
+    /// reference node rejects wider ones.
+    pub fn with_size_bytes(mut self, size_bytes: usize) -> Self {
+        self.size_bytes = Some(size_bytes);
+        self
+    }
+
+    /// Sets the width of data sizes and index offsets, 1 to 8 bytes.
+    pub fn with_offset_bytes(mut self, offset_bytes: usize) -> Self {
+        self.offset_bytes = Some(offset_bytes);
+        self
+    }
+
+    /// Serializes the BoC into a byte vector.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut result = Vec::new();
+        self.write_to(&mut result)?;
+        Ok(result)
+    }
+
+    /// Serializes the BoC into `writer` cell by cell and returns the number
+    /// of bytes written.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<usize> {
+        if self.roots.is_empty() {
+            bail!("BoC must contain at least one root");
+        }
+        if self.cache_bits && !self.index {
+            bail!("BoC cache bits require an index table");
+        }
+
+        let mut bag = Bag::default();
+        let mut roots = Vec::with_capacity(self.roots.len());
+        for root in &self.roots {
+            let (index, seen) = bag.import(root);
+            if seen {
+                bag.entries[index].should_cache = true;
+            }
+            roots.push(index);
+        }
+        let order = bag.reorder(&roots);
+        let cell_count = order.len();
+
+        let size_bytes = field_width("size_bytes", self.size_bytes, cell_count as u64, 4)?;
+        let data_size = bag.data_bytes + bag.ref_count * size_bytes;
+        let max_offset = if self.cache_bits {
+            data_size * 2
+        } else {
+            data_size
+        };
+        let offset_bytes = field_width("offset_bytes", self.offset_bytes, max_offset as u64, 8)?;
+
+        // Position in the output of the cell imported at each index.
+        let mut positions = vec![0; cell_count];
+        for (allocated, &index) in order.iter().enumerate() {
+            positions[index] = cell_count - 1 - allocated;
+        }
+
+        let mut writer = ChecksumWriter::new(writer, self.crc32c);
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&BOC_GENERIC_MAGIC.to_be_bytes());
+        buf.push(
+            (u8::from(self.index) << 7)
+                | (u8::from(self.crc32c) << 6)
+                | (u8::from(self.cache_bits) << 5)
+                | size_bytes as u8,
+        );
+        buf.push(offset_bytes as u8);
+        write_uint(&mut buf, cell_count, size_bytes);
+        write_uint(&mut buf, roots.len(), size_bytes);
+        write_uint(&mut buf, 0, size_bytes);
+        write_uint(&mut buf, data_size, offset_bytes);
+        for &root in &roots {
+            write_uint(&mut buf, positions[root], size_bytes);
+        }
+        writer.write(&buf)?;
+
+        if self.index {
+            let mut end = 0;
+            for &index in order.iter().rev() {
+                let entry = &bag.entries[index];
+                end += entry.data_len + entry.refs.len() * size_bytes;
+                let offset = if self.cache_bits {
+                    end * 2 + usize::from(entry.should_cache)
+                } else {
+                    end
+                };
+                buf.clear();
+                write_uint(&mut buf, offset, offset_bytes);
+                writer.write(&buf)?;
+            }
+        }
+
+        for &index in order.iter().rev() {
+            let entry = &bag.entries[index];
+            buf.clear();
+            buf.extend_from_slice(&entry.cell.descriptors());
+            buf.extend_from_slice(&entry.cell.serialize_data());
+            for &reference in &entry.refs {
+                write_uint(&mut buf, positions[reference], size_bytes);
+            }
+            writer.write(&buf)?;
+        }
+
+        writer.finish()
+    }
+}
+
+/// Checks a configured field width against the smallest one holding
+/// `max`, or returns that smallest width.
+fn field_width(name: &str, configured: Option<usize>, max: u64, limit: usize) -> Result<usize> {
+    let needed = (1..8).find(|&bytes| max < 1u64 << (bytes * 8)).unwrap_or(8);
+    let Some(bytes) = configured else {
+        return Ok(needed);
+    };
+    if bytes == 0 || bytes > limit {
+        bail!("Invalid {}: {}, expected 1 to {}", name, bytes, limit);
+    }
+    if bytes < needed {
+        bail!("{} {} cannot hold {}, need {}", name, bytes, max, needed);
+    }
+    Ok(bytes)
+}
+
+/// Cell of a [`Bag`], with references given as import indices.
+#[derive(Debug)]
+struct Entry {
+    cell: Arc<Cell>,
+    refs: Vec<usize>,
+    /// Serialized size without references.
+    data_len: usize,
+    /// Subtree weight, capped by the node's ordering heuristic; zero marks
+    /// cells visited ahead of their siblings' subtrees.
+    weight: u8,
+    should_cache: bool,
+    /// Allocation order once allocated, otherwise [`NOT_VISITED`],
+    /// [`PREVISITED`], or [`VISITED`].
+    new_index: isize,
+}
+
+const NOT_VISITED: isize = -1;
+const PREVISITED: isize = -2;
+const VISITED: isize = -3;
+
+/// Deduplicated cells in import order, children before parents.
+#[derive(Debug, Default)]
+struct Bag {
+    entries: Vec<Entry>,
+    by_address: HashMap<usize, usize>,
+    /// Cells keyed by descriptors, data, and reference indices. Equal keys
+    /// are exactly equal representation hashes, without rehashing subtrees.
+    by_content: HashMap<Vec<u8>, usize>,
+    data_bytes: usize,
+    ref_count: usize,
+}
+
+impl Bag {
+    /// Imports `cell` and returns its index and whether it was already
+    /// present. Only references of newly imported cells count as repeated.
+    fn import(&mut self, cell: &Arc<Cell>) -> (usize, bool) {
+        let address = Arc::as_ptr(cell) as usize;
+        if let Some(&index) = self.by_address.get(&address) {
+            return (index, true);
+        }
+
+        let children: Vec<_> = cell
+            .references()
+            .iter()
+            .map(|child| self.import(child))
+            .collect();
+        let data = cell.serialize_data();
+        let mut key = Vec::with_capacity(2 + data.len() + children.len() * 4);
+        key.extend_from_slice(&cell.descriptors());
+        key.extend_from_slice(&data);
+        for &(child, _) in &children {
+            key.extend_from_slice(&(child as u32).to_be_bytes());
+        }
+        if let Some(&index) = self.by_content.get(&key) {
+            self.by_address.insert(address, index);
+            return (index, true);
+        }
+
+        let mut weight = 1u32;
+        for &(child, seen) in &children {
+            let entry = &mut self.entries[child];
+            entry.should_cache |= seen;
+            weight += u32::from(entry.weight);
+        }
+        let index = self.entries.len();
+        self.entries.push(Entry {
+            cell: cell.clone(),
+            refs: children.into_iter().map(|(child, _)| child).collect(),
+            data_len: 2 + data.len(),
+            weight: weight.min(0xff) as u8,
+            should_cache: false,
+            new_index: NOT_VISITED,
+        });
+        self.data_bytes += 2 + data.len();
+        self.ref_count += cell.reference_count();
+        self.by_address.insert(address, index);
+        self.by_content.insert(key, index);
+        (index, false)
+    }
+
+    /// Returns import indices in the node's allocation order, which is the
+    /// reverse of the output order.
+    fn reorder(&mut self, roots: &[usize]) -> Vec<usize> {
+        // Spread each cell's weight budget over its references, parents
+        // first, capping heavy references.
+        for index in (0..self.entries.len()).rev() {
+            let count = self.entries[index].refs.len() as i32;
+            let mut heavy = count;
+            let mut sum = MAX_CELL_WEIGHT - 1;
+            let mut light = 0u32;
+            for (j, &child) in self.entries[index].refs.iter().enumerate() {
+                let weight = i32::from(self.entries[child].weight);
+                if weight <= (MAX_CELL_WEIGHT - 1 + j as i32) / count {
+                    sum -= weight;
+                    heavy -= 1;
+                    light |= 1 << j;
+                }
+            }
+            if heavy > 0 {
+                for j in 0..self.entries[index].refs.len() {
+                    if light & (1 << j) == 0 {
+                        let child = self.entries[index].refs[j];
+                        let limit = sum / heavy;
+                        sum += 1;
+                        let entry = &mut self.entries[child];
+                        if i32::from(entry.weight) > limit {
+                            entry.weight = limit as u8;
+                        }
+                    }
+                }
+            }
+        }
+        // Cells whose subtree exceeds the cap are visited specially.
+        for index in 0..self.entries.len() {
+            let sum = 1 + self.entries[index]
+                .refs
+                .iter()
+                .map(|&child| u32::from(self.entries[child].weight))
+                .sum::<u32>();
+            let entry = &mut self.entries[index];
+            entry.weight = if sum <= u32::from(entry.weight) {
+                sum as u8
+            } else {
+                0
+            };
+        }
+
+        let mut order = Vec::with_capacity(self.entries.len());
+        for &root in roots {
+            self.revisit(root, Pass::Previsit, &mut order);
+            self.revisit(root, Pass::Visit, &mut order);
+        }
+        for &root in roots {
+            self.revisit(root, Pass::Allocate, &mut order);
+        }
+        order
+    }
+
+    fn revisit(&mut self, index: usize, pass: Pass, order: &mut Vec<usize>) -> isize {
+        let new_index = self.entries[index].new_index;
+        if new_index >= 0 {
+            return new_index;
+        }
+        let refs = self.entries[index].refs.clone();
+        match pass {
+            Pass::Previsit => {
+                if new_index != NOT_VISITED {
+                    return new_index;
+                }
+                // Special references are visited right away.
+                for &child in refs.iter().rev() {
+                    let pass = if self.entries[child].weight == 0 {
+                        Pass::Visit
+                    } else {
+                        Pass::Previsit
+                    };
+                    self.revisit(child, pass, order);
+                }
+                self.entries[index].new_index = PREVISITED;
+            }
+            Pass::Visit => {
+                if new_index == VISITED {
+                    return new_index;
+                }
+                if self.entries[index].weight == 0 {
+                    self.revisit(index, Pass::Previsit, order);
+                }
+                for &child in refs.iter().rev() {
+                    self.revisit(child, Pass::Visit, order);
+                }
+                for &child in refs.iter().rev() {
+                    self.revisit(child, Pass::Allocate, order);
+                }
+                self.entries[index].new_index = VISITED;
+            }
+            Pass::Allocate => {
+                self.entries[index].new_index = order.len() as isize;
+                order.push(index);
+            }
+        }
+        self.entries[index].new_index
+    }
+}
+
+/// Passes of the node's cell ordering.
+#[derive(Debug, Clone, Copy)]
+enum Pass {
+    /// Walks down to special cells and visits them.
+    Previsit,
+    /// Visits and allocates every reference.
+    Visit,
+    /// Appends the cell to the allocation order.
+    Allocate,
+}
+
+/// Forwards writes while counting them and updating the checksum.
+struct ChecksumWriter<W> {
+    inner: W,
+    digest: Option<crc::Digest<'static, u32>>,
+    written: usize,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn new(inner: W, crc32c: bool) -> Self {
+        Self {
+            inner,
+            digest: crc32c.then(|| crate::crc::CRC32C.digest()),
+            written: 0,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner.write_all(bytes)?;
+        if let Some(digest) = &mut self.digest {
+            digest.update(bytes);
+        }
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    /// Appends the checksum, if any, and returns the total size.
+    fn finish(mut self) -> Result<usize> {
+        if let Some(digest) = self.digest.take() {
+            self.write(&digest.finalize().to_le_bytes())?;
+        }
+        self.inner.flush()?;
+        Ok(self.written)
+    }
+}
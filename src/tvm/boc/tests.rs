@@ -13,6 +13,9 @@ mod tests {
     const INDEXED_REF_CELL_BOC_HEX: &str = "b5ee9c728101020100070103070002aa0102bb00";
     const LIBRARY_REFERENCE_BOC_HEX: &str = "b5ee9c72010101010023000842023333333333333333333333333333333333333333333333333333333333333333";
     const TWO_ROOT_BOC_HEX: &str = "b5ee9c72010102020005000100000002aa";
+    /// Wallet V4R2 code as published, serialized by the reference node with
+    /// CRC32C.
+    const WALLET_V4R2_CODE_BOC_HEX: &str = "b5ee9c72410214010002d4000114ff00f4a413f4bcf2c80b010201200203020148040504f8f28308d71820d31fd31fd31f02f823bbf264ed44d0d31fd31fd3fff404d15143baf2a15151baf2a205f901541064f910f2a3f80024a4c8cb1f5240cb1f5230cbff5210f400c9ed54f80f01d30721c0009f6c519320d74a96d307d402fb00e830e021c001e30021c002e30001c0039130e30d03a4c8cb1f12cb1fcbff1011121302e6d001d0d3032171b0925f04e022d749c120925f04e002d31f218210706c7567bd22821064737472bdb0925f05e003fa403020fa4401c8ca07cbffc9d0ed44d0810140d721f404305c810108f40a6fa131b3925f07e005d33fc8258210706c7567ba923830e30d03821064737472ba925f06e30d06070201200809007801fa00f40430f8276f2230500aa121bef2e0508210706c7567831eb17080185004cb0526cf1658fa0219f400cb6917cb1f5260cb3f20c98040fb0006008a5004810108f45930ed44d0810140d720c801cf16f400c9ed540172b08e23821064737472831eb17080185005cb055003cf1623fa0213cb6acb1fcb3fc98040fb00925f03e20201200a0b0059bd242b6f6a2684080a06b90fa0218470d4080847a4937d29910ce6903e9ff9837812801b7810148987159f31840201580c0d0011b8c97ed44d0d70b1f8003db29dfb513420405035c87d010c00b23281f2fff274006040423d029be84c600201200e0f0019adce76a26840206b90eb85ffc00019af1df6a26840106b90eb858fc0006ed207fa00d4d422f90005c8ca0715cbffc9d077748018c8cb05cb0222cf165005fa0214cb6b12ccccc973fb00c84014810108f451f2a7020070810108d718fa00d33fc8542047810108f451f2a782106e6f746570748018c8cb05cb025006cf165004fa0214cb6a12cb1fcb3fc973fb0002006c810108d718fa00d33f305224810108f459f2a782106473747270748018c8cb05cb025005cf165003fa0213cb6acb1f12cb3fc973fb00000af400c9ed54696225e5";
 
     fn single_cell_boc(cell_bytes: &[u8]) -> Vec<u8> {
         let mut boc = vec![
@@ -140,7 +143,7 @@ mod tests {
         boc[last] ^= 1;
 
         let err = inspect_boc(&boc).unwrap_err().to_string();
-        assert!(err.contains("CRC32 mismatch"));
+        assert!(err.contains("CRC32C mismatch"));
     }
 
     #[test]
@@ -195,6 +198,30 @@ mod tests {
         assert_eq!(cell.hash(), deserialized.hash());
     }
 
+    #[test]
+    fn test_serialize_multi_root_boc_shares_cells_and_keeps_order() {
+        let mut shared = CellBuilder::new();
+        shared.store_u8(0xAA).unwrap();
+        let shared = shared.build().unwrap();
+        let mut first = CellBuilder::new();
+        first.store_u8(1).unwrap();
+        first.store_reference(shared.clone()).unwrap();
+        let first = first.build().unwrap();
+        let mut second = CellBuilder::new();
+        second.store_u8(2).unwrap();
+        second.store_reference(shared).unwrap();
+        let second = second.build().unwrap();
+
+        let boc = serialize_boc_roots(&[first.clone(), second.clone()], true).unwrap();
+        let roots = deserialize_boc_roots(&boc).unwrap();
+        let inspection = inspect_boc(&boc).unwrap();
+
+        assert_eq!(inspection.root_hashes, vec![first.hash(), second.hash()]);
+        assert_eq!(roots[0].hash(), first.hash());
+        assert_eq!(roots[1].hash(), second.hash());
+        assert!(serialize_boc_roots(&[], false).is_err());
+    }
+
     #[test]
     fn test_hex_conversion() {
         let mut builder = CellBuilder::new();
@@ -283,6 +310,35 @@ mod tests {
         assert!(err.contains("cache bits flag"));
     }
 
+    #[test]
+    fn test_deserialize_accepts_reference_node_crc32c() {
+        let boc = decode_hex_fixture(WALLET_V4R2_CODE_BOC_HEX);
+        let code = deserialize_boc(&boc).unwrap();
+        assert_eq!(inspect_boc(&boc).unwrap().root_hashes, vec![code.hash()]);
+
+        let boc = serialize_boc(&code, true).unwrap();
+        let (payload, crc) = boc.split_at(boc.len() - 4);
+        assert_eq!(
+            u32::from_le_bytes(crc.try_into().unwrap()),
+            crate::crc::CRC32C.checksum(payload)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_accepts_cache_bits_with_index() {
+        // The leaf is referenced twice and carries the cache bit in its
+        // index entry: 5 * 2 for the root and 8 * 2 + 1 for the leaf.
+        let boc =
+            hex::decode("b5ee9c72a1010201000800 0a11 0202bb0101 0002aa".replace(' ', "")).unwrap();
+
+        let root = deserialize_boc(&boc).unwrap();
+        assert_eq!(root.reference_count(), 2);
+        assert_eq!(root.reference(0).unwrap(), root.reference(1).unwrap());
+        assert_eq!(inspect_boc(&boc).unwrap().root_hashes, vec![root.hash()]);
+        let lazy = LazyBoc::new(boc).unwrap();
+        assert_eq!(lazy.root(0).unwrap().hash().unwrap(), root.hash());
+    }
+
     #[test]
     fn test_deserialize_rejects_crc_mismatch() {
         let cell = Arc::new(Cell::with_data(vec![0xAA], 8).unwrap());
@@ -291,7 +347,7 @@ mod tests {
         boc[last] ^= 1;
 
         let err = deserialize_boc(&boc).unwrap_err().to_string();
-        assert!(err.contains("CRC32 mismatch"));
+        assert!(err.contains("CRC32C mismatch"));
     }
 
     #[test]
@@ -501,4 +557,323 @@ mod tests {
         assert_eq!(decoded.exotic_kind(), cell.exotic_kind());
         assert_eq!(decoded.hash(), cell.hash());
     }
+
+    /// Two roots sharing a subtree, with partial-byte and signed payloads.
+    fn lazy_fixture_roots() -> Vec<Arc<Cell>> {
+        let mut shared = CellBuilder::new();
+        shared.store_bits(&[0b1011_0000], 5).unwrap();
+        let shared = shared.build().unwrap();
+
+        let mut first = CellBuilder::new();
+        first.store_bit(true).unwrap();
+        first.store_u32(0xDEAD_BEEF).unwrap();
+        first
+            .store_big_int(&num_bigint::BigInt::from(-5), 11)
+            .unwrap();
+        first.store_reference(shared.clone()).unwrap();
+        let first = first.build().unwrap();
+
+        let mut second = CellBuilder::new();
+        second.store_u8(0x04).unwrap();
+        second.store_bytes(&[0x01, 0x02, 0x03, 0x04]).unwrap();
+        second.store_reference(first.clone()).unwrap();
+        second.store_reference(shared).unwrap();
+        vec![first, second.build().unwrap()]
+    }
+
+    #[test]
+    fn test_lazy_boc_reads_like_eager_decoding() {
+        let roots = lazy_fixture_roots();
+        let boc = serialize_boc_roots(&roots, true).unwrap();
+        let lazy = LazyBoc::new(boc.clone()).unwrap();
+        assert_eq!(lazy.root_count(), 2);
+        assert_eq!(lazy.cell_count(), 3);
+        assert!(!lazy.has_index());
+
+        let first = lazy.root(0).unwrap();
+        let mut slice = first.slice();
+        assert!(slice.load_bit().unwrap());
+        assert_eq!(slice.load_u32().unwrap(), 0xDEAD_BEEF);
+        assert_eq!(slice.load_int(11).unwrap(), -5);
+        assert!(slice.load_bit().is_err());
+        let shared = slice.load_reference().unwrap();
+        assert!(slice.is_empty());
+        assert_eq!(shared.bit_len(), 5);
+        assert_eq!(shared.slice().load_bits(5).unwrap(), vec![0b1011_0000]);
+
+        let mut slice = lazy.root(1).unwrap().slice();
+        slice.skip_bits(4).unwrap();
+        assert_eq!(slice.load_bits(12).unwrap(), vec![0x40, 0x10]);
+        assert_eq!(slice.load_uint_custom::<u32>(20).unwrap(), 0x02030);
+        assert_eq!(slice.preload_reference(1).unwrap().index(), shared.index());
+        slice.skip_refs(1).unwrap();
+        let mut rest = slice.to_slice().unwrap();
+        assert_eq!(
+            rest.load_reference().unwrap(),
+            roots[0].reference(0).unwrap().clone()
+        );
+
+        let mut coins = crate::tvm::Builder::new();
+        coins.store_coins(0x1234).unwrap();
+        let coins = LazyBoc::new(serialize_boc(&coins.build().unwrap(), false).unwrap()).unwrap();
+        assert_eq!(coins.root(0).unwrap().slice().load_coins().unwrap(), 0x1234);
+
+        for (lazy_root, root) in lazy.roots().unwrap().iter().zip(&roots) {
+            assert_eq!(lazy_root.hash().unwrap(), root.hash());
+            assert_eq!(lazy_root.depth().unwrap(), root.depth());
+            assert_eq!(&lazy_root.to_cell().unwrap(), root);
+        }
+        // Materialized subtrees are shared between roots.
+        let materialized = lazy.root(1).unwrap().to_cell().unwrap();
+        assert!(Arc::ptr_eq(
+            materialized.reference(0).unwrap(),
+            &lazy.root(0).unwrap().to_cell().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_lazy_boc_locates_cells_through_index_table() {
+        let lazy = LazyBoc::new(decode_hex_fixture(INDEXED_REF_CELL_BOC_HEX)).unwrap();
+        assert!(lazy.has_index());
+
+        let root = lazy.root(0).unwrap();
+        let mut slice = root.slice();
+        assert_eq!(slice.load_u8().unwrap(), 0xBB);
+        assert_eq!(
+            slice.load_reference().unwrap().slice().load_u8().unwrap(),
+            0xAA
+        );
+        assert_eq!(
+            root.hash().unwrap(),
+            deserialize_boc(&decode_hex_fixture(REF_CELL_BOC_HEX))
+                .unwrap()
+                .hash()
+        );
+    }
+
+    #[test]
+    fn test_lazy_boc_reports_pruned_branches_and_bad_cells_on_access() {
+        let roots = lazy_fixture_roots();
+        let proof = crate::tvm::MerkleProofBuilder::new(roots[1].clone())
+            .build()
+            .unwrap();
+        let lazy = LazyBoc::new(serialize_boc(&proof, false).unwrap()).unwrap();
+        let root = lazy.root(0).unwrap();
+        assert!(root.is_exotic());
+        assert_eq!(root.hash().unwrap(), proof.hash());
+        assert_eq!(root.to_cell().unwrap(), proof);
+
+        let proven = root.reference(0).unwrap();
+        assert_eq!(proven.level_mask(), 1);
+        assert_eq!(proven.hash_at_level(0).unwrap(), roots[1].hash());
+        let mut slice = proven.slice();
+        slice.skip_bits(40).unwrap();
+        let error = slice
+            .load_reference()
+            .unwrap()
+            .slice()
+            .load_bit()
+            .unwrap_err();
+        let pruned = error
+            .downcast_ref::<crate::tvm::PrunedBranchError>()
+            .unwrap();
+        assert_eq!(pruned.hash, roots[0].hash());
+
+        // The second cell references the first one and the first one
+        // references the second.
+        let cyclic = hex::decode("b5ee9c72010102010008010102aa010102bb00").unwrap();
+        assert!(deserialize_boc(&cyclic).is_err());
+        let lazy = LazyBoc::new(cyclic).unwrap();
+        assert_eq!(lazy.root(0).unwrap().slice().load_u8().unwrap(), 0xBB);
+        assert!(
+            lazy.root(0)
+                .unwrap()
+                .hash()
+                .unwrap_err()
+                .to_string()
+                .contains("cycle")
+        );
+
+        let child = Arc::new(Cell::with_data(vec![0xAA], 8).unwrap());
+        let mut parent = Cell::with_data(vec![0xBB], 8).unwrap();
+        parent.add_reference(child).unwrap();
+        let mut boc = serialize_boc(&Arc::new(parent), false).unwrap();
+        let last = boc.len() - 1;
+        boc[last] = 2;
+        let lazy = LazyBoc::new(boc).unwrap();
+        let error = lazy.root(0).unwrap().slice().load_reference().unwrap_err();
+        assert!(error.to_string().contains("Invalid reference index"));
+    }
+
+    #[test]
+    fn test_lazy_boc_rejects_malformed_pruned_branch() {
+        // One exotic cell `01 00 00 00`: a pruned branch with level mask 0.
+        let boc = decode_hex_fixture("b5ee9c7201010101000600080801000000");
+        assert_eq!(boc.len(), 17);
+        let lazy = LazyBoc::new(boc).unwrap();
+        let err = lazy.root(0).unwrap_err().to_string();
+        assert!(err.contains("Invalid pruned branch level mask: 0"));
+    }
+
+    #[test]
+    fn test_lazy_boc_rejects_level_mask_mismatch_like_deserialize_boc() {
+        let pruned =
+            crate::tvm::pruned_branch(&Cell::with_data(vec![0xAA], 8).unwrap(), 0).unwrap();
+        let mut parent = Cell::with_data(vec![0xBB], 8).unwrap();
+        parent.add_reference(pruned).unwrap();
+        let parent = Arc::new(parent);
+        let proof = crate::tvm::merkle_proof(&parent).unwrap();
+
+        // The root record comes last: the ordinary parent claims level 0
+        // above a level 1 child, and the Merkle proof claims level 1
+        // although it lowers its child back to level 0.
+        for (root, record_len, descriptor) in [(parent, 4, 0x01), (proof, 38, 0x29)] {
+            let mut boc = serialize_boc(&root, false).unwrap();
+            let pos = boc.len() - record_len;
+            assert_eq!(boc[pos] & 0x1F, descriptor & 0x1F);
+            assert_ne!(boc[pos], descriptor);
+            boc[pos] = descriptor;
+
+            let err = deserialize_boc(&boc).unwrap_err().to_string();
+            assert!(err.contains("Invalid cell descriptor level mask"), "{err}");
+            let lazy = LazyBoc::new(boc).unwrap();
+            let err = lazy.root(0).unwrap().hash().unwrap_err().to_string();
+            assert!(err.contains("Invalid cell descriptor level mask"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_boc_writer_reproduces_reference_node_output() {
+        let code = deserialize_boc(&decode_hex_fixture(WALLET_V4R2_CODE_BOC_HEX)).unwrap();
+        let mut written = Vec::new();
+        let len = BocWriter::new([code.clone()])
+            .with_crc32c(true)
+            .write_to(&mut written)
+            .unwrap();
+        assert_eq!(len, written.len());
+        assert_eq!(hex::encode(written), WALLET_V4R2_CODE_BOC_HEX);
+
+        // Children keep their position; only the flags, index and checksum
+        // change with the mode.
+        let indexed = BocWriter::new([code.clone()])
+            .with_index(true)
+            .to_bytes()
+            .unwrap();
+        assert_eq!(indexed[4], 0x80 | 0x01);
+        assert_eq!(deserialize_boc(&indexed).unwrap(), code);
+        let lazy = LazyBoc::new(indexed).unwrap();
+        assert!(lazy.has_index());
+        assert_eq!(lazy.root(0).unwrap().hash().unwrap(), code.hash());
+    }
+
+    #[test]
+    fn test_boc_writer_deduplicates_cells_across_roots() {
+        let shared = Arc::new(Cell::with_data(vec![0xAA], 8).unwrap());
+        // Equal to `shared` but a separate allocation.
+        let copy = Arc::new(Cell::with_data(vec![0xAA], 8).unwrap());
+        let mut first = Cell::with_data(vec![0x01], 8).unwrap();
+        first.add_reference(shared.clone()).unwrap();
+        let mut second = Cell::with_data(vec![0x02], 8).unwrap();
+        second.add_reference(copy).unwrap();
+        second.add_reference(shared.clone()).unwrap();
+        let roots = vec![Arc::new(first), Arc::new(second), shared.clone()];
+
+        let boc = BocWriter::new(roots.clone()).to_bytes().unwrap();
+        // Three cells, three roots, and the roots come first.
+        assert_eq!(&boc[6..9], &[3, 3, 0]);
+        let mut root_positions = boc[10..13].to_vec();
+        root_positions.sort();
+        assert_eq!(root_positions, vec![0, 1, 2]);
+        assert_eq!(deserialize_boc_roots(&boc).unwrap(), roots);
+
+        let repeated = BocWriter::new([shared.clone()])
+            .with_root(shared.clone())
+            .to_bytes()
+            .unwrap();
+        assert_eq!(&repeated[6..9], &[1, 2, 0]);
+        assert_eq!(
+            deserialize_boc_roots(&repeated).unwrap(),
+            vec![shared.clone(), shared]
+        );
+    }
+
+    #[test]
+    fn test_boc_writer_marks_repeated_cells_with_cache_bits() {
+        let leaf = Arc::new(Cell::with_data(vec![0xAA], 8).unwrap());
+        let mut root = Cell::with_data(vec![0xBB], 8).unwrap();
+        root.add_reference(leaf.clone()).unwrap();
+        root.add_reference(leaf.clone()).unwrap();
+        let root = Arc::new(root);
+
+        let boc = BocWriter::new([root.clone()])
+            .with_index(true)
+            .with_cache_bits(true)
+            .with_crc32c(true)
+            .to_bytes()
+            .unwrap();
+        assert_eq!(
+            hex::encode(&boc[..16]),
+            // Flags, widths, counts, data size 8, root 0, then index entries
+            // 5 * 2 for the root and 8 * 2 + 1 for the cached leaf.
+            "b5ee9c72 e1 01 02 01 00 08 00 0a 11 0202bb".replace(' ', "")
+        );
+        assert_eq!(deserialize_boc(&boc).unwrap(), root);
+        let lazy = LazyBoc::new(boc).unwrap();
+        let mut slice = lazy.root(0).unwrap().slice();
+        assert_eq!(slice.load_u8().unwrap(), 0xBB);
+        assert_eq!(
+            slice.load_reference().unwrap().slice().load_u8().unwrap(),
+            0xAA
+        );
+
+        let err = BocWriter::new([root])
+            .with_cache_bits(true)
+            .to_bytes()
+            .unwrap_err();
+        assert!(err.to_string().contains("index table"));
+    }
+
+    #[test]
+    fn test_boc_writer_uses_configured_field_widths() {
+        let mut root = Cell::with_data(vec![0xBB], 8).unwrap();
+        root.add_reference(Arc::new(Cell::with_data(vec![0xAA], 8).unwrap()))
+            .unwrap();
+        let root = Arc::new(root);
+
+        let boc = BocWriter::new([root.clone()])
+            .with_size_bytes(2)
+            .with_offset_bytes(4)
+            .to_bytes()
+            .unwrap();
+        assert_eq!(
+            hex::encode(&boc),
+            "b5ee9c72 02 04 0002 0001 0000 00000008 0000 0102bb0001 0002aa".replace(' ', "")
+        );
+        assert_eq!(deserialize_boc(&boc).unwrap(), root);
+
+        for writer in [
+            BocWriter::new([root.clone()]).with_size_bytes(0),
+            BocWriter::new([root.clone()]).with_size_bytes(5),
+            BocWriter::new([root.clone()]).with_offset_bytes(9),
+        ] {
+            assert!(
+                writer
+                    .to_bytes()
+                    .unwrap_err()
+                    .to_string()
+                    .contains("Invalid")
+            );
+        }
+        let mut wide = Cell::new();
+        for byte in 0..4 {
+            wide.add_reference(Arc::new(Cell::with_data(vec![byte; 127], 1016).unwrap()))
+                .unwrap();
+        }
+        let err = BocWriter::new([Arc::new(wide)])
+            .with_offset_bytes(1)
+            .to_bytes()
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot hold"));
+        assert!(BocWriter::new(Vec::new()).to_bytes().is_err());
+    }
 }
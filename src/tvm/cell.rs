@@ -14,3 +14,6 @@ mod exotic;
 mod tests;
 
 pub use cell::*;
+pub use exotic::{
+    MerkleProofBuilder, MerkleUpdateBuilder, merkle_proof, merkle_update, pruned_branch,
+};
@@ -50,6 +50,9 @@ impl ExoticCellKind {
     }
 }
 
+/// Hash and depth of a cell at one level.
+type LevelHash = ([u8; 32], u16);
+
 /// Represents a cell in the TON blockchain
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Cell {
@@ -61,8 +64,8 @@ pub struct Cell {
     references: Vec<Arc<Cell>>,
     /// Exotic cell kind when this is a special cell
     exotic: Option<ExoticCellKind>,
-    /// Cell level (0-3)
-    level: u8,
+    /// Level mask; bit `i` marks level `i + 1` as significant
+    level_mask: u8,
     /// Cached hash
     hash: Option<[u8; 32]>,
     /// Cached depth
@@ -77,7 +80,7 @@ impl Cell {
             bit_len: 0,
             references: Vec::new(),
             exotic: None,
-            level: 0,
+            level_mask: 0,
             hash: None,
             depth: None,
         }
@@ -114,7 +117,7 @@ impl Cell {
             bit_len,
             references: Vec::new(),
             exotic: None,
-            level: 0,
+            level_mask: 0,
             hash: None,
             depth: None,
         })
@@ -165,18 +168,26 @@ impl Cell {
 
     /// Returns the cell's level
     pub fn level(&self) -> u8 {
-        self.level
+        8 - self.level_mask.leading_zeros() as u8
+    }
+
+    /// Returns the cell's level mask
+    pub fn level_mask(&self) -> u8 {
+        self.level_mask
     }
 
-    /// Updates the cell's level based on its references
+    /// Updates the cell's level mask based on its references
     fn update_level(&mut self) {
         if self.is_exotic() {
             // Exotic cells have special level calculation rules
             return;
         }
 
-        // For ordinary cells, level is the maximum level of all references
-        self.level = self.references.iter().map(|r| r.level()).max().unwrap_or(0);
+        // For ordinary cells, the level mask is the union of all references
+        self.level_mask = self
+            .references
+            .iter()
+            .fold(0, |mask, reference| mask | reference.level_mask());
     }
 
     /// Computes the cell's descriptors (2 bytes)
@@ -185,8 +196,7 @@ impl Cell {
         // r = number of references (0-4)
         // s = exotic flag (0 or 1)
         // l = level (0-3)
-        let refs_descriptor =
-            self.references.len() as u8 + if self.is_exotic() { 8 } else { 0 } + self.level * 32;
+        let refs_descriptor = self.refs_descriptor(self.level_mask);
 
         // Second byte: floor(b/8) + ceil(b/8)
         // This represents the length of the data
@@ -195,6 +205,10 @@ impl Cell {
         [refs_descriptor, bits_descriptor]
     }
 
+    fn refs_descriptor(&self, level_mask: u8) -> u8 {
+        self.references.len() as u8 + if self.is_exotic() { 8 } else { 0 } + level_mask * 32
+    }
+
     /// Serializes the cell data with padding if needed
     pub fn serialize_data(&self) -> Vec<u8> {
         let mut result = self.data.clone();
@@ -218,11 +232,7 @@ impl Cell {
             return d;
         }
 
-        if self.references.is_empty() {
-            0
-        } else {
-            self.references.iter().map(|r| r.depth()).max().unwrap_or(0) + 1
-        }
+        self.depth_at_level(MAX_CELL_LEVEL)
     }
 
     /// Computes the representation hash of the cell
@@ -231,31 +241,95 @@ impl Cell {
             return h;
         }
 
-        let mut hasher = Sha256::new();
+        self.hash_at_level(MAX_CELL_LEVEL)
+    }
 
-        // 1. Add descriptors
-        hasher.update(self.descriptors());
+    /// Computes the hash of the cell at `level`.
+    ///
+    /// Level 0 is the hash of the virtual cell with every pruned branch
+    /// replaced by its original subtree; this is the hash stored in Merkle
+    /// proofs and block ids.
+    pub fn hash_at_level(&self, level: u8) -> [u8; 32] {
+        self.level_hashes()[self.hash_index(level)].0
+    }
 
-        // 2. Add serialized cell data
-        hasher.update(self.serialize_data());
+    /// Computes the depth of the cell at `level`.
+    pub fn depth_at_level(&self, level: u8) -> u16 {
+        self.level_hashes()[self.hash_index(level)].1
+    }
 
-        // 3. Add depth of each reference (2 bytes each)
-        for reference in &self.references {
-            let depth = reference.depth();
-            hasher.update(depth.to_be_bytes());
+    fn hash_index(&self, level: u8) -> usize {
+        let level = level.min(MAX_CELL_LEVEL);
+        (self.level_mask & ((1 << level) - 1)).count_ones() as usize
+    }
+
+    /// Hash and depth for every significant level, lowest level first.
+    fn level_hashes(&self) -> Vec<LevelHash> {
+        let mut result = Vec::with_capacity(self.level_mask.count_ones() as usize + 1);
+
+        // Pruned branches store the hashes of the removed subtree; only the
+        // representation hash is computed from the cell itself.
+        if let Some(ExoticCellKind::PrunedBranch { hashes, depths, .. }) = &self.exotic {
+            result.extend(hashes.iter().copied().zip(depths.iter().copied()));
+            result.push(self.compute_level_hash(self.level(), &[], None));
+            return result;
         }
 
-        // 4. Add hash of each reference
-        for reference in &self.references {
-            hasher.update(reference.hash());
+        let children: Vec<_> = self
+            .references
+            .iter()
+            .map(|reference| (reference.clone(), reference.level_hashes()))
+            .collect();
+        for level in 0..=self.level() {
+            if level > 0 && self.level_mask & (1 << (level - 1)) == 0 {
+                continue;
+            }
+            let previous = result.last().map(|(hash, _)| *hash);
+            result.push(self.compute_level_hash(level, &children, previous));
         }
+        result
+    }
 
-        // 5. Compute SHA-256
-        let result = hasher.finalize();
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(&result);
+    fn compute_level_hash(
+        &self,
+        level: u8,
+        children: &[(Arc<Cell>, Vec<LevelHash>)],
+        previous: Option<[u8; 32]>,
+    ) -> LevelHash {
+        // Merkle cells reference their children one level deeper.
+        let child_level = match self.exotic {
+            Some(ExoticCellKind::MerkleProof { .. } | ExoticCellKind::MerkleUpdate { .. }) => {
+                level + 1
+            }
+            _ => level,
+        };
+        let child_entries: Vec<LevelHash> = children
+            .iter()
+            .map(|(child, hashes)| hashes[child.hash_index(child_level)])
+            .collect();
+
+        let mut hasher = Sha256::new();
+        hasher.update([
+            self.refs_descriptor(self.level_mask & ((1 << level) - 1)),
+            self.descriptors()[1],
+        ]);
+        match previous {
+            Some(hash) => hasher.update(hash),
+            None => hasher.update(self.serialize_data()),
+        }
+        for (_, depth) in &child_entries {
+            hasher.update(depth.to_be_bytes());
+        }
+        for (hash, _) in &child_entries {
+            hasher.update(hash);
+        }
 
-        hash
+        let depth = child_entries
+            .iter()
+            .map(|(_, depth)| depth + 1)
+            .max()
+            .unwrap_or(0);
+        (hasher.finalize().into(), depth)
     }
 
     /// Returns the number of references
@@ -284,14 +358,14 @@ impl Cell {
         }
 
         let exotic = parse_exotic_kind(&data, bit_len, &references)?;
-        let level = exotic_level(&exotic, &references)?;
+        let level_mask = exotic_level_mask(&exotic, &references)?;
 
         Ok(Self {
             data,
             bit_len,
             references,
             exotic: Some(exotic),
-            level,
+            level_mask,
             hash: None,
             depth: None,
         })
@@ -480,23 +554,20 @@ pub(super) fn parse_merkle_update(
     })
 }
 
-pub(super) fn exotic_level(exotic: &ExoticCellKind, references: &[Arc<Cell>]) -> Result<u8> {
-    let level = match exotic {
-        ExoticCellKind::PrunedBranch { level_mask, .. } => 8 - level_mask.leading_zeros() as u8,
+pub(super) fn exotic_level_mask(exotic: &ExoticCellKind, references: &[Arc<Cell>]) -> Result<u8> {
+    let level_mask = match exotic {
+        ExoticCellKind::PrunedBranch { level_mask, .. } => *level_mask,
         ExoticCellKind::LibraryReference { .. } => 0,
-        ExoticCellKind::MerkleProof { .. } => references[0].level().saturating_sub(1),
-        ExoticCellKind::MerkleUpdate { .. } => references
+        ExoticCellKind::MerkleProof { .. } | ExoticCellKind::MerkleUpdate { .. } => references
             .iter()
-            .map(|reference| reference.level().saturating_sub(1))
-            .max()
-            .unwrap_or(0),
+            .fold(0, |mask, reference| mask | (reference.level_mask() >> 1)),
     };
 
-    if level > MAX_CELL_LEVEL {
-        bail!("Invalid exotic cell level: {}", level);
+    if level_mask >= 1 << MAX_CELL_LEVEL {
+        bail!("Invalid exotic cell level mask: {}", level_mask);
     }
 
-    Ok(level)
+    Ok(level_mask)
 }
 
 /// Low-level builder for constructing cells
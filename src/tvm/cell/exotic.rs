@@ -1 +1,311 @@
+//! Construction of pruned branches, Merkle proofs and Merkle updates.
+//!
+//! A proof keeps the cells a reader visited and replaces every other subtree
+//! with a pruned branch that stores its hashes and depths. The level-0 hash
+//! of the pruned tree equals the hash of the original tree, which is what
+//! the enclosing Merkle cell commits to.
 
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+
+use super::cell::{Cell, ExoticCellKind, MAX_CELL_LEVEL};
+use crate::tvm::UsageTree;
+
+const PRUNED_BRANCH_TAG: u8 = 0x01;
+const MERKLE_PROOF_TAG: u8 = 0x03;
+const MERKLE_UPDATE_TAG: u8 = 0x04;
+
+/// Creates the pruned branch standing in for `cell` under `merkle_depth`
+/// Merkle cells.
+///
+/// The branch has level `merkle_depth + 1` and stores the hash and depth of
+/// `cell` at every significant level below that, so the hashes of trees
+/// containing it are unchanged at those levels.
+pub fn pruned_branch(cell: &Cell, merkle_depth: u8) -> Result<Arc<Cell>> {
+    if merkle_depth >= MAX_CELL_LEVEL {
+        bail!(
+            "Pruned branch Merkle depth {} exceeds maximum {}",
+            merkle_depth,
+            MAX_CELL_LEVEL - 1
+        );
+    }
+
+    let level_mask = (cell.level_mask() & ((1 << merkle_depth) - 1)) | (1 << merkle_depth);
+    let levels: Vec<u8> = (0..=merkle_depth)
+        .filter(|&level| level == 0 || level_mask & (1 << (level - 1)) != 0)
+        .collect();
+
+    let mut data = vec![PRUNED_BRANCH_TAG, level_mask];
+    for &level in &levels {
+        data.extend_from_slice(&cell.hash_at_level(level));
+    }
+    for &level in &levels {
+        data.extend_from_slice(&cell.depth_at_level(level).to_be_bytes());
+    }
+    let bit_len = data.len() * 8;
+    Ok(Arc::new(Cell::with_exotic_data(data, bit_len, Vec::new())?))
+}
+
+/// Wraps `root`, usually a tree with pruned branches, into a Merkle proof
+/// of its level-0 hash.
+pub fn merkle_proof(root: &Arc<Cell>) -> Result<Arc<Cell>> {
+    let mut data = vec![MERKLE_PROOF_TAG];
+    data.extend_from_slice(&root.hash_at_level(0));
+    data.extend_from_slice(&root.depth_at_level(0).to_be_bytes());
+    let bit_len = data.len() * 8;
+    Ok(Arc::new(Cell::with_exotic_data(
+        data,
+        bit_len,
+        vec![root.clone()],
+    )?))
+}
+
+/// Wraps `old` and `new`, usually trees with pruned branches, into a Merkle
+/// update between their level-0 hashes.
+pub fn merkle_update(old: &Arc<Cell>, new: &Arc<Cell>) -> Result<Arc<Cell>> {
+    let mut data = vec![MERKLE_UPDATE_TAG];
+    data.extend_from_slice(&old.hash_at_level(0));
+    data.extend_from_slice(&new.hash_at_level(0));
+    data.extend_from_slice(&old.depth_at_level(0).to_be_bytes());
+    data.extend_from_slice(&new.depth_at_level(0).to_be_bytes());
+    let bit_len = data.len() * 8;
+    Ok(Arc::new(Cell::with_exotic_data(
+        data,
+        bit_len,
+        vec![old.clone(), new.clone()],
+    )?))
+}
+
+/// Builds a Merkle proof of a cell tree that keeps only visited cells.
+///
+/// Cells are identified by their representation hash. The root is always
+/// kept; every reference of a kept cell that was not visited becomes a
+/// pruned branch. Subtrees under Merkle cells inside the tree are pruned one
+/// level deeper, as their hashes require.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use tonutils::tvm::{Builder, MerkleProofBuilder};
+///
+/// let mut leaf = Builder::new();
+/// leaf.store_u32(7).unwrap();
+/// let leaf = leaf.build().unwrap();
+/// let mut root = Builder::new();
+/// root.store_ref(leaf.clone()).unwrap();
+/// root.store_ref(Arc::new(Default::default())).unwrap();
+/// let root = root.build().unwrap();
+///
+/// let proof = MerkleProofBuilder::new(root.clone())
+///     .visit(leaf.hash())
+///     .build()
+///     .unwrap();
+/// assert_eq!(proof.reference(0).unwrap().hash_at_level(0), root.hash());
+/// ```
+#[derive(Debug, Clone)]
+pub struct MerkleProofBuilder {
+    root: Arc<Cell>,
+    visited: HashSet<[u8; 32]>,
+}
+
+impl MerkleProofBuilder {
+    /// Starts a proof of `root` that keeps only the root cell.
+    pub fn new(root: Arc<Cell>) -> Self {
+        Self {
+            root,
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Keeps the cell with representation hash `hash`.
+    pub fn visit(&mut self, hash: [u8; 32]) -> &mut Self {
+        self.visited.insert(hash);
+        self
+    }
+
+    /// Keeps every cell in `hashes`.
+    pub fn visit_all(&mut self, hashes: impl IntoIterator<Item = [u8; 32]>) -> &mut Self {
+        self.visited.extend(hashes);
+        self
+    }
+
+    /// Keeps every cell read during `usage`.
+    pub fn visit_usage(&mut self, usage: &UsageTree) -> &mut Self {
+        self.visit_all(usage.hashes())
+    }
+
+    /// Keeps `cell` and its whole subtree.
+    pub fn visit_tree(&mut self, cell: &Arc<Cell>) -> &mut Self {
+        self.visited.extend(tree_hashes(cell));
+        self
+    }
+
+    /// Returns the root with every unvisited subtree pruned.
+    ///
+    /// Its level-0 hash equals the hash of the original root.
+    pub fn pruned_root(&self) -> Result<Arc<Cell>> {
+        Pruner::new(|cell| self.visited.contains(&cell.hash())).prune(&self.root, 0)
+    }
+
+    /// Returns the Merkle proof cell wrapping [`Self::pruned_root`].
+    pub fn build(&self) -> Result<Arc<Cell>> {
+        merkle_proof(&self.pruned_root()?)
+    }
+}
+
+/// Builds a Merkle update between two versions of a cell tree.
+///
+/// Subtrees present in both trees are pruned from both sides, so the update
+/// carries the changed cells of each version plus any cells of the old tree
+/// that were visited while computing the new one.
+#[derive(Debug, Clone)]
+pub struct MerkleUpdateBuilder {
+    old: Arc<Cell>,
+    new: Arc<Cell>,
+    visited: HashSet<[u8; 32]>,
+}
+
+impl MerkleUpdateBuilder {
+    /// Starts an update from `old` to `new`.
+    pub fn new(old: Arc<Cell>, new: Arc<Cell>) -> Self {
+        Self {
+            old,
+            new,
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Keeps the old tree's cell with representation hash `hash` even when
+    /// it is unchanged.
+    pub fn visit(&mut self, hash: [u8; 32]) -> &mut Self {
+        self.visited.insert(hash);
+        self
+    }
+
+    /// Keeps every old tree cell in `hashes`.
+    pub fn visit_all(&mut self, hashes: impl IntoIterator<Item = [u8; 32]>) -> &mut Self {
+        self.visited.extend(hashes);
+        self
+    }
+
+    /// Keeps every old tree cell read during `usage`.
+    pub fn visit_usage(&mut self, usage: &UsageTree) -> &mut Self {
+        self.visit_all(usage.hashes())
+    }
+
+    /// Returns the Merkle update cell.
+    pub fn build(&self) -> Result<Arc<Cell>> {
+        let new_hashes = tree_hashes(&self.new);
+        let keep_old = |cell: &Cell| {
+            let hash = cell.hash();
+            !new_hashes.contains(&hash) || self.visited.contains(&hash)
+        };
+        let old = Pruner::new(keep_old).prune(&self.old, 0)?;
+        // The new side may only refer to cells the old side still carries,
+        // kept or pruned; anything hidden under a pruned branch is resent.
+        let old_hashes = kept_hashes(&self.old, keep_old);
+        let new = Pruner::new(|cell| !old_hashes.contains(&cell.hash())).prune(&self.new, 0)?;
+        merkle_update(&old, &new)
+    }
+}
+
+/// Copies kept cells and prunes the rest, sharing the copies of cells
+/// reachable through several paths.
+struct Pruner<F> {
+    keep: F,
+    copies: HashMap<([u8; 32], u8), Arc<Cell>>,
+}
+
+impl<F: Fn(&Cell) -> bool> Pruner<F> {
+    fn new(keep: F) -> Self {
+        Self {
+            keep,
+            copies: HashMap::new(),
+        }
+    }
+
+    /// Copies the kept cell `cell` found under `merkle_depth` Merkle cells.
+    fn prune(&mut self, cell: &Arc<Cell>, merkle_depth: u8) -> Result<Arc<Cell>> {
+        let key = (cell.hash(), merkle_depth);
+        if let Some(copy) = self.copies.get(&key) {
+            return Ok(copy.clone());
+        }
+
+        let child_depth = match cell.exotic_kind() {
+            Some(ExoticCellKind::MerkleProof { .. } | ExoticCellKind::MerkleUpdate { .. }) => {
+                merkle_depth + 1
+            }
+            _ => merkle_depth,
+        };
+        let mut references = Vec::with_capacity(cell.reference_count());
+        for child in cell.references() {
+            let child = if matches!(
+                child.exotic_kind(),
+                Some(ExoticCellKind::PrunedBranch { .. } | ExoticCellKind::LibraryReference { .. })
+            ) {
+                child.clone()
+            } else if (self.keep)(child) {
+                self.prune(child, child_depth)?
+            } else {
+                pruned_branch(child, child_depth)?
+            };
+            references.push(child);
+        }
+
+        let unchanged = references
+            .iter()
+            .zip(cell.references())
+            .all(|(copy, original)| Arc::ptr_eq(copy, original));
+        let copy = if unchanged {
+            cell.clone()
+        } else if cell.is_exotic() {
+            Arc::new(Cell::with_exotic_data(
+                cell.data().to_vec(),
+                cell.bit_len(),
+                references,
+            )?)
+        } else {
+            let mut copy = Cell::with_data(cell.data().to_vec(), cell.bit_len())?;
+            for reference in references {
+                copy.add_reference(reference)?;
+            }
+            Arc::new(copy)
+        };
+        self.copies.insert(key, copy.clone());
+        Ok(copy)
+    }
+}
+
+/// Representation hashes of the cells a [`Pruner`] with `keep` leaves in
+/// the tree under `root`, either copied or as pruned branches.
+fn kept_hashes(root: &Arc<Cell>, keep: impl Fn(&Cell) -> bool) -> HashSet<[u8; 32]> {
+    let mut seen = HashSet::from([root.hash()]);
+    let mut pending = vec![root];
+    while let Some(cell) = pending.pop() {
+        for child in cell.references() {
+            let expand = !matches!(
+                child.exotic_kind(),
+                Some(ExoticCellKind::PrunedBranch { .. } | ExoticCellKind::LibraryReference { .. })
+            ) && keep(child);
+            if seen.insert(child.hash()) && expand {
+                pending.push(child);
+            }
+        }
+    }
+    seen
+}
+
+/// Representation hashes of every cell under `root`, including `root`.
+fn tree_hashes(root: &Arc<Cell>) -> HashSet<[u8; 32]> {
+    let mut seen = HashSet::new();
+    let mut pending = vec![root];
+    while let Some(cell) = pending.pop() {
+        if seen.insert(cell.hash()) {
+            pending.extend(cell.references());
+        }
+    }
+    seen
+}
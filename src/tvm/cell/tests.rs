@@ -302,4 +302,197 @@ mod tests {
             hex_bytes("6d112e22e9b4f47922b27cb78ffb8c4c3be4be304cdcb9ad24560e3104827eb6")
         );
     }
+
+    #[test]
+    fn test_level_hashes_see_through_pruned_branches() {
+        let mut leaf = Cell::with_data(vec![0xAB], 8).unwrap();
+        leaf.add_reference(Arc::new(Cell::with_data(vec![0xCD], 8).unwrap()))
+            .unwrap();
+        let leaf = Arc::new(leaf);
+        let mut original = Cell::with_data(vec![0x01], 8).unwrap();
+        original.add_reference(leaf.clone()).unwrap();
+
+        let mut pruned_data = vec![0x01, 0x01];
+        pruned_data.extend_from_slice(&leaf.hash());
+        pruned_data.extend_from_slice(&leaf.depth().to_be_bytes());
+        let pruned = Arc::new(Cell::with_exotic_data(pruned_data, 288, Vec::new()).unwrap());
+        let mut proven = Cell::with_data(vec![0x01], 8).unwrap();
+        proven.add_reference(pruned.clone()).unwrap();
+
+        assert_eq!(pruned.hash_at_level(0), leaf.hash());
+        assert_eq!(pruned.depth_at_level(0), 1);
+        assert_eq!(pruned.depth(), 0);
+        assert_eq!(proven.level_mask(), 1);
+        assert_eq!(proven.descriptors()[0], 0x21);
+        assert_eq!(proven.hash_at_level(0), original.hash());
+        assert_eq!(proven.depth_at_level(0), original.depth());
+        assert_ne!(proven.hash(), original.hash());
+
+        // A Merkle proof lowers the level of its child by one.
+        let mut proof_data = vec![0x03];
+        proof_data.extend_from_slice(&original.hash());
+        proof_data.extend_from_slice(&original.depth().to_be_bytes());
+        let proof = Cell::with_exotic_data(proof_data, 280, vec![Arc::new(proven)]).unwrap();
+        assert_eq!(proof.level_mask(), 0);
+        assert_eq!(proof.hash(), proof.hash_at_level(0));
+    }
+
+    fn cell(byte: u8, references: &[Arc<Cell>]) -> Arc<Cell> {
+        let mut cell = Cell::with_data(vec![byte], 8).unwrap();
+        for reference in references {
+            cell.add_reference(reference.clone()).unwrap();
+        }
+        Arc::new(cell)
+    }
+
+    fn is_pruned(cell: &Cell) -> bool {
+        matches!(
+            cell.exotic_kind(),
+            Some(ExoticCellKind::PrunedBranch { .. })
+        )
+    }
+
+    #[test]
+    fn test_merkle_proof_builder_prunes_unvisited_subtrees() {
+        let kept_leaf = cell(0x11, &[]);
+        let hidden_leaf = cell(0x22, &[]);
+        let kept = cell(0x33, &[kept_leaf.clone(), hidden_leaf.clone()]);
+        let hidden = cell(0x44, &[cell(0x55, &[])]);
+        let root = cell(0x66, &[kept.clone(), hidden.clone()]);
+
+        let proof = MerkleProofBuilder::new(root.clone())
+            .visit_all([kept.hash(), kept_leaf.hash()])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            proof.exotic_kind(),
+            Some(&ExoticCellKind::MerkleProof {
+                proof_hash: root.hash(),
+                proof_depth: root.depth(),
+            })
+        );
+        assert_eq!(proof.level(), 0);
+        let proven = proof.reference(0).unwrap();
+        assert_eq!(proven.level_mask(), 1);
+        assert_eq!(proven.hash_at_level(0), root.hash());
+        assert_eq!(proven.depth_at_level(0), root.depth());
+
+        let proven_kept = proven.reference(0).unwrap();
+        assert!(!is_pruned(proven_kept));
+        assert_eq!(proven_kept.reference(0).unwrap(), &kept_leaf);
+        assert!(is_pruned(proven_kept.reference(1).unwrap()));
+        let proven_hidden = proven.reference(1).unwrap();
+        assert!(is_pruned(proven_hidden));
+        assert_eq!(proven_hidden.hash_at_level(0), hidden.hash());
+        assert_eq!(proven_hidden.depth_at_level(0), 1);
+
+        // The pruned tree survives a BoC round trip unchanged.
+        let boc = crate::tvm::serialize_boc(&proof, false).unwrap();
+        assert_eq!(
+            crate::tvm::deserialize_boc(&boc).unwrap().hash(),
+            proof.hash()
+        );
+
+        let whole = MerkleProofBuilder::new(root.clone())
+            .visit_tree(&root)
+            .build()
+            .unwrap();
+        assert_eq!(whole.reference(0).unwrap(), &root);
+    }
+
+    #[test]
+    fn test_merkle_proof_builder_prunes_deeper_under_merkle_cells() {
+        let inner_leaf = cell(0x77, &[]);
+        let inner_root = cell(0x88, std::slice::from_ref(&inner_leaf));
+        let inner = merkle_proof(&inner_root).unwrap();
+        let root = cell(0x99, &[inner.clone(), cell(0xAA, &[])]);
+
+        let proof = MerkleProofBuilder::new(root.clone())
+            .visit_all([inner.hash(), inner_root.hash()])
+            .build()
+            .unwrap();
+
+        let proven = proof.reference(0).unwrap();
+        assert_eq!(proven.hash_at_level(0), root.hash());
+        let proven_inner_root = proven.reference(0).unwrap().reference(0).unwrap();
+        let pruned = proven_inner_root.reference(0).unwrap();
+        assert_eq!(
+            pruned.exotic_kind(),
+            Some(&ExoticCellKind::PrunedBranch {
+                level_mask: 0b10,
+                hashes: vec![inner_leaf.hash()],
+                depths: vec![0],
+            })
+        );
+        assert_eq!(pruned.level(), 2);
+        assert_eq!(proven_inner_root.hash_at_level(0), inner_root.hash());
+
+        assert!(pruned_branch(&root, MAX_CELL_LEVEL).is_err());
+    }
+
+    #[test]
+    fn test_merkle_update_builder_prunes_shared_subtrees() {
+        let shared = cell(0x01, &[cell(0x02, &[])]);
+        let old_changed = cell(0x03, &[]);
+        let new_changed = cell(0x04, &[]);
+        let old = cell(0x05, &[shared.clone(), old_changed.clone()]);
+        let new = cell(0x05, &[shared.clone(), new_changed.clone()]);
+
+        let update = MerkleUpdateBuilder::new(old.clone(), new.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            update.exotic_kind(),
+            Some(&ExoticCellKind::MerkleUpdate {
+                old_hash: old.hash(),
+                new_hash: new.hash(),
+                old_depth: old.depth(),
+                new_depth: new.depth(),
+            })
+        );
+        assert_eq!(update.level(), 0);
+        for (side, original, changed) in [(0, &old, &old_changed), (1, &new, &new_changed)] {
+            let side = update.reference(side).unwrap();
+            assert_eq!(side.hash_at_level(0), original.hash());
+            assert!(is_pruned(side.reference(0).unwrap()));
+            assert_eq!(side.reference(1).unwrap(), changed);
+        }
+
+        let visited = MerkleUpdateBuilder::new(old.clone(), new)
+            .visit(shared.hash())
+            .build()
+            .unwrap();
+        let old_side = visited.reference(0).unwrap();
+        assert!(!is_pruned(old_side.reference(0).unwrap()));
+        assert!(is_pruned(
+            old_side.reference(0).unwrap().reference(0).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_merkle_update_builder_resends_cells_hidden_in_old_side() {
+        let leaf = cell(0x01, &[]);
+        let moved = cell(0x02, std::slice::from_ref(&leaf));
+        let old = cell(0x03, std::slice::from_ref(&moved));
+        let new = cell(
+            0x04,
+            &[moved.clone(), cell(0x05, std::slice::from_ref(&leaf))],
+        );
+
+        let update = MerkleUpdateBuilder::new(old.clone(), new.clone())
+            .build()
+            .unwrap();
+
+        // The old side only carries `moved` as a pruned branch, so the new
+        // side can refer to `moved` but must include `leaf` itself.
+        let old_side = update.reference(0).unwrap();
+        assert_eq!(old_side.hash_at_level(0), old.hash());
+        assert!(is_pruned(old_side.reference(0).unwrap()));
+        let new_side = update.reference(1).unwrap();
+        assert_eq!(new_side.hash_at_level(0), new.hash());
+        assert!(is_pruned(new_side.reference(0).unwrap()));
+        assert_eq!(new_side.reference(1).unwrap().reference(0).unwrap(), &leaf);
+    }
 }
@@ -17,6 +17,7 @@ mod bits;
 mod compat;
 mod hashmap;
 mod labels;
+mod path;
 #[cfg(test)]
 mod tests;
 
@@ -24,3 +25,4 @@ use hashmap::*;
 use labels::*;
 
 pub use hashmap::*;
+pub use path::*;
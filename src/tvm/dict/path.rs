@@ -0,0 +1,40 @@
+use super::*;
+
+use crate::tvm::cell::Cell;
+use crate::tvm::slice::PrunedBranchError;
+use std::sync::Arc;
+
+/// Finds the leaf for `key` in a `Hashmap n X` or `HashmapAug n X Y` edge
+/// rooted at `root`, loading only the cells on the key path.
+///
+/// Returns the leaf slice positioned right after the edge label, or `None`
+/// when the key is absent. Sibling subtrees are never loaded, so the lookup
+/// works on Merkle proof trees where they are pruned; a pruned branch on the
+/// path fails with [`PrunedBranchError`], and any other exotic cell on the
+/// path is an error.
+pub fn lookup_hashmap_path(root: Arc<Cell>, key: &BitKey) -> Result<Option<Slice>> {
+    let mut cell = root;
+    let mut depth = 0;
+    loop {
+        PrunedBranchError::check(&cell)?;
+        if cell.is_exotic() {
+            bail!("Dictionary path reaches an exotic cell after {depth} key bits");
+        }
+        let mut slice = Slice::new(cell);
+        let label = load_label(&mut slice, key.bit_len() - depth)?;
+        for (offset, bit) in label.iter().enumerate() {
+            if key.bit(depth + offset)? != *bit {
+                return Ok(None);
+            }
+        }
+        depth += label.len();
+        if depth == key.bit_len() {
+            return Ok(Some(slice));
+        }
+
+        let left = slice.load_reference()?;
+        let right = slice.load_reference()?;
+        cell = if key.bit(depth)? { right } else { left };
+        depth += 1;
+    }
+}
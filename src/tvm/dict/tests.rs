@@ -4,6 +4,8 @@ use super::*;
 #[allow(clippy::module_inception)]
 mod tests {
     use super::*;
+    use crate::tvm::Cell;
+    use std::sync::Arc;
 
     #[test]
     fn bit_key_canonicalizes_and_orders() {
@@ -432,4 +434,46 @@ mod tests {
             .is_err()
         );
     }
+
+    #[test]
+    fn path_lookup_skips_pruned_siblings() {
+        let mut dict = HashmapE::new(8);
+        for (key, value) in [(0x01u64, 10u8), (0x80, 20), (0x81, 30)] {
+            dict.insert_bit_key(BitKey::from_u64(key, 8).unwrap(), value)
+                .unwrap();
+        }
+        let mut builder = Builder::new();
+        builder
+            .store_hashmap_e_with(&dict, |builder, value| {
+                builder.store_uint::<u8>(*value)?;
+                Ok(())
+            })
+            .unwrap();
+        let root = builder.build().unwrap().reference(0).unwrap().clone();
+        let lookup = |root: &Arc<Cell>, key: u64| {
+            lookup_hashmap_path(root.clone(), &BitKey::from_u64(key, 8).unwrap())
+                .map(|leaf| leaf.map(|mut leaf| leaf.load_uint::<u8>().unwrap()))
+        };
+
+        assert_eq!(lookup(&root, 0x81).unwrap(), Some(30));
+        assert_eq!(lookup(&root, 0x01).unwrap(), Some(10));
+        assert_eq!(lookup(&root, 0x82).unwrap(), None);
+
+        // Replace the left branch with a pruned branch, as a Merkle proof would.
+        let left = root.reference(0).unwrap();
+        let mut pruned = vec![0x01, 0x01];
+        pruned.extend_from_slice(&left.hash());
+        pruned.extend_from_slice(&left.depth().to_be_bytes());
+        let pruned = Arc::new(Cell::with_exotic_data(pruned, 288, Vec::new()).unwrap());
+        let mut proof = Cell::with_data(root.data().to_vec(), root.bit_len()).unwrap();
+        proof.add_reference(pruned).unwrap();
+        proof
+            .add_reference(root.reference(1).unwrap().clone())
+            .unwrap();
+        let proof = Arc::new(proof);
+
+        assert_eq!(proof.hash_at_level(0), root.hash());
+        assert_eq!(lookup(&proof, 0x80).unwrap(), Some(20));
+        assert!(lookup(&proof, 0x01).is_err());
+    }
 }
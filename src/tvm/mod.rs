@@ -9,6 +9,8 @@
 //! - ordinary cells store at most 1023 bits and 4 references;
 //! - builders and slices are bounds checked and return errors instead of
 //!   truncating data;
+//! - slices refuse to read pruned branch cells and name the missing subtree
+//!   in a [`PrunedBranchError`];
 //! - BoC decoding preserves supported exotic cell kinds and rejects cache-bit
 //!   payloads until the crate has a lossless cache-bit representation;
 //! - dictionaries use fixed-width bit keys and canonical label encoding where
@@ -30,17 +32,24 @@ pub mod stack;
 pub mod tests;
 #[doc(hidden)]
 pub mod uint;
+pub mod usage;
+pub mod vm;
 
 pub use address::{Address, ExternalAddress};
 pub use boc::{
-    BocInspection, base64_to_boc, boc_to_base64, boc_to_hex, deserialize_boc,
-    deserialize_boc_roots, hex_to_boc, inspect_boc, serialize_boc,
+    BocInspection, BocWriter, LazyBoc, LazyCell, LazySlice, base64_to_boc, boc_to_base64,
+    boc_to_hex, deserialize_boc, deserialize_boc_roots, hex_to_boc, inspect_boc, serialize_boc,
+    serialize_boc_roots,
 };
 pub use builder::Builder;
-pub use cell::{Cell, CellBuilder, ExoticCellKind, MAX_CELL_BITS, MAX_CELL_LEVEL, MAX_CELL_REFS};
+pub use cell::{
+    Cell, CellBuilder, ExoticCellKind, MAX_CELL_BITS, MAX_CELL_LEVEL, MAX_CELL_REFS,
+    MerkleProofBuilder, MerkleUpdateBuilder, merkle_proof, merkle_update, pruned_branch,
+};
 pub use dict::{
     BitKey, Dict, DictKey, DictValue, HashmapAug, HashmapAugE, HashmapAugFork, HashmapAugLeaf,
-    HashmapE,
+    HashmapE, lookup_hashmap_path,
 };
-pub use slice::Slice;
+pub use slice::{PrunedBranchError, Slice};
 pub use stack::{TvmStack, TvmStackEntry};
+pub use usage::UsageTree;
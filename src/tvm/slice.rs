@@ -3,12 +3,39 @@
 //! A Slice provides a way to read data from a Cell sequentially,
 //! tracking the current position in both bits and references.
 
-use crate::tvm::cell::{Cell, MAX_CELL_BITS};
+use crate::tvm::cell::{Cell, ExoticCellKind, MAX_CELL_BITS};
 use crate::tvm::uint::UnsignedInteger;
+use crate::tvm::usage;
 use anyhow::{Result, bail};
 use num_bigint::{BigInt, BigUint};
 use std::sync::Arc;
 
+/// Error returned when a [`Slice`] reads a pruned branch cell.
+///
+/// Pruned branches stand in for subtrees left out of a Merkle proof, so the
+/// value being decoded is not part of the proof.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Cannot read pruned branch of cell {}", hex::encode(.hash))]
+pub struct PrunedBranchError {
+    /// Hash of the pruned subtree.
+    pub hash: [u8; 32],
+    /// Depth of the pruned subtree.
+    pub depth: u16,
+}
+
+impl PrunedBranchError {
+    /// Returns an error when `cell` is a pruned branch.
+    pub fn check(cell: &Cell) -> std::result::Result<(), Self> {
+        match cell.exotic_kind() {
+            Some(ExoticCellKind::PrunedBranch { .. }) => Err(Self {
+                hash: cell.hash_at_level(0),
+                depth: cell.depth_at_level(0),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// A slice for reading data from a cell
 #[derive(Debug, Clone)]
 pub struct Slice {
@@ -18,6 +45,8 @@ pub struct Slice {
     bit_pos: usize,
     /// Current reference position
     ref_pos: usize,
+    /// Whether the cell's data or references were read
+    accessed: bool,
 }
 
 impl Slice {
@@ -27,16 +56,19 @@ impl Slice {
             cell,
             bit_pos: 0,
             ref_pos: 0,
+            accessed: false,
         }
     }
 
     /// Returns the number of remaining bits
     pub fn remaining_bits(&self) -> usize {
+        self.observe();
         self.cell.bit_len().saturating_sub(self.bit_pos)
     }
 
     /// Returns the number of remaining references
     pub fn remaining_refs(&self) -> usize {
+        self.observe();
         self.cell.reference_count().saturating_sub(self.ref_pos)
     }
 
@@ -47,6 +79,7 @@ impl Slice {
 
     /// Loads a single bit
     pub fn load_bit(&mut self) -> Result<bool> {
+        self.access()?;
         if self.remaining_bits() == 0 {
             bail!("No more bits to read");
         }
@@ -67,6 +100,9 @@ impl Slice {
 
     /// Loads multiple bits into a byte vector
     pub fn load_bits(&mut self, n: usize) -> Result<Vec<u8>> {
+        if n > 0 {
+            self.access()?;
+        }
         if n > self.remaining_bits() {
             bail!(
                 "Not enough bits remaining: requested {}, available {}",
@@ -186,6 +222,7 @@ impl Slice {
 
     /// Loads a reference to another cell
     pub fn load_reference(&mut self) -> Result<Arc<Cell>> {
+        self.access()?;
         if self.remaining_refs() == 0 {
             bail!("No more references to read");
         }
@@ -202,6 +239,10 @@ impl Slice {
 
     /// Preloads a reference without advancing the position
     pub fn preload_reference(&self, index: usize) -> Result<Arc<Cell>> {
+        if !self.accessed {
+            PrunedBranchError::check(&self.cell)?;
+            usage::record(&self.cell);
+        }
         let actual_index = self.ref_pos + index;
         self.cell
             .reference(actual_index)
@@ -211,6 +252,9 @@ impl Slice {
 
     /// Skips a number of bits
     pub fn skip_bits(&mut self, n: usize) -> Result<()> {
+        if n > 0 {
+            self.access()?;
+        }
         if n > self.remaining_bits() {
             bail!(
                 "Cannot skip {} bits: only {} remaining",
@@ -224,6 +268,9 @@ impl Slice {
 
     /// Skips a number of references
     pub fn skip_refs(&mut self, n: usize) -> Result<()> {
+        if n > 0 {
+            self.access()?;
+        }
         if n > self.remaining_refs() {
             bail!(
                 "Cannot skip {} references: only {} remaining",
@@ -262,6 +309,7 @@ impl Slice {
             cell: self.cell.clone(),
             bit_pos: self.bit_pos,
             ref_pos: self.ref_pos,
+            accessed: self.accessed,
         }
     }
 
@@ -352,6 +400,25 @@ impl Slice {
 
         Ok(result)
     }
+
+    /// Checks the cell on its first read and records it in the usage tree
+    /// being recorded on this thread, if any.
+    fn access(&mut self) -> Result<()> {
+        if !self.accessed {
+            PrunedBranchError::check(&self.cell)?;
+            usage::record(&self.cell);
+            self.accessed = true;
+        }
+        Ok(())
+    }
+
+    /// Records the cell when its length is checked before any read: a
+    /// decoder that only sees an empty cell still needs it in a proof.
+    fn observe(&self) {
+        if !self.accessed {
+            usage::record(&self.cell);
+        }
+    }
 }
 
 impl From<Arc<Cell>> for Slice {
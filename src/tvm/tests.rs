@@ -483,3 +483,141 @@ fn test_boc_conversions() {
     let from_b64 = base64_to_boc(&b64).unwrap();
     assert_eq!(cell.hash(), from_b64.hash());
 }
+
+/// Dictionary with values `!key` under 8-bit keys.
+fn dictionary_root(keys: &[u64]) -> Arc<Cell> {
+    let mut dict = HashmapE::new(8);
+    for &key in keys {
+        dict.insert_bit_key(BitKey::from_u64(key, 8).unwrap(), !(key as u8))
+            .unwrap();
+    }
+    let mut builder = Builder::new();
+    builder
+        .store_hashmap_e_with(&dict, |builder, value| {
+            builder.store_uint::<u8>(*value)?;
+            Ok(())
+        })
+        .unwrap();
+    builder.build().unwrap().reference(0).unwrap().clone()
+}
+
+fn lookup(root: &Arc<Cell>, key: u64) -> anyhow::Result<Option<u8>> {
+    match lookup_hashmap_path(root.clone(), &BitKey::from_u64(key, 8)?)? {
+        Some(mut leaf) => Ok(Some(leaf.load_uint::<u8>()?)),
+        None => Ok(None),
+    }
+}
+
+/// Test that a recorded lookup can be replayed against a proof of the cells
+/// it read
+#[test]
+fn test_usage_tree_feeds_merkle_proof() {
+    let root = dictionary_root(&[0x01, 0x02, 0x80, 0x81, 0xC0]);
+
+    let (value, usage) = UsageTree::record(|| lookup(&root, 0x81));
+    assert_eq!(value.unwrap(), Some(0x7E));
+    assert!(usage.contains(&root));
+    assert!(usage.len() < 9);
+    assert_eq!(
+        usage.bit_len(),
+        usage.cells().map(|cell| cell.bit_len() as u64).sum::<u64>()
+    );
+
+    let proof = MerkleProofBuilder::new(root.clone())
+        .visit_usage(&usage)
+        .build()
+        .unwrap();
+    let proven = proof.reference(0).unwrap();
+    assert_eq!(proven.hash_at_level(0), root.hash());
+    assert_eq!(lookup(proven, 0x81).unwrap(), Some(0x7E));
+
+    let error = lookup(proven, 0x01).unwrap_err();
+    let pruned = error.downcast_ref::<PrunedBranchError>().unwrap();
+    assert_eq!(pruned.hash, root.reference(0).unwrap().hash());
+}
+
+/// Test that references loaded without being read are not recorded
+#[test]
+fn test_usage_tree_records_only_read_cells() {
+    let root = dictionary_root(&[0x01, 0x80]);
+    let left = root.reference(0).unwrap().clone();
+
+    let ((), usage) = UsageTree::record(|| {
+        let mut slice = Slice::new(root.clone());
+        slice.skip_refs(1).unwrap();
+        Slice::new(slice.load_reference().unwrap())
+            .load_bit()
+            .unwrap();
+    });
+    assert_eq!(usage.len(), 2);
+    assert!(usage.contains(&root));
+    assert!(usage.contains(root.reference(1).unwrap()));
+    assert!(!usage.contains(&left));
+
+    // Nothing is recorded outside a recording.
+    Slice::new(left.clone()).load_bit().unwrap();
+    let ((), empty) = UsageTree::record(|| ());
+    assert!(empty.is_empty());
+}
+
+/// Test that a decoder which only checks that a cell is empty still keeps
+/// it in the proof
+#[test]
+fn test_usage_tree_records_empty_cells() {
+    use crate::tlb::{CellRef, RawCell, TlbDeserialize};
+
+    let empty = Builder::new().build().unwrap();
+    let mut root = Builder::new();
+    root.store_ref(empty.clone()).unwrap();
+    root.store_ref(dictionary_root(&[0x01])).unwrap();
+    let root = root.build().unwrap();
+    let decode = |cell: &Arc<Cell>| {
+        let mut slice = Slice::new(cell.clone());
+        let value = CellRef::<RawCell>::load_tlb(&mut slice)?;
+        anyhow::Ok(value.0.0)
+    };
+
+    let (value, usage) = UsageTree::record(|| decode(&root));
+    assert_eq!(value.unwrap(), empty);
+    assert!(usage.contains(&empty));
+
+    let proof = MerkleProofBuilder::new(root.clone())
+        .visit_usage(&usage)
+        .build()
+        .unwrap();
+    let proven = proof.reference(0).unwrap();
+    assert!(matches!(
+        proven.reference(1).unwrap().exotic_kind(),
+        Some(ExoticCellKind::PrunedBranch { .. })
+    ));
+    assert_eq!(decode(proven).unwrap(), empty);
+}
+
+/// Test that nested recordings also count toward the enclosing one
+#[test]
+fn test_usage_tree_nesting_and_unwinding() {
+    let root = dictionary_root(&[0x01, 0x80]);
+    let read = |cell: &Arc<Cell>| {
+        Slice::new(cell.clone()).load_bit().unwrap();
+    };
+
+    let (inner, outer) = UsageTree::record(|| {
+        read(&root);
+        let ((), inner) = UsageTree::record(|| read(root.reference(0).unwrap()));
+        let panicked = std::panic::catch_unwind(|| {
+            UsageTree::record(|| {
+                read(root.reference(1).unwrap());
+                panic!("decoder failed");
+            })
+        });
+        assert!(panicked.is_err());
+        inner
+    });
+    assert_eq!(inner.len(), 1);
+    assert_eq!(outer.len(), 3);
+
+    let mut merged = UsageTree::default();
+    merged.merge(&inner);
+    merged.merge(&outer);
+    assert_eq!(merged.hashes(), outer.hashes());
+}
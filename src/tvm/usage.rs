@@ -0,0 +1,135 @@
+//! Recording of the cells a decoder reads.
+//!
+//! [`UsageTree::record`] runs a closure and collects every cell whose data,
+//! references or length were read through a [`Slice`](crate::tvm::Slice) on
+//! the current thread meanwhile. Cells that were only passed around, such as
+//! references loaded but never opened, are not recorded. The recorded set is
+//! what a Merkle proof of the decoded value has to keep; see
+//! [`MerkleProofBuilder::visit_usage`](crate::tvm::MerkleProofBuilder::visit_usage).
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::tvm::cell::Cell;
+
+thread_local! {
+    /// Active recordings of this thread, innermost last.
+    static RECORDINGS: RefCell<Vec<CellSet>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Recorded cells keyed by address, so recording never hashes a tree.
+type CellSet = HashMap<usize, Arc<Cell>>;
+
+/// Cells read through [`Slice`](crate::tvm::Slice) during a recording.
+///
+/// # Example
+///
+/// ```rust
+/// use tonutils::tvm::{Builder, Slice, UsageTree};
+///
+/// let mut child = Builder::new();
+/// child.store_u8(1).unwrap();
+/// let child = child.build().unwrap();
+/// let mut root = Builder::new();
+/// root.store_ref(child).unwrap();
+/// let root = root.build().unwrap();
+///
+/// let (child, usage) = UsageTree::record(|| Slice::new(root.clone()).load_reference());
+/// assert!(usage.contains(&root));
+/// assert!(!usage.contains(&child.unwrap()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UsageTree {
+    cells: CellSet,
+}
+
+impl UsageTree {
+    /// Runs `f` and returns its result with the cells it read on this thread.
+    ///
+    /// Recordings nest: cells read inside an inner recording also belong to
+    /// the enclosing one.
+    pub fn record<T>(f: impl FnOnce() -> T) -> (T, Self) {
+        let recording = Recording::start();
+        let value = f();
+        let cells = recording.cells();
+        (value, Self { cells })
+    }
+
+    /// Number of recorded cells.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Whether no cell was read.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Whether `cell` was read.
+    pub fn contains(&self, cell: &Arc<Cell>) -> bool {
+        self.cells.contains_key(&address(cell))
+    }
+
+    /// Iterates over the recorded cells in no particular order.
+    pub fn cells(&self) -> impl Iterator<Item = &Arc<Cell>> {
+        self.cells.values()
+    }
+
+    /// Representation hashes of the recorded cells.
+    pub fn hashes(&self) -> HashSet<[u8; 32]> {
+        self.cells.values().map(|cell| cell.hash()).collect()
+    }
+
+    /// Total data bits of the recorded cells.
+    pub fn bit_len(&self) -> u64 {
+        self.cells.values().map(|cell| cell.bit_len() as u64).sum()
+    }
+
+    /// Adds the cells recorded in `other`.
+    pub fn merge(&mut self, other: &UsageTree) {
+        for (key, cell) in &other.cells {
+            self.cells.entry(*key).or_insert_with(|| cell.clone());
+        }
+    }
+}
+
+/// Adds `cell` to the innermost recording of this thread, if any.
+pub(crate) fn record(cell: &Arc<Cell>) {
+    RECORDINGS.with_borrow_mut(|recordings| {
+        if let Some(cells) = recordings.last_mut() {
+            cells.entry(address(cell)).or_insert_with(|| cell.clone());
+        }
+    });
+}
+
+fn address(cell: &Arc<Cell>) -> usize {
+    Arc::as_ptr(cell) as usize
+}
+
+/// Active recording; ending it, even by unwinding, hands its cells to the
+/// enclosing recording.
+struct Recording;
+
+impl Recording {
+    fn start() -> Self {
+        RECORDINGS.with_borrow_mut(|recordings| recordings.push(CellSet::new()));
+        Self
+    }
+
+    fn cells(self) -> CellSet {
+        RECORDINGS.with_borrow(|recordings| recordings.last().cloned().unwrap_or_default())
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        RECORDINGS.with_borrow_mut(|recordings| {
+            if let Some(cells) = recordings.pop()
+                && let Some(outer) = recordings.last_mut()
+            {
+                outer.extend(cells);
+            }
+        });
+    }
+}
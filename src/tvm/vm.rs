@@ -0,0 +1,50 @@
+//! Pure Rust TVM interpreter for offline get-method execution.
+//!
+//! The interpreter executes contract code against a `StateInit`-style pair of
+//! code and data cells without a liteserver round trip. It implements the
+//! codepage 0 instruction subset used by FunC and Fift-assembled contracts for
+//! read-only get-methods: stack manipulation, 257-bit integer arithmetic,
+//! cell/slice/builder primitives, continuations and loops, exceptions,
+//! dictionaries, tuples, `c7` context parameters, hashing and signature checks.
+//!
+//! Important invariants:
+//!
+//! - integers are range checked to signed 257 bits and overflow raises TVM
+//!   exception 4 unless a quiet opcode variant is used;
+//! - basic gas follows `10 + instruction bits + 5 * instruction refs`, with
+//!   cell load, cell creation, implicit jump/return and exception surcharges;
+//! - out-of-gas terminates with exit code `-14` and cannot be caught;
+//! - unsupported opcodes raise TVM exception 6 (invalid opcode) instead of
+//!   being skipped, so partial coverage never produces silent wrong results.
+//!
+//! The action-producing primitives (`SENDRAWMSG`, `RAWRESERVE`, `SETCODE`)
+//! append to `c5` so transaction-level emulation can reuse the same engine.
+//! Library cells, `RUNVM`, and extended codepages are not implemented.
+
+pub(super) use crate::tvm::cell::{Cell, CellBuilder, MAX_CELL_BITS, MAX_CELL_REFS};
+pub(super) use crate::tvm::stack::{TvmStack, TvmStackEntry};
+pub(super) use anyhow::{Result, bail};
+pub(super) use num_bigint::{BigInt, BigUint, Sign};
+pub(super) use std::sync::Arc;
+
+mod cont;
+mod dict_ops;
+mod exec;
+mod get_method;
+mod ops_arith;
+mod ops_cell;
+mod ops_control;
+mod ops_dict;
+mod ops_misc;
+mod ops_stack;
+mod state;
+#[cfg(test)]
+mod tests;
+mod value;
+
+pub(crate) use state::{VmError, VmInit, VmResult, VmState};
+
+pub use cont::{ContData, Continuation, SaveList};
+pub use get_method::{GetMethodRunner, SmartContractContext, VmRunResult};
+pub use state::{DEFAULT_GET_METHOD_GAS_LIMIT, EXIT_CODE_OUT_OF_GAS, VmExitCode, VmGasLimits};
+pub use value::{VmBuilder, VmSlice, VmValue};
@@ -0,0 +1,120 @@
+use super::*;
+
+/// Control registers saved inside a continuation (`c0`..`c7`).
+#[derive(Debug, Clone, Default)]
+pub struct SaveList {
+    regs: [Option<VmValue>; 8],
+}
+
+impl SaveList {
+    pub(crate) fn get(&self, index: usize) -> Option<&VmValue> {
+        self.regs.get(index).and_then(Option::as_ref)
+    }
+
+    pub(crate) fn has(&self, index: usize) -> bool {
+        self.get(index).is_some()
+    }
+
+    /// Stores a register value, replacing any previous one.
+    pub(crate) fn set(&mut self, index: usize, value: VmValue) {
+        self.regs[index] = Some(value);
+    }
+
+    /// Stores a register value only when the slot is still empty.
+    pub(crate) fn define(&mut self, index: usize, value: VmValue) {
+        if self.regs[index].is_none() {
+            self.regs[index] = Some(value);
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (usize, &VmValue)> {
+        self.regs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| value.as_ref().map(|value| (index, value)))
+    }
+}
+
+/// Saved registers, captured stack and argument count of a continuation.
+#[derive(Debug, Clone, Default)]
+pub struct ContData {
+    pub(crate) save: SaveList,
+    pub(crate) stack: Option<Vec<VmValue>>,
+    pub(crate) nargs: Option<usize>,
+}
+
+/// A TVM continuation.
+///
+/// Only ordinary continuations carry code; loop continuations wrap other
+/// continuations and re-enter them until their exit condition holds.
+#[derive(Debug, Clone)]
+pub enum Continuation {
+    /// Code with saved registers and an optional captured stack.
+    Ordinary { code: VmSlice, data: ContData },
+    /// Terminates execution with the given exit code.
+    Quit(i32),
+    /// Default exception handler: terminates with the thrown exit code.
+    ExcQuit,
+    /// Runs `body` `count` more times, then jumps to `after`.
+    Repeat {
+        body: Arc<Continuation>,
+        after: Arc<Continuation>,
+        count: i64,
+    },
+    /// Runs `body` forever.
+    Again { body: Arc<Continuation> },
+    /// Runs `body` until it leaves `true` on the stack.
+    Until {
+        body: Arc<Continuation>,
+        after: Arc<Continuation>,
+    },
+    /// Runs `cond`, then `body` while `cond` leaves `true` on the stack.
+    While {
+        cond: Arc<Continuation>,
+        body: Arc<Continuation>,
+        after: Arc<Continuation>,
+        check_cond: bool,
+    },
+    /// A non-ordinary continuation extended with saved registers or stack.
+    Envelope {
+        inner: Arc<Continuation>,
+        data: ContData,
+    },
+}
+
+impl Continuation {
+    pub(crate) fn ordinary(code: VmSlice) -> Self {
+        Self::Ordinary {
+            code,
+            data: ContData::default(),
+        }
+    }
+
+    pub(crate) fn data(&self) -> Option<&ContData> {
+        match self {
+            Self::Ordinary { data, .. } | Self::Envelope { data, .. } => Some(data),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn has_c0(&self) -> bool {
+        self.data().is_some_and(|data| data.save.has(0))
+    }
+
+    /// Returns a copy with mutable continuation data, wrapping
+    /// non-ordinary continuations into an envelope when necessary.
+    pub(crate) fn with_data(self: &Arc<Self>, f: impl FnOnce(&mut ContData)) -> Arc<Self> {
+        let mut cont = match self.as_ref() {
+            Self::Ordinary { .. } | Self::Envelope { .. } => self.as_ref().clone(),
+            _ => Self::Envelope {
+                inner: self.clone(),
+                data: ContData::default(),
+            },
+        };
+        match &mut cont {
+            Self::Ordinary { data, .. } | Self::Envelope { data, .. } => f(data),
+            _ => unreachable!("continuation data was just forced"),
+        }
+        Arc::new(cont)
+    }
+}
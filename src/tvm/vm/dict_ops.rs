@@ -0,0 +1,465 @@
+//! Dictionary algorithms over raw `HashmapE` cells.
+//!
+//! Lookups walk labels without materializing the dictionary; updates rebuild
+//! only the cells on the path to the modified leaf and use canonical label
+//! encoding. Every visited cell is charged as a cell load and every rebuilt
+//! cell as a cell creation.
+
+use super::*;
+
+/// Value stored by a dictionary update.
+#[derive(Debug, Clone)]
+pub(crate) enum DictValue {
+    Slice(VmSlice),
+    Ref(Arc<Cell>),
+    Builder(VmBuilder),
+}
+
+/// Which existing-key condition a dictionary update requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DictSetMode {
+    /// Insert or overwrite.
+    Set,
+    /// Only overwrite an existing key.
+    Replace,
+    /// Only insert a missing key.
+    Add,
+}
+
+fn label_len_bits(max: usize) -> usize {
+    (usize::BITS - max.leading_zeros()) as usize
+}
+
+/// Reads an `HmLabel` with at most `max` bits.
+fn read_label(slice: &mut VmSlice, max: usize) -> Option<Vec<bool>> {
+    let len_bits = label_len_bits(max);
+    if slice.load_small(1)? == 0 {
+        let mut len = 0;
+        while slice.load_small(1)? == 1 {
+            len += 1;
+        }
+        if len > max || slice.bits() < len {
+            return None;
+        }
+        let bits = (0..len)
+            .map(|offset| slice.bit_at(offset))
+            .collect::<Vec<_>>();
+        slice.advance(len, 0);
+        Some(bits)
+    } else if slice.load_small(1)? == 0 {
+        let len = slice.load_small(len_bits)? as usize;
+        if len > max || slice.bits() < len {
+            return None;
+        }
+        let bits = (0..len)
+            .map(|offset| slice.bit_at(offset))
+            .collect::<Vec<_>>();
+        slice.advance(len, 0);
+        Some(bits)
+    } else {
+        let bit = slice.load_small(1)? == 1;
+        let len = slice.load_small(len_bits)? as usize;
+        if len > max {
+            return None;
+        }
+        Some(vec![bit; len])
+    }
+}
+
+/// Writes a canonical `HmLabel` for `label` with at most `max` bits.
+fn write_label(builder: &mut VmBuilder, label: &[bool], max: usize) {
+    let len = label.len();
+    let len_bits = label_len_bits(max);
+    let same = len > 1 && label.iter().all(|bit| *bit == label[0]);
+    let store_len = |builder: &mut VmBuilder| {
+        for shift in (0..len_bits).rev() {
+            builder.store_bit((len >> shift) & 1 == 1);
+        }
+    };
+    if same && len_bits < 2 * len - 1 {
+        builder.store_bit(true);
+        builder.store_bit(true);
+        builder.store_bit(label[0]);
+        store_len(builder);
+    } else if len_bits < len {
+        builder.store_bit(true);
+        builder.store_bit(false);
+        store_len(builder);
+        for bit in label {
+            builder.store_bit(*bit);
+        }
+    } else {
+        builder.store_bit(false);
+        for _ in 0..len {
+            builder.store_bit(true);
+        }
+        builder.store_bit(false);
+        for bit in label {
+            builder.store_bit(*bit);
+        }
+    }
+}
+
+/// Encodes `value` as a big-endian two's complement key of `bits` bits.
+pub(crate) fn int_to_bits(value: &BigInt, bits: usize) -> Vec<bool> {
+    let modulus = BigInt::from(1) << bits;
+    let value = ((value % &modulus) + &modulus) % &modulus;
+    let (_, digits) = value.to_u64_digits();
+    (0..bits)
+        .rev()
+        .map(|bit| {
+            digits
+                .get(bit / 64)
+                .is_some_and(|digit| (digit >> (bit % 64)) & 1 == 1)
+        })
+        .collect()
+}
+
+/// Decodes a key back into an integer.
+pub(crate) fn bits_to_int(bits: &[bool], signed: bool) -> BigInt {
+    let mut value = BigInt::from(0);
+    for bit in bits {
+        value <<= 1;
+        if *bit {
+            value += 1;
+        }
+    }
+    if signed && bits.first() == Some(&true) {
+        value -= BigInt::from(1) << bits.len();
+    }
+    value
+}
+
+/// Result of a recursive dictionary update.
+enum Update {
+    Unchanged,
+    Replaced(Option<Arc<Cell>>),
+}
+
+impl VmState {
+    fn load_edge(&mut self, cell: Arc<Cell>, max: usize) -> VmResult<(Vec<bool>, VmSlice)> {
+        let mut slice = self.load_cell_slice(cell)?;
+        let label = read_label(&mut slice, max).ok_or(VmError::new(VmExitCode::DictionaryError))?;
+        Ok((label, slice))
+    }
+
+    fn fork_child(slice: &VmSlice, bit: bool) -> VmResult<Arc<Cell>> {
+        slice
+            .reference(bit as usize)
+            .cloned()
+            .ok_or(VmError::new(VmExitCode::DictionaryError))
+    }
+
+    /// Looks up `key` and returns the value slice.
+    pub(crate) fn dict_get(
+        &mut self,
+        root: Option<Arc<Cell>>,
+        key: &[bool],
+    ) -> VmResult<Option<VmSlice>> {
+        let Some(mut cell) = root else {
+            return Ok(None);
+        };
+        let mut key = key;
+        loop {
+            let (label, slice) = self.load_edge(cell, key.len())?;
+            if !key.starts_with(&label) {
+                return Ok(None);
+            }
+            key = &key[label.len()..];
+            if key.is_empty() {
+                return Ok(Some(slice));
+            }
+            cell = Self::fork_child(&slice, key[0])?;
+            key = &key[1..];
+        }
+    }
+
+    fn build_leaf(&mut self, label: &[bool], max: usize, value: &DictValue) -> VmResult<Arc<Cell>> {
+        let mut builder = VmBuilder::new();
+        write_label(&mut builder, label, max);
+        let ok = match value {
+            DictValue::Slice(slice) => builder.append_slice(slice).is_ok(),
+            DictValue::Builder(other) => builder.append_builder(other).is_ok(),
+            DictValue::Ref(cell) => {
+                let ok = builder.can_extend(0, 1);
+                if ok {
+                    builder.store_ref(cell.clone());
+                }
+                ok
+            }
+        };
+        if !ok {
+            return Err(VmExitCode::CellOverflow.into());
+        }
+        self.build_cell(&builder)
+    }
+
+    fn build_edge(&mut self, label: &[bool], max: usize, rest: &VmSlice) -> VmResult<Arc<Cell>> {
+        let mut builder = VmBuilder::new();
+        write_label(&mut builder, label, max);
+        builder
+            .append_slice(rest)
+            .map_err(|_| VmError::new(VmExitCode::CellOverflow))?;
+        self.build_cell(&builder)
+    }
+
+    fn build_fork(
+        &mut self,
+        label: &[bool],
+        max: usize,
+        left: Arc<Cell>,
+        right: Arc<Cell>,
+    ) -> VmResult<Arc<Cell>> {
+        let mut builder = VmBuilder::new();
+        write_label(&mut builder, label, max);
+        builder.store_ref(left);
+        builder.store_ref(right);
+        self.build_cell(&builder)
+    }
+
+    /// Inserts or replaces `key`; returns the new root (when changed) and the
+    /// previous value.
+    pub(crate) fn dict_set(
+        &mut self,
+        root: Option<Arc<Cell>>,
+        key: &[bool],
+        value: &DictValue,
+        mode: DictSetMode,
+    ) -> VmResult<(Option<Arc<Cell>>, bool, Option<VmSlice>)> {
+        let mut old = None;
+        match self.dict_set_rec(root.clone(), key, value, mode, &mut old)? {
+            Update::Unchanged => Ok((root, false, old)),
+            Update::Replaced(root) => Ok((root, true, old)),
+        }
+    }
+
+    fn dict_set_rec(
+        &mut self,
+        cell: Option<Arc<Cell>>,
+        key: &[bool],
+        value: &DictValue,
+        mode: DictSetMode,
+        old: &mut Option<VmSlice>,
+    ) -> VmResult<Update> {
+        let max = key.len();
+        let Some(cell) = cell else {
+            if mode == DictSetMode::Replace {
+                return Ok(Update::Unchanged);
+            }
+            return Ok(Update::Replaced(Some(self.build_leaf(key, max, value)?)));
+        };
+        let (label, rest) = self.load_edge(cell, max)?;
+        let common = label
+            .iter()
+            .zip(key)
+            .take_while(|(left, right)| left == right)
+            .count();
+        if common == label.len() {
+            if label.len() == max {
+                *old = Some(rest);
+                if mode == DictSetMode::Add {
+                    return Ok(Update::Unchanged);
+                }
+                return Ok(Update::Replaced(Some(self.build_leaf(key, max, value)?)));
+            }
+            let bit = key[common];
+            let child = Self::fork_child(&rest, bit)?;
+            let other = Self::fork_child(&rest, !bit)?;
+            match self.dict_set_rec(Some(child), &key[common + 1..], value, mode, old)? {
+                Update::Unchanged => Ok(Update::Unchanged),
+                Update::Replaced(child) => {
+                    let child = child.ok_or(VmError::new(VmExitCode::FatalError))?;
+                    let (left, right) = if bit { (other, child) } else { (child, other) };
+                    Ok(Update::Replaced(Some(
+                        self.build_fork(&label, max, left, right)?,
+                    )))
+                }
+            }
+        } else {
+            if mode == DictSetMode::Replace {
+                return Ok(Update::Unchanged);
+            }
+            let child_max = max - common - 1;
+            let existing = self.build_edge(&label[common + 1..], child_max, &rest)?;
+            let leaf = self.build_leaf(&key[common + 1..], child_max, value)?;
+            let (left, right) = if key[common] {
+                (existing, leaf)
+            } else {
+                (leaf, existing)
+            };
+            Ok(Update::Replaced(Some(self.build_fork(
+                &key[..common],
+                max,
+                left,
+                right,
+            )?)))
+        }
+    }
+
+    /// Deletes `key`; returns the new root (when changed) and the old value.
+    pub(crate) fn dict_delete(
+        &mut self,
+        root: Option<Arc<Cell>>,
+        key: &[bool],
+    ) -> VmResult<(Option<Arc<Cell>>, Option<VmSlice>)> {
+        let mut old = None;
+        match self.dict_delete_rec(root.clone(), key, &mut old)? {
+            Update::Unchanged => Ok((root, None)),
+            Update::Replaced(root) => Ok((root, old)),
+        }
+    }
+
+    fn dict_delete_rec(
+        &mut self,
+        cell: Option<Arc<Cell>>,
+        key: &[bool],
+        old: &mut Option<VmSlice>,
+    ) -> VmResult<Update> {
+        let max = key.len();
+        let Some(cell) = cell else {
+            return Ok(Update::Unchanged);
+        };
+        let (label, rest) = self.load_edge(cell, max)?;
+        if !key.starts_with(&label) {
+            return Ok(Update::Unchanged);
+        }
+        if label.len() == max {
+            *old = Some(rest);
+            return Ok(Update::Replaced(None));
+        }
+        let bit = key[label.len()];
+        let child = Self::fork_child(&rest, bit)?;
+        let other = Self::fork_child(&rest, !bit)?;
+        match self.dict_delete_rec(Some(child), &key[label.len() + 1..], old)? {
+            Update::Unchanged => Ok(Update::Unchanged),
+            Update::Replaced(Some(child)) => {
+                let (left, right) = if bit { (other, child) } else { (child, other) };
+                Ok(Update::Replaced(Some(
+                    self.build_fork(&label, max, left, right)?,
+                )))
+            }
+            Update::Replaced(None) => {
+                let child_max = max - label.len() - 1;
+                let (other_label, other_rest) = self.load_edge(other, child_max)?;
+                let mut merged = label;
+                merged.push(!bit);
+                merged.extend(other_label);
+                Ok(Update::Replaced(Some(self.build_edge(
+                    &merged,
+                    max,
+                    &other_rest,
+                )?)))
+            }
+        }
+    }
+
+    /// Returns the smallest or largest key of a non-empty subtree.
+    fn dict_extreme_from(
+        &mut self,
+        cell: Arc<Cell>,
+        mut prefix: Vec<bool>,
+        total: usize,
+        want_max: bool,
+        signed: bool,
+    ) -> VmResult<(Vec<bool>, VmSlice)> {
+        let mut cell = cell;
+        loop {
+            let (label, rest) = self.load_edge(cell, total - prefix.len())?;
+            prefix.extend(label);
+            if prefix.len() == total {
+                return Ok((prefix, rest));
+            }
+            let invert = signed && prefix.is_empty();
+            let bit = want_max != invert;
+            prefix.push(bit);
+            cell = Self::fork_child(&rest, bit)?;
+        }
+    }
+
+    pub(crate) fn dict_extreme(
+        &mut self,
+        root: Option<Arc<Cell>>,
+        total: usize,
+        want_max: bool,
+        signed: bool,
+    ) -> VmResult<Option<(Vec<bool>, VmSlice)>> {
+        match root {
+            Some(root) => self
+                .dict_extreme_from(root, Vec::new(), total, want_max, signed)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Finds the nearest key after (`next`) or before `key`, optionally
+    /// accepting `key` itself. Signed dictionaries order the first key bit
+    /// inverted.
+    pub(crate) fn dict_nearest(
+        &mut self,
+        root: Option<Arc<Cell>>,
+        key: &[bool],
+        next: bool,
+        allow_eq: bool,
+        signed: bool,
+    ) -> VmResult<Option<(Vec<bool>, VmSlice)>> {
+        match root {
+            Some(root) => self.dict_nearest_rec(root, Vec::new(), key, next, allow_eq, signed),
+            None => Ok(None),
+        }
+    }
+
+    fn dict_nearest_rec(
+        &mut self,
+        cell: Arc<Cell>,
+        prefix: Vec<bool>,
+        key: &[bool],
+        next: bool,
+        allow_eq: bool,
+        signed: bool,
+    ) -> VmResult<Option<(Vec<bool>, VmSlice)>> {
+        let total = key.len();
+        let position = prefix.len();
+        let (label, rest) = self.load_edge(cell.clone(), total - position)?;
+        let order = |bit: bool, index: usize| if signed && index == 0 { !bit } else { bit };
+        for (offset, bit) in label.iter().enumerate() {
+            let index = position + offset;
+            let ours = order(*bit, index);
+            let theirs = order(key[index], index);
+            if ours != theirs {
+                // The whole subtree is on one side of `key`.
+                return if ours == next {
+                    self.dict_extreme_from(cell, prefix, total, !next, signed)
+                        .map(Some)
+                } else {
+                    Ok(None)
+                };
+            }
+        }
+        let mut prefix = prefix;
+        prefix.extend(label);
+        if prefix.len() == total {
+            return Ok(allow_eq.then_some((prefix, rest)));
+        }
+        let index = prefix.len();
+        let bit = key[index];
+        let child = Self::fork_child(&rest, bit)?;
+        let mut child_prefix = prefix.clone();
+        child_prefix.push(bit);
+        if let Some(found) =
+            self.dict_nearest_rec(child, child_prefix, key, next, allow_eq, signed)?
+        {
+            return Ok(Some(found));
+        }
+        // The other branch is entirely after (or before) `key` only when it
+        // sorts on the requested side.
+        if order(!bit, index) == next {
+            let other = Self::fork_child(&rest, !bit)?;
+            let mut other_prefix = prefix;
+            other_prefix.push(!bit);
+            return self
+                .dict_extreme_from(other, other_prefix, total, !next, signed)
+                .map(Some);
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,410 @@
+use super::cont::ContData;
+use super::state::{
+    GAS_BASIC_INSTRUCTION, GAS_EXCEPTION, GAS_IMPLICIT_JMPREF, GAS_IMPLICIT_RET, GAS_PER_REF,
+};
+use super::*;
+
+impl VmState {
+    /// Runs until the machine halts and returns the exit code.
+    pub(crate) fn run(&mut self) -> i32 {
+        loop {
+            if let Some(code) = self.halted {
+                if VmExitCode::is_success_code(code) {
+                    self.committed = Some((self.regs.c4.clone(), self.regs.c5.clone()));
+                }
+                return code;
+            }
+            let mut result = self.step();
+            while let Err(VmError::Exception { code, arg }) = result {
+                result = self.throw_exception(code, arg);
+            }
+            if let Err(VmError::OutOfGas) = result {
+                let consumed = self.gas.consumed();
+                self.stack.clear();
+                self.push_small(consumed);
+                return EXIT_CODE_OUT_OF_GAS;
+            }
+        }
+    }
+
+    fn throw_exception(&mut self, code: i32, arg: VmValue) -> VmResult<()> {
+        self.stack.clear();
+        self.push(arg);
+        self.push_small(code as i64);
+        self.consume_gas(GAS_EXCEPTION)?;
+        let handler = self.regs.conts[2].clone();
+        self.jump(handler)
+    }
+
+    fn step(&mut self) -> VmResult<()> {
+        self.steps += 1;
+        if self.cc.bits() == 0 {
+            if self.cc.refs() > 0 {
+                self.consume_gas(GAS_IMPLICIT_JMPREF)?;
+                let cell = self
+                    .cc
+                    .load_ref()
+                    .ok_or(VmError::new(VmExitCode::FatalError))?;
+                let code = self.load_cell_slice(cell)?;
+                return self.jump(Arc::new(Continuation::ordinary(code)));
+            }
+            self.consume_gas(GAS_IMPLICIT_RET)?;
+            return self.ret();
+        }
+        let op = self.cc.peek_padded(24) as u32;
+        match op >> 16 {
+            0x00..=0x6C => self.exec_stack(op),
+            0x6D..=0x6F => self.exec_tuple(op),
+            0x70..=0x9F => self.exec_const(op),
+            0xA0..=0xC6 => self.exec_arith(op),
+            0xC7..=0xD7 => self.exec_cell(op),
+            0xD8..=0xF3 => self.exec_control(op),
+            0xF4 => self.exec_dict(op),
+            0xF8..=0xFB | 0xFE | 0xFF => self.exec_app(op),
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    // ---- instruction fetch ----
+
+    /// Consumes an opcode of `bits` bits and charges the basic gas price.
+    pub(crate) fn fetch(&mut self, bits: usize) -> VmResult<u32> {
+        let value = self
+            .cc
+            .load_small(bits)
+            .ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+        self.consume_gas(GAS_BASIC_INSTRUCTION + bits as i64)?;
+        Ok(value as u32)
+    }
+
+    /// Consumes an inline reference of the current instruction.
+    pub(crate) fn fetch_ref(&mut self) -> VmResult<Arc<Cell>> {
+        let cell = self
+            .cc
+            .load_ref()
+            .ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+        self.consume_gas(GAS_PER_REF)?;
+        Ok(cell)
+    }
+
+    /// Consumes inline instruction data of `bits` bits and `refs` references.
+    pub(crate) fn fetch_data(&mut self, bits: usize, refs: usize) -> VmResult<VmSlice> {
+        let data = self
+            .cc
+            .split_prefix(bits, refs)
+            .ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+        self.consume_gas(bits as i64 + GAS_PER_REF * refs as i64)?;
+        Ok(data)
+    }
+
+    /// Consumes inline data terminated by a completion tag.
+    pub(crate) fn fetch_tagged_data(&mut self, bits: usize, refs: usize) -> VmResult<VmSlice> {
+        let mut data = self.fetch_data(bits, refs)?;
+        if !data.remove_completion_tag() {
+            return Err(VmExitCode::InvalidOpcode.into());
+        }
+        Ok(data)
+    }
+
+    // ---- continuation transfers ----
+
+    pub(crate) fn jump(&mut self, cont: Arc<Continuation>) -> VmResult<()> {
+        self.jump_args(cont, None)
+    }
+
+    /// Jumps to `cont`, passing the top `pass_args` entries (or all).
+    pub(crate) fn jump_args(
+        &mut self,
+        cont: Arc<Continuation>,
+        pass_args: Option<usize>,
+    ) -> VmResult<()> {
+        let depth = self.depth();
+        if let Some(data) = cont.data() {
+            if pass_args.is_some_and(|pass| pass > depth) || data.nargs.is_some_and(|n| n > depth) {
+                return Err(VmExitCode::StackUnderflow.into());
+            }
+            if let (Some(nargs), Some(pass)) = (data.nargs, pass_args)
+                && nargs > pass
+            {
+                return Err(VmExitCode::StackUnderflow.into());
+            }
+            let copy = data.nargs.or(pass_args);
+            match &data.stack {
+                Some(saved) if !saved.is_empty() => {
+                    let copy = copy.unwrap_or(depth);
+                    let mut stack = saved.clone();
+                    stack.extend(self.stack.drain(depth - copy..));
+                    self.stack = stack;
+                    self.charge_stack_depth(self.depth())?;
+                }
+                _ => {
+                    if let Some(copy) = copy
+                        && copy < depth
+                    {
+                        self.stack.drain(..depth - copy);
+                        self.charge_stack_depth(copy)?;
+                    }
+                }
+            }
+        } else if let Some(pass) = pass_args {
+            if pass > depth {
+                return Err(VmExitCode::StackUnderflow.into());
+            }
+            if pass < depth {
+                self.stack.drain(..depth - pass);
+                self.charge_stack_depth(pass)?;
+            }
+        }
+        self.jump_to(cont)
+    }
+
+    fn apply_save_list(&mut self, data: &ContData) -> VmResult<()> {
+        for (index, value) in data.save.iter() {
+            self.regs.set(index, value.clone())?;
+        }
+        Ok(())
+    }
+
+    fn jump_to(&mut self, cont: Arc<Continuation>) -> VmResult<()> {
+        match cont.as_ref() {
+            Continuation::Ordinary { code, data } => {
+                self.apply_save_list(data)?;
+                self.cc = code.clone();
+                Ok(())
+            }
+            Continuation::Envelope { inner, data } => {
+                self.apply_save_list(data)?;
+                self.jump_to(inner.clone())
+            }
+            Continuation::Quit(code) => {
+                self.halted = Some(*code);
+                Ok(())
+            }
+            Continuation::ExcQuit => {
+                let code = self.pop_range(0, 0xffff).unwrap_or(0);
+                self.halted = Some(code as i32);
+                Ok(())
+            }
+            Continuation::Repeat { body, after, count } => {
+                if *count <= 0 {
+                    return self.jump(after.clone());
+                }
+                if !body.has_c0() {
+                    self.regs.conts[0] = Arc::new(Continuation::Repeat {
+                        body: body.clone(),
+                        after: after.clone(),
+                        count: count - 1,
+                    });
+                }
+                self.jump(body.clone())
+            }
+            Continuation::Again { body } => {
+                if !body.has_c0() {
+                    self.regs.conts[0] = cont.clone();
+                }
+                self.jump(body.clone())
+            }
+            Continuation::Until { body, after } => {
+                if self.pop_bool()? {
+                    return self.jump(after.clone());
+                }
+                if !body.has_c0() {
+                    self.regs.conts[0] = cont.clone();
+                }
+                self.jump(body.clone())
+            }
+            Continuation::While {
+                cond,
+                body,
+                after,
+                check_cond,
+            } => {
+                if *check_cond {
+                    if !self.pop_bool()? {
+                        return self.jump(after.clone());
+                    }
+                    if !body.has_c0() {
+                        self.regs.conts[0] = Arc::new(Continuation::While {
+                            cond: cond.clone(),
+                            body: body.clone(),
+                            after: after.clone(),
+                            check_cond: false,
+                        });
+                    }
+                    self.jump(body.clone())
+                } else {
+                    if !cond.has_c0() {
+                        self.regs.conts[0] = Arc::new(Continuation::While {
+                            cond: cond.clone(),
+                            body: body.clone(),
+                            after: after.clone(),
+                            check_cond: true,
+                        });
+                    }
+                    self.jump(cond.clone())
+                }
+            }
+        }
+    }
+
+    /// Builds the return continuation from the remaining code and `c0`.
+    fn return_cont(
+        &mut self,
+        stack: Option<Vec<VmValue>>,
+        nargs: Option<usize>,
+    ) -> Arc<Continuation> {
+        let mut data = ContData {
+            stack: stack.filter(|stack| !stack.is_empty()),
+            nargs,
+            ..ContData::default()
+        };
+        data.save.set(0, VmValue::Cont(self.regs.conts[0].clone()));
+        Arc::new(Continuation::Ordinary {
+            code: self.cc.clone(),
+            data,
+        })
+    }
+
+    pub(crate) fn call(&mut self, cont: Arc<Continuation>) -> VmResult<()> {
+        if let Some(data) = cont.data() {
+            if data.save.has(0) {
+                return self.jump(cont);
+            }
+            if data.stack.is_some() || data.nargs.is_some() {
+                return self.call_args(cont, None, None);
+            }
+        }
+        let ret = self.return_cont(None, None);
+        self.regs.conts[0] = ret;
+        self.jump_to(cont)
+    }
+
+    /// Calls `cont` passing `pass_args` entries and expecting `ret_args`
+    /// return values; the rest of the stack is kept in the return
+    /// continuation.
+    pub(crate) fn call_args(
+        &mut self,
+        cont: Arc<Continuation>,
+        pass_args: Option<usize>,
+        ret_args: Option<usize>,
+    ) -> VmResult<()> {
+        let depth = self.depth();
+        let new_stack = if let Some(data) = cont.data() {
+            if data.save.has(0) {
+                return self.jump_args(cont, pass_args);
+            }
+            if pass_args.is_some_and(|pass| pass > depth) || data.nargs.is_some_and(|n| n > depth) {
+                return Err(VmExitCode::StackUnderflow.into());
+            }
+            let mut copy = data.nargs;
+            let mut skip = 0;
+            if let Some(pass) = pass_args {
+                match copy {
+                    Some(nargs) if nargs > pass => {
+                        return Err(VmExitCode::StackUnderflow.into());
+                    }
+                    Some(nargs) => skip = pass - nargs,
+                    None => copy = Some(pass),
+                }
+            }
+            match (&data.stack, copy) {
+                (Some(saved), _) if !saved.is_empty() => {
+                    let copy = copy.unwrap_or(depth);
+                    let mut stack = saved.clone();
+                    stack.extend(self.stack.drain(depth - copy..));
+                    self.stack.truncate(self.depth() - skip);
+                    self.charge_stack_depth(stack.len())?;
+                    stack
+                }
+                (_, Some(copy)) => {
+                    let stack: Vec<_> = self.stack.drain(depth - copy..).collect();
+                    self.stack.truncate(self.depth() - skip);
+                    self.charge_stack_depth(stack.len())?;
+                    stack
+                }
+                _ => std::mem::take(&mut self.stack),
+            }
+        } else {
+            match pass_args {
+                Some(pass) if pass > depth => return Err(VmExitCode::StackUnderflow.into()),
+                Some(pass) => {
+                    let stack: Vec<_> = self.stack.drain(depth - pass..).collect();
+                    self.charge_stack_depth(stack.len())?;
+                    stack
+                }
+                None => std::mem::take(&mut self.stack),
+            }
+        };
+        let rest = std::mem::replace(&mut self.stack, new_stack);
+        let ret = self.return_cont(Some(rest), ret_args);
+        self.regs.conts[0] = ret;
+        self.jump_to(cont)
+    }
+
+    pub(crate) fn ret(&mut self) -> VmResult<()> {
+        let cont = std::mem::replace(&mut self.regs.conts[0], Arc::new(Continuation::Quit(0)));
+        self.jump(cont)
+    }
+
+    pub(crate) fn ret_args(&mut self, count: usize) -> VmResult<()> {
+        let cont = std::mem::replace(&mut self.regs.conts[0], Arc::new(Continuation::Quit(0)));
+        self.jump_args(cont, Some(count))
+    }
+
+    pub(crate) fn ret_alt(&mut self) -> VmResult<()> {
+        let cont = std::mem::replace(&mut self.regs.conts[1], Arc::new(Continuation::Quit(1)));
+        self.jump(cont)
+    }
+
+    /// Captures the current continuation.
+    ///
+    /// `save_mask` bits 0..2 move `c0`, `c1`, `c2` into the continuation
+    /// (resetting `c0` and `c1` to their quit defaults). With `stack_copy`,
+    /// only the top entries stay on the stack and the rest are captured.
+    pub(crate) fn extract_cc(
+        &mut self,
+        save_mask: u8,
+        stack_copy: Option<usize>,
+        cc_args: Option<usize>,
+    ) -> VmResult<Arc<Continuation>> {
+        let depth = self.depth();
+        let captured = match stack_copy {
+            None => None,
+            Some(copy) if copy == depth => None,
+            Some(copy) => {
+                self.check_underflow(copy)?;
+                let top: Vec<_> = self.stack.drain(depth - copy..).collect();
+                self.charge_stack_depth(top.len())?;
+                Some(std::mem::replace(&mut self.stack, top))
+            }
+        };
+        let mut data = ContData {
+            stack: captured.filter(|stack| !stack.is_empty()),
+            nargs: cc_args,
+            ..ContData::default()
+        };
+        if save_mask & 1 != 0 {
+            let c0 = std::mem::replace(&mut self.regs.conts[0], Arc::new(Continuation::Quit(0)));
+            data.save.set(0, VmValue::Cont(c0));
+        }
+        if save_mask & 2 != 0 {
+            let c1 = std::mem::replace(&mut self.regs.conts[1], Arc::new(Continuation::Quit(1)));
+            data.save.set(1, VmValue::Cont(c1));
+        }
+        if save_mask & 4 != 0 {
+            data.save.set(2, VmValue::Cont(self.regs.conts[2].clone()));
+        }
+        Ok(Arc::new(Continuation::Ordinary {
+            code: std::mem::replace(&mut self.cc, VmSlice::empty()),
+            data,
+        }))
+    }
+
+    /// Raises a TVM exception with an integer argument.
+    pub(crate) fn throw(&self, code: i64, arg: VmValue) -> VmError {
+        VmError::Exception {
+            code: code as i32,
+            arg,
+        }
+    }
+}
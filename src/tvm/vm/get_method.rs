@@ -0,0 +1,243 @@
+use super::state::EXIT_CODE_OUT_OF_GAS;
+use super::*;
+use crate::tl::BlockIdExt;
+use crate::tl::response::RunMethodResult;
+use crate::tlb::StateInit;
+use crate::tvm::Address;
+
+/// Magic tag of the `SmartContractInfo` tuple stored at `c7[0]`.
+const SMART_CONTRACT_INFO_TAG: i64 = 0x076e_f1ea;
+
+/// Blockchain context exposed to contract code through `c7`.
+///
+/// Get-methods rarely depend on these values; the defaults describe an
+/// account at the zero address with zero balance and no configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmartContractContext {
+    /// Unix time returned by `NOW`.
+    pub now: u32,
+    /// Block logical time returned by `BLOCKLT`.
+    pub block_lt: u64,
+    /// Transaction logical time returned by `LTIME`.
+    pub trans_lt: u64,
+    /// Initial random seed returned by `RANDSEED`.
+    pub rand_seed: [u8; 32],
+    /// Account balance in nanotons returned by `BALANCE`.
+    pub balance: BigUint,
+    /// Account address returned by `MYADDR`.
+    pub address: Address,
+    /// Global configuration dictionary returned by `CONFIGROOT`.
+    pub config: Option<Arc<Cell>>,
+    /// Value of the inbound message in nanotons returned by `INCOMINGVALUE`.
+    pub incoming_value: BigUint,
+    /// Storage fees collected before the compute phase, returned by
+    /// `STORAGEFEES`.
+    pub storage_fees: u64,
+}
+
+impl Default for SmartContractContext {
+    fn default() -> Self {
+        Self {
+            now: 0,
+            block_lt: 0,
+            trans_lt: 0,
+            rand_seed: [0; 32],
+            balance: BigUint::from(0u8),
+            address: Address::new(0, [0; 32]),
+            config: None,
+            incoming_value: BigUint::from(0u8),
+            storage_fees: 0,
+        }
+    }
+}
+
+impl SmartContractContext {
+    /// Builds the `c7` register contents for `code`.
+    pub(crate) fn to_c7(&self, code: &Arc<Cell>) -> Result<Vec<VmValue>> {
+        let mut address = VmBuilder::new();
+        address.store_uint(&BigUint::from(0b100u8), 3);
+        address.store_int(&BigInt::from(self.address.workchain), 8);
+        address.store_bytes(&self.address.hash_part);
+        let address = address.build()?;
+        let info = vec![
+            VmValue::int(SMART_CONTRACT_INFO_TAG),
+            VmValue::int(0),
+            VmValue::int(0),
+            VmValue::int(self.now),
+            VmValue::int(self.block_lt),
+            VmValue::int(self.trans_lt),
+            VmValue::Int(BigInt::from_bytes_be(Sign::Plus, &self.rand_seed)),
+            VmValue::tuple(vec![
+                VmValue::Int(BigInt::from(self.balance.clone())),
+                VmValue::Null,
+            ]),
+            VmValue::Slice(VmSlice::new(address)),
+            self.config.clone().map_or(VmValue::Null, VmValue::Cell),
+            VmValue::Cell(code.clone()),
+            VmValue::tuple(vec![
+                VmValue::Int(BigInt::from(self.incoming_value.clone())),
+                VmValue::Null,
+            ]),
+            VmValue::int(self.storage_fees),
+            VmValue::Null,
+        ];
+        Ok(vec![VmValue::tuple(info)])
+    }
+}
+
+/// Outcome of one offline VM run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmRunResult {
+    /// TVM exit code; `0` and `1` are successful, `-14` is out of gas.
+    pub exit_code: i32,
+    /// Gas consumed by the run.
+    pub gas_used: u64,
+    /// Number of executed instructions.
+    pub steps: u64,
+    /// Final stack, bottom first.
+    pub stack: TvmStack,
+    /// Committed `c4` data cell, present after a successful run.
+    pub data: Option<Arc<Cell>>,
+    /// Committed `c5` output action list, present after a successful run.
+    pub actions: Option<Arc<Cell>>,
+}
+
+impl VmRunResult {
+    /// Returns `true` for exit codes `0` and `1`.
+    pub fn is_success(&self) -> bool {
+        VmExitCode::is_success_code(self.exit_code)
+    }
+
+    /// Returns `true` when execution stopped because gas ran out.
+    pub fn is_out_of_gas(&self) -> bool {
+        self.exit_code == EXIT_CODE_OUT_OF_GAS
+    }
+
+    /// Converts the run into the LiteAPI `runSmcMethod` response shape.
+    ///
+    /// Proof fields are empty because nothing was fetched from a liteserver;
+    /// `block` is reported as both the masterchain and shard block.
+    pub fn into_run_method_result(self, block: BlockIdExt) -> Result<RunMethodResult> {
+        Ok(RunMethodResult {
+            mode: (),
+            id: block.clone(),
+            shardblk: block,
+            shard_proof: None,
+            proof: None,
+            state_proof: None,
+            init_c7: None,
+            lib_extras: None,
+            exit_code: self.exit_code,
+            result: Some(self.stack.to_boc()?),
+        })
+    }
+}
+
+/// Runs get-methods of one contract offline.
+///
+/// ```no_run
+/// # fn example(code: std::sync::Arc<tonutils::tvm::Cell>, data: std::sync::Arc<tonutils::tvm::Cell>) -> anyhow::Result<()> {
+/// use tonutils::tvm::TvmStack;
+/// use tonutils::tvm::vm::GetMethodRunner;
+///
+/// let result = GetMethodRunner::new(code, data).run_by_name("seqno", TvmStack::empty())?;
+/// assert_eq!(result.exit_code, 0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GetMethodRunner {
+    code: Arc<Cell>,
+    data: Arc<Cell>,
+    context: SmartContractContext,
+    gas: VmGasLimits,
+}
+
+impl GetMethodRunner {
+    pub fn new(code: Arc<Cell>, data: Arc<Cell>) -> Self {
+        Self {
+            code,
+            data,
+            context: SmartContractContext::default(),
+            gas: VmGasLimits::default(),
+        }
+    }
+
+    /// Creates a runner from an active account `StateInit`.
+    pub fn from_state_init(state_init: &StateInit) -> Result<Self> {
+        let Some(code) = state_init.code.clone() else {
+            bail!("StateInit has no code");
+        };
+        let data = state_init
+            .data
+            .clone()
+            .unwrap_or_else(|| Arc::new(Cell::new()));
+        Ok(Self::new(code, data))
+    }
+
+    pub fn with_context(mut self, context: SmartContractContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn with_gas_limits(mut self, gas: VmGasLimits) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    pub fn with_gas_limit(self, limit: u64) -> Self {
+        self.with_gas_limits(VmGasLimits::get_method(limit))
+    }
+
+    pub fn code(&self) -> &Arc<Cell> {
+        &self.code
+    }
+
+    pub fn data(&self) -> &Arc<Cell> {
+        &self.data
+    }
+
+    pub fn context(&self) -> &SmartContractContext {
+        &self.context
+    }
+
+    /// Runs `method_id` with `stack` as arguments, bottom first.
+    pub fn run(&self, method_id: u64, stack: TvmStack) -> Result<VmRunResult> {
+        let mut entries = stack
+            .entries()
+            .iter()
+            .map(VmValue::from_stack_entry)
+            .collect::<Result<Vec<_>>>()?;
+        entries.push(VmValue::int(method_id));
+        let mut state = VmState::new(VmInit {
+            code: self.code.clone(),
+            data: self.data.clone(),
+            c7: self.context.to_c7(&self.code)?,
+            stack: entries,
+            gas: self.gas,
+        });
+        let exit_code = state.run();
+        let stack = state
+            .stack
+            .iter()
+            .map(VmValue::to_stack_entry)
+            .collect::<Result<Vec<_>>>()?;
+        let (data, actions) = match state.committed {
+            Some((data, actions)) => (Some(data), Some(actions)),
+            None => (None, None),
+        };
+        Ok(VmRunResult {
+            exit_code,
+            gas_used: state.gas.consumed().max(0) as u64,
+            steps: state.steps,
+            stack: TvmStack::new(stack),
+            data,
+            actions,
+        })
+    }
+
+    /// Runs a get-method by its FunC name.
+    pub fn run_by_name(&self, method: &str, stack: TvmStack) -> Result<VmRunResult> {
+        self.run(crate::utils::method_name_to_id(method), stack)
+    }
+}
@@ -0,0 +1,426 @@
+use super::state::fits;
+use super::*;
+use std::cmp::Ordering;
+
+/// Rounding mode of TVM division primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rounding {
+    Floor,
+    Nearest,
+    Ceil,
+}
+
+impl Rounding {
+    fn from_bits(bits: u32) -> Option<Self> {
+        match bits {
+            0 => Some(Self::Floor),
+            1 => Some(Self::Nearest),
+            2 => Some(Self::Ceil),
+            _ => None,
+        }
+    }
+}
+
+/// Divides `x` by non-zero `y`, returning the quotient and remainder for the
+/// given rounding mode (`x = q * y + r`).
+fn div_rem(x: &BigInt, y: &BigInt, rounding: Rounding) -> (BigInt, BigInt) {
+    let q = match rounding {
+        Rounding::Floor => floor_div(x, y),
+        Rounding::Ceil => -floor_div(&-x, y),
+        Rounding::Nearest => floor_div(&(x * 2 + y), &(y * 2)),
+    };
+    let r = x - &q * y;
+    (q, r)
+}
+
+fn floor_div(x: &BigInt, y: &BigInt) -> BigInt {
+    let q = x / y;
+    let r = x - &q * y;
+    if r.sign() != Sign::NoSign && (r.sign() == Sign::Minus) != (y.sign() == Sign::Minus) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+/// Smallest bit width of a signed integer holding `value`.
+fn signed_bit_size(value: &BigInt) -> u64 {
+    if value.sign() == Sign::Minus {
+        (-value - 1u8).bits() + 1
+    } else if value.sign() == Sign::NoSign {
+        0
+    } else {
+        value.bits() + 1
+    }
+}
+
+impl VmState {
+    fn arith_pop(&mut self, quiet: bool) -> VmResult<Option<BigInt>> {
+        if quiet {
+            self.pop_int_or_nan()
+        } else {
+            self.pop_int().map(Some)
+        }
+    }
+
+    fn arith_push(&mut self, quiet: bool, value: Option<BigInt>) -> VmResult<()> {
+        match value {
+            Some(value) if quiet => {
+                self.push_int_quiet(value);
+                Ok(())
+            }
+            Some(value) => self.push_int(value),
+            None if quiet => {
+                self.push(VmValue::NaN);
+                Ok(())
+            }
+            None => Err(VmExitCode::IntegerOverflow.into()),
+        }
+    }
+
+    fn arith_unary(
+        &mut self,
+        quiet: bool,
+        f: impl FnOnce(BigInt) -> Option<BigInt>,
+    ) -> VmResult<()> {
+        let x = self.arith_pop(quiet)?;
+        self.arith_push(quiet, x.and_then(f))
+    }
+
+    fn arith_binary(
+        &mut self,
+        quiet: bool,
+        f: impl FnOnce(BigInt, BigInt) -> Option<BigInt>,
+    ) -> VmResult<()> {
+        let y = self.arith_pop(quiet)?;
+        let x = self.arith_pop(quiet)?;
+        let result = match (x, y) {
+            (Some(x), Some(y)) => f(x, y),
+            _ => None,
+        };
+        self.arith_push(quiet, result)
+    }
+
+    fn compare(&mut self, quiet: bool, f: impl FnOnce(Ordering) -> i64) -> VmResult<()> {
+        self.arith_binary(quiet, |x, y| Some(BigInt::from(f(x.cmp(&y)))))
+    }
+
+    /// Integer arithmetic and comparison primitives (`A0`..`C6`), including
+    /// the quiet `B7` prefix.
+    pub(crate) fn exec_arith(&mut self, op: u32) -> VmResult<()> {
+        if op >> 16 == 0xb7 {
+            let op = (self.cc.peek_padded(32) & 0x00ff_ffff) as u32;
+            return self.exec_arith_inner(op, true);
+        }
+        self.exec_arith_inner(op, false)
+    }
+
+    fn exec_arith_inner(&mut self, op: u32, quiet: bool) -> VmResult<()> {
+        let prefix = if quiet { 8 } else { 0 };
+        let b0 = op >> 16;
+        let b1 = (op >> 8) & 0xff;
+        match b0 {
+            0xa0 => {
+                self.fetch(prefix + 8)?;
+                self.arith_binary(quiet, |x, y| Some(x + y))
+            }
+            0xa1 => {
+                self.fetch(prefix + 8)?;
+                self.arith_binary(quiet, |x, y| Some(x - y))
+            }
+            0xa2 => {
+                self.fetch(prefix + 8)?;
+                self.arith_binary(quiet, |x, y| Some(y - x))
+            }
+            0xa3 => {
+                self.fetch(prefix + 8)?;
+                self.arith_unary(quiet, |x| Some(-x))
+            }
+            0xa4 => {
+                self.fetch(prefix + 8)?;
+                self.arith_unary(quiet, |x| Some(x + 1))
+            }
+            0xa5 => {
+                self.fetch(prefix + 8)?;
+                self.arith_unary(quiet, |x| Some(x - 1))
+            }
+            0xa6 => {
+                self.fetch(prefix + 16)?;
+                let value = b1 as u8 as i8;
+                self.arith_unary(quiet, |x| Some(x + value))
+            }
+            0xa7 => {
+                self.fetch(prefix + 16)?;
+                let value = b1 as u8 as i8;
+                self.arith_unary(quiet, |x| Some(x * value))
+            }
+            0xa8 => {
+                self.fetch(prefix + 8)?;
+                self.arith_binary(quiet, |x, y| Some(x * y))
+            }
+            0xa9 => self.exec_divmod(op, quiet, prefix),
+            0xaa => {
+                self.fetch(prefix + 16)?;
+                let shift = b1 as usize + 1;
+                self.arith_unary(quiet, |x| Some(x << shift))
+            }
+            0xab => {
+                self.fetch(prefix + 16)?;
+                let shift = b1 as usize + 1;
+                self.arith_unary(quiet, |x| Some(x >> shift))
+            }
+            0xac | 0xad => {
+                self.fetch(prefix + 8)?;
+                let shift = self.pop_shift(quiet, 1023)?;
+                let x = self.arith_pop(quiet)?;
+                let result = match (x, shift) {
+                    (Some(x), Some(shift)) if b0 == 0xac => Some(x << shift),
+                    (Some(x), Some(shift)) => Some(x >> shift),
+                    _ => None,
+                };
+                self.arith_push(quiet, result)
+            }
+            0xae => {
+                self.fetch(prefix + 8)?;
+                let shift = self.pop_shift(quiet, 1023)?;
+                self.arith_push(quiet, shift.map(|shift| BigInt::from(1) << shift))
+            }
+            0xb0 => {
+                self.fetch(prefix + 8)?;
+                self.arith_binary(quiet, |x, y| Some(x & y))
+            }
+            0xb1 => {
+                self.fetch(prefix + 8)?;
+                self.arith_binary(quiet, |x, y| Some(x | y))
+            }
+            0xb2 => {
+                self.fetch(prefix + 8)?;
+                self.arith_binary(quiet, |x, y| Some(x ^ y))
+            }
+            0xb3 => {
+                self.fetch(prefix + 8)?;
+                self.arith_unary(quiet, |x| Some(-x - 1))
+            }
+            0xb4 | 0xb5 => {
+                self.fetch(prefix + 16)?;
+                let bits = b1 as usize + 1;
+                let unsigned = b0 == 0xb5;
+                self.arith_unary(quiet, |x| fits(&x, bits, unsigned).then_some(x))
+            }
+            0xb6 => self.exec_arith_b6(b1, quiet, prefix),
+            0xb8 => {
+                self.fetch(prefix + 8)?;
+                self.arith_unary(quiet, |x| {
+                    Some(BigInt::from(match x.sign() {
+                        Sign::Minus => -1,
+                        Sign::NoSign => 0,
+                        Sign::Plus => 1,
+                    }))
+                })
+            }
+            0xb9 => {
+                self.fetch(prefix + 8)?;
+                self.compare(quiet, |ord| -((ord == Ordering::Less) as i64))
+            }
+            0xba => {
+                self.fetch(prefix + 8)?;
+                self.compare(quiet, |ord| -((ord == Ordering::Equal) as i64))
+            }
+            0xbb => {
+                self.fetch(prefix + 8)?;
+                self.compare(quiet, |ord| -((ord != Ordering::Greater) as i64))
+            }
+            0xbc => {
+                self.fetch(prefix + 8)?;
+                self.compare(quiet, |ord| -((ord == Ordering::Greater) as i64))
+            }
+            0xbd => {
+                self.fetch(prefix + 8)?;
+                self.compare(quiet, |ord| -((ord != Ordering::Equal) as i64))
+            }
+            0xbe => {
+                self.fetch(prefix + 8)?;
+                self.compare(quiet, |ord| -((ord != Ordering::Less) as i64))
+            }
+            0xbf => {
+                self.fetch(prefix + 8)?;
+                self.compare(quiet, |ord| ord as i64)
+            }
+            0xc0..=0xc3 => {
+                self.fetch(prefix + 16)?;
+                let y = BigInt::from(b1 as u8 as i8);
+                self.arith_unary(quiet, |x| {
+                    let result = match b0 {
+                        0xc0 => x == y,
+                        0xc1 => x < y,
+                        0xc2 => x > y,
+                        _ => x != y,
+                    };
+                    Some(BigInt::from(-(result as i64)))
+                })
+            }
+            0xc4 => {
+                self.fetch(prefix + 8)?;
+                let value = self.pop_int_or_nan()?;
+                self.push_bool(value.is_none());
+                Ok(())
+            }
+            0xc5 => {
+                self.fetch(prefix + 8)?;
+                match self.peek(0)? {
+                    VmValue::Int(_) => Ok(()),
+                    VmValue::NaN => Err(VmExitCode::IntegerOverflow.into()),
+                    _ => Err(VmExitCode::TypeCheck.into()),
+                }
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    /// Pops a shift amount; in quiet mode an out-of-range amount yields NaN.
+    fn pop_shift(&mut self, quiet: bool, max: i64) -> VmResult<Option<usize>> {
+        if !quiet {
+            return Ok(Some(self.pop_range(0, max)? as usize));
+        }
+        Ok(self
+            .pop_int_or_nan()?
+            .and_then(|value| i64::try_from(&value).ok())
+            .filter(|value| (0..=max).contains(value))
+            .map(|value| value as usize))
+    }
+
+    fn exec_arith_b6(&mut self, b1: u32, quiet: bool, prefix: usize) -> VmResult<()> {
+        self.fetch(prefix + 16)?;
+        match b1 {
+            0x00 | 0x01 => {
+                let bits = self.pop_shift(quiet, 1023)?;
+                let x = self.arith_pop(quiet)?;
+                let result = match (x, bits) {
+                    (Some(x), Some(bits)) => fits(&x, bits, b1 == 0x01).then_some(x),
+                    _ => None,
+                };
+                self.arith_push(quiet, result)
+            }
+            0x02 => self.arith_unary(quiet, |x| Some(BigInt::from(signed_bit_size(&x)))),
+            0x03 => {
+                let x = self.arith_pop(quiet)?;
+                match x {
+                    Some(x) if x.sign() == Sign::Minus => {
+                        if quiet {
+                            self.push(VmValue::NaN);
+                            Ok(())
+                        } else {
+                            Err(VmExitCode::RangeCheck.into())
+                        }
+                    }
+                    Some(x) => self.arith_push(quiet, Some(BigInt::from(x.bits()))),
+                    None => self.arith_push(quiet, None),
+                }
+            }
+            0x08 => self.arith_binary(quiet, |x, y| Some(x.min(y))),
+            0x09 => self.arith_binary(quiet, |x, y| Some(x.max(y))),
+            0x0a => {
+                let y = self.arith_pop(quiet)?;
+                let x = self.arith_pop(quiet)?;
+                match (x, y) {
+                    (Some(x), Some(y)) => {
+                        let (min, max) = if x <= y { (x, y) } else { (y, x) };
+                        self.arith_push(quiet, Some(min))?;
+                        self.arith_push(quiet, Some(max))
+                    }
+                    _ => {
+                        self.arith_push(quiet, None)?;
+                        self.arith_push(quiet, None)
+                    }
+                }
+            }
+            0x0b => self.arith_unary(quiet, |x| {
+                Some(if x.sign() == Sign::Minus { -x } else { x })
+            }),
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    /// The `A9mscdf` family: multiply (`m`), shift mode (`s`), constant
+    /// shift (`c`), result selector (`d`) and rounding (`f`).
+    fn exec_divmod(&mut self, op: u32, quiet: bool, prefix: usize) -> VmResult<()> {
+        let args = (op >> 8) & 0xff;
+        let multiply = args >> 7 == 1;
+        let shift_mode = (args >> 5) & 3;
+        let constant = (args >> 4) & 1 == 1;
+        let selector = (args >> 2) & 3;
+        let rounding =
+            Rounding::from_bits(args & 3).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+        let valid = match (multiply, shift_mode) {
+            (_, 0) => !constant,
+            (_, 1) | (true, 2) => true,
+            _ => false,
+        };
+        if !valid {
+            return Err(VmExitCode::InvalidOpcode.into());
+        }
+        let shift = if constant {
+            let bits = self.fetch(prefix + 24)?;
+            Some(Some((bits & 0xff) as usize + 1))
+        } else {
+            self.fetch(prefix + 16)?;
+            None
+        };
+
+        let shift = match (shift_mode, shift) {
+            (0, _) => None,
+            (_, Some(shift)) => Some(shift),
+            (_, None) => Some(self.pop_shift(quiet, 256)?),
+        };
+        let divisor = match shift_mode {
+            0 | 2 => Some(self.arith_pop(quiet)?),
+            _ => None,
+        };
+        let addend = if selector == 0 {
+            Some(self.arith_pop(quiet)?)
+        } else {
+            None
+        };
+        let factor = if multiply && shift_mode != 2 {
+            Some(self.arith_pop(quiet)?)
+        } else {
+            None
+        };
+        let x = self.arith_pop(quiet)?;
+
+        let compute = || -> Option<(BigInt, BigInt)> {
+            let mut x = x?;
+            if let Some(factor) = factor {
+                x *= factor?;
+            }
+            let divisor = match shift_mode {
+                1 => BigInt::from(1) << shift??,
+                2 => {
+                    x <<= shift??;
+                    divisor??
+                }
+                _ => divisor??,
+            };
+            if let Some(addend) = addend {
+                x += addend?;
+            }
+            if divisor.sign() == Sign::NoSign {
+                return None;
+            }
+            Some(div_rem(&x, &divisor, rounding))
+        };
+        let result = compute();
+        let (q, r) = match result {
+            Some((q, r)) => (Some(q), Some(r)),
+            None => (None, None),
+        };
+        match selector {
+            1 => self.arith_push(quiet, q),
+            2 => self.arith_push(quiet, r),
+            _ => {
+                self.arith_push(quiet, q)?;
+                self.arith_push(quiet, r)
+            }
+        }
+    }
+}
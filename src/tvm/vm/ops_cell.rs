@@ -0,0 +1,850 @@
+use super::state::{GAS_CELL_CREATE, fits};
+use super::*;
+use std::cmp::Ordering;
+
+impl VmState {
+    /// Slice comparison, cell serialization and deserialization primitives
+    /// (`C7`..`D7`).
+    pub(crate) fn exec_cell(&mut self, op: u32) -> VmResult<()> {
+        let b0 = op >> 16;
+        let b1 = (op >> 8) & 0xff;
+        match b0 {
+            0xc7 => self.exec_slice_compare(b1),
+            0xc8 => {
+                self.fetch(8)?;
+                self.push(VmValue::Builder(VmBuilder::new()));
+                Ok(())
+            }
+            0xc9 => {
+                self.fetch(8)?;
+                let builder = self.pop_builder()?;
+                let cell = self.build_cell(&builder)?;
+                self.push(VmValue::Cell(cell));
+                Ok(())
+            }
+            0xca | 0xcb => {
+                self.fetch(16)?;
+                self.store_int(b1 as usize + 1, b0 == 0xcb, false, false)
+            }
+            0xcc => {
+                self.fetch(8)?;
+                self.store_ref_common(false, false)
+            }
+            0xcd => {
+                self.fetch(8)?;
+                self.store_builder_ref_common(true, false)
+            }
+            0xce => {
+                self.fetch(8)?;
+                self.store_slice_common(false, false)
+            }
+            0xcf => self.exec_cell_store_ext(op),
+            0xd0 => {
+                self.fetch(8)?;
+                let cell = self.pop_cell()?;
+                let slice = self.load_cell_slice(cell)?;
+                self.push(VmValue::Slice(slice));
+                Ok(())
+            }
+            0xd1 => {
+                self.fetch(8)?;
+                let slice = self.pop_slice()?;
+                if !slice.is_empty() {
+                    return Err(VmExitCode::CellUnderflow.into());
+                }
+                Ok(())
+            }
+            0xd2 | 0xd3 => {
+                self.fetch(16)?;
+                self.load_int(b1 as usize + 1, b0 == 0xd3, false, false)
+            }
+            0xd4 => {
+                self.fetch(8)?;
+                let mut slice = self.pop_slice()?;
+                let cell = slice
+                    .load_ref()
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                self.push(VmValue::Cell(cell));
+                self.push(VmValue::Slice(slice));
+                Ok(())
+            }
+            0xd5 => {
+                self.fetch(8)?;
+                let mut slice = self.pop_slice()?;
+                let cell = slice
+                    .load_ref()
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                self.push(VmValue::Slice(slice));
+                let loaded = self.load_cell_slice(cell)?;
+                self.push(VmValue::Slice(loaded));
+                Ok(())
+            }
+            0xd6 => {
+                self.fetch(16)?;
+                self.load_slice_common(b1 as usize + 1, false, false)
+            }
+            0xd7 => self.exec_cell_load_ext(op),
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn exec_slice_compare(&mut self, b1: u32) -> VmResult<()> {
+        self.fetch(16)?;
+        match b1 {
+            0x00..=0x03 => {
+                let slice = self.pop_slice()?;
+                let result = match b1 {
+                    0x00 => slice.is_empty(),
+                    0x01 => slice.bits() == 0,
+                    0x02 => slice.refs() == 0,
+                    _ => slice.bits() > 0 && slice.bit_at(0),
+                };
+                self.push_bool(result);
+                Ok(())
+            }
+            0x04 | 0x05 => {
+                let right = self.pop_slice()?.bit_vec();
+                let left = self.pop_slice()?.bit_vec();
+                if b1 == 0x04 {
+                    self.push_small(match left.cmp(&right) {
+                        Ordering::Less => -1,
+                        Ordering::Equal => 0,
+                        Ordering::Greater => 1,
+                    });
+                } else {
+                    self.push_bool(left == right);
+                }
+                Ok(())
+            }
+            0x08..=0x0f => {
+                let second = self.pop_slice()?.bit_vec();
+                let first = self.pop_slice()?.bit_vec();
+                let (a, b) = if b1 & 1 == 0 {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                let proper = b1 & 2 != 0;
+                let matches = if b1 & 4 == 0 {
+                    b.starts_with(&a)
+                } else {
+                    b.ends_with(&a)
+                };
+                self.push_bool(matches && (!proper || a.len() < b.len()));
+                Ok(())
+            }
+            0x10..=0x13 => {
+                let slice = self.pop_slice()?;
+                let bits = slice.bit_vec();
+                let target = b1 & 1 == 1;
+                let count = if b1 & 2 == 0 {
+                    bits.iter().take_while(|bit| **bit == target).count()
+                } else {
+                    bits.iter().rev().take_while(|bit| **bit == target).count()
+                };
+                self.push_small(count as i64);
+                Ok(())
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn exec_cell_store_ext(&mut self, op: u32) -> VmResult<()> {
+        let b1 = (op >> 8) & 0xff;
+        if b1 >= 0x80 {
+            // STSLICECONST: 9-bit prefix, 2-bit refs, 3-bit length.
+            let args = self.fetch(14)?;
+            let refs = ((args >> 3) & 3) as usize;
+            let bits = 8 * (args & 7) as usize + 2;
+            let data = self.fetch_tagged_data(bits, refs)?;
+            let mut builder = self.pop_builder()?;
+            builder
+                .append_slice(&data)
+                .map_err(|_| VmError::new(VmExitCode::CellOverflow))?;
+            self.push(VmValue::Builder(builder));
+            return Ok(());
+        }
+        let b2 = op & 0xff;
+        match b1 {
+            0x00..=0x07 => {
+                self.fetch(16)?;
+                let unsigned = b1 & 1 == 1;
+                let max = if unsigned { 256 } else { 257 };
+                let bits = self.pop_range(0, max)? as usize;
+                self.store_int(bits, unsigned, b1 & 2 != 0, b1 & 4 != 0)
+            }
+            0x08..=0x0f => {
+                self.fetch(24)?;
+                self.store_int(b2 as usize + 1, b1 & 1 == 1, b1 & 2 != 0, b1 & 4 != 0)
+            }
+            0x10..=0x1f => {
+                self.fetch(16)?;
+                let reversed = b1 & 4 != 0;
+                let quiet = b1 & 8 != 0;
+                match b1 & 3 {
+                    0 => self.store_ref_common(reversed, quiet),
+                    1 => self.store_builder_ref_common(reversed, quiet),
+                    2 => self.store_slice_common(reversed, quiet),
+                    _ => self.store_builder_common(reversed, quiet),
+                }
+            }
+            0x20 | 0x21 => {
+                self.fetch(16)?;
+                let count = if b1 == 0x20 { 1 } else { 2 };
+                let mut refs = Vec::with_capacity(count);
+                for _ in 0..count {
+                    refs.push(self.fetch_ref()?);
+                }
+                let mut builder = self.pop_builder()?;
+                if !builder.can_extend(0, count) {
+                    return Err(VmExitCode::CellOverflow.into());
+                }
+                for cell in refs {
+                    builder.store_ref(cell);
+                }
+                self.push(VmValue::Builder(builder));
+                Ok(())
+            }
+            0x23 => {
+                self.fetch(16)?;
+                let exotic = self.pop_bool()?;
+                let builder = self.pop_builder()?;
+                if exotic {
+                    self.consume_gas(GAS_CELL_CREATE)?;
+                    let cell = builder
+                        .build_exotic()
+                        .map_err(|_| VmError::new(VmExitCode::CellOverflow))?;
+                    self.push(VmValue::Cell(cell));
+                } else {
+                    let cell = self.build_cell(&builder)?;
+                    self.push(VmValue::Cell(cell));
+                }
+                Ok(())
+            }
+            0x28..=0x2b => {
+                self.fetch(16)?;
+                let unsigned = b1 & 1 == 1;
+                let bytes = if b1 & 2 != 0 { 8 } else { 4 };
+                let builder = self.pop_builder()?;
+                let value = self.pop_int()?;
+                if !fits(&value, bytes * 8, unsigned) {
+                    return Err(VmExitCode::RangeCheck.into());
+                }
+                let mut builder = builder;
+                if !builder.can_extend(bytes * 8, 0) {
+                    return Err(VmExitCode::CellOverflow.into());
+                }
+                let wrapped = if value.sign() == Sign::Minus {
+                    (BigInt::from(1) << (bytes * 8)) + value
+                } else {
+                    value
+                };
+                let (_, magnitude) = wrapped.into_parts();
+                let mut le = magnitude.to_bytes_le();
+                le.resize(bytes, 0);
+                builder.store_bytes(&le);
+                self.push(VmValue::Builder(builder));
+                Ok(())
+            }
+            0x30..=0x37 => {
+                self.fetch(16)?;
+                let builder = self.pop_builder()?;
+                match b1 & 7 {
+                    0 => {
+                        let cell = builder
+                            .build()
+                            .map_err(|_| VmError::new(VmExitCode::CellOverflow))?;
+                        let depth = if cell.reference_count() == 0 {
+                            0
+                        } else {
+                            cell.depth()
+                        };
+                        self.push_small(depth as i64);
+                    }
+                    1 => self.push_small(builder.bits() as i64),
+                    2 => self.push_small(builder.refs() as i64),
+                    3 => {
+                        self.push_small(builder.bits() as i64);
+                        self.push_small(builder.refs() as i64);
+                    }
+                    5 => self.push_small((MAX_CELL_BITS - builder.bits()) as i64),
+                    6 => self.push_small((MAX_CELL_REFS - builder.refs()) as i64),
+                    7 => {
+                        self.push_small((MAX_CELL_BITS - builder.bits()) as i64);
+                        self.push_small((MAX_CELL_REFS - builder.refs()) as i64);
+                    }
+                    _ => return Err(VmExitCode::InvalidOpcode.into()),
+                }
+                Ok(())
+            }
+            0x38..=0x3f => {
+                let quiet = b1 & 4 != 0;
+                let (bits, refs) = match b1 & 3 {
+                    0 => {
+                        self.fetch(24)?;
+                        (b2 as usize + 1, 0)
+                    }
+                    1 => {
+                        self.fetch(16)?;
+                        (self.pop_range(0, 1023)? as usize, 0)
+                    }
+                    2 => {
+                        self.fetch(16)?;
+                        (0, self.pop_range(0, 7)? as usize)
+                    }
+                    _ => {
+                        self.fetch(16)?;
+                        let refs = self.pop_range(0, 7)? as usize;
+                        (self.pop_range(0, 1023)? as usize, refs)
+                    }
+                };
+                let builder = self.pop_builder()?;
+                let ok = builder.can_extend(bits, refs);
+                if quiet {
+                    self.push_bool(ok);
+                } else if !ok {
+                    return Err(VmExitCode::CellOverflow.into());
+                }
+                Ok(())
+            }
+            0x40..=0x42 => {
+                self.fetch(16)?;
+                let bit = match b1 {
+                    0x40 => false,
+                    0x41 => true,
+                    _ => self.pop_range(0, 1)? == 1,
+                };
+                let count = self.pop_range(0, 1023)? as usize;
+                let mut builder = self.pop_builder()?;
+                if !builder.can_extend(count, 0) {
+                    return Err(VmExitCode::CellOverflow.into());
+                }
+                for _ in 0..count {
+                    builder.store_bit(bit);
+                }
+                self.push(VmValue::Builder(builder));
+                Ok(())
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    /// Stores an integer into a builder.
+    ///
+    /// Normal order is `x b`, reversed order is `b x`. Quiet variants push
+    /// back the arguments with `-1` on overflow or `1` on range failure, and
+    /// `0` on success.
+    fn store_int(
+        &mut self,
+        bits: usize,
+        unsigned: bool,
+        reversed: bool,
+        quiet: bool,
+    ) -> VmResult<()> {
+        let (value, builder) = if reversed {
+            let value = self.pop_int_or_nan()?;
+            (value, self.pop_builder()?)
+        } else {
+            let builder = self.pop_builder()?;
+            (self.pop_int_or_nan()?, builder)
+        };
+        let failure = if !builder.can_extend(bits, 0) {
+            Some((-1, VmExitCode::CellOverflow))
+        } else if !value
+            .as_ref()
+            .is_some_and(|value| fits(value, bits, unsigned))
+        {
+            Some((1, VmExitCode::RangeCheck))
+        } else {
+            None
+        };
+        if let Some((quiet_code, error)) = failure {
+            if !quiet {
+                return Err(error.into());
+            }
+            let value = value.map(VmValue::Int).unwrap_or(VmValue::NaN);
+            if reversed {
+                self.push(VmValue::Builder(builder));
+                self.push(value);
+            } else {
+                self.push(value);
+                self.push(VmValue::Builder(builder));
+            }
+            self.push_small(quiet_code);
+            return Ok(());
+        }
+        let mut builder = builder;
+        let value = value.unwrap_or_default();
+        if unsigned {
+            let (_, magnitude) = value.into_parts();
+            builder.store_uint(&magnitude, bits);
+        } else {
+            builder.store_int(&value, bits);
+        }
+        self.push(VmValue::Builder(builder));
+        if quiet {
+            self.push_small(0);
+        }
+        Ok(())
+    }
+
+    fn pop_store_args<T>(
+        &mut self,
+        reversed: bool,
+        pop_value: impl FnOnce(&mut Self) -> VmResult<T>,
+    ) -> VmResult<(T, VmBuilder)> {
+        if reversed {
+            let value = pop_value(self)?;
+            Ok((value, self.pop_builder()?))
+        } else {
+            let builder = self.pop_builder()?;
+            Ok((pop_value(self)?, builder))
+        }
+    }
+
+    fn finish_store(
+        &mut self,
+        reversed: bool,
+        quiet: bool,
+        ok: bool,
+        value: VmValue,
+        builder: VmBuilder,
+    ) -> VmResult<()> {
+        if ok {
+            self.push(VmValue::Builder(builder));
+            if quiet {
+                self.push_small(0);
+            }
+            return Ok(());
+        }
+        if !quiet {
+            return Err(VmExitCode::CellOverflow.into());
+        }
+        if reversed {
+            self.push(VmValue::Builder(builder));
+            self.push(value);
+        } else {
+            self.push(value);
+            self.push(VmValue::Builder(builder));
+        }
+        self.push_small(-1);
+        Ok(())
+    }
+
+    fn store_ref_common(&mut self, reversed: bool, quiet: bool) -> VmResult<()> {
+        let (cell, mut builder) = self.pop_store_args(reversed, Self::pop_cell)?;
+        let ok = builder.can_extend(0, 1);
+        if ok {
+            builder.store_ref(cell.clone());
+        }
+        self.finish_store(reversed, quiet, ok, VmValue::Cell(cell), builder)
+    }
+
+    /// STBREF (`b' b`) or, with `child_on_top`, STBREFR (`b b'`): finalizes
+    /// `b'` into a cell and stores it as a reference of `b`.
+    fn store_builder_ref_common(&mut self, child_on_top: bool, quiet: bool) -> VmResult<()> {
+        let (child, mut builder) = self.pop_store_args(child_on_top, Self::pop_builder)?;
+        let ok = builder.can_extend(0, 1);
+        if ok {
+            let cell = self.build_cell(&child)?;
+            builder.store_ref(cell);
+        }
+        self.finish_store(child_on_top, quiet, ok, VmValue::Builder(child), builder)
+    }
+
+    fn store_slice_common(&mut self, reversed: bool, quiet: bool) -> VmResult<()> {
+        let (slice, mut builder) = self.pop_store_args(reversed, Self::pop_slice)?;
+        let ok = builder.can_extend(slice.bits(), slice.refs());
+        if ok {
+            builder
+                .append_slice(&slice)
+                .map_err(|_| VmError::new(VmExitCode::CellOverflow))?;
+        }
+        self.finish_store(reversed, quiet, ok, VmValue::Slice(slice), builder)
+    }
+
+    fn store_builder_common(&mut self, reversed: bool, quiet: bool) -> VmResult<()> {
+        let (other, mut builder) = self.pop_store_args(reversed, Self::pop_builder)?;
+        let ok = builder.can_extend(other.bits(), other.refs());
+        if ok {
+            builder
+                .append_builder(&other)
+                .map_err(|_| VmError::new(VmExitCode::CellOverflow))?;
+        }
+        self.finish_store(reversed, quiet, ok, VmValue::Builder(other), builder)
+    }
+
+    /// Loads an integer from a slice (`s – x s'`), optionally preloading or
+    /// using the quiet `-1`/`0` success flag.
+    fn load_int(
+        &mut self,
+        bits: usize,
+        unsigned: bool,
+        preload: bool,
+        quiet: bool,
+    ) -> VmResult<()> {
+        let mut slice = self.pop_slice()?;
+        let value = if unsigned {
+            slice.load_uint(bits).map(BigInt::from)
+        } else {
+            slice.load_int(bits)
+        };
+        self.finish_load(value.map(VmValue::Int), slice, preload, quiet)
+    }
+
+    fn finish_load(
+        &mut self,
+        value: Option<VmValue>,
+        remainder: VmSlice,
+        preload: bool,
+        quiet: bool,
+    ) -> VmResult<()> {
+        match value {
+            Some(value) => {
+                self.push(value);
+                if !preload {
+                    self.push(VmValue::Slice(remainder));
+                }
+                if quiet {
+                    self.push_bool(true);
+                }
+                Ok(())
+            }
+            None if quiet => {
+                if !preload {
+                    self.push(VmValue::Slice(remainder));
+                }
+                self.push_bool(false);
+                Ok(())
+            }
+            None => Err(VmExitCode::CellUnderflow.into()),
+        }
+    }
+
+    fn load_slice_common(&mut self, bits: usize, preload: bool, quiet: bool) -> VmResult<()> {
+        let mut slice = self.pop_slice()?;
+        let original = slice.clone();
+        let prefix = slice.split_prefix(bits, 0);
+        let remainder = if prefix.is_some() { slice } else { original };
+        self.finish_load(prefix.map(VmValue::Slice), remainder, preload, quiet)
+    }
+
+    fn exec_cell_load_ext(&mut self, op: u32) -> VmResult<()> {
+        let b1 = (op >> 8) & 0xff;
+        let b2 = op & 0xff;
+        match b1 {
+            0x00..=0x07 => {
+                self.fetch(16)?;
+                let unsigned = b1 & 1 == 1;
+                let max = if unsigned { 256 } else { 257 };
+                let bits = self.pop_range(0, max)? as usize;
+                self.load_int(bits, unsigned, b1 & 2 != 0, b1 & 4 != 0)
+            }
+            0x08..=0x0f => {
+                self.fetch(24)?;
+                self.load_int(b2 as usize + 1, b1 & 1 == 1, b1 & 2 != 0, b1 & 4 != 0)
+            }
+            0x10..=0x17 => {
+                self.fetch(16)?;
+                let bits = 32 * ((b1 & 7) as usize + 1);
+                let slice = self.pop_slice()?;
+                let available = slice.bits().min(bits);
+                let value = slice.preload_uint(available).unwrap_or_default() << (bits - available);
+                self.push(VmValue::Slice(slice));
+                self.push(VmValue::Int(BigInt::from(value)));
+                Ok(())
+            }
+            0x18..=0x1b => {
+                self.fetch(16)?;
+                let bits = self.pop_range(0, 1023)? as usize;
+                self.load_slice_common(bits, b1 & 1 != 0, b1 & 2 != 0)
+            }
+            0x1c..=0x1f => {
+                self.fetch(24)?;
+                self.load_slice_common(b2 as usize + 1, b1 & 1 != 0, b1 & 2 != 0)
+            }
+            0x20..=0x24 => {
+                self.fetch(16)?;
+                let (skip, keep) = if b1 == 0x24 {
+                    let keep = self.pop_range(0, 1023)? as usize;
+                    (self.pop_range(0, 1023)? as usize, Some(keep))
+                } else {
+                    (self.pop_range(0, 1023)? as usize, None)
+                };
+                let mut slice = self.pop_slice()?;
+                let ok = match b1 {
+                    0x20 => slice
+                        .split_prefix(skip, 0)
+                        .map(|prefix| slice = prefix)
+                        .is_some(),
+                    0x21 => slice.advance(skip, 0),
+                    0x22 => slice.only_last(skip, 0),
+                    0x23 => slice.skip_last(skip, 0),
+                    _ => {
+                        slice.advance(skip, 0)
+                            && slice
+                                .split_prefix(keep.unwrap_or_default(), 0)
+                                .map(|prefix| slice = prefix)
+                                .is_some()
+                    }
+                };
+                if !ok {
+                    return Err(VmExitCode::CellUnderflow.into());
+                }
+                self.push(VmValue::Slice(slice));
+                Ok(())
+            }
+            0x26 | 0x27 => {
+                self.fetch(16)?;
+                let prefix = self.pop_slice()?;
+                let slice = self.pop_slice()?;
+                self.begins_with(slice, &prefix, b1 == 0x27)
+            }
+            0x28..=0x2f => {
+                let args = self.fetch(21)?;
+                let quiet = (args >> 7) & 1 == 1;
+                let bits = 8 * (args & 0x7f) as usize + 3;
+                let prefix = self.fetch_tagged_data(bits, 0)?;
+                let slice = self.pop_slice()?;
+                self.begins_with(slice, &prefix, quiet)
+            }
+            0x30..=0x34 => {
+                self.fetch(16)?;
+                if b1 == 0x34 {
+                    let refs2 = self.pop_range(0, 4)? as usize;
+                    let bits2 = self.pop_range(0, 1023)? as usize;
+                    let refs1 = self.pop_range(0, 4)? as usize;
+                    let bits1 = self.pop_range(0, 1023)? as usize;
+                    let mut slice = self.pop_slice()?;
+                    if !slice.advance(bits1, refs1) {
+                        return Err(VmExitCode::CellUnderflow.into());
+                    }
+                    let result = slice
+                        .split_prefix(bits2, refs2)
+                        .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                    self.push(VmValue::Slice(result));
+                    return Ok(());
+                }
+                let refs = self.pop_range(0, 4)? as usize;
+                let bits = self.pop_range(0, 1023)? as usize;
+                let mut slice = self.pop_slice()?;
+                let ok = match b1 {
+                    0x30 => slice
+                        .split_prefix(bits, refs)
+                        .map(|prefix| slice = prefix)
+                        .is_some(),
+                    0x31 => slice.advance(bits, refs),
+                    0x32 => slice.only_last(bits, refs),
+                    _ => slice.skip_last(bits, refs),
+                };
+                if !ok {
+                    return Err(VmExitCode::CellUnderflow.into());
+                }
+                self.push(VmValue::Slice(slice));
+                Ok(())
+            }
+            0x36 | 0x37 => {
+                self.fetch(16)?;
+                let refs = self.pop_range(0, 4)? as usize;
+                let bits = self.pop_range(0, 1023)? as usize;
+                let mut slice = self.pop_slice()?;
+                let original = slice.clone();
+                match slice.split_prefix(bits, refs) {
+                    Some(prefix) => {
+                        self.push(VmValue::Slice(prefix));
+                        self.push(VmValue::Slice(slice));
+                        if b1 == 0x37 {
+                            self.push_bool(true);
+                        }
+                        Ok(())
+                    }
+                    None if b1 == 0x37 => {
+                        self.push(VmValue::Slice(original));
+                        self.push_bool(false);
+                        Ok(())
+                    }
+                    None => Err(VmExitCode::CellUnderflow.into()),
+                }
+            }
+            0x39 => {
+                self.fetch(16)?;
+                let cell = self.pop_cell()?;
+                let exotic = cell.is_exotic();
+                self.charge_cell_load(&cell)?;
+                self.push(VmValue::Slice(VmSlice::new(cell)));
+                self.push_bool(exotic);
+                Ok(())
+            }
+            0x3a | 0x3b => {
+                self.fetch(16)?;
+                let cell = self.pop_cell()?;
+                if cell.is_exotic() {
+                    if b1 == 0x3b {
+                        self.push(VmValue::Cell(cell));
+                        self.push_bool(false);
+                        return Ok(());
+                    }
+                    return Err(VmExitCode::CellUnderflow.into());
+                }
+                self.push(VmValue::Cell(cell));
+                if b1 == 0x3b {
+                    self.push_bool(true);
+                }
+                Ok(())
+            }
+            0x41..=0x43 | 0x45..=0x47 => {
+                self.fetch(16)?;
+                let quiet = b1 & 4 != 0;
+                let refs = if b1 & 2 != 0 {
+                    self.pop_range(0, 4)? as usize
+                } else {
+                    0
+                };
+                let bits = if b1 & 1 != 0 {
+                    self.pop_range(0, 1023)? as usize
+                } else {
+                    0
+                };
+                let slice = self.pop_slice()?;
+                let ok = slice.bits() >= bits && slice.refs() >= refs;
+                if quiet {
+                    self.push_bool(ok);
+                } else if !ok {
+                    return Err(VmExitCode::CellUnderflow.into());
+                }
+                Ok(())
+            }
+            0x48 => {
+                self.fetch(16)?;
+                let index = self.pop_range(0, 3)? as usize;
+                let slice = self.pop_slice()?;
+                let cell = slice
+                    .reference(index)
+                    .cloned()
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                self.push(VmValue::Cell(cell));
+                Ok(())
+            }
+            0x49..=0x4b => {
+                self.fetch(16)?;
+                let slice = self.pop_slice()?;
+                if b1 & 1 != 0 {
+                    self.push_small(slice.bits() as i64);
+                }
+                if b1 & 2 != 0 {
+                    self.push_small(slice.refs() as i64);
+                }
+                Ok(())
+            }
+            0x4c..=0x4f => {
+                self.fetch(16)?;
+                let slice = self.pop_slice()?;
+                let cell = slice
+                    .reference((b1 & 3) as usize)
+                    .cloned()
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                self.push(VmValue::Cell(cell));
+                Ok(())
+            }
+            0x50..=0x5f => {
+                self.fetch(16)?;
+                let unsigned = b1 & 1 != 0;
+                let bytes = if b1 & 2 != 0 { 8 } else { 4 };
+                let preload = b1 & 4 != 0;
+                let quiet = b1 & 8 != 0;
+                let mut slice = self.pop_slice()?;
+                let value = slice.load_bits(bytes * 8).map(|mut raw| {
+                    raw.reverse();
+                    let unsigned_value = BigInt::from(BigUint::from_bytes_be(&raw));
+                    if !unsigned && raw[0] & 0x80 != 0 {
+                        VmValue::Int(unsigned_value - (BigInt::from(1) << (bytes * 8)))
+                    } else {
+                        VmValue::Int(unsigned_value)
+                    }
+                });
+                self.finish_load(value, slice, preload, quiet)
+            }
+            0x60..=0x62 => {
+                self.fetch(16)?;
+                let bit = match b1 {
+                    0x60 => false,
+                    0x61 => true,
+                    _ => self.pop_range(0, 1)? == 1,
+                };
+                let mut slice = self.pop_slice()?;
+                let count = slice
+                    .bit_vec()
+                    .iter()
+                    .take_while(|value| **value == bit)
+                    .count();
+                slice.advance(count, 0);
+                self.push_small(count as i64);
+                self.push(VmValue::Slice(slice));
+                Ok(())
+            }
+            0x64 => {
+                self.fetch(16)?;
+                let slice = self.pop_slice()?;
+                let depth = (0..slice.refs())
+                    .filter_map(|index| slice.reference(index))
+                    .map(|cell| cell.depth() + 1)
+                    .max()
+                    .unwrap_or(0);
+                self.push_small(depth as i64);
+                Ok(())
+            }
+            0x65 => {
+                self.fetch(16)?;
+                let depth = self.pop_maybe_cell()?.map(|cell| cell.depth()).unwrap_or(0);
+                self.push_small(depth as i64);
+                Ok(())
+            }
+            0x66 | 0x67 => {
+                self.fetch(16)?;
+                let cell = self.pop_cell()?;
+                self.push_small(if b1 == 0x66 {
+                    cell.level() as i64
+                } else {
+                    cell.level_mask() as i64
+                });
+                Ok(())
+            }
+            0x68..=0x71 => {
+                self.fetch(16)?;
+                // Only level-zero cells are produced by the interpreter, so
+                // every hash and depth index refers to the representation.
+                if b1 >= 0x70 {
+                    self.pop_range(0, 3)?;
+                }
+                let cell = self.pop_cell()?;
+                if b1 == 0x70 || (0x68..=0x6b).contains(&b1) {
+                    let hash = cell.hash();
+                    self.push(VmValue::Int(BigInt::from(BigUint::from_bytes_be(&hash))));
+                } else {
+                    self.push_small(cell.depth() as i64);
+                }
+                Ok(())
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn begins_with(&mut self, mut slice: VmSlice, prefix: &VmSlice, quiet: bool) -> VmResult<()> {
+        let matches = slice.bits() >= prefix.bits()
+            && (0..prefix.bits()).all(|offset| slice.bit_at(offset) == prefix.bit_at(offset));
+        if matches {
+            slice.advance(prefix.bits(), 0);
+            self.push(VmValue::Slice(slice));
+            if quiet {
+                self.push_bool(true);
+            }
+            Ok(())
+        } else if quiet {
+            self.push(VmValue::Slice(slice));
+            self.push_bool(false);
+            Ok(())
+        } else {
+            Err(VmExitCode::CellUnderflow.into())
+        }
+    }
+}
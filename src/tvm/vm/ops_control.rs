@@ -0,0 +1,812 @@
+use super::*;
+
+/// Decodes a 4-bit argument count where `15` means "all" (`-1`).
+fn optional_count(value: u32) -> Option<usize> {
+    if value == 15 {
+        None
+    } else {
+        Some(value as usize)
+    }
+}
+
+impl VmState {
+    /// Continuation, conditional, loop, dictionary-call and exception
+    /// primitives (`D8`..`F3`).
+    pub(crate) fn exec_control(&mut self, op: u32) -> VmResult<()> {
+        let b0 = op >> 16;
+        let b1 = (op >> 8) & 0xff;
+        match b0 {
+            0xd8 => {
+                self.fetch(8)?;
+                let cont = self.pop_cont()?;
+                self.call(cont)
+            }
+            0xd9 => {
+                self.fetch(8)?;
+                let cont = self.pop_cont()?;
+                self.jump(cont)
+            }
+            0xda => {
+                self.fetch(16)?;
+                let cont = self.pop_cont()?;
+                self.call_args(cont, Some((b1 >> 4) as usize), Some((b1 & 0xf) as usize))
+            }
+            0xdb => self.exec_control_db(op),
+            0xdc | 0xdd => {
+                self.fetch(8)?;
+                if self.pop_bool()? == (b0 == 0xdc) {
+                    return self.ret();
+                }
+                Ok(())
+            }
+            0xde | 0xdf => {
+                self.fetch(8)?;
+                let cont = self.pop_cont()?;
+                if self.pop_bool()? == (b0 == 0xde) {
+                    return self.call(cont);
+                }
+                Ok(())
+            }
+            0xe0 | 0xe1 => {
+                self.fetch(8)?;
+                let cont = self.pop_cont()?;
+                if self.pop_bool()? == (b0 == 0xe0) {
+                    return self.jump(cont);
+                }
+                Ok(())
+            }
+            0xe2 => {
+                self.fetch(8)?;
+                let otherwise = self.pop_cont()?;
+                let then = self.pop_cont()?;
+                if self.pop_bool()? {
+                    self.call(then)
+                } else {
+                    self.call(otherwise)
+                }
+            }
+            0xe3 => self.exec_control_e3(op),
+            0xe4..=0xeb => self.exec_loop(b0),
+            0xec => {
+                self.fetch(16)?;
+                self.set_cont_args((b1 >> 4) as usize, optional_count(b1 & 0xf))
+            }
+            0xed => self.exec_control_ed(op),
+            0xee => {
+                self.fetch(16)?;
+                let code = self.pop_slice()?;
+                let cont = Arc::new(Continuation::ordinary(code));
+                self.push(VmValue::Cont(cont));
+                self.set_cont_args((b1 >> 4) as usize, optional_count(b1 & 0xf))
+            }
+            0xf0 => {
+                self.fetch(16)?;
+                self.push_small(b1 as i64);
+                let c3 = self.regs.conts[3].clone();
+                self.call(c3)
+            }
+            0xf1 => {
+                let word = self.fetch(24)?;
+                let index = (word & 0x3fff) as i64;
+                self.push_small(index);
+                let c3 = self.regs.conts[3].clone();
+                match (word >> 14) & 3 {
+                    0 => self.call(c3),
+                    1 => self.jump(c3),
+                    2 => {
+                        self.push(VmValue::Cont(c3));
+                        Ok(())
+                    }
+                    _ => Err(VmExitCode::InvalidOpcode.into()),
+                }
+            }
+            0xf2 => self.exec_throw(op),
+            0xf3 => {
+                self.fetch(16)?;
+                self.exec_try(Some((b1 >> 4) as usize), Some((b1 & 0xf) as usize))
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn exec_control_db(&mut self, op: u32) -> VmResult<()> {
+        let b1 = (op >> 8) & 0xff;
+        let b2 = op & 0xff;
+        match b1 >> 4 {
+            0x0 => {
+                self.fetch(16)?;
+                let cont = self.pop_cont()?;
+                self.call_args(cont, Some((b1 & 0xf) as usize), None)
+            }
+            0x1 => {
+                self.fetch(16)?;
+                let cont = self.pop_cont()?;
+                self.jump_args(cont, Some((b1 & 0xf) as usize))
+            }
+            0x2 => {
+                self.fetch(16)?;
+                self.ret_args((b1 & 0xf) as usize)
+            }
+            0x3 => match b1 & 0xf {
+                0x0 => {
+                    self.fetch(16)?;
+                    self.ret()
+                }
+                0x1 => {
+                    self.fetch(16)?;
+                    self.ret_alt()
+                }
+                0x2 => {
+                    self.fetch(16)?;
+                    if self.pop_bool()? {
+                        self.ret()
+                    } else {
+                        self.ret_alt()
+                    }
+                }
+                0x4 => {
+                    self.fetch(16)?;
+                    let cont = self.pop_cont()?;
+                    let cc = self.extract_cc(3, None, None)?;
+                    self.push(VmValue::Cont(cc));
+                    self.jump(cont)
+                }
+                0x5 => {
+                    self.fetch(16)?;
+                    let cont = self.pop_cont()?;
+                    let code = self.cc.clone();
+                    self.push(VmValue::Slice(code));
+                    self.jump(cont)
+                }
+                0x6 => {
+                    self.fetch(24)?;
+                    let cont = self.pop_cont()?;
+                    let cc =
+                        self.extract_cc(3, Some((b2 >> 4) as usize), optional_count(b2 & 0xf))?;
+                    self.push(VmValue::Cont(cc));
+                    self.jump(cont)
+                }
+                0x8 => {
+                    self.fetch(16)?;
+                    let ret = self.pop_optional_count()?;
+                    let pass = self.pop_optional_count()?;
+                    let cont = self.pop_cont()?;
+                    self.call_args(cont, pass, ret)
+                }
+                0x9 => {
+                    self.fetch(16)?;
+                    match self.pop_optional_count()? {
+                        Some(count) => self.ret_args(count),
+                        None => self.ret(),
+                    }
+                }
+                0xa => {
+                    self.fetch(16)?;
+                    let pass = self.pop_optional_count()?;
+                    let cont = self.pop_cont()?;
+                    self.jump_args(cont, pass)
+                }
+                0xb => {
+                    self.fetch(16)?;
+                    let ret = self.pop_optional_count()?;
+                    let pass = self.pop_optional_count()?;
+                    let cont = self.pop_cont()?;
+                    let cc = self.extract_cc(3, pass, ret)?;
+                    self.push(VmValue::Cont(cc));
+                    self.jump(cont)
+                }
+                0xc | 0xd => {
+                    self.fetch(16)?;
+                    let cont = self.fetch_ref_cont()?;
+                    if b1 & 0xf == 0xc {
+                        self.call(cont)
+                    } else {
+                        self.jump(cont)
+                    }
+                }
+                0xe => {
+                    self.fetch(16)?;
+                    let cont = self.fetch_ref_cont()?;
+                    let code = self.cc.clone();
+                    self.push(VmValue::Slice(code));
+                    self.jump(cont)
+                }
+                0xf => {
+                    self.fetch(16)?;
+                    let code = self.cc.clone();
+                    self.push(VmValue::Slice(code));
+                    self.ret()
+                }
+                _ => Err(VmExitCode::InvalidOpcode.into()),
+            },
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    /// Pops an argument count in `-1..=254`, mapping `-1` to "all".
+    fn pop_optional_count(&mut self) -> VmResult<Option<usize>> {
+        let value = self.pop_range(-1, 254)?;
+        Ok((value >= 0).then_some(value as usize))
+    }
+
+    fn fetch_ref_cont(&mut self) -> VmResult<Arc<Continuation>> {
+        let cell = self.fetch_ref()?;
+        self.ref_to_cont(cell)
+    }
+
+    fn ref_to_cont(&mut self, cell: Arc<Cell>) -> VmResult<Arc<Continuation>> {
+        let code = self.load_cell_slice(cell)?;
+        Ok(Arc::new(Continuation::ordinary(code)))
+    }
+
+    fn exec_control_e3(&mut self, op: u32) -> VmResult<()> {
+        let b1 = (op >> 8) & 0xff;
+        match b1 {
+            0x00..=0x03 => {
+                self.fetch(16)?;
+                let cell = self.fetch_ref()?;
+                let expected = b1 & 1 == 0;
+                if self.pop_bool()? != expected {
+                    return Ok(());
+                }
+                let cont = self.ref_to_cont(cell)?;
+                if b1 & 2 == 0 {
+                    self.call(cont)
+                } else {
+                    self.jump(cont)
+                }
+            }
+            0x04 | 0x05 => {
+                self.fetch(16)?;
+                let otherwise = self.pop()?;
+                let then = self.pop()?;
+                if b1 == 0x05 && then.type_name() != otherwise.type_name() {
+                    return Err(VmExitCode::TypeCheck.into());
+                }
+                let value = if self.pop_bool()? { then } else { otherwise };
+                self.push(value);
+                Ok(())
+            }
+            0x08 | 0x09 => {
+                self.fetch(16)?;
+                if self.pop_bool()? == (b1 == 0x08) {
+                    return self.ret_alt();
+                }
+                Ok(())
+            }
+            0x0d | 0x0e => {
+                self.fetch(16)?;
+                let cell = self.fetch_ref()?;
+                let cont = self.pop_cont()?;
+                let condition = self.pop_bool()?;
+                let take_ref = condition == (b1 == 0x0d);
+                if take_ref {
+                    let cont = self.ref_to_cont(cell)?;
+                    self.call(cont)
+                } else {
+                    self.call(cont)
+                }
+            }
+            0x0f => {
+                self.fetch(16)?;
+                let then = self.fetch_ref()?;
+                let otherwise = self.fetch_ref()?;
+                let cell = if self.pop_bool()? { then } else { otherwise };
+                let cont = self.ref_to_cont(cell)?;
+                self.call(cont)
+            }
+            0x14..=0x1b => {
+                self.fetch(16)?;
+                self.exec_loop_brk(b1 - 0x14)
+            }
+            0x80..=0xbf => {
+                self.fetch(16)?;
+                let bit = (b1 & 0x1f) as usize;
+                let negate = b1 & 0x20 != 0;
+                let cont = self.pop_cont()?;
+                let set = self.peek_int_bit(bit)?;
+                if set != negate {
+                    return self.jump(cont);
+                }
+                Ok(())
+            }
+            0xc0..=0xff => {
+                self.fetch(16)?;
+                let cell = self.fetch_ref()?;
+                let bit = (b1 & 0x1f) as usize;
+                let negate = b1 & 0x20 != 0;
+                let set = self.peek_int_bit(bit)?;
+                if set != negate {
+                    let cont = self.ref_to_cont(cell)?;
+                    return self.jump(cont);
+                }
+                Ok(())
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn peek_int_bit(&self, bit: usize) -> VmResult<bool> {
+        match self.peek(0)? {
+            VmValue::Int(value) => Ok(((value >> bit) & BigInt::from(1)) == BigInt::from(1)),
+            VmValue::NaN => Err(VmExitCode::IntegerOverflow.into()),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    /// Installs `cont` as `c1` with the previous `c1` saved, so that
+    /// `RETALT` inside a loop body leaves the loop.
+    fn c1_envelope(&mut self, cont: Arc<Continuation>) -> Arc<Continuation> {
+        let c1 = VmValue::Cont(self.regs.conts[1].clone());
+        let cont = cont.with_data(|data| data.save.define(1, c1));
+        self.regs.conts[1] = cont.clone();
+        cont
+    }
+
+    fn exec_loop(&mut self, b0: u32) -> VmResult<()> {
+        self.fetch(8)?;
+        self.run_loop(b0 - 0xe4, false)
+    }
+
+    fn exec_loop_brk(&mut self, kind: u32) -> VmResult<()> {
+        self.run_loop(kind, true)
+    }
+
+    /// Starts a loop; `kind` is the offset from `REPEAT` (`E4`) and `brk`
+    /// selects the variants that can be left with `RETALT`.
+    fn run_loop(&mut self, kind: u32, brk: bool) -> VmResult<()> {
+        match kind {
+            0 => {
+                let body = self.pop_cont()?;
+                let count = self.pop_range(-(1 << 31), (1 << 31) - 1)?;
+                if count <= 0 {
+                    return Ok(());
+                }
+                let mut after = self.extract_cc(1, None, None)?;
+                if brk {
+                    after = self.c1_envelope(after);
+                }
+                self.jump(Arc::new(Continuation::Repeat { body, after, count }))
+            }
+            1 => {
+                let count = self.pop_range(-(1 << 31), (1 << 31) - 1)?;
+                if count <= 0 {
+                    return self.ret();
+                }
+                let body = self.extract_cc(0, None, None)?;
+                let mut after = self.regs.conts[0].clone();
+                if brk {
+                    after = self.c1_envelope(after);
+                }
+                self.jump(Arc::new(Continuation::Repeat { body, after, count }))
+            }
+            2 | 3 => {
+                let (body, mut after) = if kind == 2 {
+                    let body = self.pop_cont()?;
+                    (body, self.extract_cc(1, None, None)?)
+                } else {
+                    let body = self.extract_cc(0, None, None)?;
+                    (body, self.regs.conts[0].clone())
+                };
+                if brk {
+                    after = self.c1_envelope(after);
+                }
+                if !body.has_c0() {
+                    self.regs.conts[0] = Arc::new(Continuation::Until {
+                        body: body.clone(),
+                        after,
+                    });
+                }
+                self.jump(body)
+            }
+            4 | 5 => {
+                let (cond, body, mut after) = if kind == 4 {
+                    let body = self.pop_cont()?;
+                    let cond = self.pop_cont()?;
+                    (cond, body, self.extract_cc(1, None, None)?)
+                } else {
+                    let cond = self.pop_cont()?;
+                    let body = self.extract_cc(0, None, None)?;
+                    (cond, body, self.regs.conts[0].clone())
+                };
+                if brk {
+                    after = self.c1_envelope(after);
+                }
+                if !cond.has_c0() {
+                    self.regs.conts[0] = Arc::new(Continuation::While {
+                        cond: cond.clone(),
+                        body,
+                        after,
+                        check_cond: true,
+                    });
+                }
+                self.jump(cond)
+            }
+            6 | 7 => {
+                let body = if kind == 6 {
+                    self.pop_cont()?
+                } else {
+                    self.extract_cc(0, None, None)?
+                };
+                if brk {
+                    let c0 = if kind == 6 {
+                        self.extract_cc(1, None, None)?
+                    } else {
+                        self.regs.conts[0].clone()
+                    };
+                    self.c1_envelope(c0);
+                }
+                self.jump(Arc::new(Continuation::Again { body }))
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    /// SETCONTARGS: moves `count` stack entries into the continuation on top
+    /// of the stack and adjusts its expected argument count.
+    fn set_cont_args(&mut self, count: usize, more: Option<usize>) -> VmResult<()> {
+        self.check_underflow(count + 1)?;
+        let cont = self.pop_cont()?;
+        if count == 0 && more.is_none() {
+            self.push(VmValue::Cont(cont));
+            return Ok(());
+        }
+        if let Some(nargs) = cont.data().and_then(|data| data.nargs)
+            && nargs < count
+        {
+            return Err(VmExitCode::StackOverflow.into());
+        }
+        let depth = self.depth();
+        let moved: Vec<_> = self.stack.drain(depth - count..).collect();
+        self.charge_stack_depth(moved.len())?;
+        let cont = cont.with_data(|data| {
+            if count > 0 {
+                data.stack.get_or_insert_with(Vec::new).extend(moved);
+            }
+            if let Some(nargs) = data.nargs.as_mut() {
+                *nargs -= count;
+            }
+            if let Some(more) = more
+                && data.nargs.is_none_or(|nargs| nargs > more)
+            {
+                data.nargs = Some(more);
+            }
+        });
+        self.push(VmValue::Cont(cont));
+        Ok(())
+    }
+
+    /// RETURNARGS: keeps the top `count` entries and moves the rest into `c0`.
+    fn return_args(&mut self, count: usize) -> VmResult<()> {
+        self.check_underflow(count)?;
+        let depth = self.depth();
+        if depth == count {
+            return Ok(());
+        }
+        let moved_count = depth - count;
+        let c0 = self.regs.conts[0].clone();
+        if let Some(nargs) = c0.data().and_then(|data| data.nargs)
+            && nargs < moved_count
+        {
+            return Err(VmExitCode::StackOverflow.into());
+        }
+        let moved: Vec<_> = self.stack.drain(..moved_count).collect();
+        self.charge_stack_depth(moved.len())?;
+        self.regs.conts[0] = c0.with_data(|data| {
+            data.stack.get_or_insert_with(Vec::new).extend(moved);
+            if let Some(nargs) = data.nargs.as_mut() {
+                *nargs -= moved_count;
+            }
+        });
+        Ok(())
+    }
+
+    fn define_saved(
+        &mut self,
+        cont: Arc<Continuation>,
+        index: usize,
+        value: VmValue,
+    ) -> VmResult<Arc<Continuation>> {
+        if cont.data().is_some_and(|data| data.save.has(index)) {
+            return Err(VmExitCode::TypeCheck.into());
+        }
+        Ok(cont.with_data(|data| data.save.define(index, value)))
+    }
+
+    fn ctr_index(value: u32) -> VmResult<usize> {
+        let index = value as usize;
+        if state_register_valid(index) {
+            Ok(index)
+        } else {
+            Err(VmExitCode::InvalidOpcode.into())
+        }
+    }
+
+    fn exec_control_ed(&mut self, op: u32) -> VmResult<()> {
+        let b1 = (op >> 8) & 0xff;
+        let low = b1 & 0xf;
+        self.fetch(16)?;
+        match b1 >> 4 {
+            0x0 => self.return_args(low as usize),
+            0x1 => match low {
+                0x0 => {
+                    let count = self.pop_range(0, 255)? as usize;
+                    self.return_args(count)
+                }
+                0x1 => {
+                    let more = self.pop_optional_count()?;
+                    let count = self.pop_range(0, 255)? as usize;
+                    self.set_cont_args(count, more)
+                }
+                0x2 => {
+                    let more = self.pop_optional_count()?;
+                    self.set_cont_args(0, more)
+                }
+                0xe => {
+                    let code = self.pop_slice()?;
+                    self.push(VmValue::Cont(Arc::new(Continuation::ordinary(code))));
+                    Ok(())
+                }
+                0xf => {
+                    let more = self.pop_optional_count()?;
+                    let count = self.pop_range(0, 255)? as usize;
+                    let code = self.pop_slice()?;
+                    self.push(VmValue::Cont(Arc::new(Continuation::ordinary(code))));
+                    self.set_cont_args(count, more)
+                }
+                _ => Err(VmExitCode::InvalidOpcode.into()),
+            },
+            0x4 => {
+                let index = Self::ctr_index(low)?;
+                let value = self
+                    .regs
+                    .get(index)
+                    .ok_or(VmError::new(VmExitCode::RangeCheck))?;
+                self.push(value);
+                Ok(())
+            }
+            0x5 => {
+                let index = Self::ctr_index(low)?;
+                let value = self.pop()?;
+                self.regs.set(index, value)
+            }
+            0x6 => {
+                let index = Self::ctr_index(low)?;
+                let cont = self.pop_cont()?;
+                let value = self.pop()?;
+                let cont = self.define_saved(cont, index, value)?;
+                self.push(VmValue::Cont(cont));
+                Ok(())
+            }
+            0x7 | 0x8 => {
+                let index = Self::ctr_index(low)?;
+                let target = if b1 >> 4 == 0x7 { 0 } else { 1 };
+                let value = self.pop()?;
+                let cont = self.regs.conts[target].clone();
+                self.regs.conts[target] = self.define_saved(cont, index, value)?;
+                Ok(())
+            }
+            0x9 => {
+                let index = Self::ctr_index(low)?;
+                let value = self.pop()?;
+                let old = self
+                    .regs
+                    .get(index)
+                    .ok_or(VmError::new(VmExitCode::RangeCheck))?;
+                if index == 0 {
+                    let VmValue::Cont(cont) = value else {
+                        return Err(VmExitCode::TypeCheck.into());
+                    };
+                    self.regs.conts[0] = cont.with_data(|data| data.save.define(0, old));
+                    return Ok(());
+                }
+                let c0 = self.regs.conts[0].clone();
+                self.regs.conts[0] = c0.with_data(|data| data.save.define(index, old));
+                self.regs.set(index, value)
+            }
+            0xa..=0xc => {
+                let index = Self::ctr_index(low)?;
+                let value = self
+                    .regs
+                    .get(index)
+                    .ok_or(VmError::new(VmExitCode::RangeCheck))?;
+                let mode = b1 >> 4;
+                if mode != 0xb {
+                    let c0 = self.regs.conts[0].clone();
+                    self.regs.conts[0] =
+                        c0.with_data(|data| data.save.define(index, value.clone()));
+                }
+                if mode != 0xa {
+                    let c1 = self.regs.conts[1].clone();
+                    self.regs.conts[1] = c1.with_data(|data| data.save.define(index, value));
+                }
+                Ok(())
+            }
+            0xe => match low {
+                0x0 => {
+                    let index = self.pop_range(0, 16)? as usize;
+                    let value = self
+                        .regs
+                        .get(index)
+                        .ok_or(VmError::new(VmExitCode::RangeCheck))?;
+                    self.push(value);
+                    Ok(())
+                }
+                0x1 => {
+                    let index = self.pop_range(0, 16)? as usize;
+                    let value = self.pop()?;
+                    self.regs.set(index, value)
+                }
+                0x2 => {
+                    let index = self.pop_range(0, 16)? as usize;
+                    if !state_register_valid(index) {
+                        return Err(VmExitCode::RangeCheck.into());
+                    }
+                    let cont = self.pop_cont()?;
+                    let value = self.pop()?;
+                    let cont = self.define_saved(cont, index, value)?;
+                    self.push(VmValue::Cont(cont));
+                    Ok(())
+                }
+                _ => Err(VmExitCode::InvalidOpcode.into()),
+            },
+            0xf => match low {
+                0x0..=0x2 => {
+                    let second = self.pop_cont()?;
+                    let first = self.pop_cont()?;
+                    let cont = first.with_data(|data| {
+                        if low != 1 {
+                            data.save.define(0, VmValue::Cont(second.clone()));
+                        }
+                        if low != 0 {
+                            data.save.define(1, VmValue::Cont(second.clone()));
+                        }
+                    });
+                    self.push(VmValue::Cont(cont));
+                    Ok(())
+                }
+                0x3 | 0x4 => {
+                    let index = (low - 3) as usize;
+                    let cont = self.pop_cont()?;
+                    let current = VmValue::Cont(self.regs.conts[index].clone());
+                    self.regs.conts[index] =
+                        cont.with_data(|data| data.save.define(index, current));
+                    Ok(())
+                }
+                0x5 => {
+                    let cont = self.pop_cont()?;
+                    let c0 = VmValue::Cont(self.regs.conts[0].clone());
+                    let c1 = VmValue::Cont(self.regs.conts[1].clone());
+                    self.regs.conts[1] = cont.with_data(|data| {
+                        data.save.define(0, c0);
+                        data.save.define(1, c1);
+                    });
+                    Ok(())
+                }
+                0x6 | 0x7 => {
+                    let cont = self.pop_cont()?;
+                    let next = VmValue::Cont(self.regs.conts[(low - 6) as usize].clone());
+                    let cont = cont.with_data(|data| data.save.define(0, next));
+                    self.push(VmValue::Cont(cont));
+                    Ok(())
+                }
+                0x8 => {
+                    self.regs.conts.swap(0, 1);
+                    Ok(())
+                }
+                0xa => {
+                    self.regs.conts[1] = self.regs.conts[0].clone();
+                    Ok(())
+                }
+                0xb => {
+                    let c1 = VmValue::Cont(self.regs.conts[1].clone());
+                    let c0 = self.regs.conts[0].clone();
+                    let c0 = c0.with_data(|data| data.save.define(1, c1));
+                    self.regs.conts[0] = c0.clone();
+                    self.regs.conts[1] = c0;
+                    Ok(())
+                }
+                _ => Err(VmExitCode::InvalidOpcode.into()),
+            },
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn exec_throw(&mut self, op: u32) -> VmResult<()> {
+        let b1 = (op >> 8) & 0xff;
+        if b1 < 0xc0 {
+            // 6-bit exception numbers: THROW, THROWIF, THROWIFNOT.
+            self.fetch(16)?;
+            let code = (b1 & 0x3f) as i64;
+            return match b1 >> 6 {
+                0 => Err(self.throw(code, VmValue::int(0))),
+                1 | 2 => {
+                    if self.pop_bool()? == (b1 >> 6 == 1) {
+                        Err(self.throw(code, VmValue::int(0)))
+                    } else {
+                        Ok(())
+                    }
+                }
+                _ => Err(VmExitCode::InvalidOpcode.into()),
+            };
+        }
+        if b1 < 0xf0 {
+            let word = self.fetch(24)?;
+            let code = (word & 0x7ff) as i64;
+            let kind = (word >> 11) & 0x1f;
+            // 11-bit exception numbers: the low bit selects THROWARG variants,
+            // the next bits select unconditional, IF and IFNOT forms.
+            let with_arg = kind & 1 == 1;
+            let condition = match (kind >> 1) & 3 {
+                0 => None,
+                1 => Some(true),
+                2 => Some(false),
+                _ => return Err(VmExitCode::InvalidOpcode.into()),
+            };
+            return self.throw_common(code, with_arg, condition);
+        }
+        self.fetch(16)?;
+        match b1 {
+            0xf0..=0xf5 => {
+                let with_arg = b1 & 1 == 1;
+                let condition = match (b1 >> 1) & 3 {
+                    0 => None,
+                    1 => Some(true),
+                    _ => Some(false),
+                };
+                let flag = match condition {
+                    Some(_) => Some(self.pop_bool()?),
+                    None => None,
+                };
+                let code = self.pop_range(0, 0xffff)?;
+                let arg = if with_arg {
+                    self.pop()?
+                } else {
+                    VmValue::int(0)
+                };
+                match (condition, flag) {
+                    (Some(expected), Some(flag)) if expected != flag => Ok(()),
+                    _ => Err(self.throw(code, arg)),
+                }
+            }
+            0xff => self.exec_try(None, None),
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn throw_common(&mut self, code: i64, with_arg: bool, condition: Option<bool>) -> VmResult<()> {
+        if let Some(expected) = condition
+            && self.pop_bool()? != expected
+        {
+            if with_arg {
+                self.pop()?;
+            }
+            return Ok(());
+        }
+        let arg = if with_arg {
+            self.pop()?
+        } else {
+            VmValue::int(0)
+        };
+        Err(self.throw(code, arg))
+    }
+
+    /// TRY / TRYARGS: runs `c` with `c'` installed as the exception handler.
+    fn exec_try(&mut self, pass: Option<usize>, ret: Option<usize>) -> VmResult<()> {
+        self.check_underflow(2 + pass.unwrap_or(0))?;
+        let handler = self.pop_cont()?;
+        let cont = self.pop_cont()?;
+        let old_c2 = VmValue::Cont(self.regs.conts[2].clone());
+        let cc = self.extract_cc(7, pass, ret)?;
+        let handler = handler.with_data(|data| {
+            data.save.set(0, VmValue::Cont(cc));
+            data.save.set(2, old_c2);
+        });
+        self.regs.conts[2] = handler;
+        self.jump(cont)
+    }
+}
+
+fn state_register_valid(index: usize) -> bool {
+    super::state::Registers::is_valid_index(index)
+}
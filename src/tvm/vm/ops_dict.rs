@@ -0,0 +1,507 @@
+use super::dict_ops::{DictSetMode, DictValue, bits_to_int, int_to_bits};
+use super::state::fits;
+use super::*;
+
+/// How a dictionary primitive passes keys: as a slice or as an integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyKind {
+    Slice,
+    Signed,
+    Unsigned,
+}
+
+impl KeyKind {
+    /// Decodes the two-bit key selector shared by most `F4xx` opcodes.
+    fn from_bits(bits: u32) -> Option<Self> {
+        match bits & 3 {
+            1 => Some(Self::Slice),
+            2 => Some(Self::Signed),
+            3 => Some(Self::Unsigned),
+            _ => None,
+        }
+    }
+
+    fn is_signed(self) -> bool {
+        self == Self::Signed
+    }
+}
+
+impl VmState {
+    /// Dictionary primitives (`F4xx`).
+    pub(crate) fn exec_dict(&mut self, op: u32) -> VmResult<()> {
+        let b1 = (op >> 8) & 0xff;
+        match b1 {
+            0x00 => {
+                self.fetch(16)?;
+                self.check_underflow(2)?;
+                let mut builder = self.pop_builder()?;
+                let dict = self.pop_maybe_cell()?;
+                if !builder.can_extend(1, dict.is_some() as usize) {
+                    return Err(VmExitCode::CellOverflow.into());
+                }
+                builder.store_bit(dict.is_some());
+                if let Some(dict) = dict {
+                    builder.store_ref(dict);
+                }
+                self.push(VmValue::Builder(builder));
+                Ok(())
+            }
+            0x01..=0x07 => {
+                self.fetch(16)?;
+                self.load_dict(b1)
+            }
+            0x0a..=0x0f => {
+                self.fetch(16)?;
+                let kind =
+                    KeyKind::from_bits(b1 >> 1).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                self.dict_get_op(kind, b1 & 1 == 1)
+            }
+            0x12..=0x17 | 0x1a..=0x1f | 0x22..=0x27 | 0x2a..=0x2f | 0x32..=0x37 | 0x3a..=0x3f => {
+                self.fetch(16)?;
+                let kind =
+                    KeyKind::from_bits(b1 >> 1).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                let mode = match (b1 >> 4) & 3 {
+                    1 => DictSetMode::Set,
+                    2 => DictSetMode::Replace,
+                    _ => DictSetMode::Add,
+                };
+                let value = if b1 & 1 == 1 {
+                    ValueKind::Ref
+                } else {
+                    ValueKind::Slice
+                };
+                self.dict_set_op(kind, value, mode, b1 & 8 != 0)
+            }
+            0x41..=0x43 | 0x45..=0x47 | 0x49..=0x4b | 0x4d..=0x4f | 0x51..=0x53 | 0x55..=0x57 => {
+                self.fetch(16)?;
+                let kind = KeyKind::from_bits(b1).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                let mode = match (b1 >> 3) & 3 {
+                    0 => DictSetMode::Set,
+                    1 => DictSetMode::Replace,
+                    _ => DictSetMode::Add,
+                };
+                self.dict_set_op(kind, ValueKind::Builder, mode, b1 & 4 != 0)
+            }
+            0x59..=0x5b => {
+                self.fetch(16)?;
+                let kind =
+                    KeyKind::from_bits(b1 - 0x58).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                self.dict_delete_op(kind, None)
+            }
+            0x62..=0x67 => {
+                self.fetch(16)?;
+                let kind =
+                    KeyKind::from_bits(b1 >> 1).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                self.dict_delete_op(kind, Some(b1 & 1 == 1))
+            }
+            0x69..=0x6b => {
+                self.fetch(16)?;
+                let kind =
+                    KeyKind::from_bits(b1 - 0x68).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                self.dict_get_opt_ref(kind)
+            }
+            0x6d..=0x6f => {
+                self.fetch(16)?;
+                let kind =
+                    KeyKind::from_bits(b1 - 0x6c).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                self.dict_set_get_opt_ref(kind)
+            }
+            0x74..=0x7f => {
+                self.fetch(16)?;
+                let kind = match (b1 - 0x74) >> 2 {
+                    0 => KeyKind::Slice,
+                    1 => KeyKind::Signed,
+                    _ => KeyKind::Unsigned,
+                };
+                self.dict_nearest_op(kind, b1 & 2 == 0, b1 & 1 == 1)
+            }
+            0x82..=0x87 | 0x8a..=0x8f | 0x92..=0x97 | 0x9a..=0x9f => {
+                self.fetch(16)?;
+                let kind =
+                    KeyKind::from_bits(b1 >> 1).ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                self.dict_extreme_op(kind, b1 & 1 == 1, b1 & 8 != 0, b1 & 0x10 != 0)
+            }
+            0xa0..=0xa3 => {
+                self.fetch(16)?;
+                let kind = if b1 & 1 == 0 {
+                    KeyKind::Signed
+                } else {
+                    KeyKind::Unsigned
+                };
+                self.dict_get_exec(kind, b1 & 2 == 0, false)
+            }
+            0xa4..=0xa7 => {
+                let word = self.fetch(24)?;
+                let dict = self.fetch_ref()?;
+                self.push(VmValue::Cell(dict));
+                self.push_small((word & 0x3ff) as i64);
+                Ok(())
+            }
+            0xbc..=0xbf => {
+                self.fetch(16)?;
+                let kind = if b1 & 1 == 0 {
+                    KeyKind::Signed
+                } else {
+                    KeyKind::Unsigned
+                };
+                self.dict_get_exec(kind, b1 & 2 == 0, true)
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    /// SKIPDICT, LDDICTS, PLDDICTS, LDDICT, PLDDICT, LDDICTQ, PLDDICTQ.
+    fn load_dict(&mut self, b1: u32) -> VmResult<()> {
+        let mut slice = self.pop_slice()?;
+        let original = slice.clone();
+        let present = match slice.load_small(1) {
+            Some(bit) => bit == 1,
+            None => return self.load_dict_failed(b1, original),
+        };
+        let dict = if present {
+            match slice.load_ref() {
+                Some(cell) => Some(cell),
+                None => return self.load_dict_failed(b1, original),
+            }
+        } else {
+            None
+        };
+        let as_value = |dict: Option<Arc<Cell>>| dict.map_or(VmValue::Null, VmValue::Cell);
+        match b1 {
+            0x01 => self.push(VmValue::Slice(slice)),
+            0x02 | 0x03 => {
+                let mut prefix = original;
+                let parsed = prefix
+                    .split_prefix(1, present as usize)
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                self.push(VmValue::Slice(parsed));
+                if b1 == 0x02 {
+                    self.push(VmValue::Slice(slice));
+                }
+            }
+            0x04 | 0x06 => {
+                self.push(as_value(dict));
+                self.push(VmValue::Slice(slice));
+                if b1 == 0x06 {
+                    self.push_small(-1);
+                }
+            }
+            _ => {
+                self.push(as_value(dict));
+                if b1 == 0x07 {
+                    self.push_small(-1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn load_dict_failed(&mut self, b1: u32, original: VmSlice) -> VmResult<()> {
+        match b1 {
+            0x06 => {
+                self.push(VmValue::Slice(original));
+                self.push_small(0);
+                Ok(())
+            }
+            0x07 => {
+                self.push_small(0);
+                Ok(())
+            }
+            _ => Err(VmExitCode::CellUnderflow.into()),
+        }
+    }
+
+    /// Pops a key of `bits` bits. Integer keys that do not fit yield `None`.
+    fn pop_dict_key(&mut self, kind: KeyKind, bits: usize) -> VmResult<Option<Vec<bool>>> {
+        match kind {
+            KeyKind::Slice => {
+                let slice = self.pop_slice()?;
+                if slice.bits() < bits {
+                    return Err(VmExitCode::CellUnderflow.into());
+                }
+                Ok(Some((0..bits).map(|offset| slice.bit_at(offset)).collect()))
+            }
+            KeyKind::Signed | KeyKind::Unsigned => {
+                let value = self.pop_int()?;
+                if fits(&value, bits, kind == KeyKind::Unsigned) {
+                    Ok(Some(int_to_bits(&value, bits)))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    fn push_dict_key(&mut self, kind: KeyKind, key: &[bool]) -> VmResult<()> {
+        match kind {
+            KeyKind::Slice => {
+                let mut builder = VmBuilder::new();
+                for bit in key {
+                    builder.store_bit(*bit);
+                }
+                let cell = self.build_cell(&builder)?;
+                self.push(VmValue::Slice(VmSlice::new(cell)));
+                Ok(())
+            }
+            KeyKind::Signed | KeyKind::Unsigned => {
+                self.push_int(bits_to_int(key, kind.is_signed()))
+            }
+        }
+    }
+
+    /// Pushes a looked-up value as a slice, or as its single reference.
+    fn push_dict_value(&mut self, value: VmSlice, as_ref: bool) -> VmResult<()> {
+        if as_ref {
+            if value.bits() != 0 || value.refs() != 1 {
+                return Err(VmExitCode::DictionaryError.into());
+            }
+            let cell = value
+                .reference(0)
+                .cloned()
+                .ok_or(VmError::new(VmExitCode::DictionaryError))?;
+            self.push(VmValue::Cell(cell));
+        } else {
+            self.push(VmValue::Slice(value));
+        }
+        Ok(())
+    }
+
+    fn push_dict(&mut self, dict: Option<Arc<Cell>>) {
+        self.push(dict.map_or(VmValue::Null, VmValue::Cell));
+    }
+
+    fn pop_dict_args(&mut self) -> VmResult<(usize, Option<Arc<Cell>>)> {
+        let bits = self.pop_range(0, MAX_CELL_BITS as i64)? as usize;
+        let dict = self.pop_maybe_cell()?;
+        Ok((bits, dict))
+    }
+
+    /// DICTGET family (`k D n – x -1` or `0`).
+    fn dict_get_op(&mut self, kind: KeyKind, as_ref: bool) -> VmResult<()> {
+        self.check_underflow(3)?;
+        let (bits, dict) = self.pop_dict_args()?;
+        let Some(key) = self.pop_dict_key(kind, bits)? else {
+            self.push_small(0);
+            return Ok(());
+        };
+        match self.dict_get(dict, &key)? {
+            Some(value) => {
+                self.push_dict_value(value, as_ref)?;
+                self.push_small(-1);
+            }
+            None => self.push_small(0),
+        }
+        Ok(())
+    }
+
+    /// DICTSET, DICTREPLACE, DICTADD and their GET, REF and builder forms.
+    fn dict_set_op(
+        &mut self,
+        kind: KeyKind,
+        value_kind: ValueKind,
+        mode: DictSetMode,
+        get: bool,
+    ) -> VmResult<()> {
+        self.check_underflow(4)?;
+        let (bits, dict) = self.pop_dict_args()?;
+        let key = self
+            .pop_dict_key(kind, bits)?
+            .ok_or(VmError::new(VmExitCode::RangeCheck))?;
+        let value = match value_kind {
+            ValueKind::Slice => DictValue::Slice(self.pop_slice()?),
+            ValueKind::Ref => DictValue::Ref(self.pop_cell()?),
+            ValueKind::Builder => DictValue::Builder(self.pop_builder()?),
+        };
+        let as_ref = value_kind == ValueKind::Ref;
+        let (dict, changed, old) = self.dict_set(dict, &key, &value, mode)?;
+        self.push_dict(dict);
+        match (mode, get) {
+            (DictSetMode::Set, false) => {}
+            (DictSetMode::Set, true) | (DictSetMode::Replace, true) => match old {
+                Some(old) => {
+                    self.push_dict_value(old, as_ref)?;
+                    self.push_small(-1);
+                }
+                None => self.push_small(0),
+            },
+            (DictSetMode::Replace, false) | (DictSetMode::Add, false) => self.push_bool(changed),
+            (DictSetMode::Add, true) => match old {
+                Some(old) => {
+                    self.push_dict_value(old, as_ref)?;
+                    self.push_small(0);
+                }
+                None => self.push_small(-1),
+            },
+        }
+        Ok(())
+    }
+
+    /// DICTDEL (`k D n – D' -1` or `D 0`) and DICTDELGET[REF].
+    fn dict_delete_op(&mut self, kind: KeyKind, get_as_ref: Option<bool>) -> VmResult<()> {
+        self.check_underflow(3)?;
+        let (bits, dict) = self.pop_dict_args()?;
+        let Some(key) = self.pop_dict_key(kind, bits)? else {
+            self.push_dict(dict);
+            self.push_small(0);
+            return Ok(());
+        };
+        let (dict, old) = self.dict_delete(dict, &key)?;
+        self.push_dict(dict);
+        match old {
+            Some(old) => {
+                if let Some(as_ref) = get_as_ref {
+                    self.push_dict_value(old, as_ref)?;
+                }
+                self.push_small(-1);
+            }
+            None => self.push_small(0),
+        }
+        Ok(())
+    }
+
+    /// DICTGETOPTREF (`k D n – c^?`).
+    fn dict_get_opt_ref(&mut self, kind: KeyKind) -> VmResult<()> {
+        self.check_underflow(3)?;
+        let (bits, dict) = self.pop_dict_args()?;
+        let found = match self.pop_dict_key(kind, bits)? {
+            Some(key) => self.dict_get(dict, &key)?,
+            None => None,
+        };
+        match found {
+            Some(value) => self.push_dict_value(value, true),
+            None => {
+                self.push(VmValue::Null);
+                Ok(())
+            }
+        }
+    }
+
+    /// DICTSETGETOPTREF (`c^? k D n – D' ~c^?`).
+    fn dict_set_get_opt_ref(&mut self, kind: KeyKind) -> VmResult<()> {
+        self.check_underflow(4)?;
+        let (bits, dict) = self.pop_dict_args()?;
+        let key = self
+            .pop_dict_key(kind, bits)?
+            .ok_or(VmError::new(VmExitCode::RangeCheck))?;
+        let new_value = self.pop_maybe_cell()?;
+        let (dict, old) = match new_value {
+            Some(cell) => {
+                let (dict, _, old) =
+                    self.dict_set(dict, &key, &DictValue::Ref(cell), DictSetMode::Set)?;
+                (dict, old)
+            }
+            None => self.dict_delete(dict, &key)?,
+        };
+        self.push_dict(dict);
+        match old {
+            Some(old) => self.push_dict_value(old, true),
+            None => {
+                self.push(VmValue::Null);
+                Ok(())
+            }
+        }
+    }
+
+    /// DICTGETNEXT family (`k D n – x' k' -1` or `0`).
+    fn dict_nearest_op(&mut self, kind: KeyKind, next: bool, allow_eq: bool) -> VmResult<()> {
+        self.check_underflow(3)?;
+        let (bits, dict) = self.pop_dict_args()?;
+        let key = match kind {
+            KeyKind::Slice => self.pop_dict_key(kind, bits)?.unwrap_or_default(),
+            KeyKind::Signed | KeyKind::Unsigned => {
+                let value = self.pop_int()?;
+                if !fits(&value, bits, kind == KeyKind::Unsigned) {
+                    // Out-of-range keys are either before or after every key.
+                    let negative = value.sign() == Sign::Minus;
+                    let found = if negative == next {
+                        self.dict_extreme(dict, bits, !next, kind.is_signed())?
+                    } else {
+                        None
+                    };
+                    return self.push_nearest(kind, found);
+                }
+                int_to_bits(&value, bits)
+            }
+        };
+        let found = self.dict_nearest(dict, &key, next, allow_eq, kind.is_signed())?;
+        self.push_nearest(kind, found)
+    }
+
+    fn push_nearest(&mut self, kind: KeyKind, found: Option<(Vec<bool>, VmSlice)>) -> VmResult<()> {
+        match found {
+            Some((key, value)) => {
+                self.push(VmValue::Slice(value));
+                self.push_dict_key(kind, &key)?;
+                self.push_small(-1);
+            }
+            None => self.push_small(0),
+        }
+        Ok(())
+    }
+
+    /// DICTMIN, DICTMAX, DICTREMMIN and DICTREMMAX families
+    /// (`D n – x k -1` or `0`; removing forms also return `D'`).
+    fn dict_extreme_op(
+        &mut self,
+        kind: KeyKind,
+        as_ref: bool,
+        want_max: bool,
+        remove: bool,
+    ) -> VmResult<()> {
+        self.check_underflow(2)?;
+        let (bits, dict) = self.pop_dict_args()?;
+        let found = self.dict_extreme(dict.clone(), bits, want_max, kind.is_signed())?;
+        let Some((key, value)) = found else {
+            if remove {
+                self.push_dict(dict);
+            }
+            self.push_small(0);
+            return Ok(());
+        };
+        if remove {
+            let (dict, _) = self.dict_delete(dict, &key)?;
+            self.push_dict(dict);
+        }
+        self.push_dict_value(value, as_ref)?;
+        self.push_dict_key(kind, &key)?;
+        self.push_small(-1);
+        Ok(())
+    }
+
+    /// DICTIGETJMP/EXEC (`i D n –`) and their `Z` forms, which push `i`
+    /// back when the key is missing.
+    fn dict_get_exec(&mut self, kind: KeyKind, jump: bool, push_back: bool) -> VmResult<()> {
+        self.check_underflow(3)?;
+        let (bits, dict) = self.pop_dict_args()?;
+        let index = self.pop_int()?;
+        let found = if fits(&index, bits, kind == KeyKind::Unsigned) {
+            self.dict_get(dict, &int_to_bits(&index, bits))?
+        } else {
+            None
+        };
+        match found {
+            Some(code) => {
+                let cont = Arc::new(Continuation::ordinary(code));
+                if jump {
+                    self.jump(cont)
+                } else {
+                    self.call(cont)
+                }
+            }
+            None => {
+                if push_back {
+                    self.push_int(index)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Value representation used by a dictionary update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Slice,
+    Ref,
+    Builder,
+}
@@ -0,0 +1,656 @@
+use super::dict_ops::int_to_bits;
+use super::state::{GAS_TUPLE_ENTRY, fits};
+use super::*;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashSet;
+
+/// Index of the `SmartContractInfo` tuple in `c7`.
+const C7_CONTEXT: usize = 0;
+/// Index of the random seed in the context tuple.
+const CONTEXT_RAND_SEED: usize = 6;
+/// Index of the global configuration root in the context tuple.
+const CONTEXT_CONFIG_ROOT: usize = 9;
+
+const ACTION_SEND_MSG: u32 = 0x0ec3_c86d;
+const ACTION_RESERVE_CURRENCY: u32 = 0x36e6_b809;
+const ACTION_SET_CODE: u32 = 0xad4d_e08e;
+const ACTION_CHANGE_LIBRARY: u32 = 0x26fa_1dd4;
+
+fn uint256(bytes: &[u8]) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, bytes)
+}
+
+fn uint256_bytes(value: &BigInt) -> [u8; 32] {
+    let (_, bytes) = value.to_bytes_be();
+    let mut out = [0u8; 32];
+    out[32 - bytes.len()..].copy_from_slice(&bytes);
+    out
+}
+
+/// Parsed `MsgAddress` fields used by the address primitives.
+enum MsgAddress {
+    None,
+    External(VmSlice),
+    Std {
+        anycast: Option<VmSlice>,
+        workchain: i32,
+        address: VmSlice,
+    },
+    Var {
+        anycast: Option<VmSlice>,
+        workchain: i32,
+        address: VmSlice,
+    },
+}
+
+fn parse_anycast(slice: &mut VmSlice) -> Option<Option<VmSlice>> {
+    if slice.load_small(1)? == 0 {
+        return Some(None);
+    }
+    let depth = slice.load_small(5)? as usize;
+    if depth == 0 || depth > 30 {
+        return None;
+    }
+    Some(Some(slice.split_prefix(depth, 0)?))
+}
+
+fn parse_msg_address(slice: &mut VmSlice) -> Option<MsgAddress> {
+    match slice.load_small(2)? {
+        0 => Some(MsgAddress::None),
+        1 => {
+            let len = slice.load_small(9)? as usize;
+            Some(MsgAddress::External(slice.split_prefix(len, 0)?))
+        }
+        2 => {
+            let anycast = parse_anycast(slice)?;
+            let workchain = slice.load_int(8)?;
+            let address = slice.split_prefix(256, 0)?;
+            Some(MsgAddress::Std {
+                anycast,
+                workchain: i32::try_from(workchain).ok()?,
+                address,
+            })
+        }
+        _ => {
+            let anycast = parse_anycast(slice)?;
+            let len = slice.load_small(9)? as usize;
+            let workchain = slice.load_int(32)?;
+            let address = slice.split_prefix(len, 0)?;
+            Some(MsgAddress::Var {
+                anycast,
+                workchain: i32::try_from(workchain).ok()?,
+                address,
+            })
+        }
+    }
+}
+
+/// Replaces the leading bits of `address` with an anycast rewrite prefix.
+fn rewrite_address(address: &VmSlice, anycast: &Option<VmSlice>) -> Vec<bool> {
+    let mut bits = address.bit_vec();
+    if let Some(prefix) = anycast {
+        for (offset, bit) in prefix.bit_vec().into_iter().enumerate() {
+            if offset < bits.len() {
+                bits[offset] = bit;
+            }
+        }
+    }
+    bits
+}
+
+impl VmState {
+    /// Blockchain-specific primitives: gas, randomness, `c7` parameters,
+    /// globals, hashing, signatures, data sizes, currency and address
+    /// helpers, output actions, debug and codepage instructions.
+    pub(crate) fn exec_app(&mut self, op: u32) -> VmResult<()> {
+        let b0 = op >> 16;
+        let b1 = (op >> 8) & 0xff;
+        match b0 {
+            0xf8 => self.exec_app_f8(b1),
+            0xf9 => self.exec_app_crypto(b1),
+            0xfa => self.exec_app_currency(b1),
+            0xfb => self.exec_app_actions(b1),
+            0xfe => {
+                if b1 >= 0xf0 {
+                    let len = (b1 & 0xf) as usize + 1;
+                    self.fetch(16)?;
+                    self.fetch_data(len * 8, 0)?;
+                } else {
+                    self.fetch(16)?;
+                }
+                Ok(())
+            }
+            _ => {
+                self.fetch(16)?;
+                match b1 {
+                    0x00 => Ok(()),
+                    0xf0 => {
+                        if self.pop_int()? == BigInt::from(0) {
+                            Ok(())
+                        } else {
+                            Err(VmExitCode::InvalidOpcode.into())
+                        }
+                    }
+                    _ => Err(VmExitCode::InvalidOpcode.into()),
+                }
+            }
+        }
+    }
+
+    fn exec_app_f8(&mut self, b1: u32) -> VmResult<()> {
+        self.fetch(16)?;
+        match b1 {
+            0x00 => {
+                let max = self.gas.max;
+                self.gas.change_limit(max);
+                Ok(())
+            }
+            0x01 => {
+                let limit = self.pop_int()?;
+                let limit = i64::try_from(&limit).unwrap_or(if limit.sign() == Sign::Minus {
+                    0
+                } else {
+                    i64::MAX
+                });
+                if limit < self.gas.consumed() {
+                    return Err(VmError::OutOfGas);
+                }
+                self.gas.change_limit(limit);
+                Ok(())
+            }
+            0x0f => {
+                self.committed = Some((self.regs.c4.clone(), self.regs.c5.clone()));
+                Ok(())
+            }
+            0x10 => {
+                let value = self.next_random()?;
+                self.push_int(value)
+            }
+            0x11 => {
+                let range = self.pop_int()?;
+                let value = self.next_random()?;
+                self.push_int((value * range) >> 256)
+            }
+            0x14 => {
+                let seed = self.pop_int()?;
+                if !fits(&seed, 256, true) {
+                    return Err(VmExitCode::RangeCheck.into());
+                }
+                self.set_context_param(CONTEXT_RAND_SEED, VmValue::Int(seed))
+            }
+            0x15 => {
+                let mixin = self.pop_int()?;
+                if !fits(&mixin, 256, true) {
+                    return Err(VmExitCode::RangeCheck.into());
+                }
+                let seed = self.rand_seed()?;
+                let mut hasher = Sha256::new();
+                hasher.update(uint256_bytes(&seed));
+                hasher.update(uint256_bytes(&mixin));
+                let seed = uint256(&hasher.finalize());
+                self.set_context_param(CONTEXT_RAND_SEED, VmValue::Int(seed))
+            }
+            0x20..=0x2f => {
+                let value = self.context_param((b1 & 0xf) as usize)?;
+                self.push(value);
+                Ok(())
+            }
+            0x30 => {
+                let root = self.context_param(CONTEXT_CONFIG_ROOT)?;
+                self.push(root);
+                self.push_small(32);
+                Ok(())
+            }
+            0x32 | 0x33 => {
+                let index = self.pop_int()?;
+                let root = match self.context_param(CONTEXT_CONFIG_ROOT)? {
+                    VmValue::Null => None,
+                    VmValue::Cell(cell) => Some(cell),
+                    _ => return Err(VmExitCode::TypeCheck.into()),
+                };
+                let value = if fits(&index, 32, false) {
+                    self.dict_get(root, &int_to_bits(&index, 32))?
+                } else {
+                    None
+                };
+                let cell = value.and_then(|value| value.reference(0).cloned());
+                match (b1, cell) {
+                    (0x32, Some(cell)) => {
+                        self.push(VmValue::Cell(cell));
+                        self.push_small(-1);
+                    }
+                    (0x32, None) => self.push_small(0),
+                    (_, cell) => self.push(cell.map_or(VmValue::Null, VmValue::Cell)),
+                }
+                Ok(())
+            }
+            0x40..=0x5f => {
+                let index = match b1 & 0x1f {
+                    0 => self.pop_index(254)?,
+                    index => index as usize,
+                };
+                let value = self.regs.c7.get(index).cloned().unwrap_or(VmValue::Null);
+                self.push(value);
+                Ok(())
+            }
+            0x60..=0x7f => {
+                let index = match b1 & 0x1f {
+                    0 => self.pop_index(254)?,
+                    index => index as usize,
+                };
+                let value = self.pop()?;
+                self.set_global(index, value)
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn context(&self) -> VmResult<Arc<Vec<VmValue>>> {
+        match self.regs.c7.get(C7_CONTEXT) {
+            Some(VmValue::Tuple(context)) => Ok(context.clone()),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    fn context_param(&self, index: usize) -> VmResult<VmValue> {
+        self.context()?
+            .get(index)
+            .cloned()
+            .ok_or(VmError::new(VmExitCode::RangeCheck))
+    }
+
+    fn set_context_param(&mut self, index: usize, value: VmValue) -> VmResult<()> {
+        let mut context = self.context()?.as_ref().clone();
+        if index >= context.len() {
+            return Err(VmExitCode::RangeCheck.into());
+        }
+        context[index] = value;
+        self.consume_gas(GAS_TUPLE_ENTRY * context.len() as i64)?;
+        self.set_global(C7_CONTEXT, VmValue::tuple(context))
+    }
+
+    fn set_global(&mut self, index: usize, value: VmValue) -> VmResult<()> {
+        let mut c7 = self.regs.c7.as_ref().clone();
+        if index >= c7.len() {
+            if matches!(value, VmValue::Null) {
+                return Ok(());
+            }
+            c7.resize(index + 1, VmValue::Null);
+        }
+        c7[index] = value;
+        self.consume_gas(GAS_TUPLE_ENTRY * c7.len() as i64)?;
+        self.regs.c7 = Arc::new(c7);
+        Ok(())
+    }
+
+    fn rand_seed(&self) -> VmResult<BigInt> {
+        match self.context_param(CONTEXT_RAND_SEED)? {
+            VmValue::Int(seed) if fits(&seed, 256, true) => Ok(seed),
+            VmValue::Int(_) => Err(VmExitCode::RangeCheck.into()),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    /// Advances the random seed: `sha512(seed)` splits into the new seed and
+    /// the generated value.
+    fn next_random(&mut self) -> VmResult<BigInt> {
+        let seed = self.rand_seed()?;
+        let hash = Sha512::digest(uint256_bytes(&seed));
+        self.set_context_param(CONTEXT_RAND_SEED, VmValue::Int(uint256(&hash[..32])))?;
+        Ok(uint256(&hash[32..]))
+    }
+
+    fn exec_app_crypto(&mut self, b1: u32) -> VmResult<()> {
+        self.fetch(16)?;
+        match b1 {
+            0x00 => {
+                let cell = self.pop_cell()?;
+                self.push_int(uint256(&cell.hash()))
+            }
+            0x01 => {
+                let slice = self.pop_slice()?;
+                let cell = slice
+                    .to_cell()
+                    .map_err(|_| VmError::new(VmExitCode::CellOverflow))?;
+                self.push_int(uint256(&cell.hash()))
+            }
+            0x02 => {
+                let mut slice = self.pop_slice()?;
+                if slice.bits() % 8 != 0 {
+                    return Err(VmExitCode::CellUnderflow.into());
+                }
+                let bits = slice.bits();
+                let bytes = slice
+                    .load_bits(bits)
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                self.push_int(uint256(&Sha256::digest(bytes)))
+            }
+            0x10 | 0x11 => {
+                self.check_underflow(3)?;
+                let key = self.pop_int()?;
+                let mut signature = self.pop_slice()?;
+                let message = if b1 == 0x10 {
+                    let hash = self.pop_int()?;
+                    if !fits(&hash, 256, true) {
+                        return Err(VmExitCode::RangeCheck.into());
+                    }
+                    uint256_bytes(&hash).to_vec()
+                } else {
+                    let mut data = self.pop_slice()?;
+                    if data.bits() % 8 != 0 {
+                        return Err(VmExitCode::CellUnderflow.into());
+                    }
+                    let bits = data.bits();
+                    data.load_bits(bits)
+                        .ok_or(VmError::new(VmExitCode::CellUnderflow))?
+                };
+                if !fits(&key, 256, true) {
+                    return Err(VmExitCode::RangeCheck.into());
+                }
+                let signature = signature
+                    .load_bits(512)
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                let signature: [u8; 64] = signature
+                    .try_into()
+                    .map_err(|_| VmError::new(VmExitCode::CellUnderflow))?;
+                let valid = VerifyingKey::from_bytes(&uint256_bytes(&key))
+                    .map(|key| {
+                        key.verify(&message, &Signature::from_bytes(&signature))
+                            .is_ok()
+                    })
+                    .unwrap_or(false);
+                self.push_bool(valid);
+                Ok(())
+            }
+            0x40..=0x43 => {
+                let limit = self.pop_int()?;
+                let limit = if limit.sign() == Sign::Minus {
+                    return Err(VmExitCode::RangeCheck.into());
+                } else {
+                    u64::try_from(&limit).unwrap_or(u64::MAX)
+                };
+                let quiet = b1 & 1 == 0;
+                let counted = if b1 & 2 == 0 {
+                    let cell = self.pop_maybe_cell()?;
+                    match cell {
+                        Some(cell) => self.data_size(vec![cell], 0, 0, limit)?,
+                        None => Some((0, 0, 0)),
+                    }
+                } else {
+                    let slice = self.pop_slice()?;
+                    let refs = (0..slice.refs())
+                        .filter_map(|index| slice.reference(index).cloned())
+                        .collect();
+                    self.data_size(refs, slice.bits() as u64, slice.refs() as u64, limit)?
+                };
+                match counted {
+                    Some((cells, bits, refs)) => {
+                        self.push_int(BigInt::from(cells))?;
+                        self.push_int(BigInt::from(bits))?;
+                        self.push_int(BigInt::from(refs))?;
+                        if quiet {
+                            self.push_small(-1);
+                        }
+                        Ok(())
+                    }
+                    None if quiet => {
+                        self.push_small(0);
+                        Ok(())
+                    }
+                    None => Err(VmExitCode::CellOverflow.into()),
+                }
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    /// Counts distinct cells, data bits and references reachable from
+    /// `roots`, stopping with `None` after more than `limit` cells.
+    fn data_size(
+        &mut self,
+        roots: Vec<Arc<Cell>>,
+        mut bits: u64,
+        mut refs: u64,
+        limit: u64,
+    ) -> VmResult<Option<(u64, u64, u64)>> {
+        let mut seen = HashSet::new();
+        let mut pending = roots;
+        let mut cells = 0u64;
+        while let Some(cell) = pending.pop() {
+            if !seen.insert(cell.hash()) {
+                continue;
+            }
+            cells += 1;
+            if cells > limit {
+                return Ok(None);
+            }
+            self.charge_cell_load(&cell)?;
+            bits += cell.bit_len() as u64;
+            refs += cell.reference_count() as u64;
+            pending.extend(cell.references().iter().cloned());
+        }
+        Ok(Some((cells, bits, refs)))
+    }
+
+    fn exec_app_currency(&mut self, b1: u32) -> VmResult<()> {
+        self.fetch(16)?;
+        match b1 {
+            0x00..=0x07 => {
+                let len_bits = if b1 & 4 == 0 { 4 } else { 5 };
+                let signed = b1 & 1 == 1;
+                if b1 & 2 == 0 {
+                    let mut slice = self.pop_slice()?;
+                    let len = slice
+                        .load_small(len_bits)
+                        .ok_or(VmError::new(VmExitCode::CellUnderflow))?
+                        as usize;
+                    let value = if signed {
+                        slice.load_int(len * 8)
+                    } else {
+                        slice.load_uint(len * 8).map(BigInt::from)
+                    }
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                    self.push_int(value)?;
+                    self.push(VmValue::Slice(slice));
+                    Ok(())
+                } else {
+                    self.check_underflow(2)?;
+                    let value = self.pop_int()?;
+                    let mut builder = self.pop_builder()?;
+                    let max_len = (1usize << len_bits) - 1;
+                    let len = (0..=max_len)
+                        .find(|len| fits(&value, len * 8, !signed))
+                        .ok_or(VmError::new(VmExitCode::RangeCheck))?;
+                    if !builder.can_extend(len_bits + len * 8, 0) {
+                        return Err(VmExitCode::CellOverflow.into());
+                    }
+                    builder.store_uint(&BigUint::from(len), len_bits);
+                    builder.store_int(&value, len * 8);
+                    self.push(VmValue::Builder(builder));
+                    Ok(())
+                }
+            }
+            0x40..=0x47 => self.exec_msg_address(b1),
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    /// LDMSGADDR, PARSEMSGADDR, REWRITESTDADDR and REWRITEVARADDR, with their
+    /// quiet forms.
+    fn exec_msg_address(&mut self, b1: u32) -> VmResult<()> {
+        let quiet = b1 & 1 == 1;
+        let original = self.pop_slice()?;
+        let mut rest = original.clone();
+        let parsed = parse_msg_address(&mut rest);
+        let fail = |state: &mut Self| -> VmResult<()> {
+            if !quiet {
+                return Err(VmExitCode::CellUnderflow.into());
+            }
+            if b1 & 6 == 0 {
+                state.push(VmValue::Slice(original.clone()));
+            }
+            state.push_small(0);
+            Ok(())
+        };
+        let Some(parsed) = parsed else {
+            return fail(self);
+        };
+        match b1 & 6 {
+            0 => {
+                let consumed = original.bits() - rest.bits();
+                let mut address = original.clone();
+                let address = address
+                    .split_prefix(consumed, 0)
+                    .ok_or(VmError::new(VmExitCode::CellUnderflow))?;
+                self.push(VmValue::Slice(address));
+                self.push(VmValue::Slice(rest));
+            }
+            2 => {
+                let anycast_value =
+                    |anycast: Option<VmSlice>| anycast.map_or(VmValue::Null, VmValue::Slice);
+                let tuple = match parsed {
+                    MsgAddress::None => vec![VmValue::int(0)],
+                    MsgAddress::External(address) => vec![VmValue::int(1), VmValue::Slice(address)],
+                    MsgAddress::Std {
+                        anycast,
+                        workchain,
+                        address,
+                    } => vec![
+                        VmValue::int(2),
+                        anycast_value(anycast),
+                        VmValue::int(workchain),
+                        VmValue::Slice(address),
+                    ],
+                    MsgAddress::Var {
+                        anycast,
+                        workchain,
+                        address,
+                    } => vec![
+                        VmValue::int(3),
+                        anycast_value(anycast),
+                        VmValue::int(workchain),
+                        VmValue::Slice(address),
+                    ],
+                };
+                self.consume_gas(GAS_TUPLE_ENTRY * tuple.len() as i64)?;
+                self.push(VmValue::tuple(tuple));
+            }
+            4 => {
+                let (workchain, bits) = match parsed {
+                    MsgAddress::Std {
+                        anycast,
+                        workchain,
+                        address,
+                    } => (workchain, rewrite_address(&address, &anycast)),
+                    MsgAddress::Var {
+                        anycast,
+                        workchain,
+                        address,
+                    } if address.bits() == 256 => (workchain, rewrite_address(&address, &anycast)),
+                    _ => return fail(self),
+                };
+                self.push_int(BigInt::from(workchain))?;
+                self.push_int(super::dict_ops::bits_to_int(&bits, false))?;
+            }
+            _ => {
+                let (workchain, bits) = match parsed {
+                    MsgAddress::Std {
+                        anycast,
+                        workchain,
+                        address,
+                    }
+                    | MsgAddress::Var {
+                        anycast,
+                        workchain,
+                        address,
+                    } => (workchain, rewrite_address(&address, &anycast)),
+                    _ => return fail(self),
+                };
+                let mut builder = VmBuilder::new();
+                for bit in bits {
+                    builder.store_bit(bit);
+                }
+                let cell = self.build_cell(&builder)?;
+                self.push_int(BigInt::from(workchain))?;
+                self.push(VmValue::Slice(VmSlice::new(cell)));
+            }
+        }
+        if quiet {
+            self.push_small(-1);
+        }
+        Ok(())
+    }
+
+    fn exec_app_actions(&mut self, b1: u32) -> VmResult<()> {
+        self.fetch(16)?;
+        let mut action = VmBuilder::new();
+        match b1 {
+            0x00 => {
+                self.check_underflow(2)?;
+                let mode = self.pop_range(0, 255)?;
+                let message = self.pop_cell()?;
+                action.store_uint(&BigUint::from(ACTION_SEND_MSG), 32);
+                action.store_uint(&BigUint::from(mode as u64), 8);
+                action.store_ref(message);
+            }
+            0x02 | 0x03 => {
+                self.check_underflow(2 + (b1 & 1) as usize)?;
+                let mode = self.pop_range(0, 31)?;
+                let extra = if b1 == 0x03 {
+                    self.pop_maybe_cell()?
+                } else {
+                    None
+                };
+                let amount = self.pop_int()?;
+                if amount.sign() == Sign::Minus || !fits(&amount, 120, true) {
+                    return Err(VmExitCode::RangeCheck.into());
+                }
+                let len = (0..=15usize)
+                    .find(|len| fits(&amount, len * 8, true))
+                    .unwrap_or(15);
+                action.store_uint(&BigUint::from(ACTION_RESERVE_CURRENCY), 32);
+                action.store_uint(&BigUint::from(mode as u64), 8);
+                action.store_uint(&BigUint::from(len), 4);
+                action.store_int(&amount, len * 8);
+                action.store_bit(extra.is_some());
+                if let Some(extra) = extra {
+                    action.store_ref(extra);
+                }
+            }
+            0x04 => {
+                let code = self.pop_cell()?;
+                action.store_uint(&BigUint::from(ACTION_SET_CODE), 32);
+                action.store_ref(code);
+            }
+            0x06 | 0x07 => {
+                self.check_underflow(2)?;
+                let mode = self.pop_range(0, 2)?;
+                action.store_uint(&BigUint::from(ACTION_CHANGE_LIBRARY), 32);
+                action.store_uint(&BigUint::from(mode as u64 * 2 + (b1 == 0x06) as u64), 8);
+                if b1 == 0x06 {
+                    let code = self.pop_cell()?;
+                    action.store_ref(code);
+                } else {
+                    let hash = self.pop_int()?;
+                    if !fits(&hash, 256, true) {
+                        return Err(VmExitCode::RangeCheck.into());
+                    }
+                    action.store_bytes(&uint256_bytes(&hash));
+                }
+            }
+            _ => return Err(VmExitCode::InvalidOpcode.into()),
+        }
+        self.install_action(action)
+    }
+
+    /// Prepends an output action to the `c5` action list.
+    fn install_action(&mut self, action: VmBuilder) -> VmResult<()> {
+        let mut list = VmBuilder::new();
+        list.store_ref(self.regs.c5.clone());
+        list.append_builder(&action)
+            .map_err(|_| VmError::new(VmExitCode::CellOverflow))?;
+        self.regs.c5 = self.build_cell(&list)?;
+        Ok(())
+    }
+}
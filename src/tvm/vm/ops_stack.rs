@@ -0,0 +1,680 @@
+use super::state::GAS_TUPLE_ENTRY;
+use super::*;
+
+impl VmState {
+    /// Stack manipulation primitives (`00`..`6C`).
+    pub(crate) fn exec_stack(&mut self, op: u32) -> VmResult<()> {
+        let b0 = op >> 16;
+        let b1 = (op >> 8) & 0xff;
+        let hi = (b1 >> 4) as usize;
+        let lo = (b1 & 0xf) as usize;
+        match b0 {
+            0x00 => {
+                self.fetch(8)?;
+                Ok(())
+            }
+            0x01..=0x0f => {
+                self.fetch(8)?;
+                self.swap(0, b0 as usize)
+            }
+            0x10 => {
+                self.fetch(16)?;
+                if hi == 0 || hi >= lo {
+                    return Err(VmExitCode::InvalidOpcode.into());
+                }
+                self.swap(hi, lo)
+            }
+            0x11 => {
+                self.fetch(16)?;
+                self.swap(0, b1 as usize)
+            }
+            0x12..=0x1f => {
+                self.fetch(8)?;
+                self.swap(1, (b0 & 0xf) as usize)
+            }
+            0x20..=0x2f => {
+                self.fetch(8)?;
+                self.op_push((b0 & 0xf) as usize)
+            }
+            0x30..=0x3f => {
+                self.fetch(8)?;
+                self.op_pop((b0 & 0xf) as usize)
+            }
+            0x40..=0x4f => {
+                self.fetch(16)?;
+                self.op_xchg3((b0 & 0xf) as usize, hi, lo)
+            }
+            0x50 => {
+                self.fetch(16)?;
+                self.check_underflow(hi.max(lo).max(1) + 1)?;
+                self.swap(1, hi)?;
+                self.swap(0, lo)
+            }
+            0x51 => {
+                self.fetch(16)?;
+                self.check_underflow(hi.max(lo) + 1)?;
+                self.swap(0, hi)?;
+                self.op_push(lo)
+            }
+            0x52 => {
+                self.fetch(16)?;
+                self.op_push(hi)?;
+                self.swap(0, 1)?;
+                self.swap(0, lo)
+            }
+            0x53 => {
+                self.fetch(16)?;
+                self.check_underflow(hi.max(lo) + 1)?;
+                self.op_push(hi)?;
+                self.op_push(lo + 1)
+            }
+            0x54 => self.exec_stack_long(op),
+            0x55 => {
+                self.fetch(16)?;
+                self.op_blkswap(hi + 1, lo + 1)
+            }
+            0x56 => {
+                self.fetch(16)?;
+                self.op_push(b1 as usize)
+            }
+            0x57 => {
+                self.fetch(16)?;
+                self.op_pop(b1 as usize)
+            }
+            0x58 => {
+                self.fetch(8)?;
+                self.op_blkswap(1, 2)
+            }
+            0x59 => {
+                self.fetch(8)?;
+                self.op_blkswap(2, 1)
+            }
+            0x5a => {
+                self.fetch(8)?;
+                self.op_blkswap(2, 2)
+            }
+            0x5b => {
+                self.fetch(8)?;
+                self.op_blkdrop(2)
+            }
+            0x5c => {
+                self.fetch(8)?;
+                self.check_underflow(2)?;
+                self.op_push(1)?;
+                self.op_push(1)
+            }
+            0x5d => {
+                self.fetch(8)?;
+                self.check_underflow(4)?;
+                self.op_push(3)?;
+                self.op_push(3)
+            }
+            0x5e => {
+                self.fetch(16)?;
+                self.op_reverse(hi + 2, lo)
+            }
+            0x5f => {
+                self.fetch(16)?;
+                if hi == 0 {
+                    self.op_blkdrop(lo)
+                } else {
+                    self.check_underflow(lo + 1)?;
+                    for _ in 0..hi {
+                        self.op_push(lo)?;
+                    }
+                    Ok(())
+                }
+            }
+            0x60 => {
+                self.fetch(8)?;
+                let index = self.pop_index(255)?;
+                self.op_push(index)
+            }
+            0x61 => {
+                self.fetch(8)?;
+                let index = self.pop_index(255)?;
+                self.op_blkswap(1, index)
+            }
+            0x62 => {
+                self.fetch(8)?;
+                let index = self.pop_index(255)?;
+                self.op_blkswap(index, 1)
+            }
+            0x63 => {
+                self.fetch(8)?;
+                let j = self.pop_index(255)?;
+                let i = self.pop_index(255)?;
+                self.op_blkswap(i, j)
+            }
+            0x64 => {
+                self.fetch(8)?;
+                let j = self.pop_index(255)?;
+                let i = self.pop_index(255)?;
+                self.op_reverse(i, j)
+            }
+            0x65 => {
+                self.fetch(8)?;
+                let count = self.pop_index(255)?;
+                self.op_blkdrop(count)
+            }
+            0x66 => {
+                self.fetch(8)?;
+                self.check_underflow(2)?;
+                self.swap(0, 1)?;
+                self.op_push(1)
+            }
+            0x67 => {
+                self.fetch(8)?;
+                let index = self.pop_index(255)?;
+                self.swap(0, index)
+            }
+            0x68 => {
+                self.fetch(8)?;
+                let depth = self.depth();
+                self.push_small(depth as i64);
+                Ok(())
+            }
+            0x69 => {
+                self.fetch(8)?;
+                let count = self.pop_index(255)?;
+                self.check_underflow(count)
+            }
+            0x6a => {
+                self.fetch(8)?;
+                let count = self.pop_index(255)?;
+                self.check_underflow(count)?;
+                let depth = self.depth();
+                self.stack.drain(..depth - count);
+                Ok(())
+            }
+            0x6b => {
+                self.fetch(8)?;
+                let count = self.pop_index(255)?;
+                self.check_underflow(count)?;
+                self.stack.truncate(self.depth() - count);
+                Ok(())
+            }
+            0x6c => {
+                self.fetch(16)?;
+                if hi == 0 {
+                    return Err(VmExitCode::InvalidOpcode.into());
+                }
+                self.check_underflow(hi + lo)?;
+                let depth = self.depth();
+                self.stack.drain(depth - hi - lo..depth - lo);
+                Ok(())
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn exec_stack_long(&mut self, op: u32) -> VmResult<()> {
+        let kind = (op >> 12) & 0xf;
+        let i = ((op >> 8) & 0xf) as usize;
+        let j = ((op >> 4) & 0xf) as usize;
+        let k = (op & 0xf) as usize;
+        self.fetch(24)?;
+        match kind {
+            0 => self.op_xchg3(i, j, k),
+            1 => {
+                // XC2PU
+                self.swap(1, i)?;
+                self.swap(0, j)?;
+                self.op_push(k)
+            }
+            2 => {
+                // XCPUXC
+                self.swap(1, i)?;
+                self.op_push(j)?;
+                self.swap(0, 1)?;
+                self.swap(0, k)
+            }
+            3 => {
+                // XCPU2
+                self.swap(0, i)?;
+                self.op_push(j)?;
+                self.op_push(k + 1)
+            }
+            4 => {
+                // PUXC2
+                self.op_push(i)?;
+                self.swap(0, 2)?;
+                self.swap(1, j)?;
+                self.swap(0, k)
+            }
+            5 => {
+                // PUXCPU
+                self.op_push(i)?;
+                self.swap(0, 1)?;
+                self.swap(0, j)?;
+                self.op_push(k)
+            }
+            6 => {
+                // PU2XC
+                self.op_push(i)?;
+                self.swap(0, 1)?;
+                self.op_push(j)?;
+                self.swap(0, 1)?;
+                self.swap(0, k)
+            }
+            7 => {
+                // PUSH3
+                self.op_push(i)?;
+                self.op_push(j + 1)?;
+                self.op_push(k + 2)
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    pub(crate) fn op_push(&mut self, index: usize) -> VmResult<()> {
+        let value = self.peek(index)?.clone();
+        self.push(value);
+        Ok(())
+    }
+
+    pub(crate) fn op_pop(&mut self, index: usize) -> VmResult<()> {
+        self.check_underflow(index + 1)?;
+        self.swap(0, index)?;
+        self.pop()?;
+        Ok(())
+    }
+
+    fn op_xchg3(&mut self, i: usize, j: usize, k: usize) -> VmResult<()> {
+        self.check_underflow(i.max(j).max(k).max(2) + 1)?;
+        self.swap(2, i)?;
+        self.swap(1, j)?;
+        self.swap(0, k)
+    }
+
+    fn op_blkswap(&mut self, i: usize, j: usize) -> VmResult<()> {
+        self.check_underflow(i + j)?;
+        let depth = self.depth();
+        self.stack[depth - i - j..].rotate_left(i);
+        Ok(())
+    }
+
+    fn op_blkdrop(&mut self, count: usize) -> VmResult<()> {
+        self.check_underflow(count)?;
+        self.stack.truncate(self.depth() - count);
+        Ok(())
+    }
+
+    fn op_reverse(&mut self, count: usize, offset: usize) -> VmResult<()> {
+        self.check_underflow(count + offset)?;
+        let depth = self.depth();
+        self.stack[depth - offset - count..depth - offset].reverse();
+        Ok(())
+    }
+
+    /// Null and tuple primitives (`6D`..`6F`).
+    pub(crate) fn exec_tuple(&mut self, op: u32) -> VmResult<()> {
+        let b0 = op >> 16;
+        if b0 == 0x6d {
+            self.fetch(8)?;
+            self.push(VmValue::Null);
+            return Ok(());
+        }
+        if b0 == 0x6e {
+            self.fetch(8)?;
+            let value = self.pop()?;
+            self.push_bool(matches!(value, VmValue::Null));
+            return Ok(());
+        }
+        let b1 = (op >> 8) & 0xff;
+        let n = (b1 & 0xf) as usize;
+        match b1 >> 4 {
+            0x0 => {
+                self.fetch(16)?;
+                self.op_tuple(n)
+            }
+            0x1 => {
+                self.fetch(16)?;
+                self.op_index(n, false)
+            }
+            0x2 => {
+                self.fetch(16)?;
+                self.op_untuple(n, n, true)
+            }
+            0x3 => {
+                self.fetch(16)?;
+                self.op_untuple(n, n, false)
+            }
+            0x4 => {
+                self.fetch(16)?;
+                self.op_explode(n)
+            }
+            0x5 => {
+                self.fetch(16)?;
+                self.op_setindex(n, false)
+            }
+            0x6 => {
+                self.fetch(16)?;
+                self.op_index(n, true)
+            }
+            0x7 => {
+                self.fetch(16)?;
+                self.op_setindex(n, true)
+            }
+            0x8 => {
+                self.fetch(16)?;
+                match n {
+                    0x0 => {
+                        let n = self.pop_index(255)?;
+                        self.op_tuple(n)
+                    }
+                    0x1 => {
+                        let n = self.pop_index(254)?;
+                        self.op_index(n, false)
+                    }
+                    0x2 => {
+                        let n = self.pop_index(255)?;
+                        self.op_untuple(n, n, true)
+                    }
+                    0x3 => {
+                        let n = self.pop_index(255)?;
+                        self.op_untuple(n, n, false)
+                    }
+                    0x4 => {
+                        let n = self.pop_index(255)?;
+                        self.op_explode(n)
+                    }
+                    0x5 => {
+                        let n = self.pop_index(254)?;
+                        self.op_setindex(n, false)
+                    }
+                    0x6 => {
+                        let n = self.pop_index(254)?;
+                        self.op_index(n, true)
+                    }
+                    0x7 => {
+                        let n = self.pop_index(254)?;
+                        self.op_setindex(n, true)
+                    }
+                    0x8 => {
+                        let tuple = self.pop_tuple()?;
+                        self.push_small(tuple.len() as i64);
+                        Ok(())
+                    }
+                    0x9 => {
+                        let value = self.pop()?;
+                        match value {
+                            VmValue::Tuple(tuple) => self.push_small(tuple.len() as i64),
+                            _ => self.push_small(-1),
+                        }
+                        Ok(())
+                    }
+                    0xa => {
+                        let value = self.pop()?;
+                        self.push_bool(matches!(value, VmValue::Tuple(_)));
+                        Ok(())
+                    }
+                    0xb => {
+                        let tuple = self.pop_tuple()?;
+                        let last = tuple
+                            .last()
+                            .cloned()
+                            .ok_or(VmError::new(VmExitCode::TypeCheck))?;
+                        self.push(last);
+                        Ok(())
+                    }
+                    0xc => {
+                        let value = self.pop()?;
+                        let tuple = self.pop_tuple()?;
+                        if tuple.len() >= 255 {
+                            return Err(VmExitCode::TypeCheck.into());
+                        }
+                        let mut tuple = tuple.as_ref().clone();
+                        tuple.push(value);
+                        self.consume_gas(tuple.len() as i64 * GAS_TUPLE_ENTRY)?;
+                        self.push(VmValue::tuple(tuple));
+                        Ok(())
+                    }
+                    0xd => {
+                        let tuple = self.pop_tuple()?;
+                        let mut tuple = tuple.as_ref().clone();
+                        let last = tuple.pop().ok_or(VmError::new(VmExitCode::TypeCheck))?;
+                        self.consume_gas(tuple.len() as i64 * GAS_TUPLE_ENTRY)?;
+                        self.push(VmValue::tuple(tuple));
+                        self.push(last);
+                        Ok(())
+                    }
+                    _ => Err(VmExitCode::InvalidOpcode.into()),
+                }
+            }
+            0xa => {
+                self.fetch(16)?;
+                // NULLSWAPIF, NULLSWAPIFNOT, NULLROTRIF, NULLROTRIFNOT and
+                // their double-null variants.
+                if n > 7 {
+                    return Err(VmExitCode::InvalidOpcode.into());
+                }
+                let when_true = n & 1 == 0;
+                let depth_shift = if n & 2 != 0 { 1 } else { 0 };
+                let nulls = if n & 4 != 0 { 2 } else { 1 };
+                self.check_underflow(1 + depth_shift)?;
+                let condition = match self.peek(0)? {
+                    VmValue::Int(value) => value.sign() != Sign::NoSign,
+                    VmValue::NaN => return Err(VmExitCode::IntegerOverflow.into()),
+                    _ => return Err(VmExitCode::TypeCheck.into()),
+                };
+                if condition == when_true {
+                    let depth = self.depth();
+                    let position = depth - 1 - depth_shift;
+                    for _ in 0..nulls {
+                        self.stack.insert(position, VmValue::Null);
+                    }
+                }
+                Ok(())
+            }
+            0xb => {
+                self.fetch(16)?;
+                let i = n >> 2;
+                let j = n & 3;
+                self.op_index(i, false)?;
+                self.op_index(j, false)
+            }
+            0xc..=0xf => {
+                self.fetch(16)?;
+                let bits = b1 & 0x3f;
+                self.op_index((bits >> 4) as usize, false)?;
+                self.op_index(((bits >> 2) & 3) as usize, false)?;
+                self.op_index((bits & 3) as usize, false)
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+
+    fn op_tuple(&mut self, n: usize) -> VmResult<()> {
+        self.check_underflow(n)?;
+        let depth = self.depth();
+        let values: Vec<_> = self.stack.drain(depth - n..).collect();
+        self.consume_gas(n as i64 * GAS_TUPLE_ENTRY)?;
+        self.push(VmValue::tuple(values));
+        Ok(())
+    }
+
+    fn op_index(&mut self, index: usize, quiet: bool) -> VmResult<()> {
+        let tuple = if quiet {
+            self.pop_maybe_tuple()?
+        } else {
+            Some(self.pop_tuple()?)
+        };
+        let value = match tuple {
+            Some(tuple) => match tuple.get(index) {
+                Some(value) => value.clone(),
+                None if quiet => VmValue::Null,
+                None => return Err(VmExitCode::RangeCheck.into()),
+            },
+            None => VmValue::Null,
+        };
+        self.push(value);
+        Ok(())
+    }
+
+    /// UNTUPLE (`exact`) or UNPACKFIRST: pushes the first `count` entries.
+    fn op_untuple(&mut self, count: usize, expected: usize, exact: bool) -> VmResult<()> {
+        let tuple = self.pop_tuple()?;
+        if (exact && tuple.len() != expected) || tuple.len() < count {
+            return Err(VmExitCode::TypeCheck.into());
+        }
+        self.consume_gas(count as i64 * GAS_TUPLE_ENTRY)?;
+        for value in tuple.iter().take(count) {
+            self.push(value.clone());
+        }
+        Ok(())
+    }
+
+    fn op_explode(&mut self, max: usize) -> VmResult<()> {
+        let tuple = self.pop_tuple()?;
+        if tuple.len() > max {
+            return Err(VmExitCode::TypeCheck.into());
+        }
+        self.consume_gas(tuple.len() as i64 * GAS_TUPLE_ENTRY)?;
+        for value in tuple.iter() {
+            self.push(value.clone());
+        }
+        self.push_small(tuple.len() as i64);
+        Ok(())
+    }
+
+    fn op_setindex(&mut self, index: usize, quiet: bool) -> VmResult<()> {
+        let value = self.pop()?;
+        let tuple = if quiet {
+            self.pop_maybe_tuple()?
+        } else {
+            Some(self.pop_tuple()?)
+        };
+        let mut tuple = tuple
+            .map(|tuple| tuple.as_ref().clone())
+            .unwrap_or_default();
+        if index >= tuple.len() {
+            if !quiet {
+                return Err(VmExitCode::RangeCheck.into());
+            }
+            if matches!(value, VmValue::Null) {
+                self.push(if tuple.is_empty() {
+                    VmValue::Null
+                } else {
+                    VmValue::tuple(tuple)
+                });
+                return Ok(());
+            }
+            tuple.resize(index + 1, VmValue::Null);
+        }
+        tuple[index] = value;
+        self.consume_gas(tuple.len() as i64 * GAS_TUPLE_ENTRY)?;
+        self.push(VmValue::tuple(tuple));
+        Ok(())
+    }
+
+    /// Integer, slice, cell and continuation constants (`7`..`9F`).
+    pub(crate) fn exec_const(&mut self, op: u32) -> VmResult<()> {
+        let b0 = op >> 16;
+        let b1 = (op >> 8) & 0xff;
+        match b0 {
+            0x70..=0x7f => {
+                self.fetch(8)?;
+                let value = (b0 & 0xf) as i64;
+                self.push_small(if value > 10 { value - 16 } else { value });
+                Ok(())
+            }
+            0x80 => {
+                self.fetch(16)?;
+                self.push_small(b1 as u8 as i8 as i64);
+                Ok(())
+            }
+            0x81 => {
+                self.fetch(24)?;
+                self.push_small((op & 0xffff) as u16 as i16 as i64);
+                Ok(())
+            }
+            0x82 => {
+                let len = (self.fetch(13)? & 0x1f) as usize;
+                let bits = 8 * len + 19;
+                let mut data = self.fetch_data(bits, 0)?;
+                let value = data
+                    .load_int(bits)
+                    .ok_or(VmError::new(VmExitCode::InvalidOpcode))?;
+                self.push_int(value)
+            }
+            0x83 => {
+                self.fetch(16)?;
+                if b1 == 0xff {
+                    self.push(VmValue::NaN);
+                    Ok(())
+                } else {
+                    self.push_int(BigInt::from(1) << (b1 as usize + 1))
+                }
+            }
+            0x84 => {
+                self.fetch(16)?;
+                self.push_int((BigInt::from(1) << (b1 as usize + 1)) - 1)
+            }
+            0x85 => {
+                self.fetch(16)?;
+                self.push_int(-(BigInt::from(1) << (b1 as usize + 1)))
+            }
+            0x88 => {
+                self.fetch(8)?;
+                let cell = self.fetch_ref()?;
+                self.push(VmValue::Cell(cell));
+                Ok(())
+            }
+            0x89 => {
+                self.fetch(8)?;
+                let cell = self.fetch_ref()?;
+                let slice = self.load_cell_slice(cell)?;
+                self.push(VmValue::Slice(slice));
+                Ok(())
+            }
+            0x8a => {
+                self.fetch(8)?;
+                let cell = self.fetch_ref()?;
+                let code = self.load_cell_slice(cell)?;
+                self.push(VmValue::Cont(Arc::new(Continuation::ordinary(code))));
+                Ok(())
+            }
+            0x8b => {
+                let len = (self.fetch(12)? & 0xf) as usize;
+                let data = self.fetch_tagged_data(8 * len + 4, 0)?;
+                self.push(VmValue::Slice(data));
+                Ok(())
+            }
+            0x8c => {
+                let args = self.fetch(15)?;
+                let refs = ((args >> 5) & 3) as usize + 1;
+                let bits = 8 * (args & 0x1f) as usize + 1;
+                let data = self.fetch_tagged_data(bits, refs)?;
+                self.push(VmValue::Slice(data));
+                Ok(())
+            }
+            0x8d => {
+                let args = self.fetch(18)?;
+                let refs = ((args >> 7) & 7) as usize;
+                if refs > 4 {
+                    return Err(VmExitCode::InvalidOpcode.into());
+                }
+                let bits = 8 * (args & 0x7f) as usize + 6;
+                let data = self.fetch_tagged_data(bits, refs)?;
+                self.push(VmValue::Slice(data));
+                Ok(())
+            }
+            0x8e | 0x8f => {
+                let args = self.fetch(16)?;
+                let refs = ((args >> 7) & 3) as usize;
+                let bytes = (args & 0x7f) as usize;
+                let code = self.fetch_data(8 * bytes, refs)?;
+                self.push(VmValue::Cont(Arc::new(Continuation::ordinary(code))));
+                Ok(())
+            }
+            0x90..=0x9f => {
+                self.fetch(8)?;
+                let code = self.fetch_data(8 * (b0 & 0xf) as usize, 0)?;
+                self.push(VmValue::Cont(Arc::new(Continuation::ordinary(code))));
+                Ok(())
+            }
+            _ => Err(VmExitCode::InvalidOpcode.into()),
+        }
+    }
+}
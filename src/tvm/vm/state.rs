@@ -0,0 +1,513 @@
+use super::*;
+use std::collections::HashSet;
+
+/// Gas limit used for get-methods when the caller does not provide one.
+pub const DEFAULT_GET_METHOD_GAS_LIMIT: u64 = 1_000_000;
+
+/// Exit code reported when execution runs out of gas.
+pub const EXIT_CODE_OUT_OF_GAS: i32 = -14;
+
+pub(crate) const GAS_BASIC_INSTRUCTION: i64 = 10;
+pub(crate) const GAS_PER_REF: i64 = 5;
+pub(crate) const GAS_CELL_LOAD: i64 = 100;
+pub(crate) const GAS_CELL_RELOAD: i64 = 25;
+pub(crate) const GAS_CELL_CREATE: i64 = 500;
+pub(crate) const GAS_EXCEPTION: i64 = 50;
+pub(crate) const GAS_IMPLICIT_JMPREF: i64 = 10;
+pub(crate) const GAS_IMPLICIT_RET: i64 = 5;
+pub(crate) const GAS_TUPLE_ENTRY: i64 = 1;
+pub(crate) const GAS_STACK_ENTRY: i64 = 1;
+pub(crate) const FREE_STACK_DEPTH: usize = 32;
+
+/// Standard TVM exit codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VmExitCode {
+    /// Normal termination.
+    Success,
+    /// Alternative normal termination through `c1`.
+    AlternativeSuccess,
+    /// Not enough stack entries for an instruction.
+    StackUnderflow,
+    /// Too many stack entries.
+    StackOverflow,
+    /// Integer does not fit into 257 bits, or division by zero.
+    IntegerOverflow,
+    /// Integer argument is outside the allowed range.
+    RangeCheck,
+    /// Unknown or unsupported instruction.
+    InvalidOpcode,
+    /// Stack entry has an unexpected type.
+    TypeCheck,
+    /// Builder cannot hold more bits or references.
+    CellOverflow,
+    /// Slice does not contain enough bits or references.
+    CellUnderflow,
+    /// Dictionary has an invalid layout.
+    DictionaryError,
+    /// Application-level error raised by some primitives.
+    UnknownError,
+    /// Internal interpreter invariant failed.
+    FatalError,
+    /// Execution exhausted its gas limit.
+    OutOfGas,
+}
+
+impl VmExitCode {
+    /// Returns the numeric exception code thrown inside TVM.
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Success => 0,
+            Self::AlternativeSuccess => 1,
+            Self::StackUnderflow => 2,
+            Self::StackOverflow => 3,
+            Self::IntegerOverflow => 4,
+            Self::RangeCheck => 5,
+            Self::InvalidOpcode => 6,
+            Self::TypeCheck => 7,
+            Self::CellOverflow => 8,
+            Self::CellUnderflow => 9,
+            Self::DictionaryError => 10,
+            Self::UnknownError => 11,
+            Self::FatalError => 12,
+            Self::OutOfGas => 13,
+        }
+    }
+
+    /// Maps a reported exit code to a standard code.
+    ///
+    /// Out-of-gas is reported as `-14`; `13` is accepted as well.
+    pub fn from_code(code: i32) -> Option<Self> {
+        Some(match code {
+            0 => Self::Success,
+            1 => Self::AlternativeSuccess,
+            2 => Self::StackUnderflow,
+            3 => Self::StackOverflow,
+            4 => Self::IntegerOverflow,
+            5 => Self::RangeCheck,
+            6 => Self::InvalidOpcode,
+            7 => Self::TypeCheck,
+            8 => Self::CellOverflow,
+            9 => Self::CellUnderflow,
+            10 => Self::DictionaryError,
+            11 => Self::UnknownError,
+            12 => Self::FatalError,
+            13 | EXIT_CODE_OUT_OF_GAS => Self::OutOfGas,
+            _ => return None,
+        })
+    }
+
+    /// Returns `true` for exit codes `0` and `1`.
+    pub fn is_success_code(code: i32) -> bool {
+        code == 0 || code == 1
+    }
+}
+
+/// Gas limits for one VM run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VmGasLimits {
+    /// Gas available before `ACCEPT`.
+    pub limit: u64,
+    /// Upper bound for `ACCEPT` and `SETGASLIMIT`.
+    pub max: u64,
+    /// Gas credit available before `ACCEPT` (external messages).
+    pub credit: u64,
+}
+
+impl VmGasLimits {
+    /// Limits for a get-method: `limit` is both the current and maximum gas.
+    pub fn get_method(limit: u64) -> Self {
+        Self {
+            limit,
+            max: limit,
+            credit: 0,
+        }
+    }
+}
+
+impl Default for VmGasLimits {
+    fn default() -> Self {
+        Self::get_method(DEFAULT_GET_METHOD_GAS_LIMIT)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Gas {
+    pub(crate) max: i64,
+    pub(crate) limit: i64,
+    pub(crate) credit: i64,
+    pub(crate) base: i64,
+    pub(crate) remaining: i64,
+}
+
+impl Gas {
+    pub(crate) fn new(limits: VmGasLimits) -> Self {
+        let clamp = |value: u64| value.min(i64::MAX as u64 / 4) as i64;
+        let limit = clamp(limits.limit);
+        let credit = clamp(limits.credit);
+        Self {
+            max: clamp(limits.max).max(limit),
+            limit,
+            credit,
+            base: limit + credit,
+            remaining: limit + credit,
+        }
+    }
+
+    pub(crate) fn consumed(&self) -> i64 {
+        self.base - self.remaining
+    }
+
+    pub(crate) fn change_limit(&mut self, limit: i64) {
+        let limit = limit.clamp(0, self.max);
+        self.credit = 0;
+        self.limit = limit;
+        let base = limit;
+        self.remaining += base - self.base;
+        self.base = base;
+    }
+}
+
+/// Error raised while executing one instruction.
+#[derive(Debug, Clone)]
+pub(crate) enum VmError {
+    /// A catchable TVM exception with its argument.
+    Exception { code: i32, arg: VmValue },
+    /// Gas exhausted; terminates execution immediately.
+    OutOfGas,
+}
+
+impl VmError {
+    pub(crate) fn new(code: VmExitCode) -> Self {
+        Self::Exception {
+            code: code.code(),
+            arg: VmValue::int(0),
+        }
+    }
+}
+
+impl From<VmExitCode> for VmError {
+    fn from(code: VmExitCode) -> Self {
+        Self::new(code)
+    }
+}
+
+pub(crate) type VmResult<T> = std::result::Result<T, VmError>;
+
+/// Control registers of a running VM.
+#[derive(Debug, Clone)]
+pub(crate) struct Registers {
+    pub(crate) conts: [Arc<Continuation>; 4],
+    pub(crate) c4: Arc<Cell>,
+    pub(crate) c5: Arc<Cell>,
+    pub(crate) c7: Arc<Vec<VmValue>>,
+}
+
+impl Registers {
+    pub(crate) fn get(&self, index: usize) -> Option<VmValue> {
+        match index {
+            0..=3 => Some(VmValue::Cont(self.conts[index].clone())),
+            4 => Some(VmValue::Cell(self.c4.clone())),
+            5 => Some(VmValue::Cell(self.c5.clone())),
+            7 => Some(VmValue::Tuple(self.c7.clone())),
+            _ => None,
+        }
+    }
+
+    /// Stores a value into a control register, checking its type.
+    pub(crate) fn set(&mut self, index: usize, value: VmValue) -> VmResult<()> {
+        match (index, value) {
+            (0..=3, VmValue::Cont(cont)) => self.conts[index] = cont,
+            (4, VmValue::Cell(cell)) => self.c4 = cell,
+            (5, VmValue::Cell(cell)) => self.c5 = cell,
+            (7, VmValue::Tuple(tuple)) => self.c7 = tuple,
+            (0..=5 | 7, _) => return Err(VmExitCode::TypeCheck.into()),
+            _ => return Err(VmExitCode::RangeCheck.into()),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_valid_index(index: usize) -> bool {
+        matches!(index, 0..=5 | 7)
+    }
+}
+
+/// Initial machine state.
+#[derive(Debug, Clone)]
+pub(crate) struct VmInit {
+    pub(crate) code: Arc<Cell>,
+    pub(crate) data: Arc<Cell>,
+    pub(crate) c7: Vec<VmValue>,
+    pub(crate) stack: Vec<VmValue>,
+    pub(crate) gas: VmGasLimits,
+}
+
+/// Complete state of a running TVM instance.
+#[derive(Debug)]
+pub(crate) struct VmState {
+    pub(crate) stack: Vec<VmValue>,
+    pub(crate) cc: VmSlice,
+    pub(crate) regs: Registers,
+    pub(crate) gas: Gas,
+    pub(crate) committed: Option<(Arc<Cell>, Arc<Cell>)>,
+    pub(crate) halted: Option<i32>,
+    pub(crate) steps: u64,
+    loaded_cells: HashSet<usize>,
+    loaded_cell_refs: Vec<Arc<Cell>>,
+}
+
+impl VmState {
+    pub(crate) fn new(init: VmInit) -> Self {
+        let quit0 = Arc::new(Continuation::Quit(0));
+        let quit1 = Arc::new(Continuation::Quit(1));
+        let exc_quit = Arc::new(Continuation::ExcQuit);
+        let code = VmSlice::new(init.code.clone());
+        let c3 = Arc::new(Continuation::ordinary(code.clone()));
+        let mut state = Self {
+            stack: init.stack,
+            cc: code,
+            regs: Registers {
+                conts: [quit0, quit1, exc_quit, c3],
+                c4: init.data,
+                c5: Arc::new(Cell::new()),
+                c7: Arc::new(init.c7),
+            },
+            gas: Gas::new(init.gas),
+            committed: None,
+            halted: None,
+            steps: 0,
+            loaded_cells: HashSet::new(),
+            loaded_cell_refs: Vec::new(),
+        };
+        // The root code cell is loaded as part of starting the VM.
+        state.loaded_cells.insert(Arc::as_ptr(&init.code) as usize);
+        state.loaded_cell_refs.push(init.code);
+        state
+    }
+
+    pub(crate) fn consume_gas(&mut self, amount: i64) -> VmResult<()> {
+        self.gas.remaining -= amount;
+        if self.gas.remaining < 0 {
+            return Err(VmError::OutOfGas);
+        }
+        Ok(())
+    }
+
+    /// Charges cell-load gas: full price for the first load of a cell and the
+    /// reload price afterwards. Cells are identified by allocation.
+    pub(crate) fn charge_cell_load(&mut self, cell: &Arc<Cell>) -> VmResult<()> {
+        let key = Arc::as_ptr(cell) as usize;
+        if self.loaded_cells.insert(key) {
+            self.loaded_cell_refs.push(cell.clone());
+            self.consume_gas(GAS_CELL_LOAD)
+        } else {
+            self.consume_gas(GAS_CELL_RELOAD)
+        }
+    }
+
+    /// Converts a cell into a slice for reading, charging load gas.
+    ///
+    /// Exotic cells cannot be read directly; library and pruned cells raise
+    /// a cell underflow exception.
+    pub(crate) fn load_cell_slice(&mut self, cell: Arc<Cell>) -> VmResult<VmSlice> {
+        self.charge_cell_load(&cell)?;
+        if cell.is_exotic() {
+            return Err(VmExitCode::CellUnderflow.into());
+        }
+        Ok(VmSlice::new(cell))
+    }
+
+    pub(crate) fn build_cell(&mut self, builder: &VmBuilder) -> VmResult<Arc<Cell>> {
+        self.consume_gas(GAS_CELL_CREATE)?;
+        builder
+            .build()
+            .map_err(|_| VmError::new(VmExitCode::CellOverflow))
+    }
+
+    // ---- stack access ----
+
+    pub(crate) fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub(crate) fn check_underflow(&self, n: usize) -> VmResult<()> {
+        if self.stack.len() < n {
+            return Err(VmExitCode::StackUnderflow.into());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn push(&mut self, value: VmValue) {
+        self.stack.push(value);
+    }
+
+    pub(crate) fn pop(&mut self) -> VmResult<VmValue> {
+        self.stack
+            .pop()
+            .ok_or_else(|| VmExitCode::StackUnderflow.into())
+    }
+
+    /// Returns a reference to `s(i)`.
+    pub(crate) fn peek(&self, i: usize) -> VmResult<&VmValue> {
+        let len = self.stack.len();
+        if i >= len {
+            return Err(VmExitCode::StackUnderflow.into());
+        }
+        Ok(&self.stack[len - 1 - i])
+    }
+
+    pub(crate) fn swap(&mut self, i: usize, j: usize) -> VmResult<()> {
+        let len = self.stack.len();
+        if i >= len || j >= len {
+            return Err(VmExitCode::StackUnderflow.into());
+        }
+        self.stack.swap(len - 1 - i, len - 1 - j);
+        Ok(())
+    }
+
+    pub(crate) fn push_int(&mut self, value: BigInt) -> VmResult<()> {
+        if !fits_int257(&value) {
+            return Err(VmExitCode::IntegerOverflow.into());
+        }
+        self.push(VmValue::Int(value));
+        Ok(())
+    }
+
+    /// Pushes an integer, replacing out-of-range values with NaN.
+    pub(crate) fn push_int_quiet(&mut self, value: BigInt) {
+        if fits_int257(&value) {
+            self.push(VmValue::Int(value));
+        } else {
+            self.push(VmValue::NaN);
+        }
+    }
+
+    pub(crate) fn push_small(&mut self, value: i64) {
+        self.push(VmValue::int(value));
+    }
+
+    pub(crate) fn push_bool(&mut self, value: bool) {
+        self.push(VmValue::bool(value));
+    }
+
+    /// Pops a finite integer; NaN raises an integer overflow.
+    pub(crate) fn pop_int(&mut self) -> VmResult<BigInt> {
+        match self.pop()? {
+            VmValue::Int(value) => Ok(value),
+            VmValue::NaN => Err(VmExitCode::IntegerOverflow.into()),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    /// Pops an integer that may be NaN.
+    pub(crate) fn pop_int_or_nan(&mut self) -> VmResult<Option<BigInt>> {
+        match self.pop()? {
+            VmValue::Int(value) => Ok(Some(value)),
+            VmValue::NaN => Ok(None),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    pub(crate) fn pop_bool(&mut self) -> VmResult<bool> {
+        Ok(self.pop_int()?.sign() != Sign::NoSign)
+    }
+
+    /// Pops an integer in `min..=max`, raising a range check otherwise.
+    pub(crate) fn pop_range(&mut self, min: i64, max: i64) -> VmResult<i64> {
+        let value = self.pop_int()?;
+        match i64::try_from(&value) {
+            Ok(value) if (min..=max).contains(&value) => Ok(value),
+            _ => Err(VmExitCode::RangeCheck.into()),
+        }
+    }
+
+    pub(crate) fn pop_index(&mut self, max: usize) -> VmResult<usize> {
+        Ok(self.pop_range(0, max as i64)? as usize)
+    }
+
+    pub(crate) fn pop_cell(&mut self) -> VmResult<Arc<Cell>> {
+        match self.pop()? {
+            VmValue::Cell(cell) => Ok(cell),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    pub(crate) fn pop_maybe_cell(&mut self) -> VmResult<Option<Arc<Cell>>> {
+        match self.pop()? {
+            VmValue::Cell(cell) => Ok(Some(cell)),
+            VmValue::Null => Ok(None),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    pub(crate) fn pop_slice(&mut self) -> VmResult<VmSlice> {
+        match self.pop()? {
+            VmValue::Slice(slice) => Ok(slice),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    pub(crate) fn pop_builder(&mut self) -> VmResult<VmBuilder> {
+        match self.pop()? {
+            VmValue::Builder(builder) => Ok(builder),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    pub(crate) fn pop_cont(&mut self) -> VmResult<Arc<Continuation>> {
+        match self.pop()? {
+            VmValue::Cont(cont) => Ok(cont),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    pub(crate) fn pop_tuple(&mut self) -> VmResult<Arc<Vec<VmValue>>> {
+        match self.pop()? {
+            VmValue::Tuple(tuple) => Ok(tuple),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    pub(crate) fn pop_maybe_tuple(&mut self) -> VmResult<Option<Arc<Vec<VmValue>>>> {
+        match self.pop()? {
+            VmValue::Tuple(tuple) => Ok(Some(tuple)),
+            VmValue::Null => Ok(None),
+            _ => Err(VmExitCode::TypeCheck.into()),
+        }
+    }
+
+    /// Charges gas for stack entries above the free depth when a
+    /// continuation installs a new stack.
+    pub(crate) fn charge_stack_depth(&mut self, depth: usize) -> VmResult<()> {
+        if depth > FREE_STACK_DEPTH {
+            self.consume_gas((depth - FREE_STACK_DEPTH) as i64 * GAS_STACK_ENTRY)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn fits_int257(value: &BigInt) -> bool {
+    fits_signed_bits(value, 257)
+}
+
+/// Returns `true` when `value` fits into a signed integer of `bits` bits.
+pub(crate) fn fits_signed_bits(value: &BigInt, bits: usize) -> bool {
+    if bits == 0 {
+        return value.sign() == Sign::NoSign;
+    }
+    let bound = BigInt::from(1) << (bits - 1);
+    value >= &-bound.clone() && value < &bound
+}
+
+/// Returns `true` when `value` fits into an unsigned integer of `bits` bits.
+pub(crate) fn fits_unsigned_bits(value: &BigInt, bits: usize) -> bool {
+    value.sign() != Sign::Minus && value.bits() <= bits as u64
+}
+
+/// Returns `true` when `value` fits into a signed or unsigned `bits`-bit integer.
+pub(crate) fn fits(value: &BigInt, bits: usize, unsigned: bool) -> bool {
+    if unsigned {
+        fits_unsigned_bits(value, bits)
+    } else {
+        fits_signed_bits(value, bits)
+    }
+}
@@ -0,0 +1,264 @@
+use super::*;
+use crate::tlb::TlbSerialize;
+use crate::tvm::{Dict, DictValue};
+use crate::wallet::{WalletV4R2Data, WalletV5R1Data, wallet_v4r2_code, wallet_v5r1_code};
+
+fn code(hex_code: &str) -> Arc<Cell> {
+    let bytes = hex::decode(hex_code.replace(' ', "")).unwrap();
+    let bit_len = bytes.len() * 8;
+    Arc::new(Cell::with_data(bytes, bit_len).unwrap())
+}
+
+fn run_code(hex_code: &str, stack: Vec<VmValue>) -> (i32, Vec<VmValue>, i64) {
+    let code = code(hex_code);
+    let mut state = VmState::new(VmInit {
+        code,
+        data: Arc::new(Cell::new()),
+        c7: Vec::new(),
+        stack,
+        gas: VmGasLimits::default(),
+    });
+    let exit_code = state.run();
+    (exit_code, state.stack, state.gas.consumed())
+}
+
+fn int_of(value: &VmValue) -> i64 {
+    match value {
+        VmValue::Int(value) => i64::try_from(value).unwrap(),
+        other => panic!("expected integer, got {}", other.type_name()),
+    }
+}
+
+fn sample_dict(entries: &[(u64, u64)]) -> Option<Arc<Cell>> {
+    let mut dict = Dict::new(8);
+    for (key, value) in entries {
+        dict.set_int_key(*key, DictValue::Uint(*value, 32)).unwrap();
+    }
+    dict.serialize().unwrap()
+}
+
+#[test]
+fn test_vm_arithmetic_charges_basic_gas() {
+    // PUSHINT 2; PUSHINT 3; ADD; implicit RET
+    let (exit_code, stack, gas) = run_code("72 73 A0", Vec::new());
+    assert_eq!(exit_code, 0);
+    assert_eq!(stack.len(), 1);
+    assert_eq!(int_of(&stack[0]), 5);
+    assert_eq!(gas, 3 * 18 + 5);
+}
+
+#[test]
+fn test_vm_throw_sets_exit_code() {
+    // THROW 42
+    let (exit_code, stack, _) = run_code("F22A", Vec::new());
+    assert_eq!(exit_code, 42);
+    assert_eq!(int_of(&stack[0]), 0);
+}
+
+#[test]
+fn test_vm_integer_overflow_raises_exception() {
+    // PUSHPOW2 255; DUP; ADD; ADD
+    let (exit_code, _, _) = run_code("83FE 20 A0 A0", Vec::new());
+    assert_eq!(exit_code, VmExitCode::IntegerOverflow.code());
+}
+
+#[test]
+fn test_vm_try_catches_exception() {
+    // PUSHCONT { THROW 7 }; PUSHCONT { NIP }; TRY
+    let (exit_code, stack, _) = run_code("92F207 9131 F2FF", Vec::new());
+    assert_eq!(exit_code, 0);
+    assert_eq!(stack.len(), 1);
+    assert_eq!(int_of(&stack[0]), 7);
+}
+
+#[test]
+fn test_vm_out_of_gas_stops_infinite_loop() {
+    // AGAINEND with an empty body.
+    let code = code("EB");
+    let mut state = VmState::new(VmInit {
+        code,
+        data: Arc::new(Cell::new()),
+        c7: Vec::new(),
+        stack: Vec::new(),
+        gas: VmGasLimits::get_method(1_000),
+    });
+    assert_eq!(state.run(), EXIT_CODE_OUT_OF_GAS);
+    assert!(state.gas.consumed() > 1_000);
+    assert_eq!(int_of(&state.stack[0]), state.gas.consumed());
+}
+
+#[test]
+fn test_vm_repeat_loop() {
+    // PUSHINT 0; PUSHINT 5; PUSHCONT { INC }; REPEAT
+    let (exit_code, stack, _) = run_code("70 75 91A4 E4", Vec::new());
+    assert_eq!(exit_code, 0);
+    assert_eq!(int_of(&stack[0]), 5);
+}
+
+#[test]
+fn test_vm_dict_set_builds_canonical_dictionary() {
+    // For each entry: PUSHINT value; NEWC; STU 32; PUSHINT key; ROT;
+    // PUSHINT 8; DICTUSETB
+    let entry = |key: u8, value: u8| format!("80{value:02x} C8 CB1F 80{key:02x} 58 8008 F443");
+    let program = format!("6D {} {} {}", entry(1, 10), entry(2, 20), entry(100, 30));
+    let (exit_code, stack, _) = run_code(&program, Vec::new());
+    assert_eq!(exit_code, 0);
+    let expected = sample_dict(&[(1, 10), (2, 20), (100, 30)]).unwrap();
+    match &stack[..] {
+        [VmValue::Cell(dict)] => assert_eq!(dict.hash(), expected.hash()),
+        other => panic!("unexpected stack {other:?}"),
+    }
+}
+
+#[test]
+fn test_vm_dict_get_delete_and_min_max() {
+    let dict = sample_dict(&[(1, 10), (2, 20), (100, 30)]).unwrap();
+    let dict_value = || VmValue::Cell(dict.clone());
+
+    // DICTUGET; DROP; PLDU 32
+    let (exit_code, stack, _) = run_code(
+        "F40E 30 D70B1F",
+        vec![VmValue::int(2), dict_value(), VmValue::int(8)],
+    );
+    assert_eq!(exit_code, 0);
+    assert_eq!(int_of(&stack[0]), 20);
+
+    // DICTUGET on a missing key.
+    let (_, stack, _) = run_code("F40E", vec![VmValue::int(3), dict_value(), VmValue::int(8)]);
+    assert_eq!(stack.len(), 1);
+    assert_eq!(int_of(&stack[0]), 0);
+
+    // DICTUDEL rebuilds the canonical two-entry dictionary.
+    let (_, stack, _) = run_code("F45B", vec![VmValue::int(2), dict_value(), VmValue::int(8)]);
+    let expected = sample_dict(&[(1, 10), (100, 30)]).unwrap();
+    match &stack[..] {
+        [VmValue::Cell(dict), VmValue::Int(flag)] => {
+            assert_eq!(dict.hash(), expected.hash());
+            assert_eq!(*flag, BigInt::from(-1));
+        }
+        other => panic!("unexpected stack {other:?}"),
+    }
+
+    // DICTUMIN and DICTUMAX return value, key and -1.
+    let (_, stack, _) = run_code("F486", vec![dict_value(), VmValue::int(8)]);
+    assert_eq!(int_of(&stack[1]), 1);
+    let (_, stack, _) = run_code("F48E", vec![dict_value(), VmValue::int(8)]);
+    assert_eq!(int_of(&stack[1]), 100);
+
+    // DICTUGETNEXT and DICTUGETPREV.
+    let (_, stack, _) = run_code("F47C", vec![VmValue::int(2), dict_value(), VmValue::int(8)]);
+    assert_eq!(int_of(&stack[1]), 100);
+    let (_, stack, _) = run_code("F47E", vec![VmValue::int(2), dict_value(), VmValue::int(8)]);
+    assert_eq!(int_of(&stack[1]), 1);
+}
+
+#[test]
+fn test_vm_signed_dict_orders_negative_keys_first() {
+    // DICTIMIN over keys -1 and 1.
+    let mut dict = Dict::new(8);
+    dict.set_int_key(0xff, DictValue::Uint(1, 8)).unwrap();
+    dict.set_int_key(0x01, DictValue::Uint(2, 8)).unwrap();
+    let dict = dict.serialize().unwrap().unwrap();
+    let (_, stack, _) = run_code("F484", vec![VmValue::Cell(dict), VmValue::int(8)]);
+    assert_eq!(int_of(&stack[1]), -1);
+}
+
+#[test]
+fn test_wallet_v5r1_get_methods_run_offline() {
+    let mut data = WalletV5R1Data::new(0x7fff_ff11, [7; 32]);
+    data.seqno = 42;
+    let runner = GetMethodRunner::new(wallet_v5r1_code().unwrap(), data.to_cell().unwrap());
+
+    let seqno = runner.run_by_name("seqno", TvmStack::empty()).unwrap();
+    assert_eq!(seqno.exit_code, 0);
+    assert_eq!(seqno.stack.entries(), &[TvmStackEntry::int(42)]);
+    assert!(seqno.gas_used > 0);
+
+    let wallet_id = runner
+        .run_by_name("get_subwallet_id", TvmStack::empty())
+        .unwrap();
+    assert_eq!(
+        wallet_id.stack.entries(),
+        &[TvmStackEntry::int(0x7fff_ff11)]
+    );
+
+    let public_key = runner
+        .run_by_name("get_public_key", TvmStack::empty())
+        .unwrap();
+    assert_eq!(
+        public_key.stack.entries(),
+        &[TvmStackEntry::Int(BigInt::from_bytes_be(
+            Sign::Plus,
+            &[7; 32]
+        ))]
+    );
+
+    let allowed = runner
+        .run_by_name("is_signature_allowed", TvmStack::empty())
+        .unwrap();
+    assert_eq!(allowed.stack.entries(), &[TvmStackEntry::int(-1)]);
+}
+
+#[test]
+fn test_wallet_v4r2_get_methods_run_offline() {
+    let mut data = WalletV4R2Data::new(698_983_191, [9; 32]);
+    data.seqno = 3;
+    let runner = GetMethodRunner::new(wallet_v4r2_code().unwrap(), data.to_cell().unwrap());
+
+    let seqno = runner.run_by_name("seqno", TvmStack::empty()).unwrap();
+    assert_eq!(seqno.stack.entries(), &[TvmStackEntry::int(3)]);
+    let subwallet = runner
+        .run_by_name("get_subwallet_id", TvmStack::empty())
+        .unwrap();
+    assert_eq!(
+        subwallet.stack.entries(),
+        &[TvmStackEntry::int(698_983_191)]
+    );
+    let plugins = runner
+        .run_by_name("get_plugin_list", TvmStack::empty())
+        .unwrap();
+    assert_eq!(plugins.exit_code, 0);
+    assert_eq!(plugins.stack.entries(), &[TvmStackEntry::Null]);
+}
+
+#[test]
+fn test_unknown_get_method_exits_with_code_11() {
+    let data = WalletV5R1Data::new(1, [1; 32]);
+    let runner = GetMethodRunner::new(wallet_v5r1_code().unwrap(), data.to_cell().unwrap());
+    let result = runner
+        .run_by_name("missing_method", TvmStack::empty())
+        .unwrap();
+    assert_eq!(result.exit_code, 11);
+    assert!(!result.is_success());
+}
+
+#[test]
+fn test_get_method_gas_limit_reports_out_of_gas() {
+    let data = WalletV5R1Data::new(1, [1; 32]);
+    let runner = GetMethodRunner::new(wallet_v5r1_code().unwrap(), data.to_cell().unwrap())
+        .with_gas_limit(100);
+    let result = runner.run_by_name("seqno", TvmStack::empty()).unwrap();
+    assert!(result.is_out_of_gas());
+    assert!(result.gas_used > 100);
+}
+
+#[test]
+fn test_run_result_converts_to_run_method_result() {
+    let data = WalletV5R1Data::new(1, [1; 32]);
+    let runner = GetMethodRunner::new(wallet_v5r1_code().unwrap(), data.to_cell().unwrap());
+    let block = crate::tl::BlockIdExt {
+        workchain: -1,
+        shard: i64::MIN,
+        seqno: 1,
+        root_hash: crate::tl::Int256([0; 32]),
+        file_hash: crate::tl::Int256([0; 32]),
+    };
+    let result = runner
+        .run_by_name("seqno", TvmStack::empty())
+        .unwrap()
+        .into_run_method_result(block)
+        .unwrap();
+    assert_eq!(result.exit_code, 0);
+    let stack = TvmStack::from_boc(result.result.as_deref().unwrap()).unwrap();
+    assert_eq!(stack.entries(), &[TvmStackEntry::int(0)]);
+}
@@ -0,0 +1,416 @@
+use super::*;
+
+/// A value stored on the TVM stack or in a control register.
+#[derive(Debug, Clone)]
+pub enum VmValue {
+    /// The TVM `null` value.
+    Null,
+    /// A signed 257-bit integer.
+    Int(BigInt),
+    /// The integer `NaN` produced by quiet arithmetic.
+    NaN,
+    /// An ordinary or exotic cell.
+    Cell(Arc<Cell>),
+    /// A read window over a cell.
+    Slice(VmSlice),
+    /// An unfinished cell builder.
+    Builder(VmBuilder),
+    /// An executable continuation.
+    Cont(Arc<Continuation>),
+    /// A tuple of at most 255 values.
+    Tuple(Arc<Vec<VmValue>>),
+}
+
+impl VmValue {
+    pub(crate) fn int(value: impl Into<BigInt>) -> Self {
+        Self::Int(value.into())
+    }
+
+    pub(crate) fn bool(value: bool) -> Self {
+        Self::Int(if value {
+            BigInt::from(-1)
+        } else {
+            BigInt::from(0)
+        })
+    }
+
+    pub(crate) fn tuple(values: Vec<VmValue>) -> Self {
+        Self::Tuple(Arc::new(values))
+    }
+
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Int(_) | Self::NaN => "integer",
+            Self::Cell(_) => "cell",
+            Self::Slice(_) => "slice",
+            Self::Builder(_) => "builder",
+            Self::Cont(_) => "continuation",
+            Self::Tuple(_) => "tuple",
+        }
+    }
+
+    /// Converts a get-method argument into a VM value.
+    pub fn from_stack_entry(entry: &TvmStackEntry) -> Result<Self> {
+        Ok(match entry {
+            TvmStackEntry::Null => Self::Null,
+            TvmStackEntry::Int(value) => Self::Int(value.clone()),
+            TvmStackEntry::Cell(cell) => Self::Cell(cell.clone()),
+            TvmStackEntry::Slice(cell) => Self::Slice(VmSlice::new(cell.clone())),
+            TvmStackEntry::Tuple(entries) => Self::tuple(
+                entries
+                    .iter()
+                    .map(Self::from_stack_entry)
+                    .collect::<Result<_>>()?,
+            ),
+            TvmStackEntry::List(entries) => {
+                let mut list = Self::Null;
+                for entry in entries.iter().rev() {
+                    list = Self::tuple(vec![Self::from_stack_entry(entry)?, list]);
+                }
+                list
+            }
+            TvmStackEntry::Unsupported(_) => {
+                bail!("Unsupported TVM stack entry cannot be passed to the interpreter")
+            }
+        })
+    }
+
+    /// Converts a VM value into the crate stack representation.
+    ///
+    /// Builders are finalized into cells; NaN and continuations have no
+    /// lossless stack representation and are preserved as unsupported entries
+    /// tagged with their TVM type name.
+    pub fn to_stack_entry(&self) -> Result<TvmStackEntry> {
+        Ok(match self {
+            Self::Null => TvmStackEntry::Null,
+            Self::Int(value) => TvmStackEntry::Int(value.clone()),
+            Self::NaN => TvmStackEntry::Unsupported(b"nan".to_vec()),
+            Self::Cell(cell) => TvmStackEntry::Cell(cell.clone()),
+            Self::Slice(slice) => TvmStackEntry::Slice(slice.to_cell()?),
+            Self::Builder(builder) => TvmStackEntry::Cell(builder.build()?),
+            Self::Cont(_) => TvmStackEntry::Unsupported(b"continuation".to_vec()),
+            Self::Tuple(values) => TvmStackEntry::Tuple(
+                values
+                    .iter()
+                    .map(Self::to_stack_entry)
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+/// A TVM slice: a bit and reference window over one cell.
+#[derive(Debug, Clone)]
+pub struct VmSlice {
+    cell: Arc<Cell>,
+    bit_start: usize,
+    bit_end: usize,
+    ref_start: usize,
+    ref_end: usize,
+}
+
+impl VmSlice {
+    pub(crate) fn new(cell: Arc<Cell>) -> Self {
+        let bit_end = cell.bit_len();
+        let ref_end = cell.reference_count();
+        Self {
+            cell,
+            bit_start: 0,
+            bit_end,
+            ref_start: 0,
+            ref_end,
+        }
+    }
+
+    pub(crate) fn empty() -> Self {
+        Self::new(Arc::new(Cell::new()))
+    }
+
+    pub(crate) fn bits(&self) -> usize {
+        self.bit_end - self.bit_start
+    }
+
+    pub(crate) fn refs(&self) -> usize {
+        self.ref_end - self.ref_start
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bits() == 0 && self.refs() == 0
+    }
+
+    pub(crate) fn bit_at(&self, offset: usize) -> bool {
+        let index = self.bit_start + offset;
+        (self.cell.data()[index / 8] >> (7 - index % 8)) & 1 == 1
+    }
+
+    /// Returns up to `n` leading bits as an integer padded with zero bits on
+    /// the right when fewer bits remain.
+    pub(crate) fn peek_padded(&self, n: usize) -> u64 {
+        let mut value = 0u64;
+        for offset in 0..n {
+            value <<= 1;
+            if offset < self.bits() && self.bit_at(offset) {
+                value |= 1;
+            }
+        }
+        value
+    }
+
+    pub(crate) fn bit_vec(&self) -> Vec<bool> {
+        (0..self.bits()).map(|offset| self.bit_at(offset)).collect()
+    }
+
+    pub(crate) fn reference(&self, index: usize) -> Option<&Arc<Cell>> {
+        if index < self.refs() {
+            self.cell.reference(self.ref_start + index)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn advance(&mut self, bits: usize, refs: usize) -> bool {
+        if bits > self.bits() || refs > self.refs() {
+            return false;
+        }
+        self.bit_start += bits;
+        self.ref_start += refs;
+        true
+    }
+
+    /// Splits off the first `bits` and `refs` as a new slice and advances.
+    pub(crate) fn split_prefix(&mut self, bits: usize, refs: usize) -> Option<VmSlice> {
+        if bits > self.bits() || refs > self.refs() {
+            return None;
+        }
+        let prefix = Self {
+            cell: self.cell.clone(),
+            bit_start: self.bit_start,
+            bit_end: self.bit_start + bits,
+            ref_start: self.ref_start,
+            ref_end: self.ref_start + refs,
+        };
+        self.advance(bits, refs);
+        Some(prefix)
+    }
+
+    /// Keeps only the last `bits` and `refs` of the window.
+    pub(crate) fn only_last(&mut self, bits: usize, refs: usize) -> bool {
+        if bits > self.bits() || refs > self.refs() {
+            return false;
+        }
+        self.bit_start = self.bit_end - bits;
+        self.ref_start = self.ref_end - refs;
+        true
+    }
+
+    /// Drops the last `bits` and `refs` of the window.
+    pub(crate) fn skip_last(&mut self, bits: usize, refs: usize) -> bool {
+        if bits > self.bits() || refs > self.refs() {
+            return false;
+        }
+        self.bit_end -= bits;
+        self.ref_end -= refs;
+        true
+    }
+
+    pub(crate) fn load_bits(&mut self, n: usize) -> Option<Vec<u8>> {
+        if n > self.bits() {
+            return None;
+        }
+        let mut out = vec![0u8; n.div_ceil(8)];
+        for offset in 0..n {
+            if self.bit_at(offset) {
+                out[offset / 8] |= 1 << (7 - offset % 8);
+            }
+        }
+        self.bit_start += n;
+        Some(out)
+    }
+
+    pub(crate) fn preload_uint(&self, n: usize) -> Option<BigUint> {
+        if n > self.bits() {
+            return None;
+        }
+        let mut value = BigUint::from(0u8);
+        for offset in 0..n {
+            value <<= 1;
+            if self.bit_at(offset) {
+                value |= BigUint::from(1u8);
+            }
+        }
+        Some(value)
+    }
+
+    pub(crate) fn load_uint(&mut self, n: usize) -> Option<BigUint> {
+        let value = self.preload_uint(n)?;
+        self.bit_start += n;
+        Some(value)
+    }
+
+    pub(crate) fn preload_int(&self, n: usize) -> Option<BigInt> {
+        let unsigned = self.preload_uint(n)?;
+        if n > 0 && self.bit_at(0) {
+            Some(BigInt::from(unsigned) - (BigInt::from(1) << n))
+        } else {
+            Some(BigInt::from(unsigned))
+        }
+    }
+
+    pub(crate) fn load_int(&mut self, n: usize) -> Option<BigInt> {
+        let value = self.preload_int(n)?;
+        self.bit_start += n;
+        Some(value)
+    }
+
+    pub(crate) fn load_small(&mut self, n: usize) -> Option<u64> {
+        debug_assert!(n <= 64);
+        if n > self.bits() {
+            return None;
+        }
+        let value = self.peek_padded(n);
+        self.bit_start += n;
+        Some(value)
+    }
+
+    pub(crate) fn load_ref(&mut self) -> Option<Arc<Cell>> {
+        let cell = self.reference(0)?.clone();
+        self.ref_start += 1;
+        Some(cell)
+    }
+
+    /// Removes trailing zero bits and the final one bit (completion tag).
+    pub(crate) fn remove_completion_tag(&mut self) -> bool {
+        while self.bits() > 0 {
+            let last = self.bit_at(self.bits() - 1);
+            self.bit_end -= 1;
+            if last {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub(crate) fn to_cell(&self) -> Result<Arc<Cell>> {
+        if self.bit_start == 0
+            && self.ref_start == 0
+            && self.bit_end == self.cell.bit_len()
+            && self.ref_end == self.cell.reference_count()
+            && !self.cell.is_exotic()
+        {
+            return Ok(self.cell.clone());
+        }
+        let mut builder = VmBuilder::new();
+        builder.append_slice(self)?;
+        builder.build()
+    }
+}
+
+/// A TVM builder holding at most 1023 bits and four references.
+#[derive(Debug, Clone, Default)]
+pub struct VmBuilder {
+    data: Vec<u8>,
+    bit_len: usize,
+    refs: Vec<Arc<Cell>>,
+}
+
+impl VmBuilder {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn bits(&self) -> usize {
+        self.bit_len
+    }
+
+    pub(crate) fn refs(&self) -> usize {
+        self.refs.len()
+    }
+
+    pub(crate) fn can_extend(&self, bits: usize, refs: usize) -> bool {
+        self.bit_len + bits <= MAX_CELL_BITS && self.refs.len() + refs <= MAX_CELL_REFS
+    }
+
+    pub(crate) fn store_bit(&mut self, bit: bool) {
+        if self.bit_len.is_multiple_of(8) {
+            self.data.push(0);
+        }
+        if bit {
+            let index = self.bit_len;
+            self.data[index / 8] |= 1 << (7 - index % 8);
+        }
+        self.bit_len += 1;
+    }
+
+    pub(crate) fn store_uint(&mut self, value: &BigUint, bits: usize) {
+        for shift in (0..bits).rev() {
+            self.store_bit(value.bit(shift as u64));
+        }
+    }
+
+    /// Stores a signed integer in two's complement; the caller checks range.
+    pub(crate) fn store_int(&mut self, value: &BigInt, bits: usize) {
+        let unsigned = if value.sign() == Sign::Minus {
+            (BigInt::from(1) << bits) + value
+        } else {
+            value.clone()
+        };
+        let (_, magnitude) = unsigned.into_parts();
+        self.store_uint(&magnitude, bits);
+    }
+
+    pub(crate) fn store_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            for shift in (0..8).rev() {
+                self.store_bit((byte >> shift) & 1 == 1);
+            }
+        }
+    }
+
+    pub(crate) fn store_ref(&mut self, cell: Arc<Cell>) {
+        self.refs.push(cell);
+    }
+
+    pub(crate) fn append_slice(&mut self, slice: &VmSlice) -> Result<()> {
+        if !self.can_extend(slice.bits(), slice.refs()) {
+            bail!("Builder overflow while appending slice");
+        }
+        for offset in 0..slice.bits() {
+            self.store_bit(slice.bit_at(offset));
+        }
+        for index in 0..slice.refs() {
+            if let Some(cell) = slice.reference(index) {
+                self.refs.push(cell.clone());
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn append_builder(&mut self, other: &VmBuilder) -> Result<()> {
+        if !self.can_extend(other.bit_len, other.refs.len()) {
+            bail!("Builder overflow while appending builder");
+        }
+        for index in 0..other.bit_len {
+            self.store_bit((other.data[index / 8] >> (7 - index % 8)) & 1 == 1);
+        }
+        self.refs.extend(other.refs.iter().cloned());
+        Ok(())
+    }
+
+    pub(crate) fn build(&self) -> Result<Arc<Cell>> {
+        let mut builder = CellBuilder::new();
+        builder.store_bits(&self.data, self.bit_len)?;
+        for cell in &self.refs {
+            builder.store_reference(cell.clone())?;
+        }
+        builder.build()
+    }
+
+    pub(crate) fn build_exotic(&self) -> Result<Arc<Cell>> {
+        Ok(Arc::new(Cell::with_exotic_data(
+            self.data.clone(),
+            self.bit_len,
+            self.refs.clone(),
+        )?))
+    }
+}
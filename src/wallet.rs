@@ -1,6 +1,7 @@
 //! Offline wallet helpers.
 //!
-//! The first wallet surface covers offline Wallet V4R2 and V5R1 helpers.
+//! The wallet surface covers offline Wallet V3R2, V4R2, V5R1, and Highload
+//! Wallet V3 helpers.
 //! It intentionally starts with deterministic cell construction, address
 //! derivation, signing, and external message BoC assembly; live send helpers
 //! are thin provider adapters.
@@ -19,6 +20,7 @@ pub(super) use std::sync::Arc;
 
 mod code;
 mod errors;
+mod highload_v3;
 mod message;
 mod mnemonic;
 mod provider;
@@ -30,5 +32,6 @@ mod v5r1;
 use mnemonic::*;
 use v5r1::*;
 
+pub use highload_v3::*;
 pub use message::*;
 pub use mnemonic::*;
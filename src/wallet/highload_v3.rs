@@ -0,0 +1,710 @@
+use super::*;
+use std::sync::OnceLock;
+
+pub(super) const HIGHLOAD_WALLET_V3_QUERIES_KEY_BITS: usize = 13;
+pub(super) const HIGHLOAD_WALLET_V3_TIMEOUT_BITS: usize = 22;
+pub(super) const HIGHLOAD_WALLET_V3_QUERY_ID_BITS: usize = 23;
+pub(super) const HIGHLOAD_WALLET_V3_BIT_NUMBER_BITS: u32 = 10;
+pub(super) const HIGHLOAD_WALLET_V3_INTERNAL_TRANSFER_OP: u32 = 0xae42_e5a4;
+pub(super) const HIGHLOAD_WALLET_V3_SEND_MODE_PAY_GAS_SEPARATELY: u8 = 1;
+pub(super) const HIGHLOAD_WALLET_V3_SEND_MODE_CARRY_ALL_BALANCE: u8 = 128;
+pub(super) const HIGHLOAD_WALLET_V3_CODE_BOC_HEX: &str = "b5ee9c7241021001000228000114ff00f4a413f4bcf2c80b01020120020d02014803040078d020d74bc00101c060b0915be101d0d3030171b0915be0fa4030f828c705b39130e0d31f018210ae42e5a4ba9d8040d721d74cf82a01ed55fb04e030020120050a02027306070011adce76a2686b85ffc00201200809001aabb6ed44d0810122d721d70b3f0018aa3bed44d08307d721d70b1f0201200b0c001bb9a6eed44d0810162d721d70b15800e5b8bf2eda2edfb21ab09028409b0ed44d0810120d721f404f404d33fd315d1058e1bf82325a15210b99f326df82305aa0015a112b992306dde923033e2923033e25230800df40f6fa19ed021d721d70a00955f037fdb31e09130e259800df40f6fa19cd001d721d70a00937fdb31e0915be270801f6f2d48308d718d121f900ed44d0d3ffd31ff404f404d33fd315d1f82321a15220b98e12336df82324aa00a112b9926d32de58f82301de541675f910f2a106d0d31fd4d307d30cd309d33fd315d15168baf2a2515abaf2a6f8232aa15250bcf2a304f823bbf2a35304800df40f6fa199d024d721d70a00f2649130e20e01fe5309800df40f6fa18e13d05004d718d20001f264c858cf16cf8301cf168e1030c824cf40cf8384095005a1a514cf40e2f800c94039800df41704c8cbff13cb1ff40012f40012cb3f12cb15c9ed54f80f21d0d30001f265d3020171b0925f03e0fa4001d70b01c000f2a5fa4031fa0031f401fa0031fa00318060d721d300010f0020f265d2000193d431d19130e272b1fb00b585bf03";
+
+static HIGHLOAD_WALLET_V3_CODE: OnceLock<Result<Arc<Cell>, String>> = OnceLock::new();
+
+/// Highload Wallet V3 code hash for the embedded upstream code BoC.
+pub const HIGHLOAD_WALLET_V3_CODE_HASH: [u8; 32] = [
+    0x11, 0xac, 0xad, 0x79, 0x55, 0x84, 0x40, 0x90, 0xf2, 0x83, 0xbf, 0x23, 0x8b, 0xc1, 0x44, 0x98,
+    0x71, 0xf7, 0x83, 0xe7, 0xcc, 0x09, 0x79, 0x40, 0x8d, 0x3f, 0x48, 0x59, 0x48, 0x3e, 0x85, 0x25,
+];
+
+/// Default Highload Wallet V3 `subwallet_id` used by common wallet apps.
+pub const HIGHLOAD_WALLET_V3_DEFAULT_SUBWALLET_ID: u32 = 0x10ad;
+
+/// Largest query-id `shift` accepted by Highload Wallet V3.
+pub const HIGHLOAD_WALLET_V3_MAX_SHIFT: u16 = (1 << HIGHLOAD_WALLET_V3_QUERIES_KEY_BITS) - 1;
+
+/// Largest query-id `bit_number` accepted by Highload Wallet V3.
+pub const HIGHLOAD_WALLET_V3_MAX_BIT_NUMBER: u16 = 1022;
+
+/// Largest `timeout` value that fits the contract's `uint22` field.
+pub const HIGHLOAD_WALLET_V3_MAX_TIMEOUT: u32 = (1 << HIGHLOAD_WALLET_V3_TIMEOUT_BITS) - 1;
+
+/// Maximum number of actions packed into one Highload Wallet V3 action list.
+///
+/// Larger batches keep 253 transfers per list and spend the last slot on an
+/// `internal_transfer` message to the wallet itself carrying the remainder.
+pub const HIGHLOAD_WALLET_V3_MAX_ACTIONS_PER_LIST: usize = 254;
+
+/// Highload Wallet V3 query id split into the `shift` dictionary key and the
+/// `bit_number` inside that key's 1023-bit bitmap.
+///
+/// The packed 23-bit value is `shift << 10 | bit_number`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HighloadQueryId {
+    shift: u16,
+    bit_number: u16,
+}
+
+impl HighloadQueryId {
+    /// Creates a query id from `shift` and `bit_number`.
+    pub fn new(shift: u16, bit_number: u16) -> Result<Self, WalletError> {
+        if shift > HIGHLOAD_WALLET_V3_MAX_SHIFT || bit_number > HIGHLOAD_WALLET_V3_MAX_BIT_NUMBER {
+            return Err(WalletError::InvalidHighloadQueryId { shift, bit_number });
+        }
+        Ok(Self { shift, bit_number })
+    }
+
+    /// Unpacks a raw 23-bit query id.
+    pub fn from_raw(query_id: u32) -> Result<Self, WalletError> {
+        if query_id >> HIGHLOAD_WALLET_V3_QUERY_ID_BITS != 0 {
+            return Err(WalletError::InvalidHighloadQueryId {
+                shift: (query_id >> HIGHLOAD_WALLET_V3_BIT_NUMBER_BITS) as u16,
+                bit_number: (query_id & 0x3ff) as u16,
+            });
+        }
+        Self::new(
+            (query_id >> HIGHLOAD_WALLET_V3_BIT_NUMBER_BITS) as u16,
+            (query_id & 0x3ff) as u16,
+        )
+    }
+
+    /// Returns the dictionary key half of the query id.
+    pub fn shift(&self) -> u16 {
+        self.shift
+    }
+
+    /// Returns the bitmap position half of the query id.
+    pub fn bit_number(&self) -> u16 {
+        self.bit_number
+    }
+
+    /// Packs the query id into the raw 23-bit value signed by the wallet.
+    pub fn raw(&self) -> u32 {
+        ((self.shift as u32) << HIGHLOAD_WALLET_V3_BIT_NUMBER_BITS) | self.bit_number as u32
+    }
+
+    /// Returns true when [`Self::next`] still has an id to hand out.
+    pub fn has_next(&self) -> bool {
+        self.shift < HIGHLOAD_WALLET_V3_MAX_SHIFT
+            || self.bit_number < HIGHLOAD_WALLET_V3_MAX_BIT_NUMBER - 1
+    }
+
+    /// Returns the following query id.
+    ///
+    /// `bit_number` advances first and carries into `shift`. The final id
+    /// (`shift = 8191`, `bit_number = 1022`) is never returned so it stays
+    /// available for emergency transfers.
+    pub fn next(&self) -> Result<Self, WalletError> {
+        if !self.has_next() {
+            return Err(WalletError::HighloadQueryIdExhausted);
+        }
+        if self.bit_number < HIGHLOAD_WALLET_V3_MAX_BIT_NUMBER {
+            Ok(Self {
+                shift: self.shift,
+                bit_number: self.bit_number + 1,
+            })
+        } else {
+            Ok(Self {
+                shift: self.shift + 1,
+                bit_number: 0,
+            })
+        }
+    }
+}
+
+/// Sequential Highload Wallet V3 query-id allocator.
+///
+/// The allocator only remembers the next id to hand out. Persist
+/// [`Self::peek`] between process restarts; an id may be reused once
+/// `2 * timeout` seconds have passed since the wallet last accepted it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighloadQueryIdAllocator {
+    next: Option<HighloadQueryId>,
+}
+
+impl Default for HighloadQueryIdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HighloadQueryIdAllocator {
+    /// Creates an allocator that starts from `shift = 0`, `bit_number = 0`.
+    pub fn new() -> Self {
+        Self::starting_at(HighloadQueryId::default())
+    }
+
+    /// Creates an allocator that hands out `next` first.
+    pub fn starting_at(next: HighloadQueryId) -> Self {
+        Self { next: Some(next) }
+    }
+
+    /// Returns the id the next [`Self::allocate`] call will hand out.
+    pub fn peek(&self) -> Option<HighloadQueryId> {
+        self.next
+    }
+
+    /// Hands out the next query id and advances the allocator.
+    pub fn allocate(&mut self) -> Result<HighloadQueryId, WalletError> {
+        let current = self.next.ok_or(WalletError::HighloadQueryIdExhausted)?;
+        self.next = current.next().ok();
+        Ok(current)
+    }
+
+    /// Restarts allocation from the first query id.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Persistent Highload Wallet V3 storage data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighloadWalletV3Data {
+    /// Ed25519 public key bytes.
+    pub public_key: [u8; 32],
+    /// Wallet `subwallet_id`.
+    pub subwallet_id: u32,
+    /// Query bitmaps from the previous timeout window, keyed by `shift`.
+    pub old_queries: HashmapE<Arc<Cell>>,
+    /// Query bitmaps from the current timeout window, keyed by `shift`.
+    pub queries: HashmapE<Arc<Cell>>,
+    /// Unix time of the last `queries` to `old_queries` rotation.
+    pub last_clean_time: u64,
+    /// Replay-protection window in seconds.
+    pub timeout: u32,
+}
+
+impl HighloadWalletV3Data {
+    /// Creates initial Highload Wallet V3 data with empty query dictionaries.
+    pub fn new(public_key: [u8; 32], subwallet_id: u32, timeout: u32) -> Self {
+        Self {
+            public_key,
+            subwallet_id,
+            old_queries: HashmapE::new(HIGHLOAD_WALLET_V3_QUERIES_KEY_BITS),
+            queries: HashmapE::new(HIGHLOAD_WALLET_V3_QUERIES_KEY_BITS),
+            last_clean_time: 0,
+            timeout,
+        }
+    }
+
+    /// Returns whether `query_id` is marked in either query dictionary.
+    ///
+    /// This mirrors the `processed?` get-method without the `need_clean`
+    /// rotation, so it is only meaningful for a freshly read state.
+    pub fn is_processed(&self, query_id: HighloadQueryId) -> crate::tlb::Result<bool> {
+        Ok(query_bitmap_contains(&self.queries, query_id)?
+            || query_bitmap_contains(&self.old_queries, query_id)?)
+    }
+}
+
+impl TlbSerialize for HighloadWalletV3Data {
+    fn store_tlb(&self, builder: &mut Builder) -> crate::tlb::Result<()> {
+        for (field, dict) in [
+            ("HighloadWalletV3Data.old_queries", &self.old_queries),
+            ("HighloadWalletV3Data.queries", &self.queries),
+        ] {
+            if dict.key_bits() != HIGHLOAD_WALLET_V3_QUERIES_KEY_BITS {
+                return Err(TlbError::CustomSchema {
+                    schema: field,
+                    message: format!("query dictionary key width {} is not 13", dict.key_bits()),
+                });
+            }
+        }
+        if self.timeout > HIGHLOAD_WALLET_V3_MAX_TIMEOUT {
+            return Err(TlbError::CustomSchema {
+                schema: "HighloadWalletV3Data.timeout",
+                message: format!("timeout {} does not fit uint22", self.timeout),
+            });
+        }
+
+        builder.store_bytes(&self.public_key)?;
+        builder.store_u32(self.subwallet_id)?;
+        builder.store_hashmap_e_with(&self.old_queries, |builder, value| {
+            builder.store_ref(value.clone())?;
+            Ok(())
+        })?;
+        builder.store_hashmap_e_with(&self.queries, |builder, value| {
+            builder.store_ref(value.clone())?;
+            Ok(())
+        })?;
+        builder.store_u64(self.last_clean_time)?;
+        builder.store_uint_custom(self.timeout, HIGHLOAD_WALLET_V3_TIMEOUT_BITS)?;
+        Ok(())
+    }
+}
+
+impl TlbDeserialize for HighloadWalletV3Data {
+    fn load_tlb(slice: &mut Slice) -> crate::tlb::Result<Self> {
+        Ok(Self {
+            public_key: {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(&slice.load_bytes(32)?);
+                bytes
+            },
+            subwallet_id: slice.load_u32()?,
+            old_queries: slice
+                .load_hashmap_e_with(HIGHLOAD_WALLET_V3_QUERIES_KEY_BITS, |slice| {
+                    slice.load_reference()
+                })?,
+            queries: slice.load_hashmap_e_with(HIGHLOAD_WALLET_V3_QUERIES_KEY_BITS, |slice| {
+                slice.load_reference()
+            })?,
+            last_clean_time: slice.load_u64()?,
+            timeout: slice.load_uint_custom(HIGHLOAD_WALLET_V3_TIMEOUT_BITS)?,
+        })
+    }
+}
+
+fn query_bitmap_contains(
+    dict: &HashmapE<Arc<Cell>>,
+    query_id: HighloadQueryId,
+) -> crate::tlb::Result<bool> {
+    let key =
+        crate::tvm::BitKey::from_u64(query_id.shift() as u64, HIGHLOAD_WALLET_V3_QUERIES_KEY_BITS)?;
+    let Some(bitmap) = dict.get_bit_key(&key)? else {
+        return Ok(false);
+    };
+    let bit_number = query_id.bit_number() as usize;
+    if bit_number >= bitmap.bit_len() {
+        return Ok(false);
+    }
+    let mut slice = Slice::new(bitmap.clone());
+    slice.skip_bits(bit_number)?;
+    Ok(slice.load_bit()?)
+}
+
+/// Highload Wallet V3 offline helper bound to code, workchain, subwallet id,
+/// timeout, and public key.
+#[derive(Debug, Clone)]
+pub struct HighloadWalletV3 {
+    workchain: i8,
+    subwallet_id: u32,
+    timeout: u32,
+    public_key: [u8; 32],
+    pub(super) code: Arc<Cell>,
+}
+
+impl HighloadWalletV3 {
+    /// Creates a Highload Wallet V3 helper from a public key, subwallet id,
+    /// timeout in seconds, code cell, and workchain.
+    pub fn new(
+        public_key: [u8; 32],
+        subwallet_id: u32,
+        timeout: u32,
+        code: Arc<Cell>,
+        workchain: i8,
+    ) -> Result<Self, WalletError> {
+        if timeout > HIGHLOAD_WALLET_V3_MAX_TIMEOUT {
+            return Err(WalletError::HighloadTimeoutTooLarge(timeout));
+        }
+        Ok(Self {
+            workchain,
+            subwallet_id,
+            timeout,
+            public_key,
+            code,
+        })
+    }
+
+    /// Creates a Highload Wallet V3 helper with the common default subwallet id.
+    pub fn default(
+        public_key: [u8; 32],
+        timeout: u32,
+        code: Arc<Cell>,
+        workchain: i8,
+    ) -> Result<Self, WalletError> {
+        Self::new(
+            public_key,
+            HIGHLOAD_WALLET_V3_DEFAULT_SUBWALLET_ID,
+            timeout,
+            code,
+            workchain,
+        )
+    }
+
+    /// Returns the wallet workchain.
+    pub fn workchain(&self) -> i8 {
+        self.workchain
+    }
+
+    /// Returns the raw 32-bit subwallet id.
+    pub fn subwallet_id(&self) -> u32 {
+        self.subwallet_id
+    }
+
+    /// Returns the replay-protection timeout in seconds.
+    pub fn timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    /// Returns the configured public key.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    /// Returns whether the contract accepts a message with `created_at` at
+    /// unix time `now`.
+    ///
+    /// The wallet rejects messages from the future and messages older than
+    /// `timeout`, so callers usually backdate `created_at` by a few seconds
+    /// to absorb clock skew between the signer and validators.
+    pub fn is_created_at_valid(&self, created_at: u64, now: u64) -> bool {
+        created_at <= now && created_at > now.saturating_sub(self.timeout as u64)
+    }
+
+    /// Builds the initial data cell value.
+    pub fn data(&self) -> HighloadWalletV3Data {
+        HighloadWalletV3Data::new(self.public_key, self.subwallet_id, self.timeout)
+    }
+
+    /// Builds the wallet `StateInit`.
+    pub fn state_init(&self) -> Result<StateInit, WalletError> {
+        Ok(StateInit {
+            code: Some(self.code.clone()),
+            data: Some(self.data().to_cell()?),
+            ..StateInit::empty()
+        })
+    }
+
+    /// Derives the wallet address from `StateInit`.
+    pub fn address(&self) -> Result<Address, WalletError> {
+        let state_init = self.state_init()?;
+        Ok(Address::new(self.workchain, state_init.to_cell()?.hash()))
+    }
+
+    /// Packs `messages` into the `internal_transfer` message the wallet sends
+    /// to itself for a batch.
+    ///
+    /// Each action list holds at most 254 actions. When a batch is larger,
+    /// the first 253 messages stay in the list and the last action carries
+    /// the remainder in a nested `internal_transfer` to the wallet. `value`
+    /// is attached to every self-message; zero forwards the whole balance.
+    pub fn pack_actions(
+        &self,
+        messages: Vec<WalletMessage>,
+        value: u64,
+        query_id: HighloadQueryId,
+    ) -> Result<MessageRelaxed, WalletError> {
+        let address = self.address()?;
+        let mode = batch_send_mode(value);
+        let mut messages = messages;
+        let mut tail = None;
+        let mut chunks = Vec::new();
+        while messages.len() > HIGHLOAD_WALLET_V3_MAX_ACTIONS_PER_LIST {
+            let rest = messages.split_off(HIGHLOAD_WALLET_V3_MAX_ACTIONS_PER_LIST - 1);
+            chunks.push(messages);
+            messages = rest;
+        }
+        chunks.push(messages);
+
+        for chunk in chunks.into_iter().rev() {
+            let mut actions = chunk
+                .into_iter()
+                .map(WalletMessage::into_action)
+                .collect::<Vec<_>>();
+            if let Some(out_msg) = tail.take() {
+                actions.push(OutAction::SendMsg { mode, out_msg });
+            }
+            let mut body = Builder::new();
+            body.store_u32(HIGHLOAD_WALLET_V3_INTERNAL_TRANSFER_OP)?;
+            body.store_u64(query_id.raw() as u64)?;
+            body.store_ref(OutList::new(actions).to_cell()?)?;
+            tail = Some(
+                WalletMessage::internal(address.clone(), value)
+                    .with_body(body.build()?)
+                    .into_message_relaxed(),
+            );
+        }
+        Ok(tail.expect("at least one chunk is packed"))
+    }
+
+    /// Builds the unsigned signing cell for one outgoing message.
+    pub fn build_external_signing_cell(
+        &self,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        message: WalletMessage,
+    ) -> Result<Arc<Cell>, WalletError> {
+        let mode = message.mode;
+        self.build_signing_cell(query_id, created_at, mode, message.into_message_relaxed())
+    }
+
+    /// Builds the unsigned signing cell for a batch of outgoing messages.
+    ///
+    /// See [`Self::pack_actions`] for how `messages` and `value` are packed.
+    pub fn build_batch_signing_cell(
+        &self,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        messages: Vec<WalletMessage>,
+        value: u64,
+    ) -> Result<Arc<Cell>, WalletError> {
+        let message = self.pack_actions(messages, value, query_id)?;
+        self.build_signing_cell(query_id, created_at, batch_send_mode(value), message)
+    }
+
+    fn build_signing_cell(
+        &self,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        mode: u8,
+        message: MessageRelaxed,
+    ) -> Result<Arc<Cell>, WalletError> {
+        let mut builder = Builder::new();
+        builder.store_u32(self.subwallet_id)?;
+        builder.store_ref(message.to_cell()?)?;
+        builder.store_u8(mode)?;
+        builder.store_uint_custom(query_id.raw(), HIGHLOAD_WALLET_V3_QUERY_ID_BITS)?;
+        builder.store_u64(created_at)?;
+        builder.store_uint_custom(self.timeout, HIGHLOAD_WALLET_V3_TIMEOUT_BITS)?;
+        Ok(builder.build()?)
+    }
+
+    /// Builds a signed external body cell for one outgoing message and returns
+    /// the body, signed hash, and Ed25519 signature.
+    pub fn build_signed_external_body(
+        &self,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        message: WalletMessage,
+        signing_key: &SigningKey,
+    ) -> Result<HighloadWalletV3SignedBody, WalletError> {
+        let signing_cell = self.build_external_signing_cell(query_id, created_at, message)?;
+        sign_highload_body(signing_cell, signing_key)
+    }
+
+    /// Builds a signed external body cell for a batch of outgoing messages.
+    pub fn build_signed_batch_body(
+        &self,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        messages: Vec<WalletMessage>,
+        value: u64,
+        signing_key: &SigningKey,
+    ) -> Result<HighloadWalletV3SignedBody, WalletError> {
+        let signing_cell = self.build_batch_signing_cell(query_id, created_at, messages, value)?;
+        sign_highload_body(signing_cell, signing_key)
+    }
+
+    /// Builds an external inbound message BoC for one outgoing message.
+    pub fn build_external_message_boc(
+        &self,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        message: WalletMessage,
+        signing_key: &SigningKey,
+        include_state_init: bool,
+    ) -> Result<Vec<u8>, WalletError> {
+        let signed = self.build_signed_external_body(query_id, created_at, message, signing_key)?;
+        self.external_message_boc(signed, include_state_init)
+    }
+
+    /// Builds an external inbound message BoC for a batch of outgoing messages.
+    pub fn build_batch_message_boc(
+        &self,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        messages: Vec<WalletMessage>,
+        value: u64,
+        signing_key: &SigningKey,
+        include_state_init: bool,
+    ) -> Result<Vec<u8>, WalletError> {
+        let signed =
+            self.build_signed_batch_body(query_id, created_at, messages, value, signing_key)?;
+        self.external_message_boc(signed, include_state_init)
+    }
+
+    fn external_message_boc(
+        &self,
+        signed: HighloadWalletV3SignedBody,
+        include_state_init: bool,
+    ) -> Result<Vec<u8>, WalletError> {
+        let state_init = if include_state_init {
+            Some(Either::Right(self.state_init()?))
+        } else {
+            None
+        };
+        let message = Message {
+            info: CommonMsgInfo::ExternalIn {
+                src: MsgAddressExt::None,
+                dest: MsgAddressInt::std(self.address()?),
+                import_fee: Grams(BigUint::from(0u8)),
+            },
+            init: state_init,
+            body: Either::Right(signed.body),
+        };
+        Ok(serialize_boc(&message.to_cell()?, false)?)
+    }
+
+    /// Reads the deployed subwallet id through `get_subwallet_id`.
+    #[cfg(feature = "liteclient")]
+    pub async fn subwallet_id_onchain<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+    ) -> Result<u32, WalletGetMethodError<P::Error>> {
+        let stack = run_wallet_get_method(provider, self.address()?, "get_subwallet_id").await?;
+        wallet_stack_u32("get_subwallet_id", &stack, 0)
+    }
+
+    /// Reads the deployed Ed25519 public key through `get_public_key`.
+    #[cfg(feature = "liteclient")]
+    pub async fn public_key_onchain<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+    ) -> Result<[u8; 32], WalletGetMethodError<P::Error>> {
+        let stack = run_wallet_get_method(provider, self.address()?, "get_public_key").await?;
+        wallet_stack_public_key("get_public_key", &stack, 0)
+    }
+
+    /// Reads the deployed timeout through `get_timeout`.
+    #[cfg(feature = "liteclient")]
+    pub async fn timeout_onchain<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+    ) -> Result<u32, WalletGetMethodError<P::Error>> {
+        let stack = run_wallet_get_method(provider, self.address()?, "get_timeout").await?;
+        wallet_stack_u32("get_timeout", &stack, 0)
+    }
+
+    /// Reads the last query-dictionary rotation time through
+    /// `get_last_clean_time`.
+    #[cfg(feature = "liteclient")]
+    pub async fn last_clean_time_onchain<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+    ) -> Result<u64, WalletGetMethodError<P::Error>> {
+        let stack = run_wallet_get_method(provider, self.address()?, "get_last_clean_time").await?;
+        wallet_stack_u64("get_last_clean_time", &stack, 0)
+    }
+
+    /// Reads whether `query_id` was already accepted through `processed?`.
+    ///
+    /// With `need_clean`, the get-method first applies the same dictionary
+    /// rotation the next external message would, so ids older than
+    /// `2 * timeout` report as unprocessed.
+    #[cfg(feature = "liteclient")]
+    pub async fn is_processed<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        query_id: HighloadQueryId,
+        need_clean: bool,
+    ) -> Result<bool, WalletGetMethodError<P::Error>> {
+        use crate::tvm::{TvmStack, TvmStackEntry};
+
+        let stack = run_wallet_get_method_with_stack(
+            provider,
+            self.address()?,
+            "processed?",
+            TvmStack::new(vec![
+                TvmStackEntry::int(query_id.raw()),
+                TvmStackEntry::int(if need_clean { -1 } else { 0 }),
+            ]),
+        )
+        .await?;
+        wallet_stack_bool_int("processed?", &stack, 0)
+    }
+
+    /// Sends one signed outgoing message through any contract provider.
+    #[cfg(feature = "liteclient")]
+    pub async fn send_external_message<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        message: WalletMessage,
+        signing_key: &SigningKey,
+        include_state_init: bool,
+    ) -> Result<u32, WalletSendError<P::Error>> {
+        let boc = self
+            .build_external_message_boc(
+                query_id,
+                created_at,
+                message,
+                signing_key,
+                include_state_init,
+            )
+            .map_err(WalletSendError::Build)?;
+        provider
+            .send_external_message_boc(boc)
+            .await
+            .map_err(WalletSendError::Provider)
+    }
+
+    /// Sends a signed batch of outgoing messages through any contract provider.
+    #[cfg(feature = "liteclient")]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_batch<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        query_id: HighloadQueryId,
+        created_at: u64,
+        messages: Vec<WalletMessage>,
+        value: u64,
+        signing_key: &SigningKey,
+        include_state_init: bool,
+    ) -> Result<u32, WalletSendError<P::Error>> {
+        let boc = self
+            .build_batch_message_boc(
+                query_id,
+                created_at,
+                messages,
+                value,
+                signing_key,
+                include_state_init,
+            )
+            .map_err(WalletSendError::Build)?;
+        provider
+            .send_external_message_boc(boc)
+            .await
+            .map_err(WalletSendError::Provider)
+    }
+}
+
+/// Signed Highload Wallet V3 external body material.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighloadWalletV3SignedBody {
+    /// Final body cell containing the signature and a reference to the signed
+    /// message cell.
+    pub body: Arc<Cell>,
+    /// Representation hash that was signed.
+    pub signing_hash: [u8; 32],
+    /// Ed25519 signature bytes.
+    pub signature: [u8; 64],
+}
+
+/// Returns the embedded official Highload Wallet V3 code cell.
+pub fn highload_wallet_v3_code() -> Result<Arc<Cell>, WalletError> {
+    cached_wallet_code(&HIGHLOAD_WALLET_V3_CODE, decode_highload_wallet_v3_code)
+}
+
+pub(super) fn decode_highload_wallet_v3_code() -> Result<Arc<Cell>, WalletError> {
+    let bytes = hex::decode(HIGHLOAD_WALLET_V3_CODE_BOC_HEX)
+        .map_err(|error| WalletError::CodeDecode(error.to_string()))?;
+    crate::tvm::deserialize_boc(&bytes).map_err(WalletError::Tvm)
+}
+
+fn batch_send_mode(value: u64) -> u8 {
+    if value > 0 {
+        HIGHLOAD_WALLET_V3_SEND_MODE_PAY_GAS_SEPARATELY
+    } else {
+        HIGHLOAD_WALLET_V3_SEND_MODE_CARRY_ALL_BALANCE
+    }
+}
+
+fn sign_highload_body(
+    signing_cell: Arc<Cell>,
+    signing_key: &SigningKey,
+) -> Result<HighloadWalletV3SignedBody, WalletError> {
+    let signing_hash = signing_cell.hash();
+    let signature = signing_key.sign(&signing_hash).to_bytes();
+
+    let mut builder = Builder::new();
+    builder.store_bytes(&signature)?;
+    builder.store_ref(signing_cell)?;
+    Ok(HighloadWalletV3SignedBody {
+        body: builder.build()?,
+        signing_hash,
+        signature,
+    })
+}
@@ -24,17 +24,20 @@ impl WalletMessage {
         self
     }
 
+    /// Sets an op `0` text comment body.
+    pub fn with_comment(self, comment: &str) -> Result<Self, crate::comment::CommentError> {
+        Ok(self.with_body(crate::comment::text_comment_cell(comment)?))
+    }
+
     /// Sets the bounce flag.
     pub fn with_bounce(mut self, bounce: bool) -> Self {
         self.bounce = bounce;
         self
     }
 
-    fn into_message_relaxed(self) -> MessageRelaxed {
-        let body = self
-            .body
-            .unwrap_or_else(|| Builder::new().build().expect("empty cell builds"));
-        MessageRelaxed {
+    pub(super) fn into_message_relaxed(self) -> MessageRelaxed {
+        let body = self.body;
+        let mut message = MessageRelaxed {
             info: CommonMsgInfoRelaxed::Internal {
                 ihr_disabled: true,
                 bounce: self.bounce,
@@ -48,11 +51,19 @@ impl WalletMessage {
                 created_at: 0,
             },
             init: None,
-            body: Either::Left(body),
+            body: Either::Left(Builder::new().build().expect("empty cell builds")),
+        };
+        if let Some(body) = body {
+            message.body = if body_fits_inline(&message, &body) {
+                Either::Left(body)
+            } else {
+                Either::Right(body)
+            };
         }
+        message
     }
 
-    fn into_action(self) -> OutAction {
+    pub(super) fn into_action(self) -> OutAction {
         OutAction::SendMsg {
             mode: self.mode,
             out_msg: self.into_message_relaxed(),
@@ -60,6 +71,14 @@ impl WalletMessage {
     }
 }
 
+fn body_fits_inline(message: &MessageRelaxed, body: &Cell) -> bool {
+    let Ok(header) = message.to_cell() else {
+        return false;
+    };
+    header.bit_len() + body.bit_len() <= 1023
+        && header.references().len() + body.references().len() <= 4
+}
+
 /// Wallet V5R1 management action from `W5ExtendedAction`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WalletV5R1ExtendedAction {
@@ -252,6 +271,193 @@ impl TlbDeserialize for WalletV5R1ExtendedActionList {
     }
 }
 
+/// Wallet V3R2 offline helper bound to code, workchain, wallet id, and public key.
+#[derive(Debug, Clone)]
+pub struct WalletV3R2 {
+    workchain: i8,
+    wallet_id: u32,
+    public_key: [u8; 32],
+    pub(super) code: Arc<Cell>,
+}
+
+impl WalletV3R2 {
+    /// Creates a Wallet V3R2 helper from a public key, raw wallet id, code cell,
+    /// and workchain.
+    pub fn new(public_key: [u8; 32], wallet_id: u32, code: Arc<Cell>, workchain: i8) -> Self {
+        Self {
+            workchain,
+            wallet_id,
+            public_key,
+            code,
+        }
+    }
+
+    /// Creates a Wallet V3R2 helper with the common default wallet id.
+    pub fn default(public_key: [u8; 32], code: Arc<Cell>, workchain: i8) -> Self {
+        Self::new(public_key, WALLET_V3R2_DEFAULT_ID, code, workchain)
+    }
+
+    /// Returns the wallet workchain.
+    pub fn workchain(&self) -> i8 {
+        self.workchain
+    }
+
+    /// Returns the raw 32-bit wallet id.
+    pub fn wallet_id(&self) -> u32 {
+        self.wallet_id
+    }
+
+    /// Returns the configured public key.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    /// Builds the initial data cell value.
+    pub fn data(&self) -> WalletV3R2Data {
+        WalletV3R2Data::new(self.wallet_id, self.public_key)
+    }
+
+    /// Builds the wallet `StateInit`.
+    pub fn state_init(&self) -> Result<StateInit, WalletError> {
+        Ok(StateInit {
+            code: Some(self.code.clone()),
+            data: Some(self.data().to_cell()?),
+            ..StateInit::empty()
+        })
+    }
+
+    /// Derives the wallet address from `StateInit`.
+    pub fn address(&self) -> Result<Address, WalletError> {
+        let state_init = self.state_init()?;
+        Ok(Address::new(self.workchain, state_init.to_cell()?.hash()))
+    }
+
+    /// Builds the unsigned signing cell for a Wallet V3R2 transfer.
+    pub fn build_external_signing_cell(
+        &self,
+        seqno: u32,
+        valid_until: u32,
+        messages: Vec<WalletMessage>,
+    ) -> Result<Arc<Cell>, WalletError> {
+        if messages.len() > WALLET_V3R2_MAX_MESSAGES {
+            return Err(WalletError::TooManyActions {
+                count: messages.len(),
+                max: WALLET_V3R2_MAX_MESSAGES,
+            });
+        }
+
+        let mut builder = Builder::new();
+        builder.store_u32(self.wallet_id)?;
+        builder.store_u32(valid_until)?;
+        builder.store_u32(seqno)?;
+        for message in messages {
+            let mode = message.mode;
+            let relaxed = message.into_message_relaxed();
+            builder.store_u8(mode)?;
+            builder.store_ref(relaxed.to_cell()?)?;
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Builds a signed external body cell and returns the body, signed hash,
+    /// and Ed25519 signature.
+    pub fn build_signed_external_body(
+        &self,
+        seqno: u32,
+        valid_until: u32,
+        messages: Vec<WalletMessage>,
+        signing_key: &SigningKey,
+    ) -> Result<WalletV3R2SignedBody, WalletError> {
+        let signing_cell = self.build_external_signing_cell(seqno, valid_until, messages)?;
+        let signing_hash = signing_cell.hash();
+        let signature = signing_key.sign(&signing_hash).to_bytes();
+
+        let mut builder = Builder::new();
+        builder.store_bytes(&signature)?;
+        builder.store_cell(&signing_cell)?;
+        Ok(WalletV3R2SignedBody {
+            body: builder.build()?,
+            signing_hash,
+            signature,
+        })
+    }
+
+    /// Builds an external inbound message BoC with the signed body.
+    pub fn build_external_message_boc(
+        &self,
+        seqno: u32,
+        valid_until: u32,
+        messages: Vec<WalletMessage>,
+        signing_key: &SigningKey,
+        include_state_init: bool,
+    ) -> Result<Vec<u8>, WalletError> {
+        let signed = self.build_signed_external_body(seqno, valid_until, messages, signing_key)?;
+        let state_init = if include_state_init {
+            Some(Either::Right(self.state_init()?))
+        } else {
+            None
+        };
+        let message = Message {
+            info: CommonMsgInfo::ExternalIn {
+                src: MsgAddressExt::None,
+                dest: MsgAddressInt::std(self.address()?),
+                import_fee: Grams(BigUint::from(0u8)),
+            },
+            init: state_init,
+            body: Either::Right(signed.body),
+        };
+        Ok(serialize_boc(&message.to_cell()?, false)?)
+    }
+
+    /// Reads the deployed wallet `seqno` get-method from the latest
+    /// masterchain block known by the provider.
+    #[cfg(feature = "liteclient")]
+    pub async fn seqno<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+    ) -> Result<u32, WalletGetMethodError<P::Error>> {
+        let stack = run_wallet_get_method(provider, self.address()?, "seqno").await?;
+        wallet_stack_u32("seqno", &stack, 0)
+    }
+
+    /// Sends a signed external message BoC through any contract provider.
+    #[cfg(feature = "liteclient")]
+    pub async fn send_external_message<P: crate::contracts::ContractProvider + ?Sized>(
+        &self,
+        provider: &mut P,
+        seqno: u32,
+        valid_until: u32,
+        messages: Vec<WalletMessage>,
+        signing_key: &SigningKey,
+        include_state_init: bool,
+    ) -> Result<u32, WalletSendError<P::Error>> {
+        let boc = self
+            .build_external_message_boc(
+                seqno,
+                valid_until,
+                messages,
+                signing_key,
+                include_state_init,
+            )
+            .map_err(WalletSendError::Build)?;
+        provider
+            .send_external_message_boc(boc)
+            .await
+            .map_err(WalletSendError::Provider)
+    }
+}
+
+/// Signed Wallet V3R2 external body material.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletV3R2SignedBody {
+    /// Final body cell containing signature followed by signing fields.
+    pub body: Arc<Cell>,
+    /// Representation hash that was signed.
+    pub signing_hash: [u8; 32],
+    /// Ed25519 signature bytes.
+    pub signature: [u8; 64],
+}
+
 /// Wallet V4R2 offline helper bound to code, workchain, wallet id, and public key.
 #[derive(Debug, Clone)]
 pub struct WalletV4R2 {
@@ -331,7 +537,7 @@ impl WalletV4R2 {
         builder.store_u32(self.wallet_id)?;
         builder.store_u32(valid_until)?;
         builder.store_u32(seqno)?;
-        builder.store_u32(WALLET_V4R2_SIMPLE_SEND_OP)?;
+        builder.store_u8(WALLET_V4R2_SIMPLE_SEND_OP)?;
         for message in messages {
             let mode = message.mode;
             let relaxed = message.into_message_relaxed();
@@ -661,7 +867,7 @@ impl WalletV5R1 {
         &self,
         provider: &mut P,
     ) -> Result<u32, WalletGetMethodError<P::Error>> {
-        let stack = self.run_v5r1_get_method(provider, "seqno").await?;
+        let stack = run_wallet_get_method(provider, self.address()?, "seqno").await?;
         wallet_stack_u32("seqno", &stack, 0)
     }
 
@@ -671,7 +877,7 @@ impl WalletV5R1 {
         &self,
         provider: &mut P,
     ) -> Result<u32, WalletGetMethodError<P::Error>> {
-        let stack = self.run_v5r1_get_method(provider, "get_wallet_id").await?;
+        let stack = run_wallet_get_method(provider, self.address()?, "get_wallet_id").await?;
         wallet_stack_u32("get_wallet_id", &stack, 0)
     }
 
@@ -681,7 +887,7 @@ impl WalletV5R1 {
         &self,
         provider: &mut P,
     ) -> Result<[u8; 32], WalletGetMethodError<P::Error>> {
-        let stack = self.run_v5r1_get_method(provider, "get_public_key").await?;
+        let stack = run_wallet_get_method(provider, self.address()?, "get_public_key").await?;
         wallet_stack_public_key("get_public_key", &stack, 0)
     }
 
@@ -692,9 +898,8 @@ impl WalletV5R1 {
         &self,
         provider: &mut P,
     ) -> Result<bool, WalletGetMethodError<P::Error>> {
-        let stack = self
-            .run_v5r1_get_method(provider, "is_signature_allowed")
-            .await?;
+        let stack =
+            run_wallet_get_method(provider, self.address()?, "is_signature_allowed").await?;
         wallet_stack_bool_int("is_signature_allowed", &stack, 0)
     }
 
@@ -707,7 +912,7 @@ impl WalletV5R1 {
         &self,
         provider: &mut P,
     ) -> Result<Arc<Cell>, WalletGetMethodError<P::Error>> {
-        let stack = self.run_v5r1_get_method(provider, "get_extensions").await?;
+        let stack = run_wallet_get_method(provider, self.address()?, "get_extensions").await?;
         wallet_stack_cell("get_extensions", &stack, 0)
     }
 
@@ -733,47 +938,6 @@ impl WalletV5R1 {
         Ok(extensions)
     }
 
-    #[cfg(feature = "liteclient")]
-    async fn run_v5r1_get_method<P: crate::contracts::ContractProvider + ?Sized>(
-        &self,
-        provider: &mut P,
-        method: &'static str,
-    ) -> Result<crate::tvm::TvmStack, WalletGetMethodError<P::Error>> {
-        use crate::contracts::{DecodedRunMethodResult, RunMethodResultExt};
-        use crate::tvm::TvmStack;
-
-        let block = provider
-            .get_masterchain_info()
-            .await
-            .map_err(WalletGetMethodError::Provider)?
-            .last;
-        let result = provider
-            .run_get_method(
-                0,
-                block,
-                self.address()?,
-                crate::utils::method_name_to_id(method),
-                TvmStack::empty(),
-            )
-            .await
-            .map_err(WalletGetMethodError::Provider)?;
-
-        if result.exit_code != 0 {
-            return Err(WalletGetMethodError::NonZeroExitCode {
-                method,
-                exit_code: result.exit_code,
-            });
-        }
-
-        match result.result_stack_lossless() {
-            DecodedRunMethodResult::Decoded(stack) => Ok(stack),
-            DecodedRunMethodResult::Missing => Err(WalletGetMethodError::MissingStack { method }),
-            DecodedRunMethodResult::Undecodable { error, .. } => {
-                Err(WalletGetMethodError::UndecodableStack { method, error })
-            }
-        }
-    }
-
     /// Sends a signed external message BoC through any contract provider.
     #[cfg(feature = "liteclient")]
     pub async fn send_external_message<P: crate::contracts::ContractProvider + ?Sized>(
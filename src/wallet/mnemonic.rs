@@ -19,9 +19,14 @@ pub(super) const TON_DEFAULT_SEED_ITERATIONS: usize = 100_000;
 pub(super) const TON_SEED_VERSION_ITERATIONS: usize = 100_000 / 256;
 pub(super) const TON_FAST_SEED_VERSION_ITERATIONS: usize = 1;
 
+pub(super) const WALLET_V3R2_MAX_MESSAGES: usize = 4;
+/// Default Wallet V3R2 `subwallet_id` used by common wallet apps.
+pub const WALLET_V3R2_DEFAULT_ID: u32 = 0x29a9_a317;
+pub(super) const WALLET_V3R2_CODE_BOC_HEX: &str = "b5ee9c724101010100710000deff0020dd2082014c97ba218201339cbab19f71b0ed44d0d31fd31f31d70bffe304e0a4f2608308d71820d31fd31fd31ff82313bbf263ed44d0d31fd31fd3ffd15132baf2a15144baf2a204f901541055f910f2a3f8009320d74a96d307d402fb00e8d101a4c8cb1fcb1fcbffc9ed5410bd6dad";
+
 pub(super) const WALLET_V4R2_PLUGINS_KEY_BITS: usize = 256;
 pub(super) const WALLET_V4R2_MAX_MESSAGES: usize = 4;
-pub(super) const WALLET_V4R2_SIMPLE_SEND_OP: u32 = 0;
+pub(super) const WALLET_V4R2_SIMPLE_SEND_OP: u8 = 0;
 /// Default Wallet V4R2 wallet id used by common TON wallets.
 pub const WALLET_V4R2_DEFAULT_ID: u32 = 0x29a9_a317;
 pub(super) const WALLET_V4R2_CODE_BOC_HEX: &str = "b5ee9c72010214010002d4000114ff00f4a413f4bcf2c80b01020120020f020148030602e6d001d0d3032171b0925f04e022d749c120925f04e002d31f218210706c7567bd22821064737472bdb0925f05e003fa403020fa4401c8ca07cbffc9d0ed44d0810140d721f404305c810108f40a6fa131b3925f07e005d33fc8258210706c7567ba923830e30d03821064737472ba925f06e30d0405007801fa00f40430f8276f2230500aa121bef2e0508210706c7567831eb17080185004cb0526cf1658fa0219f400cb6917cb1f5260cb3f20c98040fb0006008a5004810108f45930ed44d0810140d720c801cf16f400c9ed540172b08e23821064737472831eb17080185005cb055003cf1623fa0213cb6acb1fcb3fc98040fb00925f03e2020120070e020120080d020158090a003db29dfb513420405035c87d010c00b23281f2fff274006040423d029be84c600201200b0c0019adce76a26840206b90eb85ffc00019af1df6a26840106b90eb858fc00011b8c97ed44d0d70b1f80059bd242b6f6a2684080a06b90fa0218470d4080847a4937d29910ce6903e9ff9837812801b7810148987159f318404f8f28308d71820d31fd31fd31f02f823bbf264ed44d0d31fd31fd3fff404d15143baf2a15151baf2a205f901541064f910f2a3f80024a4c8cb1f5240cb1f5230cbff5210f400c9ed54f80f01d30721c0009f6c519320d74a96d307d402fb00e830e021c001e30021c002e30001c0039130e30d03a4c8cb1f12cb1fcbff10111213006ed207fa00d4d422f90005c8ca0715cbffc9d077748018c8cb05cb0222cf165005fa0214cb6b12ccccc973fb00c84014810108f451f2a7020070810108d718fa00d33fc8542047810108f451f2a782106e6f746570748018c8cb05cb025006cf165004fa0214cb6a12cb1fcb3fc973fb0002006c810108d718fa00d33f305224810108f459f2a782106473747270748018c8cb05cb025005cf165003fa0213cb6acb1f12cb3fc973fb00000af400c9ed54";
@@ -38,9 +43,16 @@ pub(super) const WALLET_V5R1_CLIENT_SUBWALLET_MAX: u16 =
     (1 << WALLET_V5R1_CLIENT_SUBWALLET_BITS) - 1;
 pub(super) const WALLET_V5R1_CODE_BOC_HEX: &str = "b5ee9c7241021401000281000114ff00f4a413f4bcf2c80b01020120020d020148030402dcd020d749c120915b8f6320d70b1f2082106578746ebd21821073696e74bdb0925f03e082106578746eba8eb48020d72101d074d721fa4030fa44f828fa443058bd915be0ed44d0810141d721f4058307f40e6fa1319130e18040d721707fdb3ce03120d749810280b99130e070e2100f020120050c020120060902016e07080019adce76a2684020eb90eb85ffc00019af1df6a2684010eb90eb858fc00201480a0b0017b325fb51341c75c875c2c7e00011b262fb513435c280200019be5f0f6a2684080a0eb90fa02c0102f20e011e20d70b1f82107369676ebaf2e08a7f0f01e68ef0eda2edfb218308d722028308d723208020d721d31fd31fd31fed44d0d200d31f20d31fd3ffd70a000af90140ccf9109a28945f0adb31e1f2c087df02b35007b0f2d0845125baf2e0855036baf2e086f823bbf2d0882292f800de01a47fc8ca00cb1f01cf16c9ed542092f80fde70db3cd81003f6eda2edfb02f404216e926c218e4c0221d73930709421c700b38e2d01d72820761e436c20d749c008f2e09320d74ac002f2e09320d71d06c712c2005230b0f2d089d74cd7393001a4e86c128407bbf2e093d74ac000f2e093ed55e2d20001c000915be0ebd72c08142091709601d72c081c12e25210b1e30f20d74a111213009601fa4001fa44f828fa443058baf2e091ed44d0810141d718f405049d7fc8ca0040048307f453f2e08b8e14038307f45bf2e08c22d70a00216e01b3b0f2d090e2c85003cf1612f400c9ed54007230d72c08248e2d21f2e092d200ed44d0d2005113baf2d08f54503091319c01810140d721d70a00f2e08ee2c8ca0058cf16c9ed5493f2c08de20010935bdb31e1d74cd0b4d6c35e";
 
+static WALLET_V3R2_CODE: OnceLock<Result<Arc<Cell>, String>> = OnceLock::new();
 static WALLET_V4R2_CODE: OnceLock<Result<Arc<Cell>, String>> = OnceLock::new();
 static WALLET_V5R1_CODE: OnceLock<Result<Arc<Cell>, String>> = OnceLock::new();
 
+/// Wallet V3R2 code hash for the embedded `@ton/ton` V3R2 code BoC.
+pub const WALLET_V3R2_CODE_HASH: [u8; 32] = [
+    0x84, 0xda, 0xfa, 0x44, 0x9f, 0x98, 0xa6, 0x98, 0x77, 0x89, 0xba, 0x23, 0x23, 0x58, 0x07, 0x2b,
+    0xc0, 0xf7, 0x6d, 0xc4, 0x52, 0x40, 0x02, 0xa5, 0xd0, 0x91, 0x8b, 0x9a, 0x75, 0xd2, 0xd5, 0x99,
+];
+
 /// Wallet V4R2 code hash for the embedded `@ton/ton` V4R2 code BoC.
 pub const WALLET_V4R2_CODE_HASH: [u8; 32] = [
     0xfe, 0xb5, 0xff, 0x68, 0x20, 0xe2, 0xff, 0x0d, 0x94, 0x83, 0xe7, 0xe0, 0xd6, 0x2c, 0x81, 0x7d,
@@ -84,6 +96,12 @@ pub enum WalletError {
     TooManyActions { count: usize, max: usize },
     #[error("Wallet V5R1 extension dictionary key width {actual} does not match {expected}")]
     InvalidExtensionKeyWidth { actual: usize, expected: usize },
+    #[error("Highload Wallet V3 query id shift {shift} or bit number {bit_number} is out of range")]
+    InvalidHighloadQueryId { shift: u16, bit_number: u16 },
+    #[error("Highload Wallet V3 query ids are exhausted")]
+    HighloadQueryIdExhausted,
+    #[error("Highload Wallet V3 timeout {0} exceeds 22-bit maximum 4194303")]
+    HighloadTimeoutTooLarge(u32),
     #[error("failed to serialize wallet TL-B value: {0}")]
     Tlb(#[from] TlbError),
     #[error("failed to build wallet cell or BoC: {0}")]
@@ -93,6 +111,8 @@ pub enum WalletError {
 /// Wallet contract versions supported by the offline helpers and CLI.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WalletVersion {
+    /// Wallet V3R2.
+    V3R2,
     /// Wallet V4R2.
     V4R2,
     /// Wallet V5R1.
@@ -603,6 +623,51 @@ impl TlbDeserialize for WalletV5R1Data {
     }
 }
 
+/// Persistent Wallet V3R2 storage data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletV3R2Data {
+    /// Current wallet sequence number.
+    pub seqno: u32,
+    /// V3 wallet id, named `subwallet_id` by the contract.
+    pub wallet_id: u32,
+    /// Ed25519 public key bytes.
+    pub public_key: [u8; 32],
+}
+
+impl WalletV3R2Data {
+    /// Creates initial Wallet V3R2 data with seqno zero.
+    pub fn new(wallet_id: u32, public_key: [u8; 32]) -> Self {
+        Self {
+            seqno: 0,
+            wallet_id,
+            public_key,
+        }
+    }
+}
+
+impl TlbSerialize for WalletV3R2Data {
+    fn store_tlb(&self, builder: &mut Builder) -> crate::tlb::Result<()> {
+        builder.store_u32(self.seqno)?;
+        builder.store_u32(self.wallet_id)?;
+        builder.store_bytes(&self.public_key)?;
+        Ok(())
+    }
+}
+
+impl TlbDeserialize for WalletV3R2Data {
+    fn load_tlb(slice: &mut Slice) -> crate::tlb::Result<Self> {
+        Ok(Self {
+            seqno: slice.load_u32()?,
+            wallet_id: slice.load_u32()?,
+            public_key: {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(&slice.load_bytes(32)?);
+                bytes
+            },
+        })
+    }
+}
+
 /// Persistent Wallet V4R2 storage data.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WalletV4R2Data {
@@ -667,6 +732,11 @@ impl TlbDeserialize for WalletV4R2Data {
     }
 }
 
+/// Returns the embedded official Wallet V3R2 code cell.
+pub fn wallet_v3r2_code() -> Result<Arc<Cell>, WalletError> {
+    cached_wallet_code(&WALLET_V3R2_CODE, decode_wallet_v3r2_code)
+}
+
 /// Returns the embedded official Wallet V4R2 code cell.
 pub fn wallet_v4r2_code() -> Result<Arc<Cell>, WalletError> {
     cached_wallet_code(&WALLET_V4R2_CODE, decode_wallet_v4r2_code)
@@ -687,29 +757,22 @@ pub(super) fn cached_wallet_code(
     }
 }
 
-pub(super) fn decode_wallet_v4r2_code() -> Result<Arc<Cell>, WalletError> {
-    let mut bytes = hex::decode(WALLET_V4R2_CODE_BOC_HEX)
+pub(super) fn decode_wallet_v3r2_code() -> Result<Arc<Cell>, WalletError> {
+    let bytes = hex::decode(WALLET_V3R2_CODE_BOC_HEX)
         .map_err(|error| WalletError::CodeDecode(error.to_string()))?;
-    strip_boc_crc32c_for_local_decoder(&mut bytes);
     crate::tvm::deserialize_boc(&bytes).map_err(WalletError::Tvm)
 }
 
-pub(super) fn decode_wallet_v5r1_code() -> Result<Arc<Cell>, WalletError> {
-    let mut bytes = hex::decode(WALLET_V5R1_CODE_BOC_HEX)
+pub(super) fn decode_wallet_v4r2_code() -> Result<Arc<Cell>, WalletError> {
+    let bytes = hex::decode(WALLET_V4R2_CODE_BOC_HEX)
         .map_err(|error| WalletError::CodeDecode(error.to_string()))?;
-    strip_boc_crc32c_for_local_decoder(&mut bytes);
     crate::tvm::deserialize_boc(&bytes).map_err(WalletError::Tvm)
 }
 
-pub(super) fn strip_boc_crc32c_for_local_decoder(bytes: &mut Vec<u8>) {
-    if bytes.len() >= 9
-        && bytes[0..4] == [0xb5, 0xee, 0x9c, 0x72]
-        && (bytes[4] & 0x40) != 0
-        && bytes.len() >= 4
-    {
-        bytes[4] &= !0x40;
-        bytes.truncate(bytes.len() - 4);
-    }
+pub(super) fn decode_wallet_v5r1_code() -> Result<Arc<Cell>, WalletError> {
+    let bytes = hex::decode(WALLET_V5R1_CODE_BOC_HEX)
+        .map_err(|error| WalletError::CodeDecode(error.to_string()))?;
+    crate::tvm::deserialize_boc(&bytes).map_err(WalletError::Tvm)
 }
 
 /// Wallet V5R1 wallet-id context before XOR with the network global id.
@@ -821,7 +884,8 @@ pub struct WalletMessage {
     pub destination: Address,
     /// Native TON amount in nanotons.
     pub value: u64,
-    /// Optional body cell, stored inline when present.
+    /// Optional body cell, stored inline when it fits next to the message
+    /// header and by reference otherwise.
     pub body: Option<Arc<Cell>>,
     /// Whether the internal message should bounce.
     pub bounce: bool,
@@ -1,4 +1,5 @@
 use super::*;
 
+mod highload_v3;
 mod messages;
 mod mnemonic;
@@ -0,0 +1,268 @@
+use super::mnemonic::*;
+use super::*;
+use crate::tvm::deserialize_boc;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+fn highload_wallet() -> (SigningKey, HighloadWalletV3) {
+    let key = signing_key();
+    let wallet = HighloadWalletV3::default(
+        VerifyingKey::from(&key).to_bytes(),
+        3_600,
+        highload_wallet_v3_code().unwrap(),
+        0,
+    )
+    .unwrap();
+    (key, wallet)
+}
+
+fn transfers(count: usize) -> Vec<WalletMessage> {
+    (0..count)
+        .map(|index| {
+            WalletMessage::internal(Address::new(0, [index as u8; 32]), 1_000 + index as u64)
+        })
+        .collect()
+}
+
+fn unpack_internal_transfer(message: &MessageRelaxed) -> (u64, OutList) {
+    let Either::Left(body) = &message.body else {
+        panic!("internal_transfer body must be inline");
+    };
+    let mut slice = Slice::new(body.clone());
+    assert_eq!(
+        slice.load_u32().unwrap(),
+        HIGHLOAD_WALLET_V3_INTERNAL_TRANSFER_OP
+    );
+    let query_id = slice.load_u64().unwrap();
+    let actions = OutList::from_cell(slice.load_reference().unwrap()).unwrap();
+    ensure_empty(&slice).unwrap();
+    (query_id, actions)
+}
+
+#[test]
+fn embedded_highload_v3_code_hash_matches_official_value() {
+    let first = highload_wallet_v3_code().unwrap();
+    let second = highload_wallet_v3_code().unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(first.hash(), HIGHLOAD_WALLET_V3_CODE_HASH);
+}
+
+#[test]
+fn highload_query_id_packs_and_advances() {
+    let query_id = HighloadQueryId::new(3, 17).unwrap();
+    assert_eq!(query_id.raw(), (3 << 10) | 17);
+    assert_eq!(HighloadQueryId::from_raw(query_id.raw()).unwrap(), query_id);
+
+    let carried = HighloadQueryId::new(3, HIGHLOAD_WALLET_V3_MAX_BIT_NUMBER)
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!((carried.shift(), carried.bit_number()), (4, 0));
+
+    assert!(matches!(
+        HighloadQueryId::new(0, 1023),
+        Err(WalletError::InvalidHighloadQueryId { .. })
+    ));
+    assert!(matches!(
+        HighloadQueryId::from_raw(1 << 23),
+        Err(WalletError::InvalidHighloadQueryId { .. })
+    ));
+}
+
+#[test]
+fn highload_query_id_keeps_last_id_for_emergency() {
+    let last_regular = HighloadQueryId::new(
+        HIGHLOAD_WALLET_V3_MAX_SHIFT,
+        HIGHLOAD_WALLET_V3_MAX_BIT_NUMBER - 2,
+    )
+    .unwrap();
+    let final_regular = last_regular.next().unwrap();
+    assert_eq!(
+        final_regular.bit_number(),
+        HIGHLOAD_WALLET_V3_MAX_BIT_NUMBER - 1
+    );
+    assert!(!final_regular.has_next());
+    assert!(matches!(
+        final_regular.next(),
+        Err(WalletError::HighloadQueryIdExhausted)
+    ));
+
+    let mut allocator = HighloadQueryIdAllocator::starting_at(final_regular);
+    assert_eq!(allocator.allocate().unwrap(), final_regular);
+    assert_eq!(allocator.peek(), None);
+    assert!(matches!(
+        allocator.allocate(),
+        Err(WalletError::HighloadQueryIdExhausted)
+    ));
+    allocator.reset();
+    assert_eq!(allocator.allocate().unwrap().raw(), 0);
+    assert_eq!(allocator.allocate().unwrap().raw(), 1);
+}
+
+#[test]
+fn highload_v3_data_cell_roundtrips_and_reports_processed_queries() {
+    let mut data =
+        HighloadWalletV3Data::new([0x44; 32], HIGHLOAD_WALLET_V3_DEFAULT_SUBWALLET_ID, 600);
+    let mut bitmap = Builder::new();
+    bitmap.store_uint_custom::<u8>(0b001, 3).unwrap();
+    data.queries
+        .insert_bit_key(BitKey::from_u64(2, 13).unwrap(), bitmap.build().unwrap())
+        .unwrap();
+    data.last_clean_time = 1_700_000_000;
+
+    let cell = data.to_cell().unwrap();
+    let decoded = HighloadWalletV3Data::from_cell(cell).unwrap();
+    assert_eq!(decoded, data);
+    assert!(
+        decoded
+            .is_processed(HighloadQueryId::new(2, 2).unwrap())
+            .unwrap()
+    );
+    assert!(
+        !decoded
+            .is_processed(HighloadQueryId::new(2, 1).unwrap())
+            .unwrap()
+    );
+    assert!(
+        !decoded
+            .is_processed(HighloadQueryId::new(2, 900).unwrap())
+            .unwrap()
+    );
+    assert!(
+        !decoded
+            .is_processed(HighloadQueryId::new(3, 2).unwrap())
+            .unwrap()
+    );
+}
+
+#[test]
+fn highload_v3_rejects_timeout_wider_than_uint22() {
+    let result = HighloadWalletV3::default(
+        [0x11; 32],
+        HIGHLOAD_WALLET_V3_MAX_TIMEOUT + 1,
+        highload_wallet_v3_code().unwrap(),
+        0,
+    );
+    assert!(matches!(
+        result,
+        Err(WalletError::HighloadTimeoutTooLarge(_))
+    ));
+}
+
+#[test]
+fn highload_v3_created_at_window_matches_contract_checks() {
+    let (_, wallet) = highload_wallet();
+    let now = 1_700_000_000;
+    assert!(wallet.is_created_at_valid(now, now));
+    assert!(wallet.is_created_at_valid(now - 10, now));
+    assert!(!wallet.is_created_at_valid(now + 1, now));
+    assert!(!wallet.is_created_at_valid(now - 3_600, now));
+}
+
+#[test]
+fn highload_v3_signed_body_references_signed_message_cell() {
+    let (key, wallet) = highload_wallet();
+    let query_id = HighloadQueryId::new(1, 5).unwrap();
+    let message = WalletMessage::internal(Address::new(0, [0x22; 32]), 1_000_000);
+    let signed = wallet
+        .build_signed_external_body(query_id, 1_700_000_000, message, &key)
+        .unwrap();
+
+    VerifyingKey::from(&key)
+        .verify(
+            &signed.signing_hash,
+            &Signature::from_bytes(&signed.signature),
+        )
+        .unwrap();
+
+    let mut body = Slice::new(signed.body.clone());
+    assert_eq!(body.load_bytes(64).unwrap(), signed.signature.to_vec());
+    let inner = body.load_reference().unwrap();
+    ensure_empty(&body).unwrap();
+    assert_eq!(inner.hash(), signed.signing_hash);
+
+    let mut inner = Slice::new(inner);
+    assert_eq!(
+        inner.load_u32().unwrap(),
+        HIGHLOAD_WALLET_V3_DEFAULT_SUBWALLET_ID
+    );
+    let out_msg = MessageRelaxed::from_cell(inner.load_reference().unwrap()).unwrap();
+    assert!(matches!(
+        out_msg.info,
+        CommonMsgInfoRelaxed::Internal { .. }
+    ));
+    assert_eq!(inner.load_u8().unwrap(), 3);
+    assert_eq!(inner.load_uint_custom::<u32>(23).unwrap(), query_id.raw());
+    assert_eq!(inner.load_u64().unwrap(), 1_700_000_000);
+    assert_eq!(inner.load_uint_custom::<u32>(22).unwrap(), 3_600);
+    ensure_empty(&inner).unwrap();
+}
+
+#[test]
+fn highload_v3_batch_fits_single_action_list() {
+    let (_, wallet) = highload_wallet();
+    let query_id = HighloadQueryId::new(0, 9).unwrap();
+    let packed = wallet
+        .pack_actions(
+            transfers(HIGHLOAD_WALLET_V3_MAX_ACTIONS_PER_LIST),
+            0,
+            query_id,
+        )
+        .unwrap();
+    let CommonMsgInfoRelaxed::Internal { dest, .. } = &packed.info else {
+        panic!("batch must be an internal message");
+    };
+    assert_eq!(dest, &MsgAddressInt::std(wallet.address().unwrap()));
+
+    let (packed_query_id, actions) = unpack_internal_transfer(&packed);
+    assert_eq!(packed_query_id, query_id.raw() as u64);
+    assert_eq!(actions.len(), HIGHLOAD_WALLET_V3_MAX_ACTIONS_PER_LIST);
+}
+
+#[test]
+fn highload_v3_large_batch_chains_internal_transfers_to_self() {
+    let (_, wallet) = highload_wallet();
+    let query_id = HighloadQueryId::new(0, 1).unwrap();
+    let packed = wallet
+        .pack_actions(transfers(600), 50_000_000, query_id)
+        .unwrap();
+
+    let (_, first) = unpack_internal_transfer(&packed);
+    assert_eq!(first.len(), HIGHLOAD_WALLET_V3_MAX_ACTIONS_PER_LIST);
+    let OutAction::SendMsg { mode, out_msg } = first.actions.last().unwrap() else {
+        panic!("last action must forward the remainder");
+    };
+    assert_eq!(*mode, HIGHLOAD_WALLET_V3_SEND_MODE_PAY_GAS_SEPARATELY);
+
+    let (_, second) = unpack_internal_transfer(out_msg);
+    assert_eq!(second.len(), HIGHLOAD_WALLET_V3_MAX_ACTIONS_PER_LIST);
+    let OutAction::SendMsg { out_msg, .. } = second.actions.last().unwrap() else {
+        panic!("last action must forward the remainder");
+    };
+
+    let (_, third) = unpack_internal_transfer(out_msg);
+    assert_eq!(
+        third.len(),
+        600 - 2 * (HIGHLOAD_WALLET_V3_MAX_ACTIONS_PER_LIST - 1)
+    );
+}
+
+#[test]
+fn highload_v3_batch_message_boc_decodes_as_message() {
+    let (key, wallet) = highload_wallet();
+    let boc = wallet
+        .build_batch_message_boc(
+            HighloadQueryId::default(),
+            1_700_000_000,
+            transfers(3),
+            0,
+            &key,
+            true,
+        )
+        .unwrap();
+    let message = Message::from_cell(deserialize_boc(&boc).unwrap()).unwrap();
+    let CommonMsgInfo::ExternalIn { dest, .. } = message.info else {
+        panic!("expected external-in message");
+    };
+    assert_eq!(dest, MsgAddressInt::std(wallet.address().unwrap()));
+    assert!(message.init.is_some());
+}
@@ -147,8 +147,21 @@ fn v4r2_data_cell_roundtrips_with_empty_plugins() {
     assert!(decoded.plugins.is_empty());
 }
 
+#[test]
+fn v3r2_data_cell_roundtrips() {
+    let data = WalletV3R2Data::new(WALLET_V3R2_DEFAULT_ID, [0x11; 32]);
+    let cell = data.to_cell().unwrap();
+    assert_eq!(cell.bit_len(), 32 + 32 + 256);
+    assert_eq!(WalletV3R2Data::from_cell(cell).unwrap(), data);
+}
+
 #[test]
 fn embedded_wallet_code_hashes_match_official_values() {
+    let v3_first = wallet_v3r2_code().unwrap();
+    let v3_second = wallet_v3r2_code().unwrap();
+    assert!(Arc::ptr_eq(&v3_first, &v3_second));
+    assert_eq!(v3_first.hash(), WALLET_V3R2_CODE_HASH);
+
     let v4_first = wallet_v4r2_code().unwrap();
     let v4_second = wallet_v4r2_code().unwrap();
     assert!(Arc::ptr_eq(&v4_first, &v4_second));
@@ -382,7 +395,66 @@ fn v4r2_signed_external_body_verifies_against_signing_cell_hash() {
     assert_eq!(slice.load_u32().unwrap(), WALLET_V4R2_DEFAULT_ID);
     assert_eq!(slice.load_u32().unwrap(), 1_700_000_000);
     assert_eq!(slice.load_u32().unwrap(), 5);
-    assert_eq!(slice.load_u32().unwrap(), 0);
+    assert_eq!(slice.load_u8().unwrap(), 0);
+    assert_eq!(slice.load_u8().unwrap(), 3);
+}
+
+#[test]
+fn v4r2_signing_cell_stores_8_bit_op_before_send_modes() {
+    let wallet = WalletV4R2::new([0x11; 32], WALLET_V4R2_DEFAULT_ID, test_code(), 0);
+    let destination = Address::new(0, [0x22; 32]);
+    let messages = vec![
+        WalletMessage::internal(destination.clone(), 1_000_000).with_mode(3),
+        WalletMessage::internal(destination, 2_000_000).with_mode(1),
+    ];
+
+    let signing_cell = wallet
+        .build_external_signing_cell(5, 1_700_000_000, messages)
+        .unwrap();
+
+    let mut slice = Slice::new(signing_cell);
+    assert_eq!(slice.remaining_bits(), 32 * 3 + 8 + 8 * 2);
+    assert_eq!(slice.remaining_refs(), 2);
+    slice.load_bytes(12).unwrap();
+    assert_eq!(slice.load_u8().unwrap(), 0);
+    assert_eq!(slice.load_u8().unwrap(), 3);
+    assert_eq!(slice.load_u8().unwrap(), 1);
+}
+
+#[test]
+fn v3r2_signed_external_body_omits_v4_op() {
+    let key = signing_key();
+    let public_key = VerifyingKey::from(&key);
+    let wallet = WalletV3R2::default(public_key.to_bytes(), test_code(), 0);
+    let destination = Address::new(0, [0x22; 32]);
+    let message = WalletMessage::internal(destination, 1_000_000).with_mode(3);
+
+    let signed = wallet
+        .build_signed_external_body(5, 1_700_000_000, vec![message], &key)
+        .unwrap();
+    public_key
+        .verify(
+            &signed.signing_hash,
+            &Signature::from_bytes(&signed.signature),
+        )
+        .unwrap();
+
+    let mut slice = Slice::new(signed.body);
+    assert_eq!(slice.load_bytes(64).unwrap(), signed.signature);
+    assert_eq!(slice.load_u32().unwrap(), WALLET_V3R2_DEFAULT_ID);
+    assert_eq!(slice.load_u32().unwrap(), 1_700_000_000);
+    assert_eq!(slice.load_u32().unwrap(), 5);
+    assert_eq!(slice.load_u8().unwrap(), 3);
+    assert_eq!(slice.remaining_bits(), 0);
+    assert_eq!(slice.remaining_refs(), 1);
+
+    let messages = vec![WalletMessage::internal(Address::new(0, [1; 32]), 1); 5];
+    assert!(matches!(
+        wallet
+            .build_external_signing_cell(0, 1, messages)
+            .unwrap_err(),
+        WalletError::TooManyActions { count: 5, max: 4 }
+    ));
 }
 
 #[test]
@@ -469,3 +541,29 @@ fn v4r2_external_message_boc_decodes_as_message() {
     }
     assert!(decoded.init.is_some());
 }
+
+#[test]
+fn v3r2_external_message_boc_decodes_as_message() {
+    let key = signing_key();
+    let public_key = VerifyingKey::from(&key).to_bytes();
+    let wallet = WalletV3R2::new(
+        public_key,
+        WALLET_V3R2_DEFAULT_ID,
+        wallet_v3r2_code().unwrap(),
+        0,
+    );
+    let boc = wallet
+        .build_external_message_boc(0, 1_700_000_001, Vec::new(), &key, true)
+        .unwrap();
+    let decoded = Message::from_cell(deserialize_boc(&boc).unwrap()).unwrap();
+    match decoded.info {
+        CommonMsgInfo::ExternalIn { dest, .. } => {
+            assert_eq!(dest, MsgAddressInt::std(wallet.address().unwrap()));
+        }
+        _ => panic!("expected external inbound message"),
+    }
+    let Some(Either::Right(init)) = decoded.init else {
+        panic!("expected inline state init");
+    };
+    assert_eq!(init.code.unwrap().hash(), WALLET_V3R2_CODE_HASH);
+}
@@ -110,6 +110,14 @@ impl ContractProvider for WalletGetMockProvider {
     ) -> Result<TransactionList, Self::Error> {
         unimplemented!("wallet get-method helpers do not read transactions")
     }
+
+    async fn get_config_param(
+        &mut self,
+        _block: BlockIdExt,
+        _id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error> {
+        unimplemented!("wallet get-method helpers do not read config params")
+    }
 }
 
 #[cfg(feature = "liteclient")]
@@ -173,6 +181,14 @@ impl ContractProvider for WalletSendMockProvider {
     ) -> Result<TransactionList, Self::Error> {
         unimplemented!("wallet send helpers do not read transactions")
     }
+
+    async fn get_config_param(
+        &mut self,
+        _block: BlockIdExt,
+        _id: u32,
+    ) -> Result<Option<Arc<Cell>>, Self::Error> {
+        unimplemented!("wallet send helpers do not read config params")
+    }
 }
 
 pub(super) fn test_code() -> Arc<Cell> {
@@ -390,6 +406,34 @@ async fn v5r1_get_method_helpers_route_expected_methods() {
     );
 }
 
+#[cfg(feature = "liteclient")]
+#[tokio::test]
+async fn v5r1_get_method_helpers_run_offline_with_local_provider() {
+    let wallet = WalletV5R1::new(
+        VerifyingKey::from(&signing_key()).to_bytes(),
+        WALLET_V5R1_MAINNET_DEFAULT_ID,
+        wallet_v5r1_code().unwrap(),
+        0,
+    );
+    let mut provider = crate::contracts::LocalContractProvider::new();
+    let address = provider
+        .insert_state_init(0, &wallet.state_init().unwrap())
+        .unwrap();
+    assert_eq!(address, wallet.address().unwrap());
+
+    assert_eq!(wallet.seqno(&mut provider).await.unwrap(), 0);
+    assert_eq!(
+        wallet.public_key_onchain(&mut provider).await.unwrap(),
+        VerifyingKey::from(&signing_key()).to_bytes()
+    );
+    assert!(
+        wallet
+            .is_signature_allowed_onchain(&mut provider)
+            .await
+            .unwrap()
+    );
+}
+
 #[cfg(feature = "liteclient")]
 #[tokio::test]
 async fn v5r1_get_method_uint32_decoding_rejects_invalid_values() {
@@ -451,7 +495,7 @@ async fn v5r1_get_public_key_decodes_uint256_integer() {
 
 #[cfg(feature = "liteclient")]
 #[tokio::test]
-async fn v5r1_signature_allowed_accepts_only_zero_or_one() {
+async fn v5r1_signature_allowed_accepts_only_booleans() {
     let wallet = wallet_get_wallet();
 
     let mut provider = wallet_get_mock(&wallet, TvmStack::new(vec![TvmStackEntry::int(0)]));
@@ -470,6 +514,14 @@ async fn v5r1_signature_allowed_accepts_only_zero_or_one() {
             .unwrap()
     );
 
+    let mut provider = wallet_get_mock(&wallet, TvmStack::new(vec![TvmStackEntry::int(-1)]));
+    assert!(
+        wallet
+            .is_signature_allowed_onchain(&mut provider)
+            .await
+            .unwrap()
+    );
+
     let mut provider = wallet_get_mock(&wallet, TvmStack::new(vec![TvmStackEntry::int(2)]));
     assert!(matches!(
         wallet
@@ -478,7 +530,7 @@ async fn v5r1_signature_allowed_accepts_only_zero_or_one() {
             .unwrap_err(),
         WalletGetMethodError::IntegerRange {
             method: "is_signature_allowed",
-            expected: "0 or 1",
+            expected: "0, 1 or -1",
             ..
         }
     ));
@@ -794,3 +846,29 @@ async fn wallet_send_external_message_build_errors_do_not_call_provider() {
     ));
     assert!(provider.bodies.is_empty());
 }
+
+#[cfg(feature = "liteclient")]
+#[tokio::test]
+async fn v3r2_seqno_reads_wallet_get_method() {
+    let wallet = WalletV3R2::default(
+        VerifyingKey::from(&signing_key()).to_bytes(),
+        test_code(),
+        0,
+    );
+    let mut provider = WalletGetMockProvider {
+        latest: wallet_get_block(),
+        account: wallet.address().unwrap(),
+        result: Ok(wallet_get_result(
+            0,
+            Some(TvmStack::new(vec![TvmStackEntry::int(9)])),
+        )),
+        method_calls: Vec::new(),
+        account_calls: Vec::new(),
+    };
+    assert_eq!(wallet.seqno(&mut provider).await.unwrap(), 9);
+    assert_eq!(
+        provider.method_calls,
+        vec![crate::utils::method_name_to_id("seqno")]
+    );
+    assert_eq!(provider.account_calls, vec![wallet.address().unwrap()]);
+}
@@ -91,6 +91,58 @@ pub(super) fn load_v5_inner_request(
     Ok((out_list, extended_actions))
 }
 
+#[cfg(feature = "liteclient")]
+pub(super) async fn run_wallet_get_method<P: crate::contracts::ContractProvider + ?Sized>(
+    provider: &mut P,
+    address: Address,
+    method: &'static str,
+) -> Result<crate::tvm::TvmStack, WalletGetMethodError<P::Error>> {
+    run_wallet_get_method_with_stack(provider, address, method, crate::tvm::TvmStack::empty()).await
+}
+
+#[cfg(feature = "liteclient")]
+pub(super) async fn run_wallet_get_method_with_stack<
+    P: crate::contracts::ContractProvider + ?Sized,
+>(
+    provider: &mut P,
+    address: Address,
+    method: &'static str,
+    stack: crate::tvm::TvmStack,
+) -> Result<crate::tvm::TvmStack, WalletGetMethodError<P::Error>> {
+    use crate::contracts::{DecodedRunMethodResult, RunMethodResultExt};
+
+    let block = provider
+        .get_masterchain_info()
+        .await
+        .map_err(WalletGetMethodError::Provider)?
+        .last;
+    let result = provider
+        .run_get_method(
+            0,
+            block,
+            address,
+            crate::utils::method_name_to_id(method),
+            stack,
+        )
+        .await
+        .map_err(WalletGetMethodError::Provider)?;
+
+    if result.exit_code != 0 {
+        return Err(WalletGetMethodError::NonZeroExitCode {
+            method,
+            exit_code: result.exit_code,
+        });
+    }
+
+    match result.result_stack_lossless() {
+        DecodedRunMethodResult::Decoded(stack) => Ok(stack),
+        DecodedRunMethodResult::Missing => Err(WalletGetMethodError::MissingStack { method }),
+        DecodedRunMethodResult::Undecodable { error, .. } => {
+            Err(WalletGetMethodError::UndecodableStack { method, error })
+        }
+    }
+}
+
 #[cfg(feature = "liteclient")]
 pub(super) fn wallet_stack_entry<'a, E>(
     method: &'static str,
@@ -152,6 +204,33 @@ where
         })
 }
 
+#[cfg(feature = "liteclient")]
+pub(super) fn wallet_stack_u64<E>(
+    method: &'static str,
+    stack: &crate::tvm::TvmStack,
+    index: usize,
+) -> Result<u64, WalletGetMethodError<E>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let value = wallet_stack_int(method, stack, index)?;
+    if value.sign() == Sign::Minus || value > &BigInt::from(u64::MAX) {
+        return Err(WalletGetMethodError::IntegerRange {
+            method,
+            index,
+            expected: "uint64",
+        });
+    }
+    value
+        .to_string()
+        .parse::<u64>()
+        .map_err(|_| WalletGetMethodError::IntegerRange {
+            method,
+            index,
+            expected: "uint64",
+        })
+}
+
 #[cfg(feature = "liteclient")]
 pub(super) fn wallet_stack_bool_int<E>(
     method: &'static str,
@@ -164,13 +243,14 @@ where
     let value = wallet_stack_int(method, stack, index)?;
     if value == &BigInt::from(0u8) {
         Ok(false)
-    } else if value == &BigInt::from(1u8) {
+    } else if value == &BigInt::from(1u8) || value == &BigInt::from(-1) {
+        // FunC booleans are `-1`; some indexers normalize them to `1`.
         Ok(true)
     } else {
         Err(WalletGetMethodError::IntegerRange {
             method,
             index,
-            expected: "0 or 1",
+            expected: "0, 1 or -1",
         })
     }
 }